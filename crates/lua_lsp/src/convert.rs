@@ -0,0 +1,96 @@
+//! Conversions between the byte offsets of `lua_parser` and the positions of the protocol, which
+//! count columns in UTF-16 code units.
+
+use lsp_types::{
+    DiagnosticRelatedInformation, DiagnosticSeverity, NumberOrString, Position, Range, Url,
+};
+use lua_parser::{Diagnostic, LineCol, LineIndex, Severity, TextRange, TextSize};
+
+pub(crate) fn position(index: &LineIndex, text: &str, offset: TextSize) -> Position {
+    let line_col = index.line_col(offset);
+    let line_start = offset - line_col.col;
+    let character = text[TextRange::new(line_start, offset)]
+        .encode_utf16()
+        .count();
+    Position::new(line_col.line, character as u32)
+}
+
+pub(crate) fn range(index: &LineIndex, text: &str, range: TextRange) -> Range {
+    Range::new(
+        position(index, text, range.start()),
+        position(index, text, range.end()),
+    )
+}
+
+/// Returns the offset of `position`, or `None` if its line is past the end of the text. A column
+/// past the end of the line is clamped to the end of the line.
+pub(crate) fn offset(index: &LineIndex, text: &str, position: Position) -> Option<TextSize> {
+    if position.line >= index.line_count() {
+        return None;
+    }
+    let line = index.line_range(position.line);
+    let mut units = 0;
+    let mut col = TextSize::from(0);
+    for c in text[line].chars() {
+        if units >= position.character as usize || c == '\r' || c == '\n' {
+            break;
+        }
+        units += c.len_utf16();
+        col += TextSize::of(c);
+    }
+    Some(index.offset(LineCol {
+        line: position.line,
+        col,
+    }))
+}
+
+/// Converts a diagnostic of the document `uri`, with its labels as related information.
+pub(crate) fn diagnostic(
+    index: &LineIndex,
+    text: &str,
+    uri: &Url,
+    diagnostic: &Diagnostic,
+) -> lsp_types::Diagnostic {
+    let severity = match diagnostic.severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+        Severity::Hint => DiagnosticSeverity::HINT,
+    };
+    let related_information = diagnostic
+        .labels
+        .iter()
+        .map(|label| DiagnosticRelatedInformation {
+            location: lsp_types::Location::new(uri.clone(), range(index, text, label.range)),
+            message: label.message.clone(),
+        })
+        .collect::<Vec<_>>();
+    lsp_types::Diagnostic {
+        range: range(index, text, diagnostic.range),
+        severity: Some(severity),
+        code: Some(NumberOrString::String(diagnostic.code.to_string())),
+        source: Some("lua_parser".to_string()),
+        message: diagnostic.message.clone(),
+        related_information: Some(related_information).filter(|related| !related.is_empty()),
+        ..lsp_types::Diagnostic::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_columns() {
+        let text = "s = 'é😀'\nx";
+        let index = LineIndex::new(text);
+        let end = TextSize::from(text.find('\n').unwrap() as u32);
+        assert_eq!(position(&index, text, end), Position::new(0, 9));
+        assert_eq!(offset(&index, text, Position::new(0, 9)), Some(end));
+        assert_eq!(offset(&index, text, Position::new(0, 100)), Some(end));
+        assert_eq!(
+            offset(&index, text, Position::new(1, 0)),
+            Some(end + TextSize::from(1))
+        );
+        assert_eq!(offset(&index, text, Position::new(2, 0)), None);
+    }
+}
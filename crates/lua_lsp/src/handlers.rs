@@ -0,0 +1,274 @@
+//! Computes the results of requests from the files of a `Workspace`. Positions are converted by
+//! the caller, except where a result is a list of protocol types.
+
+use crate::convert;
+use lsp_types::{
+    DocumentSymbol, FoldingRange, FoldingRangeKind, SemanticToken, SemanticTokenModifier,
+    SemanticTokenType, SemanticTokensLegend, SymbolKind,
+};
+use lua_parser::{
+    check_ambiguous_calls, check_compatibility, check_constants, check_patterns,
+    comment_directives, detect_line_endings, format, resolve_names, tokenize, AstNode, Diagnostic,
+    Dialect, FileId, FmtConfig, IndentStyle, SymbolScope, SyntaxKind::*, TextRange, TextSize,
+    Workspace, SEMANTIC_TOKEN_MODIFIERS, SEMANTIC_TOKEN_TYPES,
+};
+
+/// Returns the syntax errors and the results of the checks of a file, ordered by their position.
+/// The levels of the checks can be changed by directives in comments.
+pub(crate) fn diagnostics(
+    workspace: &Workspace,
+    file_id: FileId,
+    dialect: Dialect,
+) -> Vec<Diagnostic> {
+    let text = workspace.file_text(file_id);
+    let parse = workspace.parse(file_id);
+    let mut diagnostics = parse.diagnostics();
+    diagnostics.extend(check_ambiguous_calls(&text, dialect, false));
+    diagnostics.extend(check_compatibility(&text, dialect));
+    diagnostics.extend(check_patterns(&parse.tree()));
+    diagnostics.extend(check_constants(&parse.tree(), dialect));
+    let mut diagnostics = comment_directives(&parse.tree()).apply(diagnostics);
+    diagnostics.sort_by_key(|diagnostic| diagnostic.range.start());
+    diagnostics
+}
+
+/// Returns the globals and module fields that a file defines and its top-level local functions.
+pub(crate) fn document_symbols(workspace: &Workspace, file_id: FileId) -> Vec<DocumentSymbol> {
+    let text = workspace.file_text(file_id);
+    let index = workspace.line_index(file_id);
+    let symbol = |name: String, kind, range, name_range| {
+        #[allow(deprecated)]
+        DocumentSymbol {
+            name,
+            detail: None,
+            kind,
+            tags: None,
+            deprecated: None,
+            range: convert::range(&index, &text, range),
+            selection_range: convert::range(&index, &text, name_range),
+            children: None,
+        }
+    };
+    let mut symbols: Vec<_> = workspace
+        .symbol_index(file_id)
+        .symbols()
+        .iter()
+        .map(|definition| {
+            let kind = match (definition.kind, definition.scope) {
+                (lua_parser::SymbolKind::Function, _) => SymbolKind::FUNCTION,
+                (lua_parser::SymbolKind::Method, _) => SymbolKind::METHOD,
+                (lua_parser::SymbolKind::Table, _) => SymbolKind::OBJECT,
+                (lua_parser::SymbolKind::Variable, SymbolScope::Global) => SymbolKind::VARIABLE,
+                (lua_parser::SymbolKind::Variable, SymbolScope::Module) => SymbolKind::FIELD,
+            };
+            let name = definition.name.clone();
+            (
+                definition.range,
+                symbol(name, kind, definition.range, definition.name_range),
+            )
+        })
+        .collect();
+    let local_functions = workspace
+        .parse(file_id)
+        .tree()
+        .top_level_functions()
+        .into_iter()
+        .filter(|function| function.is_local);
+    for function in local_functions {
+        let range = function.function.text_range().cover(function.name_range);
+        let local = symbol(
+            function.name,
+            SymbolKind::FUNCTION,
+            range,
+            function.name_range,
+        );
+        symbols.push((range, local));
+    }
+    symbols.sort_by_key(|(range, _)| range.start());
+    symbols.into_iter().map(|(_, symbol)| symbol).collect()
+}
+
+/// Returns the ranges of the functions, blocks, tables and comments that span multiple lines.
+pub(crate) fn folding_ranges(workspace: &Workspace, file_id: FileId) -> Vec<FoldingRange> {
+    let index = workspace.line_index(file_id);
+    let root = workspace.parse(file_id).syntax_node();
+    let mut ranges = Vec::new();
+    for element in root.descendants_with_tokens() {
+        let kind = match element.kind() {
+            COMMENT => Some(FoldingRangeKind::Comment),
+            FUNCTION_STMT | LOCAL_FUNCTION_STMT | FUNCTION_EXPR | DO_STMT | WHILE_STMT
+            | REPEAT_STMT | IF_STMT | ELSE_IF_CLAUSE | ELSE_CLAUSE | NUMERIC_FOR_STMT
+            | GENERIC_FOR_STMT | TABLE_EXPR => None,
+            _ => continue,
+        };
+        let range = element.text_range();
+        let start_line = index.line_col(range.start()).line;
+        let end_line = index.line_col(range.end()).line;
+        if start_line < end_line {
+            ranges.push(FoldingRange {
+                start_line,
+                end_line,
+                kind,
+                ..FoldingRange::default()
+            });
+        }
+    }
+    ranges
+}
+
+/// Returns the formatted text of a file, or `None` if it contains syntax errors.
+pub(crate) fn formatting(
+    workspace: &Workspace,
+    file_id: FileId,
+    options: &lsp_types::FormattingOptions,
+) -> Option<String> {
+    let parse = workspace.parse(file_id);
+    if !parse.errors().is_empty() {
+        return None;
+    }
+    let indent_style = if options.insert_spaces {
+        IndentStyle::Spaces(options.tab_size as usize)
+    } else {
+        IndentStyle::Tabs
+    };
+    let config = FmtConfig {
+        indent_style,
+        line_ending: detect_line_endings(&workspace.file_text(file_id)).dominant(),
+        ..FmtConfig::default()
+    };
+    Some(format(&parse.tree(), &config))
+}
+
+pub(crate) fn semantic_tokens_legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: SEMANTIC_TOKEN_TYPES
+            .iter()
+            .map(|&token_type| SemanticTokenType::new(token_type))
+            .collect(),
+        token_modifiers: SEMANTIC_TOKEN_MODIFIERS
+            .iter()
+            .map(|&modifier| SemanticTokenModifier::new(modifier))
+            .collect(),
+    }
+}
+
+/// Returns the highlighted ranges of a file as semantic tokens of the legend.
+pub(crate) fn semantic_tokens(workspace: &Workspace, file_id: FileId) -> Vec<SemanticToken> {
+    lua_parser::semantic_tokens(&workspace.parse(file_id).syntax_node())
+        .chunks(5)
+        .map(|data| SemanticToken {
+            delta_line: data[0],
+            delta_start: data[1],
+            length: data[2],
+            token_type: data[3],
+            token_modifiers_bitset: data[4],
+        })
+        .collect()
+}
+
+/// Returns the ranges of the declaration and the references of the local at `offset`, which are
+/// replaced by `new_name` to rename it. Only locals can be renamed, globals and fields can be
+/// defined in other files.
+pub(crate) fn rename(
+    workspace: &Workspace,
+    file_id: FileId,
+    offset: TextSize,
+    new_name: &str,
+) -> Result<Vec<TextRange>, String> {
+    let tokens = tokenize(new_name);
+    if tokens.len() != 1 || tokens[0].kind != IDENT {
+        return Err(format!("`{}` is not a valid name", new_name));
+    }
+    let chunk = workspace.parse(file_id).tree();
+    let resolution = resolve_names(&chunk);
+    let local = resolution
+        .local_at(offset)
+        .ok_or_else(|| "only locals can be renamed".to_string())?;
+    let declaration = resolution.local(local);
+    if workspace.file_text(file_id)[declaration.range] != declaration.name {
+        return Err("the implicit `self` parameter can't be renamed".to_string());
+    }
+    let references = resolution
+        .local_references(local)
+        .map(|reference| reference.name_ref.syntax().text_range());
+    Ok(std::iter::once(declaration.range)
+        .chain(references)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single_file(text: &str) -> (Workspace, FileId) {
+        let mut workspace = Workspace::new();
+        let file_id = workspace.add_file("main.lua", text);
+        (workspace, file_id)
+    }
+
+    #[test]
+    fn test_folding_ranges() {
+        let (workspace, file_id) =
+            single_file("local function f()\n  return {\n    1,\n  }\nend\n--[[\n]]\nx = {}");
+        let ranges: Vec<_> = folding_ranges(&workspace, file_id)
+            .into_iter()
+            .map(|range| (range.start_line, range.end_line, range.kind))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (0, 4, None),
+                (1, 3, None),
+                (5, 6, Some(FoldingRangeKind::Comment))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_semantic_tokens() {
+        let (workspace, file_id) = single_file("local x = 1");
+        let legend = semantic_tokens_legend();
+        let tokens = semantic_tokens(&workspace, file_id);
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(
+            legend.token_types[tokens[1].token_type as usize],
+            SemanticTokenType::VARIABLE
+        );
+        assert_eq!(tokens[1].token_modifiers_bitset, 1);
+        assert_eq!(
+            legend.token_modifiers,
+            vec![SemanticTokenModifier::DECLARATION]
+        );
+    }
+
+    #[test]
+    fn test_rename() {
+        let text = "local x = 1\nlocal function f() return x end\nx = x + 1";
+        let (workspace, file_id) = single_file(text);
+        let ranges = rename(&workspace, file_id, TextSize::from(6), "y").unwrap();
+        let texts: Vec<_> = ranges.iter().map(|&range| &text[range]).collect();
+        assert_eq!(texts, vec!["x"; 4]);
+        assert!(rename(&workspace, file_id, TextSize::from(6), "end").is_err());
+        let (workspace, file_id) = single_file("print(1)");
+        assert!(rename(&workspace, file_id, TextSize::from(1), "y").is_err());
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        let (workspace, file_id) = single_file(
+            "local M = {}\nlocal function helper() end\nfunction M.run() end\nVERSION = 1\nreturn M",
+        );
+        let symbols: Vec<_> = document_symbols(&workspace, file_id)
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.kind))
+            .collect();
+        assert_eq!(
+            symbols,
+            vec![
+                ("helper".to_string(), SymbolKind::FUNCTION),
+                ("M.run".to_string(), SymbolKind::FUNCTION),
+                ("VERSION".to_string(), SymbolKind::VARIABLE),
+            ]
+        );
+    }
+}
@@ -0,0 +1,20 @@
+//! A minimal language server for Lua on top of `lua_parser`.
+//!
+//! The server keeps the open documents in a `Workspace` and wires the analyses of the parser to
+//! the language server protocol: diagnostics, document symbols, folding, formatting, semantic
+//! tokens, rename and go to definition. It communicates over stdin and stdout.
+
+mod convert;
+mod handlers;
+mod server;
+
+use lsp_server::Connection;
+
+fn main() -> server::Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = serde_json::to_value(server::capabilities())?;
+    let params = connection.initialize(capabilities)?;
+    server::run(&connection, serde_json::from_value(params)?)?;
+    io_threads.join()?;
+    Ok(())
+}
@@ -0,0 +1,490 @@
+//! The main loop of the language server, which keeps the open documents in a `Workspace` and
+//! answers requests from it.
+
+use crate::{convert, handlers};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{
+        DocumentSymbolRequest, FoldingRangeRequest, Formatting, GotoDefinition, Rename,
+        Request as _, SemanticTokensFullRequest,
+    },
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    DocumentSymbolResponse, FoldingRange, GotoDefinitionResponse, InitializeParams, OneOf,
+    PublishDiagnosticsParams, SemanticTokens, SemanticTokensFullOptions, SemanticTokensOptions,
+    SemanticTokensResult, SemanticTokensServerCapabilities, ServerCapabilities,
+    TextDocumentPositionParams, TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url,
+    WorkspaceEdit,
+};
+use lua_parser::{Dialect, FileId, TextRange, TextSize, Workspace};
+use std::{collections::HashMap, error::Error, path::PathBuf};
+
+pub(crate) type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Returns the features that the server supports, which it announces when it is initialized.
+pub(crate) fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        folding_range_provider: Some(true.into()),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: handlers::semantic_tokens_legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..SemanticTokensOptions::default()
+            },
+        )),
+        rename_provider: Some(OneOf::Left(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// Handles the messages of an initialized connection until the client shuts the server down.
+pub(crate) fn run(connection: &Connection, params: InitializeParams) -> Result<()> {
+    let mut server = Server::new(&params);
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let response = server.handle_request(request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(diagnostics) = server.handle_notification(notification)? {
+                    connection.sender.send(Message::Notification(diagnostics))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+struct Server {
+    workspace: Workspace,
+    files: HashMap<Url, FileId>,
+    dialect: Dialect,
+}
+
+impl Server {
+    fn new(params: &InitializeParams) -> Server {
+        let mut workspace = Workspace::new();
+        let folders = params.workspace_folders.iter().flatten();
+        let folders: Vec<_> = folders
+            .filter_map(|folder| folder.uri.to_file_path().ok())
+            .collect();
+        if !folders.is_empty() {
+            let search_paths = folders
+                .iter()
+                .flat_map(|folder| {
+                    let folder = folder.display();
+                    vec![
+                        format!("{}/?.lua", folder),
+                        format!("{}/?/init.lua", folder),
+                    ]
+                })
+                .collect();
+            workspace.set_search_paths(search_paths);
+        }
+        Server {
+            workspace,
+            files: HashMap::new(),
+            dialect: Dialect::default(),
+        }
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            DocumentSymbolRequest::METHOD => {
+                self.dispatch::<DocumentSymbolRequest>(request, |server, params| {
+                    let file_id = server.file(&params.text_document.uri)?;
+                    let symbols = handlers::document_symbols(&server.workspace, file_id);
+                    Ok(Some(DocumentSymbolResponse::Nested(symbols)))
+                })
+            }
+            FoldingRangeRequest::METHOD => {
+                self.dispatch::<FoldingRangeRequest>(request, |server, params| {
+                    let file_id = server.file(&params.text_document.uri)?;
+                    let ranges: Vec<FoldingRange> =
+                        handlers::folding_ranges(&server.workspace, file_id);
+                    Ok(Some(ranges))
+                })
+            }
+            Formatting::METHOD => self.dispatch::<Formatting>(request, |server, params| {
+                let file_id = server.file(&params.text_document.uri)?;
+                let text = server.workspace.file_text(file_id);
+                let formatted =
+                    match handlers::formatting(&server.workspace, file_id, &params.options) {
+                        Some(formatted) if formatted != *text => formatted,
+                        _ => return Ok(None),
+                    };
+                let range = TextRange::up_to(TextSize::of(text.as_str()));
+                let range = convert::range(&server.workspace.line_index(file_id), &text, range);
+                Ok(Some(vec![TextEdit::new(range, formatted)]))
+            }),
+            SemanticTokensFullRequest::METHOD => {
+                self.dispatch::<SemanticTokensFullRequest>(request, |server, params| {
+                    let file_id = server.file(&params.text_document.uri)?;
+                    let data = handlers::semantic_tokens(&server.workspace, file_id);
+                    Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+                        result_id: None,
+                        data,
+                    })))
+                })
+            }
+            Rename::METHOD => self.dispatch::<Rename>(request, |server, params| {
+                let (file_id, offset) = server.offset(&params.text_document_position)?;
+                let ranges =
+                    handlers::rename(&server.workspace, file_id, offset, &params.new_name)?;
+                let text = server.workspace.file_text(file_id);
+                let index = server.workspace.line_index(file_id);
+                let edits = ranges
+                    .into_iter()
+                    .map(|range| {
+                        TextEdit::new(
+                            convert::range(&index, &text, range),
+                            params.new_name.clone(),
+                        )
+                    })
+                    .collect();
+                let uri = params.text_document_position.text_document.uri;
+                Ok(Some(WorkspaceEdit::new(HashMap::from([(uri, edits)]))))
+            }),
+            GotoDefinition::METHOD => self.dispatch::<GotoDefinition>(request, |server, params| {
+                let (file_id, offset) = server.offset(&params.text_document_position_params)?;
+                let locations = server
+                    .workspace
+                    .goto_definition(file_id, offset)
+                    .into_iter()
+                    .map(|location| server.location(location))
+                    .collect();
+                Ok(Some(GotoDefinitionResponse::Array(locations)))
+            }),
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request `{}`", request.method),
+            ),
+        }
+    }
+
+    /// Extracts the parameters of the request `R` and answers it with the result of `handler`.
+    fn dispatch<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: fn(&Server, R::Params) -> std::result::Result<R::Result, String>,
+    ) -> Response {
+        let id = request.id.clone();
+        let params = match request.extract::<R::Params>(R::METHOD) {
+            Ok((_, params)) => params,
+            Err(error) => {
+                return Response::new_err(id, ErrorCode::InvalidParams as i32, error.to_string())
+            }
+        };
+        match handler(self, params) {
+            Ok(result) => Response::new_ok(id, result),
+            Err(message) => Response::new_err(id, ErrorCode::RequestFailed as i32, message),
+        }
+    }
+
+    /// Updates the workspace for a notification. Returns the diagnostics to publish for the
+    /// document that changed.
+    fn handle_notification(&mut self, notification: Notification) -> Result<Option<Notification>> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    notification.extract(DidOpenTextDocument::METHOD)?;
+                let document = params.text_document;
+                let file_id = self.set_text(document.uri, document.text);
+                Ok(Some(self.publish_diagnostics(file_id)))
+            }
+            DidChangeTextDocument::METHOD => {
+                let mut params: DidChangeTextDocumentParams =
+                    notification.extract(DidChangeTextDocument::METHOD)?;
+                // With full synchronization every change contains the whole text
+                let change = match params.content_changes.pop() {
+                    Some(change) => change,
+                    None => return Ok(None),
+                };
+                let file_id = self.set_text(params.text_document.uri, change.text);
+                Ok(Some(self.publish_diagnostics(file_id)))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    notification.extract(DidCloseTextDocument::METHOD)?;
+                let uri = params.text_document.uri;
+                if let Some(file_id) = self.files.remove(&uri) {
+                    self.workspace.remove_file(file_id);
+                }
+                Ok(Some(diagnostics_notification(uri, Vec::new())))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn set_text(&mut self, uri: Url, text: String) -> FileId {
+        if let Some(&file_id) = self.files.get(&uri) {
+            self.workspace.set_file_text(file_id, text);
+            return file_id;
+        }
+        let path = uri
+            .to_file_path()
+            .unwrap_or_else(|()| PathBuf::from(uri.as_str()));
+        let file_id = self.workspace.add_file(path, text);
+        self.files.insert(uri, file_id);
+        file_id
+    }
+
+    fn publish_diagnostics(&self, file_id: FileId) -> Notification {
+        let uri = self.uri(file_id).clone();
+        let text = self.workspace.file_text(file_id);
+        let index = self.workspace.line_index(file_id);
+        let diagnostics = handlers::diagnostics(&self.workspace, file_id, self.dialect)
+            .iter()
+            .map(|diagnostic| convert::diagnostic(&index, &text, &uri, diagnostic))
+            .collect();
+        diagnostics_notification(uri, diagnostics)
+    }
+
+    fn file(&self, uri: &Url) -> std::result::Result<FileId, String> {
+        self.files
+            .get(uri)
+            .copied()
+            .ok_or_else(|| format!("`{}` is not open", uri))
+    }
+
+    fn uri(&self, file_id: FileId) -> &Url {
+        self.files
+            .iter()
+            .find(|(_, &id)| id == file_id)
+            .map(|(uri, _)| uri)
+            .expect("every file in the workspace is an open document")
+    }
+
+    fn offset(
+        &self,
+        params: &TextDocumentPositionParams,
+    ) -> std::result::Result<(FileId, TextSize), String> {
+        let file_id = self.file(&params.text_document.uri)?;
+        let text = self.workspace.file_text(file_id);
+        let index = self.workspace.line_index(file_id);
+        let offset = convert::offset(&index, &text, params.position)
+            .ok_or_else(|| "the position is past the end of the document".to_string())?;
+        Ok((file_id, offset))
+    }
+
+    fn location(&self, location: lua_parser::Location) -> lsp_types::Location {
+        let text = self.workspace.file_text(location.file_id);
+        let index = self.workspace.line_index(location.file_id);
+        lsp_types::Location::new(
+            self.uri(location.file_id).clone(),
+            convert::range(&index, &text, location.range),
+        )
+    }
+}
+
+fn diagnostics_notification(uri: Url, diagnostics: Vec<lsp_types::Diagnostic>) -> Notification {
+    Notification::new(
+        PublishDiagnostics::METHOD.to_string(),
+        PublishDiagnosticsParams::new(uri, diagnostics, None),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_server::RequestId;
+    use lsp_types::{
+        DocumentSymbolParams, Position, PublishDiagnosticsParams, RenameParams,
+        TextDocumentIdentifier, TextDocumentItem, WorkspaceFolder,
+    };
+    use serde_json::{json, Value};
+    use std::thread;
+
+    /// A client that talks to a server on another thread.
+    struct Client {
+        connection: Connection,
+        server: Option<thread::JoinHandle<()>>,
+        next_id: i32,
+    }
+
+    impl Client {
+        fn new(params: InitializeParams) -> Client {
+            let (client, server) = Connection::memory();
+            let server = thread::spawn(move || run(&server, params).unwrap());
+            Client {
+                connection: client,
+                server: Some(server),
+                next_id: 0,
+            }
+        }
+
+        fn notify<N: lsp_types::notification::Notification>(&self, params: N::Params) {
+            let notification = Notification::new(N::METHOD.to_string(), params);
+            self.connection.sender.send(notification.into()).unwrap();
+        }
+
+        fn request<R: lsp_types::request::Request>(&mut self, params: R::Params) -> Value {
+            self.next_id += 1;
+            let request =
+                Request::new(RequestId::from(self.next_id), R::METHOD.to_string(), params);
+            self.connection.sender.send(request.into()).unwrap();
+            match self.connection.receiver.recv().unwrap() {
+                Message::Response(response) => {
+                    assert_eq!(response.id, RequestId::from(self.next_id));
+                    match response.error {
+                        Some(error) => json!({ "error": error.message }),
+                        None => response.result.unwrap(),
+                    }
+                }
+                message => panic!("expected a response, got {:?}", message),
+            }
+        }
+
+        fn diagnostics(&self) -> PublishDiagnosticsParams {
+            match self.connection.receiver.recv().unwrap() {
+                Message::Notification(notification) => {
+                    notification.extract(PublishDiagnostics::METHOD).unwrap()
+                }
+                message => panic!("expected diagnostics, got {:?}", message),
+            }
+        }
+
+        fn open(&self, uri: &Url, text: &str) -> PublishDiagnosticsParams {
+            self.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    "lua".to_string(),
+                    0,
+                    text.to_string(),
+                ),
+            });
+            self.diagnostics()
+        }
+    }
+
+    impl Drop for Client {
+        fn drop(&mut self) {
+            self.next_id += 1;
+            let shutdown = Request::new(RequestId::from(self.next_id), "shutdown".to_string(), ());
+            self.connection.sender.send(shutdown.into()).unwrap();
+            self.connection.receiver.recv().unwrap();
+            let exit = Notification::new("exit".to_string(), ());
+            self.connection.sender.send(exit.into()).unwrap();
+            self.server.take().unwrap().join().unwrap();
+        }
+    }
+
+    fn uri(path: &str) -> Url {
+        Url::parse(&format!("file:///project/{}", path)).unwrap()
+    }
+
+    #[test]
+    fn test_diagnostics_and_document_symbols() {
+        let mut client = Client::new(InitializeParams::default());
+        let main = uri("main.lua");
+        let diagnostics = client.open(&main, "local x = ");
+        assert_eq!(diagnostics.uri, main);
+        assert_eq!(diagnostics.diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics.diagnostics[0].code,
+            Some(lsp_types::NumberOrString::String("E0001".to_string()))
+        );
+
+        client.notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+            text_document: lsp_types::VersionedTextDocumentIdentifier::new(main.clone(), 1),
+            content_changes: vec![lsp_types::TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "function setup() end".to_string(),
+            }],
+        });
+        assert!(client.diagnostics().diagnostics.is_empty());
+
+        let symbols = client.request::<DocumentSymbolRequest>(DocumentSymbolParams {
+            text_document: TextDocumentIdentifier::new(main.clone()),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        assert_eq!(symbols[0]["name"], "setup");
+        assert_eq!(
+            symbols[0]["selectionRange"]["end"],
+            json!({ "line": 0, "character": 14 })
+        );
+
+        client.notify::<DidCloseTextDocument>(DidCloseTextDocumentParams {
+            text_document: TextDocumentIdentifier::new(main.clone()),
+        });
+        assert!(client.diagnostics().diagnostics.is_empty());
+        let symbols = client.request::<DocumentSymbolRequest>(DocumentSymbolParams {
+            text_document: TextDocumentIdentifier::new(main),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        assert_eq!(symbols["error"], "`file:///project/main.lua` is not open");
+    }
+
+    #[test]
+    fn test_rename_and_definition() {
+        let mut client = Client::new(InitializeParams {
+            workspace_folders: Some(vec![WorkspaceFolder {
+                uri: Url::parse("file:///project").unwrap(),
+                name: "project".to_string(),
+            }]),
+            ..InitializeParams::default()
+        });
+        let main = uri("main.lua");
+        client.open(
+            &uri("util.lua"),
+            "local M = {}\nfunction M.trim(s) end\nreturn M",
+        );
+        client.open(&main, "local util = require 'util'\nutil.trim(x)");
+
+        let position = |line, character| TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier::new(main.clone()),
+            position: Position::new(line, character),
+        };
+        let definition = client.request::<GotoDefinition>(lsp_types::GotoDefinitionParams {
+            text_document_position_params: position(1, 6),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        assert_eq!(
+            definition,
+            json!([{
+                "uri": "file:///project/util.lua",
+                "range": {
+                    "start": { "line": 1, "character": 9 },
+                    "end": { "line": 1, "character": 15 },
+                },
+            }])
+        );
+
+        let edit = client.request::<Rename>(RenameParams {
+            text_document_position: position(1, 1),
+            new_name: "strings".to_string(),
+            work_done_progress_params: Default::default(),
+        });
+        let edits = edit["changes"]["file:///project/main.lua"]
+            .as_array()
+            .unwrap();
+        assert_eq!(edits.len(), 2);
+        assert_eq!(
+            edits[1]["range"]["start"],
+            json!({ "line": 1, "character": 0 })
+        );
+        let edit = client.request::<Rename>(RenameParams {
+            text_document_position: position(1, 1),
+            new_name: "1x".to_string(),
+            work_done_progress_params: Default::default(),
+        });
+        assert_eq!(edit["error"], "`1x` is not a valid name");
+    }
+}
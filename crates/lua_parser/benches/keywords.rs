@@ -0,0 +1,197 @@
+//! Benchmarks of looking up keywords, to compare the generated `SyntaxKind::from_keyword` with
+//! the alternatives that were tried for it. Every lookup is run on the identifiers and keywords of
+//! an identifier-heavy chunk, most of which are not keywords.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use lua_parser::{tokenize, SyntaxKind, TextRange, TextSize};
+
+const MODULE: &str = r#"
+local M = {}
+
+function M.render(items, options, callback)
+    local result, index, count = {}, 0, #items
+    for position, item in ipairs(items) do
+        if item.visible and not item.hidden then
+            index = index + 1
+            result[index] = callback(item.value, options.indent, position)
+        elseif item.children ~= nil then
+            local nested = M.render(item.children, options, callback)
+            for _, child in ipairs(nested) do
+                index = index + 1
+                result[index] = child
+            end
+        end
+    end
+    while count > index do
+        result[count] = nil
+        count = count - 1
+    end
+    return result, index
+end
+
+return M
+"#;
+
+/// Returns the text of every identifier and keyword in `MODULE`.
+fn words() -> Vec<&'static str> {
+    let mut offset = TextSize::from(0);
+    let mut words = Vec::new();
+    for token in tokenize(MODULE) {
+        let range = TextRange::at(offset, token.len);
+        offset += token.len;
+        if token.kind == SyntaxKind::IDENT || token.kind.is_keyword() {
+            words.push(&MODULE[range]);
+        }
+    }
+    words
+}
+
+fn keywords() -> Vec<(&'static str, SyntaxKind)> {
+    SyntaxKind::keywords()
+        .map(|kind| (kind.token_text().unwrap(), kind))
+        .collect()
+}
+
+/// Matches on the length first, then compares with the keywords of that length.
+struct ByLength(Vec<Vec<(&'static str, SyntaxKind)>>);
+
+impl ByLength {
+    fn new() -> ByLength {
+        let keywords = keywords();
+        let max_len = keywords.iter().map(|(text, _)| text.len()).max().unwrap();
+        let mut by_length = vec![Vec::new(); max_len + 1];
+        for (text, kind) in keywords {
+            by_length[text.len()].push((text, kind));
+        }
+        ByLength(by_length)
+    }
+
+    fn lookup(&self, ident: &str) -> Option<SyntaxKind> {
+        let candidates = self.0.get(ident.len())?;
+        candidates
+            .iter()
+            .find(|(text, _)| *text == ident)
+            .map(|&(_, kind)| kind)
+    }
+}
+
+/// Rejects words by their length and first byte before the generated match.
+struct Prefilter {
+    lengths: u64,
+    first_bytes: [bool; 256],
+}
+
+impl Prefilter {
+    fn new() -> Prefilter {
+        let mut prefilter = Prefilter {
+            lengths: 0,
+            first_bytes: [false; 256],
+        };
+        for (text, _) in keywords() {
+            prefilter.lengths |= 1 << text.len();
+            prefilter.first_bytes[usize::from(text.as_bytes()[0])] = true;
+        }
+        prefilter
+    }
+
+    fn lookup(&self, ident: &str) -> Option<SyntaxKind> {
+        if ident.len() >= 64
+            || self.lengths & (1 << ident.len()) == 0
+            || !self.first_bytes[usize::from(ident.as_bytes()[0])]
+        {
+            return None;
+        }
+        SyntaxKind::from_keyword(ident)
+    }
+}
+
+/// A perfect hash of the length, first and last byte of the keywords.
+struct PerfectHash {
+    multipliers: (usize, usize),
+    table: Vec<Option<(&'static str, SyntaxKind)>>,
+}
+
+impl PerfectHash {
+    fn new() -> PerfectHash {
+        let keywords = keywords();
+        let size = (keywords.len() * 2).next_power_of_two();
+        for first in 1..256 {
+            for last in 1..256 {
+                let mut hash = PerfectHash {
+                    multipliers: (first, last),
+                    table: vec![None; size],
+                };
+                let is_perfect = keywords.iter().all(|&(text, kind)| {
+                    let slot = hash.slot(text);
+                    hash.table[slot].replace((text, kind)).is_none()
+                });
+                if is_perfect {
+                    return hash;
+                }
+            }
+        }
+        panic!("no perfect hash for the keywords");
+    }
+
+    fn slot(&self, ident: &str) -> usize {
+        let bytes = ident.as_bytes();
+        let (first, last) = self.multipliers;
+        (usize::from(bytes[0]) * first + usize::from(bytes[bytes.len() - 1]) * last + bytes.len())
+            & (self.table.len() - 1)
+    }
+
+    fn lookup(&self, ident: &str) -> Option<SyntaxKind> {
+        match self.table[self.slot(ident)] {
+            Some((text, kind)) if text == ident => Some(kind),
+            _ => None,
+        }
+    }
+}
+
+fn from_keyword(c: &mut Criterion) {
+    let words = words();
+    let by_length = ByLength::new();
+    let prefilter = Prefilter::new();
+    let perfect_hash = PerfectHash::new();
+    for &word in &words {
+        let expected = SyntaxKind::from_keyword(word);
+        assert_eq!(by_length.lookup(word), expected);
+        assert_eq!(prefilter.lookup(word), expected);
+        assert_eq!(perfect_hash.lookup(word), expected);
+    }
+
+    let mut group = c.benchmark_group("from_keyword");
+    group.throughput(Throughput::Elements(words.len() as u64));
+    group.bench_function("generated", |b| {
+        b.iter(|| {
+            for &word in &words {
+                black_box(SyntaxKind::from_keyword(black_box(word)));
+            }
+        })
+    });
+    group.bench_function("by_length", |b| {
+        b.iter(|| {
+            for &word in &words {
+                black_box(by_length.lookup(black_box(word)));
+            }
+        })
+    });
+    group.bench_function("prefilter", |b| {
+        b.iter(|| {
+            for &word in &words {
+                black_box(prefilter.lookup(black_box(word)));
+            }
+        })
+    });
+    group.bench_function("perfect_hash", |b| {
+        b.iter(|| {
+            for &word in &words {
+                black_box(perfect_hash.lookup(black_box(word)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, from_keyword);
+criterion_main!(benches);
@@ -0,0 +1,120 @@
+//! Benchmarks of lexing and parsing a large file. Besides the time, the number of allocations of a
+//! single parse is printed, to check the allocation churn of the parser internals. The
+//! `deep_chains` group parses long chains of nested expressions at two lengths each, so a build
+//! time that isn't linear in the depth of the tree shows up as more than a doubling.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use lua_parser::{tokenize, Chunk};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Counts the allocations of the benchmark process.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const MODULE: &str = r#"
+local M = {}
+local insert, concat = table.insert, table.concat
+
+--- Splits `s` at every occurrence of `sep`.
+function M.split(s, sep)
+    local parts = {}
+    for part in string.gmatch(s, "([^" .. sep .. "]+)") do
+        insert(parts, part)
+    end
+    return parts
+end
+
+function M:render(items, options)
+    options = options or { indent = 2, width = 80 }
+    local lines = {}
+    for i, item in ipairs(items) do
+        if type(item) == "table" and item.children ~= nil then
+            lines[#lines + 1] = self:render(item.children, options)
+        elseif item.kind == 'text' or (item.kind == 'raw' and not options.strict) then
+            lines[#lines + 1] = string.rep(" ", options.indent * i) .. tostring(item.value)
+        else
+            error(("unknown item %d: %s"):format(i, item.kind))
+        end
+    end
+    return concat(lines, "\n")
+end
+
+local function fib(n)
+    if n < 2 then return n end
+    return fib(n - 1) + fib(n - 2)
+end
+
+M.values = { 1, 2.5, 0x10, "four", [[five]], fib(6), nil, true }
+return M
+"#;
+
+fn large_file() -> String {
+    MODULE.repeat(2000)
+}
+
+fn lex(c: &mut Criterion) {
+    let text = large_file();
+    let mut group = c.benchmark_group("lex");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("tokenize", |b| b.iter(|| tokenize(black_box(&text))));
+    group.finish();
+}
+
+fn parse(c: &mut Criterion) {
+    let text = large_file();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(Chunk::parse(&text));
+    println!(
+        "allocations of a parse of {} bytes: {}",
+        text.len(),
+        ALLOCATIONS.load(Ordering::Relaxed) - before
+    );
+
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("chunk", |b| b.iter(|| Chunk::parse(black_box(&text))));
+    group.finish();
+}
+
+fn deep_chains(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep_chains");
+    for &n in &[10_000, 20_000] {
+        let text = format!("x = a{}", ".b".repeat(n));
+        group.bench_with_input(BenchmarkId::new("field", n), &text, |b, text| {
+            b.iter(|| Chunk::parse(black_box(text)))
+        });
+    }
+    for &n in &[8_000, 16_000] {
+        let text = format!("return 1{}", "+1".repeat(n));
+        group.bench_with_input(BenchmarkId::new("add", n), &text, |b, text| {
+            b.iter(|| Chunk::parse(black_box(text)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lex, parse, deep_chains);
+criterion_main!(benches);
@@ -0,0 +1,68 @@
+//! Algorithms on syntax trees.
+
+use crate::{NodeOrToken, SyntaxKind, SyntaxNode, WalkEvent};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// A step of a preorder walk over the tree that ignores trivia.
+#[derive(PartialEq, Eq, Hash)]
+enum StructuralEvent {
+    Enter(SyntaxKind),
+    Leave,
+    Token(SyntaxKind, String),
+}
+
+fn structural_events(node: &SyntaxNode) -> impl Iterator<Item = StructuralEvent> {
+    node.preorder_with_tokens().filter_map(|event| match event {
+        WalkEvent::Enter(NodeOrToken::Node(node)) => Some(StructuralEvent::Enter(node.kind())),
+        WalkEvent::Leave(NodeOrToken::Node(_)) => Some(StructuralEvent::Leave),
+        WalkEvent::Enter(NodeOrToken::Token(token)) if !token.kind().is_trivia() => Some(
+            StructuralEvent::Token(token.kind(), token.text().to_string()),
+        ),
+        _ => None,
+    })
+}
+
+/// Returns true if `a` and `b` have the same structure and the same tokens, ignoring whitespace
+/// and comments. E.g. `f(a, b)` and `f( a,--[[x]] b )` are structurally equal, `f(a, b)` and
+/// `f(b, a)` are not.
+pub fn structurally_equal(a: &SyntaxNode, b: &SyntaxNode) -> bool {
+    structural_events(a).eq(structural_events(b))
+}
+
+/// Returns a hash of the structure and tokens of `node`, ignoring whitespace and comments. Nodes
+/// that are `structurally_equal` have the same hash. The hash is not stable across versions of
+/// this crate, so it should not be persisted.
+pub fn structural_hash(node: &SyntaxNode) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for event in structural_events(node) {
+        event.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chunk;
+
+    fn root(text: &str) -> SyntaxNode {
+        Chunk::parse(text).syntax_node()
+    }
+
+    #[test]
+    fn test_structurally_equal() {
+        let a = root("local x = f(a, b) -- call f");
+        let b = root("local  x=f( a,--[[ b ]]\n b )");
+        assert!(structurally_equal(&a, &b));
+        assert_eq!(structural_hash(&a), structural_hash(&b));
+
+        for other in &["local x = f(b, a)", "x = f(a, b)", "local x = f(a, b)()"] {
+            let other = root(other);
+            assert!(!structurally_equal(&a, &other));
+            assert_ne!(structural_hash(&a), structural_hash(&other));
+        }
+    }
+}
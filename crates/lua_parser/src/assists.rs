@@ -0,0 +1,148 @@
+//! Code actions that editors offer for the code at the cursor, e.g. to wrap a call in `pcall`.
+//!
+//! Every assist inspects the syntax tree around the cursor or selection and returns an edit if it
+//! applies there. Assists don't know anything about the values at runtime, so e.g. "extract local
+//! variable" doesn't check whether the extracted expression has side effects.
+
+mod convert_to_string_format;
+mod extract_local;
+mod inline_local;
+mod swap_if_else;
+mod wrap_in_pcall;
+
+pub use self::inline_local::inline_local;
+
+use crate::{AstNode, Chunk, NodeOrToken, SyntaxNode, TextEdit, TextRange};
+
+/// A code action that applies to the code at the cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assist {
+    /// A stable identifier of the assist, e.g. `wrap_in_pcall`
+    pub id: &'static str,
+    /// The description that is shown to the user, e.g. "Wrap in pcall"
+    pub label: String,
+    /// The code the assist applies to, e.g. for editors to highlight it
+    pub target: TextRange,
+    pub edit: TextEdit,
+}
+
+/// The position the assists are requested for.
+pub(crate) struct AssistContext<'a> {
+    chunk: &'a Chunk,
+    range: TextRange,
+}
+
+impl AssistContext<'_> {
+    pub(crate) fn root(&self) -> &SyntaxNode {
+        self.chunk.syntax()
+    }
+
+    /// Returns the selection, which is empty for a cursor position.
+    pub(crate) fn range(&self) -> TextRange {
+        self.range
+    }
+
+    /// Returns the innermost node that contains the selection. For a cursor position the node of
+    /// the token right of the cursor is preferred, unless that token is trivia.
+    pub(crate) fn covering_node(&self) -> SyntaxNode {
+        let root = self.root();
+        if !self.range.is_empty() {
+            return match root.covering_element(self.range) {
+                NodeOrToken::Node(node) => node,
+                NodeOrToken::Token(token) => token.parent().unwrap(),
+            };
+        }
+        let tokens = root.token_at_offset(self.range.start());
+        let token = match (tokens.clone().left_biased(), tokens.right_biased()) {
+            (Some(left), Some(right)) if right.kind().is_trivia() => left,
+            (_, Some(right)) => right,
+            (left, None) => match left {
+                Some(left) => left,
+                None => return root.clone(),
+            },
+        };
+        token.parent().unwrap()
+    }
+
+    /// Returns the innermost node of type `N` that contains the selection.
+    pub(crate) fn find_node<N: AstNode>(&self) -> Option<N> {
+        self.covering_node().ancestors().find_map(N::cast)
+    }
+}
+
+type AssistHandler = fn(&AssistContext) -> Option<Assist>;
+
+const ASSISTS: &[AssistHandler] = &[
+    wrap_in_pcall::wrap_in_pcall,
+    convert_to_string_format::convert_to_string_format,
+    swap_if_else::swap_if_else,
+    extract_local::extract_local,
+    inline_local::inline_local_assist,
+];
+
+/// Returns the assists that apply to `range` in `chunk`, where an empty range is a cursor
+/// position. The edits of the assists refer to the text of `chunk`.
+pub fn assists(chunk: &Chunk, range: TextRange) -> Vec<Assist> {
+    let ctx = AssistContext { chunk, range };
+    ASSISTS.iter().filter_map(|assist| assist(&ctx)).collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::TextSize;
+
+    /// Returns `text` without the `$0` markers and the range between them, or the position of the
+    /// single marker.
+    fn extract_range(text: &str) -> (String, TextRange) {
+        let markers: Vec<_> = text.match_indices("$0").map(|(idx, _)| idx).collect();
+        let range = match markers[..] {
+            [cursor] => TextRange::empty(TextSize::from(cursor as u32)),
+            [start, end] => TextRange::new(
+                TextSize::from(start as u32),
+                TextSize::from((end - 2) as u32),
+            ),
+            _ => panic!("expected one or two `$0` markers in {:?}", text),
+        };
+        (text.replace("$0", ""), range)
+    }
+
+    fn find_assist(id: &str, before: &str) -> (String, Option<Assist>) {
+        let (text, range) = extract_range(before);
+        let chunk = Chunk::parse(&text).tree();
+        let assist = assists(&chunk, range)
+            .into_iter()
+            .find(|assist| assist.id == id);
+        (text, assist)
+    }
+
+    /// Checks that the assist `id` turns `before` into `after`, `$0` marks the cursor or the
+    /// start and end of the selection in `before`.
+    pub(crate) fn check_assist(id: &str, before: &str, after: &str) {
+        let (text, assist) = find_assist(id, before);
+        let assist = assist.unwrap_or_else(|| panic!("`{}` doesn't apply to {:?}", id, before));
+        assert_eq!(assist.edit.apply(&text), after);
+    }
+
+    pub(crate) fn check_assist_not_applicable(id: &str, before: &str) {
+        let (_, assist) = find_assist(id, before);
+        assert_eq!(assist, None, "`{}` applies to {:?}", id, before);
+    }
+
+    #[test]
+    fn test_assists_at_cursor() {
+        let text = r#"if x then f("a" .. b) else g() end"#;
+        let chunk = Chunk::parse(text).tree();
+        let ids = |offset: u32| -> Vec<_> {
+            assists(&chunk, TextRange::empty(offset.into()))
+                .into_iter()
+                .map(|assist| assist.id)
+                .collect()
+        };
+        assert_eq!(ids(1), vec!["swap_if_else"]);
+        assert_eq!(
+            ids(16),
+            vec!["wrap_in_pcall", "convert_to_string_format", "extract_local"]
+        );
+    }
+}
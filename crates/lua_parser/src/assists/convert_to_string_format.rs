@@ -0,0 +1,131 @@
+use super::{Assist, AssistContext};
+use crate::{
+    ast::{BinExpr, BinOp, Expr, StringLit},
+    AstNode, AstToken, TextEdit,
+};
+
+/// Converts a concatenation of strings and values to a call of `string.format`:
+///
+/// ```lua
+/// "x = " .. x .. "%"   -->  string.format("x = %s%%", x)
+/// ```
+///
+/// Only quoted strings become part of the format string, long strings and parenthesized
+/// concatenations are passed as arguments.
+pub(super) fn convert_to_string_format(ctx: &AssistContext) -> Option<Assist> {
+    let mut concat = ctx
+        .covering_node()
+        .ancestors()
+        .filter_map(BinExpr::cast)
+        .find(is_concat)?;
+    while let Some(parent) = concat.syntax().parent().and_then(BinExpr::cast) {
+        if !is_concat(&parent) {
+            break;
+        }
+        concat = parent;
+    }
+
+    let mut operands = Vec::new();
+    flatten(Expr::BinExpr(concat.clone()), &mut operands);
+    let mut format = String::new();
+    let mut args = Vec::new();
+    for operand in &operands {
+        match quoted_string(operand) {
+            Some(string) => push_format_text(&mut format, &string),
+            None => {
+                format.push_str("%s");
+                args.push(operand.syntax().to_string());
+            }
+        }
+    }
+    if args.is_empty() || args.len() == operands.len() {
+        return None;
+    }
+
+    let range = concat.syntax().text_range();
+    Some(Assist {
+        id: "convert_to_string_format",
+        label: "Convert to string.format".to_string(),
+        target: range,
+        edit: TextEdit::replace(
+            range,
+            format!("string.format(\"{}\", {})", format, args.join(", ")),
+        ),
+    })
+}
+
+fn is_concat(expr: &BinExpr) -> bool {
+    expr.op_kind() == Some(BinOp::Concat)
+}
+
+/// Collects the operands of a chain of concatenations from left to right.
+fn flatten(expr: Expr, operands: &mut Vec<Expr>) {
+    match expr {
+        Expr::BinExpr(bin) if is_concat(&bin) => {
+            if let Some(lhs) = bin.lhs() {
+                flatten(lhs, operands);
+            }
+            if let Some(rhs) = bin.rhs() {
+                flatten(rhs, operands);
+            }
+        }
+        expr => operands.push(expr),
+    }
+}
+
+fn quoted_string(expr: &Expr) -> Option<StringLit> {
+    match expr {
+        Expr::Literal(literal) => literal
+            .syntax()
+            .first_token()
+            .and_then(StringLit::cast)
+            .filter(|string| !string.is_long()),
+        _ => None,
+    }
+}
+
+/// Appends the contents of `string` to a double quoted format string, keeping its escape
+/// sequences as written.
+fn push_format_text(format: &mut String, string: &StringLit) {
+    let offset = string.syntax().text_range().start();
+    let content = &string.text()[string.content_range() - offset];
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                format.push(c);
+                format.extend(chars.next());
+            }
+            '%' => format.push_str("%%"),
+            '"' => format.push_str("\\\""),
+            c => format.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assists::tests::{check_assist, check_assist_not_applicable};
+
+    #[test]
+    fn test_convert_to_string_format() {
+        check_assist(
+            "convert_to_string_format",
+            r#"print("x = " .. x$0 .. "%")"#,
+            r#"print(string.format("x = %s%%", x))"#,
+        );
+        check_assist(
+            "convert_to_string_format",
+            r#"s = 'say "hi\'' .. $0name"#,
+            r#"s = string.format("say \"hi\'%s", name)"#,
+        );
+        check_assist(
+            "convert_to_string_format",
+            r#"s = a .. $0"\n" .. (b .. c)"#,
+            r#"s = string.format("%s\n%s", a, (b .. c))"#,
+        );
+        check_assist_not_applicable("convert_to_string_format", r#"s = "a" $0.. "b""#);
+        check_assist_not_applicable("convert_to_string_format", "s = a $0.. b");
+        check_assist_not_applicable("convert_to_string_format", r#"s = "a" $0+ b"#);
+    }
+}
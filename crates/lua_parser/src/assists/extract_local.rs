@@ -0,0 +1,136 @@
+use super::{Assist, AssistContext};
+use crate::{
+    ast::{AssignStmt, Expr, ExprStmt},
+    AstNode,
+    SyntaxKind::*,
+    SyntaxNode, TextEditBuilder,
+};
+
+/// Moves the selected expression, or the expression at the cursor, into a new local variable in
+/// front of the statement that contains it:
+///
+/// ```lua
+/// print(a + b)  -->  local value = a + b
+///                    print(value)
+/// ```
+///
+/// Expressions that are evaluated more than once, e.g. the condition of a `while` loop, can't be
+/// extracted.
+pub(super) fn extract_local(ctx: &AssistContext) -> Option<Assist> {
+    let expr = find_expr(ctx)?;
+    let anchor = expr
+        .syntax()
+        .ancestors()
+        .find(|node| node.parent().is_some_and(|parent| parent.kind() == BLOCK))?;
+    if !can_extract(&expr, &anchor) {
+        return None;
+    }
+
+    let text = ctx.root().text().to_string();
+    let name = fresh_name(ctx.root());
+    let anchor_start = usize::from(anchor.text_range().start());
+    let line_start = text[..anchor_start].rfind('\n').map_or(0, |idx| idx + 1);
+    let indent = &text[line_start..anchor_start];
+    let local = format!("local {} = {}", name, expr.syntax());
+    let declaration = if indent.trim().is_empty() {
+        let line_ending = crate::detect_line_endings(&text).dominant();
+        format!("{}{}{}", local, line_ending.as_str(), indent)
+    } else {
+        format!("{}; ", local)
+    };
+
+    let mut edit = TextEditBuilder::default();
+    edit.insert(anchor.text_range().start(), declaration);
+    edit.replace(expr.syntax().text_range(), name);
+    Some(Assist {
+        id: "extract_local",
+        label: "Extract into local variable".to_string(),
+        target: expr.syntax().text_range(),
+        edit: edit.finish(),
+    })
+}
+
+/// Returns the selected expression, or the innermost expression at the cursor that isn't just a
+/// name.
+fn find_expr(ctx: &AssistContext) -> Option<Expr> {
+    let mut exprs = ctx.covering_node().ancestors().filter_map(Expr::cast);
+    if ctx.range().is_empty() {
+        exprs.find(|expr| !matches!(expr, Expr::NameRef(_)))
+    } else {
+        exprs.find(|expr| expr.syntax().text_range() == ctx.range())
+    }
+}
+
+/// Returns whether `expr` is evaluated exactly once when `anchor` is executed, so it can be
+/// evaluated in front of `anchor` instead.
+fn can_extract(expr: &Expr, anchor: &SyntaxNode) -> bool {
+    let parent = match expr.syntax().parent() {
+        Some(parent) => parent,
+        None => return false,
+    };
+    if ExprStmt::can_cast(parent.kind()) {
+        return false;
+    }
+    let is_target = parent
+        .parent()
+        .and_then(AssignStmt::cast)
+        .and_then(|assign| assign.targets())
+        .is_some_and(|targets| targets.syntax() == &parent);
+    if is_target {
+        return false;
+    }
+    // Loop conditions are evaluated on every iteration and `elseif` conditions only if the
+    // previous conditions are false
+    let mut ancestors = expr.syntax().ancestors().take_while(|node| node != anchor);
+    !ancestors.any(|node| {
+        node.kind() != BLOCK
+            && node.parent().is_some_and(|parent| {
+                matches!(parent.kind(), WHILE_STMT | REPEAT_STMT | ELSE_IF_CLAUSE)
+            })
+    })
+}
+
+/// Returns `value`, or `value2`, `value3`, ... if that name is already used in the chunk.
+fn fresh_name(root: &SyntaxNode) -> String {
+    let used: Vec<String> = root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == IDENT)
+        .map(|token| token.text().to_string())
+        .collect();
+    (1..)
+        .map(|idx| match idx {
+            1 => "value".to_string(),
+            idx => format!("value{}", idx),
+        })
+        .find(|name| !used.contains(name))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assists::tests::{check_assist, check_assist_not_applicable};
+
+    #[test]
+    fn test_extract_local() {
+        check_assist(
+            "extract_local",
+            "function f()\n    print(a $0+ b)\nend",
+            "function f()\n    local value = a + b\n    print(value)\nend",
+        );
+        check_assist(
+            "extract_local",
+            "local value = 1; x = $0t.n$0 * 2",
+            "local value = 1; local value2 = t.n; x = value2 * 2",
+        );
+        check_assist(
+            "extract_local",
+            "if x then\r\n  f(g($0y))\r\nend\r\n",
+            "if x then\r\n  local value = g(y)\r\n  f(value)\r\nend\r\n",
+        );
+        check_assist_not_applicable("extract_local", "f$0(x)");
+        check_assist_not_applicable("extract_local", "$0t.n$0 = 1");
+        check_assist_not_applicable("extract_local", "while n $0> 0 do n = n - 1 end");
+        check_assist_not_applicable("extract_local", "if a then elseif f$0(b) then end");
+    }
+}
@@ -0,0 +1,359 @@
+use super::{Assist, AssistContext};
+use crate::{
+    ast::{BinExpr, Expr, LocalAssignStmt, NameRef},
+    precedence::{needs_parens, BinOp},
+    resolve::{resolve_names, NameResolution},
+    AstNode, Chunk,
+    SyntaxKind::*,
+    SyntaxNode, TextEdit, TextEditBuilder, TextRange, TextSize,
+};
+
+/// Replaces every use of the local at `offset` with its initializer and removes the declaration:
+///
+/// ```lua
+/// local n = a + b  -->  print((a + b) * 2)
+/// print(n * 2)
+/// ```
+///
+/// `offset` can be on the declaration or on any use of the local. Returns `None` unless the local
+/// is declared with a single value and never assigned afterwards, and inlining keeps the meaning
+/// of the code:
+///
+/// - An initializer with calls, tables or functions must be used exactly once, directly after the
+///   declaration, because evaluating it again or later could change the result.
+/// - No variable that the initializer reads may be assigned between the declaration and a use.
+/// - No use may be in a function that captures the local.
+pub fn inline_local(chunk: &Chunk, offset: TextSize) -> Option<TextEdit> {
+    let resolution = resolve_names(chunk);
+    let id = resolution.local_at(offset)?;
+    let stmt = LocalAssignStmt::cast(resolution.local(id).declaration.clone())?;
+    let names = stmt.name_list()?;
+    let mut values = stmt.expr_list()?.exprs();
+    let value = values.next()?;
+    let has_close_attribute = names
+        .syntax()
+        .children()
+        .any(|attribute| attribute.kind() == ATTRIBUTE && attribute.text() != "<const>");
+    if values.next().is_some() || names.names().count() != 1 || has_close_attribute {
+        return None;
+    }
+
+    let uses: Vec<_> = resolution.local_references(id).collect();
+    if uses.iter().any(|reference| reference.is_write) {
+        return None;
+    }
+    let function = enclosing_function(stmt.syntax().ancestors());
+    for reference in &uses {
+        let slot = reference.name_ref.syntax();
+        if !same_meaning(&resolution, &value, slot)
+            || enclosing_function(slot.ancestors()) != function
+            || assigned_before_use(&resolution, &value, stmt.syntax(), slot)
+        {
+            return None;
+        }
+    }
+    let evaluated_once = match uses.as_slice() {
+        [reference] => evaluated_in_place(stmt.syntax(), reference.name_ref.syntax()),
+        _ => false,
+    };
+    if !evaluated_once && !is_pure(&value) {
+        return None;
+    }
+
+    let text = chunk.syntax().text().to_string();
+    let mut edit = TextEditBuilder::default();
+    edit.delete(declaration_range(&text, stmt.syntax()));
+    for reference in &uses {
+        let slot = reference.name_ref.syntax();
+        let inlined = if requires_parens(&value, slot) {
+            format!("({})", value.syntax())
+        } else {
+            value.syntax().to_string()
+        };
+        edit.replace(slot.text_range(), inlined);
+    }
+    Some(edit.finish())
+}
+
+/// Offers `inline_local` as an assist.
+pub(super) fn inline_local_assist(ctx: &AssistContext) -> Option<Assist> {
+    let name_range = ctx
+        .covering_node()
+        .ancestors()
+        .find(|node| matches!(node.kind(), NAME | NAME_REF))?
+        .text_range();
+    let edit = inline_local(ctx.chunk, ctx.range().start())?;
+    Some(Assist {
+        id: "inline_local",
+        label: "Inline local variable".to_string(),
+        target: name_range,
+        edit,
+    })
+}
+
+/// Returns whether `value` means the same at `slot` as where it is declared: the names it uses
+/// must refer to the same variables, and `...` to the arguments of the same function.
+fn same_meaning(resolution: &NameResolution, value: &Expr, slot: &SyntaxNode) -> bool {
+    let names_match = value
+        .syntax()
+        .descendants()
+        .filter_map(NameRef::cast)
+        .all(|name_ref| {
+            let name = name_ref.syntax().text().to_string();
+            resolution.lookup(&name, slot.text_range().start()) == resolution.resolve(&name_ref)
+        });
+    let function = enclosing_function(value.syntax().ancestors().skip(1));
+    let uses_varargs = value
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == DOTDOTDOT)
+        .any(|token| {
+            enclosing_function(token.parent().into_iter().flat_map(|node| node.ancestors()))
+                == function
+        });
+    names_match && (!uses_varargs || enclosing_function(slot.ancestors()) == function)
+}
+
+/// Returns true if evaluating `value` has no side effects and gives the same result every time.
+/// Calls can have side effects, and table constructors and functions create a new value.
+fn is_pure(value: &Expr) -> bool {
+    !value.syntax().descendants().any(|node| {
+        matches!(
+            node.kind(),
+            CALL_EXPR | METHOD_CALL_EXPR | TABLE_EXPR | FUNCTION_EXPR
+        )
+    })
+}
+
+/// Returns true if `slot` is evaluated exactly once and right after the declaration `decl`: in
+/// the next statement, not in a nested block, loop condition or right-hand side of `and` or `or`,
+/// and after nothing that can have side effects.
+fn evaluated_in_place(decl: &SyntaxNode, slot: &SyntaxNode) -> bool {
+    let next = match decl.next_sibling() {
+        Some(next) => next,
+        None => return false,
+    };
+    if matches!(next.kind(), WHILE_STMT | REPEAT_STMT) {
+        return false;
+    }
+    let mut node = slot.clone();
+    while node != next {
+        let parent = match node.parent() {
+            Some(parent) => parent,
+            None => return false,
+        };
+        let is_conditional = match parent.kind() {
+            BLOCK | ELSE_IF_CLAUSE => true,
+            BIN_EXPR => {
+                let op = BinExpr::cast(parent.clone()).and_then(|expr| expr.op_kind());
+                matches!(op, Some(BinOp::And) | Some(BinOp::Or))
+                    && parent.first_child().as_ref() != Some(&node)
+            }
+            _ => false,
+        };
+        if is_conditional {
+            return false;
+        }
+        node = parent;
+    }
+    !next.descendants().any(|node| {
+        matches!(node.kind(), CALL_EXPR | METHOD_CALL_EXPR)
+            && node.text_range().end() <= slot.text_range().start()
+    })
+}
+
+/// Returns true if a variable that `value` reads can be assigned after the declaration `decl`
+/// and before `slot` is evaluated: between them in the text, or in a loop around `slot` that
+/// doesn't contain the declaration.
+fn assigned_before_use(
+    resolution: &NameResolution,
+    value: &Expr,
+    decl: &SyntaxNode,
+    slot: &SyntaxNode,
+) -> bool {
+    let loops: Vec<_> = slot
+        .ancestors()
+        .take_while(|node| !node.text_range().contains_range(decl.text_range()))
+        .filter(|node| {
+            matches!(
+                node.kind(),
+                WHILE_STMT | REPEAT_STMT | NUMERIC_FOR_STMT | GENERIC_FOR_STMT
+            )
+        })
+        .collect();
+    let is_between = |range: TextRange| {
+        (range.start() >= decl.text_range().end() && range.end() <= slot.text_range().start())
+            || loops
+                .iter()
+                .any(|node| node.text_range().contains_range(range))
+    };
+    value
+        .syntax()
+        .descendants()
+        .filter_map(NameRef::cast)
+        .any(|name_ref| {
+            let local = resolution.resolve(&name_ref);
+            let name = name_ref.syntax().text();
+            resolution.references().iter().any(|reference| {
+                reference.is_write
+                    && reference.local == local
+                    && (local.is_some() || reference.name_ref.syntax().text() == name)
+                    && is_between(reference.name_ref.syntax().text_range())
+            })
+        })
+}
+
+fn enclosing_function(mut ancestors: impl Iterator<Item = SyntaxNode>) -> Option<SyntaxNode> {
+    ancestors.find(|node| {
+        matches!(
+            node.kind(),
+            FUNCTION_EXPR | FUNCTION_STMT | LOCAL_FUNCTION_STMT
+        )
+    })
+}
+
+/// Returns whether `value` needs parentheses to keep its meaning in place of the name `slot`.
+fn requires_parens(value: &Expr, slot: &SyntaxNode) -> bool {
+    if needs_parens(value.syntax(), slot) {
+        return true;
+    }
+    let parent = match slot.parent() {
+        Some(parent) => parent,
+        None => return false,
+    };
+    // `("s"):upper()`, `({ 1 })[1]`
+    let is_prefix = matches!(
+        parent.kind(),
+        CALL_EXPR | METHOD_CALL_EXPR | FIELD_EXPR | INDEX_EXPR
+    ) && parent.first_child().as_ref() == Some(slot);
+    let is_prefix_expr = matches!(
+        value,
+        Expr::NameRef(_)
+            | Expr::ParenExpr(_)
+            | Expr::CallExpr(_)
+            | Expr::MethodCallExpr(_)
+            | Expr::FieldExpr(_)
+            | Expr::IndexExpr(_)
+    );
+    if is_prefix && !is_prefix_expr {
+        return true;
+    }
+    // The local holds only the first value of a call or `...`, which would otherwise expand to all
+    // its values at the end of a list
+    let is_multi_value = match value {
+        Expr::CallExpr(_) | Expr::MethodCallExpr(_) => true,
+        Expr::Literal(literal) => {
+            literal.syntax().first_token().map(|t| t.kind()) == Some(DOTDOTDOT)
+        }
+        _ => false,
+    };
+    let is_last_in_list = match parent.kind() {
+        EXPR_LIST | ARG_LIST => parent.last_child().as_ref() == Some(slot),
+        POSITIONAL_FIELD => parent
+            .parent()
+            .and_then(|table| table.last_child())
+            .is_some_and(|field| field == parent),
+        _ => false,
+    };
+    is_multi_value && is_last_in_list
+}
+
+/// Returns the range to delete to remove the declaration `stmt`, including the line it is on if
+/// there is nothing else on it.
+fn declaration_range(text: &str, stmt: &SyntaxNode) -> TextRange {
+    let mut start = usize::from(stmt.text_range().start());
+    let mut end = usize::from(stmt.text_range().end());
+    let is_space = |c: char| c == ' ' || c == '\t';
+    let spaces_after =
+        |end: usize| text[end..].len() - text[end..].trim_start_matches(is_space).len();
+    if text[end + spaces_after(end)..].starts_with(';') {
+        end += spaces_after(end) + 1;
+    }
+
+    let line_start = text[..start].rfind('\n').map_or(0, |idx| idx + 1);
+    let line_end = text[end..].find('\n').map_or(text.len(), |idx| end + idx);
+    let is_blank = |s: &str| s.trim_end_matches('\r').chars().all(is_space);
+    if is_blank(&text[line_start..start]) && is_blank(&text[end..line_end]) {
+        start = line_start;
+        end = (line_end + 1).min(text.len());
+    } else {
+        end += spaces_after(end);
+    }
+    TextRange::new(TextSize::from(start as u32), TextSize::from(end as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(before: &str, after: &str) {
+        let offset = TextSize::from(before.find("$0").unwrap() as u32);
+        let text = before.replace("$0", "");
+        let chunk = Chunk::parse(&text).tree();
+        let edit = inline_local(&chunk, offset).expect("local can't be inlined");
+        assert_eq!(edit.apply(&text), after);
+    }
+
+    fn check_not_inlined(before: &str) {
+        let offset = TextSize::from(before.find("$0").unwrap() as u32);
+        let text = before.replace("$0", "");
+        let chunk = Chunk::parse(&text).tree();
+        assert_eq!(inline_local(&chunk, offset), None);
+    }
+
+    #[test]
+    fn test_inline_local() {
+        check(
+            "do\n    local $0n = a + b\n    print(n * 2, n)\nend",
+            "do\n    print((a + b) * 2, a + b)\nend",
+        );
+        check("local s = 'x'; print(s$0:upper())", "print(('x'):upper())");
+        check("local v = f() g(a, v$0)", "g(a, (f()))");
+        check("local v = f() g(v$0, a)", "g(f(), a)");
+        check("local t <const> = {}\nreturn t$0", "return {}");
+        check("local x$0 = -y\nreturn 2 ^ x", "return 2 ^ -y");
+    }
+
+    #[test]
+    fn test_not_inlined() {
+        check_not_inlined("local x$0 = 1\nx = 2");
+        check_not_inlined("local a, b$0 = 1, 2\nprint(b)");
+        check_not_inlined("local f <close> = io.open(p)\nreturn f$0");
+        // `y` would refer to another local
+        check_not_inlined("local x$0 = y\nlocal y = 2\nprint(x)");
+        check_not_inlined("local a$0 = ...\nreturn function() return a end");
+        check_not_inlined("print(x$0)");
+    }
+
+    #[test]
+    fn test_side_effects() {
+        // A call must run once, at the same point
+        check_not_inlined("local x$0 = f()\nprint(x, x)");
+        check_not_inlined("local x$0 = f()\ng()\nprint(x)");
+        check_not_inlined("local x$0 = f()\nprint(g(), x)");
+        check_not_inlined("local x$0 = f()\nif c then print(x) end");
+        check_not_inlined("local x$0 = f()\nwhile x do end");
+        check_not_inlined("local x$0 = f()\nprint(c and x)");
+        check_not_inlined("local x$0 = f()");
+        // A new table or function every time
+        check_not_inlined("local t$0 = {}\nt.a = 1\nreturn t");
+        check_not_inlined("local f$0 = function() end\nreturn f == f");
+        check("local x$0 = f()\nprint(x or 1)", "print(f() or 1)");
+        check("local x = a.b\nprint(x$0, x)", "print(a.b, a.b)");
+    }
+
+    #[test]
+    fn test_assigned_between() {
+        check_not_inlined("local x$0 = a\na = 2\nprint(x)");
+        check_not_inlined("local b = 1\nlocal x$0 = b + 1\nb = 2\nprint(x)");
+        check_not_inlined("local x$0 = a\nfor i = 1, 2 do print(x) a = i end");
+        check("local x$0 = a\nprint(x)\na = 2", "print(a)\na = 2");
+    }
+
+    #[test]
+    fn test_captured() {
+        check_not_inlined("local x$0 = a\nlocal f = function() return x end");
+        check_not_inlined("local x$0 = a\nfunction g() print(x) end");
+    }
+}
@@ -0,0 +1,116 @@
+use super::{Assist, AssistContext};
+use crate::{
+    ast::{Expr, IfStmt, UnOp},
+    AstNode,
+    SyntaxKind::*,
+    TextEditBuilder,
+};
+use std::ops::Range;
+
+/// Negates the condition of an `if` statement with an `else` branch and swaps the branches:
+///
+/// ```lua
+/// if a == b then f() else g() end  -->  if a ~= b then g() else f() end
+/// ```
+///
+/// The assist applies on the `if`, the condition and the `else` keyword, but not inside the
+/// branches. Statements with `elseif` branches are left alone.
+pub(super) fn swap_if_else(ctx: &AssistContext) -> Option<Assist> {
+    let if_stmt: IfStmt = ctx.find_node()?;
+    if if_stmt.else_if_clauses().next().is_some() {
+        return None;
+    }
+    let then_block = if_stmt.block()?;
+    let else_block = if_stmt.else_clause()?.block()?;
+    let in_branch = [&then_block, &else_block]
+        .iter()
+        .any(|block| block.syntax().text_range().contains_range(ctx.range()));
+    if in_branch {
+        return None;
+    }
+    let condition = if_stmt.condition()?;
+
+    let mut edit = TextEditBuilder::default();
+    edit.replace(condition.syntax().text_range(), negate(&condition));
+    edit.replace(
+        then_block.syntax().text_range(),
+        else_block.syntax().to_string(),
+    );
+    edit.replace(
+        else_block.syntax().text_range(),
+        then_block.syntax().to_string(),
+    );
+    Some(Assist {
+        id: "swap_if_else",
+        label: "Swap if and else branches".to_string(),
+        target: if_stmt.syntax().text_range(),
+        edit: edit.finish(),
+    })
+}
+
+/// Returns the text of the negation of `condition`.
+fn negate(condition: &Expr) -> String {
+    match condition {
+        Expr::BinExpr(bin) => {
+            let op = bin.op_token();
+            let negated_op = op.as_ref().and_then(|op| match op.kind() {
+                EQEQ => Some("~="),
+                NEQ => Some("=="),
+                _ => None,
+            });
+            match (op, negated_op) {
+                (Some(op), Some(negated_op)) => {
+                    let op_range = op.text_range() - bin.syntax().text_range().start();
+                    let mut text = bin.syntax().to_string();
+                    text.replace_range(Range::<usize>::from(op_range), negated_op);
+                    text
+                }
+                _ => format!("not ({})", bin.syntax()),
+            }
+        }
+        Expr::UnaryExpr(unary) if unary.op_kind() == Some(UnOp::Not) => match unary.operand() {
+            Some(Expr::ParenExpr(paren)) => paren.expr().map_or_else(
+                || paren.syntax().to_string(),
+                |expr| expr.syntax().to_string(),
+            ),
+            Some(operand) => operand.syntax().to_string(),
+            None => format!("not ({})", unary.syntax()),
+        },
+        _ => format!("not {}", condition.syntax()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assists::tests::{check_assist, check_assist_not_applicable};
+
+    #[test]
+    fn test_swap_if_else() {
+        check_assist(
+            "swap_if_else",
+            "$0if a == b then f() else g() end",
+            "if a ~= b then g() else f() end",
+        );
+        check_assist(
+            "swap_if_else",
+            "if not (a and b) then\n    f()\nel$0se\n    g()\n    h()\nend",
+            "if a and b then\n    g()\n    h()\nelse\n    f()\nend",
+        );
+        check_assist(
+            "swap_if_else",
+            "if a <$0 b then f() else g() end",
+            "if not (a < b) then g() else f() end",
+        );
+        check_assist(
+            "swap_if_else",
+            "if $0ok then f() else g() end",
+            "if not ok then g() else f() end",
+        );
+        check_assist_not_applicable("swap_if_else", "if a then f() end$0");
+        check_assist_not_applicable(
+            "swap_if_else",
+            "if a then f() elseif b then g() else$0 h() end",
+        );
+        check_assist_not_applicable("swap_if_else", "if a then f$0() else g() end");
+    }
+}
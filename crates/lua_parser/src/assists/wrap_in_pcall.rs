@@ -0,0 +1,73 @@
+use super::{Assist, AssistContext};
+use crate::{
+    ast::{Expr, ExprStmt},
+    AstNode,
+    SyntaxKind::*,
+    TextEdit,
+};
+
+/// Wraps a call statement in `pcall`, so errors raised by the call are caught:
+///
+/// ```lua
+/// f(a, b)         -->  pcall(f, a, b)
+/// obj:m(a)        -->  pcall(obj.m, obj, a)
+/// get():m(a)      -->  pcall(function() return get():m(a) end)
+/// ```
+///
+/// The receiver of a method call is only passed as an argument if it is a name, otherwise it
+/// could be evaluated twice.
+pub(super) fn wrap_in_pcall(ctx: &AssistContext) -> Option<Assist> {
+    let stmt: ExprStmt = ctx.find_node()?;
+    let (mut args, arg_list) = match stmt.expr()? {
+        Expr::CallExpr(call) => (vec![call.callee()?.syntax().to_string()], call.arg_list()?),
+        Expr::MethodCallExpr(call) => match call.receiver()? {
+            Expr::NameRef(receiver) => {
+                let method = call
+                    .syntax()
+                    .children_with_tokens()
+                    .filter_map(|element| element.into_token())
+                    .find(|token| token.kind() == IDENT)?;
+                let args = vec![
+                    format!("{}.{}", receiver.syntax(), method.text()),
+                    receiver.syntax().to_string(),
+                ];
+                (args, call.arg_list()?)
+            }
+            _ => {
+                let wrapped = format!("pcall(function() return {} end)", call.syntax());
+                return Some(assist(&stmt, wrapped));
+            }
+        },
+        _ => return None,
+    };
+    args.extend(arg_list.args().map(|arg| arg.syntax().to_string()));
+    Some(assist(&stmt, format!("pcall({})", args.join(", "))))
+}
+
+fn assist(stmt: &ExprStmt, wrapped: String) -> Assist {
+    Assist {
+        id: "wrap_in_pcall",
+        label: "Wrap in pcall".to_string(),
+        target: stmt.syntax().text_range(),
+        edit: TextEdit::replace(stmt.syntax().text_range(), wrapped),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assists::tests::{check_assist, check_assist_not_applicable};
+
+    #[test]
+    fn test_wrap_in_pcall() {
+        check_assist("wrap_in_pcall", "f$0(a, b)", "pcall(f, a, b)");
+        check_assist("wrap_in_pcall", "t.f{ 1 }$0", "pcall(t.f, { 1 })");
+        check_assist("wrap_in_pcall", "f(a)(b$0)", "pcall(f(a), b)");
+        check_assist("wrap_in_pcall", "obj:m$0(a)", "pcall(obj.m, obj, a)");
+        check_assist(
+            "wrap_in_pcall",
+            "get():m$0()",
+            "pcall(function() return get():m() end)",
+        );
+        check_assist_not_applicable("wrap_in_pcall", "local x = f$0()");
+    }
+}
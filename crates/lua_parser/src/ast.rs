@@ -1,43 +1,67 @@
+mod chunk_ext;
+mod expr_ext;
 mod generated;
+mod literal_ext;
+mod stmt_ext;
+mod tokens;
 
-use crate::{syntax_node::SyntaxNodeChildren, SmolStr, SyntaxNode, SyntaxToken, TreeArc};
+use crate::{syntax_node::SyntaxNodeChildren, SyntaxKind, SyntaxNode, SyntaxToken};
 
-pub use self::{generated::*};
+pub use self::{
+    chunk_ext::TopLevelFunction,
+    generated::*,
+    literal_ext::{LiteralKind, LuaValue},
+    tokens::*,
+};
+pub use crate::precedence::{BinOp, UnOp};
 
 use std::marker::PhantomData;
 
 /// The main trait to go from untyped `SyntaxNode` to a typed ast. The conversion itself has zero
 /// runtime cost; ast and syntax nodes have exactly the same representation; a pointer to the tree
 /// root and a pointer to the node itself.
-pub trait AstNode:
-rowan::TransparentNewType<Repr = rowan::SyntaxNode> + ToOwned<Owned = TreeArc<Self>>
-{
-    fn cast(syntax: &SyntaxNode) -> Option<&Self>
-        where
-            Self: Sized;
+pub trait AstNode {
+    fn can_cast(kind: SyntaxKind) -> bool
+    where
+        Self: Sized;
+
+    fn cast(syntax: SyntaxNode) -> Option<Self>
+    where
+        Self: Sized;
+
     fn syntax(&self) -> &SyntaxNode;
+
+    /// Writes the node as canonical Lua source, see `format` to format parsed code instead. This
+    /// is meant for trees that are built from scratch, which have no whitespace: every statement
+    /// is put on its own line and missing parentheses and semicolons are added. Comments are left
+    /// out.
+    fn to_source(&self) -> String {
+        crate::fmt::to_source(self.syntax())
+    }
 }
 
 /// Like an `AstNode`, but wraps tokens rather than interior nodes.
-pub trait AstToken<'a> {
-    fn cast(token: SyntaxToken<'a>) -> Option<Self>
-        where
-            Self: Sized;
-    fn syntax(&self) -> SyntaxToken<'a>;
-    fn text(&self) -> &'a SmolStr {
+pub trait AstToken {
+    fn cast(token: SyntaxToken) -> Option<Self>
+    where
+        Self: Sized;
+
+    fn syntax(&self) -> &SyntaxToken;
+
+    fn text(&self) -> &str {
         self.syntax().text()
     }
 }
 
 /// An iterator over `SyntaxNode` children of a particular AST type.
-#[derive(Debug)]
-pub struct AstChildren<'a, N> {
-    inner: SyntaxNodeChildren<'a>,
+#[derive(Debug, Clone)]
+pub struct AstChildren<N> {
+    inner: SyntaxNodeChildren,
     ph: PhantomData<N>,
 }
 
-impl<'a, N> AstChildren<'a, N> {
-    fn new(parent: &'a SyntaxNode) -> Self {
+impl<N> AstChildren<N> {
+    fn new(parent: &SyntaxNode) -> Self {
         AstChildren {
             inner: parent.children(),
             ph: PhantomData,
@@ -45,17 +69,95 @@ impl<'a, N> AstChildren<'a, N> {
     }
 }
 
-impl<'a, N: AstNode + 'a> Iterator for AstChildren<'a, N> {
-    type Item = &'a N;
-    fn next(&mut self) -> Option<&'a N> {
+impl<N: AstNode> Iterator for AstChildren<N> {
+    type Item = N;
+    fn next(&mut self) -> Option<N> {
         self.inner.by_ref().find_map(N::cast)
     }
 }
 
-fn child_opt<P: AstNode, C: AstNode>(parent: &P) -> Option<&C> {
+fn child_opt<P: AstNode, C: AstNode>(parent: &P) -> Option<C> {
     children(parent).next()
 }
 
 fn children<P: AstNode, C: AstNode>(parent: &P) -> AstChildren<C> {
     AstChildren::new(parent.syntax())
 }
+
+fn nth_child<P: AstNode, C: AstNode>(parent: &P, n: usize) -> Option<C> {
+    children(parent).nth(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enum_nodes() {
+        let chunk = Chunk::parse("local x = 1 + f() return { a = x, [1] = 2, 3 }").tree();
+        let statements: Vec<Stmt> = chunk.block().unwrap().statements().collect();
+        assert_eq!(statements.len(), 2);
+
+        let exprs: Vec<_> = match &statements[0] {
+            Stmt::LocalAssignStmt(stmt) => {
+                stmt.syntax().descendants().filter_map(Expr::cast).collect()
+            }
+            _ => panic!("expected a local assignment"),
+        };
+        let kinds: Vec<_> = exprs
+            .iter()
+            .map(|expr| match expr {
+                Expr::BinExpr(_) => "bin",
+                Expr::Literal(_) => "literal",
+                Expr::CallExpr(_) => "call",
+                Expr::NameRef(_) => "name",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(kinds, vec!["bin", "literal", "call", "name"]);
+
+        let table = statements[1]
+            .syntax()
+            .descendants()
+            .find_map(TableExpr::cast)
+            .unwrap();
+        let fields: Vec<_> = table
+            .fields()
+            .map(|field| match field {
+                TableField::NamedField(_) => "named",
+                TableField::IndexedField(_) => "indexed",
+                TableField::PositionalField(_) => "positional",
+            })
+            .collect();
+        assert_eq!(fields, vec!["named", "indexed", "positional"]);
+        assert_eq!(Expr::from(table.clone()).syntax(), table.syntax());
+    }
+
+    #[test]
+    fn test_fields() {
+        let chunk = Chunk::parse("a, b = c - d, t[k]").tree();
+        let assign = chunk
+            .syntax()
+            .descendants()
+            .find_map(AssignStmt::cast)
+            .unwrap();
+        assert_eq!(assign.targets().unwrap().syntax().text(), "a, b");
+        assert_eq!(assign.values().unwrap().syntax().text(), "c - d, t[k]");
+
+        let bin = chunk
+            .syntax()
+            .descendants()
+            .find_map(BinExpr::cast)
+            .unwrap();
+        assert_eq!(bin.lhs().unwrap().syntax().text(), "c");
+        assert_eq!(bin.rhs().unwrap().syntax().text(), "d");
+
+        let index = chunk
+            .syntax()
+            .descendants()
+            .find_map(IndexExpr::cast)
+            .unwrap();
+        assert_eq!(index.base().unwrap().syntax().text(), "t");
+        assert_eq!(index.index().unwrap().syntax().text(), "k");
+    }
+}
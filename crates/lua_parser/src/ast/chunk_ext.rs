@@ -0,0 +1,238 @@
+//! Queries of the top-level block of a chunk, for tools that index Lua libraries.
+
+use super::{AstNode, Chunk, Expr, ExprList, Name, Stmt};
+use crate::{SyntaxNode, TextRange};
+
+/// A function that a statement of the top-level block of a chunk defines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopLevelFunction {
+    /// The name the function is assigned to as written, e.g. `foo`, `M.foo` or `M:foo`
+    pub name: String,
+    /// The range of the name, e.g. of `M.foo` in `M.foo = function() end`
+    pub name_range: TextRange,
+    /// Whether the function is assigned to a local, e.g. `local function foo() end`
+    pub is_local: bool,
+    /// The `FUNCTION_STMT` or `LOCAL_FUNCTION_STMT`, or the `FUNCTION_EXPR` that is assigned
+    pub function: SyntaxNode,
+}
+
+impl Chunk {
+    /// Returns the names of the locals that the top-level block declares, in order.
+    pub fn top_level_locals(&self) -> Vec<Name> {
+        let mut locals = Vec::new();
+        for stmt in self.top_level_statements() {
+            match stmt {
+                Stmt::LocalAssignStmt(stmt) => {
+                    locals.extend(stmt.name_list().into_iter().flat_map(|list| list.names()))
+                }
+                Stmt::LocalFunctionStmt(stmt) => locals.extend(stmt.name()),
+                _ => {}
+            }
+        }
+        locals
+    }
+
+    /// Returns the functions that the top-level block defines with a function statement or by
+    /// assigning a function expression to a name or field, e.g. `M.foo = function() end`.
+    pub fn top_level_functions(&self) -> Vec<TopLevelFunction> {
+        let mut functions = Vec::new();
+        for stmt in self.top_level_statements() {
+            match stmt {
+                Stmt::FunctionStmt(stmt) => {
+                    if let Some(name) = stmt.function_name() {
+                        functions.push(TopLevelFunction {
+                            name: non_trivia_text(name.syntax()),
+                            name_range: name.syntax().text_range(),
+                            is_local: false,
+                            function: stmt.syntax().clone(),
+                        });
+                    }
+                }
+                Stmt::LocalFunctionStmt(stmt) => {
+                    if let Some(name) = stmt.name() {
+                        functions.push(TopLevelFunction {
+                            name: name.syntax().text().to_string(),
+                            name_range: name.syntax().text_range(),
+                            is_local: true,
+                            function: stmt.syntax().clone(),
+                        });
+                    }
+                }
+                Stmt::LocalAssignStmt(stmt) => {
+                    let names = stmt.name_list().into_iter().flat_map(|list| list.names());
+                    let values = stmt.expr_list().into_iter().flat_map(|list| list.exprs());
+                    for (name, value) in names.zip(values) {
+                        if let Expr::FunctionExpr(function) = value {
+                            functions.push(TopLevelFunction {
+                                name: name.syntax().text().to_string(),
+                                name_range: name.syntax().text_range(),
+                                is_local: true,
+                                function: function.syntax().clone(),
+                            });
+                        }
+                    }
+                }
+                Stmt::AssignStmt(stmt) => {
+                    let targets = stmt.targets().into_iter().flat_map(|list| list.exprs());
+                    let values = stmt.values().into_iter().flat_map(|list| list.exprs());
+                    for (target, value) in targets.zip(values) {
+                        let path = match (target.full_index_path(), &value) {
+                            (Some(path), Expr::FunctionExpr(_)) => path,
+                            _ => continue,
+                        };
+                        functions.push(TopLevelFunction {
+                            name: path.join("."),
+                            name_range: target.syntax().text_range(),
+                            is_local: false,
+                            function: value.syntax().clone(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        functions
+    }
+
+    /// Returns the expressions of the `return` statement that ends the top-level block, e.g. `M`
+    /// in `return M`.
+    pub fn module_returns(&self) -> Option<ExprList> {
+        self.block()?.return_stmt()?.expr_list()
+    }
+
+    /// Returns true if the chunk looks like a module: it calls `module(...)` like in Lua 5.1, or
+    /// it returns a single table constructor or call, or a top-level local that is initialized
+    /// with one, e.g. `local M = {} ... return M` or `local M = setmetatable({}, mt) ... return M`.
+    pub fn is_module_style(&self) -> bool {
+        let calls_module = self.top_level_statements().any(|stmt| match stmt {
+            Stmt::ExprStmt(stmt) => match stmt.expr() {
+                Some(Expr::CallExpr(call)) => match call.callee() {
+                    Some(Expr::NameRef(name)) => name.syntax().text() == "module",
+                    _ => false,
+                },
+                _ => false,
+            },
+            _ => false,
+        });
+        if calls_module {
+            return true;
+        }
+        let mut returns = match self.module_returns() {
+            Some(returns) => returns.exprs(),
+            None => return false,
+        };
+        let value = match (returns.next(), returns.next()) {
+            (Some(Expr::NameRef(name)), None) => {
+                self.local_value(&name.syntax().text().to_string())
+            }
+            (value, None) => value,
+            _ => None,
+        };
+        matches!(value, Some(Expr::TableExpr(_)) | Some(Expr::CallExpr(_)))
+    }
+
+    fn top_level_statements(&self) -> impl Iterator<Item = Stmt> {
+        self.block()
+            .into_iter()
+            .flat_map(|block| block.statements())
+    }
+
+    /// Returns the value that the last top-level declaration of the local `name` initializes it
+    /// with.
+    fn local_value(&self, name: &str) -> Option<Expr> {
+        let mut value = None;
+        for stmt in self.top_level_statements() {
+            match stmt {
+                Stmt::LocalAssignStmt(stmt) => {
+                    let names = stmt.name_list().into_iter().flat_map(|list| list.names());
+                    let mut values = stmt.expr_list().into_iter().flat_map(|list| list.exprs());
+                    for declared in names {
+                        let declared_value = values.next();
+                        if declared.syntax().text() == name {
+                            value = declared_value;
+                        }
+                    }
+                }
+                Stmt::LocalFunctionStmt(stmt)
+                    if stmt
+                        .name()
+                        .is_some_and(|declared| declared.syntax().text() == name) =>
+                {
+                    value = None
+                }
+                _ => {}
+            }
+        }
+        value
+    }
+}
+
+/// Returns the text of `node` without whitespace and comments, e.g. `M.foo` for `M . foo`.
+fn non_trivia_text(node: &SyntaxNode) -> String {
+    node.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+        .map(|token| token.text().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_module_style(text: &str) -> bool {
+        Chunk::parse(text).tree().is_module_style()
+    }
+
+    #[test]
+    fn test_top_level_definitions() {
+        let text = "local M, n = {}, 1\n\
+                    local function helper() end\n\
+                    function M.foo() local x = function() end end\n\
+                    function M : bar() end\n\
+                    M.baz, M.qux = function() end, 2\n\
+                    local f = function() end\n\
+                    do local hidden = 1 end\n\
+                    return M";
+        let chunk = Chunk::parse(text).tree();
+        let locals: Vec<_> = chunk
+            .top_level_locals()
+            .iter()
+            .map(|name| name.syntax().text().to_string())
+            .collect();
+        assert_eq!(locals, vec!["M", "n", "helper", "f"]);
+
+        let functions: Vec<_> = chunk
+            .top_level_functions()
+            .into_iter()
+            .map(|function| (function.name, &text[function.name_range], function.is_local))
+            .collect();
+        assert_eq!(
+            functions,
+            vec![
+                ("helper".to_string(), "helper", true),
+                ("M.foo".to_string(), "M.foo", false),
+                ("M:bar".to_string(), "M : bar", false),
+                ("M.baz".to_string(), "M.baz", false),
+                ("f".to_string(), "f", true),
+            ]
+        );
+        let returns = chunk.module_returns().unwrap();
+        assert_eq!(returns.syntax().text(), "M");
+    }
+
+    #[test]
+    fn test_is_module_style() {
+        assert!(is_module_style("local M = {} function M.f() end return M"));
+        assert!(is_module_style("local M = setmetatable({}, mt) return M"));
+        assert!(is_module_style("return { f = f }"));
+        assert!(is_module_style(
+            "module(..., package.seeall)\nfunction f() end"
+        ));
+        assert!(!is_module_style("local x = 1 return x"));
+        assert!(!is_module_style("local M = {} local M = 1 return M"));
+        assert!(!is_module_style("return M"));
+        assert!(!is_module_style("return a, b"));
+        assert!(!is_module_style("print(1)"));
+    }
+}
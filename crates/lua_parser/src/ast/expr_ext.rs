@@ -0,0 +1,200 @@
+//! Accessors of expressions that can't be generated from the grammar.
+
+use super::{
+    AstNode, BinExpr, BinOp, Expr, FieldExpr, MethodCallExpr, NamedField, Placeholder, UnOp,
+    UnaryExpr,
+};
+use crate::{
+    requires::string_value,
+    SyntaxKind::{IDENT, STRING},
+    SyntaxNode, SyntaxToken,
+};
+
+impl Expr {
+    /// Returns the expression that a call, method call, index or field access is applied to, e.g.
+    /// `a.b` in `a.b(x)`, `a.b:c(x)`, `a.b[x]` and `a.b.c`, or `None` for other expressions.
+    pub fn receiver(&self) -> Option<Expr> {
+        match self {
+            Expr::CallExpr(call) => call.callee(),
+            Expr::MethodCallExpr(call) => call.receiver(),
+            Expr::IndexExpr(index) => index.base(),
+            Expr::FieldExpr(field) => field.base(),
+            _ => None,
+        }
+    }
+
+    /// Returns the expressions of the chain of calls and accesses that ends with this expression,
+    /// starting with the expression the chain is applied to, e.g. `a`, `a.b`, `a.b:c(x)` and
+    /// `a.b:c(x)[y]` for `a.b:c(x)[y]`.
+    pub fn chain(&self) -> Vec<Expr> {
+        let mut chain: Vec<_> = std::iter::successors(Some(self.clone()), Expr::receiver).collect();
+        chain.reverse();
+        chain
+    }
+
+    /// Returns the expression the chain of calls and accesses that ends with this expression is
+    /// applied to, e.g. `a` in `a.b:c(x)[y]`, or the expression itself if it isn't a chain.
+    pub fn chain_root(&self) -> Expr {
+        std::iter::successors(Some(self.clone()), Expr::receiver)
+            .last()
+            .unwrap_or_else(|| self.clone())
+    }
+
+    /// Returns the names of a path of field accesses that starts with a name, e.g. `a`, `b` and
+    /// `c` for `a.b["c"]`, for lints and completions that look for uses of an API. Returns `None`
+    /// if the path contains anything else, e.g. a call or an index that isn't a string literal.
+    pub fn full_index_path(&self) -> Option<Vec<String>> {
+        let mut path = Vec::new();
+        let mut expr = self.clone();
+        loop {
+            match expr {
+                Expr::NameRef(name_ref) => {
+                    path.push(name_ref.syntax().text().to_string());
+                    break;
+                }
+                Expr::FieldExpr(field) => {
+                    path.push(field.field_name()?.text().to_string());
+                    expr = field.base()?;
+                }
+                Expr::IndexExpr(index) => {
+                    let key = match index.index()? {
+                        Expr::Literal(literal) => literal.syntax().first_token()?,
+                        _ => return None,
+                    };
+                    if key.kind() != STRING {
+                        return None;
+                    }
+                    path.push(string_value(key.text())?);
+                    expr = index.base()?;
+                }
+                _ => return None,
+            }
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+impl BinExpr {
+    /// Returns the operator, e.g. `BinOp::Add` for `a + b`.
+    pub fn op_kind(&self) -> Option<BinOp> {
+        BinOp::from_kind(self.op_token()?.kind())
+    }
+
+    /// Returns the token of the operator, e.g. `+` in `a + b`.
+    pub fn op_token(&self) -> Option<SyntaxToken> {
+        first_token(self.syntax())
+    }
+}
+
+impl UnaryExpr {
+    /// Returns the operator, e.g. `UnOp::Not` for `not a`.
+    pub fn op_kind(&self) -> Option<UnOp> {
+        UnOp::from_kind(self.op_token()?.kind())
+    }
+
+    /// Returns the token of the operator, e.g. `not` in `not a`.
+    pub fn op_token(&self) -> Option<SyntaxToken> {
+        first_token(self.syntax())
+    }
+}
+
+impl FieldExpr {
+    /// Returns the name of the field, e.g. `b` in `a.b`.
+    pub fn field_name(&self) -> Option<SyntaxToken> {
+        ident(self.syntax())
+    }
+}
+
+impl MethodCallExpr {
+    /// Returns the name of the method, e.g. `b` in `a:b()`.
+    pub fn method_name(&self) -> Option<SyntaxToken> {
+        ident(self.syntax())
+    }
+}
+
+impl NamedField {
+    /// Returns the name of the field, e.g. `a` in `{ a = 1 }`.
+    pub fn name(&self) -> Option<SyntaxToken> {
+        ident(self.syntax())
+    }
+}
+
+impl Placeholder {
+    /// Returns the name of the placeholder, e.g. `x` in `$x`.
+    pub fn name(&self) -> Option<SyntaxToken> {
+        ident(self.syntax())
+    }
+}
+
+/// Returns the first `IDENT` token that is a direct child of `node`.
+fn ident(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| token.kind() == IDENT)
+}
+
+/// Returns the first token that is a direct child of `node` and not trivia.
+fn first_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| !token.kind().is_trivia())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast::Expr, AstNode, Chunk};
+
+    fn expr(text: &str) -> Expr {
+        let chunk = Chunk::parse(&format!("return {}", text)).tree();
+        chunk.syntax().descendants().find_map(Expr::cast).unwrap()
+    }
+
+    #[test]
+    fn test_chains() {
+        let call = expr("a.b.c:d(x)(y)[z]");
+        let chain: Vec<_> = call
+            .chain()
+            .iter()
+            .map(|expr| expr.syntax().text().to_string())
+            .collect();
+        assert_eq!(
+            chain,
+            vec![
+                "a",
+                "a.b",
+                "a.b.c",
+                "a.b.c:d(x)",
+                "a.b.c:d(x)(y)",
+                "a.b.c:d(x)(y)[z]"
+            ]
+        );
+        assert_eq!(call.chain_root().syntax().text(), "a");
+        assert_eq!(call.receiver().unwrap().syntax().text(), "a.b.c:d(x)(y)");
+
+        let method = match &call.chain()[3] {
+            Expr::MethodCallExpr(method) => method.clone(),
+            _ => unreachable!(),
+        };
+        assert_eq!(method.method_name().unwrap().text(), "d");
+        let receiver = method.receiver().unwrap();
+        assert_eq!(
+            receiver.full_index_path(),
+            Some(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+        );
+        assert_eq!(call.full_index_path(), None);
+    }
+
+    #[test]
+    fn test_full_index_path() {
+        let path = |text| expr(text).full_index_path();
+        assert_eq!(
+            path("os['execute']"),
+            Some(vec!["os".to_string(), "execute".to_string()])
+        );
+        assert_eq!(path("x"), Some(vec!["x".to_string()]));
+        assert_eq!(path("a[1].b"), None);
+        assert_eq!(path("(a).b"), None);
+        assert_eq!(path("f().b"), None);
+    }
+}
@@ -1,47 +1,1634 @@
 // This file is automatically generated based on the file `./generated.rs.tera` when `cargo gen-syntax` is run
 // Do not edit manually
 
-//! This module contains auto-generated Rust AST. Like `SyntaxNode`s, AST nodes
-//! are generic over ownership: `X<'a>` things are `Copy` references, `XNode`
-//! are Arc-based. You can switch between the two variants using `.owned` and
-//! `.borrowed` functions. Most of the code works with borrowed mode, and only
-//! this mode has all AST accessors.
-
-use rowan::TransparentNewType;
+//! This module contains auto-generated Rust AST. AST nodes are thin wrappers around a
+//! `SyntaxNode` of a specific kind, so they are cheap to clone and convert.
 
 use crate::{
-    SyntaxNode, SyntaxKind::*,
-    syntax_node::{TreeArc},
-    ast::{self, AstNode},
+    ast::{AstChildren, AstNode},
+    SyntaxKind::{self, *},
+    SyntaxNode,
 };
 
+// ArgList
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArgList {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for ArgList {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == ARG_LIST
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(ArgList { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl ArgList {
+    pub fn args(&self) -> AstChildren<Expr> {
+        super::children(self)
+    }
+}
+
+// AssignStmt
+/// Fields:
+/// * `targets: ExprList`
+/// * `values: ExprList`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssignStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for AssignStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == ASSIGN_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(AssignStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl AssignStmt {
+    /// The `targets` of this `AssignStmt`, its `ExprList` child at index 0.
+    pub fn targets(&self) -> Option<ExprList> {
+        super::nth_child(self, 0)
+    }
+
+    /// The `values` of this `AssignStmt`, its `ExprList` child at index 1.
+    pub fn values(&self) -> Option<ExprList> {
+        super::nth_child(self, 1)
+    }
+}
+
+// Attribute
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Attribute {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for Attribute {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == ATTRIBUTE
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(Attribute { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl Attribute {}
+
+// BinExpr
+/// Fields:
+/// * `lhs: Expr`
+/// * `rhs: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BinExpr {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for BinExpr {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == BIN_EXPR
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(BinExpr { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl BinExpr {
+    /// The `lhs` of this `BinExpr`, its `Expr` child at index 0.
+    pub fn lhs(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+
+    /// The `rhs` of this `BinExpr`, its `Expr` child at index 1.
+    pub fn rhs(&self) -> Option<Expr> {
+        super::nth_child(self, 1)
+    }
+}
+
+// Block
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Block {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for Block {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == BLOCK
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(Block { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl Block {
+    pub fn statements(&self) -> AstChildren<Stmt> {
+        super::children(self)
+    }
+
+    pub fn return_stmt(&self) -> Option<ReturnStmt> {
+        super::child_opt(self)
+    }
+}
+
+// BreakStmt
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BreakStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for BreakStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == BREAK_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(BreakStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl BreakStmt {}
+
+// CallExpr
+/// Fields:
+/// * `callee: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CallExpr {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for CallExpr {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == CALL_EXPR
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(CallExpr { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl CallExpr {
+    pub fn arg_list(&self) -> Option<ArgList> {
+        super::child_opt(self)
+    }
+
+    /// The `callee` of this `CallExpr`, its `Expr` child at index 0.
+    pub fn callee(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+}
 
 // Chunk
-#[derive(Debug, PartialEq, Eq, Hash)]
-#[repr(transparent)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Chunk {
     pub(crate) syntax: SyntaxNode,
 }
 
-unsafe impl TransparentNewType for Chunk {
-    type Repr = rowan::SyntaxNode;
+impl AstNode for Chunk {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == CHUNK
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(Chunk { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
 }
 
-impl AstNode for Chunk {
-    fn cast(syntax: &SyntaxNode) -> Option<&Self> {
-        match syntax.kind() {
-            CHUNK => Some(Chunk::from_repr(syntax.into_repr())),
-            _ => None,
+impl Chunk {
+    pub fn block(&self) -> Option<Block> {
+        super::child_opt(self)
+    }
+}
+
+// DoStmt
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DoStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for DoStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == DO_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(DoStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl DoStmt {
+    pub fn block(&self) -> Option<Block> {
+        super::child_opt(self)
+    }
+}
+
+// ElseClause
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ElseClause {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for ElseClause {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == ELSE_CLAUSE
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(ElseClause { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl ElseClause {
+    pub fn block(&self) -> Option<Block> {
+        super::child_opt(self)
+    }
+}
+
+// ElseIfClause
+/// Fields:
+/// * `condition: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ElseIfClause {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for ElseIfClause {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == ELSE_IF_CLAUSE
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(ElseIfClause { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl ElseIfClause {
+    pub fn block(&self) -> Option<Block> {
+        super::child_opt(self)
+    }
+
+    /// The `condition` of this `ElseIfClause`, its `Expr` child at index 0.
+    pub fn condition(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+}
+
+// Expr
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr {
+    Literal(Literal),
+    NameRef(NameRef),
+    ParenExpr(ParenExpr),
+    BinExpr(BinExpr),
+    UnaryExpr(UnaryExpr),
+    CallExpr(CallExpr),
+    MethodCallExpr(MethodCallExpr),
+    IndexExpr(IndexExpr),
+    FieldExpr(FieldExpr),
+    FunctionExpr(FunctionExpr),
+    TableExpr(TableExpr),
+    Placeholder(Placeholder),
+}
+impl From<Literal> for Expr {
+    fn from(n: Literal) -> Expr {
+        Expr::Literal(n)
+    }
+}
+impl From<NameRef> for Expr {
+    fn from(n: NameRef) -> Expr {
+        Expr::NameRef(n)
+    }
+}
+impl From<ParenExpr> for Expr {
+    fn from(n: ParenExpr) -> Expr {
+        Expr::ParenExpr(n)
+    }
+}
+impl From<BinExpr> for Expr {
+    fn from(n: BinExpr) -> Expr {
+        Expr::BinExpr(n)
+    }
+}
+impl From<UnaryExpr> for Expr {
+    fn from(n: UnaryExpr) -> Expr {
+        Expr::UnaryExpr(n)
+    }
+}
+impl From<CallExpr> for Expr {
+    fn from(n: CallExpr) -> Expr {
+        Expr::CallExpr(n)
+    }
+}
+impl From<MethodCallExpr> for Expr {
+    fn from(n: MethodCallExpr) -> Expr {
+        Expr::MethodCallExpr(n)
+    }
+}
+impl From<IndexExpr> for Expr {
+    fn from(n: IndexExpr) -> Expr {
+        Expr::IndexExpr(n)
+    }
+}
+impl From<FieldExpr> for Expr {
+    fn from(n: FieldExpr) -> Expr {
+        Expr::FieldExpr(n)
+    }
+}
+impl From<FunctionExpr> for Expr {
+    fn from(n: FunctionExpr) -> Expr {
+        Expr::FunctionExpr(n)
+    }
+}
+impl From<TableExpr> for Expr {
+    fn from(n: TableExpr) -> Expr {
+        Expr::TableExpr(n)
+    }
+}
+impl From<Placeholder> for Expr {
+    fn from(n: Placeholder) -> Expr {
+        Expr::Placeholder(n)
+    }
+}
+
+impl AstNode for Expr {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        matches!(
+            kind,
+            LITERAL
+                | NAME_REF
+                | PAREN_EXPR
+                | BIN_EXPR
+                | UNARY_EXPR
+                | CALL_EXPR
+                | METHOD_CALL_EXPR
+                | INDEX_EXPR
+                | FIELD_EXPR
+                | FUNCTION_EXPR
+                | TABLE_EXPR
+                | PLACEHOLDER
+        )
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        let res = match syntax.kind() {
+            LITERAL => Expr::Literal(Literal { syntax }),
+            NAME_REF => Expr::NameRef(NameRef { syntax }),
+            PAREN_EXPR => Expr::ParenExpr(ParenExpr { syntax }),
+            BIN_EXPR => Expr::BinExpr(BinExpr { syntax }),
+            UNARY_EXPR => Expr::UnaryExpr(UnaryExpr { syntax }),
+            CALL_EXPR => Expr::CallExpr(CallExpr { syntax }),
+            METHOD_CALL_EXPR => Expr::MethodCallExpr(MethodCallExpr { syntax }),
+            INDEX_EXPR => Expr::IndexExpr(IndexExpr { syntax }),
+            FIELD_EXPR => Expr::FieldExpr(FieldExpr { syntax }),
+            FUNCTION_EXPR => Expr::FunctionExpr(FunctionExpr { syntax }),
+            TABLE_EXPR => Expr::TableExpr(TableExpr { syntax }),
+            PLACEHOLDER => Expr::Placeholder(Placeholder { syntax }),
+            _ => return None,
+        };
+        Some(res)
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Expr::Literal(it) => &it.syntax,
+            Expr::NameRef(it) => &it.syntax,
+            Expr::ParenExpr(it) => &it.syntax,
+            Expr::BinExpr(it) => &it.syntax,
+            Expr::UnaryExpr(it) => &it.syntax,
+            Expr::CallExpr(it) => &it.syntax,
+            Expr::MethodCallExpr(it) => &it.syntax,
+            Expr::IndexExpr(it) => &it.syntax,
+            Expr::FieldExpr(it) => &it.syntax,
+            Expr::FunctionExpr(it) => &it.syntax,
+            Expr::TableExpr(it) => &it.syntax,
+            Expr::Placeholder(it) => &it.syntax,
+        }
+    }
+}
+
+impl Expr {}
+
+// ExprList
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExprList {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for ExprList {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == EXPR_LIST
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(ExprList { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl ExprList {
+    pub fn exprs(&self) -> AstChildren<Expr> {
+        super::children(self)
+    }
+}
+
+// ExprStmt
+/// Fields:
+/// * `expr: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExprStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for ExprStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == EXPR_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(ExprStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl ExprStmt {
+    /// The `expr` of this `ExprStmt`, its `Expr` child at index 0.
+    pub fn expr(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+}
+
+// FieldExpr
+/// Fields:
+/// * `base: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FieldExpr {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for FieldExpr {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == FIELD_EXPR
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(FieldExpr { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl FieldExpr {
+    /// The `base` of this `FieldExpr`, its `Expr` child at index 0.
+    pub fn base(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+}
+
+// FunctionExpr
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FunctionExpr {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for FunctionExpr {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == FUNCTION_EXPR
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(FunctionExpr { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl FunctionExpr {
+    pub fn param_list(&self) -> Option<ParamList> {
+        super::child_opt(self)
+    }
+
+    pub fn block(&self) -> Option<Block> {
+        super::child_opt(self)
+    }
+}
+
+// FunctionName
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FunctionName {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for FunctionName {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == FUNCTION_NAME
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(FunctionName { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl FunctionName {
+    pub fn name_ref(&self) -> Option<NameRef> {
+        super::child_opt(self)
+    }
+}
+
+// FunctionStmt
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FunctionStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for FunctionStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == FUNCTION_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(FunctionStmt { syntax })
+        } else {
+            None
         }
     }
-    fn syntax(&self) -> &SyntaxNode { &self.syntax }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl FunctionStmt {
+    pub fn function_name(&self) -> Option<FunctionName> {
+        super::child_opt(self)
+    }
+
+    pub fn param_list(&self) -> Option<ParamList> {
+        super::child_opt(self)
+    }
+
+    pub fn block(&self) -> Option<Block> {
+        super::child_opt(self)
+    }
+}
+
+// GenericForStmt
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GenericForStmt {
+    pub(crate) syntax: SyntaxNode,
 }
 
-impl ToOwned for Chunk {
-    type Owned = TreeArc<Chunk>;
-    fn to_owned(&self) -> TreeArc<Chunk> { TreeArc::cast(self.syntax.to_owned()) }
+impl AstNode for GenericForStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == GENERIC_FOR_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(GenericForStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
 }
 
+impl GenericForStmt {
+    pub fn name_list(&self) -> Option<NameList> {
+        super::child_opt(self)
+    }
+
+    pub fn expr_list(&self) -> Option<ExprList> {
+        super::child_opt(self)
+    }
 
-impl Chunk {}
+    pub fn block(&self) -> Option<Block> {
+        super::child_opt(self)
+    }
+}
 
+// GotoStmt
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GotoStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for GotoStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == GOTO_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(GotoStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl GotoStmt {}
+
+// IfStmt
+/// Fields:
+/// * `condition: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IfStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for IfStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == IF_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(IfStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl IfStmt {
+    pub fn else_if_clauses(&self) -> AstChildren<ElseIfClause> {
+        super::children(self)
+    }
+
+    pub fn block(&self) -> Option<Block> {
+        super::child_opt(self)
+    }
+
+    pub fn else_clause(&self) -> Option<ElseClause> {
+        super::child_opt(self)
+    }
+
+    /// The `condition` of this `IfStmt`, its `Expr` child at index 0.
+    pub fn condition(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+}
+
+// IndexExpr
+/// Fields:
+/// * `base: Expr`
+/// * `index: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IndexExpr {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for IndexExpr {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == INDEX_EXPR
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(IndexExpr { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl IndexExpr {
+    /// The `base` of this `IndexExpr`, its `Expr` child at index 0.
+    pub fn base(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+
+    /// The `index` of this `IndexExpr`, its `Expr` child at index 1.
+    pub fn index(&self) -> Option<Expr> {
+        super::nth_child(self, 1)
+    }
+}
+
+// IndexedField
+/// Fields:
+/// * `key: Expr`
+/// * `value: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IndexedField {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for IndexedField {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == INDEXED_FIELD
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(IndexedField { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl IndexedField {
+    /// The `key` of this `IndexedField`, its `Expr` child at index 0.
+    pub fn key(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+
+    /// The `value` of this `IndexedField`, its `Expr` child at index 1.
+    pub fn value(&self) -> Option<Expr> {
+        super::nth_child(self, 1)
+    }
+}
+
+// LabelStmt
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LabelStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for LabelStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == LABEL_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(LabelStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl LabelStmt {
+    pub fn name(&self) -> Option<Name> {
+        super::child_opt(self)
+    }
+}
+
+// Literal
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Literal {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for Literal {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == LITERAL
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(Literal { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl Literal {}
+
+// LocalAssignStmt
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LocalAssignStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for LocalAssignStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == LOCAL_ASSIGN_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(LocalAssignStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl LocalAssignStmt {
+    pub fn name_list(&self) -> Option<NameList> {
+        super::child_opt(self)
+    }
+
+    pub fn expr_list(&self) -> Option<ExprList> {
+        super::child_opt(self)
+    }
+}
+
+// LocalFunctionStmt
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LocalFunctionStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for LocalFunctionStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == LOCAL_FUNCTION_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(LocalFunctionStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl LocalFunctionStmt {
+    pub fn name(&self) -> Option<Name> {
+        super::child_opt(self)
+    }
+
+    pub fn param_list(&self) -> Option<ParamList> {
+        super::child_opt(self)
+    }
+
+    pub fn block(&self) -> Option<Block> {
+        super::child_opt(self)
+    }
+}
+
+// MethodCallExpr
+/// Fields:
+/// * `receiver: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodCallExpr {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for MethodCallExpr {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == METHOD_CALL_EXPR
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(MethodCallExpr { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl MethodCallExpr {
+    pub fn arg_list(&self) -> Option<ArgList> {
+        super::child_opt(self)
+    }
+
+    /// The `receiver` of this `MethodCallExpr`, its `Expr` child at index 0.
+    pub fn receiver(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+}
+
+// Name
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Name {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for Name {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == NAME
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(Name { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl Name {}
+
+// NameList
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NameList {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for NameList {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == NAME_LIST
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(NameList { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl NameList {
+    pub fn names(&self) -> AstChildren<Name> {
+        super::children(self)
+    }
+}
+
+// NameRef
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NameRef {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for NameRef {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == NAME_REF
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(NameRef { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl NameRef {}
+
+// NamedField
+/// Fields:
+/// * `value: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NamedField {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for NamedField {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == NAMED_FIELD
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(NamedField { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl NamedField {
+    /// The `value` of this `NamedField`, its `Expr` child at index 0.
+    pub fn value(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+}
+
+// NumericForStmt
+/// Fields:
+/// * `start: Expr`
+/// * `end: Expr`
+/// * `step: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NumericForStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for NumericForStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == NUMERIC_FOR_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(NumericForStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl NumericForStmt {
+    pub fn name(&self) -> Option<Name> {
+        super::child_opt(self)
+    }
+
+    pub fn block(&self) -> Option<Block> {
+        super::child_opt(self)
+    }
+
+    /// The `start` of this `NumericForStmt`, its `Expr` child at index 0.
+    pub fn start(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+
+    /// The `end` of this `NumericForStmt`, its `Expr` child at index 1.
+    pub fn end(&self) -> Option<Expr> {
+        super::nth_child(self, 1)
+    }
+
+    /// The `step` of this `NumericForStmt`, its `Expr` child at index 2.
+    pub fn step(&self) -> Option<Expr> {
+        super::nth_child(self, 2)
+    }
+}
+
+// ParamList
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParamList {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for ParamList {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == PARAM_LIST
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(ParamList { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl ParamList {
+    pub fn params(&self) -> AstChildren<Name> {
+        super::children(self)
+    }
+}
+
+// ParenExpr
+/// Fields:
+/// * `expr: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParenExpr {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for ParenExpr {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == PAREN_EXPR
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(ParenExpr { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl ParenExpr {
+    /// The `expr` of this `ParenExpr`, its `Expr` child at index 0.
+    pub fn expr(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+}
+
+// Placeholder
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Placeholder {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for Placeholder {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == PLACEHOLDER
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(Placeholder { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl Placeholder {}
+
+// PositionalField
+/// Fields:
+/// * `value: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PositionalField {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for PositionalField {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == POSITIONAL_FIELD
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(PositionalField { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl PositionalField {
+    /// The `value` of this `PositionalField`, its `Expr` child at index 0.
+    pub fn value(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+}
+
+// RepeatStmt
+/// Fields:
+/// * `condition: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RepeatStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for RepeatStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == REPEAT_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(RepeatStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl RepeatStmt {
+    pub fn block(&self) -> Option<Block> {
+        super::child_opt(self)
+    }
+
+    /// The `condition` of this `RepeatStmt`, its `Expr` child at index 0.
+    pub fn condition(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+}
+
+// ReturnStmt
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReturnStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for ReturnStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == RETURN_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(ReturnStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl ReturnStmt {
+    pub fn expr_list(&self) -> Option<ExprList> {
+        super::child_opt(self)
+    }
+}
+
+// Stmt
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Stmt {
+    ExprStmt(ExprStmt),
+    AssignStmt(AssignStmt),
+    LocalAssignStmt(LocalAssignStmt),
+    LocalFunctionStmt(LocalFunctionStmt),
+    FunctionStmt(FunctionStmt),
+    DoStmt(DoStmt),
+    WhileStmt(WhileStmt),
+    RepeatStmt(RepeatStmt),
+    IfStmt(IfStmt),
+    NumericForStmt(NumericForStmt),
+    GenericForStmt(GenericForStmt),
+    ReturnStmt(ReturnStmt),
+    BreakStmt(BreakStmt),
+    GotoStmt(GotoStmt),
+    LabelStmt(LabelStmt),
+}
+impl From<ExprStmt> for Stmt {
+    fn from(n: ExprStmt) -> Stmt {
+        Stmt::ExprStmt(n)
+    }
+}
+impl From<AssignStmt> for Stmt {
+    fn from(n: AssignStmt) -> Stmt {
+        Stmt::AssignStmt(n)
+    }
+}
+impl From<LocalAssignStmt> for Stmt {
+    fn from(n: LocalAssignStmt) -> Stmt {
+        Stmt::LocalAssignStmt(n)
+    }
+}
+impl From<LocalFunctionStmt> for Stmt {
+    fn from(n: LocalFunctionStmt) -> Stmt {
+        Stmt::LocalFunctionStmt(n)
+    }
+}
+impl From<FunctionStmt> for Stmt {
+    fn from(n: FunctionStmt) -> Stmt {
+        Stmt::FunctionStmt(n)
+    }
+}
+impl From<DoStmt> for Stmt {
+    fn from(n: DoStmt) -> Stmt {
+        Stmt::DoStmt(n)
+    }
+}
+impl From<WhileStmt> for Stmt {
+    fn from(n: WhileStmt) -> Stmt {
+        Stmt::WhileStmt(n)
+    }
+}
+impl From<RepeatStmt> for Stmt {
+    fn from(n: RepeatStmt) -> Stmt {
+        Stmt::RepeatStmt(n)
+    }
+}
+impl From<IfStmt> for Stmt {
+    fn from(n: IfStmt) -> Stmt {
+        Stmt::IfStmt(n)
+    }
+}
+impl From<NumericForStmt> for Stmt {
+    fn from(n: NumericForStmt) -> Stmt {
+        Stmt::NumericForStmt(n)
+    }
+}
+impl From<GenericForStmt> for Stmt {
+    fn from(n: GenericForStmt) -> Stmt {
+        Stmt::GenericForStmt(n)
+    }
+}
+impl From<ReturnStmt> for Stmt {
+    fn from(n: ReturnStmt) -> Stmt {
+        Stmt::ReturnStmt(n)
+    }
+}
+impl From<BreakStmt> for Stmt {
+    fn from(n: BreakStmt) -> Stmt {
+        Stmt::BreakStmt(n)
+    }
+}
+impl From<GotoStmt> for Stmt {
+    fn from(n: GotoStmt) -> Stmt {
+        Stmt::GotoStmt(n)
+    }
+}
+impl From<LabelStmt> for Stmt {
+    fn from(n: LabelStmt) -> Stmt {
+        Stmt::LabelStmt(n)
+    }
+}
+
+impl AstNode for Stmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        matches!(
+            kind,
+            EXPR_STMT
+                | ASSIGN_STMT
+                | LOCAL_ASSIGN_STMT
+                | LOCAL_FUNCTION_STMT
+                | FUNCTION_STMT
+                | DO_STMT
+                | WHILE_STMT
+                | REPEAT_STMT
+                | IF_STMT
+                | NUMERIC_FOR_STMT
+                | GENERIC_FOR_STMT
+                | RETURN_STMT
+                | BREAK_STMT
+                | GOTO_STMT
+                | LABEL_STMT
+        )
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        let res = match syntax.kind() {
+            EXPR_STMT => Stmt::ExprStmt(ExprStmt { syntax }),
+            ASSIGN_STMT => Stmt::AssignStmt(AssignStmt { syntax }),
+            LOCAL_ASSIGN_STMT => Stmt::LocalAssignStmt(LocalAssignStmt { syntax }),
+            LOCAL_FUNCTION_STMT => Stmt::LocalFunctionStmt(LocalFunctionStmt { syntax }),
+            FUNCTION_STMT => Stmt::FunctionStmt(FunctionStmt { syntax }),
+            DO_STMT => Stmt::DoStmt(DoStmt { syntax }),
+            WHILE_STMT => Stmt::WhileStmt(WhileStmt { syntax }),
+            REPEAT_STMT => Stmt::RepeatStmt(RepeatStmt { syntax }),
+            IF_STMT => Stmt::IfStmt(IfStmt { syntax }),
+            NUMERIC_FOR_STMT => Stmt::NumericForStmt(NumericForStmt { syntax }),
+            GENERIC_FOR_STMT => Stmt::GenericForStmt(GenericForStmt { syntax }),
+            RETURN_STMT => Stmt::ReturnStmt(ReturnStmt { syntax }),
+            BREAK_STMT => Stmt::BreakStmt(BreakStmt { syntax }),
+            GOTO_STMT => Stmt::GotoStmt(GotoStmt { syntax }),
+            LABEL_STMT => Stmt::LabelStmt(LabelStmt { syntax }),
+            _ => return None,
+        };
+        Some(res)
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Stmt::ExprStmt(it) => &it.syntax,
+            Stmt::AssignStmt(it) => &it.syntax,
+            Stmt::LocalAssignStmt(it) => &it.syntax,
+            Stmt::LocalFunctionStmt(it) => &it.syntax,
+            Stmt::FunctionStmt(it) => &it.syntax,
+            Stmt::DoStmt(it) => &it.syntax,
+            Stmt::WhileStmt(it) => &it.syntax,
+            Stmt::RepeatStmt(it) => &it.syntax,
+            Stmt::IfStmt(it) => &it.syntax,
+            Stmt::NumericForStmt(it) => &it.syntax,
+            Stmt::GenericForStmt(it) => &it.syntax,
+            Stmt::ReturnStmt(it) => &it.syntax,
+            Stmt::BreakStmt(it) => &it.syntax,
+            Stmt::GotoStmt(it) => &it.syntax,
+            Stmt::LabelStmt(it) => &it.syntax,
+        }
+    }
+}
+
+impl Stmt {}
+
+// TableExpr
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TableExpr {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for TableExpr {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == TABLE_EXPR
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(TableExpr { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl TableExpr {
+    pub fn fields(&self) -> AstChildren<TableField> {
+        super::children(self)
+    }
+}
+
+// TableField
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TableField {
+    NamedField(NamedField),
+    IndexedField(IndexedField),
+    PositionalField(PositionalField),
+}
+impl From<NamedField> for TableField {
+    fn from(n: NamedField) -> TableField {
+        TableField::NamedField(n)
+    }
+}
+impl From<IndexedField> for TableField {
+    fn from(n: IndexedField) -> TableField {
+        TableField::IndexedField(n)
+    }
+}
+impl From<PositionalField> for TableField {
+    fn from(n: PositionalField) -> TableField {
+        TableField::PositionalField(n)
+    }
+}
+
+impl AstNode for TableField {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        matches!(kind, NAMED_FIELD | INDEXED_FIELD | POSITIONAL_FIELD)
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        let res = match syntax.kind() {
+            NAMED_FIELD => TableField::NamedField(NamedField { syntax }),
+            INDEXED_FIELD => TableField::IndexedField(IndexedField { syntax }),
+            POSITIONAL_FIELD => TableField::PositionalField(PositionalField { syntax }),
+            _ => return None,
+        };
+        Some(res)
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            TableField::NamedField(it) => &it.syntax,
+            TableField::IndexedField(it) => &it.syntax,
+            TableField::PositionalField(it) => &it.syntax,
+        }
+    }
+}
+
+impl TableField {}
+
+// UnaryExpr
+/// Fields:
+/// * `operand: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnaryExpr {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for UnaryExpr {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == UNARY_EXPR
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(UnaryExpr { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl UnaryExpr {
+    /// The `operand` of this `UnaryExpr`, its `Expr` child at index 0.
+    pub fn operand(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+}
+
+// WhileStmt
+/// Fields:
+/// * `condition: Expr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WhileStmt {
+    pub(crate) syntax: SyntaxNode,
+}
+
+impl AstNode for WhileStmt {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == WHILE_STMT
+    }
+    fn cast(syntax: SyntaxNode) -> Option<Self> {
+        if Self::can_cast(syntax.kind()) {
+            Some(WhileStmt { syntax })
+        } else {
+            None
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+}
+
+impl WhileStmt {
+    pub fn block(&self) -> Option<Block> {
+        super::child_opt(self)
+    }
+
+    /// The `condition` of this `WhileStmt`, its `Expr` child at index 0.
+    pub fn condition(&self) -> Option<Expr> {
+        super::nth_child(self, 0)
+    }
+}
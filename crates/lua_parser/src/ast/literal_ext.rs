@@ -0,0 +1,217 @@
+//! The kinds and values of literals, for analyses of constants.
+
+use super::{AstNode, AstToken, Literal, StringLit};
+use crate::{SyntaxKind::*, SyntaxToken};
+use std::borrow::Cow;
+
+/// The kind of a `Literal`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LiteralKind {
+    Nil,
+    True,
+    False,
+    /// A number without a fraction or exponent, e.g. `1` or `0xff`
+    Integer,
+    /// A number with a fraction or exponent, e.g. `1.5` or `1e3`
+    Float,
+    String,
+    /// `...`
+    Vararg,
+}
+
+/// The value of a `Literal`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LuaValue {
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    /// The bytes of a string, which don't have to be valid UTF-8 in Lua
+    String(Vec<u8>),
+}
+
+impl LuaValue {
+    /// Returns the text of a string value, with invalid UTF-8 replaced by `U+FFFD`.
+    pub fn as_str(&self) -> Option<Cow<'_, str>> {
+        match self {
+            LuaValue::String(bytes) => Some(String::from_utf8_lossy(bytes)),
+            _ => None,
+        }
+    }
+
+    /// Returns true unless the value is `nil` or `false`, the values that are false in conditions.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, LuaValue::Nil | LuaValue::Boolean(false))
+    }
+}
+
+impl Literal {
+    /// Returns the token of the literal, e.g. `nil` or `"a"`.
+    pub fn token(&self) -> Option<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(|element| element.into_token())
+            .find(|token| !token.kind().is_trivia())
+    }
+
+    pub fn kind(&self) -> Option<LiteralKind> {
+        let kind = match self.token()?.kind() {
+            NIL_KW => LiteralKind::Nil,
+            TRUE_KW => LiteralKind::True,
+            FALSE_KW => LiteralKind::False,
+            INT_NUMBER => LiteralKind::Integer,
+            FLOAT_NUMBER => LiteralKind::Float,
+            STRING => LiteralKind::String,
+            DOTDOTDOT => LiteralKind::Vararg,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// Returns the value of the literal like Lua 5.4 reads it, e.g. an integer for `0xff` and a
+    /// float for a decimal integer that doesn't fit in 64 bits. Returns `None` for `...` and for
+    /// invalid literals, e.g. a string with an invalid escape sequence.
+    pub fn value(&self) -> Option<LuaValue> {
+        let token = self.token()?;
+        let value = match self.kind()? {
+            LiteralKind::Nil => LuaValue::Nil,
+            LiteralKind::True => LuaValue::Boolean(true),
+            LiteralKind::False => LuaValue::Boolean(false),
+            LiteralKind::Integer | LiteralKind::Float => number_value(token.text())?,
+            LiteralKind::String => LuaValue::String(StringLit::cast(token)?.value()?),
+            LiteralKind::Vararg => return None,
+        };
+        Some(value)
+    }
+}
+
+/// Returns the value of a numeral.
+fn number_value(text: &str) -> Option<LuaValue> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return hex_number_value(hex);
+    }
+    let is_decimal = text
+        .bytes()
+        .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'+' | b'-'));
+    if !is_decimal || !text.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        if let Ok(value) = text.parse() {
+            return Some(LuaValue::Integer(value));
+        }
+    }
+    text.parse().ok().map(LuaValue::Float)
+}
+
+/// Returns the value of a hexadecimal numeral without its `0x`, e.g. `ff` or `1.8p3`. Integers
+/// wrap around like in Lua.
+fn hex_number_value(text: &str) -> Option<LuaValue> {
+    let (mantissa, exponent) = match text.find(['p', 'P']) {
+        Some(idx) => (&text[..idx], Some(text[idx + 1..].parse::<i32>().ok()?)),
+        None => (text, None),
+    };
+    let (int, fraction) = match mantissa.split_once('.') {
+        Some((int, fraction)) => (int, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let digits = int.chars().chain(fraction.unwrap_or("").chars());
+    let digits: Option<Vec<u32>> = digits.map(|c| c.to_digit(16)).collect();
+    let digits = digits?;
+    if digits.is_empty() {
+        return None;
+    }
+    if fraction.is_none() && exponent.is_none() {
+        let value = digits.iter().fold(0u64, |value, &digit| {
+            value.wrapping_mul(16).wrapping_add(digit as u64)
+        });
+        return Some(LuaValue::Integer(value as i64));
+    }
+    let fraction_len = fraction.map_or(0, str::len) as i32;
+    let value = digits
+        .iter()
+        .fold(0f64, |value, &digit| value * 16.0 + digit as f64);
+    let exponent = exponent.unwrap_or(0) - 4 * fraction_len;
+    // Scaled in two steps, because `2^exponent` itself can be out of range when the result isn't,
+    // e.g. for the subnormal `0x0.0000000000001p-1022`
+    let half = exponent / 2;
+    Some(LuaValue::Float(
+        value * 2f64.powi(half) * 2f64.powi(exponent - half),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chunk;
+
+    fn literals(text: &str) -> Vec<Literal> {
+        Chunk::parse(&format!("return {}", text))
+            .syntax_node()
+            .descendants()
+            .filter_map(Literal::cast)
+            .collect()
+    }
+
+    fn value(text: &str) -> Option<LuaValue> {
+        literals(text)[0].value()
+    }
+
+    #[test]
+    fn test_kinds() {
+        let kinds: Vec<_> = literals("nil, true, false, 1, 1.5, 'a', ...")
+            .iter()
+            .map(|literal| literal.kind().unwrap())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                LiteralKind::Nil,
+                LiteralKind::True,
+                LiteralKind::False,
+                LiteralKind::Integer,
+                LiteralKind::Float,
+                LiteralKind::String,
+                LiteralKind::Vararg
+            ]
+        );
+    }
+
+    #[test]
+    fn test_values() {
+        assert_eq!(value("nil"), Some(LuaValue::Nil));
+        assert_eq!(value("false"), Some(LuaValue::Boolean(false)));
+        assert_eq!(value("42"), Some(LuaValue::Integer(42)));
+        assert_eq!(value("0xff"), Some(LuaValue::Integer(255)));
+        assert_eq!(value("0xffffffffffffffff"), Some(LuaValue::Integer(-1)));
+        assert_eq!(
+            value("9223372036854775808"),
+            Some(LuaValue::Float(9223372036854775808.0))
+        );
+        assert_eq!(value("1.5e2"), Some(LuaValue::Float(150.0)));
+        assert_eq!(value("5."), Some(LuaValue::Float(5.0)));
+        assert_eq!(value(".5"), Some(LuaValue::Float(0.5)));
+        assert_eq!(value("3.e2"), Some(LuaValue::Float(300.0)));
+        assert_eq!(value("0x1.8"), Some(LuaValue::Float(1.5)));
+        assert_eq!(value("..."), None);
+
+        let string = |text| value(text).unwrap().as_str().unwrap().into_owned();
+        assert_eq!(
+            string(r#""a\tb\x41\066\u{48}\u{e9}\z   c""#),
+            "a\tbAB\u{48}\u{e9}c"
+        );
+        assert_eq!(string("'a\\\r\nb'"), "a\nb");
+        assert_eq!(string("[==[\r\na\r\nb]]]==]"), "a\nb]]");
+        assert_eq!(value(r#""\xff""#), Some(LuaValue::String(vec![0xff])));
+        assert_eq!(
+            value(r#""\u{7FFFFFFF}""#),
+            Some(LuaValue::String(vec![0xfd, 0xbf, 0xbf, 0xbf, 0xbf, 0xbf]))
+        );
+        assert_eq!(value(r#""\q""#), None);
+        assert_eq!(value(r#""\256""#), None);
+        assert_eq!(value("[[a"), None);
+
+        assert!(LuaValue::Integer(0).is_truthy());
+        assert!(!LuaValue::Nil.is_truthy());
+    }
+}
@@ -0,0 +1,141 @@
+//! Accessors of statements and blocks that can't be generated from the grammar.
+
+use super::{AstNode, Block, Expr, GotoStmt, Stmt, WhileStmt};
+use crate::{SyntaxKind::*, SyntaxToken};
+
+impl Block {
+    /// Returns the last expression of the `return` statement of the block, e.g. `b` in
+    /// `return a, b`, which passes on all its values if it is a call or `...`.
+    pub fn last_expr_of_return(&self) -> Option<Expr> {
+        self.return_stmt()?.expr_list()?.exprs().last()
+    }
+
+    /// Returns true if execution can reach the end of the block, i.e. the block doesn't always
+    /// end with a `return`, `break` or `goto`. This only looks at the syntax: calls are assumed to
+    /// return, e.g. of `error`, and loops to end unless they are `while true` loops without a
+    /// `break` or `goto`.
+    pub fn falls_through(&self) -> bool {
+        let mut falls_through = true;
+        for stmt in self.statements() {
+            match stmt {
+                // A `goto` can jump to the label, after a statement that doesn't fall through
+                Stmt::LabelStmt(_) => falls_through = true,
+                stmt if !stmt_falls_through(&stmt) => falls_through = false,
+                _ => {}
+            }
+        }
+        falls_through
+    }
+}
+
+impl GotoStmt {
+    /// Returns the name of the label the statement jumps to, e.g. `done` in `goto done`.
+    pub fn label(&self) -> Option<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(|element| element.into_token())
+            .find(|token| token.kind() == IDENT)
+    }
+}
+
+fn stmt_falls_through(stmt: &Stmt) -> bool {
+    let block_falls_through =
+        |block: Option<Block>| block.is_none_or(|block| block.falls_through());
+    match stmt {
+        Stmt::ReturnStmt(_) | Stmt::BreakStmt(_) | Stmt::GotoStmt(_) => false,
+        Stmt::DoStmt(stmt) => block_falls_through(stmt.block()),
+        Stmt::IfStmt(stmt) => {
+            let else_clause = match stmt.else_clause() {
+                Some(else_clause) => else_clause,
+                None => return true,
+            };
+            block_falls_through(stmt.block())
+                || stmt
+                    .else_if_clauses()
+                    .any(|clause| block_falls_through(clause.block()))
+                || block_falls_through(else_clause.block())
+        }
+        Stmt::WhileStmt(stmt) => !is_infinite(stmt),
+        _ => true,
+    }
+}
+
+/// Returns true if `stmt` is a `while true` loop that no `break` or `goto` in its body leaves.
+fn is_infinite(stmt: &WhileStmt) -> bool {
+    let is_true = stmt
+        .condition()
+        .and_then(|condition| condition.syntax().first_token())
+        .is_some_and(|token| token.kind() == TRUE_KW);
+    let body = match stmt.block() {
+        Some(body) => body,
+        None => return false,
+    };
+    let leaves = body.syntax().descendants().any(|node| match node.kind() {
+        // Jumps to labels outside of the loop can't be told apart without resolving the labels
+        GOTO_STMT => true,
+        BREAK_STMT => node
+            .ancestors()
+            .find(|ancestor| {
+                matches!(
+                    ancestor.kind(),
+                    WHILE_STMT | REPEAT_STMT | NUMERIC_FOR_STMT | GENERIC_FOR_STMT
+                )
+            })
+            .is_some_and(|ancestor| &ancestor == stmt.syntax()),
+        _ => false,
+    });
+    is_true && !leaves
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast::Block, AstNode, Chunk};
+
+    fn falls_through(text: &str) -> bool {
+        let chunk = Chunk::parse(text).tree();
+        chunk.block().unwrap().falls_through()
+    }
+
+    #[test]
+    fn test_return_accessors() {
+        let chunk = Chunk::parse("local x = 1 return x, f()").tree();
+        let block = chunk.block().unwrap();
+        assert_eq!(block.statements().count(), 2);
+        assert_eq!(
+            block.return_stmt().unwrap().syntax().text(),
+            "return x, f()"
+        );
+        assert_eq!(block.last_expr_of_return().unwrap().syntax().text(), "f()");
+
+        let chunk = Chunk::parse("do return end").tree();
+        let blocks: Vec<_> = chunk
+            .syntax()
+            .descendants()
+            .filter_map(Block::cast)
+            .collect();
+        assert!(blocks[0].return_stmt().is_none());
+        assert!(blocks[1].last_expr_of_return().is_none());
+    }
+
+    #[test]
+    fn test_falls_through() {
+        assert!(falls_through("f()"));
+        assert!(falls_through(""));
+        assert!(!falls_through("f() return"));
+        assert!(!falls_through("do goto done end"));
+        assert!(!falls_through(
+            "if a then return 1 elseif b then error() return else return end"
+        ));
+        assert!(falls_through(
+            "if a then return 1 elseif b then error() else return end"
+        ));
+        assert!(falls_through("if a then return 1 end"));
+        assert!(!falls_through("goto skip f() ::skip:: return"));
+        assert!(falls_through("goto skip f() ::skip::"));
+        assert!(!falls_through(
+            "while true do for i = 1, 2 do break end end"
+        ));
+        assert!(falls_through("while true do if a then break end end"));
+        assert!(falls_through("while x do end"));
+    }
+}
@@ -0,0 +1,454 @@
+//! Hand-written wrappers around tokens, the token counterpart of the generated AST nodes.
+
+use crate::{
+    ast::AstToken, escape::closes_long_bracket, long_bracket_level, SyntaxKind::*, SyntaxToken,
+    TextRange, TextSize,
+};
+
+/// A string literal, either quoted (`"a"` or `'a'`) or a long string (`[[a]]` or `[==[a]==]`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StringLit {
+    syntax: SyntaxToken,
+}
+
+impl AstToken for StringLit {
+    fn cast(token: SyntaxToken) -> Option<Self> {
+        if token.kind() == STRING {
+            Some(StringLit { syntax: token })
+        } else {
+            None
+        }
+    }
+
+    fn syntax(&self) -> &SyntaxToken {
+        &self.syntax
+    }
+}
+
+impl StringLit {
+    /// Returns true for long strings, e.g. `[[a]]`.
+    pub fn is_long(&self) -> bool {
+        self.text().starts_with('[')
+    }
+
+    /// Returns the range of the contents of the string, without the quotes or brackets. Escape
+    /// sequences are part of the contents as written. The line break that directly follows the
+    /// opening bracket of a long string is skipped by Lua, so it isn't part of the contents. The
+    /// contents of an unterminated string extend to the end of the token.
+    pub fn content_range(&self) -> TextRange {
+        let text = self.text();
+        let (start, end) = if self.is_long() {
+            long_string_content(text)
+        } else {
+            (1, scan_quoted(text).0)
+        };
+        self.range_in_token(start, end)
+    }
+
+    /// Returns the number of `=` in the brackets of a long string, e.g. 2 for `[==[a]==]`, or
+    /// `None` for a quoted string.
+    pub fn long_bracket_level(&self) -> Option<usize> {
+        if self.is_long() {
+            Some(bracket_level(self.text()))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the text of a long string with its contents replaced by `content`, which is
+    /// written as is, see `content_range`. The level of the brackets is kept, unless `content`
+    /// contains the closing bracket, in which case the lowest level that fits is used. Returns
+    /// `None` for a quoted string.
+    pub fn with_content(&self, content: &str) -> Option<String> {
+        let level = self.long_bracket_level()?;
+        Some(with_long_content("", level, content))
+    }
+
+    /// Returns the ranges of the escape sequences in a quoted string, e.g. `\n`, `\065` or
+    /// `\u{48}`. The range of a `\z` escape includes the whitespace it skips. Long strings don't
+    /// have escape sequences.
+    pub fn escape_ranges(&self) -> Vec<TextRange> {
+        if self.is_long() {
+            return Vec::new();
+        }
+        scan_quoted(self.text())
+            .1
+            .into_iter()
+            .map(|(start, end)| self.range_in_token(start, end))
+            .collect()
+    }
+
+    /// Returns the bytes of the string as Lua sees them, with the escape sequences decoded and the
+    /// line breaks of long strings converted to `\n`. Returns `None` if the string is
+    /// unterminated or contains an invalid escape sequence.
+    pub fn value(&self) -> Option<Vec<u8>> {
+        let text = self.text();
+        if self.is_long() {
+            let (start, end) = long_string_content(text);
+            let level = bracket_level(text);
+            if end + level + 2 != text.len() {
+                return None;
+            }
+            return Some(normalize_line_breaks(&text[start..end]).into_bytes());
+        }
+
+        let (end, escapes) = scan_quoted(text);
+        if end + 1 != text.len() {
+            return None;
+        }
+        let mut value = Vec::with_capacity(end);
+        let mut copied = 1;
+        for (start, escape_end) in escapes {
+            value.extend_from_slice(&text.as_bytes()[copied..start]);
+            decode_escape(&text[start + 1..escape_end], &mut value)?;
+            copied = escape_end;
+        }
+        value.extend_from_slice(&text.as_bytes()[copied..end]);
+        Some(value)
+    }
+
+    fn range_in_token(&self, start: usize, end: usize) -> TextRange {
+        let offset = self.syntax.text_range().start();
+        TextRange::new(
+            offset + TextSize::from(start as u32),
+            offset + TextSize::from(end as u32),
+        )
+    }
+}
+
+/// A comment, either a line comment (`-- a`) or a long comment (`--[[a]]` or `--[==[a]==]`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Comment {
+    syntax: SyntaxToken,
+}
+
+impl AstToken for Comment {
+    fn cast(token: SyntaxToken) -> Option<Self> {
+        if token.kind() == COMMENT {
+            Some(Comment { syntax: token })
+        } else {
+            None
+        }
+    }
+
+    fn syntax(&self) -> &SyntaxToken {
+        &self.syntax
+    }
+}
+
+impl Comment {
+    /// Returns true for long comments, e.g. `--[[a]]`. A comment like `--[ a` is a line comment.
+    pub fn is_long(&self) -> bool {
+        self.long_text().is_some()
+    }
+
+    /// Returns the range of the text of the comment, without the dashes or brackets, or without
+    /// the `#` of a shebang line like `#!/usr/bin/lua`. Like in long strings, the line break that
+    /// directly follows the opening bracket isn't part of it.
+    pub fn content_range(&self) -> TextRange {
+        let (start, end) = match self.long_text() {
+            Some(rest) => {
+                let (start, end) = long_string_content(rest);
+                (start + 2, end + 2)
+            }
+            None if self.text().starts_with('#') => (1, self.text().len()),
+            None => (2, self.text().len()),
+        };
+        let offset = self.syntax.text_range().start();
+        TextRange::new(
+            offset + TextSize::from(start as u32),
+            offset + TextSize::from(end as u32),
+        )
+    }
+
+    /// Returns the number of `=` in the brackets of a long comment, e.g. 2 for `--[==[a]==]`, or
+    /// `None` for a line comment.
+    pub fn long_bracket_level(&self) -> Option<usize> {
+        self.long_text().map(bracket_level)
+    }
+
+    /// Returns the text of a long comment with its contents replaced by `content`, like
+    /// `StringLit::with_content`. Returns `None` for a line comment.
+    pub fn with_content(&self, content: &str) -> Option<String> {
+        let level = self.long_bracket_level()?;
+        Some(with_long_content("--", level, content))
+    }
+
+    /// Returns the text after the `--` of a long comment, starting at the opening bracket.
+    fn long_text(&self) -> Option<&str> {
+        let rest = self.text().strip_prefix("--")?;
+        if rest.starts_with('[') && rest[1 + bracket_level(rest)..].starts_with('[') {
+            Some(rest)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the number of `=` after the `[` at the start of `text`.
+fn bracket_level(text: &str) -> usize {
+    text[1..].bytes().take_while(|&b| b == b'=').count()
+}
+
+/// Returns the line break at the start of `text`, which Lua skips after an opening long bracket.
+fn leading_line_break(text: &str) -> &str {
+    ["\r\n", "\n\r", "\n", "\r"]
+        .iter()
+        .find(|newline| text.starts_with(*newline))
+        .map_or("", |newline| newline)
+}
+
+/// Returns the text of long brackets of `level` that enclose `content`, after `prefix`. If
+/// `content` starts with a line break, the same line break is inserted before it, because the
+/// first one would be skipped.
+fn with_long_content(prefix: &str, level: usize, content: &str) -> String {
+    let level = if closes_long_bracket(content, level) {
+        long_bracket_level(content)
+    } else {
+        level
+    };
+    let equals = "=".repeat(level);
+    format!(
+        "{}[{}[{}{}]{}]",
+        prefix,
+        equals,
+        leading_line_break(content),
+        content,
+        equals
+    )
+}
+
+/// Returns the byte offsets of the start and end of the contents of a long string.
+fn long_string_content(text: &str) -> (usize, usize) {
+    let level = bracket_level(text);
+    let open = (level + 2).min(text.len());
+    let start = open + leading_line_break(&text[open..]).len();
+
+    let close = format!("]{}]", "=".repeat(level));
+    let end = if text.len() >= open + close.len() && text.ends_with(&close) {
+        text.len() - close.len()
+    } else {
+        text.len()
+    };
+    (start, end.max(start))
+}
+
+/// Converts every line break to `\n`, as Lua does in long strings.
+fn normalize_line_breaks(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' | '\n' => {
+                let other = if c == '\r' { '\n' } else { '\r' };
+                chars.next_if_eq(&other);
+                result.push('\n');
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Appends the bytes of an escape sequence, without its backslash, to `value`. Returns `None` if
+/// the escape sequence is invalid.
+fn decode_escape(escape: &str, value: &mut Vec<u8>) -> Option<()> {
+    let simple = match escape {
+        "a" => Some(0x07),
+        "b" => Some(0x08),
+        "f" => Some(0x0c),
+        "n" | "\n" | "\r" | "\r\n" | "\n\r" => Some(b'\n'),
+        "r" => Some(b'\r'),
+        "t" => Some(b'\t'),
+        "v" => Some(0x0b),
+        "\\" | "\"" | "'" => Some(escape.as_bytes()[0]),
+        _ => None,
+    };
+    if let Some(byte) = simple {
+        value.push(byte);
+        return Some(());
+    }
+    let bytes = escape.as_bytes();
+    match bytes.first()? {
+        b'z' => {}
+        b'x' if escape.len() == 3 => value.push(u8::from_str_radix(&escape[1..], 16).ok()?),
+        b'0'..=b'9' => value.push(escape.parse::<u8>().ok()?),
+        b'u' if escape.len() > 3 && escape.ends_with('}') => {
+            let code = u32::from_str_radix(&escape[2..escape.len() - 1], 16).ok()?;
+            push_utf8(code, value)?;
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+/// Appends the UTF-8 encoding of `code` to `value`, including the code points up to 2^31 that Lua
+/// encodes with up to six bytes. Returns `None` if `code` is larger.
+fn push_utf8(code: u32, value: &mut Vec<u8>) -> Option<()> {
+    if code < 0x80 {
+        value.push(code as u8);
+        return Some(());
+    }
+    if code >= 0x8000_0000 {
+        return None;
+    }
+    // The continuation bytes, from the last one, and the bits that are left for the first byte
+    let mut continuation = Vec::new();
+    let mut rest = code;
+    let mut first_bits = 0x3f;
+    while rest > first_bits {
+        continuation.push(0x80 | (rest & 0x3f) as u8);
+        rest >>= 6;
+        first_bits >>= 1;
+    }
+    // The first byte starts with as many ones as the sequence has bytes, followed by a zero
+    let prefix = !((first_bits << 1) | 1) as u8;
+    value.push(prefix | rest as u8);
+    value.extend(continuation.iter().rev());
+    Some(())
+}
+
+/// Scans a quoted string, returns the byte offset where its contents end and the byte ranges of
+/// its escape sequences.
+fn scan_quoted(text: &str) -> (usize, Vec<(usize, usize)>) {
+    let quote = text.as_bytes()[0];
+    let mut escapes = Vec::new();
+    let mut idx = 1;
+    while idx < text.len() {
+        match text.as_bytes()[idx] {
+            b'\\' => {
+                let end = idx + 1 + escape_len(&text[idx + 1..]);
+                escapes.push((idx, end));
+                idx = end;
+            }
+            b if b == quote => return (idx, escapes),
+            _ => idx += text[idx..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    (text.len(), escapes)
+}
+
+/// Returns the length in bytes of an escape sequence after its backslash.
+fn escape_len(rest: &str) -> usize {
+    let count = |skip: usize, max: usize, pred: fn(&u8) -> bool| {
+        skip + rest.as_bytes()[skip..]
+            .iter()
+            .take(max)
+            .take_while(|b| pred(b))
+            .count()
+    };
+    match rest.as_bytes() {
+        [] => 0,
+        [b'x', ..] => count(1, 2, u8::is_ascii_hexdigit),
+        [b'z', ..] => count(1, usize::MAX, u8::is_ascii_whitespace),
+        [b'u', b'{', ..] => {
+            let digits = count(2, usize::MAX, u8::is_ascii_hexdigit);
+            if rest.as_bytes().get(digits) == Some(&b'}') {
+                digits + 1
+            } else {
+                digits
+            }
+        }
+        [b'0'..=b'9', ..] => count(0, 3, u8::is_ascii_digit),
+        [b'\r', b'\n', ..] | [b'\n', b'\r', ..] => 2,
+        _ => rest.chars().next().map_or(0, char::len_utf8),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AstNode, Chunk};
+
+    fn string(text: &str) -> StringLit {
+        let parse = Chunk::parse(text);
+        parse
+            .tree()
+            .syntax()
+            .descendants_with_tokens()
+            .filter_map(|element| element.into_token())
+            .find_map(StringLit::cast)
+            .unwrap()
+    }
+
+    fn content(text: &str) -> &str {
+        &text[string(text).content_range()]
+    }
+
+    #[test]
+    fn test_content_range() {
+        assert_eq!(content(r#"x = "a\"b""#), r#"a\"b"#);
+        assert_eq!(content("x = 'a'"), "a");
+        assert_eq!(content("x = ''"), "");
+        assert_eq!(content("x = 'a"), "a");
+        assert_eq!(content("x = [[a]]"), "a");
+        assert_eq!(content("x = [==[\na]]]==]"), "a]]");
+        assert_eq!(content("x = [[\r\n\na]]"), "\na");
+        assert_eq!(content("x = [=[a"), "a");
+        assert!(string("x = [[a]]").is_long());
+    }
+
+    #[test]
+    fn test_escape_ranges() {
+        let text = r#"x = "\n\x41\065\u{48}\z  a\q\"""#;
+        let escapes: Vec<_> = string(text)
+            .escape_ranges()
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+        assert_eq!(
+            escapes,
+            vec![r"\n", r"\x41", r"\065", r"\u{48}", "\\z  ", r"\q", r#"\""#]
+        );
+        assert!(string("x = [[\\n]]").escape_ranges().is_empty());
+    }
+
+    fn comment(text: &str) -> Comment {
+        Chunk::parse(text)
+            .tree()
+            .syntax()
+            .descendants_with_tokens()
+            .filter_map(|element| element.into_token())
+            .find_map(Comment::cast)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_long_brackets() {
+        assert_eq!(string("x = [==[a]==]").long_bracket_level(), Some(2));
+        assert_eq!(string("x = 'a'").long_bracket_level(), None);
+        assert_eq!(comment("--[=[\na]=]").long_bracket_level(), Some(1));
+        assert_eq!(comment("--[= a").long_bracket_level(), None);
+        assert_eq!(comment("-- [[a]]").long_bracket_level(), None);
+
+        let text = "--[==[\r\na]==]";
+        assert_eq!(&text[comment(text).content_range()], "a");
+        let text = "--[ a";
+        assert_eq!(&text[comment(text).content_range()], "[ a");
+        let text = "#!/usr/bin/lua\nx = 1";
+        assert!(!comment(text).is_long());
+        assert_eq!(&text[comment(text).content_range()], "!/usr/bin/lua");
+
+        let long = string("x = [=[\na]=]");
+        assert_eq!(long.with_content("b").as_deref(), Some("[=[b]=]"));
+        assert_eq!(long.with_content("]]").as_deref(), Some("[=[]]]=]"));
+        assert_eq!(long.with_content("]=]").as_deref(), Some("[==[]=]]==]"));
+        assert_eq!(
+            long.with_content("\r\nb").as_deref(),
+            Some("[=[\r\n\r\nb]=]")
+        );
+        assert_eq!(string("x = 'a'").with_content("b"), None);
+        assert_eq!(
+            comment("--[[a]]").with_content("a]").as_deref(),
+            Some("--[=[a]]=]")
+        );
+        assert_eq!(comment("-- a").with_content("b"), None);
+
+        for content in ["", "\n", "\r\nb\n", "]", "]]", "]=", "[[x]]"].iter() {
+            let text = format!("x = {}", long.with_content(content).unwrap());
+            let edited = string(&text);
+            assert_eq!(&text[edited.content_range()], *content);
+            assert!(edited.value().is_some(), "{}", text);
+        }
+    }
+}
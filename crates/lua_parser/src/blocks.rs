@@ -0,0 +1,59 @@
+//! Matches the keywords that open and close blocks in a stream of tokens, e.g. `function` and
+//! `if` with their `end` or `repeat` with its `until`.
+
+use crate::SyntaxKind::{self, *};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BlockChange {
+    None,
+    /// A block was opened by the visited token
+    Opened,
+    /// The visited token closed the block opened by the token at the contained index
+    Closed(usize),
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct BlockTracker {
+    /// The indices of the tokens that opened the currently open blocks, innermost last.
+    open: Vec<usize>,
+    /// True if the innermost block is a `while` or `for` loop whose `do` has not been seen yet.
+    awaiting_do: bool,
+}
+
+impl BlockTracker {
+    pub fn open_blocks(&self) -> &[usize] {
+        &self.open
+    }
+
+    pub fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Updates the tracker with the next significant token.
+    pub fn visit(&mut self, idx: usize, kind: SyntaxKind) -> BlockChange {
+        match kind {
+            FUNCTION_KW | IF_KW | REPEAT_KW => {
+                self.open.push(idx);
+                BlockChange::Opened
+            }
+            WHILE_KW | FOR_KW => {
+                self.open.push(idx);
+                self.awaiting_do = true;
+                BlockChange::Opened
+            }
+            DO_KW if self.awaiting_do => {
+                self.awaiting_do = false;
+                BlockChange::None
+            }
+            DO_KW => {
+                self.open.push(idx);
+                BlockChange::Opened
+            }
+            END_KW | UNTIL_KW => match self.open.pop() {
+                Some(open) => BlockChange::Closed(open),
+                None => BlockChange::None,
+            },
+            _ => BlockChange::None,
+        }
+    }
+}
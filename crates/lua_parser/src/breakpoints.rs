@@ -0,0 +1,106 @@
+//! Determines on which lines a debugger can stop, for debug adapters that have to validate or
+//! move the breakpoints of a client.
+//!
+//! Lua stops on a line when it executes the first instruction of that line. This module
+//! approximates those lines from the syntax tree: the lines on which a statement starts, except
+//! labels, and the lines of the conditions of `elseif` and `until`. Blank lines, comments and the
+//! lines of `end`, `else` and closing brackets are never executable.
+
+use crate::{
+    ast::{ElseIfClause, RepeatStmt, Stmt},
+    AstNode, Chunk, LineIndex, SyntaxKind, SyntaxNode, SyntaxToken,
+};
+
+/// Returns the zero-based lines of `chunk` that contain an executable statement, in ascending
+/// order.
+pub fn executable_lines(chunk: &Chunk) -> Vec<u32> {
+    let index = LineIndex::new(&chunk.syntax().text().to_string());
+    let mut lines = Vec::new();
+    let mut push_line = |node: &SyntaxNode| {
+        if let Some(token) = first_significant_token(node) {
+            lines.push(index.line_col(token.text_range().start()).line);
+        }
+    };
+    for node in chunk.syntax().descendants() {
+        if Stmt::can_cast(node.kind()) && node.kind() != SyntaxKind::LABEL_STMT {
+            push_line(&node);
+        }
+        let condition = match ElseIfClause::cast(node.clone()) {
+            Some(clause) => clause.condition(),
+            None => RepeatStmt::cast(node).and_then(|repeat| repeat.condition()),
+        };
+        if let Some(condition) = condition {
+            push_line(condition.syntax());
+        }
+    }
+    lines.sort_unstable();
+    lines.dedup();
+    lines
+}
+
+/// Returns true if a breakpoint on the zero-based `line` of `chunk` can be hit.
+pub fn is_valid_breakpoint_line(chunk: &Chunk, line: u32) -> bool {
+    executable_lines(chunk).binary_search(&line).is_ok()
+}
+
+/// Returns the line a breakpoint on the zero-based `line` of `chunk` should be moved to: the first
+/// executable line at or after `line`, or the last executable line before it if there is none.
+/// Returns `None` if the chunk has no executable lines.
+pub fn nearest_executable_line(chunk: &Chunk, line: u32) -> Option<u32> {
+    let lines = executable_lines(chunk);
+    match lines.binary_search(&line) {
+        Ok(idx) => Some(lines[idx]),
+        Err(idx) => lines.get(idx).or_else(|| lines.last()).cloned(),
+    }
+}
+
+fn first_significant_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| !token.kind().is_trivia())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_executable_lines() {
+        let text = r#"-- comment
+local function f(x)
+
+    if x then
+        return 1
+    elseif
+        x == 2
+    then
+        print(
+            "two"
+        )
+    else
+        return 3
+    end
+end
+::continue::
+repeat
+    f(1)
+until
+    f(2)
+"#;
+        let chunk = Chunk::parse(text).tree();
+        assert_eq!(
+            executable_lines(&chunk),
+            vec![1, 3, 4, 6, 8, 12, 16, 17, 19]
+        );
+        assert!(is_valid_breakpoint_line(&chunk, 4));
+        assert!(!is_valid_breakpoint_line(&chunk, 0));
+        assert!(!is_valid_breakpoint_line(&chunk, 13));
+        assert_eq!(nearest_executable_line(&chunk, 0), Some(1));
+        assert_eq!(nearest_executable_line(&chunk, 13), Some(16));
+        assert_eq!(nearest_executable_line(&chunk, 100), Some(19));
+        assert_eq!(
+            nearest_executable_line(&Chunk::parse("-- empty").tree(), 0),
+            None
+        );
+    }
+}
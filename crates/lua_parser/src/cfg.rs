@@ -0,0 +1,565 @@
+//! Control-flow graphs of functions, the foundation of reachability and data-flow analyses.
+//!
+//! A graph consists of basic blocks: sequences of statements and conditions that always run from
+//! the first to the last one. Every function has a graph of its own, the chunk included; the
+//! bodies of nested functions are not part of the graph of the function that defines them.
+
+use crate::{
+    ast::{Block, Chunk, GotoStmt, Stmt},
+    AstNode,
+    SyntaxKind::*,
+    SyntaxNode,
+};
+use std::collections::HashMap;
+
+/// Identifies a basic block in a `ControlFlowGraph`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockId(usize);
+
+/// A sequence of statements and conditions that always run one after another.
+#[derive(Debug, Clone, Default)]
+pub struct BasicBlock {
+    elements: Vec<SyntaxNode>,
+    successors: Vec<BlockId>,
+    predecessors: Vec<BlockId>,
+}
+
+impl BasicBlock {
+    /// Returns the statements and conditions of the block in the order in which they run.
+    /// Compound statements like `if` and loops are split up: their conditions are elements of
+    /// the blocks that decide where to continue, e.g. the `NAME_LIST` of a generic `for` loop is
+    /// the element of the block that assigns it on every iteration. A jump, i.e. a `return`,
+    /// `break` or `goto`, is always the last element of its block.
+    pub fn elements(&self) -> &[SyntaxNode] {
+        &self.elements
+    }
+
+    pub fn successors(&self) -> &[BlockId] {
+        &self.successors
+    }
+
+    pub fn predecessors(&self) -> &[BlockId] {
+        &self.predecessors
+    }
+}
+
+/// The basic blocks of a function and the jumps between them.
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph {
+    function: SyntaxNode,
+    blocks: Vec<BasicBlock>,
+    reachable: Vec<bool>,
+}
+
+impl ControlFlowGraph {
+    /// Returns the function of the graph: a `CHUNK`, `FUNCTION_STMT`, `LOCAL_FUNCTION_STMT` or
+    /// `FUNCTION_EXPR`.
+    pub fn function(&self) -> &SyntaxNode {
+        &self.function
+    }
+
+    /// Returns the block where the function starts.
+    pub fn entry(&self) -> BlockId {
+        BlockId(0)
+    }
+
+    /// Returns the block that every `return` and the end of the function lead to. It has no
+    /// elements.
+    pub fn exit(&self) -> BlockId {
+        BlockId(1)
+    }
+
+    /// Returns all blocks, in the order in which they were created.
+    pub fn blocks(&self) -> impl Iterator<Item = BlockId> {
+        (0..self.blocks.len()).map(BlockId)
+    }
+
+    pub fn block(&self, id: BlockId) -> &BasicBlock {
+        &self.blocks[id.0]
+    }
+
+    /// Returns the blocks that can run directly after `id`.
+    pub fn successors(&self, id: BlockId) -> impl Iterator<Item = BlockId> + '_ {
+        self.blocks[id.0].successors.iter().copied()
+    }
+
+    /// Returns the blocks that can run directly before `id`.
+    pub fn predecessors(&self, id: BlockId) -> impl Iterator<Item = BlockId> + '_ {
+        self.blocks[id.0].predecessors.iter().copied()
+    }
+
+    /// Returns true if there is a path from the entry to `id`. Code after a jump is in a block
+    /// that isn't reachable, unless a label in front of it is the target of a `goto`.
+    pub fn is_reachable(&self, id: BlockId) -> bool {
+        self.reachable[id.0]
+    }
+
+    /// Returns the block that contains `node`, e.g. a statement or a part of a condition. Returns
+    /// `None` if the node isn't part of the function, e.g. because it is in a nested function.
+    pub fn block_of(&self, node: &SyntaxNode) -> Option<BlockId> {
+        if enclosing_function(node).as_ref() != Some(&self.function) {
+            return None;
+        }
+        self.blocks().find(|&id| {
+            self.block(id)
+                .elements
+                .iter()
+                .any(|element| node.ancestors().any(|ancestor| &ancestor == element))
+        })
+    }
+}
+
+/// Builds the graph of `function`, a `CHUNK`, `FUNCTION_STMT`, `LOCAL_FUNCTION_STMT` or
+/// `FUNCTION_EXPR`. Returns `None` for other nodes.
+pub fn control_flow_graph(function: &SyntaxNode) -> Option<ControlFlowGraph> {
+    if !is_function(function) {
+        return None;
+    }
+    let mut builder = Builder {
+        blocks: Vec::new(),
+        current: BlockId(0),
+        loop_exits: Vec::new(),
+        labels: HashMap::new(),
+        gotos: Vec::new(),
+    };
+    let entry = builder.new_block();
+    let exit = builder.new_block();
+    if let Some(body) = function.children().find_map(Block::cast) {
+        builder.block(&body, exit);
+    }
+    let last = builder.current;
+    builder.edge(last, exit);
+    builder.resolve_gotos();
+
+    let mut reachable = vec![false; builder.blocks.len()];
+    let mut stack = vec![entry];
+    while let Some(id) = stack.pop() {
+        if !reachable[id.0] {
+            reachable[id.0] = true;
+            stack.extend(builder.blocks[id.0].successors.iter().copied());
+        }
+    }
+    Some(ControlFlowGraph {
+        function: function.clone(),
+        blocks: builder.blocks,
+        reachable,
+    })
+}
+
+/// Builds the graphs of the chunk and of all functions in it, in the order of the text.
+pub fn control_flow_graphs(chunk: &Chunk) -> Vec<ControlFlowGraph> {
+    chunk
+        .syntax()
+        .descendants()
+        .filter_map(|node| control_flow_graph(&node))
+        .collect()
+}
+
+fn is_function(node: &SyntaxNode) -> bool {
+    matches!(
+        node.kind(),
+        CHUNK | FUNCTION_STMT | LOCAL_FUNCTION_STMT | FUNCTION_EXPR
+    )
+}
+
+/// Returns the function whose body contains `node`.
+fn enclosing_function(node: &SyntaxNode) -> Option<SyntaxNode> {
+    node.ancestors().skip(1).find(is_function)
+}
+
+struct Builder {
+    blocks: Vec<BasicBlock>,
+    /// The block that the next statement is added to
+    current: BlockId,
+    /// The blocks after the loops that enclose the current statement, the targets of `break`
+    loop_exits: Vec<BlockId>,
+    /// The blocks that start at the labels, by the `BLOCK` that declares them and their name
+    labels: HashMap<(SyntaxNode, String), BlockId>,
+    /// The `goto` statements and the blocks they end
+    gotos: Vec<(BlockId, GotoStmt)>,
+}
+
+impl Builder {
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(BasicBlock::default());
+        BlockId(self.blocks.len() - 1)
+    }
+
+    fn edge(&mut self, from: BlockId, to: BlockId) {
+        if !self.blocks[from.0].successors.contains(&to) {
+            self.blocks[from.0].successors.push(to);
+            self.blocks[to.0].predecessors.push(from);
+        }
+    }
+
+    fn push(&mut self, node: &SyntaxNode) {
+        let current = self.current;
+        self.blocks[current.0].elements.push(node.clone());
+    }
+
+    /// Ends the current block with a jump to `target`. The statements after the jump start a new
+    /// block, which has no predecessors unless a label starts it.
+    fn jump(&mut self, node: &SyntaxNode, target: Option<BlockId>) {
+        self.push(node);
+        if let Some(target) = target {
+            let current = self.current;
+            self.edge(current, target);
+        }
+        self.current = self.new_block();
+    }
+
+    /// Continues in a new block that follows the current one.
+    fn next_block(&mut self) -> BlockId {
+        let next = self.new_block();
+        let current = self.current;
+        self.edge(current, next);
+        self.current = next;
+        next
+    }
+
+    /// Adds the statements of `block`. `exit` is the exit block of the function.
+    fn block(&mut self, block: &Block, exit: BlockId) {
+        for stmt in block.statements() {
+            self.stmt(&stmt, block, exit);
+        }
+    }
+
+    fn opt_block(&mut self, block: Option<Block>, exit: BlockId) {
+        if let Some(block) = block {
+            self.block(&block, exit);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, parent: &Block, exit: BlockId) {
+        match stmt {
+            Stmt::ReturnStmt(stmt) => self.jump(stmt.syntax(), Some(exit)),
+            Stmt::BreakStmt(stmt) => {
+                let target = self.loop_exits.last().copied();
+                self.jump(stmt.syntax(), target);
+            }
+            Stmt::GotoStmt(stmt) => {
+                self.push(stmt.syntax());
+                self.gotos.push((self.current, stmt.clone()));
+                self.current = self.new_block();
+            }
+            Stmt::LabelStmt(stmt) => {
+                let target = self.next_block();
+                if let Some(name) = stmt.name() {
+                    let key = (parent.syntax().clone(), name.syntax().text().to_string());
+                    self.labels.insert(key, target);
+                }
+            }
+            Stmt::DoStmt(stmt) => self.opt_block(stmt.block(), exit),
+            Stmt::IfStmt(stmt) => {
+                let mut ends = Vec::new();
+                let mut condition = stmt.condition();
+                let mut block = stmt.block();
+                let mut clauses = stmt.else_if_clauses();
+                loop {
+                    if let Some(condition) = &condition {
+                        self.push(condition.syntax());
+                    }
+                    let test = self.current;
+                    self.next_block();
+                    self.opt_block(block, exit);
+                    ends.push(self.current);
+                    self.current = test;
+                    match clauses.next() {
+                        Some(clause) => {
+                            self.next_block();
+                            condition = clause.condition();
+                            block = clause.block();
+                        }
+                        None => break,
+                    }
+                }
+                if let Some(else_clause) = stmt.else_clause() {
+                    self.next_block();
+                    self.opt_block(else_clause.block(), exit);
+                    ends.push(self.current);
+                } else {
+                    ends.push(self.current);
+                }
+                let after = self.new_block();
+                for end in ends {
+                    self.edge(end, after);
+                }
+                self.current = after;
+            }
+            Stmt::WhileStmt(stmt) => {
+                let header = self.next_block();
+                if let Some(condition) = stmt.condition() {
+                    self.push(condition.syntax());
+                }
+                self.loop_body(header, stmt.block(), exit);
+            }
+            Stmt::RepeatStmt(stmt) => {
+                let after = self.new_block();
+                let body = self.next_block();
+                self.loop_exits.push(after);
+                self.opt_block(stmt.block(), exit);
+                self.loop_exits.pop();
+                // The condition is part of the body, it can see the locals of the body
+                if let Some(condition) = stmt.condition() {
+                    self.push(condition.syntax());
+                }
+                let end = self.current;
+                self.edge(end, body);
+                self.edge(end, after);
+                self.current = after;
+            }
+            Stmt::NumericForStmt(stmt) => {
+                for expr in [stmt.start(), stmt.end(), stmt.step()].iter().flatten() {
+                    self.push(expr.syntax());
+                }
+                let header = self.next_block();
+                if let Some(name) = stmt.name() {
+                    self.push(name.syntax());
+                }
+                self.loop_body(header, stmt.block(), exit);
+            }
+            Stmt::GenericForStmt(stmt) => {
+                if let Some(exprs) = stmt.expr_list() {
+                    self.push(exprs.syntax());
+                }
+                let header = self.next_block();
+                if let Some(names) = stmt.name_list() {
+                    self.push(names.syntax());
+                }
+                self.loop_body(header, stmt.block(), exit);
+            }
+            Stmt::ExprStmt(_)
+            | Stmt::AssignStmt(_)
+            | Stmt::LocalAssignStmt(_)
+            | Stmt::LocalFunctionStmt(_)
+            | Stmt::FunctionStmt(_) => self.push(stmt.syntax()),
+        }
+    }
+
+    /// Adds the body of a loop whose `header` decides whether to run the body once more or to
+    /// continue after the loop.
+    fn loop_body(&mut self, header: BlockId, body: Option<Block>, exit: BlockId) {
+        let after = self.new_block();
+        self.edge(header, after);
+        self.next_block();
+        self.loop_exits.push(after);
+        self.opt_block(body, exit);
+        self.loop_exits.pop();
+        let end = self.current;
+        self.edge(end, header);
+        self.current = after;
+    }
+
+    /// Adds the edges of the `goto` statements to the labels they jump to. A label is visible in
+    /// the block that declares it and the blocks nested in it, but not in nested functions. A
+    /// `goto` without a visible label doesn't lead anywhere.
+    fn resolve_gotos(&mut self) {
+        for (from, goto) in std::mem::take(&mut self.gotos) {
+            let name = match goto.label() {
+                Some(name) => name.text().to_string(),
+                None => continue,
+            };
+            let target = goto
+                .syntax()
+                .ancestors()
+                .take_while(|node| !is_function(node))
+                .filter(|node| node.kind() == BLOCK)
+                .find_map(|block| self.labels.get(&(block, name.clone())).copied());
+            if let Some(target) = target {
+                self.edge(from, target);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Write;
+
+    /// Renders the reachable blocks of the graph of the first function in `text`, e.g.
+    /// `bb0: x = 1 -> bb1`.
+    fn render(text: &str) -> String {
+        let chunk = Chunk::parse(text).tree();
+        let graph = control_flow_graphs(&chunk).pop().unwrap();
+        let mut result = String::new();
+        for id in graph.blocks().filter(|&id| graph.is_reachable(id)) {
+            let elements: Vec<_> = graph
+                .block(id)
+                .elements()
+                .iter()
+                .map(|element| element.text().to_string())
+                .collect();
+            let successors: Vec<_> = graph
+                .successors(id)
+                .map(|successor| format!("bb{}", successor.0))
+                .collect();
+            writeln!(
+                result,
+                "bb{}: {} -> {}",
+                id.0,
+                elements.join("; "),
+                successors.join(", ")
+            )
+            .unwrap();
+        }
+        result
+    }
+
+    #[test]
+    fn test_if() {
+        assert_eq!(
+            render("if a then f() elseif b then g() else h() end i()"),
+            "bb0: a -> bb2, bb3\n\
+             bb1:  -> \n\
+             bb2: f() -> bb6\n\
+             bb3: b -> bb4, bb5\n\
+             bb4: g() -> bb6\n\
+             bb5: h() -> bb6\n\
+             bb6: i() -> bb1\n"
+        );
+        assert_eq!(
+            render("if a then f() end"),
+            "bb0: a -> bb2, bb3\n\
+             bb1:  -> \n\
+             bb2: f() -> bb3\n\
+             bb3:  -> bb1\n"
+        );
+    }
+
+    #[test]
+    fn test_loops() {
+        assert_eq!(
+            render("while a do f() end g()"),
+            "bb0:  -> bb2\n\
+             bb1:  -> \n\
+             bb2: a -> bb3, bb4\n\
+             bb3: g() -> bb1\n\
+             bb4: f() -> bb2\n"
+        );
+        assert_eq!(
+            render("repeat local x = f() until x"),
+            "bb0:  -> bb3\n\
+             bb1:  -> \n\
+             bb2:  -> bb1\n\
+             bb3: local x = f(); x -> bb3, bb2\n"
+        );
+        assert_eq!(
+            render("for i = 1, n do f(i) end"),
+            "bb0: 1; n -> bb2\n\
+             bb1:  -> \n\
+             bb2: i -> bb3, bb4\n\
+             bb3:  -> bb1\n\
+             bb4: f(i) -> bb2\n"
+        );
+        assert_eq!(
+            render("for k, v in pairs(t) do f(k) end"),
+            "bb0: pairs(t) -> bb2\n\
+             bb1:  -> \n\
+             bb2: k, v -> bb3, bb4\n\
+             bb3:  -> bb1\n\
+             bb4: f(k) -> bb2\n"
+        );
+    }
+
+    #[test]
+    fn test_break() {
+        assert_eq!(
+            render("while true do if a then break end f() end g()"),
+            "bb0:  -> bb2\n\
+             bb1:  -> \n\
+             bb2: true -> bb3, bb4\n\
+             bb3: g() -> bb1\n\
+             bb4: a -> bb5, bb7\n\
+             bb5: break -> bb3\n\
+             bb7: f() -> bb2\n"
+        );
+        // The `break` leaves the inner loop only
+        let text = "for i = 1, 2 do while a do break end f() end";
+        let chunk = Chunk::parse(text).tree();
+        let graph = control_flow_graph(chunk.syntax()).unwrap();
+        let node = |text: &str| {
+            chunk
+                .syntax()
+                .descendants()
+                .find(|node| node.kind() != BLOCK && node.text() == text)
+                .unwrap()
+        };
+        let break_block = graph.block_of(&node("break")).unwrap();
+        let after = graph.block_of(&node("f()")).unwrap();
+        assert_eq!(
+            graph.successors(break_block).collect::<Vec<_>>(),
+            vec![after]
+        );
+    }
+
+    #[test]
+    fn test_goto_and_labels() {
+        assert_eq!(
+            render("for i = 1, 2 do if a then goto continue end f() ::continue:: end"),
+            "bb0: 1; 2 -> bb2\n\
+             bb1:  -> \n\
+             bb2: i -> bb3, bb4\n\
+             bb3:  -> bb1\n\
+             bb4: a -> bb5, bb7\n\
+             bb5: goto continue -> bb8\n\
+             bb7: f() -> bb8\n\
+             bb8:  -> bb2\n"
+        );
+        // Code after a `goto` is only reachable through a label
+        assert_eq!(
+            render("goto a f() ::a:: g()"),
+            "bb0: goto a -> bb3\n\
+             bb1:  -> \n\
+             bb3: g() -> bb1\n"
+        );
+        assert_eq!(
+            render("::top:: f() goto top"),
+            "bb0:  -> bb2\n\
+             bb2: f(); goto top -> bb2\n"
+        );
+        // Labels in nested blocks and functions aren't visible
+        let entry_successors = |text: &str| {
+            let chunk = Chunk::parse(text).tree();
+            let graph = control_flow_graph(chunk.syntax()).unwrap();
+            graph.successors(graph.entry()).count()
+        };
+        assert_eq!(entry_successors("goto a do ::a:: end"), 0);
+        assert_eq!(entry_successors("goto a local f = function() ::a:: end"), 0);
+    }
+
+    #[test]
+    fn test_return() {
+        assert_eq!(
+            render("if a then return 1 end f() return 2"),
+            "bb0: a -> bb2, bb4\n\
+             bb1:  -> \n\
+             bb2: return 1 -> bb1\n\
+             bb4: f(); return 2 -> bb1\n"
+        );
+        let text = "local function f() return g() end h()";
+        let chunk = Chunk::parse(text).tree();
+        let graphs = control_flow_graphs(&chunk);
+        assert_eq!(graphs.len(), 2);
+        assert_eq!(graphs[1].function().kind(), LOCAL_FUNCTION_STMT);
+        let unreachable: Vec<_> = graphs[1]
+            .blocks()
+            .filter(|&id| !graphs[1].is_reachable(id))
+            .collect();
+        assert_eq!(unreachable.len(), 1);
+        assert!(graphs[1].block(unreachable[0]).elements().is_empty());
+
+        let call = chunk
+            .syntax()
+            .descendants()
+            .find(|node| node.text() == "g()")
+            .unwrap();
+        assert_eq!(graphs[0].block_of(&call), None);
+        assert!(graphs[1].block_of(&call).is_some());
+        assert_eq!(
+            graphs[1].predecessors(graphs[1].exit()).count(),
+            2,
+            "the return and the unreachable end of the function"
+        );
+    }
+}
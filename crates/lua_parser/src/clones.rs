@@ -0,0 +1,288 @@
+//! Detection of duplicated code.
+//!
+//! Statements are compared with `algo::structurally_equal`, so whitespace and comments don't
+//! matter but names and literals do. A clone is a sequence of consecutive statements of a block
+//! that occurs at least twice. Sequences are found by grouping the shortest sequences that reach
+//! the size threshold by their structural hash, and extending each group for as long as the
+//! following statements of every occurrence are equal as well.
+
+use crate::{
+    algo::{structural_hash, structurally_equal},
+    ast::Block,
+    AstNode, Chunk, SyntaxNode, TextRange,
+};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloneConfig {
+    /// The minimum number of tokens, excluding trivia, of a duplicated sequence of statements
+    pub min_tokens: usize,
+}
+
+impl Default for CloneConfig {
+    fn default() -> CloneConfig {
+        CloneConfig { min_tokens: 50 }
+    }
+}
+
+/// A sequence of statements that occurs in several places.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CloneGroup {
+    /// The occurrences, ordered by their chunk and position
+    pub occurrences: Vec<CloneOccurrence>,
+    /// The number of tokens of the sequence, excluding trivia
+    pub token_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CloneOccurrence {
+    /// The index of the chunk in the slice passed to `find_clones`
+    pub chunk: usize,
+    /// The range from the start of the first to the end of the last statement of the sequence
+    pub range: TextRange,
+}
+
+struct Statement {
+    node: SyntaxNode,
+    hash: u64,
+    token_count: usize,
+}
+
+struct StatementList {
+    chunk: usize,
+    statements: Vec<Statement>,
+}
+
+/// A sequence of statements `start..end` of the statement list at `list`.
+#[derive(Clone, Copy)]
+struct Sequence {
+    list: usize,
+    start: usize,
+    end: usize,
+}
+
+/// Finds the sequences of statements of at least `config.min_tokens` tokens that occur more than
+/// once in `chunks`. Only maximal sequences are reported, a clone that is part of a larger clone
+/// with the same occurrences is not.
+pub fn find_clones(chunks: &[Chunk], config: &CloneConfig) -> Vec<CloneGroup> {
+    let lists = statement_lists(chunks);
+
+    let mut seeds: HashMap<u64, Vec<Sequence>> = HashMap::new();
+    let mut order = Vec::new();
+    for (list_idx, list) in lists.iter().enumerate() {
+        let statements = &list.statements;
+        for start in 0..statements.len() {
+            let mut token_count = 0;
+            let end = (start..statements.len()).find(|&idx| {
+                token_count += statements[idx].token_count;
+                token_count >= config.min_tokens.max(1)
+            });
+            let end = match end {
+                Some(end) => end + 1,
+                None => break,
+            };
+            let mut hasher = DefaultHasher::new();
+            for statement in &statements[start..end] {
+                statement.hash.hash(&mut hasher);
+            }
+            let hash = hasher.finish();
+            let sequences = seeds.entry(hash).or_default();
+            if sequences.is_empty() {
+                order.push(hash);
+            }
+            sequences.push(Sequence {
+                list: list_idx,
+                start,
+                end,
+            });
+        }
+    }
+
+    let mut groups: Vec<CloneGroup> = Vec::new();
+    for hash in order {
+        let mut sequences = equal_non_overlapping(&lists, &seeds[&hash]);
+        if sequences.len() < 2 {
+            continue;
+        }
+        extend(&lists, &mut sequences);
+
+        let occurrences: Vec<_> = sequences
+            .iter()
+            .map(|sequence| CloneOccurrence {
+                chunk: lists[sequence.list].chunk,
+                range: sequence_range(&lists, *sequence),
+            })
+            .collect();
+        let is_reported = occurrences.iter().all(|occurrence| {
+            groups
+                .iter()
+                .flat_map(|group| &group.occurrences)
+                .any(|reported| {
+                    reported.chunk == occurrence.chunk
+                        && reported.range.contains_range(occurrence.range)
+                })
+        });
+        if is_reported {
+            continue;
+        }
+        let first = sequences[0];
+        groups.push(CloneGroup {
+            occurrences,
+            token_count: lists[first.list].statements[first.start..first.end]
+                .iter()
+                .map(|statement| statement.token_count)
+                .sum(),
+        });
+    }
+    groups
+}
+
+/// Collects the statements of every block in `chunks`.
+fn statement_lists(chunks: &[Chunk]) -> Vec<StatementList> {
+    let mut lists = Vec::new();
+    for (chunk_idx, chunk) in chunks.iter().enumerate() {
+        for block in chunk.syntax().descendants().filter_map(Block::cast) {
+            let statements = block
+                .statements()
+                .map(|statement| {
+                    let node = statement.syntax().clone();
+                    Statement {
+                        hash: structural_hash(&node),
+                        token_count: node
+                            .descendants_with_tokens()
+                            .filter_map(|element| element.into_token())
+                            .filter(|token| !token.kind().is_trivia())
+                            .count(),
+                        node,
+                    }
+                })
+                .collect();
+            lists.push(StatementList {
+                chunk: chunk_idx,
+                statements,
+            });
+        }
+    }
+    lists
+}
+
+/// Returns the sequences that are structurally equal to the first one, skipping sequences that
+/// overlap a preceding one. Sequences with the same hash are only different on a hash collision.
+fn equal_non_overlapping(lists: &[StatementList], sequences: &[Sequence]) -> Vec<Sequence> {
+    let first = sequences[0];
+    let mut result: Vec<Sequence> = Vec::new();
+    for &sequence in sequences {
+        let overlaps = result
+            .last()
+            .is_some_and(|last| last.list == sequence.list && last.end > sequence.start);
+        if overlaps {
+            continue;
+        }
+        let len = first.end - first.start;
+        let is_equal = sequence.end - sequence.start == len
+            && (0..len).all(|offset| {
+                structurally_equal(
+                    &lists[first.list].statements[first.start + offset].node,
+                    &lists[sequence.list].statements[sequence.start + offset].node,
+                )
+            });
+        if is_equal {
+            result.push(sequence);
+        }
+    }
+    result
+}
+
+/// Extends all sequences by the statements that follow them for as long as these are equal and
+/// the sequences don't overlap.
+fn extend(lists: &[StatementList], sequences: &mut [Sequence]) {
+    loop {
+        let first = sequences[0];
+        let next = match lists[first.list].statements.get(first.end) {
+            Some(next) => next,
+            None => return,
+        };
+        let can_extend = sequences.iter().enumerate().all(|(idx, sequence)| {
+            let statement = match lists[sequence.list].statements.get(sequence.end) {
+                Some(statement) => statement,
+                None => return false,
+            };
+            let overlaps = sequences.get(idx + 1).is_some_and(|following| {
+                following.list == sequence.list && following.start <= sequence.end
+            });
+            !overlaps
+                && statement.hash == next.hash
+                && structurally_equal(&statement.node, &next.node)
+        });
+        if !can_extend {
+            return;
+        }
+        for sequence in sequences.iter_mut() {
+            sequence.end += 1;
+        }
+    }
+}
+
+fn sequence_range(lists: &[StatementList], sequence: Sequence) -> TextRange {
+    let statements = &lists[sequence.list].statements;
+    statements[sequence.start]
+        .node
+        .text_range()
+        .cover(statements[sequence.end - 1].node.text_range())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clones(texts: &[&str], min_tokens: usize) -> Vec<Vec<(usize, String)>> {
+        let chunks: Vec<_> = texts.iter().map(|text| Chunk::parse(text).tree()).collect();
+        find_clones(&chunks, &CloneConfig { min_tokens })
+            .into_iter()
+            .map(|group| {
+                group
+                    .occurrences
+                    .iter()
+                    .map(|occurrence| {
+                        (
+                            occurrence.chunk,
+                            texts[occurrence.chunk][occurrence.range].to_string(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_clones() {
+        let a = "local x = 1\nprint(x) -- a\nx = x + 1\nlocal y = 2";
+        let b = "function f()\n  local x = 1\n  print( x )\n  x = x + 1\nend";
+        assert_eq!(
+            clones(&[a, b], 8),
+            vec![vec![
+                (0, "local x = 1\nprint(x) -- a\nx = x + 1".to_string()),
+                (1, "local x = 1\n  print( x )\n  x = x + 1".to_string()),
+            ]]
+        );
+        assert_eq!(clones(&[a, b], 100), Vec::<Vec<_>>::new());
+    }
+
+    #[test]
+    fn test_repeated_statements() {
+        // The occurrences of `f(1)` can't be extended without overlapping each other, and
+        // `g() g()` only occurs once.
+        let text = "f(1) f(1) f(1) g() g()";
+        assert_eq!(
+            clones(&[text], 4),
+            vec![vec![
+                (0, "f(1)".to_string()),
+                (0, "f(1)".to_string()),
+                (0, "f(1)".to_string())
+            ]]
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! Detects language features that are not available in every version of Lua, so code can be
+//! checked against the `Dialect` it targets.
+
+use crate::{
+    blocks::BlockTracker,
+    validation::{significant_tokens, SignificantToken},
+    Diagnostic, DiagnosticCode, Dialect,
+    SyntaxKind::*,
+    TextRange, TextSize,
+};
+use std::fmt;
+
+pub const UNAVAILABLE_FEATURE: DiagnosticCode = DiagnosticCode("E0003");
+pub const REMOVED_FEATURE: DiagnosticCode = DiagnosticCode("W0002");
+
+/// A language feature that was introduced or removed in a specific version of Lua.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LanguageFeature {
+    /// `goto name`
+    Goto,
+    /// `::name::`
+    Label,
+    /// `\xXX` escape sequences in strings
+    HexEscape,
+    /// `\z` escape sequences in strings
+    SkipWhitespaceEscape,
+    /// The `//` operator
+    IntegerDivision,
+    /// The `&`, `|`, `~`, `<<` and `>>` operators
+    BitwiseOperator,
+    /// `\u{XXX}` escape sequences in strings
+    UnicodeEscape,
+    /// `local x <const>` and `local x <close>`
+    VariableAttribute,
+    /// The implicit `arg` table of vararg functions
+    ImplicitArgTable,
+    /// `goto` as the name of a variable or field, e.g. `goto = 1`, before it became a keyword
+    GotoAsName,
+}
+
+impl LanguageFeature {
+    /// Returns the first version of Lua that supports this feature.
+    pub fn introduced_in(self) -> Dialect {
+        use self::LanguageFeature::*;
+        match self {
+            ImplicitArgTable | GotoAsName => Dialect::Lua51,
+            Goto | Label | HexEscape | SkipWhitespaceEscape => Dialect::Lua52,
+            IntegerDivision | BitwiseOperator | UnicodeEscape => Dialect::Lua53,
+            VariableAttribute => Dialect::Lua54,
+        }
+    }
+
+    /// Returns the first version of Lua that no longer supports this feature, if any.
+    pub fn removed_in(self) -> Option<Dialect> {
+        match self {
+            LanguageFeature::ImplicitArgTable | LanguageFeature::GotoAsName => Some(Dialect::Lua52),
+            _ => None,
+        }
+    }
+
+    pub fn is_available_in(self, dialect: Dialect) -> bool {
+        dialect >= self.introduced_in()
+            && self.removed_in().is_none_or(|removed| dialect < removed)
+    }
+}
+
+impl fmt::Display for LanguageFeature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::LanguageFeature::*;
+        let description = match self {
+            Goto => "`goto` statement",
+            Label => "label",
+            HexEscape => "hexadecimal escape",
+            SkipWhitespaceEscape => "`\\z` escape",
+            IntegerDivision => "integer division",
+            BitwiseOperator => "bitwise operator",
+            UnicodeEscape => "unicode escape",
+            VariableAttribute => "variable attribute",
+            ImplicitArgTable => "implicit `arg` table",
+            GotoAsName => "`goto` as a name",
+        };
+        f.write_str(description)
+    }
+}
+
+/// An occurrence of a version specific `LanguageFeature` in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FeatureUsage {
+    pub feature: LanguageFeature,
+    pub range: TextRange,
+}
+
+/// Returns all usages of version specific features in `text`, ordered by their position.
+pub fn find_feature_usages(text: &str) -> Vec<FeatureUsage> {
+    let tokens = significant_tokens(text);
+    let mut usages = Vec::new();
+    let mut blocks = BlockTracker::default();
+    for (idx, token) in tokens.iter().enumerate() {
+        let next = tokens.get(idx + 1);
+        let is_joint = |kind| {
+            next.is_some_and(|next| {
+                next.kind == kind && next.range.start() == token.range.end()
+            })
+        };
+        let span = |len: usize| {
+            TextRange::new(token.range.start(), tokens[idx + len - 1].range.end())
+        };
+        let feature = match token.kind {
+            GOTO_KW if next.is_some_and(|next| next.kind == IDENT) => {
+                Some((LanguageFeature::Goto, token.range))
+            }
+            GOTO_KW => Some((LanguageFeature::GotoAsName, token.range)),
+            COLON if is_joint(COLON) && is_label(&tokens, idx) => {
+                Some((LanguageFeature::Label, span(5)))
+            }
+            SLASH if is_joint(SLASH) => Some((LanguageFeature::IntegerDivision, span(2))),
+            LT if is_joint(LT) => Some((LanguageFeature::BitwiseOperator, span(2))),
+            GT if is_joint(GT) => Some((LanguageFeature::BitwiseOperator, span(2))),
+            AMP | PIPE | TILDE => Some((LanguageFeature::BitwiseOperator, token.range)),
+            LT if is_variable_attribute(text, &tokens, idx) => {
+                Some((LanguageFeature::VariableAttribute, span(3)))
+            }
+            STRING => {
+                let escapes = escape_features(&text[token.range], token.range.start());
+                usages.extend(
+                    escapes
+                        .into_iter()
+                        .map(|(feature, range)| FeatureUsage { feature, range }),
+                );
+                None
+            }
+            IDENT
+                if &text[token.range] == "arg"
+                    && in_implicit_arg_scope(text, &tokens, &blocks) =>
+            {
+                let is_field =
+                    idx > 0 && (tokens[idx - 1].kind == DOT || tokens[idx - 1].kind == COLON);
+                if is_field {
+                    None
+                } else {
+                    Some((LanguageFeature::ImplicitArgTable, token.range))
+                }
+            }
+            _ => None,
+        };
+        if let Some((feature, range)) = feature {
+            usages.push(FeatureUsage { feature, range });
+        }
+        blocks.visit(idx, token.kind);
+    }
+    usages.sort_by_key(|usage| usage.range.start());
+    usages.dedup();
+    usages
+}
+
+/// Returns the oldest version of Lua which supports all features used in `text`.
+pub fn minimum_dialect(text: &str) -> Dialect {
+    find_feature_usages(text)
+        .into_iter()
+        .map(|usage| usage.feature.introduced_in())
+        .max()
+        .unwrap_or(Dialect::Lua51)
+}
+
+/// Reports every usage of a feature that is not supported by `dialect`.
+pub fn check_compatibility(text: &str, dialect: Dialect) -> Vec<Diagnostic> {
+    find_feature_usages(text)
+        .into_iter()
+        .filter(|usage| !usage.feature.is_available_in(dialect))
+        .map(|usage| match usage.feature.removed_in() {
+            // Unlike the `arg` table, `goto` as a name doesn't even parse in later versions
+            Some(removed) if dialect >= removed && usage.feature == LanguageFeature::GotoAsName => {
+                Diagnostic::error(
+                    UNAVAILABLE_FEATURE,
+                    usage.range,
+                    format!("{} is not available since {}", usage.feature, removed),
+                )
+            }
+            Some(removed) if dialect >= removed => Diagnostic::warning(
+                REMOVED_FEATURE,
+                usage.range,
+                format!("{} is not available since {}", usage.feature, removed),
+            ),
+            _ => Diagnostic::error(
+                UNAVAILABLE_FEATURE,
+                usage.range,
+                format!(
+                    "{} requires {} or later (targeting {})",
+                    usage.feature,
+                    usage.feature.introduced_in(),
+                    dialect
+                ),
+            ),
+        })
+        .collect()
+}
+
+/// Returns true if the colon at `idx` starts a `::name::` label.
+fn is_label(tokens: &[SignificantToken], idx: usize) -> bool {
+    let kinds: Vec<_> = tokens[idx..].iter().take(5).map(|t| t.kind).collect();
+    kinds == [COLON, COLON, IDENT, COLON, COLON]
+        && tokens[idx + 4].range.start() == tokens[idx + 3].range.end()
+}
+
+/// Returns true if the `<` at `idx` starts an attribute of a local variable, e.g. `<const>`.
+fn is_variable_attribute(text: &str, tokens: &[SignificantToken], idx: usize) -> bool {
+    let is_attribute = tokens.get(idx + 1).is_some_and(|name| {
+        name.kind == IDENT && (&text[name.range] == "const" || &text[name.range] == "close")
+    }) && tokens.get(idx + 2).is_some_and(|t| t.kind == GT);
+    if !is_attribute {
+        return false;
+    }
+    tokens[..idx]
+        .iter()
+        .rev()
+        .find(|t| !(t.kind == IDENT || t.kind == COMMA || t.kind == LT || t.kind == GT))
+        .is_some_and(|t| t.kind == LOCAL_KW)
+}
+
+/// Returns the escape sequences in a quoted string that are not available in every version.
+fn escape_features(text: &str, offset: TextSize) -> Vec<(LanguageFeature, TextRange)> {
+    let mut result = Vec::new();
+    if !(text.starts_with('"') || text.starts_with('\'')) {
+        return result;
+    }
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        if c != '\\' {
+            continue;
+        }
+        let start = offset + TextSize::from(idx as u32);
+        let feature = match chars.next() {
+            Some((_, 'x')) => LanguageFeature::HexEscape,
+            Some((_, 'z')) => LanguageFeature::SkipWhitespaceEscape,
+            Some((_, 'u')) if chars.peek().map(|&(_, c)| c) == Some('{') => {
+                LanguageFeature::UnicodeEscape
+            }
+            _ => continue,
+        };
+        let end = match chars.peek() {
+            Some(&(idx, _)) => offset + TextSize::from(idx as u32),
+            None => offset + TextSize::of(text),
+        };
+        result.push((feature, TextRange::new(start, end)));
+    }
+    result
+}
+
+/// Returns true if the innermost enclosing function has an implicit `arg` table.
+fn in_implicit_arg_scope(text: &str, tokens: &[SignificantToken], blocks: &BlockTracker) -> bool {
+    blocks
+        .open_blocks()
+        .iter()
+        .rev()
+        .find(|&&open| tokens[open].kind == FUNCTION_KW)
+        .is_some_and(|&open| has_implicit_arg(text, tokens, open))
+}
+
+/// Returns true if the function starting at `idx` is a vararg function which does not declare a
+/// parameter named `arg`.
+fn has_implicit_arg(text: &str, tokens: &[SignificantToken], idx: usize) -> bool {
+    let params = tokens[idx..]
+        .iter()
+        .skip_while(|t| t.kind != L_PAREN)
+        .skip(1)
+        .take_while(|t| t.kind != R_PAREN);
+    let mut dots = 0;
+    for param in params {
+        match param.kind {
+            DOT => dots += 1,
+            IDENT if &text[param.range] == "arg" => return false,
+            _ => (),
+        }
+    }
+    dots == 3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(text: &str) -> Vec<LanguageFeature> {
+        find_feature_usages(text).into_iter().map(|u| u.feature).collect()
+    }
+
+    #[test]
+    fn test_find_feature_usages() {
+        use super::LanguageFeature::*;
+        assert_eq!(features("goto done ::done::"), vec![Goto, Label]);
+        assert_eq!(features("local goto = 1 t.goto(goto)"), vec![GotoAsName; 3]);
+        assert_eq!(features("x = a // b"), vec![IntegerDivision]);
+        assert_eq!(features("x = a / /b"), vec![]);
+        assert_eq!(features("x = a << 1 | b & ~c ~= d"), vec![BitwiseOperator; 4]);
+        assert_eq!(
+            features(r#"s = "\x41\z  \u{48}""#),
+            vec![HexEscape, SkipWhitespaceEscape, UnicodeEscape]
+        );
+        assert_eq!(features("s = [[\\x41]]"), vec![]);
+        assert_eq!(
+            features("local x <const>, y <close> = 1"),
+            vec![VariableAttribute; 2]
+        );
+        assert_eq!(
+            features("function f(...) return arg.n, t.arg end"),
+            vec![ImplicitArgTable]
+        );
+        assert_eq!(features("function f(arg, ...) return arg end"), vec![]);
+        assert_eq!(
+            features("function f(...) for i = 1, 2 do end end return arg"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_minimum_dialect() {
+        assert_eq!(minimum_dialect("print(1)"), Dialect::Lua51);
+        assert_eq!(minimum_dialect("goto x ::x::"), Dialect::Lua52);
+        assert_eq!(minimum_dialect("goto x ::x:: return 1 // 2"), Dialect::Lua53);
+    }
+
+    #[test]
+    fn test_check_compatibility() {
+        let diagnostics = check_compatibility("x = 7 // 2", Dialect::Lua52);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "integer division requires Lua 5.3 or later (targeting Lua 5.2)"
+        );
+        assert!(check_compatibility("x = 7 // 2", Dialect::Lua53).is_empty());
+
+        let diagnostics = check_compatibility("function f(...) return arg end", Dialect::Lua53);
+        assert_eq!(diagnostics[0].code, REMOVED_FEATURE);
+        assert!(check_compatibility("function f(...) return arg end", Dialect::Lua51).is_empty());
+
+        let diagnostics = check_compatibility("goto = 1", Dialect::Lua54);
+        assert_eq!(diagnostics[0].code, UNAVAILABLE_FEATURE);
+        assert_eq!(
+            diagnostics[0].message,
+            "`goto` as a name is not available since Lua 5.2"
+        );
+        assert!(check_compatibility("goto = 1", Dialect::Lua51).is_empty());
+    }
+}
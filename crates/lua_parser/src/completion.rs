@@ -0,0 +1,230 @@
+//! Classifies the position of the cursor for code completion.
+//!
+//! Completion engines need to know what kind of code can be typed at the cursor, e.g. a statement,
+//! an expression or the name of a field. The classification is based on the token in front of the
+//! cursor and the node that contains it, which also works for the incomplete code that is typed
+//! while completions are requested.
+
+use crate::{
+    precedence::{binary_operator, unary_operator},
+    AstNode, Chunk,
+    SyntaxKind::{self, *},
+    SyntaxNode, SyntaxToken, TextRange, TextSize,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompletionKind {
+    /// Where a statement can start, e.g. at the start of a block or after a complete statement
+    Statement,
+    /// Where an expression is expected, e.g. after `=`, `return` or an operator
+    Expr,
+    /// After a complete expression that doesn't complete a statement, where only operators and
+    /// keywords like `then` or `do` are valid, e.g. in `if x |`
+    AfterExpr,
+    /// The name of a field after `.`, with the range of the expression or function name in front
+    /// of the `.`
+    Field { receiver: TextRange },
+    /// The name of a method after `:`, with the range of the expression or function name in front
+    /// of the `:`
+    Method { receiver: TextRange },
+    /// A field of a table constructor, where both a key (`name =`) and a value are valid
+    TableField,
+    /// The name of a new local after `local` or `for`, `local function` is valid too
+    Declaration,
+    /// The name of a parameter
+    Param,
+    /// The name of a function after `function`, which is assigned to an existing variable
+    FunctionName,
+    /// The name of a label after `goto` or `::`
+    Label,
+    /// The attribute of a local after `<`, i.e. `const` or `close`
+    Attribute,
+    /// Inside a string, a comment or a number, where nothing can be completed
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CompletionContext {
+    pub kind: CompletionKind,
+    /// The range of the name or keyword the cursor is in or right after, which a completion
+    /// replaces. Empty if the cursor isn't in a name.
+    pub replace_range: TextRange,
+}
+
+/// Reports what can be typed at `offset` in `chunk`.
+pub fn completion_context(chunk: &Chunk, offset: TextSize) -> CompletionContext {
+    let root = chunk.syntax();
+    let mut replace_range = TextRange::empty(offset);
+    if let Some(token) = root.token_at_offset(offset).left_biased() {
+        let inside = token.text_range().start() < offset && offset < token.text_range().end();
+        let in_line_comment = token.kind() == COMMENT && !token.text().starts_with("--[");
+        let in_literal = (token.kind().is_literal() && token.kind() != DOTDOTDOT)
+            || ((inside || in_line_comment) && matches!(token.kind(), STRING | COMMENT));
+        if in_literal {
+            return CompletionContext {
+                kind: CompletionKind::None,
+                replace_range,
+            };
+        }
+        if token.kind() == IDENT || token.kind().is_keyword() {
+            replace_range = TextRange::new(token.text_range().start(), offset);
+        }
+    }
+
+    let prev = root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .take_while(|token| token.text_range().end() <= replace_range.start())
+        .filter(|token| !token.kind().is_trivia())
+        .last();
+    let kind = match prev {
+        Some(prev) => classify(&prev),
+        None => CompletionKind::Statement,
+    };
+    CompletionContext {
+        kind,
+        replace_range,
+    }
+}
+
+/// Tokens after which a statement can start.
+const BEFORE_STATEMENT: &[SyntaxKind] = &[THEN_KW, DO_KW, ELSE_KW, REPEAT_KW];
+
+/// Tokens after which an expression is expected, besides the operators.
+const BEFORE_EXPR: &[SyntaxKind] = &[
+    EQ, RETURN_KW, IF_KW, ELSEIF_KW, WHILE_KW, UNTIL_KW, IN_KW, L_BRACKET,
+];
+
+fn classify(prev: &SyntaxToken) -> CompletionKind {
+    let parent = prev.parent().map(|parent| parent.kind());
+    let kind = prev.kind();
+    match kind {
+        DOT => CompletionKind::Field {
+            receiver: receiver(prev),
+        },
+        COLON => CompletionKind::Method {
+            receiver: receiver(prev),
+        },
+        GOTO_KW => CompletionKind::Label,
+        COLONCOLON if is_first_token(prev) => CompletionKind::Label,
+        LOCAL_KW | FOR_KW => CompletionKind::Declaration,
+        FUNCTION_KW if parent == Some(LOCAL_FUNCTION_STMT) => CompletionKind::Declaration,
+        FUNCTION_KW if parent == Some(FUNCTION_STMT) => CompletionKind::FunctionName,
+        LT if parent == Some(ATTRIBUTE) => CompletionKind::Attribute,
+        L_PAREN | COMMA if parent == Some(PARAM_LIST) => CompletionKind::Param,
+        COMMA if parent == Some(NAME_LIST) => CompletionKind::Declaration,
+        L_CURLY | COMMA | SEMI if parent == Some(TABLE_EXPR) => CompletionKind::TableField,
+        R_PAREN if parent == Some(PARAM_LIST) => CompletionKind::Statement,
+        L_PAREN | COMMA => CompletionKind::Expr,
+        SEMI => CompletionKind::Statement,
+        _ if BEFORE_STATEMENT.contains(&kind) => CompletionKind::Statement,
+        _ if BEFORE_EXPR.contains(&kind) => CompletionKind::Expr,
+        _ if binary_operator(kind).is_some() || unary_operator(kind).is_some() => {
+            CompletionKind::Expr
+        }
+        _ if ends_statement(prev) => CompletionKind::Statement,
+        _ => CompletionKind::AfterExpr,
+    }
+}
+
+/// Returns the range of the expression or the part of a function name in front of `.` or `:`.
+fn receiver(separator: &SyntaxToken) -> TextRange {
+    let parent = match separator.parent() {
+        Some(parent) => parent,
+        None => return TextRange::empty(separator.text_range().start()),
+    };
+    let start = match parent.kind() {
+        FUNCTION_NAME => parent.text_range().start(),
+        _ => parent
+            .first_child()
+            .map_or(separator.text_range().start(), |base| {
+                base.text_range().start()
+            }),
+    };
+    TextRange::new(start, separator.text_range().start())
+}
+
+fn is_first_token(token: &SyntaxToken) -> bool {
+    token
+        .parent()
+        .and_then(|parent| parent.first_token())
+        .is_some_and(|first| &first == token)
+}
+
+/// Returns whether `token` is the last token of a statement.
+fn ends_statement(token: &SyntaxToken) -> bool {
+    let end = token.text_range().end();
+    token
+        .parent()
+        .into_iter()
+        .flat_map(|parent| parent.ancestors())
+        .take_while(|node: &SyntaxNode| node.text_range().end() == end)
+        .any(|node| node.parent().is_some_and(|parent| parent.kind() == BLOCK))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(text: &str) -> CompletionKind {
+        let offset = TextSize::from(text.find("$0").unwrap() as u32);
+        let text = text.replace("$0", "");
+        completion_context(&Chunk::parse(&text).tree(), offset).kind
+    }
+
+    #[test]
+    fn test_statements_and_exprs() {
+        use CompletionKind::*;
+        assert_eq!(context("$0"), Statement);
+        assert_eq!(context("if x then\n    pri$0"), Statement);
+        assert_eq!(context("f(x)\n$0"), Statement);
+        assert_eq!(context("function f() $0 end"), Statement);
+        assert_eq!(context("x = $0"), Expr);
+        assert_eq!(context("x = a + b$0"), Expr);
+        assert_eq!(context("f(a, $0)"), Expr);
+        assert_eq!(context("return not $0"), Expr);
+        assert_eq!(context("if x $0"), AfterExpr);
+        assert_eq!(context("x = 'a$0'"), None);
+        assert_eq!(context("-- com$0"), None);
+    }
+
+    #[test]
+    fn test_names() {
+        use CompletionKind::*;
+        assert_eq!(
+            context("x = t.a.$0"),
+            Field {
+                receiver: TextRange::new(4.into(), 7.into())
+            }
+        );
+        assert_eq!(
+            context("obj:me$0"),
+            Method {
+                receiver: TextRange::new(0.into(), 3.into())
+            }
+        );
+        assert_eq!(
+            context("function M.$0"),
+            Field {
+                receiver: TextRange::new(9.into(), 10.into())
+            }
+        );
+        assert_eq!(context("t = { $0 }"), TableField);
+        assert_eq!(context("t = { a = 1, $0"), TableField);
+        assert_eq!(context("local $0"), Declaration);
+        assert_eq!(context("for k, $0"), Declaration);
+        assert_eq!(context("local x <$0"), Attribute);
+        assert_eq!(context("local function f(a, $0"), Param);
+        assert_eq!(context("function $0"), FunctionName);
+        assert_eq!(context("goto $0"), Label);
+    }
+
+    #[test]
+    fn test_replace_range() {
+        let text = "x = pri";
+        let chunk = Chunk::parse(text).tree();
+        let context = completion_context(&chunk, 7.into());
+        assert_eq!(context.replace_range, TextRange::new(4.into(), 7.into()));
+        assert_eq!(context.kind, CompletionKind::Expr);
+    }
+}
@@ -0,0 +1,225 @@
+//! Reads the configuration files of luacheck and selene, `.luacheckrc` and `selene.toml`, so that
+//! projects that are set up for one of these linters are checked with the same rules.
+//!
+//! A `.luacheckrc` is a Lua file, of which the top-level `ignore` and `enable` assignments of
+//! tables of strings are read; other code, e.g. per-file settings in `files[...]`, is ignored.
+//! Of a `selene.toml` the levels of the rules in `[lints]` are read. Globals and `std` are not
+//! read, no check of this crate depends on the set of defined globals.
+//!
+//! Rules are matched against the codes of the diagnostics of this crate, e.g. `W0001` or `W`,
+//! see `Directives`. The numeric codes of luacheck, e.g. `211`, and the rule names of selene,
+//! e.g. `unused_variable`, have no equivalent among them; they are kept as they are and match no
+//! diagnostic.
+//!
+//! The module only parses the text of the files, finding and reading them is up to the caller.
+
+use crate::{
+    ast::{Expr, Stmt, TableField},
+    AstNode, Chunk, DirectiveOption, DirectiveTool, LintLevel,
+};
+use std::fmt;
+
+/// The name of the configuration file of luacheck.
+pub const LUACHECKRC: &str = ".luacheckrc";
+
+/// The name of the configuration file of selene.
+pub const SELENE_TOML: &str = "selene.toml";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError(String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The levels of the rules a project is checked with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintConfig {
+    /// The linter the configuration is read from, which determines how rules match codes, see
+    /// `Directives`
+    pub tool: DirectiveTool,
+    /// The levels of rules, later entries take precedence over earlier ones
+    pub rules: Vec<(String, LintLevel)>,
+}
+
+impl LintConfig {
+    fn new(tool: DirectiveTool) -> LintConfig {
+        LintConfig {
+            tool,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Reads the text of a `.luacheckrc`.
+    pub fn from_luacheckrc(text: &str) -> Result<LintConfig, ConfigError> {
+        let parse = Chunk::parse(text);
+        if let Some(error) = parse.errors().first() {
+            return Err(ConfigError(format!("invalid {}: {}", LUACHECKRC, error)));
+        }
+        let mut config = LintConfig::new(DirectiveTool::Luacheck);
+        let statements = parse
+            .tree()
+            .block()
+            .into_iter()
+            .flat_map(|block| block.statements());
+        for statement in statements {
+            let assign = match statement {
+                Stmt::AssignStmt(assign) => assign,
+                _ => continue,
+            };
+            let target = assign.targets().and_then(|targets| targets.exprs().next());
+            let value = assign.values().and_then(|values| values.exprs().next());
+            let (name, value) = match (target, value) {
+                (Some(Expr::NameRef(name)), Some(value)) => {
+                    (name.syntax().text().to_string(), value)
+                }
+                _ => continue,
+            };
+            match name.as_str() {
+                "ignore" => config.rules.extend(
+                    string_list(&value)
+                        .into_iter()
+                        .map(|rule| (rule, LintLevel::Allow)),
+                ),
+                "enable" => config.rules.extend(
+                    string_list(&value)
+                        .into_iter()
+                        .map(|rule| (rule, LintLevel::Warn)),
+                ),
+                _ => {}
+            }
+        }
+        Ok(config)
+    }
+
+    /// Reads the text of a `selene.toml`.
+    pub fn from_selene_toml(text: &str) -> Result<LintConfig, ConfigError> {
+        let document: toml_edit::DocumentMut = text
+            .parse()
+            .map_err(|error| ConfigError(format!("invalid {}: {}", SELENE_TOML, error)))?;
+        let mut config = LintConfig::new(DirectiveTool::Selene);
+        if let Some(lints) = document
+            .get("lints")
+            .and_then(|lints| lints.as_table_like())
+        {
+            for (rule, level) in lints.iter() {
+                let level = match level.as_str() {
+                    Some("allow") => LintLevel::Allow,
+                    Some("warn") => LintLevel::Warn,
+                    Some("deny") => LintLevel::Deny,
+                    _ => {
+                        return Err(ConfigError(format!(
+                            "invalid {}: the level of `{}` must be \"allow\", \"warn\" or \"deny\"",
+                            SELENE_TOML, rule
+                        )))
+                    }
+                };
+                config.rules.push((rule.to_string(), level));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Returns the settings as directive options, see `Directives::with_config`.
+    pub(crate) fn options(&self) -> Vec<DirectiveOption> {
+        self.rules
+            .iter()
+            .map(|(rule, level)| DirectiveOption::Lint {
+                level: *level,
+                rules: vec![rule.clone()],
+            })
+            .collect()
+    }
+}
+
+fn string_value(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Literal(literal) => Some(literal.value()?.as_str()?.into_owned()),
+        _ => None,
+    }
+}
+
+/// Returns the strings in a table, e.g. `{ "a", "b" }`.
+fn string_list(expr: &Expr) -> Vec<String> {
+    let table = match expr {
+        Expr::TableExpr(table) => table,
+        _ => return Vec::new(),
+    };
+    table
+        .fields()
+        .filter_map(|field| match field {
+            TableField::PositionalField(field) => {
+                field.value().and_then(|value| string_value(&value))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{comment_directives, TextSize};
+
+    #[test]
+    fn test_luacheckrc() {
+        let config = LintConfig::from_luacheckrc(
+            r#"
+std = "lua51+busted"
+globals = { "vim", awesome = { fields = { "emit" } }, ["client"] = {} }
+read_globals = { "jit" }
+ignore = { "W0001", "2" }
+enable = { "W0001" }
+files["spec/"] = { std = "+busted" }
+max_line_length = false
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.rules,
+            vec![
+                ("W0001".to_string(), LintLevel::Allow),
+                ("2".to_string(), LintLevel::Allow),
+                ("W0001".to_string(), LintLevel::Warn),
+            ]
+        );
+        assert!(LintConfig::from_luacheckrc("std = ").is_err());
+    }
+
+    #[test]
+    fn test_selene_toml() {
+        let config = LintConfig::from_selene_toml(
+            "std = \"lua51\"\n\n[lints]\nW0001 = \"allow\"\nW0002 = \"deny\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.rules,
+            vec![
+                ("W0001".to_string(), LintLevel::Allow),
+                ("W0002".to_string(), LintLevel::Deny),
+            ]
+        );
+        assert!(LintConfig::from_selene_toml("[lints]\nW0001 = \"never\"").is_err());
+        assert!(LintConfig::from_selene_toml("std = ").is_err());
+    }
+
+    #[test]
+    fn test_directives_with_config() {
+        let config = LintConfig::from_luacheckrc("ignore = { \"W\", \"211\" }").unwrap();
+        let text = "x = 1\n-- luacheck: enable W0001\ny = 2";
+        let directives = comment_directives(&Chunk::parse(text).tree()).with_config(&config);
+        assert_eq!(directives.lint_level("E0002", TextSize::from(0)), None);
+        assert_eq!(
+            directives.lint_level("W0001", TextSize::from(0)),
+            Some(LintLevel::Allow)
+        );
+        assert_eq!(
+            directives.lint_level("W0001", TextSize::of(text)),
+            Some(LintLevel::Warn)
+        );
+    }
+}
@@ -0,0 +1,415 @@
+//! Evaluation of constant expressions, and checks of constant expressions that behave
+//! unexpectedly with the integers of Lua 5.3 and 5.4: integer division and modulo by zero raise
+//! an error at runtime, and integer arithmetic wraps around on overflow instead of switching to
+//! floats like in earlier versions.
+
+use crate::{
+    ast::{BinExpr, Expr, Literal, LuaValue},
+    precedence::{BinOp, UnOp},
+    AstNode, Chunk, Diagnostic, DiagnosticCode, Dialect,
+    SyntaxKind::*,
+    SyntaxNode, TextRange, WalkEvent,
+};
+use std::{cmp::Ordering, collections::HashMap};
+
+pub const INTEGER_DIVISION_BY_ZERO: DiagnosticCode = DiagnosticCode("E0005");
+pub const INTEGER_OVERFLOW: DiagnosticCode = DiagnosticCode("W0007");
+
+/// Returns the value of a constant expression like Lua 5.4 computes it, e.g. `Integer(3)` for
+/// `1 + 2` and `Float(0.5)` for `1 / 2`. Returns `None` if the expression isn't constant, or if
+/// evaluating it raises an error, e.g. for `1 // 0` or `"a" + 1`.
+///
+/// Strings are not converted to numbers in arithmetic, and floats are not concatenated, because
+/// the results depend on the locale and the formatting of the Lua build.
+pub fn eval_constant(expr: &Expr) -> Option<LuaValue> {
+    eval_with(expr, |operand| eval_constant(&operand))
+}
+
+/// Evaluates `expr` from the values of its operands, which are returned by `operand`. The
+/// right-hand side of `and` and `or` is only evaluated if the result depends on it.
+fn eval_with(expr: &Expr, mut operand: impl FnMut(Expr) -> Option<LuaValue>) -> Option<LuaValue> {
+    match expr {
+        Expr::Literal(literal) => literal.value(),
+        Expr::ParenExpr(paren) => operand(paren.expr()?),
+        Expr::UnaryExpr(unary) => eval_unary(unary.op_kind()?, operand(unary.operand()?)?),
+        Expr::BinExpr(bin) => {
+            let lhs = operand(bin.lhs()?)?;
+            match bin.op_kind()? {
+                BinOp::And if !lhs.is_truthy() => Some(lhs),
+                BinOp::And => operand(bin.rhs()?),
+                BinOp::Or if lhs.is_truthy() => Some(lhs),
+                BinOp::Or => operand(bin.rhs()?),
+                op => eval_binary(op, lhs, operand(bin.rhs()?)?),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Reports constant integer divisions and modulos by zero, and constant integer arithmetic and
+/// hexadecimal integer literals that wrap around. Other dialects than Lua 5.3 and 5.4 only have
+/// floats, so nothing is reported for them.
+pub fn check_constants(chunk: &Chunk, dialect: Dialect) -> Vec<Diagnostic> {
+    if dialect < Dialect::Lua53 {
+        return Vec::new();
+    }
+    // Every expression is evaluated once, after its operands. The values of the operands are
+    // only kept until their parent expression is evaluated.
+    let mut values: HashMap<SyntaxNode, LuaValue> = HashMap::new();
+    let mut diagnostics = Vec::new();
+    for event in chunk.syntax().preorder() {
+        let expr = match event {
+            WalkEvent::Leave(node) => match Expr::cast(node) {
+                Some(expr) => expr,
+                None => continue,
+            },
+            WalkEvent::Enter(_) => continue,
+        };
+        let node = expr.syntax().clone();
+        match &expr {
+            Expr::BinExpr(bin) => diagnostics.extend(check_bin_expr(bin, &values)),
+            Expr::UnaryExpr(unary) => {
+                let operand = unary
+                    .operand()
+                    .and_then(|operand| values.get(operand.syntax()));
+                if let (Some(UnOp::Neg), Some(LuaValue::Integer(i64::MIN))) =
+                    (unary.op_kind(), operand)
+                {
+                    diagnostics.push(overflow(node.text_range()));
+                }
+            }
+            Expr::Literal(literal) if is_wrapping_hex_literal(literal) => {
+                diagnostics.push(Diagnostic::warning(
+                    INTEGER_OVERFLOW,
+                    node.text_range(),
+                    "hexadecimal integer wraps around",
+                ));
+            }
+            _ => (),
+        }
+
+        let value = eval_with(&expr, |operand| values.remove(operand.syntax()));
+        // The right-hand side of `and` and `or` is not always used
+        for child in node.children() {
+            values.remove(&child);
+        }
+        let is_operand = node
+            .parent()
+            .is_some_and(|parent| matches!(parent.kind(), PAREN_EXPR | UNARY_EXPR | BIN_EXPR));
+        if let (Some(value), true) = (value, is_operand) {
+            values.insert(node, value);
+        }
+    }
+    diagnostics
+}
+
+/// Checks a binary expression, given the values of the operands that are constant.
+fn check_bin_expr(bin: &BinExpr, values: &HashMap<SyntaxNode, LuaValue>) -> Option<Diagnostic> {
+    let op = bin.op_kind()?;
+    if !matches!(
+        op,
+        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::IntDiv | BinOp::Mod
+    ) {
+        return None;
+    }
+    let (lhs, rhs) = match (
+        values.get(bin.lhs()?.syntax())?,
+        values.get(bin.rhs()?.syntax())?,
+    ) {
+        (LuaValue::Integer(lhs), LuaValue::Integer(rhs)) => (*lhs, *rhs),
+        _ => return None,
+    };
+    let checked = match op {
+        BinOp::Add => lhs.checked_add(rhs),
+        BinOp::Sub => lhs.checked_sub(rhs),
+        BinOp::Mul => lhs.checked_mul(rhs),
+        BinOp::IntDiv | BinOp::Mod if rhs == 0 => {
+            let message = match op {
+                BinOp::IntDiv => "integer division by zero",
+                _ => "integer modulo by zero",
+            };
+            return Some(Diagnostic::error(
+                INTEGER_DIVISION_BY_ZERO,
+                bin.syntax().text_range(),
+                message,
+            ));
+        }
+        BinOp::IntDiv => lhs.checked_div(rhs),
+        _ => Some(0),
+    };
+    match checked {
+        Some(_) => None,
+        None => Some(overflow(bin.syntax().text_range())),
+    }
+}
+
+fn overflow(range: TextRange) -> Diagnostic {
+    Diagnostic::warning(
+        INTEGER_OVERFLOW,
+        range,
+        "integer overflow, the result wraps around",
+    )
+}
+
+/// Returns true for hexadecimal integers that don't fit in 64 bits, e.g. `0x10000000000000000`,
+/// which Lua reads modulo 2^64.
+fn is_wrapping_hex_literal(literal: &Literal) -> bool {
+    let token = match literal.token() {
+        Some(token) if token.kind() == INT_NUMBER => token,
+        _ => return false,
+    };
+    let text = token.text();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(digits) => digits.trim_start_matches('0').len() > 16,
+        None => false,
+    }
+}
+
+fn eval_unary(op: UnOp, operand: LuaValue) -> Option<LuaValue> {
+    let value = match (op, operand) {
+        (UnOp::Not, operand) => LuaValue::Boolean(!operand.is_truthy()),
+        (UnOp::Neg, LuaValue::Integer(i)) => LuaValue::Integer(i.wrapping_neg()),
+        (UnOp::Neg, LuaValue::Float(f)) => LuaValue::Float(-f),
+        (UnOp::Len, LuaValue::String(s)) => LuaValue::Integer(s.len() as i64),
+        (UnOp::BitNot, operand) => LuaValue::Integer(!to_integer(&operand)?),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn eval_binary(op: BinOp, lhs: LuaValue, rhs: LuaValue) -> Option<LuaValue> {
+    use LuaValue::{Float, Integer};
+
+    let value = match op {
+        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::IntDiv | BinOp::Mod => match (lhs, rhs) {
+            (Integer(a), Integer(b)) => Integer(match op {
+                BinOp::Add => a.wrapping_add(b),
+                BinOp::Sub => a.wrapping_sub(b),
+                BinOp::Mul => a.wrapping_mul(b),
+                BinOp::IntDiv => floor_div(a, b)?,
+                _ => floor_mod(a, b)?,
+            }),
+            (a, b) => {
+                let (a, b) = (to_float(&a)?, to_float(&b)?);
+                Float(match op {
+                    BinOp::Add => a + b,
+                    BinOp::Sub => a - b,
+                    BinOp::Mul => a * b,
+                    BinOp::IntDiv => (a / b).floor(),
+                    _ => float_mod(a, b),
+                })
+            }
+        },
+        BinOp::Div => Float(to_float(&lhs)? / to_float(&rhs)?),
+        BinOp::Pow => Float(to_float(&lhs)?.powf(to_float(&rhs)?)),
+        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr => {
+            let (a, b) = (to_integer(&lhs)?, to_integer(&rhs)?);
+            Integer(match op {
+                BinOp::BitAnd => a & b,
+                BinOp::BitOr => a | b,
+                BinOp::BitXor => a ^ b,
+                BinOp::Shl => shift_left(a, b),
+                _ => shift_left(a, b.wrapping_neg()),
+            })
+        }
+        BinOp::Concat => {
+            let mut bytes = concat_operand(lhs)?;
+            bytes.extend(concat_operand(rhs)?);
+            LuaValue::String(bytes)
+        }
+        BinOp::Eq => LuaValue::Boolean(compare(&lhs, &rhs) == Some(Ordering::Equal)),
+        BinOp::NotEq => LuaValue::Boolean(compare(&lhs, &rhs) != Some(Ordering::Equal)),
+        BinOp::Lt | BinOp::Gt | BinOp::LtEq | BinOp::GtEq => {
+            if !is_ordered(&lhs, &rhs) {
+                return None;
+            }
+            let ordering = compare(&lhs, &rhs);
+            LuaValue::Boolean(match op {
+                BinOp::Lt => ordering == Some(Ordering::Less),
+                BinOp::Gt => ordering == Some(Ordering::Greater),
+                BinOp::LtEq => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                _ => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            })
+        }
+        BinOp::And | BinOp::Or => return None,
+    };
+    Some(value)
+}
+
+/// Floor division of integers, which raises an error when dividing by zero.
+fn floor_div(a: i64, b: i64) -> Option<i64> {
+    if b == 0 {
+        return None;
+    }
+    let quotient = a.wrapping_div(b);
+    if a.wrapping_rem(b) != 0 && (a ^ b) < 0 {
+        Some(quotient - 1)
+    } else {
+        Some(quotient)
+    }
+}
+
+/// The modulo of integers, with the sign of the divisor. Raises an error for a zero divisor.
+fn floor_mod(a: i64, b: i64) -> Option<i64> {
+    if b == 0 {
+        return None;
+    }
+    let remainder = a.wrapping_rem(b);
+    if remainder != 0 && (remainder ^ b) < 0 {
+        Some(remainder + b)
+    } else {
+        Some(remainder)
+    }
+}
+
+fn float_mod(a: f64, b: f64) -> f64 {
+    let remainder = a % b;
+    if remainder != 0.0 && (remainder < 0.0) != (b < 0.0) {
+        remainder + b
+    } else {
+        remainder
+    }
+}
+
+/// Shifts `a` left by `b` bits, or right if `b` is negative. Bits shifted out are discarded,
+/// shifts of 64 bits or more result in zero.
+fn shift_left(a: i64, b: i64) -> i64 {
+    if b <= -64 || b >= 64 {
+        0
+    } else if b >= 0 {
+        ((a as u64) << b) as i64
+    } else {
+        ((a as u64) >> -b) as i64
+    }
+}
+
+fn to_float(value: &LuaValue) -> Option<f64> {
+    match value {
+        LuaValue::Integer(i) => Some(*i as f64),
+        LuaValue::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+/// Converts a number to an integer for the bitwise operators, which only accept floats with an
+/// exact integer representation.
+fn to_integer(value: &LuaValue) -> Option<i64> {
+    match value {
+        LuaValue::Integer(i) => Some(*i),
+        LuaValue::Float(f) if f.fract() == 0.0 && *f >= -(2f64.powi(63)) && *f < 2f64.powi(63) => {
+            Some(*f as i64)
+        }
+        _ => None,
+    }
+}
+
+fn concat_operand(value: LuaValue) -> Option<Vec<u8>> {
+    match value {
+        LuaValue::String(bytes) => Some(bytes),
+        LuaValue::Integer(i) => Some(i.to_string().into_bytes()),
+        _ => None,
+    }
+}
+
+/// Returns true if `<` and friends can compare the values without raising an error.
+fn is_ordered(lhs: &LuaValue, rhs: &LuaValue) -> bool {
+    matches!(
+        (lhs, rhs),
+        (LuaValue::String(_), LuaValue::String(_))
+            | (
+                LuaValue::Integer(_) | LuaValue::Float(_),
+                LuaValue::Integer(_) | LuaValue::Float(_)
+            )
+    )
+}
+
+/// Compares two values, returns `None` if they are of different types or one of them is NaN.
+fn compare(lhs: &LuaValue, rhs: &LuaValue) -> Option<Ordering> {
+    match (lhs, rhs) {
+        (LuaValue::Integer(a), LuaValue::Integer(b)) => Some(a.cmp(b)),
+        (LuaValue::String(a), LuaValue::String(b)) => Some(a.cmp(b)),
+        (LuaValue::Nil, LuaValue::Nil) => Some(Ordering::Equal),
+        (LuaValue::Boolean(a), LuaValue::Boolean(b)) if a == b => Some(Ordering::Equal),
+        (a, b) => to_float(a)?.partial_cmp(&to_float(b)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(text: &str) -> Option<LuaValue> {
+        let chunk = Chunk::parse(&format!("return {}", text)).tree();
+        let expr = chunk.syntax().descendants().find_map(Expr::cast).unwrap();
+        eval_constant(&expr)
+    }
+
+    #[test]
+    fn test_eval_constant() {
+        use LuaValue::*;
+        assert_eq!(eval("1 + 2 * 3"), Some(Integer(7)));
+        assert_eq!(eval("7 // -2"), Some(Integer(-4)));
+        assert_eq!(eval("-7 % 3"), Some(Integer(2)));
+        assert_eq!(eval("7.0 // 2"), Some(Float(3.0)));
+        assert_eq!(eval("-7.5 % 2"), Some(Float(0.5)));
+        assert_eq!(eval("1 / 2"), Some(Float(0.5)));
+        assert_eq!(eval("2 ^ 10"), Some(Float(1024.0)));
+        assert_eq!(eval("0x7fffffffffffffff + 1"), Some(Integer(i64::MIN)));
+        assert_eq!(eval("1 << 63 >> 63"), Some(Integer(1)));
+        assert_eq!(eval("1 << 64"), Some(Integer(0)));
+        assert_eq!(eval("~0 & 0xff | 2.0"), Some(Integer(255)));
+        assert_eq!(eval("'a' .. 1 .. \"b\""), Some(String(b"a1b".to_vec())));
+        assert_eq!(eval("#'abc' == 3 and 'x' < 'y'"), Some(Boolean(true)));
+        assert_eq!(eval("nil or false"), Some(Boolean(false)));
+        assert_eq!(eval("not nil"), Some(Boolean(true)));
+        assert_eq!(eval("1 == 1.0"), Some(Boolean(true)));
+        assert_eq!(eval("1 // 0"), None);
+        assert_eq!(eval("1.5 | 1"), None);
+        assert_eq!(eval("'a' < 1"), None);
+        assert_eq!(eval("1.5 .. ''"), None);
+        assert_eq!(eval("x + 1"), None);
+    }
+
+    #[test]
+    fn test_check_constants() {
+        let text = "local a = 1 // 0\n\
+                    local b = 1 % (2 - 2)\n\
+                    local c = 1.0 // 0\n\
+                    local d = 0x7fffffffffffffff + 1\n\
+                    local e = -(-0x7fffffffffffffff - 1)\n\
+                    local f = 0x10000000000000000\n\
+                    local g = math.maxinteger + 1\n";
+        let chunk = Chunk::parse(text).tree();
+        let diagnostics: Vec<_> = check_constants(&chunk, Dialect::Lua54)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.code, &text[diagnostic.range]))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (INTEGER_DIVISION_BY_ZERO, "1 // 0"),
+                (INTEGER_DIVISION_BY_ZERO, "1 % (2 - 2)"),
+                (INTEGER_OVERFLOW, "0x7fffffffffffffff + 1"),
+                (INTEGER_OVERFLOW, "-(-0x7fffffffffffffff - 1)"),
+                (INTEGER_OVERFLOW, "0x10000000000000000"),
+            ]
+        );
+        assert!(check_constants(&chunk, Dialect::Lua52).is_empty());
+    }
+
+    #[test]
+    fn test_check_constants_in_long_chains() {
+        // The first addition overflows, the following ones add to the wrapped value
+        let text = format!("local a = 0x7fffffffffffffff{}", " + 1".repeat(2000));
+        let chunk = Chunk::parse(&text).tree();
+        let diagnostics = check_constants(&chunk, Dialect::Lua54);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(&text[diagnostics[0].range], "0x7fffffffffffffff + 1");
+
+        let text = "local b = false and 1 // 0";
+        let chunk = Chunk::parse(text).tree();
+        let diagnostics = check_constants(&chunk, Dialect::Lua54);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(&text[diagnostics[0].range], "1 // 0");
+    }
+}
@@ -0,0 +1,231 @@
+//! A salsa based alternative to `Workspace`. All data derived from the source files is computed
+//! by memoized queries which are only re-executed when an input they depend on changed, which
+//! makes it suitable for editors that re-analyze on every keystroke.
+
+use crate::{
+    check_ambiguous_calls, check_compatibility, check_constants, check_patterns,
+    comment_directives, find_requires, resolve_names, tokenize, Chunk, Diagnostic, Dialect, FileId,
+    FunctionMetrics, LineIndex, NameResolution, Parse, Require, Token,
+};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+#[salsa::query_group(SourceDatabaseStorage)]
+pub trait SourceDatabase: salsa::Database {
+    /// The text of a file
+    #[salsa::input]
+    fn file_text(&self, file_id: FileId) -> Arc<String>;
+
+    /// The path of a file, used to resolve `require` calls
+    #[salsa::input]
+    fn file_path(&self, file_id: FileId) -> PathBuf;
+
+    /// All the files in the database
+    #[salsa::input]
+    fn files(&self) -> Arc<Vec<FileId>>;
+
+    /// The templates used to find the file of a module, see `Workspace::set_search_paths`
+    #[salsa::input]
+    fn search_paths(&self) -> Arc<Vec<String>>;
+
+    /// The version of Lua that all files target
+    #[salsa::input]
+    fn dialect(&self) -> Dialect;
+
+    fn tokens(&self, file_id: FileId) -> Arc<Vec<Token>>;
+
+    fn line_index(&self, file_id: FileId) -> Arc<LineIndex>;
+
+    fn parse(&self, file_id: FileId) -> Parse<Chunk>;
+
+    /// The locals of a file and the variables that its names refer to. The resolution holds
+    /// syntax nodes, which are not thread safe, so it is computed from the memoized `parse` on
+    /// every call instead of being stored in the database.
+    #[salsa::transparent]
+    fn resolve(&self, file_id: FileId) -> NameResolution;
+
+    fn requires(&self, file_id: FileId) -> Arc<Vec<Require>>;
+
+    fn function_metrics(&self, file_id: FileId) -> Arc<Vec<FunctionMetrics>>;
+
+    /// The syntax errors and the results of the checks of a file, ordered by their position. The
+    /// levels of the checks can be changed by directives in comments.
+    fn diagnostics(&self, file_id: FileId) -> Arc<Vec<Diagnostic>>;
+
+    fn files_by_path(&self) -> Arc<HashMap<PathBuf, FileId>>;
+
+    /// Returns the file that is loaded by `require(name)`
+    fn resolve_module(&self, name: String) -> Option<FileId>;
+
+    /// Returns the files that are required by a file
+    fn dependencies(&self, file_id: FileId) -> Arc<Vec<FileId>>;
+}
+
+fn tokens(db: &dyn SourceDatabase, file_id: FileId) -> Arc<Vec<Token>> {
+    Arc::new(tokenize(&db.file_text(file_id)))
+}
+
+fn line_index(db: &dyn SourceDatabase, file_id: FileId) -> Arc<LineIndex> {
+    Arc::new(LineIndex::new(&db.file_text(file_id)))
+}
+
+fn parse(db: &dyn SourceDatabase, file_id: FileId) -> Parse<Chunk> {
+    Chunk::parse(&db.file_text(file_id))
+}
+
+fn resolve(db: &dyn SourceDatabase, file_id: FileId) -> NameResolution {
+    resolve_names(&db.parse(file_id).tree())
+}
+
+fn requires(db: &dyn SourceDatabase, file_id: FileId) -> Arc<Vec<Require>> {
+    Arc::new(find_requires(&db.file_text(file_id)))
+}
+
+fn function_metrics(db: &dyn SourceDatabase, file_id: FileId) -> Arc<Vec<FunctionMetrics>> {
+    Arc::new(crate::function_metrics(&db.parse(file_id).tree()))
+}
+
+fn diagnostics(db: &dyn SourceDatabase, file_id: FileId) -> Arc<Vec<Diagnostic>> {
+    let text = db.file_text(file_id);
+    let dialect = db.dialect();
+    let parse = db.parse(file_id);
+    let chunk = parse.tree();
+    let mut diagnostics = parse.diagnostics();
+    diagnostics.extend(check_ambiguous_calls(&text, dialect, false));
+    diagnostics.extend(check_compatibility(&text, dialect));
+    diagnostics.extend(check_patterns(&chunk));
+    diagnostics.extend(check_constants(&chunk, dialect));
+    let mut diagnostics = comment_directives(&chunk).apply(diagnostics);
+    diagnostics.sort_by_key(|diagnostic| diagnostic.range.start());
+    Arc::new(diagnostics)
+}
+
+fn files_by_path(db: &dyn SourceDatabase) -> Arc<HashMap<PathBuf, FileId>> {
+    Arc::new(
+        db.files()
+            .iter()
+            .map(|&file_id| (db.file_path(file_id), file_id))
+            .collect(),
+    )
+}
+
+fn resolve_module(db: &dyn SourceDatabase, name: String) -> Option<FileId> {
+    let module_path = name.replace('.', "/");
+    let files = db.files_by_path();
+    db.search_paths()
+        .iter()
+        .find_map(|template| files.get(&PathBuf::from(template.replace('?', &module_path))))
+        .cloned()
+}
+
+fn dependencies(db: &dyn SourceDatabase, file_id: FileId) -> Arc<Vec<FileId>> {
+    let mut result = Vec::new();
+    for require in db.requires(file_id).iter() {
+        if let Some(dependency) = db.resolve_module(require.name.clone()) {
+            if !result.contains(&dependency) {
+                result.push(dependency);
+            }
+        }
+    }
+    Arc::new(result)
+}
+
+/// A database that only contains the `SourceDatabase` queries. Downstream crates that add their
+/// own query groups define their own database instead.
+#[salsa::database(SourceDatabaseStorage)]
+pub struct RootDatabase {
+    storage: salsa::Storage<RootDatabase>,
+}
+
+impl salsa::Database for RootDatabase {}
+
+impl Default for RootDatabase {
+    fn default() -> RootDatabase {
+        let mut db = RootDatabase {
+            storage: salsa::Storage::default(),
+        };
+        db.set_files(Arc::new(Vec::new()));
+        db.set_search_paths(Arc::new(vec![
+            "?.lua".to_string(),
+            "?/init.lua".to_string(),
+        ]));
+        db.set_dialect(Dialect::default());
+        db
+    }
+}
+
+impl RootDatabase {
+    /// Adds a file to the database and returns its id. If a file with the same path already exists
+    /// its text is replaced and the existing id is returned.
+    pub fn add_file(&mut self, path: impl Into<PathBuf>, text: impl Into<String>) -> FileId {
+        let path = path.into();
+        if let Some(&file_id) = self.files_by_path().get(&path) {
+            self.set_file_text(file_id, Arc::new(text.into()));
+            return file_id;
+        }
+
+        let mut files = (*self.files()).clone();
+        let file_id = FileId(files.len() as u32);
+        files.push(file_id);
+        self.set_file_text(file_id, Arc::new(text.into()));
+        self.set_file_path(file_id, path);
+        self.set_files(Arc::new(files));
+        file_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constants::INTEGER_DIVISION_BY_ZERO, AstNode, DiagnosticCode};
+
+    #[test]
+    fn test_queries() {
+        let mut db = RootDatabase::default();
+        let main = db.add_file("main.lua", "require 'util'\nx = 1 // 2");
+        let util = db.add_file("util.lua", "return {}");
+        assert_eq!(*db.dependencies(main), vec![util]);
+        assert!(db.diagnostics(main).is_empty());
+
+        db.set_dialect(Dialect::Lua52);
+        assert_eq!(db.diagnostics(main).len(), 1);
+
+        db.set_file_text(main, Arc::new("return 1".to_string()));
+        assert!(db.dependencies(main).is_empty());
+        assert!(db.diagnostics(main).is_empty());
+
+        db.set_dialect(Dialect::Lua54);
+        db.set_file_text(main, Arc::new("local x = 1 % 0\nreturn x +".to_string()));
+        let codes: Vec<_> = db.diagnostics(main).iter().map(|it| it.code).collect();
+        assert_eq!(
+            codes,
+            vec![INTEGER_DIVISION_BY_ZERO, DiagnosticCode("E0001")]
+        );
+    }
+
+    #[test]
+    fn test_add_existing_file() {
+        let mut db = RootDatabase::default();
+        let main = db.add_file("main.lua", "return 1");
+        assert_eq!(db.add_file("main.lua", "return 2"), main);
+        assert_eq!(*db.files(), vec![main]);
+        assert_eq!(*db.file_text(main), "return 2");
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut db = RootDatabase::default();
+        let main = db.add_file("main.lua", "local x = 1\nreturn x, y");
+        let resolution = db.resolve(main);
+        assert_eq!(resolution.locals().len(), 1);
+        let locals: Vec<_> = resolution
+            .references()
+            .iter()
+            .map(|reference| reference.local.is_some())
+            .collect();
+        assert_eq!(locals, vec![true, false]);
+        assert_eq!(
+            db.parse(main).tree().syntax().text(),
+            "local x = 1\nreturn x, y"
+        );
+    }
+}
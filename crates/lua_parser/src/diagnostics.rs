@@ -0,0 +1,188 @@
+//! Diagnostics are the user facing representation of problems found in Lua source. Where a
+//! `SyntaxError` only describes *what* went wrong, a `Diagnostic` also carries a stable code, a
+//! severity and optional secondary ranges so tools can filter and render them meaningfully.
+
+use crate::{
+    syntax_error::{Location, SyntaxError, SyntaxErrorKind},
+    TextEdit, TextRange, TextSize,
+};
+use serde::Serialize;
+use std::fmt;
+
+mod fixes;
+mod json;
+mod render;
+
+pub(crate) use self::fixes::syntax_error_fixes;
+pub use self::{
+    json::{diagnostics_to_json, JsonDiagnostic, JsonEdit, JsonFix, JsonLabel, JsonPosition},
+    render::render_diagnostic,
+};
+
+/// A stable code identifying a class of diagnostics, e.g. `E0001`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DiagnosticCode(pub &'static str);
+
+impl DiagnosticCode {
+    pub fn as_str(self) -> &'static str {
+        self.0
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+/// The severity of a diagnostic. Variants are ordered from most to least severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Hint,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Hint => "hint",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A secondary range of a diagnostic with a message explaining its relation to the primary range.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Label {
+    pub range: TextRange,
+    pub message: String,
+}
+
+/// An edit that resolves a diagnostic, e.g. for an editor to offer as a quick fix.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fix {
+    /// A short description of the edit, e.g. "insert `end`"
+    pub label: String,
+    pub edits: TextEdit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    pub code: DiagnosticCode,
+    pub severity: Severity,
+    pub message: String,
+    pub range: TextRange,
+    pub labels: Vec<Label>,
+    pub fixes: Vec<Fix>,
+}
+
+impl Diagnostic {
+    pub fn new(
+        code: DiagnosticCode,
+        severity: Severity,
+        range: TextRange,
+        message: impl Into<String>,
+    ) -> Diagnostic {
+        Diagnostic {
+            code,
+            severity,
+            message: message.into(),
+            range,
+            labels: Vec::new(),
+            fixes: Vec::new(),
+        }
+    }
+
+    pub fn error(code: DiagnosticCode, range: TextRange, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(code, Severity::Error, range, message)
+    }
+
+    pub fn warning(code: DiagnosticCode, range: TextRange, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(code, Severity::Warning, range, message)
+    }
+
+    pub fn hint(code: DiagnosticCode, range: TextRange, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(code, Severity::Hint, range, message)
+    }
+
+    /// Adds a secondary labeled range to the diagnostic.
+    pub fn with_label(mut self, range: TextRange, message: impl Into<String>) -> Diagnostic {
+        self.labels.push(Label {
+            range,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Adds a fix that applies `edits` to the text the diagnostic was found in.
+    pub fn with_fix(mut self, label: impl Into<String>, edits: TextEdit) -> Diagnostic {
+        self.fixes.push(Fix {
+            label: label.into(),
+            edits,
+        });
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Renders the diagnostic with the source lines it refers to, see `render_diagnostic`.
+    pub fn render(&self, text: &str) -> String {
+        render_diagnostic(self, None, text)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+impl SyntaxErrorKind {
+    /// Returns the diagnostic code associated with this kind of error.
+    pub fn code(&self) -> DiagnosticCode {
+        match self {
+            SyntaxErrorKind::ParseError(_) => DiagnosticCode("E0001"),
+        }
+    }
+}
+
+impl From<SyntaxError> for Diagnostic {
+    fn from(error: SyntaxError) -> Diagnostic {
+        let range = match error.location() {
+            Location::Offset(offset) => TextRange::at(offset, TextSize::from(0)),
+            Location::Range(range) => range,
+        };
+        Diagnostic::error(error.kind().code(), range, error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseError;
+
+    #[test]
+    fn test_from_syntax_error() {
+        let error = SyntaxError::new(
+            SyntaxErrorKind::ParseError(ParseError::new("expected `end`")),
+            TextSize::from(4),
+        );
+        let diagnostic = Diagnostic::from(error);
+        assert_eq!(diagnostic.code, DiagnosticCode("E0001"));
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.range, TextRange::at(4.into(), 0.into()));
+        assert_eq!(diagnostic.to_string(), "error[E0001]: expected `end`");
+    }
+
+    #[test]
+    fn test_severity_ordering() {
+        assert!(Severity::Error < Severity::Warning);
+        assert!(Severity::Warning < Severity::Hint);
+    }
+}
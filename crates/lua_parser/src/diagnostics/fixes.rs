@@ -0,0 +1,159 @@
+use super::Fix;
+use crate::{
+    SyntaxError, SyntaxErrorKind, SyntaxKind::*, SyntaxNode, SyntaxToken, TextEdit, TextRange,
+    TokenSet,
+};
+
+/// The tokens that can't follow a comma, but can follow the item in front of it.
+const AFTER_TRAILING_COMMA: TokenSet = token_set![R_PAREN, EQ, IN_KW, COMMA, END_KW];
+
+/// Returns the fixes for a syntax error in the tree `root`.
+pub(crate) fn syntax_error_fixes(root: &SyntaxNode, error: &SyntaxError) -> Vec<Fix> {
+    let SyntaxErrorKind::ParseError(parse_error) = error.kind();
+    let offset = error.location().offset();
+    let tokens: Vec<SyntaxToken> = root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+        .collect();
+    let next_idx = tokens.partition_point(|token| token.text_range().start() < offset);
+    let prev = next_idx.checked_sub(1).map(|idx| &tokens[idx]);
+    let next = tokens.get(next_idx);
+    let mut fixes = Vec::new();
+
+    let text = root.text().to_string();
+    if parse_error.expected == TokenSet::singleton(END_KW) {
+        let range = TextRange::empty(offset);
+        fixes.push(Fix {
+            label: "insert `end`".to_string(),
+            edits: TextEdit::insert(offset, separated(&text, range, "end")),
+        });
+    }
+
+    if let Some(next) = next {
+        let intended = next
+            .kind()
+            .intended_operator()
+            .filter(|&intended| parse_error.expected == TokenSet::singleton(intended));
+        if let Some(intended) = intended {
+            let intended_text = intended.token_text().unwrap();
+            let replace_with = if intended.is_keyword() {
+                separated(&text, next.text_range(), intended_text)
+            } else {
+                intended_text.to_string()
+            };
+            fixes.push(Fix {
+                label: format!("replace with `{}`", intended_text),
+                edits: TextEdit::replace(next.text_range(), replace_with),
+            });
+        }
+
+        // test_err increment_statement reports the `++` in an error node after the target
+        let target = next
+            .parent()
+            .filter(|_| next.kind() == PLUSPLUS)
+            .and_then(|error| error.prev_sibling());
+        if let Some(target) = target {
+            let target_text = target.text().to_string();
+            fixes.push(Fix {
+                label: format!("replace with `{0} = {0} + 1`", target_text),
+                edits: TextEdit::replace(
+                    TextRange::new(target.text_range().end(), next.text_range().end()),
+                    format!(" = {} + 1", target_text),
+                ),
+            });
+        }
+    }
+
+    // The error is reported in front of the trivia that precedes `next`
+    let trailing_comma = prev.filter(|prev| {
+        prev.kind() == COMMA
+            && prev.text_range().end() <= offset
+            && next.is_none_or(|next| AFTER_TRAILING_COMMA.contains(next.kind()))
+    });
+    if let Some(comma) = trailing_comma {
+        fixes.push(Fix {
+            label: "remove the comma".to_string(),
+            edits: TextEdit::delete(comma.text_range()),
+        });
+    }
+    fixes
+}
+
+/// Returns `insert` with a space on each side where it would otherwise directly follow or precede
+/// the text around `range`.
+fn separated(text: &str, range: TextRange, insert: &str) -> String {
+    let is_joint = |c: Option<char>| c.is_some_and(|c| !c.is_whitespace());
+    let before = if is_joint(text[..usize::from(range.start())].chars().next_back()) {
+        " "
+    } else {
+        ""
+    };
+    let after = if is_joint(text[usize::from(range.end())..].chars().next()) {
+        " "
+    } else {
+        ""
+    };
+    format!("{}{}{}", before, insert, after)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Chunk;
+
+    fn fixed(text: &str) -> Vec<(String, String)> {
+        Chunk::parse(text)
+            .diagnostics()
+            .into_iter()
+            .flat_map(|diagnostic| diagnostic.fixes)
+            .map(|fix| (fix.label, fix.edits.apply(text)))
+            .collect()
+    }
+
+    #[test]
+    fn test_insert_end() {
+        assert_eq!(
+            fixed("if a then b()"),
+            vec![("insert `end`".to_string(), "if a then b() end".to_string())]
+        );
+        assert_eq!(
+            fixed("while x do\n"),
+            vec![("insert `end`".to_string(), "while x do\nend".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_remove_comma() {
+        for (text, fixed_text) in &[
+            ("f(a, b,)", "f(a, b)"),
+            ("local a, = 1", "local a = 1"),
+            ("t = {1,,2}", "t = {1,2}"),
+            ("return a,", "return a"),
+        ] {
+            let fixes = fixed(text);
+            assert_eq!(fixes.len(), 1, "{}", text);
+            assert_eq!(fixes[0].1, *fixed_text);
+        }
+        assert_eq!(fixed("f(a, +)"), vec![]);
+    }
+
+    #[test]
+    fn test_typo_operators() {
+        let fixes: Vec<_> = fixed("x = a != b&&c || d === e\ni++")
+            .into_iter()
+            .map(|(label, _)| label)
+            .collect();
+        assert_eq!(
+            fixes,
+            vec![
+                "replace with `~=`",
+                "replace with `and`",
+                "replace with `or`",
+                "replace with `==`",
+                "replace with `i = i + 1`",
+            ]
+        );
+        assert_eq!(fixed("x = b&&c")[0].1, "x = b and c");
+        assert_eq!(fixed("t.n ++")[0].1, "t.n = t.n + 1");
+    }
+}
@@ -0,0 +1,151 @@
+use super::{Diagnostic, Severity};
+use crate::{LineIndex, TextSize};
+use serde::Serialize;
+
+/// A one-based line and column position. The column is counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct JsonPosition {
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JsonLabel {
+    pub message: String,
+    pub start: JsonPosition,
+    pub end: JsonPosition,
+}
+
+/// A replacement of the text between `start` and `end` by `text`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JsonEdit {
+    pub start: JsonPosition,
+    pub end: JsonPosition,
+    pub offset: [u32; 2],
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JsonFix {
+    pub label: String,
+    pub edits: Vec<JsonEdit>,
+}
+
+/// The machine readable representation of a `Diagnostic`. Next to line/column positions it also
+/// contains the byte offsets of the primary range. `fixes` is omitted if there are none.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JsonDiagnostic {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub start: JsonPosition,
+    pub end: JsonPosition,
+    pub offset: [u32; 2],
+    pub labels: Vec<JsonLabel>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fixes: Vec<JsonFix>,
+}
+
+impl JsonDiagnostic {
+    pub fn new(diagnostic: &Diagnostic, index: &LineIndex, text: &str) -> JsonDiagnostic {
+        JsonDiagnostic {
+            code: diagnostic.code.as_str(),
+            severity: diagnostic.severity,
+            message: diagnostic.message.clone(),
+            start: position(index, text, diagnostic.range.start()),
+            end: position(index, text, diagnostic.range.end()),
+            offset: [
+                diagnostic.range.start().into(),
+                diagnostic.range.end().into(),
+            ],
+            labels: diagnostic
+                .labels
+                .iter()
+                .map(|label| JsonLabel {
+                    message: label.message.clone(),
+                    start: position(index, text, label.range.start()),
+                    end: position(index, text, label.range.end()),
+                })
+                .collect(),
+            fixes: diagnostic
+                .fixes
+                .iter()
+                .map(|fix| JsonFix {
+                    label: fix.label.clone(),
+                    edits: fix
+                        .edits
+                        .as_atoms()
+                        .iter()
+                        .map(|atom| JsonEdit {
+                            start: position(index, text, atom.delete.start()),
+                            end: position(index, text, atom.delete.end()),
+                            offset: [atom.delete.start().into(), atom.delete.end().into()],
+                            text: atom.insert.clone(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Serializes the diagnostics of `text` to a JSON array.
+pub fn diagnostics_to_json(diagnostics: &[Diagnostic], text: &str) -> String {
+    let index = LineIndex::new(text);
+    let diagnostics: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| JsonDiagnostic::new(diagnostic, &index, text))
+        .collect();
+    serde_json::to_string(&diagnostics).expect("diagnostics are always serializable")
+}
+
+fn position(index: &LineIndex, text: &str, offset: TextSize) -> JsonPosition {
+    let line_col = index.line_col(offset);
+    let line_start: u32 = (offset - line_col.col).into();
+    let offset: u32 = offset.into();
+    let column = text[line_start as usize..offset as usize].chars().count() as u32;
+    JsonPosition {
+        line: line_col.line + 1,
+        column: column + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DiagnosticCode, TextEdit, TextRange};
+
+    #[test]
+    fn test_diagnostics_to_json() {
+        let text = "local ä = 1\nif a b\n";
+        let diagnostic = Diagnostic::warning(
+            DiagnosticCode("E0001"),
+            TextRange::at(18.into(), 1.into()),
+            "expected `then`",
+        );
+        assert_eq!(
+            diagnostics_to_json(&[diagnostic], text),
+            r#"[{"code":"E0001","severity":"warning","message":"expected `then`","start":{"line":2,"column":6},"end":{"line":2,"column":7},"offset":[18,19],"labels":[]}]"#
+        );
+    }
+
+    #[test]
+    fn test_fixes_to_json() {
+        let text = "f(a,)";
+        let diagnostic = Diagnostic::error(
+            DiagnosticCode("E0001"),
+            TextRange::at(4.into(), 0.into()),
+            "expected an expression",
+        )
+        .with_fix(
+            "remove the comma",
+            TextEdit::delete(TextRange::at(3.into(), 1.into())),
+        );
+        let json = diagnostics_to_json(&[diagnostic], text);
+        assert!(
+            json.ends_with(r#""labels":[],"fixes":[{"label":"remove the comma","edits":[{"start":{"line":1,"column":4},"end":{"line":1,"column":5},"offset":[3,4],"text":""}]}]}]"#),
+            "{}",
+            json
+        );
+    }
+}
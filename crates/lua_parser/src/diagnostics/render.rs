@@ -0,0 +1,167 @@
+use super::Diagnostic;
+use crate::{LineIndex, TextRange, TextSize};
+use std::{
+    cmp::{max, min},
+    fmt::Write,
+};
+
+struct Annotation<'a> {
+    range: TextRange,
+    marker: char,
+    message: Option<&'a str>,
+}
+
+/// Renders a diagnostic together with the source lines it refers to. The primary range is
+/// underlined with carets, secondary labels are underlined with dashes followed by their message.
+/// The labels of fixes are listed below the source lines:
+///
+/// ```text
+/// error[E0001]: expected 'end'
+///  --> main.lua:1:14
+///   |
+/// 1 | if a then b()
+///   |              ^
+///   |
+///   = help: insert `end`
+/// ```
+pub fn render_diagnostic(diagnostic: &Diagnostic, file_name: Option<&str>, text: &str) -> String {
+    let index = LineIndex::new(text);
+
+    let mut annotations = vec![Annotation {
+        range: diagnostic.range,
+        marker: '^',
+        message: None,
+    }];
+    annotations.extend(diagnostic.labels.iter().map(|label| Annotation {
+        range: label.range,
+        marker: '-',
+        message: Some(label.message.as_str()),
+    }));
+    annotations.sort_by_key(|annotation| annotation.range.start());
+
+    let max_line = annotations
+        .iter()
+        .map(|annotation| index.line_col(annotation.range.start()).line + 1)
+        .max()
+        .unwrap_or(1);
+    let width = max_line.to_string().len();
+
+    let mut buf = String::new();
+    writeln!(buf, "{}", diagnostic).unwrap();
+
+    let start = index.line_col(diagnostic.range.start());
+    let start_line = line_text(text, &index, start.line);
+    let column = start_line[..column_in(start_line, start.col)].chars().count() + 1;
+    match file_name {
+        Some(name) => writeln!(
+            buf,
+            "{:w$}--> {}:{}:{}",
+            "",
+            name,
+            start.line + 1,
+            column,
+            w = width
+        ),
+        None => writeln!(buf, "{:w$}--> {}:{}", "", start.line + 1, column, w = width),
+    }
+    .unwrap();
+    writeln!(buf, "{:w$} |", "", w = width).unwrap();
+
+    let mut last_line = None;
+    for annotation in annotations.iter() {
+        let line_col = index.line_col(annotation.range.start());
+        let line = line_text(text, &index, line_col.line);
+        if last_line != Some(line_col.line) {
+            if let Some(last_line) = last_line {
+                if line_col.line > last_line + 1 {
+                    writeln!(buf, "{:w$}...", "", w = width).unwrap();
+                }
+            }
+            writeln!(buf, "{:>w$} | {}", line_col.line + 1, line, w = width).unwrap();
+            last_line = Some(line_col.line);
+        }
+
+        let col = column_in(line, line_col.col);
+        let padding: String = line[..col]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let line_end = annotation.range.start() - line_col.col + TextSize::of(line);
+        let start = min(annotation.range.start(), line_end);
+        let end = min(annotation.range.end(), line_end);
+        let marked = &text[TextRange::new(start, end)];
+        let markers: String = std::iter::repeat_n(annotation.marker, max(1, marked.chars().count()))
+            .collect();
+        write!(buf, "{:w$} | {}{}", "", padding, markers, w = width).unwrap();
+        if let Some(message) = annotation.message {
+            write!(buf, " {}", message).unwrap();
+        }
+        writeln!(buf).unwrap();
+    }
+    writeln!(buf, "{:w$} |", "", w = width).unwrap();
+    for fix in diagnostic.fixes.iter() {
+        writeln!(buf, "{:w$} = help: {}", "", fix.label, w = width).unwrap();
+    }
+    buf
+}
+
+/// Returns the text of the specified line without its line terminator.
+fn line_text<'a>(text: &'a str, index: &LineIndex, line: u32) -> &'a str {
+    let line = &text[index.line_range(line)];
+    line.trim_end_matches(['\n', '\r'])
+}
+
+/// Returns the byte offset of `col` in a line returned by `line_text`. A column inside the line
+/// terminator, like the `\n` of a `\r\n`, is moved to the end of the line.
+fn column_in(line: &str, col: TextSize) -> usize {
+    min(u32::from(col) as usize, line.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiagnosticCode;
+
+    #[test]
+    fn test_render_primary() {
+        let text = "local a = 1\nif a b\n";
+        let diagnostic = Diagnostic::error(
+            DiagnosticCode("E0001"),
+            TextRange::at(17.into(), 1.into()),
+            "expected `then`",
+        );
+        assert_eq!(
+            render_diagnostic(&diagnostic, Some("main.lua"), text),
+            "error[E0001]: expected `then`\n --> main.lua:2:6\n  |\n2 | if a b\n  |      ^\n  |\n"
+        );
+    }
+
+    #[test]
+    fn test_render_label() {
+        let text = "if a then\n\tb()\n";
+        let diagnostic = Diagnostic::error(
+            DiagnosticCode("E0001"),
+            TextRange::at(14.into(), 0.into()),
+            "expected `end`",
+        )
+        .with_label(TextRange::at(0.into(), 2.into()), "`if` opened here");
+        assert_eq!(
+            render_diagnostic(&diagnostic, None, text),
+            "error[E0001]: expected `end`\n --> 2:5\n  |\n1 | if a then\n  | -- `if` opened here\n2 | \tb()\n  | \t   ^\n  |\n"
+        );
+    }
+
+    #[test]
+    fn test_render_crlf() {
+        let text = "if a then\r\nb()\r\n";
+        let diagnostic = Diagnostic::error(
+            DiagnosticCode("E0001"),
+            TextRange::at(10.into(), 2.into()),
+            "unexpected line break",
+        );
+        assert_eq!(
+            render_diagnostic(&diagnostic, None, text),
+            "error[E0001]: unexpected line break\n --> 1:10\n  |\n1 | if a then\n  |          ^\n  |\n"
+        );
+    }
+}
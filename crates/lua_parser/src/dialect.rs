@@ -0,0 +1,24 @@
+use std::fmt;
+
+/// The version of Lua that source code targets. Variants are ordered from oldest to newest so
+/// they can be compared to determine whether a feature is available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Dialect {
+    Lua51,
+    Lua52,
+    Lua53,
+    #[default]
+    Lua54,
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Dialect::Lua51 => "Lua 5.1",
+            Dialect::Lua52 => "Lua 5.2",
+            Dialect::Lua53 => "Lua 5.3",
+            Dialect::Lua54 => "Lua 5.4",
+        };
+        f.write_str(name)
+    }
+}
@@ -0,0 +1,481 @@
+//! Inline configuration of linters in comments, in the formats of luacheck and selene, e.g.
+//! `-- luacheck: globals foo bar, ignore W0001` or `-- selene: allow(W0001)`.
+//!
+//! Every directive applies to a part of the file, its scope:
+//! - A luacheck directive that follows code on the same line applies to that line. Other luacheck
+//!   directives apply until the end of the enclosing function, or of the file, unless they come
+//!   after a `push`, in which case they end at the matching `pop`.
+//! - A `--# selene:` directive applies to the whole file, and a `-- selene:` directive to the
+//!   statement, field or expression that follows it.
+//!
+//! Rules are the codes of diagnostics. Luacheck rules match every code that starts with them, and
+//! `ignore` without rules matches every code. Selene rules have to match a code exactly.
+
+use crate::{
+    AstNode, Chunk, Diagnostic, Severity, SyntaxKind::*, SyntaxNode, SyntaxToken, TextRange,
+    TextSize,
+};
+
+/// The linter whose format a directive is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DirectiveTool {
+    Luacheck,
+    Selene,
+}
+
+/// How the diagnostics of a rule are reported. Variants are ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// The kind of globals that a `globals` option of luacheck defines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GlobalsKind {
+    /// `globals`: globals that can be read and assigned
+    Globals,
+    /// `read globals`: globals that can only be read
+    ReadGlobals,
+    /// `new globals`: replaces the globals that were defined before
+    NewGlobals,
+    /// `new read globals`: replaces the read-only globals that were defined before
+    NewReadGlobals,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DirectiveOption {
+    Globals {
+        kind: GlobalsKind,
+        names: Vec<String>,
+    },
+    /// The set of standard globals, e.g. `lua51` or `max+busted`
+    Std(String),
+    /// `ignore` and `enable` of luacheck, and `allow`, `warn` and `deny` of selene
+    Lint {
+        level: LintLevel,
+        rules: Vec<String>,
+    },
+    Push,
+    Pop,
+    /// An option that isn't interpreted, e.g. `max line length 120`
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Directive {
+    pub tool: DirectiveTool,
+    pub options: Vec<DirectiveOption>,
+    /// The range of the comment
+    pub range: TextRange,
+    /// The range of the source the directive applies to
+    pub scope: TextRange,
+}
+
+/// The directives of a chunk, ordered by the start of their scope.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Directives {
+    directives: Vec<Directive>,
+}
+
+impl Directives {
+    pub fn directives(&self) -> &[Directive] {
+        &self.directives
+    }
+
+    /// Adds the settings of a configuration file. They apply to the whole file, and the
+    /// directives in comments take precedence over them.
+    #[cfg(feature = "config")]
+    pub fn with_config(mut self, config: &crate::LintConfig) -> Directives {
+        let directive = Directive {
+            tool: config.tool,
+            options: config.options(),
+            range: TextRange::empty(TextSize::from(0)),
+            scope: TextRange::new(TextSize::from(0), TextSize::from(u32::MAX)),
+        };
+        self.directives.insert(0, directive);
+        self
+    }
+
+    /// Returns the level of the rule `code` at `offset`, if a directive sets it. When multiple
+    /// directives apply, the one with the innermost scope wins.
+    pub fn lint_level(&self, code: &str, offset: TextSize) -> Option<LintLevel> {
+        let mut level = None;
+        for directive in self.at(offset) {
+            for option in &directive.options {
+                if let DirectiveOption::Lint {
+                    level: option_level,
+                    rules,
+                } = option
+                {
+                    if rules_match(directive.tool, rules, code) {
+                        level = Some(*option_level);
+                    }
+                }
+            }
+        }
+        level
+    }
+
+    /// Applies the lint levels of the directives to `diagnostics`: allowed diagnostics are
+    /// removed, and the severity of the others is set to the level. Errors are never suppressed
+    /// or downgraded.
+    pub fn apply(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        diagnostics
+            .into_iter()
+            .filter_map(|mut diagnostic| {
+                if diagnostic.is_error() {
+                    return Some(diagnostic);
+                }
+                match self.lint_level(diagnostic.code.as_str(), diagnostic.range.start()) {
+                    Some(LintLevel::Allow) => return None,
+                    Some(LintLevel::Warn) => diagnostic.severity = Severity::Warning,
+                    Some(LintLevel::Deny) => diagnostic.severity = Severity::Error,
+                    None => {}
+                }
+                Some(diagnostic)
+            })
+            .collect()
+    }
+
+    fn at(&self, offset: TextSize) -> impl Iterator<Item = &Directive> {
+        self.directives
+            .iter()
+            .filter(move |directive| directive.scope.contains_inclusive(offset))
+    }
+}
+
+/// Returns the luacheck and selene directives in the comments of `chunk`.
+pub fn comment_directives(chunk: &Chunk) -> Directives {
+    let root = chunk.syntax();
+    let text = root.text().to_string();
+    let mut directives: Vec<Directive> = Vec::new();
+    // The indices of the directives after each unmatched luacheck `push`
+    let mut pushes = Vec::new();
+    let comments = root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == COMMENT);
+    for comment in comments {
+        let range = comment.text_range();
+        let body = comment_body(comment.text());
+        if let Some(options) = body.trim_start().strip_prefix("luacheck:") {
+            let options = parse_luacheck_options(options);
+            let scope = if is_inline(&comment) {
+                let line_start = text[..usize::from(range.start())]
+                    .rfind('\n')
+                    .map_or(0, |idx| idx + 1);
+                TextRange::new(TextSize::from(line_start as u32), range.end())
+            } else {
+                TextRange::new(range.start(), enclosing_function_end(&comment))
+            };
+            if options.contains(&DirectiveOption::Pop) {
+                if let Some(first) = pushes.pop() {
+                    for directive in &mut directives[first..] {
+                        if directive.tool == DirectiveTool::Luacheck
+                            && directive.scope.end() > range.start()
+                        {
+                            directive.scope =
+                                TextRange::new(directive.scope.start(), range.start());
+                        }
+                    }
+                }
+            }
+            if options.contains(&DirectiveOption::Push) {
+                pushes.push(directives.len());
+            }
+            directives.push(Directive {
+                tool: DirectiveTool::Luacheck,
+                options,
+                range,
+                scope,
+            });
+        } else {
+            let (file_wide, body) = match body.strip_prefix('#') {
+                Some(body) => (true, body),
+                None => (false, body),
+            };
+            if let Some(options) = body.trim_start().strip_prefix("selene:") {
+                let scope = if file_wide {
+                    root.text_range()
+                } else {
+                    following_node_range(&comment)
+                };
+                directives.push(Directive {
+                    tool: DirectiveTool::Selene,
+                    options: parse_selene_options(options),
+                    range,
+                    scope,
+                });
+            }
+        }
+    }
+    directives.sort_by_key(|directive| directive.scope.start());
+    Directives { directives }
+}
+
+/// Returns the text of a comment without the dashes and long brackets.
+fn comment_body(text: &str) -> &str {
+    let body = text.strip_prefix("--").unwrap_or(text);
+    let level = body
+        .strip_prefix('[')
+        .map(|rest| rest.len() - rest.trim_start_matches('=').len());
+    match level {
+        Some(level) if body[1 + level..].starts_with('[') => {
+            let close = format!("]{}]", "=".repeat(level));
+            let body = &body[2 + level..];
+            body.strip_suffix(close.as_str()).unwrap_or(body)
+        }
+        _ => body,
+    }
+}
+
+fn parse_luacheck_options(text: &str) -> Vec<DirectiveOption> {
+    let mut options = Vec::new();
+    for option in text.split(',') {
+        let mut words: Vec<&str> = option.split_whitespace().collect();
+        if words.first() == Some(&"push") {
+            options.push(DirectiveOption::Push);
+            words.remove(0);
+        }
+        let names = |skip: usize| words[skip..].iter().map(|word| word.to_string()).collect();
+        let option = match words.as_slice() {
+            [] => continue,
+            ["pop"] => DirectiveOption::Pop,
+            ["globals", ..] => DirectiveOption::Globals {
+                kind: GlobalsKind::Globals,
+                names: names(1),
+            },
+            ["read", "globals", ..] => DirectiveOption::Globals {
+                kind: GlobalsKind::ReadGlobals,
+                names: names(2),
+            },
+            ["new", "globals", ..] => DirectiveOption::Globals {
+                kind: GlobalsKind::NewGlobals,
+                names: names(2),
+            },
+            ["new", "read", "globals", ..] => DirectiveOption::Globals {
+                kind: GlobalsKind::NewReadGlobals,
+                names: names(3),
+            },
+            ["std", std] => DirectiveOption::Std(std.to_string()),
+            ["ignore", ..] => DirectiveOption::Lint {
+                level: LintLevel::Allow,
+                rules: names(1),
+            },
+            ["enable", ..] => DirectiveOption::Lint {
+                level: LintLevel::Warn,
+                rules: names(1),
+            },
+            _ => DirectiveOption::Other(words.join(" ")),
+        };
+        options.push(option);
+    }
+    options
+}
+
+/// Parses a sequence of `allow(rule, ...)`, `warn(...)` and `deny(...)`.
+fn parse_selene_options(text: &str) -> Vec<DirectiveOption> {
+    let mut options = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let (option, after) = match (rest.find('('), rest.find(')')) {
+            (Some(open), Some(close)) if open < close => (&rest[..=close], &rest[close + 1..]),
+            _ => (rest, ""),
+        };
+        let parsed = option
+            .strip_suffix(')')
+            .and_then(|option| option.split_once('('))
+            .and_then(|(level, rules)| {
+                let level = match level.trim() {
+                    "allow" => LintLevel::Allow,
+                    "warn" => LintLevel::Warn,
+                    "deny" => LintLevel::Deny,
+                    _ => return None,
+                };
+                let rules = rules
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|rule| !rule.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                Some(DirectiveOption::Lint { level, rules })
+            });
+        options.push(parsed.unwrap_or_else(|| DirectiveOption::Other(option.trim().to_string())));
+        rest = after.trim_start();
+    }
+    options
+}
+
+fn rules_match(tool: DirectiveTool, rules: &[String], code: &str) -> bool {
+    match tool {
+        DirectiveTool::Luacheck => {
+            rules.is_empty() || rules.iter().any(|rule| code.starts_with(rule.as_str()))
+        }
+        DirectiveTool::Selene => rules.iter().any(|rule| rule == code),
+    }
+}
+
+/// Returns true if code precedes `comment` on its line.
+fn is_inline(comment: &SyntaxToken) -> bool {
+    let mut token = comment.prev_token();
+    while let Some(prev) = token {
+        if !prev.kind().is_trivia() {
+            return true;
+        }
+        if prev.text().contains('\n') {
+            return false;
+        }
+        token = prev.prev_token();
+    }
+    false
+}
+
+fn enclosing_function_end(comment: &SyntaxToken) -> TextSize {
+    comment
+        .parent_ancestors()
+        .find(|node| {
+            matches!(
+                node.kind(),
+                FUNCTION_STMT | LOCAL_FUNCTION_STMT | FUNCTION_EXPR | CHUNK
+            )
+        })
+        .map_or(comment.text_range().end(), |node| node.text_range().end())
+}
+
+/// Returns the range of the outermost node, other than a block, that starts at the first token
+/// after `comment`.
+fn following_node_range(comment: &SyntaxToken) -> TextRange {
+    let mut token = comment.next_token();
+    while let Some(next) = token.as_ref().filter(|next| next.kind().is_trivia()) {
+        token = next.next_token();
+    }
+    let token = match token {
+        Some(token) => token,
+        None => return TextRange::empty(comment.text_range().end()),
+    };
+    let start = token.text_range().start();
+    let mut node: Option<SyntaxNode> = None;
+    for ancestor in token.parent_ancestors() {
+        if ancestor.text_range().start() != start || matches!(ancestor.kind(), BLOCK | CHUNK) {
+            break;
+        }
+        node = Some(ancestor);
+    }
+    node.map_or(token.text_range(), |node| node.text_range())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiagnosticCode;
+
+    fn directives(text: &str) -> Directives {
+        comment_directives(&Chunk::parse(text).tree())
+    }
+
+    #[test]
+    fn test_luacheck_options() {
+        let text = "-- luacheck: globals foo bar, read globals baz, ignore 211 W0001, std lua51\n\
+                    --[=[luacheck: push enable W, max line length 120]=]\n";
+        let options: Vec<_> = directives(text)
+            .directives()
+            .iter()
+            .flat_map(|directive| directive.options.clone())
+            .collect();
+        assert_eq!(
+            options,
+            vec![
+                DirectiveOption::Globals {
+                    kind: GlobalsKind::Globals,
+                    names: vec!["foo".to_string(), "bar".to_string()]
+                },
+                DirectiveOption::Globals {
+                    kind: GlobalsKind::ReadGlobals,
+                    names: vec!["baz".to_string()]
+                },
+                DirectiveOption::Lint {
+                    level: LintLevel::Allow,
+                    rules: vec!["211".to_string(), "W0001".to_string()]
+                },
+                DirectiveOption::Std("lua51".to_string()),
+                DirectiveOption::Push,
+                DirectiveOption::Lint {
+                    level: LintLevel::Warn,
+                    rules: vec!["W".to_string()]
+                },
+                DirectiveOption::Other("max line length 120".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_luacheck_scopes() {
+        let text = "local function f()\n\
+                    \x20 -- luacheck: globals a\n\
+                    \x20 x = 1 -- luacheck: ignore\n\
+                    \x20 -- luacheck: push ignore W0001\n\
+                    \x20 y = 2\n\
+                    \x20 -- luacheck: pop\n\
+                    \x20 z = 3\n\
+                    end\n\
+                    w = 4\n";
+        let directives = directives(text);
+        let offset = |pattern: &str| TextSize::from(text.find(pattern).unwrap() as u32);
+        assert_eq!(
+            directives.lint_level("W0002", offset("x = 1")),
+            Some(LintLevel::Allow)
+        );
+        assert_eq!(directives.lint_level("W0002", offset("y = 2")), None);
+        assert_eq!(
+            directives.lint_level("W0001", offset("y = 2")),
+            Some(LintLevel::Allow)
+        );
+        assert_eq!(directives.lint_level("W0001", offset("z = 3")), None);
+    }
+
+    #[test]
+    fn test_selene_directives() {
+        let text = "--# selene: deny(W0001)\n\
+                    -- selene: allow(W0001, W0002)\n\
+                    local t = {\n\
+                    \x20 -- selene: warn(W0003) unknown\n\
+                    \x20 a = 1,\n\
+                    \x20 b = 2,\n\
+                    }\n\
+                    x = 1\n";
+        let directives = directives(text);
+        let offset = |pattern: &str| TextSize::from(text.find(pattern).unwrap() as u32);
+        assert_eq!(
+            directives.lint_level("W0001", offset("b = 2")),
+            Some(LintLevel::Allow)
+        );
+        assert_eq!(
+            directives.lint_level("W0001", offset("x = 1")),
+            Some(LintLevel::Deny)
+        );
+        assert_eq!(
+            directives.lint_level("W0003", offset("a = 1")),
+            Some(LintLevel::Warn)
+        );
+        assert_eq!(directives.lint_level("W0003", offset("b = 2")), None);
+        assert_eq!(
+            directives.directives()[2].options[1],
+            DirectiveOption::Other("unknown".to_string())
+        );
+
+        let range = |pattern: &str| TextRange::at(offset(pattern), TextSize::from(1));
+        let diagnostics = vec![
+            Diagnostic::warning(DiagnosticCode("W0001"), range("x = 1"), ""),
+            Diagnostic::hint(DiagnosticCode("W0002"), range("b = 2"), ""),
+            Diagnostic::warning(DiagnosticCode("W0001"), range("a = 1"), ""),
+        ];
+        let severities: Vec<_> = directives
+            .apply(diagnostics)
+            .into_iter()
+            .map(|diagnostic| diagnostic.severity)
+            .collect();
+        assert_eq!(severities, vec![Severity::Error]);
+    }
+}
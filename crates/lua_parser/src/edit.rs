@@ -0,0 +1,549 @@
+//! Edits that move and remove whole statements the way a person would, for refactorings that
+//! produce code which is read and maintained afterwards.
+//!
+//! A statement on lines of its own is edited together with the comment lines directly above it,
+//! its trailing comment and its line break. Moved lines are re-indented to the indentation of the
+//! statements at the new position, and a blank line is removed if removing a statement would
+//! leave two blank lines behind. A statement that shares its line with other code is edited
+//! without the line.
+//!
+//! `IndentLevel` describes the indentation of existing code, so that code which is inserted can
+//! be indented like the code around it.
+
+use crate::{
+    ast::{Block, Stmt},
+    detect_line_endings, AstNode, IndentStyle, InsertPosition,
+    SyntaxKind::*,
+    SyntaxNode, SyntaxToken, TextEdit, TextEditBuilder, TextRange, TextSize,
+};
+use std::{collections::HashMap, fmt};
+
+/// The indentation of a line: the whitespace in front of its first token.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct IndentLevel(String);
+
+impl IndentLevel {
+    /// Returns the indentation of the line on which `node` starts.
+    pub fn from_node(node: &SyntaxNode) -> IndentLevel {
+        match node.first_token() {
+            Some(token) => IndentLevel::from_token(&token),
+            None => IndentLevel::default(),
+        }
+    }
+
+    /// Returns the indentation of the line on which `token` starts.
+    pub fn from_token(token: &SyntaxToken) -> IndentLevel {
+        let mut prev = token.prev_token();
+        while let Some(token) = prev {
+            if let Some(idx) = token.text().rfind('\n') {
+                if token.kind() == WHITESPACE {
+                    return IndentLevel::from_whitespace(&token.text()[idx + 1..]);
+                }
+                // The line starts inside a multi-line string or comment
+                return IndentLevel::default();
+            }
+            if token.prev_token().is_none() && token.kind() == WHITESPACE {
+                return IndentLevel::from_whitespace(token.text());
+            }
+            prev = token.prev_token();
+        }
+        IndentLevel::default()
+    }
+
+    /// Returns the indentation of the statements of `block`, e.g. for a statement that is added to
+    /// it. The indentation of an empty block is one level deeper than the line it starts on, with
+    /// the unit of indentation that is used in the rest of the file.
+    pub fn of_block(block: &Block) -> IndentLevel {
+        let first = block
+            .statements()
+            .next()
+            .and_then(|stmt| stmt.syntax().first_token());
+        if let Some(first) = first {
+            if line_start_before(&first).is_some() {
+                return IndentLevel::from_token(&first);
+            }
+        }
+        let owner = block
+            .syntax()
+            .parent()
+            .unwrap_or_else(|| block.syntax().clone());
+        if owner.kind() == CHUNK {
+            return IndentLevel::default();
+        }
+        IndentLevel::from_node(&owner).increase(infer_indent_style(&root(&owner)))
+    }
+
+    fn from_whitespace(text: &str) -> IndentLevel {
+        let len = text.len() - text.trim_start_matches([' ', '\t']).len();
+        IndentLevel(text[..len].to_string())
+    }
+
+    /// Returns the indentation one level deeper.
+    pub fn increase(&self, style: IndentStyle) -> IndentLevel {
+        IndentLevel(format!("{}{}", self.0, style.unit()))
+    }
+
+    /// Returns the indentation one level shallower, or this indentation if it doesn't end with a
+    /// level of `style`.
+    pub fn decrease(&self, style: IndentStyle) -> IndentLevel {
+        let unit = style.unit();
+        match self.0.strip_suffix(unit.as_str()) {
+            Some(rest) => IndentLevel(rest.to_string()),
+            None => self.clone(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Indents `text`, e.g. code that is generated without indentation, for an insertion at the
+    /// start of a line with this indentation or after the indentation of such a line. Every line
+    /// but the first is indented, except for empty lines.
+    pub fn indent_text(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        for (idx, line) in text.split_inclusive('\n').enumerate() {
+            if idx > 0 && !line.trim_end_matches(['\r', '\n']).is_empty() {
+                result.push_str(&self.0);
+            }
+            result.push_str(line);
+        }
+        result
+    }
+
+    /// Returns the text of `node` with this indentation instead of the indentation of the line it
+    /// starts on, e.g. to move it into another block. Lines inside multi-line strings and comments
+    /// are kept as they are.
+    pub fn reindent_node(&self, node: &SyntaxNode) -> String {
+        let root = root(node);
+        let text = root.text().to_string();
+        let range = node.text_range();
+        // The first line starts after the indentation
+        let first_line_len = text[range]
+            .find('\n')
+            .map_or(range.len(), |idx| TextSize::from(idx as u32 + 1));
+        let rest = TextRange::new(range.start() + first_line_len, range.end());
+        let from = IndentLevel::from_node(node);
+        let mut result = text[TextRange::at(range.start(), first_line_len)].to_string();
+        result.push_str(&reindent(&root, &text, rest, &from, self));
+        result
+    }
+}
+
+impl fmt::Display for IndentLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Returns the unit of indentation that is used most in the tree of `root`: the difference between
+/// the indentation of the statements of a block and the line the block starts on. Returns four
+/// spaces, the default of `FmtConfig`, if no block is indented.
+pub fn infer_indent_style(root: &SyntaxNode) -> IndentStyle {
+    let mut counts: HashMap<IndentStyle, usize> = HashMap::new();
+    for block in root.descendants().filter_map(Block::cast) {
+        let owner = match block.syntax().parent() {
+            Some(owner) if owner.kind() != CHUNK => owner,
+            _ => continue,
+        };
+        let first = block
+            .statements()
+            .next()
+            .and_then(|stmt| stmt.syntax().first_token());
+        let first = match first {
+            Some(first) if line_start_before(&first).is_some() => first,
+            _ => continue,
+        };
+        let outer = IndentLevel::from_node(&owner);
+        let inner = IndentLevel::from_token(&first);
+        let style = match inner.0.strip_prefix(outer.as_str()) {
+            Some("\t") => IndentStyle::Tabs,
+            Some(unit) if !unit.is_empty() && unit.chars().all(|c| c == ' ') => {
+                IndentStyle::Spaces(unit.len())
+            }
+            _ => continue,
+        };
+        *counts.entry(style).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(style, count)| (count, style == IndentStyle::Spaces(4)))
+        .map_or(IndentStyle::Spaces(4), |(style, _)| style)
+}
+
+/// Removes `stmt` together with its comments and line, see the module documentation.
+pub fn remove_statement(stmt: &Stmt) -> TextEdit {
+    let root = root(stmt.syntax());
+    TextEdit::delete(removed_range(&root, &StatementLines::new(stmt.syntax())))
+}
+
+/// Moves `stmt` to `position`: before or after another statement, which can be in another block,
+/// or to the start or the end of the block of `stmt`. `InsertPosition::Last` moves the statement
+/// in front of a `return` or `break` that ends the block. Returns `None` if the position is inside
+/// `stmt` itself.
+pub fn move_statement(stmt: &Stmt, position: InsertPosition<Stmt>) -> Option<TextEdit> {
+    let stmt = stmt.syntax();
+    let (anchor, before) = match position {
+        InsertPosition::Before(anchor) => (anchor, true),
+        InsertPosition::After(anchor) => (anchor, false),
+        InsertPosition::First => (block_statements(stmt)?.into_iter().next()?, true),
+        InsertPosition::Last => {
+            let last = block_statements(stmt)?.pop()?;
+            let ends_block = matches!(last, Stmt::ReturnStmt(_) | Stmt::BreakStmt(_));
+            (last, ends_block)
+        }
+    };
+    let anchor = anchor.syntax();
+    if anchor == stmt {
+        return Some(TextEdit::default());
+    }
+    if stmt.text_range().contains_range(anchor.text_range()) {
+        return None;
+    }
+
+    let root = root(stmt);
+    let text = root.text().to_string();
+    let moved = StatementLines::new(stmt);
+    let target = StatementLines::new(anchor);
+    let (offset, insert) = if moved.own_lines && target.own_lines {
+        let mut lines = reindent(&root, &text, moved.range, &moved.indent, &target.indent);
+        let offset = if before {
+            target.range.start()
+        } else {
+            target.range.end()
+        };
+        // The last line of the file may have no line break
+        let line_ending = detect_line_endings(&text).dominant();
+        if !lines.ends_with('\n') {
+            lines.push_str(line_ending.as_str());
+        }
+        if usize::from(offset) == text.len() && !text.ends_with('\n') {
+            lines.truncate(lines.trim_end_matches(['\r', '\n']).len());
+            lines.insert_str(0, line_ending.as_str());
+        }
+        (offset, lines)
+    } else if before {
+        (anchor.text_range().start(), format!("{}; ", stmt.text()))
+    } else {
+        (anchor.text_range().end(), format!("; {}", stmt.text()))
+    };
+
+    let removed = removed_range(&root, &moved);
+    if removed.start() < offset && offset < removed.end() {
+        return None;
+    }
+    let mut edit = TextEditBuilder::default();
+    edit.delete(removed);
+    edit.insert(offset, insert);
+    Some(edit.finish())
+}
+
+/// The text that belongs to a statement.
+struct StatementLines {
+    /// The lines of the statement and its comments if `own_lines`, or else the statement, its `;`
+    /// and the spaces after it
+    range: TextRange,
+    /// Whether no other code is on the lines of the statement
+    own_lines: bool,
+    /// The indentation of the first line of the statement
+    indent: IndentLevel,
+}
+
+impl StatementLines {
+    fn new(stmt: &SyntaxNode) -> StatementLines {
+        let range = stmt.text_range();
+        let first = stmt.first_token();
+        let last = stmt.last_token();
+        let line_start = first.as_ref().and_then(line_start_before);
+
+        // A `;`, a comment and the line break after the statement
+        let mut end = range.end();
+        let mut line_end = None;
+        let mut has_comment = false;
+        let mut token = last.and_then(|last| last.next_token());
+        while let Some(next) = &token {
+            match next.kind() {
+                SEMI if !has_comment && end == range.end() => end = next.text_range().end(),
+                WHITESPACE => match next.text().find('\n') {
+                    Some(idx) => {
+                        let start = next.text_range().start();
+                        line_end = Some(start + TextSize::from(idx as u32 + 1));
+                        break;
+                    }
+                    None if !has_comment => end = next.text_range().end(),
+                    None => {}
+                },
+                COMMENT => has_comment = true,
+                _ => break,
+            }
+            token = next.next_token();
+        }
+        if token.is_none() {
+            line_end = Some(root(stmt).text_range().end());
+        }
+
+        let (line_start, line_end) = match (line_start, line_end) {
+            (Some(line_start), Some(line_end)) => (line_start, line_end),
+            _ => {
+                return StatementLines {
+                    range: TextRange::new(range.start(), end),
+                    own_lines: false,
+                    indent: IndentLevel::default(),
+                }
+            }
+        };
+        let indent = IndentLevel::from_node(stmt);
+
+        // The comment lines directly above the statement, up to a blank line
+        let mut start = line_start;
+        let mut token = first;
+        while let Some(line_first) = token {
+            let comment = line_first
+                .prev_token()
+                .filter(|ws| ws.kind() == WHITESPACE && ws.text().matches('\n').count() == 1)
+                .and_then(|ws| ws.prev_token())
+                .filter(|comment| comment.kind() == COMMENT);
+            token = match comment.as_ref().and_then(line_start_before) {
+                Some(comment_start) => {
+                    start = comment_start;
+                    comment
+                }
+                None => None,
+            };
+        }
+        StatementLines {
+            range: TextRange::new(start, line_end),
+            own_lines: true,
+            indent,
+        }
+    }
+}
+
+/// Returns the start of the line of `token` if only whitespace is in front of it on its line.
+fn line_start_before(token: &SyntaxToken) -> Option<TextSize> {
+    let prev = match token.prev_token() {
+        Some(prev) => prev,
+        None => return Some(token.text_range().start()),
+    };
+    if prev.kind() != WHITESPACE {
+        return None;
+    }
+    match prev.text().rfind('\n') {
+        Some(idx) => Some(prev.text_range().start() + TextSize::from(idx as u32 + 1)),
+        None if prev.prev_token().is_none() => Some(prev.text_range().start()),
+        None => None,
+    }
+}
+
+/// Returns the range that is removed with a statement: its lines, and a blank line after them if
+/// they are between a blank line or the start of the file and another blank line.
+fn removed_range(root: &SyntaxNode, lines: &StatementLines) -> TextRange {
+    let range = lines.range;
+    if !lines.own_lines {
+        return range;
+    }
+    let text = root.text().to_string();
+    let before = text[..usize::from(range.start())].trim_end_matches([' ', '\t', '\r']);
+    let follows_blank_line = before.is_empty() || before.ends_with("\n\n");
+    let after = &text[usize::from(range.end())..];
+    let blank_line = after
+        .find('\n')
+        .filter(|&idx| after[..idx].trim().is_empty());
+    match blank_line {
+        Some(idx) if follows_blank_line => {
+            TextRange::new(range.start(), range.end() + TextSize::from(idx as u32 + 1))
+        }
+        _ => range,
+    }
+}
+
+/// Returns the text in `range` with the indentation `to` instead of `from` on every line. Lines
+/// inside multi-line strings and comments are kept as they are.
+fn reindent(
+    root: &SyntaxNode,
+    text: &str,
+    range: TextRange,
+    from: &IndentLevel,
+    to: &IndentLevel,
+) -> String {
+    let mut result = String::new();
+    let mut line_start = range.start();
+    for line in text[range].split_inclusive('\n') {
+        let in_token = root
+            .token_at_offset(line_start)
+            .right_biased()
+            .is_some_and(|token| {
+                token.text_range().start() < line_start && matches!(token.kind(), STRING | COMMENT)
+            });
+        match line.strip_prefix(from.as_str()) {
+            Some(rest) if !in_token => {
+                result.push_str(to.as_str());
+                result.push_str(rest);
+            }
+            _ => result.push_str(line),
+        }
+        line_start += TextSize::of(line);
+    }
+    result
+}
+
+fn block_statements(stmt: &SyntaxNode) -> Option<Vec<Stmt>> {
+    let block = stmt.parent().and_then(Block::cast)?;
+    Some(block.statements().collect())
+}
+
+fn root(node: &SyntaxNode) -> SyntaxNode {
+    node.ancestors().last().unwrap_or_else(|| node.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chunk;
+
+    /// Returns the statements of `text` in the order of the text, including nested ones.
+    fn statements(chunk: &Chunk) -> Vec<Stmt> {
+        chunk
+            .syntax()
+            .descendants()
+            .filter_map(Stmt::cast)
+            .collect()
+    }
+
+    #[test]
+    fn test_indent_level() {
+        let text = "if a then\n\tf(function()\n\t\tg([[\n  x]])\n\tend)\nend\n\nwhile b do end";
+        let chunk = Chunk::parse(text).tree();
+        let stmts = statements(&chunk);
+        let (f, g) = (&stmts[1], &stmts[2]);
+        assert_eq!(IndentLevel::from_node(f.syntax()).as_str(), "\t");
+        assert_eq!(IndentLevel::from_node(g.syntax()).as_str(), "\t\t");
+        assert_eq!(IndentLevel::from_node(stmts[0].syntax()).as_str(), "");
+        assert_eq!(infer_indent_style(chunk.syntax()), IndentStyle::Tabs);
+
+        let blocks: Vec<_> = chunk
+            .syntax()
+            .descendants()
+            .filter_map(Block::cast)
+            .collect();
+        assert_eq!(IndentLevel::of_block(&blocks[1]).as_str(), "\t");
+        // `while b do end`
+        assert_eq!(IndentLevel::of_block(&blocks[3]).as_str(), "\t");
+
+        let level = IndentLevel::from_node(g.syntax());
+        assert_eq!(level.decrease(IndentStyle::Tabs).as_str(), "\t");
+        assert_eq!(level.decrease(IndentStyle::Spaces(2)), level);
+        assert_eq!(level.increase(IndentStyle::Spaces(2)).to_string(), "\t\t  ");
+        assert_eq!(
+            level.indent_text("do\n    x()\n\nend"),
+            "do\n\t\t    x()\n\n\t\tend"
+        );
+        assert_eq!(
+            IndentLevel::default().reindent_node(f.syntax()),
+            "f(function()\n\tg([[\n  x]])\nend)"
+        );
+    }
+
+    #[test]
+    fn test_infer_indent_style() {
+        let chunk = Chunk::parse("do\n  a()\nend\nwhile x do\n  b()\nend").tree();
+        assert_eq!(infer_indent_style(chunk.syntax()), IndentStyle::Spaces(2));
+        let chunk = Chunk::parse("do a() end").tree();
+        assert_eq!(infer_indent_style(chunk.syntax()), IndentStyle::Spaces(4));
+    }
+
+    #[test]
+    fn test_remove_statement() {
+        let text = "local a = 1\n\n-- the second\n-- statement\nlocal b = 2 -- two\n\nlocal c = 3; f() g()\n";
+        let chunk = Chunk::parse(text).tree();
+        let stmts = statements(&chunk);
+        assert_eq!(
+            remove_statement(&stmts[1]).apply(text),
+            "local a = 1\n\nlocal c = 3; f() g()\n"
+        );
+        assert_eq!(
+            remove_statement(&stmts[2]).apply(text),
+            "local a = 1\n\n-- the second\n-- statement\nlocal b = 2 -- two\n\nf() g()\n"
+        );
+        assert_eq!(
+            remove_statement(&stmts[3]).apply(text),
+            "local a = 1\n\n-- the second\n-- statement\nlocal b = 2 -- two\n\nlocal c = 3; g()\n"
+        );
+        assert_eq!(
+            remove_statement(&stmts[0]).apply(text),
+            "-- the second\n-- statement\nlocal b = 2 -- two\n\nlocal c = 3; f() g()\n"
+        );
+    }
+
+    #[test]
+    fn test_move_statement_between_blocks() {
+        let text = "\
+local function f()
+    -- checks x
+    check(x, [[
+a long string]])
+    return x
+end
+-- the answer
+local y = 42";
+        let chunk = Chunk::parse(text).tree();
+        let stmts = statements(&chunk);
+        let (check, ret, y) = (&stmts[1], &stmts[2], &stmts[3]);
+
+        let edit = move_statement(check, InsertPosition::After(y.clone())).unwrap();
+        assert_eq!(
+            edit.apply(text),
+            "\
+local function f()
+    return x
+end
+-- the answer
+local y = 42
+-- checks x
+check(x, [[
+a long string]])"
+        );
+
+        let edit = move_statement(y, InsertPosition::Before(ret.clone())).unwrap();
+        assert_eq!(
+            edit.apply(text),
+            "\
+local function f()
+    -- checks x
+    check(x, [[
+a long string]])
+    -- the answer
+    local y = 42
+    return x
+end
+"
+        );
+
+        assert_eq!(
+            move_statement(&stmts[0], InsertPosition::After(ret.clone())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reorder_statements() {
+        let text = "do\n  a()\n  b()\n  c()\n  return\nend\nd() e()";
+        let chunk = Chunk::parse(text).tree();
+        let stmts = statements(&chunk);
+        let edit = move_statement(&stmts[1], InsertPosition::Last).unwrap();
+        assert_eq!(
+            edit.apply(text),
+            "do\n  b()\n  c()\n  a()\n  return\nend\nd() e()"
+        );
+        let edit = move_statement(&stmts[3], InsertPosition::First).unwrap();
+        assert_eq!(
+            edit.apply(text),
+            "do\n  c()\n  a()\n  b()\n  return\nend\nd() e()"
+        );
+        let edit = move_statement(&stmts[6], InsertPosition::Before(stmts[5].clone())).unwrap();
+        assert_eq!(
+            edit.apply(text),
+            "do\n  a()\n  b()\n  c()\n  return\nend\ne(); d() "
+        );
+    }
+}
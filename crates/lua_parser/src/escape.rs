@@ -0,0 +1,187 @@
+//! Writes string values as Lua literals, for tools that generate code: quoted strings with the
+//! escape sequences the value needs, and long strings with a level that the content can't close.
+
+use crate::QuoteStyle;
+
+/// Returns a quoted literal that Lua reads as `value`, e.g. `"say \"hi\"\n"`.
+///
+/// The quote is chosen like the formatter does: the one preferred by `style`, unless the value
+/// contains more of it than of the other. `Preserve` has no quote to preserve and prefers `"`.
+/// Control characters and bytes that aren't valid UTF-8 are escaped, other characters are written
+/// as they are.
+pub fn escape_lua_string(value: impl AsRef<[u8]>, style: QuoteStyle) -> String {
+    let value = value.as_ref();
+    let (preferred, other) = match style {
+        QuoteStyle::Double | QuoteStyle::Preserve => (b'"', b'\''),
+        QuoteStyle::Single => (b'\'', b'"'),
+    };
+    let count = |quote: u8| value.iter().filter(|&&b| b == quote).count();
+    let quote = if count(preferred) > count(other) {
+        other
+    } else {
+        preferred
+    };
+
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push(quote as char);
+    let mut rest = value;
+    while !rest.is_empty() {
+        let (valid, invalid) = match std::str::from_utf8(rest) {
+            Ok(text) => (text, &[][..]),
+            Err(error) => {
+                let (valid, invalid) = rest.split_at(error.valid_up_to());
+                let len = error.error_len().unwrap_or(invalid.len());
+                (
+                    std::str::from_utf8(valid).expect("the prefix is valid UTF-8"),
+                    &invalid[..len],
+                )
+            }
+        };
+        let after = &rest[valid.len() + invalid.len()..];
+        for (idx, c) in valid.char_indices() {
+            let next = valid.as_bytes().get(idx + 1).or_else(|| invalid.first());
+            let next = next.or_else(|| after.first()).copied();
+            match c {
+                '\\' => result.push_str("\\\\"),
+                '\n' => result.push_str("\\n"),
+                '\r' => result.push_str("\\r"),
+                '\t' => result.push_str("\\t"),
+                '\x07' => result.push_str("\\a"),
+                '\x08' => result.push_str("\\b"),
+                '\x0b' => result.push_str("\\v"),
+                '\x0c' => result.push_str("\\f"),
+                c if c as u32 == quote as u32 => {
+                    result.push('\\');
+                    result.push(c);
+                }
+                c if c.is_ascii_control() => push_decimal_escape(&mut result, c as u8, next),
+                c => result.push(c),
+            }
+        }
+        for (idx, &byte) in invalid.iter().enumerate() {
+            let next = invalid.get(idx + 1).or_else(|| after.first()).copied();
+            push_decimal_escape(&mut result, byte, next);
+        }
+        rest = after;
+    }
+    result.push(quote as char);
+    result
+}
+
+/// Appends an escape like `\0`, which is padded to three digits if the next byte is a digit, so
+/// that it isn't read as part of the escape.
+fn push_decimal_escape(result: &mut String, byte: u8, next: Option<u8>) {
+    if next.is_some_and(|next| next.is_ascii_digit()) {
+        result.push_str(&format!("\\{:03}", byte));
+    } else {
+        result.push_str(&format!("\\{}", byte));
+    }
+}
+
+/// Returns the lowest level of long brackets that can enclose `content`, e.g. 0 for `[[a]]` and 1
+/// for `[=[a]]b]=]`, because the content would otherwise contain the closing bracket.
+pub fn long_bracket_level(content: &str) -> usize {
+    (0..)
+        .find(|&level| !closes_long_bracket(content, level))
+        .expect("some level is longer than the content")
+}
+
+/// Returns true if `content` can't be enclosed by long brackets of `level`, because the closing
+/// bracket that follows it wouldn't be the first one, e.g. because `content` ends with `]`.
+pub(crate) fn closes_long_bracket(content: &str, level: usize) -> bool {
+    let close = format!("]{}]", "=".repeat(level));
+    format!("{}]", content).contains(&close)
+}
+
+/// Returns a long string literal that Lua reads as `content`, with the lowest level that doesn't
+/// conflict with the content, e.g. `[==[a]=]b]==]`. A newline is inserted after the opening
+/// bracket if the content starts with one, because Lua skips the first newline of a long string.
+///
+/// Returns `None` if the content contains a `\r`: Lua reads every kind of line break in a long
+/// string as `\n`, so such content can only be written with `escape_lua_string`.
+pub fn long_string(content: &str) -> Option<String> {
+    if content.contains('\r') {
+        return None;
+    }
+    let equals = "=".repeat(long_bracket_level(content));
+    let newline = if content.starts_with('\n') { "\n" } else { "" };
+    Some(format!("[{}[{}{}]{}]", equals, newline, content, equals))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::LuaValue, AstNode, Chunk};
+
+    /// Reads `literal` like Lua does.
+    fn read(literal: &str) -> Option<Vec<u8>> {
+        let chunk = Chunk::parse(&format!("return {}", literal)).tree();
+        let literal = chunk
+            .syntax()
+            .descendants()
+            .find_map(crate::ast::Literal::cast)?;
+        match literal.value()? {
+            LuaValue::String(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_escape_lua_string() {
+        assert_eq!(escape_lua_string("a\"b'", QuoteStyle::Double), r#""a\"b'""#);
+        assert_eq!(
+            escape_lua_string("a\"b\"'", QuoteStyle::Double),
+            r#"'a"b"\''"#
+        );
+        assert_eq!(escape_lua_string("it's", QuoteStyle::Single), r#""it's""#);
+        assert_eq!(
+            escape_lua_string("a\\b\n", QuoteStyle::Preserve),
+            r#""a\\b\n""#
+        );
+        assert_eq!(
+            escape_lua_string("\0x\x001", QuoteStyle::Double),
+            r#""\0x\0001""#
+        );
+        assert_eq!(escape_lua_string("héllo", QuoteStyle::Double), "\"héllo\"");
+        assert_eq!(
+            escape_lua_string(b"\xff1\xfe", QuoteStyle::Double),
+            r#""\2551\254""#
+        );
+
+        let values: [&[u8]; 5] = [
+            b"plain",
+            b"\"'\\\r\n\t\x07\x08\x0b\x0c\x7f",
+            b"\x01\x02 3 \x1b[0m",
+            "\u{1F600} \u{e9}".as_bytes(),
+            b"\xe2\x82 \xc3\x28 9",
+        ];
+        for value in values.iter() {
+            for &style in [QuoteStyle::Double, QuoteStyle::Single].iter() {
+                let literal = escape_lua_string(value, style);
+                assert_eq!(read(&literal).as_deref(), Some(*value), "{}", literal);
+            }
+        }
+    }
+
+    #[test]
+    fn test_long_string() {
+        assert_eq!(long_bracket_level("a"), 0);
+        assert_eq!(long_bracket_level("a]]b"), 1);
+        assert_eq!(long_bracket_level("a]"), 1);
+        assert_eq!(long_bracket_level("a]=]b]]"), 2);
+        assert_eq!(long_bracket_level("]="), 0);
+        assert_eq!(long_string("a]]b").as_deref(), Some("[=[a]]b]=]"));
+        assert_eq!(long_string("\nx").as_deref(), Some("[[\n\nx]]"));
+        assert_eq!(long_string("a\r\nb"), None);
+
+        for content in ["", "a]", "]]", "]=", "]=]]", "\n\nline\n", "[[ nested ]]"].iter() {
+            let literal = long_string(content).unwrap();
+            assert_eq!(
+                read(&literal).as_deref(),
+                Some(content.as_bytes()),
+                "{}",
+                literal
+            );
+        }
+    }
+}
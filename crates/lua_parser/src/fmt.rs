@@ -0,0 +1,607 @@
+//! Pretty-prints Lua source code.
+//!
+//! The formatter works on the tokens of a chunk. Line breaks of the original source are kept,
+//! every line is re-indented based on the blocks and brackets that are open at its start and the
+//! spacing between tokens is normalized. Lines that exceed the maximum width are broken up at
+//! their first argument list or table constructor. Comments are preserved as written.
+
+mod codegen;
+
+pub(crate) use self::codegen::to_source;
+
+use crate::{
+    blocks::{BlockChange, BlockTracker},
+    line_endings::with_line_ending,
+    tokenize, AstNode, Chunk, LineEnding, SmolStr, SourceMap,
+    SyntaxKind::{self, *},
+    TextRange, TextSize,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndentStyle {
+    /// Indent with the given number of spaces per level
+    Spaces(usize),
+    Tabs,
+}
+
+impl IndentStyle {
+    /// Returns the text of a single level of indentation.
+    pub(crate) fn unit(self) -> String {
+        match self {
+            IndentStyle::Spaces(width) => " ".repeat(width),
+            IndentStyle::Tabs => "\t".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// Prefer `"`, unless the string contains more `"` than `'`
+    Double,
+    /// Prefer `'`, unless the string contains more `'` than `"`
+    Single,
+    /// Leave the quotes of strings as written
+    Preserve,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FmtConfig {
+    pub indent_style: IndentStyle,
+    /// The maximum number of characters on a line before it is wrapped. A tab counts as four
+    /// characters.
+    pub max_width: usize,
+    pub quote_style: QuoteStyle,
+    /// The line ending of every line, including the line breaks in comments and long strings
+    pub line_ending: LineEnding,
+}
+
+impl Default for FmtConfig {
+    fn default() -> FmtConfig {
+        FmtConfig {
+            indent_style: IndentStyle::Spaces(4),
+            max_width: 100,
+            quote_style: QuoteStyle::Double,
+            line_ending: LineEnding::Lf,
+        }
+    }
+}
+
+const TAB_WIDTH: usize = 4;
+
+/// Formats `chunk` according to `config`.
+pub fn format(chunk: &Chunk, config: &FmtConfig) -> String {
+    format_with_source_map(chunk, config).0
+}
+
+/// Formats `chunk` according to `config` and returns a `SourceMap` which maps every token in the
+/// formatted text to its range in the original text.
+pub fn format_with_source_map(chunk: &Chunk, config: &FmtConfig) -> (String, SourceMap) {
+    let tokens: Vec<_> = chunk
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .map(|token| (token.kind(), SmolStr::new(token.text()), token.text_range()))
+        .collect();
+
+    let lines = split_lines(
+        tokens
+            .iter()
+            .map(|(kind, text, range)| (*kind, text.as_str(), *range)),
+        config,
+    );
+    let lines = indent_lines(lines);
+
+    let mut result = String::new();
+    let mut source_map = SourceMap::new();
+    for (line_idx, line) in lines.iter().enumerate() {
+        if line.blank_line_before && line_idx > 0 {
+            result.push_str(config.line_ending.as_str());
+        }
+        for (indent, units) in wrap_line(&line.units, line.indent, config) {
+            push_indent(&mut result, indent, config.indent_style);
+            render_units_into(&mut result, &units, Some(&mut source_map));
+            result.push_str(config.line_ending.as_str());
+        }
+    }
+    (result, source_map)
+}
+
+/// A token or a sequence of adjacent symbol tokens that form a single operator, like `==`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Unit {
+    kind: SyntaxKind,
+    text: String,
+    /// The range of the unit in the original text
+    range: TextRange,
+}
+
+#[derive(Debug)]
+struct Line {
+    units: Vec<Unit>,
+    indent: usize,
+    blank_line_before: bool,
+}
+
+/// Splits the tokens into lines of units at the line breaks of the source. Trivia other than
+/// comments is dropped and runs of empty lines are collapsed into a single blank line.
+fn split_lines<'a>(
+    tokens: impl Iterator<Item = (SyntaxKind, &'a str, TextRange)>,
+    config: &FmtConfig,
+) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut units: Vec<Unit> = Vec::new();
+    let mut blank_line_before = false;
+    let mut joint = false;
+    for (kind, text, range) in tokens {
+        if kind == WHITESPACE {
+            let newlines = text.matches('\n').count();
+            if newlines > 0 {
+                if !units.is_empty() {
+                    lines.push(Line {
+                        units: std::mem::take(&mut units),
+                        indent: 0,
+                        blank_line_before,
+                    });
+                    blank_line_before = false;
+                }
+                blank_line_before |= newlines > 1 && !lines.is_empty();
+            }
+            joint = false;
+            continue;
+        }
+
+        if joint && kind.is_symbol() {
+            if let Some(last) = units.last_mut() {
+                let glued = format!("{}{}", last.text, text);
+                if let Some(glued_kind) = composite_kind(&glued) {
+                    last.kind = glued_kind;
+                    last.text = glued;
+                    last.range = TextRange::new(last.range.start(), range.end());
+                    continue;
+                }
+            }
+        }
+
+        let text = if kind == STRING {
+            normalize_quotes(text, config.quote_style)
+        } else {
+            text.to_string()
+        };
+        // Comments and long strings can span multiple lines
+        let text = with_line_ending(&text, config.line_ending);
+        units.push(Unit { kind, text, range });
+        joint = kind.is_symbol();
+    }
+    if !units.is_empty() {
+        lines.push(Line {
+            units,
+            indent: 0,
+            blank_line_before,
+        });
+    }
+    lines
+}
+
+fn composite_kind(text: &str) -> Option<SyntaxKind> {
+    let kind = match text {
+        "==" => EQEQ,
+        "<=" => LTEQ,
+        ">=" => GTEQ,
+        ".." => DOTDOT,
+        "..." => DOTDOTDOT,
+        "::" => COLONCOLON,
+        "//" => SLASHSLASH,
+        "<<" => SHL,
+        ">>" => SHR,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// Computes the indentation level of every line. A line is indented once for every earlier line
+/// that opened a block or bracket that is still open, regardless of how many were opened on that
+/// line, so `f(function()` only indents the body of the function once. A line that starts by
+/// closing something opened on an earlier line is not indented for that line at all, so the `)`
+/// of a wrapped `if f(` is indented like the `if`, even though the `if` is still open.
+fn indent_lines(mut lines: Vec<Line>) -> Vec<Line> {
+    let mut blocks = BlockTracker::default();
+    let mut brackets = Vec::new();
+    for (line_idx, line) in lines.iter_mut().enumerate() {
+        let mut units = line.units.iter().peekable();
+        let mut closed_lines = Vec::new();
+        while let Some(unit) = units.next_if(|unit| is_closer(unit.kind)) {
+            closed_lines.extend(visit(&mut blocks, &mut brackets, line_idx, unit.kind));
+        }
+
+        let mut open_lines: Vec<usize> = blocks
+            .open_blocks()
+            .iter()
+            .chain(brackets.iter())
+            .filter(|line| !closed_lines.contains(line))
+            .cloned()
+            .collect();
+        // `else` and `elseif` are indented like the `if` they belong to
+        let is_else = units
+            .peek()
+            .is_some_and(|unit| unit.kind == ELSE_KW || unit.kind == ELSEIF_KW);
+        if let (true, Some(&open)) = (is_else, blocks.open_blocks().last()) {
+            if let Some(idx) = open_lines.iter().position(|&line| line == open) {
+                open_lines.remove(idx);
+            }
+        }
+        open_lines.sort();
+        open_lines.dedup();
+        line.indent = open_lines.len();
+
+        for unit in units {
+            visit(&mut blocks, &mut brackets, line_idx, unit.kind);
+        }
+    }
+    lines
+}
+
+fn is_closer(kind: SyntaxKind) -> bool {
+    matches!(kind, END_KW | UNTIL_KW | R_PAREN | R_CURLY | R_BRACKET)
+}
+
+/// Updates the open blocks and brackets with a unit on line `line_idx`. Returns the line that
+/// opened the block or bracket that the unit closes.
+fn visit(
+    blocks: &mut BlockTracker,
+    brackets: &mut Vec<usize>,
+    line_idx: usize,
+    kind: SyntaxKind,
+) -> Option<usize> {
+    match kind {
+        L_PAREN | L_CURLY | L_BRACKET => {
+            brackets.push(line_idx);
+            None
+        }
+        R_PAREN | R_CURLY | R_BRACKET => brackets.pop(),
+        _ => match blocks.visit(line_idx, kind) {
+            BlockChange::Closed(open) => Some(open),
+            _ => None,
+        },
+    }
+}
+
+/// Breaks up a line that is too long at its first argument list or table constructor, placing
+/// every element on its own line. The resulting lines are wrapped again if they are still too
+/// long.
+fn wrap_line(units: &[Unit], indent: usize, config: &FmtConfig) -> Vec<(usize, Vec<Unit>)> {
+    let width = indent_width(indent, config.indent_style) + render_units(units).chars().count();
+    let is_verbatim = units
+        .iter()
+        .any(|unit| unit.kind == COMMENT || unit.text.contains('\n'));
+    if width <= config.max_width || is_verbatim {
+        return vec![(indent, units.to_vec())];
+    }
+
+    let (open, close) = match find_wrap_point(units) {
+        Some(range) => range,
+        None => return vec![(indent, units.to_vec())],
+    };
+
+    let mut result = vec![(indent, units[..=open].to_vec())];
+    let mut element_start = open + 1;
+    let mut depth = 0;
+    for (idx, unit) in units.iter().enumerate().take(close).skip(open + 1) {
+        match unit.kind {
+            L_PAREN | L_CURLY | L_BRACKET => depth += 1,
+            R_PAREN | R_CURLY | R_BRACKET => depth -= 1,
+            COMMA | SEMI if depth == 0 => {
+                result.extend(wrap_line(&units[element_start..=idx], indent + 1, config));
+                element_start = idx + 1;
+            }
+            _ => (),
+        }
+    }
+    if element_start < close {
+        result.extend(wrap_line(&units[element_start..close], indent + 1, config));
+    }
+    result.extend(wrap_line(&units[close..], indent, config));
+    result
+}
+
+/// Returns the indices of the brackets that enclose the first argument list or table constructor
+/// with multiple elements, or the first non-empty one if there is none with multiple elements.
+fn find_wrap_point(units: &[Unit]) -> Option<(usize, usize)> {
+    let mut candidates = Vec::new();
+    for (open, unit) in units.iter().enumerate() {
+        let close_kind = match unit.kind {
+            L_PAREN => R_PAREN,
+            L_CURLY => R_CURLY,
+            _ => continue,
+        };
+        let mut depth = 0;
+        let mut has_separator = false;
+        for (idx, unit) in units.iter().enumerate().skip(open + 1) {
+            match unit.kind {
+                L_PAREN | L_CURLY | L_BRACKET => depth += 1,
+                R_PAREN | R_CURLY | R_BRACKET if depth == 0 => {
+                    if unit.kind == close_kind && idx > open + 1 {
+                        candidates.push((open, idx, has_separator));
+                    }
+                    break;
+                }
+                R_PAREN | R_CURLY | R_BRACKET => depth -= 1,
+                COMMA | SEMI if depth == 0 => has_separator = true,
+                _ => (),
+            }
+        }
+    }
+    candidates
+        .iter()
+        .find(|(_, _, has_separator)| *has_separator)
+        .or_else(|| candidates.first())
+        .map(|&(open, close, _)| (open, close))
+}
+
+fn indent_width(indent: usize, style: IndentStyle) -> usize {
+    match style {
+        IndentStyle::Spaces(width) => indent * width,
+        IndentStyle::Tabs => indent * TAB_WIDTH,
+    }
+}
+
+fn push_indent(buf: &mut String, indent: usize, style: IndentStyle) {
+    for _ in 0..indent {
+        buf.push_str(&style.unit());
+    }
+}
+
+fn render_units(units: &[Unit]) -> String {
+    let mut result = String::new();
+    render_units_into(&mut result, units, None);
+    result
+}
+
+fn render_units_into(buf: &mut String, units: &[Unit], mut source_map: Option<&mut SourceMap>) {
+    let mut prev: Option<&Unit> = None;
+    let mut prev_is_unary = false;
+    let mut in_label = false;
+    for unit in units {
+        let is_unary = match unit.kind {
+            HASH | NOT_KW => true,
+            MINUS | TILDE => prev.is_none_or(|prev| !ends_expression(prev.kind)),
+            _ => false,
+        };
+        if let Some(prev) = prev {
+            let space = if unit.kind == COLONCOLON || prev.kind == COLONCOLON {
+                !in_label
+            } else {
+                needs_space(prev, prev_is_unary, unit)
+            };
+            if space {
+                buf.push(' ');
+            }
+        }
+        if unit.kind == COLONCOLON {
+            in_label = !in_label;
+        }
+        if let Some(source_map) = source_map.as_mut() {
+            let start = TextSize::of(buf.as_str());
+            source_map.add(
+                TextRange::at(start, TextSize::of(&unit.text)),
+                unit.range,
+            );
+        }
+        buf.push_str(&unit.text);
+        prev = Some(unit);
+        prev_is_unary = is_unary;
+    }
+}
+
+/// Returns true if a token of this kind can be the last token of an expression.
+fn ends_expression(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        IDENT
+            | INT_NUMBER
+            | FLOAT_NUMBER
+            | STRING
+            | R_PAREN
+            | R_BRACKET
+            | R_CURLY
+            | END_KW
+            | NIL_KW
+            | TRUE_KW
+            | FALSE_KW
+            | DOTDOTDOT
+    )
+}
+
+fn needs_space(prev: &Unit, prev_is_unary: bool, next: &Unit) -> bool {
+    // Never join tokens into a different token, e.g. `- -x` into a comment, `[ [[s]]` into a long
+    // bracket or the `. ..` of code with errors into `...`.
+    prefers_space(prev, prev_is_unary, next) || !lexes_apart(prev, next)
+}
+
+fn prefers_space(prev: &Unit, prev_is_unary: bool, next: &Unit) -> bool {
+    if prev.kind == COMMENT || next.kind == COMMENT {
+        return true;
+    }
+    if prev_is_unary {
+        return prev.kind == NOT_KW;
+    }
+    match (prev.kind, next.kind) {
+        (_, COMMA) | (_, SEMI) => false,
+        (COMMA, _) | (SEMI, _) => true,
+        (DOT, _) | (_, DOT) | (COLON, _) | (_, COLON) => false,
+        // The placeholders of templates, `$name`
+        (DOLLAR, _) => false,
+        (L_PAREN, _) | (L_BRACKET, _) | (_, R_PAREN) | (_, R_BRACKET) => false,
+        (L_CURLY, R_CURLY) => false,
+        (FUNCTION_KW, L_PAREN) => false,
+        (prev, L_PAREN) | (prev, L_BRACKET) => !ends_expression(prev) || prev == END_KW,
+        _ => true,
+    }
+}
+
+/// Returns true if the text of `prev` directly followed by the text of `next` is split into the
+/// same two units again.
+fn lexes_apart(prev: &Unit, next: &Unit) -> bool {
+    // The leading space keeps a `#` from being lexed as a shebang
+    let text = format!(" {}{}", prev.text, next.text);
+    let mut offset = TextSize::from(0);
+    let tokens = tokenize(&text).into_iter().map(|token| {
+        let range = TextRange::at(offset, token.len);
+        offset += token.len;
+        (token.kind, &text[range], range)
+    });
+    let config = FmtConfig {
+        quote_style: QuoteStyle::Preserve,
+        ..FmtConfig::default()
+    };
+    match split_lines(tokens, &config).as_slice() {
+        [line] => line
+            .units
+            .iter()
+            .map(|unit| unit.kind)
+            .eq([prev.kind, next.kind].iter().cloned()),
+        _ => false,
+    }
+}
+
+/// Converts the quotes of a short string literal to the quote preferred by `style`, unless that
+/// would require more escaped quotes than the original.
+fn normalize_quotes(literal: &str, style: QuoteStyle) -> String {
+    let (preferred, other) = match style {
+        QuoteStyle::Double => ('"', '\''),
+        QuoteStyle::Single => ('\'', '"'),
+        QuoteStyle::Preserve => return literal.to_string(),
+    };
+    if !literal.starts_with(other) || literal.len() < 2 || !literal.ends_with(other) {
+        return literal.to_string();
+    }
+    let content = &literal[1..literal.len() - 1];
+    let needs_more_escapes = content.matches(preferred).count() > content.matches(other).count();
+    if content.contains('\n') || needs_more_escapes {
+        return literal.to_string();
+    }
+
+    let mut result = String::with_capacity(literal.len());
+    result.push(preferred);
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) if escaped == other => result.push(other),
+                Some(escaped) => {
+                    result.push('\\');
+                    result.push(escaped);
+                }
+                None => result.push('\\'),
+            },
+            c if c == preferred => {
+                result.push('\\');
+                result.push(preferred);
+            }
+            c => result.push(c),
+        }
+    }
+    result.push(preferred);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flat_chunk;
+
+    fn check(text: &str, config: &FmtConfig, expected: &str) {
+        assert_eq!(format(&flat_chunk(text), config), expected);
+    }
+
+    #[test]
+    fn test_indent_and_spacing() {
+        check(
+            "local function f(a,b)\nif a==b then\nreturn -a..'x'\nelse\n\n\n  return #t[1]\nend\nend",
+            &FmtConfig::default(),
+            "local function f(a, b)\n    if a == b then\n        return -a .. \"x\"\n    else\n\n        return #t[1]\n    end\nend\n",
+        );
+    }
+
+    #[test]
+    fn test_nested_callbacks() {
+        check(
+            "call(function(x) -- comment\nprint(x)\nend)",
+            &FmtConfig {
+                indent_style: IndentStyle::Tabs,
+                ..FmtConfig::default()
+            },
+            "call(function(x) -- comment\n\tprint(x)\nend)\n",
+        );
+    }
+
+    #[test]
+    fn test_wrap_long_lines() {
+        let config = FmtConfig {
+            indent_style: IndentStyle::Spaces(2),
+            max_width: 20,
+            quote_style: QuoteStyle::Preserve,
+            line_ending: LineEnding::Lf,
+        };
+        check(
+            "local t = {first = 1, second = 'two'}",
+            &config,
+            "local t = {\n  first = 1,\n  second = 'two'\n}\n",
+        );
+        check(
+            "f(a, g(bbbbbbbbbbbbbbbbbbbbb, c))",
+            &config,
+            "f(\n  a,\n  g(\n    bbbbbbbbbbbbbbbbbbbbb,\n    c\n  )\n)\n",
+        );
+    }
+
+    #[test]
+    fn test_format_twice() {
+        let text = "local function very_long_function_name(first_argument, second_argument, third_argument, fourth) return first_argument end\nif check(aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa, bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb) then\nx = f(g(aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa, bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb) .. c)\nend";
+        let once = format(&flat_chunk(text), &FmtConfig::default());
+        assert_eq!(
+            once,
+            "local function very_long_function_name(\n    first_argument,\n    second_argument,\n    third_argument,\n    fourth\n) return first_argument end\nif check(\n    aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa,\n    bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\n) then\n    x = f(g(\n        aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa,\n        bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\n    ) .. c)\nend\n"
+        );
+        assert_eq!(format(&flat_chunk(&once), &FmtConfig::default()), once);
+    }
+
+    #[test]
+    fn test_keep_tokens_apart() {
+        check(
+            "x = a . .. b\ny = - -z\nz = 1 .x",
+            &FmtConfig::default(),
+            "x = a. .. b\ny = - -z\nz = 1 .x\n",
+        );
+    }
+
+    #[test]
+    fn test_line_ending() {
+        check(
+            "if a then --[[\r\n]] s = [[x\ny]]\r\nend",
+            &FmtConfig {
+                line_ending: LineEnding::CrLf,
+                ..FmtConfig::default()
+            },
+            "if a then --[[\r\n]] s = [[x\r\ny]]\r\nend\r\n",
+        );
+    }
+
+    #[test]
+    fn test_source_map() {
+        let text = "x=a==b";
+        let (formatted, source_map) =
+            format_with_source_map(&flat_chunk(text), &FmtConfig::default());
+        assert_eq!(formatted, "x = a == b\n");
+        assert_eq!(source_map.original_offset(6.into()), Some(3.into()));
+        assert_eq!(source_map.original_offset(9.into()), Some(5.into()));
+        assert_eq!(source_map.original_offset(3.into()), None);
+    }
+
+    #[test]
+    fn test_normalize_quotes() {
+        assert_eq!(normalize_quotes(r#"'a"b'"#, QuoteStyle::Double), r#"'a"b'"#);
+        assert_eq!(normalize_quotes(r#"'a\'b'"#, QuoteStyle::Double), r#""a'b""#);
+        assert_eq!(normalize_quotes(r#""a'""#, QuoteStyle::Single), r#""a'""#);
+        assert_eq!(normalize_quotes("[[a]]", QuoteStyle::Single), "[[a]]");
+    }
+}
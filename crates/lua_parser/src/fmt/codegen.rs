@@ -0,0 +1,257 @@
+use super::{push_indent, render_units_into, FmtConfig, Unit};
+use crate::{
+    ast::Expr,
+    precedence, AstNode, Direction, NodeOrToken,
+    SyntaxKind::{self, *},
+    SyntaxNode, TextRange, WalkEvent,
+};
+
+/// Writes `node` as canonical Lua source, e.g. for trees that are built with a
+/// `SyntaxTreeBuilder` instead of being parsed, which have no whitespace. Every statement is put
+/// on its own line, indented by the blocks it is in, and tokens are spaced like `format` does.
+/// Trivia in the tree is ignored, including comments.
+///
+/// Parentheses are added where the tree structure can't be expressed by the tokens alone, e.g. for
+/// a `BIN_EXPR` that is an operand of an operator with a higher precedence, and `;` is added after
+/// statements that would otherwise be continued by the next statement.
+pub(crate) fn to_source(root: &SyntaxNode) -> String {
+    let config = FmtConfig::default();
+    let mut lines: Vec<(usize, Vec<Unit>)> = Vec::new();
+    let mut units = Vec::new();
+    let mut indent = 0;
+    let mut start_line = |units: &mut Vec<Unit>, line_indent: usize| {
+        if !units.is_empty() {
+            lines.push((indent, std::mem::take(units)));
+        }
+        indent = line_indent;
+    };
+
+    for event in root.preorder_with_tokens() {
+        match event {
+            WalkEvent::Enter(NodeOrToken::Node(node)) => {
+                let is_statement =
+                    node != *root && node.parent().is_some_and(|parent| parent.kind() == BLOCK);
+                let is_clause =
+                    matches!(node.kind(), ELSE_IF_CLAUSE | ELSE_CLAUSE) && is_multiline(&node);
+                if is_statement && continues_previous(&node) {
+                    units.push(synthesized(SEMI, ";", &node));
+                }
+                if is_statement || is_clause {
+                    start_line(&mut units, block_depth(root, &node));
+                }
+                if needs_parens(root, &node) {
+                    units.push(synthesized(L_PAREN, "(", &node));
+                }
+            }
+            WalkEvent::Leave(NodeOrToken::Node(node)) if needs_parens(root, &node) => {
+                units.push(synthesized(R_PAREN, ")", &node));
+            }
+            WalkEvent::Enter(NodeOrToken::Token(token)) if !token.kind().is_trivia() => {
+                if let Some(parent) = token.parent() {
+                    let closes = matches!(token.kind(), END_KW | UNTIL_KW);
+                    if closes && parent.kind() != BLOCK && is_multiline(&parent) {
+                        start_line(&mut units, block_depth(root, &parent));
+                    }
+                }
+                units.push(Unit {
+                    kind: token.kind(),
+                    text: token.text().to_string(),
+                    range: token.text_range(),
+                });
+            }
+            _ => {}
+        }
+    }
+    start_line(&mut units, 0);
+
+    let mut result = String::new();
+    for (indent, units) in lines {
+        push_indent(&mut result, indent, config.indent_style);
+        render_units_into(&mut result, &units, None);
+        result.push_str(config.line_ending.as_str());
+    }
+    result
+}
+
+/// Returns the number of blocks between `root` and `node` that are indented, i.e. all blocks but
+/// the block of a chunk.
+fn block_depth(root: &SyntaxNode, node: &SyntaxNode) -> usize {
+    node.ancestors()
+        .skip(1)
+        .take_while(|ancestor| ancestor != root)
+        .filter(|ancestor| {
+            ancestor.kind() == BLOCK && ancestor.parent().map(|parent| parent.kind()) != Some(CHUNK)
+        })
+        .count()
+}
+
+/// Returns true if any block of the construct `node`, e.g. an `IF_STMT` or a `FUNCTION_EXPR`,
+/// contains a statement, in which case the blocks are written on lines of their own.
+fn is_multiline(node: &SyntaxNode) -> bool {
+    let if_stmt = match node.kind() {
+        ELSE_IF_CLAUSE | ELSE_CLAUSE => match node.parent() {
+            Some(if_stmt) => if_stmt,
+            None => return false,
+        },
+        _ => node.clone(),
+    };
+    let has_statements = |node: &SyntaxNode| {
+        node.children()
+            .filter(|child| child.kind() == BLOCK)
+            .any(|block| block.first_child().is_some())
+    };
+    has_statements(&if_stmt)
+        || if_stmt
+            .children()
+            .filter(|child| matches!(child.kind(), ELSE_IF_CLAUSE | ELSE_CLAUSE))
+            .any(|clause| has_statements(&clause))
+}
+
+/// Returns true if the statement `stmt` starts with `(` and follows another statement without a
+/// `;`, where it would be read as a call of the previous statement, e.g. `x = f` and `(g or h)()`.
+fn continues_previous(stmt: &SyntaxNode) -> bool {
+    let starts_with_paren = stmt
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| !token.kind().is_trivia())
+        .is_some_and(|token| token.kind() == L_PAREN);
+    if !starts_with_paren {
+        return false;
+    }
+    let prev = stmt
+        .siblings_with_tokens(Direction::Prev)
+        .skip(1)
+        .find(|element| !element.kind().is_trivia());
+    prev.is_some_and(|prev| prev.kind() != SEMI)
+}
+
+/// Returns true if the expression `node` needs parentheses that aren't in the tree to keep its
+/// place in the tree.
+fn needs_parens(root: &SyntaxNode, node: &SyntaxNode) -> bool {
+    if node == root || !Expr::can_cast(node.kind()) {
+        return false;
+    }
+    if precedence::needs_parens(node, node) {
+        return true;
+    }
+    // `("s"):upper()`, `({ 1 })[1]`
+    let parent = match node.parent() {
+        Some(parent) => parent,
+        None => return false,
+    };
+    let is_prefix = matches!(
+        parent.kind(),
+        CALL_EXPR | METHOD_CALL_EXPR | FIELD_EXPR | INDEX_EXPR
+    ) && parent.first_child().as_ref() == Some(node);
+    let is_prefix_expr = matches!(
+        node.kind(),
+        NAME_REF | PAREN_EXPR | CALL_EXPR | METHOD_CALL_EXPR | FIELD_EXPR | INDEX_EXPR
+    );
+    is_prefix && !is_prefix_expr
+}
+
+/// Returns a unit for a token that is not in the tree, at the start of `node`.
+fn synthesized(kind: SyntaxKind, text: &str, node: &SyntaxNode) -> Unit {
+    Unit {
+        kind,
+        text: text.to_string(),
+        range: TextRange::empty(node.text_range().start()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AstNode, Chunk, NodeOrToken, SyntaxKind::*, SyntaxNode, SyntaxTreeBuilder};
+
+    /// Rebuilds the tree of `text` without trivia, like a tree that was built from scratch.
+    fn without_trivia(text: &str) -> Chunk {
+        let mut builder = SyntaxTreeBuilder::default();
+        build(&mut builder, &Chunk::parse(text).syntax_node());
+        builder.finish().cast::<Chunk>().unwrap().tree()
+    }
+
+    fn build(builder: &mut SyntaxTreeBuilder, node: &SyntaxNode) {
+        builder.start_node(node.kind());
+        for child in node.children_with_tokens() {
+            match child {
+                NodeOrToken::Node(node) => build(builder, &node),
+                NodeOrToken::Token(token) if !token.kind().is_trivia() => {
+                    builder.token(token.kind(), token.text().into())
+                }
+                NodeOrToken::Token(_) => {}
+            }
+        }
+        builder.finish_node();
+    }
+
+    #[test]
+    fn test_statements_and_blocks() {
+        let chunk = without_trivia(
+            "local x = 1 if x then f(x) elseif y then else while true do break end end \
+             local t = { a = 1, 2 } function M.f(a, ...) return function() end end ::l:: goto l",
+        );
+        assert_eq!(
+            chunk.to_source(),
+            r#"local x = 1
+if x then
+    f(x)
+elseif y then
+else
+    while true do
+        break
+    end
+end
+local t = { a = 1, 2 }
+function M.f(a, ...)
+    return function() end
+end
+::l::
+goto l
+"#
+        );
+    }
+
+    #[test]
+    fn test_parenthesized_call_gets_a_semicolon() {
+        let chunk = without_trivia("x = 1 (g or h)() y = 2; (z)()");
+        assert_eq!(chunk.to_source(), "x = 1;\n(g or h)()\ny = 2;\n(z)()\n");
+    }
+
+    #[test]
+    fn test_missing_parens_are_added() {
+        // (a + b) * c.d, without the `PAREN_EXPR`
+        let mut builder = SyntaxTreeBuilder::default();
+        builder.start_node(CHUNK);
+        builder.start_node(BLOCK);
+        builder.start_node(RETURN_STMT);
+        builder.token(RETURN_KW, "return".into());
+        builder.start_node(EXPR_LIST);
+        builder.start_node(BIN_EXPR);
+        builder.start_node(BIN_EXPR);
+        builder.start_node(NAME_REF);
+        builder.token(IDENT, "a".into());
+        builder.finish_node();
+        builder.token(PLUS, "+".into());
+        builder.start_node(NAME_REF);
+        builder.token(IDENT, "b".into());
+        builder.finish_node();
+        builder.finish_node();
+        builder.token(STAR, "*".into());
+        builder.start_node(FIELD_EXPR);
+        builder.start_node(LITERAL);
+        builder.token(STRING, "'s'".into());
+        builder.finish_node();
+        builder.token(DOT, ".".into());
+        builder.start_node(NAME_REF);
+        builder.token(IDENT, "len".into());
+        builder.finish_node();
+        builder.finish_node();
+        builder.finish_node();
+        builder.finish_node();
+        builder.finish_node();
+        builder.finish_node();
+        builder.finish_node();
+        let chunk = builder.finish().cast::<Chunk>().unwrap().tree();
+        assert_eq!(chunk.to_source(), "return (a + b) * ('s').len\n");
+    }
+}
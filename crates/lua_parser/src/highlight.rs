@@ -0,0 +1,294 @@
+//! Classifies the tokens of a syntax tree for syntax highlighting.
+
+use crate::{LineIndex, SyntaxKind::*, SyntaxNode, SyntaxToken, TextRange};
+
+/// What a highlighted range of text represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightTag {
+    Keyword,
+    Comment,
+    String,
+    Number,
+    /// The operator of a unary or binary expression
+    Operator,
+    Punctuation,
+    /// The name of a function in its declaration or the callee of a call
+    Function,
+    Parameter,
+    /// A name declared by `local` or a `for` loop
+    Local,
+    /// A name reference that isn't called
+    Variable,
+    /// The name of a field of a table, e.g. `b` in `a.b` or `{ b = 1 }`
+    Field,
+    Label,
+    /// The attribute of a local, e.g. `const` in `local x <const> = 1`
+    Attribute,
+}
+
+/// The semantic token types of the language server protocol that `semantic_tokens` encodes tags
+/// as, in the order of the legend.
+pub const SEMANTIC_TOKEN_TYPES: &[&str] = &[
+    "keyword",
+    "comment",
+    "string",
+    "number",
+    "operator",
+    "function",
+    "parameter",
+    "variable",
+    "property",
+    "label",
+    "decorator",
+];
+
+/// The semantic token modifiers of the language server protocol that `semantic_tokens` uses, in
+/// the order of their bits.
+pub const SEMANTIC_TOKEN_MODIFIERS: &[&str] = &["declaration"];
+
+impl HighlightTag {
+    /// Returns the index of the semantic token type of the tag in `SEMANTIC_TOKEN_TYPES`, or
+    /// `None` for punctuation, which has no token type.
+    pub fn semantic_token_type(self) -> Option<u32> {
+        let token_type = match self {
+            HighlightTag::Keyword => "keyword",
+            HighlightTag::Comment => "comment",
+            HighlightTag::String => "string",
+            HighlightTag::Number => "number",
+            HighlightTag::Operator => "operator",
+            HighlightTag::Punctuation => return None,
+            HighlightTag::Function => "function",
+            HighlightTag::Parameter => "parameter",
+            HighlightTag::Local | HighlightTag::Variable => "variable",
+            HighlightTag::Field => "property",
+            HighlightTag::Label => "label",
+            HighlightTag::Attribute => "decorator",
+        };
+        SEMANTIC_TOKEN_TYPES
+            .iter()
+            .position(|&name| name == token_type)
+            .map(|idx| idx as u32)
+    }
+
+    /// Returns the bits of the semantic token modifiers of the tag in `SEMANTIC_TOKEN_MODIFIERS`.
+    pub fn semantic_token_modifiers(self) -> u32 {
+        match self {
+            HighlightTag::Local => 1,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HighlightedRange {
+    pub range: TextRange,
+    pub tag: HighlightTag,
+}
+
+/// Classifies the tokens in `node`. The returned ranges are ordered and don't overlap; whitespace
+/// and error tokens are not highlighted.
+pub fn highlight(node: &SyntaxNode) -> Vec<HighlightedRange> {
+    node.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter_map(|token| {
+            classify(&token).map(|tag| HighlightedRange {
+                range: token.text_range(),
+                tag,
+            })
+        })
+        .collect()
+}
+
+/// Encodes the highlighted ranges of the tree `root` as the data of semantic tokens in the
+/// language server protocol: five numbers per token, the line and the column of its start
+/// relative to the previous token, its length, its type and its modifiers. Columns and lengths
+/// are in UTF-16 code units. Ranges that span multiple lines, e.g. long strings, are split into a
+/// token per line, not every client supports multiline tokens.
+pub fn semantic_tokens(root: &SyntaxNode) -> Vec<u32> {
+    let text = root.text().to_string();
+    let index = LineIndex::new(&text);
+    let base = root.text_range().start();
+    let mut data = Vec::new();
+    let (mut prev_line, mut prev_col) = (0, 0);
+    for highlighted in highlight(root) {
+        let token_type = match highlighted.tag.semantic_token_type() {
+            Some(token_type) => token_type,
+            None => continue,
+        };
+        let range = highlighted.range - base;
+        let mut start = range.start();
+        while start < range.end() {
+            let line_col = index.line_col(start);
+            let end = index.line_range(line_col.line).end().min(range.end());
+            let line_start = start - line_col.col;
+            let col = text[TextRange::new(line_start, start)]
+                .encode_utf16()
+                .count() as u32;
+            let piece = text[TextRange::new(start, end)].trim_end_matches(['\r', '\n']);
+            if !piece.is_empty() {
+                let delta_col = if line_col.line == prev_line {
+                    col - prev_col
+                } else {
+                    col
+                };
+                data.extend([
+                    line_col.line - prev_line,
+                    delta_col,
+                    piece.encode_utf16().count() as u32,
+                    token_type,
+                    highlighted.tag.semantic_token_modifiers(),
+                ]);
+                prev_line = line_col.line;
+                prev_col = col;
+            }
+            start = end;
+        }
+    }
+    data
+}
+
+fn classify(token: &SyntaxToken) -> Option<HighlightTag> {
+    let tag = match token.kind() {
+        WHITESPACE | ERROR => return None,
+        COMMENT => HighlightTag::Comment,
+        STRING => HighlightTag::String,
+        INT_NUMBER | FLOAT_NUMBER => HighlightTag::Number,
+        IDENT => classify_ident(token),
+        kind if kind.is_keyword() => HighlightTag::Keyword,
+        _ => match token.parent().map(|parent| parent.kind()) {
+            Some(BIN_EXPR) | Some(UNARY_EXPR) => HighlightTag::Operator,
+            _ => HighlightTag::Punctuation,
+        },
+    };
+    Some(tag)
+}
+
+fn classify_ident(token: &SyntaxToken) -> HighlightTag {
+    let parent = match token.parent() {
+        Some(parent) => parent,
+        None => return HighlightTag::Variable,
+    };
+    match parent.kind() {
+        NAME => match parent.parent().map(|node| node.kind()) {
+            Some(PARAM_LIST) => HighlightTag::Parameter,
+            Some(LABEL_STMT) => HighlightTag::Label,
+            Some(LOCAL_FUNCTION_STMT) => HighlightTag::Function,
+            _ => HighlightTag::Local,
+        },
+        NAME_REF => {
+            let is_callee = parent.parent().is_some_and(|node| {
+                node.kind() == CALL_EXPR && node.first_child().as_ref() == Some(&parent)
+            });
+            let is_function_name = parent.parent().is_some_and(|node| {
+                node.kind() == FUNCTION_NAME && node.last_token().as_ref() == Some(token)
+            });
+            if is_callee || is_function_name {
+                HighlightTag::Function
+            } else {
+                HighlightTag::Variable
+            }
+        }
+        FUNCTION_NAME if parent.last_token().as_ref() == Some(token) => HighlightTag::Function,
+        METHOD_CALL_EXPR => HighlightTag::Function,
+        FUNCTION_NAME | FIELD_EXPR | NAMED_FIELD => HighlightTag::Field,
+        GOTO_STMT => HighlightTag::Label,
+        ATTRIBUTE => HighlightTag::Attribute,
+        _ => HighlightTag::Variable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chunk;
+
+    fn check(text: &str, expected: &[(&str, HighlightTag)]) {
+        let parse = Chunk::parse(text);
+        let actual: Vec<_> = highlight(&parse.syntax_node())
+            .into_iter()
+            .map(|range| (&text[range.range], range.tag))
+            .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_highlight_function() {
+        use HighlightTag::*;
+        check(
+            "local function f(a) return a.b + 1 end -- c",
+            &[
+                ("local", Keyword),
+                ("function", Keyword),
+                ("f", Function),
+                ("(", Punctuation),
+                ("a", Parameter),
+                (")", Punctuation),
+                ("return", Keyword),
+                ("a", Variable),
+                (".", Punctuation),
+                ("b", Field),
+                ("+", Operator),
+                ("1", Number),
+                ("end", Keyword),
+                ("-- c", Comment),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_highlight_names() {
+        use HighlightTag::*;
+        check(
+            "function t.f() end local x <const> = g('s') ::l:: goto l",
+            &[
+                ("function", Keyword),
+                ("t", Variable),
+                (".", Punctuation),
+                ("f", Function),
+                ("(", Punctuation),
+                (")", Punctuation),
+                ("end", Keyword),
+                ("local", Keyword),
+                ("x", Local),
+                ("<", Punctuation),
+                ("const", Attribute),
+                (">", Punctuation),
+                ("=", Punctuation),
+                ("g", Function),
+                ("(", Punctuation),
+                ("'s'", String),
+                (")", Punctuation),
+                ("::", Punctuation),
+                ("l", Label),
+                ("::", Punctuation),
+                ("goto", Keyword),
+                ("l", Label),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_semantic_tokens() {
+        let parse = Chunk::parse("local s = [[é\nbc]]\nprint(s)");
+        let ty = |name| {
+            SEMANTIC_TOKEN_TYPES
+                .iter()
+                .position(|&token_type| token_type == name)
+                .unwrap() as u32
+        };
+        let data = semantic_tokens(&parse.syntax_node());
+        let tokens: Vec<_> = data.chunks(5).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                [0, 0, 5, ty("keyword"), 0],
+                [0, 6, 1, ty("variable"), 1],
+                [0, 4, 3, ty("string"), 0],
+                [1, 0, 4, ty("string"), 0],
+                [1, 0, 5, ty("function"), 0],
+                [0, 6, 1, ty("variable"), 0],
+            ]
+        );
+        assert_eq!(HighlightTag::Punctuation.semantic_token_type(), None);
+    }
+}
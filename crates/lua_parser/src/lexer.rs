@@ -1,17 +1,22 @@
+// No input may make the lexer or the parser panic, out of bounds indexing has to be handled
+// explicitly with `get`. Invariants of the parser itself are checked with `unreachable!`.
+#![cfg_attr(not(test), deny(clippy::indexing_slicing))]
 mod brackets;
 mod classes;
 mod comments;
 mod cursor;
 mod numbers;
+mod relex;
 mod strings;
 
 use self::{
-    classes::*, comments::scan_comment, cursor::Cursor, numbers::scan_number, strings::scan_string, brackets::*
+    classes::*, comments::{scan_comment, scan_shebang}, cursor::Cursor, numbers::scan_number, strings::scan_string, brackets::*
 };
 use crate::lexer::strings::scan_long_string;
+pub use self::relex::relex;
 use crate::{
     SyntaxKind::{self, *},
-    TextUnit,
+    TextSize,
 };
 
 /// A token of Mun source
@@ -21,15 +26,20 @@ pub struct Token {
     pub kind: SyntaxKind,
 
     /// The length of the token
-    pub len: TextUnit,
+    pub len: TextSize,
 }
 
 /// Break a string up into its component tokens
 pub fn tokenize(text: &str) -> Vec<Token> {
+    trace_span!("tokenize", len = text.len());
     let mut text = text;
     let mut result = Vec::new();
     while !text.is_empty() {
-        let token = next_token(text);
+        let token = if result.is_empty() {
+            first_token(text)
+        } else {
+            next_token(text)
+        };
         result.push(token);
         let len: u32 = token.len.into();
         text = &text[len as usize..];
@@ -37,6 +47,18 @@ pub fn tokenize(text: &str) -> Vec<Token> {
     result
 }
 
+/// Returns the first token of a chunk, which is a comment if the first line starts with `#`, e.g.
+/// `#!/usr/bin/lua`.
+fn first_token(text: &str) -> Token {
+    if !text.starts_with('#') {
+        return next_token(text);
+    }
+    let mut cursor = Cursor::new(text);
+    let kind = scan_shebang(&mut cursor);
+    let len = cursor.into_len();
+    Token { kind, len }
+}
+
 /// Get the next token from a string
 pub fn next_token(text: &str) -> Token {
     assert!(!text.is_empty());
@@ -71,22 +93,30 @@ fn next_token_inner(c: char, cursor: &mut Cursor) -> SyntaxKind {
 
     let ident_start = is_ident_start(c);
     if ident_start {
-        return scan_identifier_or_keyword(c, cursor);
+        return scan_identifier_or_keyword(cursor);
     }
 
     if is_dec_digit(c) {
         return scan_number(c, cursor);
     }
 
-    if let Some(kind) = SyntaxKind::from_char(c) {
-        return kind;
-    }
-
     match c {
         '~' if cursor.matches('=') => {
             cursor.bump();
             return NEQ;
         }
+        _ => (),
+    }
+
+    if let Some(kind) = scan_typo(c, cursor) {
+        return kind;
+    }
+
+    if let Some(kind) = SyntaxKind::from_char(c) {
+        return kind;
+    }
+
+    match c {
         '"' | '\'' => {
             scan_string(c, cursor);
             return STRING;
@@ -96,7 +126,24 @@ fn next_token_inner(c: char, cursor: &mut Cursor) -> SyntaxKind {
     ERROR
 }
 
-fn scan_identifier_or_keyword(c: char, cursor: &mut Cursor) -> SyntaxKind {
+/// Scans the operators of other languages, e.g. `!=`. None of them can occur in valid Lua code.
+fn scan_typo(c: char, cursor: &mut Cursor) -> Option<SyntaxKind> {
+    let kind = match c {
+        '!' if cursor.matches('=') => BANGEQ,
+        '&' if cursor.matches('&') => AMPAMP,
+        '|' if cursor.matches('|') => PIPEPIPE,
+        '+' if cursor.matches('+') => PLUSPLUS,
+        '=' if cursor.matches_str("==") => {
+            cursor.bump();
+            EQEQEQ
+        }
+        _ => return None,
+    };
+    cursor.bump();
+    Some(kind)
+}
+
+fn scan_identifier_or_keyword(cursor: &mut Cursor) -> SyntaxKind {
     cursor.bump_while(is_ident_continue);
     if let Some(kind) = SyntaxKind::from_keyword(cursor.current_token_text()) {
         return kind;
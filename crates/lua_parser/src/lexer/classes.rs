@@ -5,20 +5,20 @@ pub fn is_whitespace(c: char) -> bool {
 }
 
 pub fn is_ident_start(c: char) -> bool {
-    (c >= 'a' && c <= 'z')
-        || (c >= 'A' && c <= 'Z')
+    c.is_ascii_lowercase()
+        || c.is_ascii_uppercase()
         || c == '_'
         || (c > '\x7f' && UnicodeXID::is_xid_start(c))
 }
 
 pub fn is_ident_continue(c: char) -> bool {
-    (c >= 'a' && c <= 'z')
-        || (c >= 'A' && c <= 'Z')
-        || (c >= '0' && c <= '9')
+    c.is_ascii_lowercase()
+        || c.is_ascii_uppercase()
+        || c.is_ascii_digit()
         || c == '_'
         || (c > '\x7f' && UnicodeXID::is_xid_continue(c))
 }
 
 pub fn is_dec_digit(c: char) -> bool {
-    '0' <= c && c <= '9'
+    c.is_ascii_digit()
 }
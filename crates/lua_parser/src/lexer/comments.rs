@@ -2,31 +2,32 @@ use super::cursor::Cursor;
 
 use crate::SyntaxKind::{self, *};
 use super::brackets::*;
+use super::strings::scan_long_string;
+use memchr::memchr;
 
 pub(crate) fn scan_comment(cursor: &mut Cursor) -> SyntaxKind {
     if let Some(level) = scan_long_bracket('[', cursor) {
-        loop {
-            if scan_long_bracket(']', cursor) == Some(level) {
-                break;
-            }
-            if cursor.bump().is_none() {
-                break;
-            }
-        }
-        return COMMENT;
+        scan_long_string(level, cursor);
     } else {
         bump_until_eol(cursor);
-        COMMENT
     }
+    COMMENT
 }
 
+/// Scans the first line of a chunk that starts with `#`, e.g. `#!/usr/bin/lua`. Lua skips this
+/// line, so it is lexed as a comment.
+pub(crate) fn scan_shebang(cursor: &mut Cursor) -> SyntaxKind {
+    bump_until_eol(cursor);
+    COMMENT
+}
+
+/// Moves to the end of the line, a `\r` is only part of the line break if it precedes a `\n`.
 fn bump_until_eol(cursor: &mut Cursor) {
-    loop {
-        if cursor.matches('\n') || cursor.matches_str("\r\n") {
-            return;
-        }
-        if cursor.bump().is_none() {
-            return;
-        }
-    }
+    let rest = cursor.rest().as_bytes();
+    let len = match memchr(b'\n', rest) {
+        Some(idx) if idx > 0 && rest.get(idx - 1) == Some(&b'\r') => idx - 1,
+        Some(idx) => idx,
+        None => rest.len(),
+    };
+    cursor.bump_bytes(len);
 }
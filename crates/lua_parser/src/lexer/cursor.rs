@@ -1,11 +1,11 @@
-use crate::TextUnit;
+use crate::TextSize;
 
 use std::str::Chars;
 
 /// A simple view into the characters of a string.
 pub(crate) struct Cursor<'s> {
     text: &'s str,
-    len: TextUnit,
+    len: TextSize,
 }
 
 impl<'s> Cursor<'s> {
@@ -18,7 +18,7 @@ impl<'s> Cursor<'s> {
     }
 
     /// Gets the length of the remaining string.
-    pub fn into_len(self) -> TextUnit {
+    pub fn into_len(self) -> TextSize {
         self.len
     }
 
@@ -45,11 +45,13 @@ impl<'s> Cursor<'s> {
     }
 
     /// Checks whether the current character satisfies the specified predicate
+    #[allow(dead_code)]
     pub fn matches_if<F: Fn(char) -> bool>(&self, predicate: F) -> bool {
         self.current().map(predicate) == Some(true)
     }
 
     /// Checks whether the nth character satisfies the specified predicate
+    #[allow(dead_code)]
     pub fn matches_nth_if<F: Fn(char) -> bool>(&self, n: u32, predicate: F) -> bool {
         self.nth(n).map(predicate) == Some(true)
     }
@@ -57,7 +59,7 @@ impl<'s> Cursor<'s> {
     /// Move to the next character
     pub fn bump(&mut self) -> Option<char> {
         let ch = self.chars().next()?;
-        self.len += TextUnit::of_char(ch);
+        self.len += TextSize::of(ch);
         Some(ch)
     }
 
@@ -75,12 +77,24 @@ impl<'s> Cursor<'s> {
 
     /// Moves to the next character n times
     pub fn bump_n(&mut self, n:u32) -> Option<char> {
-        for i in 0..n {
+        for _ in 0..n {
             self.bump();
         }
         self.current()
     }
 
+    /// Moves forward `len` bytes, which must end on a character boundary.
+    pub fn bump_bytes(&mut self, len: usize) {
+        debug_assert!(self.rest().is_char_boundary(len));
+        self.len += TextSize::from(len as u32);
+    }
+
+    /// Returns the remaining text.
+    pub fn rest(&self) -> &'s str {
+        let len: u32 = self.len.into();
+        &self.text[len as usize..]
+    }
+
     /// Returns the text up to the current point.
     pub fn current_token_text(&self) -> &str {
         let len: u32 = self.len.into();
@@ -88,9 +102,8 @@ impl<'s> Cursor<'s> {
     }
 
     /// Returns an iterator over the remaining characters.
-    fn chars(&self) -> Chars {
-        let len: u32 = self.len.into();
-        self.text[len as usize..].chars()
+    fn chars(&self) -> Chars<'_> {
+        self.rest().chars()
     }
 }
 
@@ -166,6 +179,14 @@ mod tests {
         assert_eq!(cursor.current(), None);
     }
 
+    #[test]
+    fn test_bump_bytes() {
+        let mut cursor = Cursor::new("héllo");
+        cursor.bump_bytes(3);
+        assert_eq!(cursor.rest(), "llo");
+        assert_eq!(cursor.current_token_text(), "hé");
+    }
+
     #[test]
     fn test_bump_while() {
         let mut cursor = Cursor::new("hello");
@@ -1,19 +1,21 @@
-use super::{classes::*, cursor::Cursor};
+use super::cursor::Cursor;
 
 use crate::SyntaxKind::{self, *};
 
 pub(crate) fn scan_number(c: char, cursor: &mut Cursor) -> SyntaxKind {
+    let mut is_hex = false;
     if c == '0' {
         match cursor.current().unwrap_or('\0') {
             'b' | 'o' => {
                 cursor.bump();
                 scan_digits(cursor, false);
             }
-            'x' => {
+            'x' | 'X' => {
                 cursor.bump();
+                is_hex = true;
                 scan_digits(cursor, true);
             }
-            '0'...'9' | '_' | '.' | 'e' | 'E' => {
+            '0'..='9' | '_' | '.' | 'e' | 'E' => {
                 scan_digits(cursor, true);
             }
             _ => return INT_NUMBER,
@@ -22,17 +24,17 @@ pub(crate) fn scan_number(c: char, cursor: &mut Cursor) -> SyntaxKind {
         scan_digits(cursor, false);
     }
 
-    if cursor.matches('.')
-        && !(cursor.matches_str("..") || cursor.matches_nth_if(1, is_ident_start))
-    {
+    // Like in Lua, a `.` after the digits is part of the number, e.g. `3.` and `3.e2`. This makes
+    // `1..2` an error instead of a concatenation, which is also what Lua does.
+    if cursor.matches('.') {
         cursor.bump();
-        scan_digits(cursor, false);
-        scan_float_exponent(cursor);
+        scan_digits(cursor, is_hex);
+        scan_float_exponent(cursor, is_hex);
         return FLOAT_NUMBER;
     }
 
-    if cursor.matches('e') || cursor.matches('E') {
-        scan_float_exponent(cursor);
+    if matches_exponent(cursor, is_hex) {
+        scan_float_exponent(cursor, is_hex);
         return FLOAT_NUMBER;
     }
 
@@ -42,10 +44,10 @@ pub(crate) fn scan_number(c: char, cursor: &mut Cursor) -> SyntaxKind {
 fn scan_digits(cursor: &mut Cursor, allow_hex: bool) {
     while let Some(c) = cursor.current() {
         match c {
-            '_' | '0'...'9' => {
+            '_' | '0'..='9' => {
                 cursor.bump();
             }
-            'a'...'f' | 'A'...'F' if allow_hex => {
+            'a'..='f' | 'A'..='F' if allow_hex => {
                 cursor.bump();
             }
             _ => return,
@@ -53,8 +55,18 @@ fn scan_digits(cursor: &mut Cursor, allow_hex: bool) {
     }
 }
 
-fn scan_float_exponent(cursor: &mut Cursor) {
-    if cursor.matches('e') || cursor.matches('E') {
+/// Returns true if the cursor is at the start of an exponent, `e` for decimal numbers and `p` for
+/// hexadecimal ones.
+fn matches_exponent(cursor: &Cursor, is_hex: bool) -> bool {
+    if is_hex {
+        cursor.matches('p') || cursor.matches('P')
+    } else {
+        cursor.matches('e') || cursor.matches('E')
+    }
+}
+
+fn scan_float_exponent(cursor: &mut Cursor, is_hex: bool) {
+    if matches_exponent(cursor, is_hex) {
         cursor.bump();
         if cursor.matches('-') || cursor.matches('+') {
             cursor.bump();
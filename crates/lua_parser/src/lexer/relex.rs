@@ -0,0 +1,145 @@
+use super::{first_token, next_token, tokenize, Token};
+use crate::{SyntaxKind::WHITESPACE, TextEdit};
+
+/// Updates the tokens of a text after `edit` was applied to it, where `old_tokens` are the tokens
+/// of the text before the edit and `text` is the text after the edit.
+///
+/// The lexer scans every token from its start without any state, so once scanning after the
+/// edited range reaches the start of an old token, all following tokens are the same as before.
+/// Only the tokens in between are scanned again, which also covers long strings and comments that
+/// grow or shrink because their closing bracket was edited.
+pub fn relex(old_tokens: &[Token], text: &str, edit: &TextEdit) -> Vec<Token> {
+    trace_span!("relex", len = text.len());
+    let atoms = edit.as_atoms();
+    let (edit_start, edit_end) = match (atoms.first(), atoms.last()) {
+        (Some(first), Some(last)) => (
+            usize::from(first.delete.start()),
+            usize::from(last.delete.end()),
+        ),
+        _ => return old_tokens.to_vec(),
+    };
+    let len_change: isize = atoms
+        .iter()
+        .map(|atom| atom.insert.len() as isize - usize::from(atom.delete.len()) as isize)
+        .sum();
+
+    let mut old_starts = Vec::with_capacity(old_tokens.len() + 1);
+    let mut old_offset = 0;
+    for token in old_tokens {
+        old_starts.push(old_offset);
+        old_offset += usize::from(token.len);
+    }
+    old_starts.push(old_offset);
+
+    // Start at the token that the edit touches. A token that ends right at the edit can be
+    // extended by it, and a few tokens can merge into one, e.g. `[`, `=` and `[` into a long
+    // string. Whitespace separates tokens that can't merge, so scanning starts at the whitespace
+    // in front of the touched tokens.
+    let mut idx = old_starts
+        .get(1..)
+        .unwrap_or_default()
+        .partition_point(|&end| end < edit_start);
+    while idx > 0
+        && old_tokens
+            .get(idx - 1)
+            .is_some_and(|token| token.kind != WHITESPACE)
+    {
+        idx -= 1;
+    }
+    idx = idx.saturating_sub(1);
+
+    let old_start = |idx: usize| {
+        old_starts
+            .get(idx)
+            .map_or(isize::MAX, |&start| start as isize)
+    };
+    let mut tokens = old_tokens.iter().take(idx).copied().collect::<Vec<_>>();
+    let mut offset = old_starts.get(idx).copied().unwrap_or_default();
+    let mut old_idx = idx;
+    while offset < text.len() {
+        let old_offset = offset as isize - len_change;
+        if old_offset >= edit_end as isize {
+            while old_idx < old_tokens.len() && old_start(old_idx) < old_offset {
+                old_idx += 1;
+            }
+            if old_idx < old_tokens.len() && old_start(old_idx) == old_offset {
+                trace_event!(
+                    relexed = tokens.len() - idx,
+                    reused = idx + old_tokens.len() - old_idx,
+                    "relexed"
+                );
+                tokens.extend_from_slice(old_tokens.get(old_idx..).unwrap_or_default());
+                return tokens;
+            }
+        }
+        // The old tokens don't match the edit, e.g. the edit ends past the old text
+        let rest = match text.get(offset..) {
+            Some(rest) => rest,
+            None => return tokenize(text),
+        };
+        let token = if offset == 0 {
+            first_token(rest)
+        } else {
+            next_token(rest)
+        };
+        tokens.push(token);
+        offset += usize::from(token.len);
+    }
+    trace_event!(relexed = tokens.len() - idx, reused = idx, "relexed");
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tokenize, TextEdit, TextRange, TextSize};
+
+    fn check_relex(text: &str, edit: TextEdit) {
+        let new_text = edit.apply(text);
+        let relexed = super::relex(&tokenize(text), &new_text, &edit);
+        assert_eq!(relexed, tokenize(&new_text), "{:?}", new_text);
+    }
+
+    fn range(start: u32, end: u32) -> TextRange {
+        TextRange::new(TextSize::from(start), TextSize::from(end))
+    }
+
+    #[test]
+    fn test_relex() {
+        let text = "local ab = [[x]] -- c\nprint(ab, 'y')";
+        check_relex(text, TextEdit::insert(8.into(), "c".into()));
+        check_relex(text, TextEdit::delete(range(6, 8)));
+        // The long string grows until the next `]]`
+        check_relex("s = [[a]] t = 1 u = [[b]]", TextEdit::delete(range(7, 9)));
+        check_relex("x = [=[a]=] y", TextEdit::insert(5.into(), "=".into()));
+        // The subtraction turns into a comment
+        check_relex("x = a - b\ny = 1", TextEdit::insert(6.into(), "-".into()));
+        check_relex("x = 1", TextEdit::insert(5.into(), "2 + f()".into()));
+        check_relex("x = 1", TextEdit::delete(range(0, 5)));
+        check_relex("'a'", TextEdit::insert(0.into(), "[".into()));
+        // Only the first line of the chunk can be a shebang
+        check_relex("x = 1 # 2\ny", TextEdit::insert(0.into(), "#!lua ".into()));
+        check_relex("#!lua\nx = 1", TextEdit::delete(range(0, 1)));
+        check_relex("#!lua\nx = #t", TextEdit::insert(10.into(), "1".into()));
+    }
+
+    #[test]
+    fn test_relex_every_offset() {
+        let text = "if a then --[[ c ]] s = \"q\" .. [==[ long ]==] end";
+        for offset in 0..=text.len() as u32 {
+            for insert in &["]", "[", "-", "=", " ", "\"", "x"] {
+                check_relex(text, TextEdit::insert(offset.into(), insert.to_string()));
+            }
+            if offset < text.len() as u32 {
+                check_relex(text, TextEdit::delete(range(offset, offset + 1)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_relex_mismatched_edit() {
+        // The text doesn't result from the edit, scanning would start inside the `😀`
+        let edit = TextEdit::insert(4.into(), "x".into());
+        let relexed = super::relex(&tokenize("ab cd"), "a😀", &edit);
+        assert_eq!(relexed, tokenize("a😀"));
+    }
+}
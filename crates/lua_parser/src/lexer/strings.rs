@@ -1,29 +1,50 @@
 use super::cursor::Cursor;
-use crate::lexer::brackets::scan_long_bracket;
+use memchr::{memchr, memchr2};
 
 pub(crate) fn scan_string(c: char, cursor: &mut Cursor) {
-    let quote_type = c;
-    while let Some(c) = cursor.current() {
-        match c {
-            '\\' => {
-                cursor.bump();
-                if cursor.matches('\\') || cursor.matches(quote_type) {
-                    cursor.bump();
-                }
-            }
-            c if c == quote_type => {
-                cursor.bump();
+    // Quotes are ASCII, the lexer only calls this for `"` and `'`
+    let quote = c as u8;
+    loop {
+        let rest = cursor.rest().as_bytes();
+        let idx = match memchr2(quote, b'\\', rest) {
+            Some(idx) => idx,
+            None => {
+                cursor.bump_bytes(rest.len());
                 return;
             }
-            _ => {
-                cursor.bump();
-            }
+        };
+        cursor.bump_bytes(idx + 1);
+        if rest.get(idx) == Some(&quote) {
+            return;
+        }
+        if cursor.matches('\\') || cursor.matches(c) {
+            cursor.bump();
         }
     }
 }
 
+/// Scans the contents and closing bracket of a long string or comment, e.g. `a]==]` for a level 2
+/// opening bracket.
 pub(crate) fn scan_long_string(level: u32, cursor: &mut Cursor) {
-    while scan_long_bracket(']', cursor) == None {
-        cursor.bump();
+    let level = level as usize;
+    loop {
+        let rest = cursor.rest().as_bytes();
+        let idx = match memchr(b']', rest) {
+            Some(idx) => idx,
+            None => {
+                cursor.bump_bytes(rest.len());
+                return;
+            }
+        };
+        let after = rest.get(idx + 1..).unwrap_or_default();
+        let is_closing = after
+            .get(..level)
+            .is_some_and(|equals| equals.iter().all(|&b| b == b'='))
+            && after.get(level) == Some(&b']');
+        if is_closing {
+            cursor.bump_bytes(idx + level + 2);
+            return;
+        }
+        cursor.bump_bytes(idx + 1);
     }
 }
@@ -1,43 +1,369 @@
-mod ast;
+#[macro_use]
+mod token_set;
+#[macro_use]
+mod trace;
+pub mod algo;
+pub mod ast;
+mod assists;
+mod blocks;
+mod breakpoints;
+mod cfg;
+mod clones;
+#[cfg(feature = "config")]
+mod config;
+mod constants;
+#[cfg(feature = "salsa")]
+mod db;
+mod compat;
+mod completion;
+mod diagnostics;
+mod dialect;
+mod directives;
+pub mod edit;
+mod escape;
+mod fmt;
+mod highlight;
 mod syntax_kind;
+mod syntax_dump;
 mod syntax_error;
 mod syntax_node;
 mod syntax_text;
 mod lexer;
+mod line_endings;
+mod line_index;
+mod matching;
+mod memory;
+mod metrics;
+mod migrate;
+mod navigation;
+mod number_format;
+mod parse_cache;
+mod parsing;
+mod patterns;
+pub mod precedence;
+mod query;
+mod quote;
+mod requires;
+mod resolve;
+mod source_map;
+mod source_text;
+mod ssr;
+mod symbols;
+mod text_edit;
+mod tree_cache;
+pub mod transform;
+mod trivia;
+mod typing;
+mod validation;
+mod workspace;
 
 pub use crate::{
-    ast::{AstNode, Chunk},
-    syntax_kind::SyntaxKind,
-    syntax_error::{SyntaxError, SyntaxErrorKind},
+    assists::{assists, inline_local, Assist},
+    ast::{AstChildren, AstNode, AstToken, Chunk},
+    breakpoints::{executable_lines, is_valid_breakpoint_line, nearest_executable_line},
+    cfg::{control_flow_graph, control_flow_graphs, BasicBlock, BlockId, ControlFlowGraph},
+    clones::{find_clones, CloneConfig, CloneGroup, CloneOccurrence},
+    constants::{check_constants, eval_constant, INTEGER_DIVISION_BY_ZERO, INTEGER_OVERFLOW},
+    completion::{completion_context, CompletionContext, CompletionKind},
+    compat::{
+        check_compatibility, find_feature_usages, minimum_dialect, FeatureUsage, LanguageFeature,
+        REMOVED_FEATURE, UNAVAILABLE_FEATURE,
+    },
+    diagnostics::{
+        diagnostics_to_json, render_diagnostic, Diagnostic, DiagnosticCode, Fix, JsonDiagnostic,
+        JsonEdit, JsonFix, JsonLabel, JsonPosition, Label, Severity,
+    },
+    dialect::Dialect,
+    directives::{
+        comment_directives, Directive, DirectiveOption, DirectiveTool, Directives, GlobalsKind,
+        LintLevel,
+    },
+    escape::{escape_lua_string, long_bracket_level, long_string},
+    fmt::{format, format_with_source_map, FmtConfig, IndentStyle, QuoteStyle},
+    highlight::{
+        highlight, semantic_tokens, HighlightTag, HighlightedRange, SEMANTIC_TOKEN_MODIFIERS,
+        SEMANTIC_TOKEN_TYPES,
+    },
+    line_endings::{detect_line_endings, normalize_line_endings, LineEnding, LineEndings},
+    line_index::{LineCol, LineIndex},
+    matching::matching_tokens,
+    memory::{KindStats, MemoryStats},
+    metrics::{function_metrics, FunctionMetrics},
+    migrate::{migrate, migrate_bit_ops, Migration, MigrationChange, UNSAFE_BIT_OPERATION},
+    navigation::Location,
+    number_format::{format_float, format_integer, lua_tostring, NumberStyle},
+    parse_cache::ParseCache,
+    parsing::{
+        parse, parse_events, parse_with_config, replay_events, ParseConfig, ParseEvent,
+        ParseStats, TokenSource, TreeSink,
+    },
+    patterns::{check_patterns, MALFORMED_PATTERN, UNKNOWN_PATTERN_CLASS},
+    query::{Query, QueryCapture, QueryError, QueryMatch, QueryMatches},
+    quote::{quote, QuoteError, Splice},
+    requires::{find_requires, Require},
+    resolve::{resolve_names, Local, LocalId, NameResolution, Reference},
+    source_map::{Mapping, SourceMap},
+    source_text::{InvalidUtf8Error, Replacement, SourceText, CONTROL_CHARACTER, INVALID_UTF8},
+    ssr::{SsrError, SsrMatch, SsrMatcher},
+    symbols::{symbol_index, Symbol, SymbolIndex, SymbolKind, SymbolScope},
+    syntax_kind::{ParseSyntaxKindError, SyntaxKind, SyntaxKindFromRawError},
+    syntax_dump::{syntax_tree_to_json, syntax_tree_to_sexpr, DumpConfig},
+    syntax_error::{ParseError, SyntaxError, SyntaxErrorKind},
     syntax_node::{
-        Direction, InsertPosition, SyntaxElement, SyntaxNode, SyntaxToken, SyntaxTreeBuilder,
-        TreeArc, WalkEvent,
+        Checkpoint, Direction, GreenNode, InsertPosition, LuaLanguage, NodeOrToken,
+        SyntaxElement, SyntaxElementChildren, SyntaxNode, SyntaxNodeChildren, SyntaxNodeExt,
+        SyntaxToken, SyntaxTreeBuilder, WalkEvent,
     },
-    syntax_text::SyntaxText,
-    lexer::{Token, tokenize},
+    syntax_text::{CharIndices, SyntaxTextExt},
+    text_edit::{AtomTextEdit, TextEdit, TextEditBuilder},
+    token_set::TokenSet,
+    tree_cache::{deserialize_parse, serialize_parse, TreeCacheError, TREE_CACHE_VERSION},
+    trivia::{TokenTrivia, TriviaView},
+    typing::{on_block_opened, on_enter, on_keyword_typed},
+    lexer::{relex, Token, tokenize},
+    validation::{check_ambiguous_calls, AMBIGUOUS_SYNTAX, AMBIGUOUS_SYNTAX_PORTABILITY},
+    workspace::{FileId, ModuleGraph, Workspace},
 };
-pub use rowan::{SmolStr, TextRange, TextUnit};
+#[cfg(feature = "config")]
+pub use crate::config::{ConfigError, LintConfig, LUACHECKRC, SELENE_TOML};
+#[cfg(feature = "salsa")]
+pub use crate::db::{RootDatabase, SourceDatabase, SourceDatabaseStorage};
+pub use rowan::{SyntaxText, TextRange, TextSize};
+pub use smol_str::SmolStr;
+
+use std::{fmt::Write, marker::PhantomData, sync::Arc};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct ParseError(pub String);
+/// The result of parsing: a syntax tree and the errors that were found while parsing.
+///
+/// The tree is stored as a green tree, which is immutable and thread safe; `Parse` can be
+/// cheaply cloned and sent to other threads. The `SyntaxNode`s returned by `syntax_node` are
+/// not thread safe, every thread creates its own from a shared `Parse`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Parse<T> {
+    green: GreenNode,
+    errors: Arc<Vec<SyntaxError>>,
+    _ty: PhantomData<fn() -> T>,
+}
 
-use rowan::GreenNode;
+impl<T> Clone for Parse<T> {
+    fn clone(&self) -> Parse<T> {
+        Parse {
+            green: self.green.clone(),
+            errors: self.errors.clone(),
+            _ty: PhantomData,
+        }
+    }
+}
+
+impl<T> Parse<T> {
+    fn new(green: GreenNode, errors: Vec<SyntaxError>) -> Parse<T> {
+        Parse {
+            green,
+            errors: Arc::new(errors),
+            _ty: PhantomData,
+        }
+    }
+
+    pub fn syntax_node(&self) -> SyntaxNode {
+        SyntaxNode::new_root(self.green.clone())
+    }
+
+    /// Returns the green tree, the compact and immutable representation of the tree that can be
+    /// stored in caches. `SyntaxNode::new_root` turns it back into a tree of `SyntaxNode`s.
+    pub fn green(&self) -> &GreenNode {
+        &self.green
+    }
+
+    pub fn errors(&self) -> &[SyntaxError] {
+        &self.errors
+    }
+
+    /// Returns all errors as user facing diagnostics, with fixes for common mistakes like a
+    /// missing `end`.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let root = self.syntax_node();
+        self.errors
+            .iter()
+            .map(|error| Diagnostic {
+                fixes: diagnostics::syntax_error_fixes(&root, error),
+                ..Diagnostic::from(error.clone())
+            })
+            .collect()
+    }
+
+    /// Returns a textual representation of the tree, with the errors printed after the token
+    /// they belong to.
+    pub fn debug_dump(&self) -> String {
+        let write_element = |buf: &mut String, element: &SyntaxElement| match element {
+            NodeOrToken::Node(node) => write!(buf, "{:?}", node),
+            NodeOrToken::Token(token) => write!(buf, "{:?}", token),
+        };
+        syntax_dump::dump_tree(
+            &self.syntax_node(),
+            &self.errors,
+            write_element,
+            syntax_dump::write_error,
+        )
+    }
+}
+
+impl<T: AstNode> Parse<T> {
+    pub fn tree(&self) -> T {
+        T::cast(self.syntax_node()).unwrap()
+    }
+
+    /// Returns the tree if there were no errors while parsing.
+    pub fn ok(self) -> Result<T, Arc<Vec<SyntaxError>>> {
+        if self.errors.is_empty() {
+            Ok(self.tree())
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+impl Parse<SyntaxNode> {
+    /// Detaches the subtree of `node` into a `Parse` of its own that can be sent to another
+    /// thread, e.g. to analyze the functions of a file in parallel. The offsets in the new tree
+    /// start at zero and no errors are carried over.
+    pub fn from_subtree(node: &SyntaxNode) -> Parse<SyntaxNode> {
+        Parse::new(node.green().into_owned(), Vec::new())
+    }
+
+    /// Creates a `Parse` from a green tree and its errors, e.g. from a cache that stores the
+    /// results of `green` and `errors`.
+    pub fn from_green(green: GreenNode, errors: Vec<SyntaxError>) -> Parse<SyntaxNode> {
+        Parse::new(green, errors)
+    }
+
+    /// Converts the untyped tree into a typed one, if the root has the right kind.
+    pub fn cast<N: AstNode>(self) -> Option<Parse<N>> {
+        if N::can_cast(self.syntax_node().kind()) {
+            Some(Parse {
+                green: self.green,
+                errors: self.errors,
+                _ty: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+}
 
 impl Chunk {
-//    fn new(green: GreenNode, errors: Vec<SyntaxError>) -> TreeArc<Chunk> {
-//        let root = SyntaxNode::new(green, errors);
-//        assert_eq!(root.kind(), SyntaxKind::CHUNK);
-//        TreeArc::cast(root)
-//    }
-//
-//    pub fn parse(text: &str) -> TreeArc<Chunk> {
-//        let (green, errors) = parsing::parse_text(text);
-//        Chunk::new(green, errors)
-//    }
-
-    pub fn errors(&self) -> Vec<SyntaxError> {
-//        let mut errors = self.syntax.root_data().to_vec();
-//        errors
-        Vec::new()
-    }
-}
\ No newline at end of file
+    pub fn parse(text: &str) -> Parse<Chunk> {
+        Chunk::parse_with_config(text, &ParseConfig::default())
+    }
+
+    pub fn parse_with_config(text: &str, config: &ParseConfig) -> Parse<Chunk> {
+        let (green, errors) = parsing::parse_text(text, config);
+        Chunk::new_parse(text, green, errors)
+    }
+
+    /// Parses `text` like `parse_with_config` and also returns statistics about the parse, e.g.
+    /// to monitor the parser in production tooling.
+    pub fn parse_with_stats(text: &str, config: &ParseConfig) -> (Parse<Chunk>, ParseStats) {
+        let (green, errors, stats) = parsing::parse_text_with_stats(text, config);
+        (Chunk::new_parse(text, green, errors), stats)
+    }
+
+    fn new_parse(text: &str, green: GreenNode, errors: Vec<SyntaxError>) -> Parse<Chunk> {
+        let parse = Parse::new(green, errors);
+        if cfg!(debug_assertions) {
+            trace_span!("validate", len = text.len());
+            let root = parse.syntax_node();
+            validation::validate_block_structure(&root);
+            validation::validate_text(&root, text);
+        }
+        parse
+    }
+}
+
+/// Builds a chunk that contains the tokens of `text` without any structure, for testing token
+/// based functionality.
+#[cfg(test)]
+pub(crate) fn flat_chunk(text: &str) -> Chunk {
+    let mut builder = SyntaxTreeBuilder::default();
+    builder.start_node(SyntaxKind::CHUNK);
+    let mut offset = 0;
+    for token in tokenize(text) {
+        let len = usize::from(token.len);
+        builder.token(token.kind, SmolStr::new(&text[offset..offset + len]));
+        offset += len;
+    }
+    builder.finish_node();
+    builder.finish().cast::<Chunk>().unwrap().tree()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn parse_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Parse<Chunk>>();
+        assert_send_sync::<Parse<SyntaxNode>>();
+    }
+
+    #[test]
+    fn parse_is_shared_between_threads() {
+        let parse = Chunk::parse("local a = 1\nlocal b = 2 +\n");
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let parse = parse.clone();
+                thread::spawn(move || {
+                    let text = parse.syntax_node().text().to_string();
+                    (text, parse.errors().len())
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(
+                handle.join().unwrap(),
+                ("local a = 1\nlocal b = 2 +\n".to_string(), 1)
+            );
+        }
+    }
+
+    #[test]
+    fn subtree_is_sent_to_other_thread() {
+        let parse = Chunk::parse("local x = 1\nfunction f() return x end");
+        let function = parse
+            .syntax_node()
+            .descendants()
+            .find(|node| node.kind() == SyntaxKind::FUNCTION_STMT)
+            .unwrap();
+        let subtree = Parse::from_subtree(&function);
+        let dump = thread::spawn(move || subtree.debug_dump()).join().unwrap();
+        assert!(dump.starts_with("FUNCTION_STMT@0..25\n"), "{}", dump);
+    }
+
+    #[test]
+    fn green_tree_is_rehydrated() {
+        let parse = Chunk::parse("local x = 1\nf(x))\n");
+        let green = parse.green().clone();
+        let errors = parse.errors().to_vec();
+        let dump = thread::spawn(move || {
+            let node = SyntaxNode::new_root(green.clone());
+            assert_eq!(node.kind(), SyntaxKind::CHUNK);
+            let parse = Parse::from_green(green, errors).cast::<Chunk>().unwrap();
+            parse.debug_dump()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(dump, parse.debug_dump());
+    }
+
+    #[test]
+    fn parse_with_stats() {
+        let text = "local x = 1\nf(x))\n";
+        let (parse, stats) = Chunk::parse_with_stats(text, &ParseConfig::default());
+        assert_eq!(parse, Chunk::parse(text));
+        assert_eq!(stats.tokens, tokenize(text).len());
+        assert_eq!(stats.nodes, parse.syntax_node().descendants().count());
+        assert_eq!(stats.errors, parse.errors().len());
+        assert_eq!(stats.recoveries, 1);
+    }
+}
@@ -0,0 +1,135 @@
+//! Detection and normalization of line endings.
+//!
+//! Lua converts every line break in a long string or escaped in a quoted string to `\n`, so
+//! changing the line endings of a file never changes the meaning of its code.
+
+use crate::{TextEdit, TextEditBuilder, TextRange, TextSize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// `\n`, used on Unix
+    Lf,
+    /// `\r\n`, used on Windows
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// The line endings used in a text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineEndings {
+    /// The text consists of a single line
+    None,
+    /// All lines end with the same line ending
+    Uniform(LineEnding),
+    /// Both line endings occur, with the number of lines that end with each
+    Mixed { lf: usize, crlf: usize },
+}
+
+impl LineEndings {
+    /// Returns the line ending that is used most, or `\n` if there are no line breaks or both are
+    /// used equally often.
+    pub fn dominant(self) -> LineEnding {
+        match self {
+            LineEndings::Uniform(line_ending) => line_ending,
+            LineEndings::Mixed { lf, crlf } if crlf > lf => LineEnding::CrLf,
+            _ => LineEnding::Lf,
+        }
+    }
+}
+
+/// Returns the ranges of the line breaks in `text` with their line ending. A `\r` that isn't
+/// followed by `\n` is not a line break.
+fn line_breaks(text: &str) -> impl Iterator<Item = (TextRange, LineEnding)> + '_ {
+    text.match_indices('\n').map(move |(idx, _)| {
+        let end = TextSize::from(idx as u32 + 1);
+        if text[..idx].ends_with('\r') {
+            (
+                TextRange::new(end - TextSize::of("\r\n"), end),
+                LineEnding::CrLf,
+            )
+        } else {
+            (
+                TextRange::new(end - TextSize::of('\n'), end),
+                LineEnding::Lf,
+            )
+        }
+    })
+}
+
+/// Detects which line endings `text` uses.
+pub fn detect_line_endings(text: &str) -> LineEndings {
+    let (mut lf, mut crlf) = (0, 0);
+    for (_, line_ending) in line_breaks(text) {
+        match line_ending {
+            LineEnding::Lf => lf += 1,
+            LineEnding::CrLf => crlf += 1,
+        }
+    }
+    match (lf, crlf) {
+        (0, 0) => LineEndings::None,
+        (_, 0) => LineEndings::Uniform(LineEnding::Lf),
+        (0, _) => LineEndings::Uniform(LineEnding::CrLf),
+        (lf, crlf) => LineEndings::Mixed { lf, crlf },
+    }
+}
+
+/// Returns the edit that replaces every line break in `text` that doesn't use `line_ending`.
+pub fn normalize_line_endings(text: &str, line_ending: LineEnding) -> TextEdit {
+    let mut builder = TextEditBuilder::default();
+    for (range, _) in line_breaks(text).filter(|&(_, found)| found != line_ending) {
+        builder.replace(range, line_ending.as_str().to_string());
+    }
+    builder.finish()
+}
+
+/// Returns `text` with all its line breaks replaced by `line_ending`.
+pub(crate) fn with_line_ending(text: &str, line_ending: LineEnding) -> String {
+    let text = text.replace("\r\n", "\n");
+    match line_ending {
+        LineEnding::Lf => text,
+        LineEnding::CrLf => text.replace('\n', "\r\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_line_endings() {
+        assert_eq!(detect_line_endings("x = 1"), LineEndings::None);
+        assert_eq!(
+            detect_line_endings("x = 1\ny = 2\n"),
+            LineEndings::Uniform(LineEnding::Lf)
+        );
+        assert_eq!(
+            detect_line_endings("x = 1\r\n"),
+            LineEndings::Uniform(LineEnding::CrLf)
+        );
+        let mixed = detect_line_endings("a\r\nb\nc\r\n");
+        assert_eq!(mixed, LineEndings::Mixed { lf: 1, crlf: 2 });
+        assert_eq!(mixed.dominant(), LineEnding::CrLf);
+    }
+
+    #[test]
+    fn test_normalize_line_endings() {
+        let text = "a\r\nb\nc\r\n";
+        assert_eq!(
+            normalize_line_endings(text, LineEnding::Lf).apply(text),
+            "a\nb\nc\n"
+        );
+        assert_eq!(
+            normalize_line_endings(text, LineEnding::CrLf).apply(text),
+            "a\r\nb\r\nc\r\n"
+        );
+        assert!(normalize_line_endings("a\nb", LineEnding::Lf).is_empty());
+    }
+}
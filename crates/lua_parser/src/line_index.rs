@@ -0,0 +1,126 @@
+//! Maps between text offsets and line/column positions.
+
+use crate::{TextRange, TextSize};
+
+/// Zero-based line and column position in a text. The column is measured in bytes from the start
+/// of the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    pub line: u32,
+    pub col: TextSize,
+}
+
+/// Stores the start offset of every line in a text so that offsets can be converted to line and
+/// column positions without rescanning the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    line_starts: Vec<TextSize>,
+    len: TextSize,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> LineIndex {
+        let mut line_starts = vec![TextSize::from(0)];
+        let mut offset = TextSize::from(0);
+        for c in text.chars() {
+            offset += TextSize::of(c);
+            if c == '\n' {
+                line_starts.push(offset);
+            }
+        }
+        LineIndex {
+            line_starts,
+            len: offset,
+        }
+    }
+
+    /// Returns the number of lines in the text. An empty text has a single line.
+    pub fn line_count(&self) -> u32 {
+        self.line_starts.len() as u32
+    }
+
+    /// Returns the line and column of the specified offset.
+    pub fn line_col(&self, offset: TextSize) -> LineCol {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        LineCol {
+            line: line as u32,
+            col: offset - self.line_starts[line],
+        }
+    }
+
+    /// Returns the offset of the specified line and column.
+    pub fn offset(&self, line_col: LineCol) -> TextSize {
+        self.line_starts[line_col.line as usize] + line_col.col
+    }
+
+    /// Returns the range of the specified line, including its line terminator.
+    pub fn line_range(&self, line: u32) -> TextRange {
+        let start = self.line_starts[line as usize];
+        let end = self
+            .line_starts
+            .get(line as usize + 1)
+            .cloned()
+            .unwrap_or(self.len);
+        TextRange::new(start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_col() {
+        let index = LineIndex::new("local a\nlocal b\n\nreturn a");
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(
+            index.line_col(0.into()),
+            LineCol {
+                line: 0,
+                col: 0.into()
+            }
+        );
+        assert_eq!(
+            index.line_col(7.into()),
+            LineCol {
+                line: 0,
+                col: 7.into()
+            }
+        );
+        assert_eq!(
+            index.line_col(8.into()),
+            LineCol {
+                line: 1,
+                col: 0.into()
+            }
+        );
+        assert_eq!(
+            index.line_col(17.into()),
+            LineCol {
+                line: 3,
+                col: 0.into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_offset_roundtrip() {
+        let text = "a = 1\r\nb = 2\nc = 3";
+        let index = LineIndex::new(text);
+        for offset in 0..text.len() as u32 {
+            let offset = TextSize::from(offset);
+            assert_eq!(index.offset(index.line_col(offset)), offset);
+        }
+    }
+
+    #[test]
+    fn test_line_range() {
+        let index = LineIndex::new("a\nbc\n");
+        assert_eq!(index.line_range(0), TextRange::new(0.into(), 2.into()));
+        assert_eq!(index.line_range(1), TextRange::new(2.into(), 5.into()));
+        assert_eq!(index.line_range(2), TextRange::new(5.into(), 5.into()));
+    }
+}
@@ -0,0 +1,131 @@
+//! Pairs the keywords and brackets that delimit a construct, e.g. to highlight the `end` that
+//! belongs to an `if` or to jump between matching brackets.
+
+use crate::{SyntaxKind::*, SyntaxNode, SyntaxToken, TokenSet};
+
+const BLOCK_KEYWORDS: TokenSet = token_set![
+    IF_KW, THEN_KW, ELSEIF_KW, ELSE_KW, END_KW, DO_KW, WHILE_KW, FOR_KW, REPEAT_KW, UNTIL_KW,
+    FUNCTION_KW
+];
+
+const BRACKETS: TokenSet = token_set![L_PAREN, R_PAREN, L_CURLY, R_CURLY, L_BRACKET, R_BRACKET];
+
+/// Returns the tokens that delimit the same construct as `token` in the order of the text,
+/// including `token` itself:
+///
+/// * `if`, `then`, `elseif`, `else` and `end` of an `if` statement
+/// * `while`, `for` or `do` with the `do` and `end` of a loop or `do` block
+/// * `repeat` and `until`
+/// * `function` and `end`
+/// * an opening bracket and the closing bracket
+///
+/// Returns an empty list if `token` doesn't delimit a construct, and only `token` if the construct
+/// is incomplete, e.g. an `if` without `end`.
+pub fn matching_tokens(token: &SyntaxToken) -> Vec<SyntaxToken> {
+    let kind = token.kind();
+    let parent = match token.parent() {
+        Some(parent) => parent,
+        None => return Vec::new(),
+    };
+    if BRACKETS.contains(kind) {
+        // Nested brackets are in child nodes
+        return delimiters(&parent, BRACKETS);
+    }
+    if !BLOCK_KEYWORDS.contains(kind) {
+        return Vec::new();
+    }
+    let construct = match parent.kind() {
+        ELSE_IF_CLAUSE | ELSE_CLAUSE => match parent.parent() {
+            Some(if_stmt) => if_stmt,
+            None => return vec![token.clone()],
+        },
+        _ => parent,
+    };
+    let mut tokens = delimiters(&construct, BLOCK_KEYWORDS);
+    if construct.kind() == IF_STMT {
+        for clause in construct
+            .children()
+            .filter(|clause| matches!(clause.kind(), ELSE_IF_CLAUSE | ELSE_CLAUSE))
+        {
+            tokens.extend(delimiters(&clause, BLOCK_KEYWORDS));
+        }
+        tokens.sort_by_key(|token| token.text_range().start());
+    }
+    tokens
+}
+
+fn delimiters(node: &SyntaxNode, kinds: TokenSet) -> Vec<SyntaxToken> {
+    node.children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| kinds.contains(token.kind()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AstNode, Chunk, TextSize};
+
+    /// Returns the texts and offsets of the tokens that match the token at `offset`.
+    fn matching(text: &str, offset: u32) -> Vec<(String, u32)> {
+        let chunk = Chunk::parse(text).tree();
+        let token = chunk
+            .syntax()
+            .token_at_offset(TextSize::from(offset))
+            .right_biased()
+            .unwrap();
+        matching_tokens(&token)
+            .into_iter()
+            .map(|token| {
+                let start = u32::from(token.text_range().start());
+                (token.text().to_string(), start)
+            })
+            .collect()
+    }
+
+    fn texts(text: &str, offset: u32) -> Vec<String> {
+        matching(text, offset)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect()
+    }
+
+    #[test]
+    fn test_keywords() {
+        let text = "if a then f() elseif b then g() else while c do end end";
+        let if_keywords = vec!["if", "then", "elseif", "then", "else", "end"];
+        assert_eq!(texts(text, 0), if_keywords);
+        assert_eq!(texts(text, 14), if_keywords);
+        assert_eq!(texts(text, 32), if_keywords);
+        assert_eq!(texts(text, 52), if_keywords);
+        assert_eq!(texts(text, 37), vec!["while", "do", "end"]);
+        assert_eq!(texts("repeat x() until y", 11), vec!["repeat", "until"]);
+        assert_eq!(
+            texts("local f = function() end", 10),
+            vec!["function", "end"]
+        );
+        assert_eq!(texts("if a then", 0), vec!["if", "then"]);
+        assert_eq!(texts("x = a", 2), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_brackets() {
+        let text = "f(t[(a)], {})";
+        assert_eq!(
+            matching(text, 1),
+            vec![("(".to_string(), 1), (")".to_string(), 12)]
+        );
+        assert_eq!(
+            matching(text, 3),
+            vec![("[".to_string(), 3), ("]".to_string(), 7)]
+        );
+        assert_eq!(
+            matching(text, 6),
+            vec![("(".to_string(), 4), (")".to_string(), 6)]
+        );
+        assert_eq!(
+            matching(text, 10),
+            vec![("{".to_string(), 10), ("}".to_string(), 11)]
+        );
+    }
+}
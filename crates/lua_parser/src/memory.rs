@@ -0,0 +1,158 @@
+//! Estimates of the memory used by syntax trees.
+//!
+//! The sizes are computed from the layout `rowan` uses for green nodes and tokens and don't
+//! include allocator overhead, so they are an approximation. Identical tokens and small nodes are
+//! shared between trees built by a `SyntaxTreeBuilder`; shared elements are only counted once.
+
+use crate::{LuaLanguage, NodeOrToken, Parse, SyntaxError, SyntaxErrorKind, SyntaxKind};
+use rowan::{GreenNodeData, GreenTokenData, Language};
+use std::{
+    collections::{BTreeMap, HashSet},
+    mem::size_of,
+};
+
+/// The header of a green node or token: a reference count, the kind, the length of the text and
+/// the number of children or bytes of text.
+const HEADER_SIZE: usize = 2 * size_of::<usize>() + size_of::<u16>() + size_of::<u32>();
+
+/// A child of a green node: its offset relative to the parent and a pointer to its data.
+const CHILD_SIZE: usize = size_of::<usize>() + size_of::<u32>();
+
+/// The memory used by the elements of a single kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct KindStats {
+    /// The number of distinct elements of this kind.
+    pub count: usize,
+    pub bytes: usize,
+}
+
+/// A breakdown of the memory used by a parse result, see `Parse::memory_stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// The number of distinct green nodes.
+    pub nodes: usize,
+    pub node_bytes: usize,
+    /// The number of distinct green tokens.
+    pub tokens: usize,
+    pub token_bytes: usize,
+    /// The number of times a node or token was reused instead of being stored again.
+    pub shared: usize,
+    /// The number of syntax errors and the memory used to store them.
+    pub errors: usize,
+    pub error_bytes: usize,
+    /// The number and size of the distinct elements of every kind that occurs in the tree.
+    pub kinds: BTreeMap<SyntaxKind, KindStats>,
+}
+
+impl MemoryStats {
+    /// Returns the total number of bytes used by the tree and its errors.
+    pub fn total_bytes(&self) -> usize {
+        self.node_bytes + self.token_bytes + self.error_bytes
+    }
+
+    fn add_node(&mut self, node: &GreenNodeData) {
+        let bytes = HEADER_SIZE + node.children().len() * CHILD_SIZE;
+        self.nodes += 1;
+        self.node_bytes += bytes;
+        self.add_kind(node.kind(), bytes);
+    }
+
+    fn add_token(&mut self, token: &GreenTokenData) {
+        let bytes = HEADER_SIZE + token.text().len();
+        self.tokens += 1;
+        self.token_bytes += bytes;
+        self.add_kind(token.kind(), bytes);
+    }
+
+    fn add_kind(&mut self, kind: rowan::SyntaxKind, bytes: usize) {
+        let kind = LuaLanguage::kind_from_raw(kind);
+        let stats = self.kinds.entry(kind).or_default();
+        stats.count += 1;
+        stats.bytes += bytes;
+    }
+}
+
+impl<T> Parse<T> {
+    /// Returns an estimate of the memory used by this parse result.
+    pub fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats::default();
+        let mut seen_nodes = HashSet::new();
+        let mut seen_tokens = HashSet::new();
+        let mut stack: Vec<&GreenNodeData> = vec![&*self.green];
+        while let Some(node) = stack.pop() {
+            if !seen_nodes.insert(node as *const GreenNodeData) {
+                stats.shared += 1;
+                continue;
+            }
+            stats.add_node(node);
+            for child in node.children() {
+                match child {
+                    NodeOrToken::Node(node) => stack.push(node),
+                    NodeOrToken::Token(token) => {
+                        if seen_tokens.insert(token as *const GreenTokenData) {
+                            stats.add_token(token);
+                        } else {
+                            stats.shared += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        stats.errors = self.errors().len();
+        stats.error_bytes = self.errors().iter().map(error_size).sum();
+        stats
+    }
+}
+
+fn error_size(error: &SyntaxError) -> usize {
+    let SyntaxErrorKind::ParseError(parse_error) = error.kind();
+    size_of::<SyntaxError>() + parse_error.message.capacity()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chunk, SyntaxKind::*, SyntaxTreeBuilder};
+
+    #[test]
+    fn test_count_elements() {
+        let parse = Chunk::parse("local a = 1\nlocal b = 1\n");
+        let stats = parse.memory_stats();
+        assert_eq!(stats.errors, 0);
+        assert_eq!(stats.error_bytes, 0);
+        assert_eq!(stats.kinds[&LOCAL_KW].count, 1);
+        assert_eq!(stats.kinds[&IDENT].count, 2);
+        assert_eq!(stats.kinds[&INT_NUMBER].count, 1);
+        assert!(stats.shared > 0);
+        assert_eq!(
+            stats.kinds.values().map(|kind| kind.count).sum::<usize>(),
+            stats.nodes + stats.tokens
+        );
+        assert_eq!(stats.total_bytes(), stats.node_bytes + stats.token_bytes);
+    }
+
+    #[test]
+    fn test_shared_subtrees_are_counted_once() {
+        let mut builder = SyntaxTreeBuilder::default();
+        builder.start_node(CHUNK);
+        for _ in 0..10 {
+            builder.start_node(NAME_REF);
+            builder.token(IDENT, "a".into());
+            builder.finish_node();
+        }
+        builder.finish_node();
+        let stats = builder.finish().memory_stats();
+        assert_eq!(stats.nodes, 2);
+        assert_eq!(stats.tokens, 1);
+        assert_eq!(stats.shared, 9);
+        assert_eq!(stats.kinds[&NAME_REF].count, 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        let stats = Chunk::parse("local = 1").memory_stats();
+        assert!(stats.errors > 0);
+        assert!(stats.error_bytes >= stats.errors * size_of::<SyntaxError>());
+    }
+}
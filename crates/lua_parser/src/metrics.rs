@@ -0,0 +1,225 @@
+//! Code-quality metrics of the functions in a chunk.
+
+use crate::{
+    ast::{BinExpr, FunctionStmt, LocalFunctionStmt, ParamList, Stmt},
+    precedence::BinOp,
+    AstNode, Chunk, LineIndex,
+    SyntaxKind::{self, *},
+    SyntaxNode, TextRange, WalkEvent,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FunctionMetrics {
+    /// The name of the function as written in its declaration, e.g. `M.foo` or `obj:bar`, or
+    /// `None` for anonymous functions.
+    pub name: Option<String>,
+    /// The range from the `function` keyword up to and including its `end`.
+    pub range: TextRange,
+    /// One plus the number of decision points (`if`, `elseif`, loops, `and` and `or`).
+    pub cyclomatic_complexity: u32,
+    /// The maximum number of nested blocks inside the function body.
+    pub max_nesting_depth: u32,
+    /// The number of parameters, a trailing `...` counts as one.
+    pub parameter_count: u32,
+    /// The number of statements in the function body, including those in nested blocks but not
+    /// those in nested functions.
+    pub statement_count: u32,
+    pub line_count: u32,
+}
+
+struct Frame {
+    function: SyntaxNode,
+    depth: u32,
+    decision_points: u32,
+    max_nesting_depth: u32,
+    statement_count: u32,
+}
+
+/// Computes the metrics of every function in `chunk`, ordered by the position of the function.
+/// Decision points, nesting and statements are attributed to the innermost function only.
+pub fn function_metrics(chunk: &Chunk) -> Vec<FunctionMetrics> {
+    let index = LineIndex::new(&chunk.syntax().text().to_string());
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut result = Vec::new();
+
+    for event in chunk.syntax().preorder() {
+        match event {
+            WalkEvent::Enter(node) => {
+                if let Some(frame) = frames.last_mut() {
+                    frame.enter(&node);
+                }
+                if is_function(node.kind()) {
+                    frames.push(Frame {
+                        function: node,
+                        depth: 0,
+                        decision_points: 0,
+                        max_nesting_depth: 0,
+                        statement_count: 0,
+                    });
+                }
+            }
+            WalkEvent::Leave(node) => {
+                if is_function(node.kind()) {
+                    let frame = frames.pop().expect("every function was entered");
+                    result.push(frame.finish(&index));
+                } else if let Some(frame) = frames.last_mut() {
+                    if is_nested_block(node.kind()) {
+                        frame.depth -= 1;
+                    }
+                }
+            }
+        }
+    }
+
+    result.sort_by_key(|metrics| metrics.range.start());
+    result
+}
+
+fn is_function(kind: SyntaxKind) -> bool {
+    matches!(kind, FUNCTION_STMT | LOCAL_FUNCTION_STMT | FUNCTION_EXPR)
+}
+
+/// Returns true for the statements that contain a block.
+fn is_nested_block(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        IF_STMT | WHILE_STMT | DO_STMT | NUMERIC_FOR_STMT | GENERIC_FOR_STMT | REPEAT_STMT
+    )
+}
+
+impl Frame {
+    /// Counts a node inside the function. Nested functions are counted as statements of this
+    /// function, their contents are counted by a frame of their own.
+    fn enter(&mut self, node: &SyntaxNode) {
+        if Stmt::can_cast(node.kind()) {
+            self.statement_count += 1;
+        }
+        let is_decision_point = match node.kind() {
+            IF_STMT | ELSE_IF_CLAUSE | WHILE_STMT | NUMERIC_FOR_STMT | GENERIC_FOR_STMT
+            | REPEAT_STMT => true,
+            BIN_EXPR => matches!(
+                BinExpr::cast(node.clone()).and_then(|bin| bin.op_kind()),
+                Some(BinOp::And | BinOp::Or)
+            ),
+            _ => false,
+        };
+        if is_decision_point {
+            self.decision_points += 1;
+        }
+        if is_nested_block(node.kind()) {
+            self.depth += 1;
+            self.max_nesting_depth = self.max_nesting_depth.max(self.depth);
+        }
+    }
+
+    fn finish(self, index: &LineIndex) -> FunctionMetrics {
+        let function = &self.function;
+        let start = function
+            .children_with_tokens()
+            .find(|element| element.kind() == FUNCTION_KW)
+            .map_or(function.text_range().start(), |keyword| {
+                keyword.text_range().start()
+            });
+        let range = TextRange::new(start, function.text_range().end());
+
+        let name = if let Some(stmt) = FunctionStmt::cast(function.clone()) {
+            stmt.function_name().map(|name| {
+                name.syntax()
+                    .descendants_with_tokens()
+                    .filter_map(|element| element.into_token())
+                    .filter(|token| !token.kind().is_trivia())
+                    .map(|token| token.text().to_string())
+                    .collect()
+            })
+        } else if let Some(stmt) = LocalFunctionStmt::cast(function.clone()) {
+            stmt.name().map(|name| name.syntax().text().to_string())
+        } else {
+            None
+        };
+
+        let param_list = function.children().find_map(ParamList::cast);
+        let parameter_count = param_list.map_or(0, |param_list| {
+            let has_varargs = param_list
+                .syntax()
+                .children_with_tokens()
+                .any(|element| element.kind() == DOTDOTDOT);
+            param_list.params().count() as u32 + has_varargs as u32
+        });
+
+        FunctionMetrics {
+            name,
+            range,
+            cyclomatic_complexity: self.decision_points + 1,
+            max_nesting_depth: self.max_nesting_depth,
+            parameter_count,
+            statement_count: self.statement_count,
+            line_count: index.line_col(range.end()).line - index.line_col(range.start()).line + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(text: &str) -> Vec<FunctionMetrics> {
+        function_metrics(&Chunk::parse(text).tree())
+    }
+
+    #[test]
+    fn test_function_metrics() {
+        let text = "function M.check(a, b, ...)
+    if a and b then
+        for i = 1, 10 do
+            while a do end
+        end
+    elseif b then
+        return function() return a or b end
+    end
+end";
+        let metrics = metrics(text);
+        assert_eq!(metrics.len(), 2);
+
+        let outer = &metrics[0];
+        assert_eq!(outer.name.as_deref(), Some("M.check"));
+        assert_eq!(outer.range, TextRange::at(0.into(), (text.len() as u32).into()));
+        assert_eq!(outer.cyclomatic_complexity, 6);
+        assert_eq!(outer.max_nesting_depth, 3);
+        assert_eq!(outer.parameter_count, 3);
+        assert_eq!(outer.statement_count, 4);
+        assert_eq!(outer.line_count, 9);
+
+        let inner = &metrics[1];
+        assert_eq!(inner.name, None);
+        assert_eq!(inner.cyclomatic_complexity, 2);
+        assert_eq!(inner.max_nesting_depth, 0);
+        assert_eq!(inner.parameter_count, 0);
+        assert_eq!(inner.statement_count, 1);
+        assert_eq!(inner.line_count, 1);
+    }
+
+    #[test]
+    fn test_statement_count() {
+        let text = "local function f(x)
+    local y = x + 1
+    local function g() return y end
+    if y then print(y) end
+    return g()
+end";
+        let metrics = metrics(text);
+        assert_eq!(metrics[0].name.as_deref(), Some("f"));
+        assert_eq!(metrics[0].range.start(), 6.into());
+        assert_eq!(metrics[0].statement_count, 5);
+        assert_eq!(metrics[1].name.as_deref(), Some("g"));
+        assert_eq!(metrics[1].statement_count, 1);
+    }
+
+    #[test]
+    fn test_unclosed_function() {
+        let metrics = metrics("local function f(x)\n  if x then");
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].name.as_deref(), Some("f"));
+        assert_eq!(metrics[0].cyclomatic_complexity, 2);
+        assert_eq!(metrics[0].line_count, 2);
+    }
+}
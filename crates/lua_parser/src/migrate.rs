@@ -0,0 +1,441 @@
+//! Migrates Lua 5.1 code to newer versions of Lua by rewriting the uses of functions that were
+//! removed or renamed, e.g. `unpack` to `table.unpack`.
+//!
+//! Only changes that keep the meaning of the code are made, and only for names that refer to the
+//! standard library and not to locals of the same name. Every change comes with an explanation,
+//! so tools can show the user why the code was changed.
+
+mod bit_ops;
+
+pub use self::bit_ops::{migrate_bit_ops, UNSAFE_BIT_OPERATION};
+
+use crate::{
+    ast::{ArgList, BinExpr, BinOp, CallExpr, Expr, LocalAssignStmt, NameRef},
+    precedence::{self, expr_precedence},
+    requires::string_value,
+    resolve::{resolve_names, NameResolution, Reference},
+    transform::{transform, Replacement, Rewriter},
+    AstNode, Chunk, Diagnostic, Dialect,
+    SyntaxKind::*,
+    SyntaxNode, TextEdit, TextSize,
+};
+
+/// A change made by `migrate`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MigrationChange {
+    /// Why the code was changed, e.g. "`unpack` was moved to `table.unpack` in Lua 5.2". Changes
+    /// inside each other are combined into a single change with all explanations.
+    pub explanation: String,
+    pub edit: TextEdit,
+}
+
+/// The result of a migration, e.g. `migrate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// The changes in the order of the text
+    pub changes: Vec<MigrationChange>,
+    /// The edit that applies all changes
+    pub edit: TextEdit,
+    /// The code that could not be migrated without changing its meaning and has to be migrated
+    /// by hand
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Global functions that were replaced by another function with the same arguments.
+const RENAMED_GLOBALS: &[(&str, &str, Dialect, &str)] = &[
+    (
+        "unpack",
+        "table.unpack",
+        Dialect::Lua52,
+        "`unpack` was moved to `table.unpack` in Lua 5.2",
+    ),
+    (
+        "loadstring",
+        "load",
+        Dialect::Lua52,
+        "`loadstring` was removed in Lua 5.2, `load` accepts strings instead",
+    ),
+];
+
+/// Library functions that were renamed.
+const RENAMED_FIELDS: &[(&str, &str, Dialect, &str)] = &[
+    (
+        "math.mod",
+        "math.fmod",
+        Dialect::Lua52,
+        "`math.mod` was renamed to `math.fmod`",
+    ),
+    (
+        "string.gfind",
+        "string.gmatch",
+        Dialect::Lua52,
+        "`string.gfind` was renamed to `string.gmatch`",
+    ),
+];
+
+/// Rewrites the removed and renamed functions of Lua 5.1 that are used in `chunk` for `target`.
+/// Functions that still exist in `target` are not changed.
+pub fn migrate(chunk: &Chunk, target: Dialect) -> Migration {
+    run(chunk, &mut Lua51 { target })
+}
+
+/// The rules of a migration.
+trait Rules {
+    /// Returns the code that replaces `node` and why, or `None` to keep it.
+    fn rewrite(&mut self, names: &mut Names, node: &SyntaxNode) -> Option<(String, String)>;
+}
+
+/// Rewrites `chunk` with `rules`.
+fn run(chunk: &Chunk, rules: &mut dyn Rules) -> Migration {
+    let mut migrator = Migrator {
+        rules,
+        names: Names::default(),
+        explanations: Vec::new(),
+    };
+    let transformed = transform(chunk.syntax(), &mut migrator);
+    let changes = transformed
+        .edit()
+        .as_atoms()
+        .iter()
+        .zip(migrator.explanations)
+        .map(|(atom, (_, explanation))| MigrationChange {
+            explanation,
+            edit: TextEdit::replace(atom.delete, atom.insert.clone()),
+        })
+        .collect();
+    Migration {
+        changes,
+        edit: transformed.edit().clone(),
+        diagnostics: Vec::new(),
+    }
+}
+
+struct Migrator<'a> {
+    rules: &'a mut dyn Rules,
+    names: Names,
+    /// The start of every change in the rewritten tree and its explanation
+    explanations: Vec<(TextSize, String)>,
+}
+
+impl Rewriter for Migrator<'_> {
+    fn rewrite(&mut self, node: &SyntaxNode) -> Option<Replacement> {
+        let (text, explanation) = self.rules.rewrite(&mut self.names, node)?;
+
+        // The changes inside the node have been recorded before and are merged into this one
+        let start = node.text_range().start();
+        let inner_start = self
+            .explanations
+            .iter()
+            .position(|(inner, _)| *inner >= start)
+            .unwrap_or(self.explanations.len());
+        let mut explanations: Vec<_> = self
+            .explanations
+            .drain(inner_start..)
+            .map(|(_, explanation)| explanation)
+            .collect();
+        explanations.push(explanation);
+        self.explanations.push((start, explanations.join("; ")));
+        Some(Replacement::Text(text))
+    }
+}
+
+/// The rules of `migrate`.
+struct Lua51 {
+    target: Dialect,
+}
+
+impl Rules for Lua51 {
+    fn rewrite(&mut self, names: &mut Names, node: &SyntaxNode) -> Option<(String, String)> {
+        let (text, explanation) = match node.kind() {
+            NAME_REF => self.rename_global(names, node)?,
+            FIELD_EXPR => self.rename_field(names, node)?,
+            CALL_EXPR => self.replace_call(names, &CallExpr::cast(node.clone())?)?,
+            _ => return None,
+        };
+        Some((text, explanation.to_string()))
+    }
+}
+
+impl Lua51 {
+    fn rename_global(
+        &self,
+        names: &mut Names,
+        node: &SyntaxNode,
+    ) -> Option<(String, &'static str)> {
+        let name = node.text().to_string();
+        let &(_, new_name, removed_in, explanation) = RENAMED_GLOBALS
+            .iter()
+            .find(|(old_name, ..)| *old_name == name)?;
+        if self.target < removed_in || !names.is_global_read(node) {
+            return None;
+        }
+        Some((new_name.to_string(), explanation))
+    }
+
+    fn rename_field(&self, names: &mut Names, node: &SyntaxNode) -> Option<(String, &'static str)> {
+        let path = names.library_path(node)?;
+        let &(_, new_path, removed_in, explanation) = RENAMED_FIELDS
+            .iter()
+            .find(|(old_path, ..)| *old_path == path)?;
+        if self.target < removed_in {
+            return None;
+        }
+        Some((new_path.to_string(), explanation))
+    }
+
+    fn replace_call(&self, names: &mut Names, call: &CallExpr) -> Option<(String, &'static str)> {
+        let path = names.library_path(call.callee()?.syntax())?;
+        let args = call_args(call);
+        let has_parens = call.arg_list().is_some_and(|args| has_parens(&args));
+        let (text, removed_in, explanation) = match (path.as_str(), args.as_slice()) {
+            ("table.getn", [table]) if has_parens => (
+                format!("#{}", operand(table.syntax())),
+                Dialect::Lua52,
+                "`table.getn` was removed in Lua 5.2, the length operator `#` returns the same",
+            ),
+            ("math.pow", [base, exponent]) => (
+                format!(
+                    "{} ^ {}",
+                    operand(base.syntax()),
+                    operand(exponent.syntax())
+                ),
+                Dialect::Lua53,
+                "`math.pow` was removed in Lua 5.3, the `^` operator returns the same",
+            ),
+            ("math.floor", [quotient]) => {
+                let quotient = BinExpr::cast(quotient.syntax().clone())?;
+                if quotient.op_kind()? != BinOp::Div {
+                    return None;
+                }
+                let lhs = quotient.syntax().first_child()?;
+                let rhs = quotient.syntax().last_child()?;
+                (
+                    format!("{} // {}", lhs.text(), rhs.text()),
+                    Dialect::Lua53,
+                    "`math.floor(a / b)` emulates the integer division operator `//` of Lua 5.3; \
+                     unlike `math.floor`, `//` returns a float if an operand is a float",
+                )
+            }
+            _ => return None,
+        };
+        if self.target < removed_in {
+            return None;
+        }
+        Some((parenthesize_in_place_of(call.syntax(), text), explanation))
+    }
+}
+
+/// Resolves the names of the tree that is being rewritten, again after every change.
+#[derive(Default)]
+struct Names {
+    resolution: Option<(SyntaxNode, NameResolution)>,
+}
+
+impl Names {
+    /// Returns the path of a field of a library table, e.g. `math.floor`, if the table is the
+    /// global variable or a local that is initialized with `require` of the library, e.g.
+    /// `local bit = require "bit"`.
+    fn library_path(&mut self, node: &SyntaxNode) -> Option<String> {
+        if node.kind() != FIELD_EXPR {
+            return None;
+        }
+        let base = node.first_child().filter(|base| base.kind() == NAME_REF)?;
+        let field = node
+            .children_with_tokens()
+            .filter_map(|element| element.into_token())
+            .filter(|token| token.kind() == IDENT)
+            .last()?;
+        let library = base.text().to_string();
+        let reference = self.reference(&base)?;
+        let is_library = match reference.local {
+            None => !reference.is_write,
+            Some(local) => {
+                let resolution = &self.resolution.as_ref()?.1;
+                let is_reassigned = resolution
+                    .local_references(local)
+                    .any(|reference| reference.is_write);
+                !is_reassigned && requires(&resolution.local(local).declaration, &library)
+            }
+        };
+        if !is_library {
+            return None;
+        }
+        Some(format!("{}.{}", library, field.text()))
+    }
+
+    /// Returns true if `node`, a `NAME_REF`, reads a global variable.
+    fn is_global_read(&mut self, node: &SyntaxNode) -> bool {
+        self.reference(node)
+            .is_some_and(|reference| reference.local.is_none() && !reference.is_write)
+    }
+
+    fn reference(&mut self, node: &SyntaxNode) -> Option<&Reference> {
+        let root = node.ancestors().last().and_then(Chunk::cast)?;
+        let is_resolved = self
+            .resolution
+            .as_ref()
+            .is_some_and(|(resolved, _)| resolved == root.syntax());
+        if !is_resolved {
+            self.resolution = Some((root.syntax().clone(), resolve_names(&root)));
+        }
+        let resolution = &self.resolution.as_ref()?.1;
+        resolution.reference(&NameRef::cast(node.clone())?)
+    }
+}
+
+/// Returns true if `declaration` is a `local` statement that initializes a local with
+/// `require "<library>"`.
+fn requires(declaration: &SyntaxNode, library: &str) -> bool {
+    let stmt = match LocalAssignStmt::cast(declaration.clone()) {
+        Some(stmt) => stmt,
+        None => return false,
+    };
+    let call = stmt
+        .expr_list()
+        .and_then(|exprs| exprs.exprs().next())
+        .and_then(|expr| CallExpr::cast(expr.syntax().clone()));
+    let call = match call {
+        Some(call) => call,
+        None => return false,
+    };
+    let is_require = call
+        .callee()
+        .is_some_and(|callee| callee.syntax().text() == "require");
+    let args = call_args(&call);
+    is_require
+        && matches!(args.as_slice(), [Expr::Literal(name)]
+            if string_value(&name.syntax().text().to_string()).as_deref() == Some(library))
+}
+
+fn call_args(call: &CallExpr) -> Vec<Expr> {
+    call.arg_list()
+        .map(|args| args.args().collect())
+        .unwrap_or_default()
+}
+
+/// Returns true if the arguments of a call are in parentheses, unlike `f "s"` or `f {}`.
+fn has_parens(args: &ArgList) -> bool {
+    args.syntax()
+        .first_token()
+        .is_some_and(|token| token.kind() == L_PAREN)
+}
+
+/// Returns the text of `expr` for an operand of an operator, in parentheses if it is a binary or
+/// unary expression itself.
+fn operand(expr: &SyntaxNode) -> String {
+    if expr_precedence(expr).is_some() {
+        format!("({})", expr.text())
+    } else {
+        expr.text().to_string()
+    }
+}
+
+/// Returns `text`, an expression that replaces `node`, in parentheses if it needs them to keep
+/// its meaning in the place of `node`.
+fn parenthesize_in_place_of(node: &SyntaxNode, text: String) -> String {
+    let chunk = Chunk::parse(&format!("return {}", text));
+    let expr = chunk
+        .syntax_node()
+        .descendants()
+        .find(|node| node.kind() == EXPR_LIST)
+        .and_then(|exprs| exprs.first_child());
+    let expr = match expr {
+        Some(expr) => expr,
+        None => return text,
+    };
+    // `(#t).x`
+    let is_prefix = node.parent().is_some_and(|parent| {
+        matches!(
+            parent.kind(),
+            CALL_EXPR | METHOD_CALL_EXPR | INDEX_EXPR | FIELD_EXPR
+        ) && parent.first_child().as_ref() == Some(node)
+    });
+    if precedence::needs_parens(&expr, node) || (is_prefix && expr_precedence(&expr).is_some()) {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_migrate(before: &str, target: Dialect, after: &str) {
+        let migration = migrate(&Chunk::parse(before).tree(), target);
+        assert_eq!(migration.edit.apply(before), after);
+    }
+
+    #[test]
+    fn test_migrate() {
+        let before = r#"local t = { unpack(args) }
+local f = loadstring("return 1")
+local n = table.getn(t) + math.pow(2, n + 1)
+local q = math.floor(a / b) * 2
+local function g(unpack) return unpack(t) end
+print(string.gfind(s, "%w+"), math.mod(7, 3), math.floor(x))
+"#;
+        check_migrate(
+            before,
+            Dialect::Lua54,
+            r#"local t = { table.unpack(args) }
+local f = load("return 1")
+local n = #t + 2 ^ (n + 1)
+local q = a // b * 2
+local function g(unpack) return unpack(t) end
+print(string.gmatch(s, "%w+"), math.fmod(7, 3), math.floor(x))
+"#,
+        );
+        check_migrate(
+            before,
+            Dialect::Lua52,
+            r#"local t = { table.unpack(args) }
+local f = load("return 1")
+local n = #t + math.pow(2, n + 1)
+local q = math.floor(a / b) * 2
+local function g(unpack) return unpack(t) end
+print(string.gmatch(s, "%w+"), math.fmod(7, 3), math.floor(x))
+"#,
+        );
+        check_migrate(before, Dialect::Lua51, before);
+    }
+
+    #[test]
+    fn test_locals_are_not_migrated() {
+        let text = "local math = require 'mymath'\nunpack = nil\nreturn math.pow(a, b)";
+        check_migrate(text, Dialect::Lua54, text);
+    }
+
+    #[test]
+    fn test_parentheses() {
+        check_migrate(
+            "x = table.getn(t).n .. -math.floor(a / b) ^ math.pow(a + 1, 2)",
+            Dialect::Lua54,
+            "x = (#t).n .. -(a // b) ^ (a + 1) ^ 2",
+        );
+    }
+
+    #[test]
+    fn test_explanations() {
+        let text = "x = math.floor(unpack(t) / 2)\ny = loadstring(s)";
+        let migration = migrate(&Chunk::parse(text).tree(), Dialect::Lua54);
+        let explanations: Vec<_> = migration
+            .changes
+            .iter()
+            .map(|change| change.explanation.as_str())
+            .collect();
+        assert_eq!(
+            explanations,
+            vec![
+                "`unpack` was moved to `table.unpack` in Lua 5.2; `math.floor(a / b)` emulates \
+                 the integer division operator `//` of Lua 5.3; unlike `math.floor`, `//` \
+                 returns a float if an operand is a float",
+                "`loadstring` was removed in Lua 5.2, `load` accepts strings instead",
+            ]
+        );
+        assert_eq!(
+            migration.changes[0].edit.apply(text),
+            "x = table.unpack(t) // 2\ny = loadstring(s)"
+        );
+    }
+}
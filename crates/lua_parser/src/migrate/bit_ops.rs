@@ -0,0 +1,337 @@
+//! Rewrites the calls of the bit libraries of LuaJIT (`bit`) and Lua 5.2 (`bit32`) to the bitwise
+//! operators of Lua 5.3, e.g. `bit.band(a, b)` to `a & b`.
+//!
+//! The operators work on 64-bit integers while the libraries work on 32 bits, so a call is only
+//! rewritten if the operator gives the same result for integer operands that fit in 32 bits, the
+//! values the libraries are used with: signed integers for `bit` and unsigned ones for `bit32`.
+//! Calls of functions without an operator, calls with constant operands outside of that range
+//! and shifts that can overflow or shift in sign bits are reported instead.
+
+use super::{call_args, operand, parenthesize_in_place_of, run, Migration, Names, Rules};
+use crate::{
+    ast::{CallExpr, Expr, LuaValue},
+    eval_constant, AstNode, Chunk, Diagnostic, DiagnosticCode,
+    SyntaxKind::*,
+    SyntaxNode,
+};
+
+pub const UNSAFE_BIT_OPERATION: DiagnosticCode = DiagnosticCode("W0006");
+
+/// How a call of a bit library function is migrated.
+enum Conversion {
+    /// The call is replaced by the expression, for the reason
+    Native(String, String),
+    /// The call can't be replaced without changing its result, for the reason
+    Unsafe(String),
+}
+
+/// Rewrites the calls of `bit` and `bit32` functions in `chunk` to the bitwise operators of
+/// Lua 5.3 where they give the same results, and reports the calls that have to be migrated by
+/// hand as `UNSAFE_BIT_OPERATION` warnings.
+pub fn migrate_bit_ops(chunk: &Chunk) -> Migration {
+    let mut names = Names::default();
+    let diagnostics = chunk
+        .syntax()
+        .descendants()
+        .filter_map(CallExpr::cast)
+        .filter_map(|call| match convert(&mut names, &call)? {
+            Conversion::Unsafe(reason) => Some(Diagnostic::warning(
+                UNSAFE_BIT_OPERATION,
+                call.syntax().text_range(),
+                reason,
+            )),
+            Conversion::Native(..) => None,
+        })
+        .collect();
+    Migration {
+        diagnostics,
+        ..run(chunk, &mut BitOps)
+    }
+}
+
+struct BitOps;
+
+impl Rules for BitOps {
+    fn rewrite(&mut self, names: &mut Names, node: &SyntaxNode) -> Option<(String, String)> {
+        match convert(names, &CallExpr::cast(node.clone())?)? {
+            Conversion::Native(text, explanation) => {
+                Some((parenthesize_in_place_of(node, text), explanation))
+            }
+            Conversion::Unsafe(_) => None,
+        }
+    }
+}
+
+fn convert(names: &mut Names, call: &CallExpr) -> Option<Conversion> {
+    // The result of a call statement is discarded, an operator can't be a statement
+    if call.syntax().parent()?.kind() == EXPR_STMT {
+        return None;
+    }
+    let path = names.library_path(call.callee()?.syntax())?;
+    let (library, function) = path.split_once('.')?;
+    let is_signed = match library {
+        "bit" => true,
+        "bit32" => false,
+        _ => return None,
+    };
+    let args = call_args(call);
+    if args.iter().any(|arg| is_vararg(arg.syntax())) {
+        return Some(Conversion::Unsafe(format!(
+            "`{}` can't be replaced by an operator, the number of its arguments is unknown",
+            path
+        )));
+    }
+    let native = |op: &str| format!("`{}` is the `{}` operator since Lua 5.3", path, op);
+    let out_of_range = |op: &str| {
+        let sign = if is_signed { "signed" } else { "unsigned" };
+        Conversion::Unsafe(format!(
+            "`{}` differs from `{}` for operands that aren't {} 32-bit integers",
+            op, path, sign
+        ))
+    };
+    let in_range = args.iter().all(|arg| is_in_range(arg, is_signed));
+    let conversion = match (function, args.as_slice()) {
+        ("band" | "bor" | "bxor", [_, _, ..]) => {
+            let op = match function {
+                "band" => "&",
+                "bor" => "|",
+                _ => "~",
+            };
+            if in_range {
+                Conversion::Native(join(&args, op), native(op))
+            } else {
+                out_of_range(op)
+            }
+        }
+        ("bnot", [x]) if is_signed && in_range => {
+            Conversion::Native(format!("~{}", operand(x.syntax())), native("~"))
+        }
+        ("bnot", [_]) if is_signed => out_of_range("~"),
+        ("bnot", [_]) => Conversion::Unsafe(format!(
+            "`~` is negative for positive operands, unlike `{}`",
+            path
+        )),
+        ("btest", [a, b]) if !is_signed => Conversion::Native(
+            format!("{} & {} ~= 0", operand(a.syntax()), operand(b.syntax())),
+            format!("`{}` is `a & b ~= 0` since Lua 5.3", path),
+        ),
+        ("lshift", [x, n]) => {
+            let limit = if is_signed { 1 << 31 } else { 1 << 32 };
+            let fits = match (integer_value(x), shift_amount(n)) {
+                (Some(x), Some(n)) => x < limit && (0..limit).contains(&(x << n)),
+                _ => false,
+            };
+            if fits {
+                Conversion::Native(binary(x, "<<", n), native("<<"))
+            } else {
+                Conversion::Unsafe(format!(
+                    "`<<` differs from `{}` if the result doesn't fit in 32 bits",
+                    path
+                ))
+            }
+        }
+        ("rshift", [x, n]) => {
+            let limit = if is_signed { 1 << 31 } else { 1 << 32 };
+            let is_unsigned = integer_value(x).is_some_and(|x| (0..limit).contains(&x));
+            if is_unsigned && shift_amount(n).is_some() {
+                Conversion::Native(binary(x, ">>", n), native(">>"))
+            } else {
+                Conversion::Unsafe(format!(
+                    "`>>` differs from `{}` for negative operands",
+                    path
+                ))
+            }
+        }
+        (
+            "arshift" | "rol" | "ror" | "lrotate" | "rrotate" | "tobit" | "tohex" | "bswap"
+            | "extract" | "replace",
+            _,
+        ) => Conversion::Unsafe(format!("`{}` has no equivalent operator", path)),
+        _ => return None,
+    };
+    Some(conversion)
+}
+
+/// Returns the operands joined by the binary operator `op`, e.g. `a & b & c`.
+fn join(operands: &[Expr], op: &str) -> String {
+    operands
+        .iter()
+        .map(|operand| super::operand(operand.syntax()))
+        .collect::<Vec<_>>()
+        .join(&format!(" {} ", op))
+}
+
+fn binary(lhs: &Expr, op: &str, rhs: &Expr) -> String {
+    format!("{} {} {}", operand(lhs.syntax()), op, operand(rhs.syntax()))
+}
+
+/// Returns the value of an integer literal, e.g. `42` or `0xff`.
+fn integer_value(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Literal(literal) => match literal.value()? {
+            LuaValue::Integer(value) => Some(value),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns false for constants that aren't integers in the range of the library, e.g.
+/// `0x80000000` for `bit`, `-1` for `bit32` or `1.5`. Other expressions are assumed to be in
+/// range, like the values the libraries are used with.
+fn is_in_range(expr: &Expr, is_signed: bool) -> bool {
+    let range = if is_signed {
+        -(1 << 31)..1 << 31
+    } else {
+        0..1 << 32
+    };
+    match eval_constant(expr) {
+        Some(LuaValue::Integer(value)) => range.contains(&value),
+        Some(LuaValue::Float(value)) => {
+            value.fract() == 0.0 && value >= range.start as f64 && value < range.end as f64
+        }
+        Some(_) => false,
+        None => !matches!(expr, Expr::Literal(_)),
+    }
+}
+
+/// Returns the value of a shift amount that shifts the same with 32 and 64 bits.
+fn shift_amount(expr: &Expr) -> Option<i64> {
+    integer_value(expr).filter(|n| (0..32).contains(n))
+}
+
+fn is_vararg(expr: &SyntaxNode) -> bool {
+    expr.first_token()
+        .is_some_and(|token| token.kind() == DOTDOTDOT && expr.kind() == LITERAL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Severity;
+
+    fn check(text: &str, expected: &str) -> Migration {
+        let migration = migrate_bit_ops(&Chunk::parse(text).tree());
+        let migrated = migration.edit.apply(text);
+        assert_eq!(migrated, expected);
+        assert!(Chunk::parse(&migrated).errors().is_empty());
+        migration
+    }
+
+    #[test]
+    fn test_operators() {
+        let migration = check(
+            "local bit = require 'bit'\n\
+             x = bit.band(a, b + 1, 0xff) .. bit.bor(bit.bxor(a, b), c)\n\
+             y = bit.bnot(a) * 2, bit32.btest(flags, 4), bit32.band(a, b)",
+            "local bit = require 'bit'\n\
+             x = (a & (b + 1) & 0xff) .. ((a ~ b) | c)\n\
+             y = ~a * 2, flags & 4 ~= 0, a & b",
+        );
+        assert!(migration.diagnostics.is_empty());
+        assert_eq!(
+            migration.changes[0].explanation,
+            "`bit.band` is the `&` operator since Lua 5.3"
+        );
+        assert_eq!(
+            migration.changes[1].explanation,
+            "`bit.bxor` is the `~` operator since Lua 5.3; \
+             `bit.bor` is the `|` operator since Lua 5.3"
+        );
+    }
+
+    #[test]
+    fn test_shifts() {
+        let migration = check(
+            "x = bit.lshift(1, 4), bit.rshift(0xff, 4), bit32.lshift(1, 31)\n\
+             y = bit.lshift(1, 31), bit.lshift(x, 4), bit.rshift(-1, 4), bit.lshift(1, 32)",
+            "x = 1 << 4, 0xff >> 4, 1 << 31\n\
+             y = bit.lshift(1, 31), bit.lshift(x, 4), bit.rshift(-1, 4), bit.lshift(1, 32)",
+        );
+        let messages: Vec<_> = migration
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "`<<` differs from `bit.lshift` if the result doesn't fit in 32 bits",
+                "`<<` differs from `bit.lshift` if the result doesn't fit in 32 bits",
+                "`>>` differs from `bit.rshift` for negative operands",
+                "`<<` differs from `bit.lshift` if the result doesn't fit in 32 bits",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsafe_calls_are_reported() {
+        let text =
+            "x = bit.arshift(bit.band(a, b), 2) + bit32.bnot(c) + bit.bor(...)\nbit.tohex(x)";
+        let migration = check(
+            text,
+            "x = bit.arshift(a & b, 2) + bit32.bnot(c) + bit.bor(...)\nbit.tohex(x)",
+        );
+        let diagnostics: Vec<_> = migration
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (&text[diagnostic.range], diagnostic.severity))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                ("bit.arshift(bit.band(a, b), 2)", Severity::Warning),
+                ("bit32.bnot(c)", Severity::Warning),
+                ("bit.bor(...)", Severity::Warning),
+            ]
+        );
+        assert_eq!(migration.diagnostics[0].code, UNSAFE_BIT_OPERATION);
+    }
+
+    #[test]
+    fn test_operands_out_of_range_are_reported() {
+        let text =
+            "x = bit.band(0x80000000, 0x80000000), bit32.band(-1, -1), bit.bnot(0xFFFFFFFF)\n\
+                    y = bit.bor(1.5, 1), bit32.bxor(a, 2.0), bit.band(-1, 0x7fffffff)";
+        let migration = check(
+            text,
+            "x = bit.band(0x80000000, 0x80000000), bit32.band(-1, -1), bit.bnot(0xFFFFFFFF)\n\
+             y = bit.bor(1.5, 1), a ~ 2.0, (-1) & 0x7fffffff",
+        );
+        let diagnostics: Vec<_> = migration
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (&text[diagnostic.range], diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    "bit.band(0x80000000, 0x80000000)",
+                    "`&` differs from `bit.band` for operands that aren't signed 32-bit integers"
+                ),
+                (
+                    "bit32.band(-1, -1)",
+                    "`&` differs from `bit32.band` for operands that aren't unsigned 32-bit \
+                     integers"
+                ),
+                (
+                    "bit.bnot(0xFFFFFFFF)",
+                    "`~` differs from `bit.bnot` for operands that aren't signed 32-bit integers"
+                ),
+                (
+                    "bit.bor(1.5, 1)",
+                    "`|` differs from `bit.bor` for operands that aren't signed 32-bit integers"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_other_bit_tables_are_not_migrated() {
+        check(
+            "local bit = {}\nx = bit.band(a, b)\nlocal bit32 = require 'mybit'\ny = bit32.bor(a, b)",
+            "local bit = {}\nx = bit.band(a, b)\nlocal bit32 = require 'mybit'\ny = bit32.bor(a, b)",
+        );
+    }
+}
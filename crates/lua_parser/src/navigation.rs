@@ -0,0 +1,333 @@
+//! Finds the definitions of names across the files of a `Workspace`.
+//!
+//! Locals, including the upvalues of nested functions, are resolved within their file. Globals
+//! and fields of the module table are looked up in the symbol indexes of the files, and fields of
+//! a required module in the symbol index of the file that `require` loads.
+
+use crate::{
+    ast::{Expr, FunctionName, LuaValue, MethodCallExpr},
+    resolve_names,
+    symbols::{initializer, module_local},
+    AstNode, Chunk, FileId, NameResolution, SymbolScope,
+    SyntaxKind::*,
+    SyntaxToken, TextRange, TextSize, Workspace,
+};
+
+/// A range in a file of a `Workspace`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub file_id: FileId,
+    pub range: TextRange,
+}
+
+impl Workspace {
+    /// Returns the definitions of the name or the required module at `offset` in a file, e.g. the
+    /// declaration of a local, the assignments of a global or the definition of `foo` in the
+    /// required module for `util.foo`. A required module is located at the start of its file.
+    /// Returns an empty list if the definition isn't in the workspace.
+    pub fn goto_definition(&self, file_id: FileId, offset: TextSize) -> Vec<Location> {
+        let chunk = self.parse(file_id).tree();
+        let token = chunk
+            .syntax()
+            .token_at_offset(offset)
+            .find(|token| matches!(token.kind(), IDENT | STRING));
+        let token = match token {
+            Some(token) => token,
+            None => return Vec::new(),
+        };
+        let resolution = resolve_names(&chunk);
+        let navigator = Navigator {
+            workspace: self,
+            file_id,
+            chunk: &chunk,
+            resolution: &resolution,
+        };
+        navigator.definitions(&token).unwrap_or_default()
+    }
+}
+
+struct Navigator<'a> {
+    workspace: &'a Workspace,
+    file_id: FileId,
+    chunk: &'a Chunk,
+    resolution: &'a NameResolution,
+}
+
+impl Navigator<'_> {
+    fn definitions(&self, token: &SyntaxToken) -> Option<Vec<Location>> {
+        if token.kind() == STRING {
+            let requires = self.workspace.requires(self.file_id);
+            let require = requires
+                .iter()
+                .find(|require| require.range == token.text_range());
+            if let Some(require) = require {
+                return Some(vec![self.module_location(&require.name)?]);
+            }
+        }
+        let mut parent = token.parent()?;
+        if parent.kind() == LITERAL {
+            // The string key of an index, e.g. `"b"` in `a["b"]`
+            parent = parent.parent()?;
+        }
+        let (root, path) = match parent.kind() {
+            NAME | NAME_REF => {
+                if let Some(local) = self.resolution.local_at(token.text_range().start()) {
+                    let range = self.resolution.local(local).range;
+                    return Some(vec![Location {
+                        file_id: self.file_id,
+                        range,
+                    }]);
+                }
+                (Expr::cast(parent)?, Vec::new())
+            }
+            FIELD_EXPR | INDEX_EXPR => field_path(Expr::cast(parent)?)?,
+            METHOD_CALL_EXPR => {
+                let call = MethodCallExpr::cast(parent)?;
+                let (root, mut path) = field_path(call.receiver()?)?;
+                path.push(token.text().to_string());
+                (root, path)
+            }
+            FUNCTION_NAME => {
+                let name = FunctionName::cast(parent)?;
+                let root = Expr::NameRef(name.name_ref()?);
+                let path = name
+                    .syntax()
+                    .children_with_tokens()
+                    .filter_map(|element| element.into_token())
+                    .filter(|ident| ident.kind() == IDENT)
+                    .take_while(|ident| ident.text_range().start() <= token.text_range().start())
+                    .map(|ident| ident.text().to_string())
+                    .collect();
+                (root, path)
+            }
+            _ => return None,
+        };
+        self.field_definitions(root, path)
+    }
+
+    /// Returns the definitions of the field at `path` in the value of `root`.
+    fn field_definitions(&self, root: Expr, path: Vec<String>) -> Option<Vec<Location>> {
+        if let Some(module) = self.required_module(&root) {
+            let file_id = self.workspace.resolve_module(&module)?;
+            if path.is_empty() {
+                return Some(vec![self.module_location(&module)?]);
+            }
+            return Some(self.lookup(file_id, SymbolScope::Module, &path));
+        }
+        let name_ref = match root {
+            Expr::NameRef(name_ref) => name_ref,
+            _ => return None,
+        };
+        match self.resolution.resolve(&name_ref) {
+            None => {
+                let mut global_path = vec![name_ref.syntax().text().to_string()];
+                global_path.extend(path);
+                let files = std::iter::once(self.file_id).chain(
+                    self.workspace
+                        .files()
+                        .filter(|&file_id| file_id != self.file_id),
+                );
+                let locations = files
+                    .flat_map(|file_id| self.lookup(file_id, SymbolScope::Global, &global_path))
+                    .collect();
+                Some(locations)
+            }
+            Some(local) if Some(local) == module_local(self.chunk, self.resolution) => {
+                Some(self.lookup(self.file_id, SymbolScope::Module, &path))
+            }
+            Some(local) => {
+                let module = self.required_module(&initializer(self.resolution, local)?)?;
+                let file_id = self.workspace.resolve_module(&module)?;
+                Some(self.lookup(file_id, SymbolScope::Module, &path))
+            }
+        }
+    }
+
+    fn lookup(&self, file_id: FileId, scope: SymbolScope, path: &[String]) -> Vec<Location> {
+        self.workspace
+            .symbol_index(file_id)
+            .lookup(scope, path)
+            .map(|symbol| Location {
+                file_id,
+                range: symbol.name_range,
+            })
+            .collect()
+    }
+
+    /// Returns the name of the module if `expr` is a call of the global `require` with a string
+    /// literal, e.g. `require "util"`.
+    fn required_module(&self, expr: &Expr) -> Option<String> {
+        let call = match expr {
+            Expr::CallExpr(call) => call,
+            _ => return None,
+        };
+        let callee = match call.callee()? {
+            Expr::NameRef(callee) => callee,
+            _ => return None,
+        };
+        if callee.syntax().text() != "require" || self.resolution.resolve(&callee).is_some() {
+            return None;
+        }
+        let mut args = call.arg_list()?.args();
+        match (args.next(), args.next()) {
+            (Some(Expr::Literal(literal)), None) => Some(literal.value()?.as_str()?.into_owned()),
+            _ => None,
+        }
+    }
+
+    fn module_location(&self, module: &str) -> Option<Location> {
+        let file_id = self.workspace.resolve_module(module)?;
+        Some(Location {
+            file_id,
+            range: TextRange::empty(TextSize::from(0)),
+        })
+    }
+}
+
+/// Splits an expression into the expression that a path of field accesses starts with and the
+/// names of the fields, e.g. `a` and `["b", "c"]` for `a.b["c"]`.
+fn field_path(expr: Expr) -> Option<(Expr, Vec<String>)> {
+    let mut path = Vec::new();
+    let mut expr = expr;
+    loop {
+        match expr {
+            Expr::FieldExpr(field) => {
+                path.push(field.field_name()?.text().to_string());
+                expr = field.base()?;
+            }
+            Expr::IndexExpr(index) => {
+                let key = match index.index()? {
+                    Expr::Literal(literal) => literal.value()?,
+                    _ => return None,
+                };
+                match key {
+                    LuaValue::String(_) => path.push(key.as_str()?.into_owned()),
+                    _ => return None,
+                }
+                expr = index.base()?;
+            }
+            _ => break,
+        }
+    }
+    path.reverse();
+    Some((expr, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the texts of the definitions of the name at the `$0` marker in the first file.
+    fn definitions(files: &[(&str, &str)]) -> Vec<(String, String)> {
+        let mut workspace = Workspace::new();
+        let (path, text) = files[0];
+        let offset = TextSize::from(text.find("$0").unwrap() as u32);
+        let file_id = workspace.add_file(path, text.replace("$0", ""));
+        for &(path, text) in &files[1..] {
+            workspace.add_file(path, text);
+        }
+        workspace
+            .goto_definition(file_id, offset)
+            .into_iter()
+            .map(|location| {
+                let text = workspace.file_text(location.file_id);
+                let path = workspace.file_path(location.file_id);
+                (path.display().to_string(), text[location.range].to_string())
+            })
+            .collect()
+    }
+
+    fn location(path: &str, text: &str) -> (String, String) {
+        (path.to_string(), text.to_string())
+    }
+
+    #[test]
+    fn test_locals_and_upvalues() {
+        let main = "local x = 1\nlocal function f() return x + $0x end";
+        assert_eq!(
+            definitions(&[("main.lua", main)]),
+            vec![location("main.lua", "x")]
+        );
+
+        let main = "local x = 1\nlocal function f(x) return $0x end";
+        let mut workspace = Workspace::new();
+        let file_id = workspace.add_file("main.lua", main.replace("$0", ""));
+        let offset = TextSize::from(main.find("$0").unwrap() as u32);
+        let param = TextSize::from(main.find("(x)").unwrap() as u32 + 1);
+        assert_eq!(
+            workspace.goto_definition(file_id, offset),
+            vec![Location {
+                file_id,
+                range: TextRange::at(param, TextSize::from(1))
+            }]
+        );
+    }
+
+    #[test]
+    fn test_globals() {
+        let files = [
+            ("main.lua", "config = {}\nprint(con$0fig.debug)"),
+            (
+                "other.lua",
+                "function setup() config = { debug = true } end",
+            ),
+        ];
+        assert_eq!(
+            definitions(&files),
+            vec![
+                location("main.lua", "config"),
+                location("other.lua", "config")
+            ]
+        );
+        let files = [
+            ("main.lua", "print(config.de$0bug)"),
+            ("other.lua", "config = { debug = true }"),
+        ];
+        assert_eq!(definitions(&files), vec![location("other.lua", "debug")]);
+    }
+
+    #[test]
+    fn test_module_fields() {
+        let util = "local M = {}\nfunction M.trim(s) end\nfunction M:new() end\nreturn M";
+        let files = [
+            (
+                "main.lua",
+                "local util = require 'util'\nutil.tr$0im(s)\nutil:new()",
+            ),
+            ("util.lua", util),
+        ];
+        assert_eq!(definitions(&files), vec![location("util.lua", "M.trim")]);
+        let files = [
+            ("main.lua", "local util = require 'util'\nutil:n$0ew()"),
+            ("util.lua", util),
+        ];
+        assert_eq!(definitions(&files), vec![location("util.lua", "M:new")]);
+        let files = [
+            ("main.lua", "require('util')['tr$0im'](s)"),
+            ("util.lua", util),
+        ];
+        assert_eq!(definitions(&files), vec![location("util.lua", "M.trim")]);
+
+        let files = [(
+            "util.lua",
+            "local M = {}\nfunction M.a() return M.b$0() end\nM.b = function() end\nreturn M",
+        )];
+        assert_eq!(definitions(&files), vec![location("util.lua", "M.b")]);
+    }
+
+    #[test]
+    fn test_required_modules() {
+        let files = [
+            ("main.lua", "local strings = require 'util.str$0ings'"),
+            ("util/strings.lua", "return {}"),
+        ];
+        assert_eq!(definitions(&files), vec![location("util/strings.lua", "")]);
+        let files = [("main.lua", "local json = require 'js$0on'")];
+        assert!(definitions(&files).is_empty());
+        let files = [
+            ("main.lua", "local x = {}\nprint(x.f$0oo)"),
+            ("util.lua", "return { foo = 1 }"),
+        ];
+        assert!(definitions(&files).is_empty());
+    }
+}
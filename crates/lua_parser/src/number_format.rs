@@ -0,0 +1,247 @@
+//! Renders numbers as Lua literals, for tools that generate or rewrite code and shouldn't change
+//! the value of a literal, e.g. when folding constants or converting between dialects.
+//!
+//! Floats are written with the fewest digits that read back as exactly the same value, which is
+//! not what Lua's `tostring` does: it uses `%.14g`, which loses precision, e.g. `0.1 + 0.2` is
+//! printed as `0.3`. `lua_tostring` reproduces that for code that needs the text a Lua program
+//! would see at runtime.
+
+/// The notation of a number literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NumberStyle {
+    /// E.g. `255` or `1.5e-3`
+    Decimal,
+    /// E.g. `0xff` or `0x1.8p+1`
+    Hex,
+}
+
+impl NumberStyle {
+    /// Returns the style of the text of a number literal, to write a changed value in the same
+    /// notation.
+    pub fn of_literal(text: &str) -> NumberStyle {
+        if text.starts_with("0x") || text.starts_with("0X") {
+            NumberStyle::Hex
+        } else {
+            NumberStyle::Decimal
+        }
+    }
+}
+
+/// Returns a literal that Lua 5.3 and later read as the integer `value`. Negative values start
+/// with a minus, which is an operator, so they are an expression rather than a literal.
+///
+/// `i64::MIN` is written in hex, because its decimal digits don't fit in an integer and would be
+/// read as a float.
+pub fn format_integer(value: i64, style: NumberStyle) -> String {
+    match style {
+        NumberStyle::Hex if value < 0 && value != i64::MIN => {
+            format!("-0x{:x}", value.unsigned_abs())
+        }
+        NumberStyle::Hex => format!("0x{:x}", value as u64),
+        NumberStyle::Decimal if value == i64::MIN => format!("0x{:x}", value as u64),
+        NumberStyle::Decimal => value.to_string(),
+    }
+}
+
+/// Returns the shortest literal that Lua reads as the float `value`, e.g. `0.1`, `1.0` or
+/// `1e+100`. Decimal literals always contain a `.` or an exponent, so they aren't read as
+/// integers. Negative values start with a minus.
+///
+/// Infinity is written as `1e999`, which overflows to infinity, and NaN as the expression
+/// `(0/0)`.
+pub fn format_float(value: f64, style: NumberStyle) -> String {
+    if value.is_nan() {
+        return "(0/0)".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "1e999" } else { "-1e999" }.to_string();
+    }
+    match style {
+        NumberStyle::Hex => hex_float(value),
+        NumberStyle::Decimal => {
+            let mut plain = value.to_string();
+            if !plain.contains('.') {
+                plain.push_str(".0");
+            }
+            let exponent = c_exponent(&format!("{:e}", value));
+            if exponent.len() < plain.len() {
+                exponent
+            } else {
+                plain
+            }
+        }
+    }
+}
+
+/// Returns the text that `tostring` returns for the float `value` in Lua 5.3 and later: the
+/// result of `%.14g`, with `.0` appended if it looks like an integer, e.g. `0.3` for
+/// `0.1 + 0.2` and `1e+15` for `10^15`.
+pub fn lua_tostring(value: f64) -> String {
+    if value.is_nan() {
+        return if value.is_sign_negative() {
+            "-nan"
+        } else {
+            "nan"
+        }
+        .to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    let text = format_g(value, 14);
+    if text.contains(['.', 'e']) {
+        text
+    } else {
+        text + ".0"
+    }
+}
+
+/// Formats `value` like `printf("%.{precision}g")`.
+fn format_g(value: f64, precision: usize) -> String {
+    let exponential = format!("{:.*e}", precision - 1, value);
+    let (mantissa, exponent) = exponential
+        .split_once('e')
+        .expect("the exponential format contains an `e`");
+    let exponent: i32 = exponent.parse().expect("the exponent is an integer");
+    if exponent < -4 || exponent >= precision as i32 {
+        format!(
+            "{}{}",
+            trim_fraction(mantissa),
+            c_exponent(&format!("e{}", exponent))
+        )
+    } else {
+        let decimals = (precision as i32 - 1 - exponent) as usize;
+        trim_fraction(&format!("{:.*}", decimals, value)).to_string()
+    }
+}
+
+/// Removes the trailing zeros of a fraction, and the `.` if nothing remains of it.
+fn trim_fraction(text: &str) -> &str {
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        text
+    }
+}
+
+/// Converts the exponent of Rust's exponential notation to the one of C, e.g. `1e5` to `1e+05`.
+fn c_exponent(text: &str) -> String {
+    let (mantissa, exponent) = text
+        .split_once('e')
+        .expect("the exponential format contains an `e`");
+    let (sign, digits) = match exponent.strip_prefix('-') {
+        Some(digits) => ('-', digits),
+        None => ('+', exponent),
+    };
+    format!("{}e{}{:0>2}", mantissa, sign, digits)
+}
+
+/// Formats a finite float like `printf("%a")`, e.g. `0x1.8p+1` for `3.0`.
+fn hex_float(value: f64) -> String {
+    let sign = if value.is_sign_negative() { "-" } else { "" };
+    let bits = value.abs().to_bits();
+    let biased_exponent = (bits >> 52) as i32;
+    let mut mantissa = bits & ((1 << 52) - 1);
+    let (lead, exponent) = match biased_exponent {
+        0 if mantissa == 0 => (0, 0),
+        0 => (0, -1022),
+        _ => (1, biased_exponent - 1023),
+    };
+    let mut digits = 13;
+    while digits > 0 && mantissa & 0xf == 0 {
+        mantissa >>= 4;
+        digits -= 1;
+    }
+    if digits == 0 {
+        format!("{}0x{}p{:+}", sign, lead, exponent)
+    } else {
+        format!(
+            "{}0x{}.{:0width$x}p{:+}",
+            sign,
+            lead,
+            mantissa,
+            exponent,
+            width = digits
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::LuaValue, AstNode, Chunk};
+
+    /// Reads `literal` like Lua does.
+    fn read(literal: &str) -> Option<LuaValue> {
+        let chunk = Chunk::parse(&format!("return {}", literal)).tree();
+        let literal = chunk
+            .syntax()
+            .descendants()
+            .find_map(crate::ast::Literal::cast)?;
+        literal.value()
+    }
+
+    #[test]
+    fn test_format_integer() {
+        use NumberStyle::*;
+        assert_eq!(format_integer(255, Decimal), "255");
+        assert_eq!(format_integer(255, Hex), "0xff");
+        assert_eq!(format_integer(-255, Hex), "-0xff");
+        assert_eq!(format_integer(i64::MIN, Decimal), "0x8000000000000000");
+        assert_eq!(format_integer(i64::MIN, Hex), "0x8000000000000000");
+        assert_eq!(
+            read(&format_integer(i64::MIN, Decimal)),
+            Some(LuaValue::Integer(i64::MIN))
+        );
+        assert_eq!(NumberStyle::of_literal("0XA"), Hex);
+        assert_eq!(NumberStyle::of_literal("1e5"), Decimal);
+    }
+
+    #[test]
+    fn test_format_float() {
+        use NumberStyle::*;
+        assert_eq!(format_float(1.0, Decimal), "1.0");
+        assert_eq!(format_float(0.1 + 0.2, Decimal), "0.30000000000000004");
+        assert_eq!(format_float(1e100, Decimal), "1e+100");
+        assert_eq!(format_float(1.5e-7, Decimal), "1.5e-07");
+        assert_eq!(format_float(-2.5, Decimal), "-2.5");
+        assert_eq!(format_float(f64::INFINITY, Decimal), "1e999");
+        assert_eq!(format_float(f64::NAN, Decimal), "(0/0)");
+        assert_eq!(format_float(3.0, Hex), "0x1.8p+1");
+        assert_eq!(format_float(1.0, Hex), "0x1p+0");
+        assert_eq!(format_float(0.0, Hex), "0x0p+0");
+        assert_eq!(format_float(-0.1, Hex), "-0x1.999999999999ap-4");
+        assert_eq!(format_float(f64::MIN_POSITIVE / 2.0, Hex), "0x0.8p-1022");
+
+        let values = [
+            0.1,
+            1.0 / 3.0,
+            123456789.125,
+            1e300,
+            5e-324,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+            2f64.powi(63),
+        ];
+        for &value in values.iter() {
+            for &style in [Decimal, Hex].iter() {
+                let literal = format_float(value, style);
+                assert_eq!(read(&literal), Some(LuaValue::Float(value)), "{}", literal);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lua_tostring() {
+        assert_eq!(lua_tostring(0.1 + 0.2), "0.3");
+        assert_eq!(lua_tostring(1.0), "1.0");
+        assert_eq!(lua_tostring(-0.0), "-0.0");
+        assert_eq!(lua_tostring(1e15), "1e+15");
+        assert_eq!(lua_tostring(1e14), "1e+14");
+        assert_eq!(lua_tostring(123456789012.5), "123456789012.5");
+        assert_eq!(lua_tostring(0.0001), "0.0001");
+        assert_eq!(lua_tostring(0.00001), "1e-05");
+        assert_eq!(lua_tostring(2f64.powi(63)), "9.2233720368548e+18");
+        assert_eq!(lua_tostring(f64::NEG_INFINITY), "-inf");
+    }
+}
@@ -0,0 +1,155 @@
+//! A cache of parses keyed by the content of the source, for editors that parse the same buffers
+//! over and over again, e.g. when a language server receives a `didOpen` for a file it already
+//! parsed or a `didChange` that restores an earlier version of the text.
+//!
+//! The parser accepts the syntax of all supported dialects, so a parse only depends on the text
+//! and the `ParseConfig`, and the dialect isn't part of the key.
+
+use crate::{Chunk, Parse, ParseConfig};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    text_hash: u64,
+    config: ParseConfig,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    /// The text the parse was created from, to tell texts with the same hash apart
+    text: String,
+    parse: Parse<Chunk>,
+    last_used: u64,
+}
+
+/// Caches the parses of the most recently parsed texts. When the cache is full, the entry that
+/// was least recently used is evicted.
+#[derive(Debug)]
+pub struct ParseCache {
+    capacity: usize,
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Incremented on every lookup, to order the entries by their last use
+    clock: u64,
+}
+
+impl ParseCache {
+    /// Creates a cache that holds at most `capacity` parses.
+    pub fn new(capacity: usize) -> ParseCache {
+        ParseCache {
+            capacity,
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Parses `text` with the default `ParseConfig`, or returns the cached parse of an identical
+    /// text.
+    pub fn parse(&mut self, text: &str) -> Parse<Chunk> {
+        self.parse_with_config(text, &ParseConfig::default())
+    }
+
+    /// Parses `text` with `config`, or returns the cached parse of an identical text that was
+    /// parsed with the same config.
+    pub fn parse_with_config(&mut self, text: &str, config: &ParseConfig) -> Parse<Chunk> {
+        self.clock += 1;
+        let key = CacheKey {
+            text_hash: text_hash(text),
+            config: config.clone(),
+        };
+        if let Some(entry) = self.entries.get_mut(&key) {
+            if entry.text == text {
+                entry.last_used = self.clock;
+                return entry.parse.clone();
+            }
+        }
+
+        let parse = Chunk::parse_with_config(text, config);
+        self.entries.insert(
+            key,
+            CacheEntry {
+                text: text.to_string(),
+                parse: parse.clone(),
+                last_used: self.clock,
+            },
+        );
+        while self.entries.len() > self.capacity {
+            self.evict_least_recently_used();
+        }
+        parse
+    }
+
+    /// Returns true if the parse of `text` with `config` is cached.
+    pub fn contains(&self, text: &str, config: &ParseConfig) -> bool {
+        let key = CacheKey {
+            text_hash: text_hash(text),
+            config: config.clone(),
+        };
+        self.entries
+            .get(&key)
+            .is_some_and(|entry| entry.text == text)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = oldest {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+fn text_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cache() {
+        let mut cache = ParseCache::new(2);
+        let config = ParseConfig::default();
+        let parse = cache.parse("local a = 1");
+        assert_eq!(cache.parse("local a = 1"), parse);
+        assert_eq!(cache.len(), 1);
+
+        let placeholders = ParseConfig {
+            placeholders: true,
+            ..ParseConfig::default()
+        };
+        cache.parse_with_config("local a = 1", &placeholders);
+        assert!(cache.contains("local a = 1", &placeholders));
+        assert_eq!(cache.len(), 2);
+
+        // The parse with the default config was used least recently
+        cache.parse("local b = 2");
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.contains("local a = 1", &config));
+        assert!(cache.contains("local a = 1", &placeholders));
+        assert!(cache.contains("local b = 2", &config));
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
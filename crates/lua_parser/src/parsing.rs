@@ -0,0 +1,449 @@
+//! Lexing and parsing of Lua source.
+//!
+//! The parser itself does not know anything about text or trees: it reads token kinds from a
+//! `TokenSource` and reports the structure it recognizes to a `TreeSink`. This crate uses it to
+//! build a lossless syntax tree from text, but consumers can drive it with their own token buffer
+//! or build their own tree representation.
+
+// No input may make the lexer or the parser panic, out of bounds indexing has to be handled
+// explicitly with `get`. Invariants of the parser itself are checked with `unreachable!`.
+#![cfg_attr(not(test), deny(clippy::indexing_slicing))]
+
+mod event;
+mod grammar;
+mod parser;
+mod text_token_source;
+mod text_tree_sink;
+
+use self::{text_token_source::TextTokenSource, text_tree_sink::TextTreeSink};
+use crate::{
+    syntax_node::GreenNode, tokenize, ParseError, SyntaxError, SyntaxErrorKind, SyntaxKind,
+    SyntaxNode,
+};
+use std::time::{Duration, Instant};
+
+/// A source of tokens for the parser. Trivia (whitespace and comments) must not be part of the
+/// source. Multi character operators like `==` or `..` are expected as a sequence of single
+/// character tokens; the parser combines them if they are joint.
+pub trait TokenSource {
+    /// Returns the kind of the token at `pos`, or `EOF` if `pos` is past the last token.
+    fn token_kind(&self, pos: usize) -> SyntaxKind;
+
+    /// Returns true if there is no trivia between the token at `pos` and the token after it.
+    fn is_token_joint_to_next(&self, pos: usize) -> bool;
+
+    /// Returns an estimate of the number of tokens, which the parser uses to reserve memory for
+    /// its events up front. Defaults to zero.
+    fn token_count_hint(&self) -> usize {
+        0
+    }
+}
+
+/// Receives the structure recognized by the parser.
+pub trait TreeSink {
+    /// Adds a token of the given kind that consists of the next `n_tokens` tokens of the
+    /// `TokenSource`.
+    fn token(&mut self, kind: SyntaxKind, n_tokens: u8);
+
+    /// Starts a new node; all tokens and nodes up to the matching `finish_node` are its children.
+    fn start_node(&mut self, kind: SyntaxKind);
+
+    fn finish_node(&mut self);
+
+    /// Reports an error at the current position.
+    fn error(&mut self, error: ParseError);
+}
+
+/// Options that control the parser.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParseConfig {
+    /// The maximum number of nested expressions and blocks. The parser uses the native stack for
+    /// nesting, so deeper code is reported as an error and consumed into an `ERROR` node instead
+    /// of being parsed. Chains of operators, e.g. `a .. b .. c`, are parsed iteratively and don't
+    /// count towards the limit.
+    pub recursion_limit: u32,
+    /// The maximum number of errors that are reported, or `None` to report all of them. The
+    /// errors past the limit are replaced by a single error that tells how many were dropped.
+    pub max_errors: Option<usize>,
+    /// Parses placeholders like `$name` or `$_` into `PLACEHOLDER` nodes, for templates that
+    /// describe code instead of being code, e.g. the patterns of structural search. A placeholder
+    /// can stand for an expression, a name that is declared or a statement. Without this option a
+    /// `$` is an unexpected token.
+    pub placeholders: bool,
+}
+
+impl Default for ParseConfig {
+    fn default() -> ParseConfig {
+        // The same limit as the reference implementation
+        ParseConfig {
+            recursion_limit: 200,
+            max_errors: Some(100),
+            placeholders: false,
+        }
+    }
+}
+
+/// Parses a Lua chunk from the tokens of `token_source` into `tree_sink`.
+pub fn parse(token_source: &dyn TokenSource, tree_sink: &mut dyn TreeSink) {
+    parse_with_config(token_source, tree_sink, &ParseConfig::default())
+}
+
+/// Parses a Lua chunk from the tokens of `token_source` into `tree_sink` using the options in
+/// `config`.
+pub fn parse_with_config(
+    token_source: &dyn TokenSource,
+    tree_sink: &mut dyn TreeSink,
+    config: &ParseConfig,
+) {
+    let mut p = parser::Parser::new(token_source, config);
+    grammar::chunk(&mut p);
+    let events = p.finish();
+    event::process(tree_sink, events);
+}
+
+/// An event reported by the parser, see `parse_events`. The events correspond one to one to the
+/// calls on a `TreeSink`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseEvent {
+    /// Starts a new node; all events up to the matching `Finish` belong to it.
+    Start { kind: SyntaxKind },
+
+    /// A token of the given kind that consists of the next `n_tokens` tokens of the
+    /// `TokenSource`.
+    Token { kind: SyntaxKind, n_tokens: u8 },
+
+    /// Finishes the last started node.
+    Finish,
+
+    /// An error at the current position.
+    Error { error: ParseError },
+}
+
+/// Parses a Lua chunk from the tokens of `token_source` and returns the events reported by the
+/// parser. Start and finish events are properly nested, so the events can be post-processed (e.g.
+/// to attach trivia differently) and fed to a `TreeSink` later with `replay_events`.
+pub fn parse_events(token_source: &dyn TokenSource) -> Vec<ParseEvent> {
+    let mut events = Vec::new();
+    parse(token_source, &mut events);
+    events
+}
+
+/// Feeds previously collected `events` to `tree_sink`.
+pub fn replay_events(events: &[ParseEvent], tree_sink: &mut dyn TreeSink) {
+    for event in events {
+        match event {
+            ParseEvent::Start { kind } => tree_sink.start_node(*kind),
+            ParseEvent::Token { kind, n_tokens } => tree_sink.token(*kind, *n_tokens),
+            ParseEvent::Finish => tree_sink.finish_node(),
+            ParseEvent::Error { error } => tree_sink.error(error.clone()),
+        }
+    }
+}
+
+/// Statistics about a single parse, see `Chunk::parse_with_stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseStats {
+    /// The number of tokens produced by the lexer, including trivia.
+    pub tokens: usize,
+    /// The number of nodes in the tree, including the root.
+    pub nodes: usize,
+    /// The number of reported errors.
+    pub errors: usize,
+    /// The number of times the parser skipped tokens to recover from an error, i.e. the number of
+    /// `ERROR` nodes.
+    pub recoveries: usize,
+    pub lex_time: Duration,
+    /// The time spent parsing and building the tree.
+    pub parse_time: Duration,
+}
+
+/// Parses `text` into a green tree and the errors found while parsing.
+pub(crate) fn parse_text(text: &str, config: &ParseConfig) -> (GreenNode, Vec<SyntaxError>) {
+    let (green, errors, _) = parse_text_timed(text, config);
+    (green, errors)
+}
+
+/// Parses `text` like `parse_text` and collects statistics about the parse.
+pub(crate) fn parse_text_with_stats(
+    text: &str,
+    config: &ParseConfig,
+) -> (GreenNode, Vec<SyntaxError>, ParseStats) {
+    let (green, errors, mut stats) = parse_text_timed(text, config);
+    stats.errors = errors.len();
+    for node in SyntaxNode::new_root(green.clone()).descendants() {
+        stats.nodes += 1;
+        if node.kind() == SyntaxKind::ERROR {
+            stats.recoveries += 1;
+        }
+    }
+    (green, errors, stats)
+}
+
+/// Parses `text` and returns the statistics that are known without walking the tree.
+fn parse_text_timed(text: &str, config: &ParseConfig) -> (GreenNode, Vec<SyntaxError>, ParseStats) {
+    trace_span!("parse", len = text.len());
+    let lex_start = Instant::now();
+    let tokens = tokenize(text);
+    let lex_time = lex_start.elapsed();
+
+    let parse_start = Instant::now();
+    let token_source = TextTokenSource::new(&tokens);
+    let mut tree_sink = TextTreeSink::new(text, &tokens);
+    parse_with_config(&token_source, &mut tree_sink, config);
+    let (green, errors) = tree_sink.finish();
+    let errors = clean_up_errors(errors, config.max_errors);
+    let stats = ParseStats {
+        tokens: tokens.len(),
+        lex_time,
+        parse_time: parse_start.elapsed(),
+        ..ParseStats::default()
+    };
+    trace_event!(
+        tokens = stats.tokens,
+        errors = errors.len(),
+        lex_time = ?stats.lex_time,
+        parse_time = ?stats.parse_time,
+        "parsed"
+    );
+    (green, errors, stats)
+}
+
+/// Sorts `errors` by their position and removes the noise from error recovery: when the parser
+/// reports several errors at the same position, only the first one is kept, the others are
+/// caused by it. At most `max_errors` errors are kept, followed by an error that tells how many
+/// errors were dropped.
+fn clean_up_errors(mut errors: Vec<SyntaxError>, max_errors: Option<usize>) -> Vec<SyntaxError> {
+    // The sort is stable, so the first error at a position stays first
+    errors.sort_by_key(|error| error.location().offset());
+    errors.dedup_by_key(|error| error.location().offset());
+    if let Some(max_errors) = max_errors {
+        if let Some(first_dropped) = errors.get(max_errors) {
+            let dropped = errors.len() - max_errors;
+            let location = first_dropped.location();
+            errors.truncate(max_errors);
+            let message = format!("too many errors, {} more errors were not reported", dropped);
+            errors.push(SyntaxError::new(
+                SyntaxErrorKind::ParseError(ParseError::new(message)),
+                location,
+            ));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        clean_up_errors, parse, parse_events, parse_with_config, replay_events, ParseConfig,
+        ParseEvent, TokenSource, TreeSink,
+    };
+    use crate::{
+        Chunk, ParseError, SyntaxError, SyntaxErrorKind, SyntaxKind, SyntaxKind::*, TextSize,
+    };
+    use std::fmt::Write;
+
+    /// A token source without any trivia, all tokens are separated.
+    struct Tokens(Vec<SyntaxKind>);
+
+    impl TokenSource for Tokens {
+        fn token_kind(&self, pos: usize) -> SyntaxKind {
+            self.0.get(pos).cloned().unwrap_or(EOF)
+        }
+
+        fn is_token_joint_to_next(&self, _pos: usize) -> bool {
+            false
+        }
+    }
+
+    /// Writes the structure reported by the parser as an s-expression.
+    #[derive(Default)]
+    struct SExpr(String);
+
+    impl TreeSink for SExpr {
+        fn token(&mut self, kind: SyntaxKind, n_tokens: u8) {
+            write!(self.0, " {:?}", kind).unwrap();
+            assert_eq!(n_tokens, 1);
+        }
+
+        fn start_node(&mut self, kind: SyntaxKind) {
+            write!(self.0, " ({:?}", kind).unwrap();
+        }
+
+        fn finish_node(&mut self) {
+            self.0.push(')');
+        }
+
+        fn error(&mut self, error: ParseError) {
+            write!(self.0, " !{}", error).unwrap();
+        }
+    }
+
+    fn parse_tokens(tokens: Vec<SyntaxKind>) -> String {
+        let mut sink = SExpr::default();
+        parse(&Tokens(tokens), &mut sink);
+        sink.0.trim_start().to_owned()
+    }
+
+    #[test]
+    fn parse_custom_token_source() {
+        assert_eq!(
+            parse_tokens(vec![LOCAL_KW, IDENT, EQ, INT_NUMBER, PLUS, IDENT]),
+            "(CHUNK (BLOCK (LOCAL_ASSIGN_STMT LOCAL_KW (NAME_LIST (NAME IDENT)) EQ \
+             (EXPR_LIST (BIN_EXPR (LITERAL INT_NUMBER) PLUS (NAME_REF IDENT))))))"
+        );
+    }
+
+    #[test]
+    fn separated_tokens_are_not_combined() {
+        assert_eq!(
+            parse_tokens(vec![IDENT, EQ, IDENT, EQ, EQ, IDENT]),
+            "(CHUNK (BLOCK (ASSIGN_STMT (EXPR_LIST (NAME_REF IDENT)) EQ (EXPR_LIST (NAME_REF IDENT))) \
+             (ERROR !expected a statement EQ) (ERROR !expected a statement EQ) \
+             (EXPR_STMT (NAME_REF IDENT) !expected an assignment or a function call)))"
+        );
+    }
+
+    #[test]
+    fn replayed_events_match_direct_parse() {
+        let tokens = Tokens(vec![
+            IDENT, L_PAREN, STRING, COMMA, R_PAREN, RETURN_KW, IDENT,
+        ]);
+        let events = parse_events(&tokens);
+        assert_eq!(events.first(), Some(&ParseEvent::Start { kind: CHUNK }));
+        assert_eq!(events.last(), Some(&ParseEvent::Finish));
+
+        let mut direct = SExpr::default();
+        parse(&tokens, &mut direct);
+        let mut replayed = SExpr::default();
+        replay_events(&events, &mut replayed);
+        assert_eq!(direct.0, replayed.0);
+    }
+
+    #[test]
+    fn nesting_beyond_the_recursion_limit_is_skipped() {
+        let config = ParseConfig {
+            recursion_limit: 2,
+            ..ParseConfig::default()
+        };
+        let mut sink = SExpr::default();
+        let tokens = Tokens(vec![
+            IDENT, EQ, L_PAREN, L_PAREN, INT_NUMBER, R_PAREN, R_PAREN, RETURN_KW,
+        ]);
+        parse_with_config(&tokens, &mut sink, &config);
+        assert_eq!(
+            sink.0.trim_start(),
+            "(CHUNK (BLOCK (ASSIGN_STMT (EXPR_LIST (NAME_REF IDENT)) EQ (EXPR_LIST (PAREN_EXPR \
+             L_PAREN (PAREN_EXPR L_PAREN (ERROR !the code is nested too deeply, the limit is 2 \
+             INT_NUMBER) R_PAREN) R_PAREN))) (RETURN_STMT RETURN_KW)))"
+        );
+    }
+
+    #[test]
+    fn deeply_nested_code_does_not_overflow_the_stack() {
+        let depth = 10_000;
+        let text = format!(
+            "x = {}1{}\nfunction f() {}{}end\nlocal y",
+            "(".repeat(depth),
+            ")".repeat(depth),
+            "do ".repeat(depth),
+            "end ".repeat(depth)
+        );
+        let parse = Chunk::parse(&text);
+        assert_eq!(parse.errors().len(), 2);
+        let node = parse.syntax_node();
+        let block = node.first_child().unwrap();
+        let kinds: Vec<_> = block.children().map(|node| node.kind()).collect();
+        assert_eq!(kinds, vec![ASSIGN_STMT, FUNCTION_STMT, LOCAL_ASSIGN_STMT]);
+    }
+
+    #[test]
+    fn operator_chains_are_not_limited() {
+        let config = ParseConfig {
+            recursion_limit: 10,
+            ..ParseConfig::default()
+        };
+        let text = format!(
+            "x = 1{}\ny = {}1\nz = 1{}",
+            " .. 1".repeat(1000),
+            "- not ".repeat(500),
+            " + 1".repeat(1000)
+        );
+        assert_eq!(Chunk::parse_with_config(&text, &config).errors(), &[]);
+    }
+
+    #[test]
+    fn errors_are_sorted_and_cascades_dropped() {
+        let error = |message: &str, offset: u32| {
+            SyntaxError::new(
+                SyntaxErrorKind::ParseError(ParseError::new(message)),
+                TextSize::from(offset),
+            )
+        };
+        let errors = vec![
+            error("b", 5),
+            error("a", 1),
+            error("a", 1),
+            error("cascade", 5),
+        ];
+        assert_eq!(
+            clean_up_errors(errors, None),
+            vec![error("a", 1), error("b", 5)]
+        );
+    }
+
+    #[test]
+    fn errors_beyond_the_limit_are_summarized() {
+        let config = ParseConfig {
+            max_errors: Some(3),
+            ..ParseConfig::default()
+        };
+        let parse = Chunk::parse_with_config(&"=\n".repeat(10), &config);
+        let messages: Vec<_> = parse.errors().iter().map(|e| e.to_string()).collect();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(
+            messages[3],
+            "too many errors, 7 more errors were not reported"
+        );
+        assert_eq!(parse.errors()[3].location().offset(), TextSize::from(6));
+    }
+
+    #[test]
+    fn placeholders_are_parsed_in_templates() {
+        let config = ParseConfig {
+            placeholders: true,
+            ..ParseConfig::default()
+        };
+        let text = "local $x = $a + $_\n$body\nfunction $f($p, ...) $t.k = $g($x) end";
+        let parse = Chunk::parse_with_config(text, &config);
+        assert_eq!(parse.errors(), &[]);
+        let parents: Vec<_> = parse
+            .syntax_node()
+            .descendants()
+            .filter(|node| node.kind() == PLACEHOLDER)
+            .map(|node| (node.text().to_string(), node.parent().unwrap().kind()))
+            .collect();
+        let expected = vec![
+            ("$x", NAME_LIST),
+            ("$a", BIN_EXPR),
+            ("$_", BIN_EXPR),
+            ("$body", EXPR_STMT),
+            ("$f", FUNCTION_NAME),
+            ("$p", PARAM_LIST),
+            ("$t", FIELD_EXPR),
+            ("$g", CALL_EXPR),
+            ("$x", ARG_LIST),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(text, kind)| (text.to_string(), kind))
+            .collect();
+        assert_eq!(parents, expected);
+
+        let parse = Chunk::parse(text);
+        assert!(!parse.errors().is_empty());
+        assert!(parse
+            .syntax_node()
+            .descendants()
+            .all(|node| node.kind() != PLACEHOLDER));
+    }
+}
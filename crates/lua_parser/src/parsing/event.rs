@@ -0,0 +1,119 @@
+//! The parser does not build a tree directly but produces a flat list of events which are
+//! converted into calls on a `TreeSink` afterwards. This makes it possible to wrap nodes that
+//! were already completed into a new parent node after the fact (see `CompletedMarker::precede`),
+//! which is how left-recursive constructs like `a.b.c` or `1 + 2 + 3` are parsed.
+
+use crate::{
+    parsing::{ParseEvent, TreeSink},
+    ParseError,
+    SyntaxKind::{self, *},
+};
+use smallvec::SmallVec;
+use std::mem;
+
+#[derive(Debug)]
+pub(crate) enum Event {
+    /// Starts a node. If `forward_parent` is set, the node at `pos + forward_parent` must be
+    /// started as the parent of this node.
+    ///
+    /// Abandoned nodes are marked with `kind` `TOMBSTONE`.
+    Start {
+        kind: SyntaxKind,
+        forward_parent: Option<u32>,
+    },
+
+    /// Finishes the last started node.
+    Finish,
+
+    /// Produces a single token of `kind` which consists of `n_raw_tokens` tokens of the
+    /// `TokenSource`, e.g. `==` which is made up of two `=` tokens.
+    Token {
+        kind: SyntaxKind,
+        n_raw_tokens: u8,
+    },
+
+    Error {
+        error: ParseError,
+    },
+}
+
+impl Event {
+    pub(crate) fn tombstone() -> Event {
+        Event::Start {
+            kind: TOMBSTONE,
+            forward_parent: None,
+        }
+    }
+}
+
+/// Feeds `events` to `sink`, resolving the forward parents of the start events.
+pub(super) fn process(sink: &mut dyn TreeSink, mut events: Vec<Event>) {
+    let mut forward_parents: SmallVec<[SyntaxKind; 4]> = SmallVec::new();
+
+    for idx in 0..events.len() {
+        match events
+            .get_mut(idx)
+            .map(|event| mem::replace(event, Event::tombstone()))
+        {
+            None
+            | Some(Event::Start {
+                kind: TOMBSTONE, ..
+            }) => (),
+
+            Some(Event::Start {
+                kind,
+                forward_parent,
+            }) => {
+                // Walk the chain of forward parents, the outermost parent has to be started
+                // first.
+                forward_parents.push(kind);
+                let mut parent_idx = idx;
+                let mut forward_parent = forward_parent;
+                while let Some(offset) = forward_parent {
+                    parent_idx += offset as usize;
+                    forward_parent = match events
+                        .get_mut(parent_idx)
+                        .map(|event| mem::replace(event, Event::tombstone()))
+                    {
+                        Some(Event::Start {
+                            kind,
+                            forward_parent,
+                        }) => {
+                            if kind != TOMBSTONE {
+                                forward_parents.push(kind);
+                            }
+                            forward_parent
+                        }
+                        _ => unreachable!(),
+                    };
+                }
+
+                for kind in forward_parents.drain(..).rev() {
+                    sink.start_node(kind);
+                }
+            }
+            Some(Event::Finish) => sink.finish_node(),
+            Some(Event::Token { kind, n_raw_tokens }) => sink.token(kind, n_raw_tokens),
+            Some(Event::Error { error }) => sink.error(error),
+        }
+    }
+}
+
+/// Collects the calls of the parser into a list of events, see `parse_events`.
+impl TreeSink for Vec<ParseEvent> {
+    fn token(&mut self, kind: SyntaxKind, n_tokens: u8) {
+        self.push(ParseEvent::Token { kind, n_tokens })
+    }
+
+    fn start_node(&mut self, kind: SyntaxKind) {
+        self.push(ParseEvent::Start { kind })
+    }
+
+    fn finish_node(&mut self) {
+        self.push(ParseEvent::Finish)
+    }
+
+    fn error(&mut self, error: ParseError) {
+        self.push(ParseEvent::Error { error })
+    }
+}
@@ -0,0 +1,143 @@
+//! This is the actual "grammar" of the Lua language.
+//!
+//! Each function in this module and its children corresponds to a production of the Lua grammar
+//! as described in the reference manual. Functions are named after the production they parse
+//! and report their structure through `Marker`s. The parser accepts the union of the syntax of
+//! all supported Lua versions; checking whether a construct is valid for a particular `Dialect`
+//! is left to later passes.
+//!
+//! The parser never fails: when it encounters unexpected tokens, it reports an error and
+//! recovers, so the resulting tree always covers the complete input.
+
+mod expressions;
+mod statements;
+
+use super::parser::{CompletedMarker, Marker, Parser};
+use crate::{
+    ParseError,
+    SyntaxKind::{self, *},
+    TokenSet,
+};
+
+/// Tokens that end a block.
+const BLOCK_END: TokenSet = token_set![EOF, END_KW, ELSE_KW, ELSEIF_KW, UNTIL_KW];
+
+pub(crate) fn chunk(p: &mut Parser) {
+    let m = p.start();
+    block_until(p, token_set![EOF]);
+    m.complete(p, CHUNK);
+}
+
+/// Parses a block that is terminated by one of the tokens in `BLOCK_END`, e.g. the body of a
+/// `while` statement.
+fn block(p: &mut Parser) {
+    if p.enter() {
+        block_until(p, BLOCK_END);
+        p.leave();
+    } else {
+        let m = p.start();
+        p.err_too_deep(BLOCK_END);
+        m.complete(p, BLOCK);
+    }
+}
+
+fn block_until(p: &mut Parser, end: TokenSet) {
+    let m = p.start();
+    while !p.at_ts(end) {
+        let is_return = p.at(RETURN_KW);
+        statements::statement(p);
+        // test_err return_not_last
+        // return 1
+        // x = 2
+        if is_return && !p.at_ts(end) {
+            p.error(ParseError::new(
+                "'return' must be the last statement of a block",
+            ));
+        }
+    }
+    m.complete(p, BLOCK);
+}
+
+/// Parses a name that is being declared, e.g. the name of a local variable or a parameter.
+fn name(p: &mut Parser) {
+    if at_placeholder(p) {
+        placeholder(p);
+    } else if p.at(IDENT) || p.at(GOTO_KW) {
+        let m = p.start();
+        expect_ident(p);
+        m.complete(p, NAME);
+    } else {
+        p.error(ParseError::expected(token_set![IDENT]));
+    }
+}
+
+/// Returns true if the current token is `goto` used as a name in an expression, e.g. `f(goto)`,
+/// which is valid in Lua 5.1. Followed by a name it is a `goto` statement. Later versions reserve
+/// `goto`, which is reported by `check_compatibility`.
+fn at_goto_name(p: &Parser) -> bool {
+    p.at(GOTO_KW) && p.nth(1) != IDENT
+}
+
+/// Consumes an identifier, or `goto` as an identifier, or emits an error otherwise.
+fn expect_ident(p: &mut Parser) -> bool {
+    if p.at(GOTO_KW) {
+        p.bump_remap(IDENT);
+        true
+    } else {
+        p.expect(IDENT)
+    }
+}
+
+/// Returns true if the current token starts a placeholder of a template, see
+/// `ParseConfig::placeholders`.
+fn at_placeholder(p: &Parser) -> bool {
+    p.placeholders() && p.at(DOLLAR)
+}
+
+fn placeholder(p: &mut Parser) -> CompletedMarker {
+    assert!(at_placeholder(p));
+    let m = p.start();
+    p.bump();
+    p.expect(IDENT);
+    m.complete(p, PLACEHOLDER)
+}
+
+/// Parses the parameters and the body of a function, i.e. everything after the name.
+fn function_body(p: &mut Parser) {
+    param_list(p);
+    block(p);
+    p.expect(END_KW);
+}
+
+fn param_list(p: &mut Parser) {
+    let m = p.start();
+    if p.expect(L_PAREN) {
+        if !p.at(R_PAREN) {
+            loop {
+                match p.current() {
+                    IDENT | GOTO_KW => name(p),
+                    DOLLAR if p.placeholders() => name(p),
+                    DOTDOTDOT => {
+                        // The vararg parameter must be the last one
+                        p.bump();
+                        break;
+                    }
+                    // test_err param_list_recovery
+                    // function f(a, 1) end
+                    _ => {
+                        p.err_recover(
+                            ParseError::expected(token_set![IDENT, DOTDOTDOT]),
+                            token_set![R_PAREN, END_KW],
+                        );
+                        break;
+                    }
+                }
+                if !p.eat(COMMA) {
+                    break;
+                }
+            }
+        }
+        p.expect(R_PAREN);
+    }
+    m.complete(p, PARAM_LIST);
+}
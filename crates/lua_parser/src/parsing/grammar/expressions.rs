@@ -0,0 +1,310 @@
+use super::*;
+use crate::precedence::{binary_binding_power, unary_binding_power};
+use smallvec::SmallVec;
+
+/// Tokens at which parsing continues after a missing expression, instead of consuming them into
+/// an error node.
+const EXPR_RECOVERY_SET: TokenSet = token_set![
+    IF_KW, WHILE_KW, DO_KW, FOR_KW, REPEAT_KW, LOCAL_KW, RETURN_KW, BREAK_KW, GOTO_KW, END_KW,
+    ELSE_KW, ELSEIF_KW, UNTIL_KW, THEN_KW, R_PAREN, R_CURLY, R_BRACKET, COMMA, SEMI, EQ
+];
+
+/// Tokens that can start an expression.
+const EXPR_FIRST: TokenSet = token_set![
+    NIL_KW,
+    TRUE_KW,
+    FALSE_KW,
+    INT_NUMBER,
+    FLOAT_NUMBER,
+    STRING,
+    DOTDOTDOT,
+    L_CURLY,
+    FUNCTION_KW,
+    IDENT,
+    L_PAREN,
+    NOT_KW,
+    MINUS,
+    HASH,
+    TILDE
+];
+
+/// Parses an expression if one starts at the current token. Returns `None` without consuming any
+/// tokens or emitting errors otherwise.
+pub(super) fn expr(p: &mut Parser) -> Option<CompletedMarker> {
+    expr_bp(p, 0)
+}
+
+/// Parses an expression or emits an error if there is none.
+pub(super) fn expect_expr(p: &mut Parser) -> Option<CompletedMarker> {
+    let expr = expr(p);
+    if expr.is_none() {
+        p.err_recover(ParseError::new("expected an expression"), EXPR_RECOVERY_SET);
+    }
+    expr
+}
+
+/// Parses a comma separated list of expressions.
+pub(super) fn expr_list(p: &mut Parser) {
+    let m = p.start();
+    expect_expr(p);
+    while p.eat(COMMA) {
+        expect_expr(p);
+    }
+    m.complete(p, EXPR_LIST);
+}
+
+/// An operator whose operand on the right-hand side is being parsed, see `expr_bp`.
+struct PendingOp {
+    marker: Marker,
+    kind: SyntaxKind,
+    /// The binding power limit of the expression the operator is part of.
+    limit: u8,
+}
+
+/// Parses an expression whose binary operators bind tighter than `limit`.
+fn expr_bp(p: &mut Parser, limit: u8) -> Option<CompletedMarker> {
+    if !p.at_ts(EXPR_FIRST) && !at_placeholder(p) && !at_goto_name(p) {
+        return None;
+    }
+    if !p.enter() {
+        return Some(p.err_too_deep(EXPR_RECOVERY_SET));
+    }
+    let expr = operator_expr(p, limit);
+    p.leave();
+    Some(expr)
+}
+
+/// Parses a sequence of operands and unary and binary operators. The operators whose right-hand
+/// side is being parsed are kept on an explicit stack instead of recursing, so long chains like
+/// `a .. b .. c` or `- - - x` don't grow the native stack.
+fn operator_expr(p: &mut Parser, limit: u8) -> CompletedMarker {
+    // test operator_precedence
+    // x = -a ^ 2 .. b .. c + d * e or not f and g
+    // y = a < b == c ~ d | e & f << g
+    let mut pending: SmallVec<[PendingOp; 8]> = SmallVec::new();
+    let mut limit = limit;
+    'operand: loop {
+        // Only `^` binds tighter than unary operators, e.g. `-x^2` is `-(x^2)`.
+        while let Some(unary_bp) = unary_binding_power(p.current()) {
+            let marker = p.start();
+            p.bump();
+            pending.push(PendingOp {
+                marker,
+                kind: UNARY_EXPR,
+                limit,
+            });
+            limit = unary_bp;
+        }
+
+        let mut lhs = simple_expr(p);
+        if lhs.is_none() {
+            // `expr_bp` only calls this at the start of an expression, so an operand can only be
+            // missing after an operator.
+            assert!(!pending.is_empty());
+            p.err_recover(ParseError::new("expected an expression"), EXPR_RECOVERY_SET);
+        }
+
+        loop {
+            // test_err typo_operators
+            // if a != b && c || d === e then end
+            let op = p.current().intended_operator().unwrap_or(p.current());
+            if let Some((left_bp, right_bp)) = binary_binding_power(op) {
+                if left_bp > limit {
+                    if let Some(lhs) = lhs {
+                        let marker = lhs.precede(p);
+                        if op != p.current() {
+                            err_typo(p, op);
+                        }
+                        p.bump();
+                        pending.push(PendingOp {
+                            marker,
+                            kind: BIN_EXPR,
+                            limit,
+                        });
+                        limit = right_bp;
+                        continue 'operand;
+                    }
+                }
+            }
+            match pending.pop() {
+                Some(op) => {
+                    lhs = Some(op.marker.complete(p, op.kind));
+                    limit = op.limit;
+                }
+                None => return lhs.unwrap(),
+            }
+        }
+    }
+}
+
+/// Reports that the current token is an operator of another language and `intended` should be
+/// used instead, e.g. "'!=' is not a Lua operator, expected '~='".
+fn err_typo(p: &mut Parser, intended: SyntaxKind) {
+    let mut error = ParseError::expected(TokenSet::singleton(intended));
+    error.message = format!(
+        "'{}' is not a Lua operator",
+        p.current().token_text().unwrap_or_default()
+    );
+    p.error(error);
+}
+
+fn simple_expr(p: &mut Parser) -> Option<CompletedMarker> {
+    let cm = match p.current() {
+        NIL_KW | TRUE_KW | FALSE_KW | INT_NUMBER | FLOAT_NUMBER | STRING | DOTDOTDOT => {
+            let m = p.start();
+            p.bump();
+            m.complete(p, LITERAL)
+        }
+        L_CURLY => table_expr(p),
+        FUNCTION_KW => {
+            let m = p.start();
+            p.bump();
+            function_body(p);
+            m.complete(p, FUNCTION_EXPR)
+        }
+        _ => return suffixed_expr(p),
+    };
+    Some(cm)
+}
+
+/// Parses a name or a parenthesized expression.
+fn primary_expr(p: &mut Parser) -> Option<CompletedMarker> {
+    let cm = match p.current() {
+        IDENT => {
+            let m = p.start();
+            p.bump();
+            m.complete(p, NAME_REF)
+        }
+        GOTO_KW if at_goto_name(p) => {
+            let m = p.start();
+            p.bump_remap(IDENT);
+            m.complete(p, NAME_REF)
+        }
+        L_PAREN => {
+            let m = p.start();
+            p.bump();
+            expect_expr(p);
+            p.expect(R_PAREN);
+            m.complete(p, PAREN_EXPR)
+        }
+        DOLLAR if p.placeholders() => placeholder(p),
+        _ => return None,
+    };
+    Some(cm)
+}
+
+/// Parses a primary expression followed by any number of field accesses, index operations and
+/// calls, e.g. `a.b[c]:d(e)`.
+pub(super) fn suffixed_expr(p: &mut Parser) -> Option<CompletedMarker> {
+    // test suffixed_expressions
+    // a.b[c]:d(e) "s" {f}
+    // (g)(h).i = j
+    let mut lhs = primary_expr(p)?;
+    loop {
+        lhs = match p.current() {
+            DOT => {
+                let m = lhs.precede(p);
+                p.bump();
+                expect_ident(p);
+                m.complete(p, FIELD_EXPR)
+            }
+            L_BRACKET => {
+                let m = lhs.precede(p);
+                p.bump();
+                expect_expr(p);
+                p.expect(R_BRACKET);
+                m.complete(p, INDEX_EXPR)
+            }
+            COLON => {
+                let m = lhs.precede(p);
+                p.bump();
+                expect_ident(p);
+                arg_list(p);
+                m.complete(p, METHOD_CALL_EXPR)
+            }
+            L_PAREN | L_CURLY | STRING => {
+                let m = lhs.precede(p);
+                arg_list(p);
+                m.complete(p, CALL_EXPR)
+            }
+            _ => return Some(lhs),
+        };
+    }
+}
+
+/// Parses the arguments of a call: a parenthesized list of expressions, a table or a string.
+fn arg_list(p: &mut Parser) {
+    let m = p.start();
+    match p.current() {
+        L_PAREN => {
+            p.bump();
+            if !p.at(R_PAREN) {
+                expect_expr(p);
+                while p.eat(COMMA) {
+                    expect_expr(p);
+                }
+            }
+            p.expect(R_PAREN);
+        }
+        L_CURLY => {
+            table_expr(p);
+        }
+        STRING => {
+            let literal = p.start();
+            p.bump();
+            literal.complete(p, LITERAL);
+        }
+        _ => p.error(ParseError::expected(token_set![L_PAREN, L_CURLY, STRING])),
+    }
+    m.complete(p, ARG_LIST);
+}
+
+fn table_expr(p: &mut Parser) -> CompletedMarker {
+    // test table_fields
+    // t = { 1, x = 2; [y] = 3, }
+
+    // test_err table_missing_separator
+    // t = { 1 2, x = 3 }
+    assert!(p.at(L_CURLY));
+    let m = p.start();
+    p.bump();
+    while !p.at(R_CURLY) && !p.at(EOF) {
+        table_field(p);
+        if !p.at(R_CURLY) && !p.eat(COMMA) && !p.eat(SEMI) {
+            p.error(ParseError::expected(token_set![COMMA, SEMI, R_CURLY]));
+            if !p.at_ts(EXPR_FIRST.union(token_set![L_BRACKET])) {
+                return m.complete(p, TABLE_EXPR);
+            }
+        }
+    }
+    p.expect(R_CURLY);
+    m.complete(p, TABLE_EXPR)
+}
+
+fn table_field(p: &mut Parser) {
+    let m = p.start();
+    match p.current() {
+        L_BRACKET => {
+            p.bump();
+            expect_expr(p);
+            p.expect(R_BRACKET);
+            p.expect(EQ);
+            expect_expr(p);
+            m.complete(p, INDEXED_FIELD);
+        }
+        IDENT | GOTO_KW if p.nth(1) == EQ => {
+            expect_ident(p);
+            p.bump();
+            expect_expr(p);
+            m.complete(p, NAMED_FIELD);
+        }
+        _ => {
+            if expr(p).is_some() {
+                m.complete(p, POSITIONAL_FIELD);
+            } else {
+                m.abandon(p);
+                p.err_recover(ParseError::new("expected a table field"), EXPR_RECOVERY_SET);
+            }
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use super::*;
+
+/// Tokens that can follow `goto` at the start of a statement if it is used as a name, e.g.
+/// `goto = 1` or `goto.f()`. Before other tokens it starts a `goto` statement, which keeps an
+/// unfinished `goto` a statement while it is typed.
+const GOTO_NAME_FOLLOW: TokenSet =
+    token_set![EQ, COMMA, DOT, L_BRACKET, COLON, L_PAREN, L_CURLY, STRING];
+
+pub(super) fn statement(p: &mut Parser) {
+    match p.current() {
+        // Empty statement
+        SEMI => p.bump(),
+        IF_KW => if_stmt(p),
+        WHILE_KW => while_stmt(p),
+        DO_KW => do_stmt(p),
+        FOR_KW => for_stmt(p),
+        REPEAT_KW => repeat_stmt(p),
+        FUNCTION_KW => function_stmt(p),
+        LOCAL_KW => local_stmt(p),
+        COLONCOLON => label_stmt(p),
+        RETURN_KW => return_stmt(p),
+        BREAK_KW => {
+            let m = p.start();
+            p.bump();
+            m.complete(p, BREAK_STMT);
+        }
+        // test goto_as_name
+        // goto = 1
+        // local t = { goto = goto }
+        // t.goto(goto)
+        GOTO_KW if !GOTO_NAME_FOLLOW.contains(p.nth(1)) => {
+            let m = p.start();
+            p.bump();
+            p.expect(IDENT);
+            m.complete(p, GOTO_STMT);
+        }
+        _ => expr_or_assign_stmt(p),
+    }
+}
+
+fn if_stmt(p: &mut Parser) {
+    assert!(p.at(IF_KW));
+    let m = p.start();
+    p.bump();
+    expressions::expect_expr(p);
+    p.expect(THEN_KW);
+    block(p);
+    while p.at(ELSEIF_KW) {
+        let m = p.start();
+        p.bump();
+        expressions::expect_expr(p);
+        p.expect(THEN_KW);
+        block(p);
+        m.complete(p, ELSE_IF_CLAUSE);
+    }
+    if p.at(ELSE_KW) {
+        let m = p.start();
+        p.bump();
+        block(p);
+        m.complete(p, ELSE_CLAUSE);
+    }
+    p.expect(END_KW);
+    m.complete(p, IF_STMT);
+}
+
+fn while_stmt(p: &mut Parser) {
+    assert!(p.at(WHILE_KW));
+    let m = p.start();
+    p.bump();
+    expressions::expect_expr(p);
+    p.expect(DO_KW);
+    block(p);
+    p.expect(END_KW);
+    m.complete(p, WHILE_STMT);
+}
+
+fn do_stmt(p: &mut Parser) {
+    assert!(p.at(DO_KW));
+    let m = p.start();
+    p.bump();
+    block(p);
+    p.expect(END_KW);
+    m.complete(p, DO_STMT);
+}
+
+fn repeat_stmt(p: &mut Parser) {
+    assert!(p.at(REPEAT_KW));
+    let m = p.start();
+    p.bump();
+    block(p);
+    p.expect(UNTIL_KW);
+    expressions::expect_expr(p);
+    m.complete(p, REPEAT_STMT);
+}
+
+/// Parses a numeric `for i = 1, 10 do end` or a generic `for k, v in pairs(t) do end` loop.
+fn for_stmt(p: &mut Parser) {
+    assert!(p.at(FOR_KW));
+    let m = p.start();
+    p.bump();
+    // test numeric_for
+    // for i = 1, 10, 2 do end
+    // for i = n, 1 do f(i) end
+
+    // test generic_for
+    // for k, v in pairs(t) do end
+    // for line in io.lines(path), 1 do end
+    let kind = if (p.at(IDENT) || p.at(GOTO_KW)) && p.nth(1) == EQ {
+        name(p);
+        p.bump();
+        expressions::expect_expr(p);
+        p.expect(COMMA);
+        expressions::expect_expr(p);
+        if p.eat(COMMA) {
+            expressions::expect_expr(p);
+        }
+        NUMERIC_FOR_STMT
+    } else {
+        name_list(p);
+        p.expect(IN_KW);
+        expressions::expr_list(p);
+        GENERIC_FOR_STMT
+    };
+    p.expect(DO_KW);
+    block(p);
+    p.expect(END_KW);
+    m.complete(p, kind);
+}
+
+fn function_stmt(p: &mut Parser) {
+    assert!(p.at(FUNCTION_KW));
+    let m = p.start();
+    p.bump();
+    function_name(p);
+    function_body(p);
+    m.complete(p, FUNCTION_STMT);
+}
+
+/// Parses the name of a function statement, e.g. `a.b.c:m`.
+fn function_name(p: &mut Parser) {
+    let m = p.start();
+    if at_placeholder(p) {
+        placeholder(p);
+    } else if p.at(IDENT) || p.at(GOTO_KW) {
+        let name_ref = p.start();
+        expect_ident(p);
+        name_ref.complete(p, NAME_REF);
+        while p.eat(DOT) {
+            expect_ident(p);
+        }
+        if p.eat(COLON) {
+            expect_ident(p);
+        }
+    } else {
+        p.error(ParseError::expected(token_set![IDENT]));
+    }
+    m.complete(p, FUNCTION_NAME);
+}
+
+fn local_stmt(p: &mut Parser) {
+    assert!(p.at(LOCAL_KW));
+    let m = p.start();
+    p.bump();
+    // test local_function
+    // local function f(a, ...) return a end
+    if p.eat(FUNCTION_KW) {
+        name(p);
+        function_body(p);
+        m.complete(p, LOCAL_FUNCTION_STMT);
+        return;
+    }
+
+    name_list(p);
+    if p.eat(EQ) {
+        expressions::expr_list(p);
+    }
+    m.complete(p, LOCAL_ASSIGN_STMT);
+}
+
+/// Parses the names of a `local` statement or a generic `for` loop. Names can carry an attribute
+/// like `<const>`, which is only valid in `local` statements.
+fn name_list(p: &mut Parser) {
+    let m = p.start();
+    loop {
+        name(p);
+        if p.at(LT) {
+            attribute(p);
+        }
+        if !p.eat(COMMA) {
+            break;
+        }
+    }
+    m.complete(p, NAME_LIST);
+}
+
+fn attribute(p: &mut Parser) {
+    // test local_attributes
+    // local x <const>, y <close> = 1, f()
+    assert!(p.at(LT));
+    let m = p.start();
+    p.bump();
+    p.expect(IDENT);
+    p.expect(GT);
+    m.complete(p, ATTRIBUTE);
+}
+
+fn label_stmt(p: &mut Parser) {
+    // test goto_and_label
+    // goto done
+    // ::done::
+    assert!(p.at(COLONCOLON));
+    let m = p.start();
+    p.bump();
+    name(p);
+    p.expect(COLONCOLON);
+    m.complete(p, LABEL_STMT);
+}
+
+fn return_stmt(p: &mut Parser) {
+    assert!(p.at(RETURN_KW));
+    let m = p.start();
+    p.bump();
+    if !p.at_ts(BLOCK_END) && !p.at(SEMI) {
+        expressions::expr_list(p);
+    }
+    p.eat(SEMI);
+    m.complete(p, RETURN_STMT);
+}
+
+/// Parses a statement that starts with an expression: either a function call or an assignment.
+fn expr_or_assign_stmt(p: &mut Parser) {
+    let m = p.start();
+    let target = match expressions::suffixed_expr(p) {
+        Some(target) => target,
+        None => {
+            m.abandon(p);
+            p.err_and_bump(ParseError::new("expected a statement"));
+            return;
+        }
+    };
+
+    // test_err increment_statement
+    // i++
+    if p.at(PLUSPLUS) {
+        let mut error = ParseError::expected(TokenSet::singleton(EQ));
+        error.message = "Lua has no increment operator".to_string();
+        p.err_and_bump(error);
+        m.complete(p, EXPR_STMT);
+        return;
+    }
+
+    // test_err expression_statement
+    // x
+    // a.b + 1
+    if !p.at(EQ) && !p.at(COMMA) {
+        // A placeholder can stand for a whole statement
+        if !matches!(target.kind(), CALL_EXPR | METHOD_CALL_EXPR | PLACEHOLDER) {
+            p.error(ParseError::new("expected an assignment or a function call"));
+        }
+        m.complete(p, EXPR_STMT);
+        return;
+    }
+
+    check_assign_target(p, &target);
+    let targets = target.precede(p);
+    while p.eat(COMMA) {
+        match expressions::suffixed_expr(p) {
+            Some(target) => check_assign_target(p, &target),
+            None => p.error(ParseError::expected(token_set![IDENT, L_PAREN])),
+        }
+    }
+    targets.complete(p, EXPR_LIST);
+    p.expect(EQ);
+    expressions::expr_list(p);
+    m.complete(p, ASSIGN_STMT);
+}
+
+fn check_assign_target(p: &mut Parser, target: &CompletedMarker) {
+    // test_err invalid_assign_target
+    // f() = 1
+    // a, (b) = 1, 2
+    match target.kind() {
+        NAME_REF | INDEX_EXPR | FIELD_EXPR | PLACEHOLDER => (),
+        _ => p.error(ParseError::new("cannot assign to this expression")),
+    }
+}
@@ -0,0 +1,306 @@
+use crate::{
+    blocks::BlockTracker,
+    parsing::{event::Event, ParseConfig, TokenSource},
+    ParseError,
+    SyntaxKind::{self, *},
+    TokenSet,
+};
+use drop_bomb::DropBomb;
+use std::cell::Cell;
+
+/// The `Parser` struct provides the low-level API for navigating through the stream of tokens and
+/// constructing the parse tree. The actual parsing happens in the `grammar` module.
+///
+/// The result of parsing is a stream of events. Nodes are started and finished through `Marker`s.
+pub(crate) struct Parser<'t> {
+    token_source: &'t dyn TokenSource,
+    token_pos: usize,
+    events: Vec<Event>,
+    steps: Cell<u32>,
+    /// The number of nested expressions and blocks that are currently being parsed.
+    depth: u32,
+    recursion_limit: u32,
+    placeholders: bool,
+}
+
+impl<'t> Parser<'t> {
+    pub(super) fn new(token_source: &'t dyn TokenSource, config: &ParseConfig) -> Parser<'t> {
+        Parser {
+            token_source,
+            token_pos: 0,
+            // Typical code has about three events per token: the token itself and the start and
+            // finish of a node for most tokens
+            events: Vec::with_capacity(token_source.token_count_hint() * 3),
+            steps: Cell::new(0),
+            depth: 0,
+            recursion_limit: config.recursion_limit,
+            placeholders: config.placeholders,
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<Event> {
+        self.events
+    }
+
+    /// Returns the kind of the current token. If the parser has already reached the end of the
+    /// input, `EOF` is returned.
+    pub(crate) fn current(&self) -> SyntaxKind {
+        self.nth(0)
+    }
+
+    /// Lookahead operation: returns the kind of the token `n` tokens ahead of the current one.
+    pub(crate) fn nth(&self, n: usize) -> SyntaxKind {
+        let steps = self.steps.get();
+        assert!(steps <= 10_000_000, "the parser seems stuck");
+        self.steps.set(steps + 1);
+
+        let mut pos = self.token_pos;
+        for _ in 0..n {
+            pos += self.composite_at(pos).1 as usize;
+        }
+        self.composite_at(pos).0
+    }
+
+    /// Returns true if placeholders like `$name` are parsed, see `ParseConfig::placeholders`.
+    pub(crate) fn placeholders(&self) -> bool {
+        self.placeholders
+    }
+
+    /// Checks if the current token is `kind`.
+    pub(crate) fn at(&self, kind: SyntaxKind) -> bool {
+        self.current() == kind
+    }
+
+    /// Checks if the current token is in `kinds`.
+    pub(crate) fn at_ts(&self, kinds: TokenSet) -> bool {
+        kinds.contains(self.current())
+    }
+
+    /// Starts a new node in the syntax tree. All nodes and tokens consumed between the `start`
+    /// and the corresponding `Marker::complete` belong to the same node.
+    pub(crate) fn start(&mut self) -> Marker {
+        let pos = self.events.len() as u32;
+        self.push_event(Event::tombstone());
+        Marker::new(pos)
+    }
+
+    /// Advances the parser by one token, unless the end of the input has been reached.
+    pub(crate) fn bump(&mut self) {
+        let (kind, n_raw_tokens) = self.composite_at(self.token_pos);
+        if kind == EOF {
+            return;
+        }
+        self.token_pos += n_raw_tokens as usize;
+        self.steps.set(0);
+        self.push_event(Event::Token { kind, n_raw_tokens });
+    }
+
+    /// Advances the parser by one token like `bump`, but adds the token to the tree as `kind`,
+    /// e.g. a keyword that is used as a name.
+    pub(crate) fn bump_remap(&mut self, kind: SyntaxKind) {
+        let (current, n_raw_tokens) = self.composite_at(self.token_pos);
+        if current == EOF {
+            return;
+        }
+        self.token_pos += n_raw_tokens as usize;
+        self.steps.set(0);
+        self.push_event(Event::Token { kind, n_raw_tokens });
+    }
+
+    /// Consumes the next token if it is `kind`.
+    pub(crate) fn eat(&mut self, kind: SyntaxKind) -> bool {
+        if !self.at(kind) {
+            return false;
+        }
+        self.bump();
+        true
+    }
+
+    /// Consumes the next token if it is `kind` or emits an error otherwise.
+    pub(crate) fn expect(&mut self, kind: SyntaxKind) -> bool {
+        if self.eat(kind) {
+            return true;
+        }
+        self.error(ParseError::expected(TokenSet::singleton(kind)));
+        false
+    }
+
+    /// Emits an error at the current position.
+    pub(crate) fn error(&mut self, error: ParseError) {
+        self.push_event(Event::Error { error })
+    }
+
+    /// Emits an error and consumes the current token into an `ERROR` node.
+    pub(crate) fn err_and_bump(&mut self, error: ParseError) {
+        let m = self.start();
+        self.error(error);
+        self.bump();
+        m.complete(self, ERROR);
+    }
+
+    /// Emits an error and consumes the current token into an `ERROR` node, unless the current
+    /// token is in `recovery`.
+    pub(crate) fn err_recover(&mut self, error: ParseError, recovery: TokenSet) {
+        if self.at_ts(recovery) || self.at(EOF) {
+            self.error(error);
+        } else {
+            self.err_and_bump(error);
+        }
+    }
+
+    /// Enters a nested expression or block. Returns false if this would exceed the recursion
+    /// limit, in which case the caller must not parse the construct recursively but skip it with
+    /// `err_too_deep`. Every successful `enter` needs a matching `leave`.
+    pub(crate) fn enter(&mut self) -> bool {
+        if self.depth >= self.recursion_limit {
+            return false;
+        }
+        self.depth += 1;
+        true
+    }
+
+    pub(crate) fn leave(&mut self) {
+        assert!(self.depth > 0, "`leave` was called without a matching `enter`");
+        self.depth -= 1;
+    }
+
+    /// Emits an error about exceeding the recursion limit and consumes the construct at the
+    /// current position into an `ERROR` node without recursing. Tokens are consumed until one of
+    /// the tokens in `stop` is found outside of any brackets and blocks that were opened in the
+    /// skipped tokens, or until a bracket or block is closed that was opened before them.
+    pub(crate) fn err_too_deep(&mut self, stop: TokenSet) -> CompletedMarker {
+        let m = self.start();
+        self.error(ParseError::new(format!(
+            "the code is nested too deeply, the limit is {}",
+            self.recursion_limit
+        )));
+        let mut blocks = BlockTracker::default();
+        let mut brackets = 0usize;
+        let mut idx = 0;
+        loop {
+            let kind = self.current();
+            let nested = brackets > 0 || blocks.depth() > 0;
+            match kind {
+                EOF => break,
+                _ if !nested && stop.contains(kind) => break,
+                L_PAREN | L_BRACKET | L_CURLY => brackets += 1,
+                R_PAREN | R_BRACKET | R_CURLY if brackets == 0 => break,
+                R_PAREN | R_BRACKET | R_CURLY => brackets -= 1,
+                END_KW | UNTIL_KW if blocks.depth() == 0 => break,
+                _ => {
+                    blocks.visit(idx, kind);
+                }
+            }
+            self.bump();
+            idx += 1;
+        }
+        m.complete(self, ERROR)
+    }
+
+    fn push_event(&mut self, event: Event) {
+        self.events.push(event)
+    }
+
+    /// Returns the kind and the number of raw tokens of the token at `pos`. Operators that
+    /// consist of multiple characters are combined from joint single character tokens, e.g. two
+    /// joint `=` tokens form a `==`.
+    fn composite_at(&self, pos: usize) -> (SyntaxKind, u8) {
+        let source = self.token_source;
+        let kind = source.token_kind(pos);
+        let joint_with = |offset: usize, next: SyntaxKind| {
+            (0..offset).all(|idx| source.is_token_joint_to_next(pos + idx))
+                && source.token_kind(pos + offset) == next
+        };
+        match kind {
+            DOT if joint_with(1, DOT) && joint_with(2, DOT) => (DOTDOTDOT, 3),
+            DOT if joint_with(1, DOT) => (DOTDOT, 2),
+            EQ if joint_with(1, EQ) => (EQEQ, 2),
+            LT if joint_with(1, EQ) => (LTEQ, 2),
+            LT if joint_with(1, LT) => (SHL, 2),
+            GT if joint_with(1, EQ) => (GTEQ, 2),
+            GT if joint_with(1, GT) => (SHR, 2),
+            COLON if joint_with(1, COLON) => (COLONCOLON, 2),
+            SLASH if joint_with(1, SLASH) => (SLASHSLASH, 2),
+            // A number that starts with a dot, e.g. `.5`. The lexer can't tell it apart from the
+            // second dot of `a..5`.
+            DOT if joint_with(1, INT_NUMBER) || joint_with(1, FLOAT_NUMBER) => (FLOAT_NUMBER, 2),
+            _ => (kind, 1),
+        }
+    }
+}
+
+/// See `Parser::start`.
+pub(crate) struct Marker {
+    pos: u32,
+    bomb: DropBomb,
+}
+
+impl Marker {
+    fn new(pos: u32) -> Marker {
+        Marker {
+            pos,
+            bomb: DropBomb::new("Marker must be either completed or abandoned"),
+        }
+    }
+
+    /// Finishes the syntax tree node and assigns `kind` to it, and creates a `CompletedMarker`
+    /// for possible future operation like `.precede()` to deal with forward_parent.
+    pub(crate) fn complete(mut self, p: &mut Parser, kind: SyntaxKind) -> CompletedMarker {
+        self.bomb.defuse();
+        let idx = self.pos as usize;
+        match p.events.get_mut(idx) {
+            Some(Event::Start {
+                kind: ref mut slot, ..
+            }) => {
+                *slot = kind;
+            }
+            _ => unreachable!(),
+        }
+        p.push_event(Event::Finish);
+        CompletedMarker::new(self.pos, kind)
+    }
+
+    /// Abandons the syntax tree node. All its children are attached to its parent instead.
+    pub(crate) fn abandon(mut self, p: &mut Parser) {
+        self.bomb.defuse();
+        let idx = self.pos as usize;
+        if idx == p.events.len() - 1 {
+            match p.events.pop() {
+                Some(Event::Start {
+                    kind: TOMBSTONE,
+                    forward_parent: None,
+                }) => (),
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+pub(crate) struct CompletedMarker(u32, SyntaxKind);
+
+impl CompletedMarker {
+    fn new(pos: u32, kind: SyntaxKind) -> Self {
+        CompletedMarker(pos, kind)
+    }
+
+    /// Creates a new node that starts before this completed node and will contain it as its
+    /// first child, e.g. the `BIN_EXPR` of `a + b` after `a` has already been parsed.
+    pub(crate) fn precede(self, p: &mut Parser) -> Marker {
+        let new_pos = p.start();
+        let idx = self.0 as usize;
+        match p.events.get_mut(idx) {
+            Some(Event::Start {
+                ref mut forward_parent,
+                ..
+            }) => {
+                *forward_parent = Some(new_pos.pos - self.0);
+            }
+            _ => unreachable!(),
+        }
+        new_pos
+    }
+
+    pub(crate) fn kind(&self) -> SyntaxKind {
+        self.1
+    }
+}
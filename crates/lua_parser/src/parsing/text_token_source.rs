@@ -0,0 +1,48 @@
+use crate::{lexer::Token, parsing::TokenSource, SyntaxKind, SyntaxKind::EOF};
+
+/// A `TokenSource` over the tokens of a text, with trivia filtered out.
+pub(crate) struct TextTokenSource {
+    /// The kinds of the non-trivia tokens
+    kinds: Vec<SyntaxKind>,
+
+    /// For every non-trivia token, whether it is directly followed by the next one
+    joint_to_next: Vec<bool>,
+}
+
+impl TextTokenSource {
+    pub fn new(raw_tokens: &[Token]) -> TextTokenSource {
+        let mut kinds = Vec::with_capacity(raw_tokens.len());
+        let mut joint_to_next = Vec::with_capacity(raw_tokens.len());
+        let mut joint = false;
+        for token in raw_tokens.iter() {
+            if token.kind.is_trivia() {
+                joint = false;
+                continue;
+            }
+            if let Some(last) = joint_to_next.last_mut() {
+                *last = joint;
+            }
+            kinds.push(token.kind);
+            joint_to_next.push(false);
+            joint = true;
+        }
+        TextTokenSource {
+            kinds,
+            joint_to_next,
+        }
+    }
+}
+
+impl TokenSource for TextTokenSource {
+    fn token_kind(&self, pos: usize) -> SyntaxKind {
+        self.kinds.get(pos).cloned().unwrap_or(EOF)
+    }
+
+    fn is_token_joint_to_next(&self, pos: usize) -> bool {
+        self.joint_to_next.get(pos).cloned().unwrap_or(false)
+    }
+
+    fn token_count_hint(&self) -> usize {
+        self.kinds.len()
+    }
+}
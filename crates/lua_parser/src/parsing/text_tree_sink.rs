@@ -0,0 +1,129 @@
+use crate::{
+    lexer::Token, parsing::TreeSink, syntax_node::GreenNode, ParseError, SmolStr, SyntaxError,
+    SyntaxKind, SyntaxTreeBuilder, TextRange, TextSize,
+};
+use std::mem;
+
+/// Bridges the parser with the lexer output: builds a `SyntaxTreeBuilder` tree from the events of
+/// the parser and attaches the trivia that the parser never sees.
+pub(crate) struct TextTreeSink<'a> {
+    text: &'a str,
+    tokens: &'a [Token],
+    text_pos: TextSize,
+    token_pos: usize,
+    state: State,
+    /// True between the start of an `ATTRIBUTE` node and its name.
+    in_attribute: bool,
+    inner: SyntaxTreeBuilder,
+}
+
+enum State {
+    PendingStart,
+    Normal,
+    PendingFinish,
+}
+
+impl<'a> TreeSink for TextTreeSink<'a> {
+    fn token(&mut self, kind: SyntaxKind, n_tokens: u8) {
+        match mem::replace(&mut self.state, State::Normal) {
+            State::PendingStart => unreachable!(),
+            State::PendingFinish => self.inner.finish_node(),
+            State::Normal => (),
+        }
+        self.eat_trivias();
+        let n_tokens = n_tokens as usize;
+        let len = self
+            .tokens
+            .iter()
+            .skip(self.token_pos)
+            .take(n_tokens)
+            .map(|it| it.len)
+            .sum::<TextSize>();
+        if self.in_attribute && kind == SyntaxKind::IDENT {
+            self.in_attribute = false;
+            self.check_attribute_name(len);
+        }
+        self.do_token(kind, len, n_tokens);
+    }
+
+    fn start_node(&mut self, kind: SyntaxKind) {
+        match mem::replace(&mut self.state, State::Normal) {
+            State::PendingStart => {
+                // The root node also contains the leading trivia of the text
+                self.inner.start_node(kind);
+                return;
+            }
+            State::PendingFinish => self.inner.finish_node(),
+            State::Normal => (),
+        }
+        self.eat_trivias();
+        self.in_attribute = kind == SyntaxKind::ATTRIBUTE;
+        self.inner.start_node(kind);
+    }
+
+    fn finish_node(&mut self) {
+        self.in_attribute = false;
+        match mem::replace(&mut self.state, State::PendingFinish) {
+            State::PendingStart => unreachable!(),
+            State::PendingFinish => self.inner.finish_node(),
+            State::Normal => (),
+        }
+    }
+
+    fn error(&mut self, error: ParseError) {
+        self.inner.error(error, self.text_pos)
+    }
+}
+
+impl<'a> TextTreeSink<'a> {
+    pub(super) fn new(text: &'a str, tokens: &'a [Token]) -> TextTreeSink<'a> {
+        TextTreeSink {
+            text,
+            tokens,
+            text_pos: 0.into(),
+            token_pos: 0,
+            state: State::PendingStart,
+            in_attribute: false,
+            inner: SyntaxTreeBuilder::default(),
+        }
+    }
+
+    pub(super) fn finish(mut self) -> (GreenNode, Vec<SyntaxError>) {
+        match mem::replace(&mut self.state, State::Normal) {
+            State::PendingFinish => {
+                // The root node also contains the trailing trivia of the text
+                self.eat_trivias();
+                self.inner.finish_node()
+            }
+            State::PendingStart | State::Normal => unreachable!(),
+        }
+        self.inner.finish_raw()
+    }
+
+    /// Reports an error for an attribute other than `<const>` and `<close>`, e.g. `<foo>`. The
+    /// parser only sees token kinds, so the name is checked here.
+    fn check_attribute_name(&mut self, len: TextSize) {
+        let name = &self.text[TextRange::at(self.text_pos, len)];
+        if name != "const" && name != "close" {
+            let message = format!("unknown attribute '{}', expected 'const' or 'close'", name);
+            self.inner.error(ParseError::new(message), self.text_pos);
+        }
+    }
+
+    fn eat_trivias(&mut self) {
+        while let Some(&token) = self.tokens.get(self.token_pos) {
+            if !token.kind.is_trivia() {
+                break;
+            }
+            self.do_token(token.kind, token.len, 1);
+        }
+    }
+
+    fn do_token(&mut self, kind: SyntaxKind, len: TextSize, n_tokens: usize) {
+        let range = TextRange::at(self.text_pos, len);
+        let text: SmolStr = self.text[range].into();
+        self.text_pos += len;
+        self.token_pos += n_tokens;
+        self.inner.token(kind, text);
+    }
+}
@@ -0,0 +1,335 @@
+//! Validation of the Lua patterns passed as string literals to the pattern matching functions of
+//! the `string` library, e.g. `string.match(s, "%d+")` or `s:gsub("(%w+", f)`.
+
+use crate::{
+    ast::{CallExpr, Expr, MethodCallExpr, StringLit},
+    AstNode, AstToken, Chunk, Diagnostic, DiagnosticCode,
+    SyntaxKind::*,
+    SyntaxNode, TextRange, TextSize,
+};
+
+pub const MALFORMED_PATTERN: DiagnosticCode = DiagnosticCode("E0004");
+pub const UNKNOWN_PATTERN_CLASS: DiagnosticCode = DiagnosticCode("W0003");
+
+/// The functions of the `string` library that take a pattern as their second argument, or first
+/// when called as a method.
+const PATTERN_FUNCTIONS: &[&str] = &["find", "match", "gmatch", "gsub"];
+
+/// The letters of the character classes, e.g. `%d`. Their uppercase variants are the complements.
+const CLASSES: &str = "acdglpsuwx";
+
+/// Reports malformed patterns in string literals passed to `string.find`, `string.match`,
+/// `string.gmatch` and `string.gsub`, or to the same functions called as a method, e.g.
+/// `s:match("[a-z")`. The diagnostics point at the offending part of the string contents.
+///
+/// Method calls are checked regardless of the type of their receiver, and calls to `find` with a
+/// literal `true` for `plain` are skipped.
+pub fn check_patterns(chunk: &Chunk) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for node in chunk.syntax().descendants() {
+        if let Some(pattern) = pattern_argument(node) {
+            diagnostics.extend(check_pattern(&decode(&pattern)));
+        }
+    }
+    diagnostics
+}
+
+/// Returns the string literal passed as the pattern if `node` calls a pattern matching function.
+fn pattern_argument(node: SyntaxNode) -> Option<StringLit> {
+    let (name, args, pattern_idx) = if let Some(call) = CallExpr::cast(node.clone()) {
+        let callee = match call.callee()? {
+            Expr::FieldExpr(callee) => callee,
+            _ => return None,
+        };
+        match callee.base()? {
+            Expr::NameRef(base) if base.syntax().text() == "string" => {}
+            _ => return None,
+        }
+        (last_ident(callee.syntax())?, call.arg_list()?, 1)
+    } else {
+        let call = MethodCallExpr::cast(node)?;
+        (last_ident(call.syntax())?, call.arg_list()?, 0)
+    };
+    if !PATTERN_FUNCTIONS.contains(&name.as_str()) {
+        return None;
+    }
+
+    let args: Vec<Expr> = args.args().collect();
+    if name == "find" && args.get(pattern_idx + 2).is_some_and(is_true) {
+        return None;
+    }
+    match args.get(pattern_idx)? {
+        Expr::Literal(literal) => StringLit::cast(literal.syntax().first_token()?),
+        _ => None,
+    }
+}
+
+fn last_ident(node: &SyntaxNode) -> Option<String> {
+    node.children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == IDENT)
+        .last()
+        .map(|token| token.text().to_string())
+}
+
+fn is_true(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(literal) => literal
+            .syntax()
+            .first_token()
+            .is_some_and(|token| token.kind() == TRUE_KW),
+        _ => false,
+    }
+}
+
+/// Returns the characters of the value of a string literal with their range in the source. The
+/// characters of an escape sequence have the range of the whole sequence.
+fn decode(literal: &StringLit) -> Vec<(char, TextRange)> {
+    let text = literal.syntax().text();
+    let token_start = literal.syntax().text_range().start();
+    let content = literal.content_range();
+    let mut escapes = literal.escape_ranges().into_iter().peekable();
+    let mut result = Vec::new();
+    let mut offset = content.start();
+    while offset < content.end() {
+        if let Some(escape) = escapes.next_if(|escape| escape.start() == offset) {
+            let escaped = &text[escape - token_start];
+            if let Some(c) = escape_value(&escaped[1..]) {
+                result.push((c, escape));
+            }
+            offset = escape.end();
+            continue;
+        }
+        let c = text[usize::from(offset - token_start)..]
+            .chars()
+            .next()
+            .unwrap();
+        let range = TextRange::at(offset, TextSize::of(c));
+        result.push((c, range));
+        offset = range.end();
+    }
+    result
+}
+
+/// Returns the character an escape sequence stands for, `escape` is the text after the backslash.
+/// Characters that are not valid Unicode are replaced by `char::REPLACEMENT_CHARACTER`, which
+/// has no meaning in patterns.
+fn escape_value(escape: &str) -> Option<char> {
+    let code = |digits: &str, radix| {
+        u32::from_str_radix(digits, radix)
+            .ok()
+            .and_then(char::from_u32)
+            .unwrap_or(char::REPLACEMENT_CHARACTER)
+    };
+    let c = match escape.chars().next()? {
+        'a' => '\u{7}',
+        'b' => '\u{8}',
+        'f' => '\u{c}',
+        'n' | '\n' | '\r' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'v' => '\u{b}',
+        'z' => return None,
+        'x' => code(&escape[1..], 16),
+        'u' => code(escape[1..].trim_matches(['{', '}']), 16),
+        '0'..='9' => code(escape, 10),
+        c => c,
+    };
+    Some(c)
+}
+
+/// Checks a pattern like `lstrlib.c` does when it is matched.
+fn check_pattern(pattern: &[(char, TextRange)]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    // The open captures and whether each capture has been closed
+    let mut open = Vec::new();
+    let mut captures: Vec<bool> = Vec::new();
+    let mut idx = usize::from(pattern.first().map(|&(c, _)| c) == Some('^'));
+    while idx < pattern.len() {
+        let (c, range) = pattern[idx];
+        idx += 1;
+        match c {
+            '(' => {
+                open.push((captures.len(), range));
+                captures.push(false);
+            }
+            ')' => match open.pop() {
+                Some((capture, _)) => captures[capture] = true,
+                None => diagnostics.push(malformed(range, "invalid pattern capture")),
+            },
+            '[' => match set_end(pattern, idx) {
+                Some(end) => {
+                    diagnostics.extend(check_set_classes(&pattern[idx..end]));
+                    idx = end + 1;
+                }
+                None => diagnostics.push(malformed(
+                    span(range, pattern),
+                    "malformed pattern (missing `]`)",
+                )),
+            },
+            '%' => {
+                let (class, class_range) = match pattern.get(idx) {
+                    Some(&class) => class,
+                    None => {
+                        diagnostics.push(malformed(range, "malformed pattern (ends with `%`)"));
+                        break;
+                    }
+                };
+                idx += 1;
+                let item_range = range.cover(class_range);
+                match class {
+                    'b' if pattern.len() - idx < 2 => {
+                        diagnostics.push(malformed(item_range, "missing arguments to `%b`"));
+                        break;
+                    }
+                    'b' => idx += 2,
+                    'f' if pattern.get(idx).map(|&(c, _)| c) != Some('[') => {
+                        diagnostics.push(malformed(item_range, "missing `[` after `%f` in pattern"))
+                    }
+                    'f' => {}
+                    '0'..='9' => {
+                        let closed = (class as usize)
+                            .checked_sub('1' as usize)
+                            .and_then(|capture| captures.get(capture))
+                            .copied()
+                            .unwrap_or(false);
+                        if !closed {
+                            diagnostics.push(malformed(
+                                item_range,
+                                format!("invalid capture index `%{}`", class),
+                            ));
+                        }
+                    }
+                    c => diagnostics.extend(check_class(c, item_range)),
+                }
+            }
+            _ => {}
+        }
+    }
+    for (_, range) in open {
+        diagnostics.push(malformed(range, "unfinished capture"));
+    }
+    diagnostics
+}
+
+/// Returns the index of the `]` that closes the set whose contents start at `start`. A `]` right
+/// after the `[` or `[^` is part of the set.
+fn set_end(pattern: &[(char, TextRange)], start: usize) -> Option<usize> {
+    let mut idx = start;
+    if pattern.get(idx).map(|&(c, _)| c) == Some('^') {
+        idx += 1;
+    }
+    loop {
+        let (c, _) = *pattern.get(idx)?;
+        idx += 1;
+        if c == '%' {
+            idx += 1;
+        }
+        if pattern.get(idx).map(|&(c, _)| c) == Some(']') {
+            return Some(idx);
+        }
+    }
+}
+
+fn check_set_classes(set: &[(char, TextRange)]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut items = set.iter();
+    while let Some(&(c, range)) = items.next() {
+        if c != '%' {
+            continue;
+        }
+        if let Some(&(class, class_range)) = items.next() {
+            diagnostics.extend(check_class(class, range.cover(class_range)));
+        }
+    }
+    diagnostics
+}
+
+/// Warns about `%` followed by a letter that isn't a character class, which Lua matches as the
+/// letter itself although the manual leaves it undefined.
+fn check_class(class: char, range: TextRange) -> Option<Diagnostic> {
+    if !class.is_ascii_alphabetic() || CLASSES.contains(class.to_ascii_lowercase()) {
+        return None;
+    }
+    Some(Diagnostic::warning(
+        UNKNOWN_PATTERN_CLASS,
+        range,
+        format!("unknown character class `%{}`", class),
+    ))
+}
+
+fn malformed(range: TextRange, message: impl Into<String>) -> Diagnostic {
+    Diagnostic::error(MALFORMED_PATTERN, range, message)
+}
+
+/// Returns the range from `start` to the end of the pattern.
+fn span(start: TextRange, pattern: &[(char, TextRange)]) -> TextRange {
+    pattern.last().map_or(start, |&(_, last)| start.cover(last))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(text: &str) -> Vec<(String, &str)> {
+        check_patterns(&Chunk::parse(text).tree())
+            .into_iter()
+            .map(|diagnostic| (diagnostic.message, &text[diagnostic.range]))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_patterns() {
+        let text = r#"
+            string.match(s, "^(%d+)%.(%d*)$")
+            s:gsub("%b()", "")
+            s:find("[]%]a-z]")
+            s:gmatch("%f[%w]%w+")
+            s:match("(a)%1")
+            s:find("(", 1, true)
+            string.format("(%d")
+            t:match(pattern)
+        "#;
+        assert_eq!(check(text), vec![]);
+    }
+
+    #[test]
+    fn test_malformed_patterns() {
+        let text = r#"
+            string.match(s, "%d%")
+            s:match("[a-z")
+            s:find("(%w+")
+            s:gsub("a)", "")
+            s:gmatch("%b(")
+            s:match("%fa")
+            s:match("(a%1)%2%0")
+            s:match("%q[%y]")
+        "#;
+        assert_eq!(
+            check(text),
+            vec![
+                ("malformed pattern (ends with `%`)".to_string(), "%"),
+                ("malformed pattern (missing `]`)".to_string(), "[a-z"),
+                ("unfinished capture".to_string(), "("),
+                ("invalid pattern capture".to_string(), ")"),
+                ("missing arguments to `%b`".to_string(), "%b"),
+                ("missing `[` after `%f` in pattern".to_string(), "%f"),
+                ("invalid capture index `%1`".to_string(), "%1"),
+                ("invalid capture index `%2`".to_string(), "%2"),
+                ("invalid capture index `%0`".to_string(), "%0"),
+                ("unknown character class `%q`".to_string(), "%q"),
+                ("unknown character class `%y`".to_string(), "%y"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escaped_pattern() {
+        // `\37` is `%`, the range covers the escape sequence
+        let text = r#"s:match("a\37")"#;
+        assert_eq!(
+            check(text),
+            vec![("malformed pattern (ends with `%`)".to_string(), r"\37")]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! The precedence and associativity of the Lua operators, as declared in `grammar.ron`. The parser
+//! uses the same table, so formatters and code generators can use it to decide when parentheses
+//! are required.
+
+mod generated;
+
+pub use self::generated::{binary_operator, unary_operator, BinOp, UnOp};
+use crate::{
+    SyntaxKind::{self, *},
+    SyntaxNode, SyntaxToken,
+};
+use std::fmt;
+
+/// How tightly an operator binds its operands, a higher precedence binds tighter.
+pub type Precedence = u8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `a .. b .. c` is `a .. (b .. c)`
+    Right,
+}
+
+impl BinOp {
+    /// Returns the text of the operator, e.g. `..`.
+    pub fn text(self) -> &'static str {
+        self.kind()
+            .token_text()
+            .expect("operators are keywords or symbols")
+    }
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.text())
+    }
+}
+
+impl UnOp {
+    /// Returns the text of the operator, e.g. `not`.
+    pub fn text(self) -> &'static str {
+        self.kind()
+            .token_text()
+            .expect("operators are keywords or symbols")
+    }
+}
+
+impl fmt::Display for UnOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.text())
+    }
+}
+
+/// Returns the precedence of the operator of a binary or unary expression, or `None` for other
+/// expressions, which never need parentheses.
+pub fn expr_precedence(expr: &SyntaxNode) -> Option<Precedence> {
+    let op = operator(expr)?;
+    match expr.kind() {
+        BIN_EXPR => binary_operator(op.kind()).map(|(precedence, _)| precedence),
+        UNARY_EXPR => unary_operator(op.kind()),
+        _ => None,
+    }
+}
+
+/// Returns true if `expr` needs parentheses to keep its meaning when it takes the place of
+/// `slot`, an operand of a binary or unary expression. `slot` is `expr` itself or, to find
+/// redundant parentheses, a `PAREN_EXPR` that contains it.
+pub fn needs_parens(expr: &SyntaxNode, slot: &SyntaxNode) -> bool {
+    let inner = match expr_precedence(expr) {
+        Some(precedence) => precedence,
+        None => return false,
+    };
+    let parent = match slot.parent() {
+        Some(parent) => parent,
+        None => return false,
+    };
+    let op = match operator(&parent) {
+        Some(op) => op,
+        None => return false,
+    };
+    match parent.kind() {
+        UNARY_EXPR => unary_operator(op.kind()).is_some_and(|outer| inner < outer),
+        BIN_EXPR => {
+            let (outer, associativity) = match binary_operator(op.kind()) {
+                Some(op) => op,
+                None => return false,
+            };
+            let is_left = slot.text_range().end() <= op.text_range().start();
+            // A unary operator can't take the preceding operator as its operand, `a ^ -b` is
+            // `a ^ (-b)`.
+            if !is_left && expr.kind() == UNARY_EXPR {
+                return false;
+            }
+            let grouped_side = match associativity {
+                Associativity::Left => is_left,
+                Associativity::Right => !is_left,
+            };
+            inner < outer || (inner == outer && !grouped_side)
+        }
+        _ => false,
+    }
+}
+
+/// Returns the operator token of a binary or unary expression.
+fn operator(expr: &SyntaxNode) -> Option<SyntaxToken> {
+    if !matches!(expr.kind(), BIN_EXPR | UNARY_EXPR) {
+        return None;
+    }
+    expr.children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| !token.kind().is_trivia())
+}
+
+/// Returns the left and right binding power of a binary operator for the parser. Operators are
+/// right associative if their right binding power is lower than the left one.
+pub(crate) fn binary_binding_power(kind: SyntaxKind) -> Option<(u8, u8)> {
+    let (precedence, associativity) = binary_operator(kind)?;
+    let left = 2 * precedence;
+    match associativity {
+        Associativity::Left => Some((left, left)),
+        Associativity::Right => Some((left, left - 1)),
+    }
+}
+
+/// Returns the binding power of the operand of a unary operator for the parser.
+pub(crate) fn unary_binding_power(kind: SyntaxKind) -> Option<u8> {
+    unary_operator(kind).map(|precedence| 2 * precedence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AstNode, Chunk};
+
+    /// Checks whether the parentheses of the first parenthesized expression in `text` are needed.
+    fn check(text: &str, expected: bool) {
+        let parse = Chunk::parse(text);
+        let paren = parse
+            .syntax_node()
+            .descendants()
+            .find(|node| node.kind() == PAREN_EXPR)
+            .unwrap();
+        let expr = paren.first_child().unwrap();
+        assert_eq!(needs_parens(&expr, &paren), expected, "{}", text);
+    }
+
+    #[test]
+    fn test_needs_parens() {
+        check("x = (a + b) * c", true);
+        check("x = a + (b * c)", false);
+        check("x = a - (b - c)", true);
+        check("x = (a - b) - c", false);
+        check("x = (a .. b) .. c", true);
+        check("x = a .. (b .. c)", false);
+        check("x = (-a) ^ b", true);
+        check("x = a ^ (-b)", false);
+        check("x = -(a ^ b)", false);
+        check("x = -(a + b)", true);
+        check("x = not (a == b)", true);
+        check("x = (a or b) and c", true);
+        check("x = f((a or b))", false);
+    }
+
+    #[test]
+    fn test_expr_precedence() {
+        let parse = Chunk::parse("x = -a * b");
+        let precedences: Vec<_> = parse
+            .syntax_node()
+            .descendants()
+            .filter_map(|node| expr_precedence(&node))
+            .collect();
+        assert_eq!(precedences, vec![10, 11]);
+    }
+
+    #[test]
+    fn test_operator_enums() {
+        let parse = Chunk::parse("x = not a .. b ^ #c");
+        let exprs: Vec<_> = parse.syntax_node().descendants().collect();
+        let bin_ops: Vec<_> = exprs
+            .iter()
+            .filter_map(|node| crate::ast::BinExpr::cast(node.clone()))
+            .filter_map(|bin| bin.op_kind())
+            .collect();
+        assert_eq!(bin_ops, vec![BinOp::Concat, BinOp::Pow]);
+        let un_ops: Vec<_> = exprs
+            .iter()
+            .filter_map(|node| crate::ast::UnaryExpr::cast(node.clone()))
+            .map(|unary| {
+                (
+                    unary.op_kind().unwrap(),
+                    unary.op_token().unwrap().text().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            un_ops,
+            vec![(UnOp::Not, "not".to_string()), (UnOp::Len, "#".to_string())]
+        );
+
+        assert_eq!(BinOp::Concat.associativity(), Associativity::Right);
+        assert!(BinOp::Pow.precedence() > UnOp::Neg.precedence());
+        assert_eq!(BinOp::from_kind(SHL), Some(BinOp::Shl));
+        assert_eq!(BinOp::from_kind(HASH), None);
+        assert_eq!(BinOp::NotEq.to_string(), "~=");
+        assert_eq!(UnOp::BitNot.text(), "~");
+    }
+}
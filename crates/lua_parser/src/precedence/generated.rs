@@ -0,0 +1,225 @@
+// This file is automatically generated based on the file `./generated.rs.tera` when `cargo gen-syntax` is run
+// Do not edit manually
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+use super::{Associativity, Precedence};
+use crate::SyntaxKind::{self, *};
+
+/// Returns the precedence and associativity of a binary operator.
+pub fn binary_operator(kind: SyntaxKind) -> Option<(Precedence, Associativity)> {
+    let op = match kind {
+        OR_KW => (1, Associativity::Left),
+        AND_KW => (2, Associativity::Left),
+        LT => (3, Associativity::Left),
+        GT => (3, Associativity::Left),
+        LTEQ => (3, Associativity::Left),
+        GTEQ => (3, Associativity::Left),
+        NEQ => (3, Associativity::Left),
+        EQEQ => (3, Associativity::Left),
+        PIPE => (4, Associativity::Left),
+        TILDE => (5, Associativity::Left),
+        AMP => (6, Associativity::Left),
+        SHL => (7, Associativity::Left),
+        SHR => (7, Associativity::Left),
+        DOTDOT => (8, Associativity::Right),
+        PLUS => (9, Associativity::Left),
+        MINUS => (9, Associativity::Left),
+        STAR => (10, Associativity::Left),
+        SLASH => (10, Associativity::Left),
+        SLASHSLASH => (10, Associativity::Left),
+        PERCENT => (10, Associativity::Left),
+        CARET => (12, Associativity::Right),
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Returns the precedence of a unary operator.
+pub fn unary_operator(kind: SyntaxKind) -> Option<Precedence> {
+    let precedence = match kind {
+        NOT_KW => 11,
+        MINUS => 11,
+        HASH => 11,
+        TILDE => 11,
+        _ => return None,
+    };
+    Some(precedence)
+}
+
+/// A binary operator, e.g. `+` in `a + b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Or,
+    And,
+    Lt,
+    Gt,
+    LtEq,
+    GtEq,
+    NotEq,
+    Eq,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shl,
+    Shr,
+    Concat,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    IntDiv,
+    Mod,
+    Pow,
+}
+
+impl BinOp {
+    /// Returns the operator of a token of `kind`, or `None` if it isn't a binary operator.
+    pub fn from_kind(kind: SyntaxKind) -> Option<BinOp> {
+        let op = match kind {
+            OR_KW => BinOp::Or,
+            AND_KW => BinOp::And,
+            LT => BinOp::Lt,
+            GT => BinOp::Gt,
+            LTEQ => BinOp::LtEq,
+            GTEQ => BinOp::GtEq,
+            NEQ => BinOp::NotEq,
+            EQEQ => BinOp::Eq,
+            PIPE => BinOp::BitOr,
+            TILDE => BinOp::BitXor,
+            AMP => BinOp::BitAnd,
+            SHL => BinOp::Shl,
+            SHR => BinOp::Shr,
+            DOTDOT => BinOp::Concat,
+            PLUS => BinOp::Add,
+            MINUS => BinOp::Sub,
+            STAR => BinOp::Mul,
+            SLASH => BinOp::Div,
+            SLASHSLASH => BinOp::IntDiv,
+            PERCENT => BinOp::Mod,
+            CARET => BinOp::Pow,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// Returns the kind of the token of the operator.
+    pub fn kind(self) -> SyntaxKind {
+        match self {
+            BinOp::Or => OR_KW,
+            BinOp::And => AND_KW,
+            BinOp::Lt => LT,
+            BinOp::Gt => GT,
+            BinOp::LtEq => LTEQ,
+            BinOp::GtEq => GTEQ,
+            BinOp::NotEq => NEQ,
+            BinOp::Eq => EQEQ,
+            BinOp::BitOr => PIPE,
+            BinOp::BitXor => TILDE,
+            BinOp::BitAnd => AMP,
+            BinOp::Shl => SHL,
+            BinOp::Shr => SHR,
+            BinOp::Concat => DOTDOT,
+            BinOp::Add => PLUS,
+            BinOp::Sub => MINUS,
+            BinOp::Mul => STAR,
+            BinOp::Div => SLASH,
+            BinOp::IntDiv => SLASHSLASH,
+            BinOp::Mod => PERCENT,
+            BinOp::Pow => CARET,
+        }
+    }
+
+    pub fn precedence(self) -> Precedence {
+        match self {
+            BinOp::Or => 1,
+            BinOp::And => 2,
+            BinOp::Lt => 3,
+            BinOp::Gt => 3,
+            BinOp::LtEq => 3,
+            BinOp::GtEq => 3,
+            BinOp::NotEq => 3,
+            BinOp::Eq => 3,
+            BinOp::BitOr => 4,
+            BinOp::BitXor => 5,
+            BinOp::BitAnd => 6,
+            BinOp::Shl => 7,
+            BinOp::Shr => 7,
+            BinOp::Concat => 8,
+            BinOp::Add => 9,
+            BinOp::Sub => 9,
+            BinOp::Mul => 10,
+            BinOp::Div => 10,
+            BinOp::IntDiv => 10,
+            BinOp::Mod => 10,
+            BinOp::Pow => 12,
+        }
+    }
+
+    pub fn associativity(self) -> Associativity {
+        match self {
+            BinOp::Or => Associativity::Left,
+            BinOp::And => Associativity::Left,
+            BinOp::Lt => Associativity::Left,
+            BinOp::Gt => Associativity::Left,
+            BinOp::LtEq => Associativity::Left,
+            BinOp::GtEq => Associativity::Left,
+            BinOp::NotEq => Associativity::Left,
+            BinOp::Eq => Associativity::Left,
+            BinOp::BitOr => Associativity::Left,
+            BinOp::BitXor => Associativity::Left,
+            BinOp::BitAnd => Associativity::Left,
+            BinOp::Shl => Associativity::Left,
+            BinOp::Shr => Associativity::Left,
+            BinOp::Concat => Associativity::Right,
+            BinOp::Add => Associativity::Left,
+            BinOp::Sub => Associativity::Left,
+            BinOp::Mul => Associativity::Left,
+            BinOp::Div => Associativity::Left,
+            BinOp::IntDiv => Associativity::Left,
+            BinOp::Mod => Associativity::Left,
+            BinOp::Pow => Associativity::Right,
+        }
+    }
+}
+
+/// A unary operator, e.g. `not` in `not a`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnOp {
+    Not,
+    Neg,
+    Len,
+    BitNot,
+}
+
+impl UnOp {
+    /// Returns the operator of a token of `kind`, or `None` if it isn't a unary operator.
+    pub fn from_kind(kind: SyntaxKind) -> Option<UnOp> {
+        let op = match kind {
+            NOT_KW => UnOp::Not,
+            MINUS => UnOp::Neg,
+            HASH => UnOp::Len,
+            TILDE => UnOp::BitNot,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// Returns the kind of the token of the operator.
+    pub fn kind(self) -> SyntaxKind {
+        match self {
+            UnOp::Not => NOT_KW,
+            UnOp::Neg => MINUS,
+            UnOp::Len => HASH,
+            UnOp::BitNot => TILDE,
+        }
+    }
+
+    pub fn precedence(self) -> Precedence {
+        match self {
+            UnOp::Not => 11,
+            UnOp::Neg => 11,
+            UnOp::Len => 11,
+            UnOp::BitNot => 11,
+        }
+    }
+}
@@ -0,0 +1,376 @@
+//! Declarative queries over syntax trees in the style of tree-sitter queries.
+//!
+//! A query consists of patterns like `(chunk (identifier) @name)` which are matched against every
+//! node of a tree. Node kinds are either tree-sitter names like `identifier` or the name of a
+//! `SyntaxKind` in lower case, e.g. `int_number`. Quoted strings like `"end"` match tokens by
+//! their text. Captures, fields, alternations (`[...]`), the quantifiers `?`, `*` and `+`, and
+//! the predicates `#eq?`, `#not-eq?`, `#match?`, `#not-match?`, `#any-of?` and `#not-any-of?`
+//! are supported. Directives like `#set!` are ignored.
+
+use crate::{SyntaxElement, SyntaxKind, SyntaxNode};
+use regex::Regex;
+use std::{collections::VecDeque, fmt};
+
+mod parse;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    pub message: String,
+    /// The byte offset in the query source at which the error was found
+    pub offset: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    patterns: Vec<Pattern>,
+    capture_names: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    root: PatternNode,
+    predicates: Vec<Predicate>,
+}
+
+#[derive(Debug, Clone)]
+struct PatternNode {
+    matcher: NodeMatcher,
+    field: Option<String>,
+    children: Vec<PatternNode>,
+    quantifier: Quantifier,
+    captures: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum NodeMatcher {
+    /// `(identifier)`, matches nodes or tokens of the given kinds
+    Kinds(Vec<SyntaxKind>),
+    /// `(_)`, matches any node or token that is not a keyword, symbol or whitespace
+    AnyNamed,
+    /// `_`, matches anything but whitespace
+    Any,
+    /// `"end"`, matches tokens with the given text
+    Token(String),
+    /// `[(a) (b)]`, matches if any of the alternatives match
+    Alternation(Vec<PatternNode>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quantifier {
+    One,
+    ZeroOrOne,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+#[derive(Debug, Clone)]
+enum Predicate {
+    EqCapture(usize, usize, bool),
+    EqText(usize, String, bool),
+    Match(usize, Regex, bool),
+    AnyOf(usize, Vec<String>, bool),
+}
+
+/// A node or token captured by a pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryCapture {
+    /// The index of the capture name in `Query::capture_names`
+    pub index: usize,
+    pub element: SyntaxElement,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryMatch {
+    /// The index of the matched pattern in the query
+    pub pattern_index: usize,
+    pub captures: Vec<QueryCapture>,
+}
+
+impl Query {
+    pub fn new(source: &str) -> Result<Query, QueryError> {
+        parse::parse_query(source)
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn capture_names(&self) -> &[String] {
+        &self.capture_names
+    }
+
+    pub fn capture_index(&self, name: &str) -> Option<usize> {
+        self.capture_names.iter().position(|it| it == name)
+    }
+
+    /// Returns the matches of all patterns in the tree rooted at `node`. Matches are produced
+    /// lazily, in the order in which their root nodes are visited by a preorder walk.
+    pub fn matches<'a>(&'a self, node: &SyntaxNode) -> QueryMatches<'a> {
+        QueryMatches {
+            query: self,
+            elements: Box::new(node.descendants_with_tokens()),
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+pub struct QueryMatches<'a> {
+    query: &'a Query,
+    elements: Box<dyn Iterator<Item = SyntaxElement>>,
+    pending: VecDeque<QueryMatch>,
+}
+
+impl<'a> Iterator for QueryMatches<'a> {
+    type Item = QueryMatch;
+
+    fn next(&mut self) -> Option<QueryMatch> {
+        loop {
+            if let Some(next) = self.pending.pop_front() {
+                return Some(next);
+            }
+            let element = self.elements.next()?;
+            for (pattern_index, pattern) in self.query.patterns.iter().enumerate() {
+                let mut captures = Vec::new();
+                if match_element(&pattern.root, &element, &mut captures)
+                    && pattern.predicates.iter().all(|p| p.holds(&captures))
+                {
+                    self.pending.push_back(QueryMatch {
+                        pattern_index,
+                        captures,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn match_element(
+    pattern: &PatternNode,
+    element: &SyntaxElement,
+    captures: &mut Vec<QueryCapture>,
+) -> bool {
+    let kind = element.kind();
+    if kind == SyntaxKind::WHITESPACE {
+        return false;
+    }
+    let start = captures.len();
+    let matches = match &pattern.matcher {
+        NodeMatcher::Kinds(kinds) => kinds.contains(&kind),
+        NodeMatcher::AnyNamed => is_named(kind),
+        NodeMatcher::Any => true,
+        NodeMatcher::Token(text) => element
+            .as_token()
+            .is_some_and(|token| token.text() == text),
+        NodeMatcher::Alternation(alternatives) => alternatives
+            .iter()
+            .any(|alternative| match_element(alternative, element, captures)),
+    };
+    if !matches {
+        captures.truncate(start);
+        return false;
+    }
+
+    if !pattern.children.is_empty() {
+        let node = match element.as_node() {
+            Some(node) => node,
+            None => return false,
+        };
+        let children: Vec<_> = node
+            .children_with_tokens()
+            .filter(|child| child.kind() != SyntaxKind::WHITESPACE)
+            .collect();
+        if !match_children(&pattern.children, node, &children, captures) {
+            captures.truncate(start);
+            return false;
+        }
+    }
+
+    captures.extend(
+        pattern
+            .captures
+            .iter()
+            .map(|&index| QueryCapture {
+                index,
+                element: element.clone(),
+            }),
+    );
+    true
+}
+
+/// Matches child patterns against the children of `parent` in order. Children that are not
+/// matched by any pattern are skipped.
+fn match_children(
+    patterns: &[PatternNode],
+    parent: &SyntaxNode,
+    children: &[SyntaxElement],
+    captures: &mut Vec<QueryCapture>,
+) -> bool {
+    let (pattern, rest) = match patterns.split_first() {
+        Some(split) => split,
+        None => return true,
+    };
+
+    let start = captures.len();
+    for (idx, child) in children.iter().enumerate() {
+        let field_matches = pattern
+            .field
+            .as_ref()
+            .is_none_or(|field| parse::field_matches(parent, child, field));
+        if field_matches && match_element(pattern, child, captures) {
+            let remaining = &children[idx + 1..];
+            let matched = match pattern.quantifier {
+                Quantifier::ZeroOrMore | Quantifier::OneOrMore => {
+                    let repeated = PatternNode {
+                        quantifier: Quantifier::ZeroOrMore,
+                        ..pattern.clone()
+                    };
+                    let mut patterns = vec![repeated];
+                    patterns.extend(rest.iter().cloned());
+                    match_children(&patterns, parent, remaining, captures)
+                }
+                _ => match_children(rest, parent, remaining, captures),
+            };
+            if matched {
+                return true;
+            }
+        }
+        captures.truncate(start);
+    }
+
+    match pattern.quantifier {
+        Quantifier::ZeroOrOne | Quantifier::ZeroOrMore => {
+            match_children(rest, parent, children, captures)
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if elements of this kind are matched by `(_)`.
+fn is_named(kind: SyntaxKind) -> bool {
+    !(kind.is_keyword() || kind.is_symbol() || kind == SyntaxKind::WHITESPACE)
+}
+
+fn element_text(element: &SyntaxElement) -> String {
+    match element {
+        SyntaxElement::Node(node) => node.text().to_string(),
+        SyntaxElement::Token(token) => token.text().to_string(),
+    }
+}
+
+impl Predicate {
+    /// Returns true if the predicate holds for all captures it refers to.
+    fn holds(&self, captures: &[QueryCapture]) -> bool {
+        match self {
+            Predicate::EqCapture(a, b, negated) => capture_texts(captures, *a)
+                .zip(capture_texts(captures, *b))
+                .all(|(a, b)| (a == b) != *negated),
+            Predicate::EqText(index, text, negated) => {
+                capture_texts(captures, *index).all(|it| (&it == text) != *negated)
+            }
+            Predicate::Match(index, regex, negated) => {
+                capture_texts(captures, *index).all(|it| regex.is_match(&it) != *negated)
+            }
+            Predicate::AnyOf(index, values, negated) => {
+                capture_texts(captures, *index).all(|it| values.contains(&it) != *negated)
+            }
+        }
+    }
+}
+
+/// Returns the text of every capture with the given index.
+fn capture_texts<'c>(
+    captures: &'c [QueryCapture],
+    index: usize,
+) -> impl Iterator<Item = String> + 'c {
+    captures
+        .iter()
+        .filter(move |capture| capture.index == index)
+        .map(|capture| element_text(&capture.element))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{flat_chunk, AstNode};
+
+    fn captures(query: &str, text: &str) -> Vec<(String, String)> {
+        let query = Query::new(query).unwrap();
+        let chunk = flat_chunk(text);
+        query
+            .matches(chunk.syntax())
+            .flat_map(|m| m.captures)
+            .map(|capture| {
+                (
+                    query.capture_names()[capture.index].clone(),
+                    element_text(&capture.element),
+                )
+            })
+            .collect()
+    }
+
+    fn capture(name: &str, text: &str) -> (String, String) {
+        (name.to_string(), text.to_string())
+    }
+
+    #[test]
+    fn test_match_kinds_and_tokens() {
+        assert_eq!(
+            captures("(identifier) @id \"end\" @kw", "if a then b end"),
+            vec![capture("id", "a"), capture("id", "b"), capture("kw", "end")]
+        );
+        assert_eq!(
+            captures("[(number) (string)] @literal", "x = 1 .. 'a'"),
+            vec![capture("literal", "1"), capture("literal", "'a'")]
+        );
+    }
+
+    #[test]
+    fn test_children_and_quantifiers() {
+        assert_eq!(
+            captures("(chunk \"local\" (identifier) @first)", "local a = b"),
+            vec![capture("first", "a")]
+        );
+        assert_eq!(
+            captures("(chunk (comment)* @comment)", "-- a\nx = 1 -- b"),
+            vec![capture("comment", "-- a"), capture("comment", "-- b")]
+        );
+        assert!(captures("(chunk (comment)+ @comment)", "x = 1").is_empty());
+        assert_eq!(
+            captures("(chunk (comment)? @comment (identifier) @id)", "x = 1").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_predicates() {
+        let query = "((identifier) @constant (#match? @constant \"^[A-Z_]+$\"))";
+        assert_eq!(
+            captures(query, "local MAX_SIZE = size"),
+            vec![capture("constant", "MAX_SIZE")]
+        );
+        let query = "((identifier) @id (#any-of? @id \"print\" \"error\") (#set! priority 1))";
+        assert_eq!(
+            captures(query, "print(x) error(y)"),
+            vec![capture("id", "print"), capture("id", "error")]
+        );
+        let query = "((identifier) @id (#not-eq? @id \"self\"))";
+        assert_eq!(captures(query, "self.x"), vec![capture("id", "x")]);
+    }
+
+    #[test]
+    fn test_query_errors() {
+        assert!(Query::new("(unknown_kind)").is_err());
+        assert!(Query::new("(identifier").is_err());
+        assert!(Query::new("((identifier) @a (#match? @a \"[\"))").is_err());
+        assert!(Query::new("((identifier) @a (#eq? @b \"x\"))").is_err());
+        assert!(Query::new("(chunk name: (identifier))").is_err());
+    }
+}
@@ -0,0 +1,407 @@
+//! Parses the source of a `Query`.
+
+use super::{NodeMatcher, Pattern, PatternNode, Predicate, Quantifier, Query, QueryError};
+use crate::{
+    SyntaxElement,
+    SyntaxKind::{self, *},
+    SyntaxNode,
+};
+use regex::Regex;
+
+/// Names used by the tree-sitter Lua grammar mapped to the kinds they match in this tree.
+const KIND_ALIASES: &[(&str, &[SyntaxKind])] = &[
+    ("chunk", &[CHUNK]),
+    ("comment", &[COMMENT]),
+    ("identifier", &[IDENT]),
+    ("number", &[INT_NUMBER, FLOAT_NUMBER]),
+    ("string", &[STRING]),
+    ("nil", &[NIL_KW]),
+    ("true", &[TRUE_KW]),
+    ("false", &[FALSE_KW]),
+    ("vararg_expression", &[DOTDOTDOT]),
+];
+
+/// Fields of node kinds, described by the parent kind, the field name and the kinds of the
+/// children that are part of the field.
+const FIELDS: &[(SyntaxKind, &str, &[SyntaxKind])] = &[];
+
+/// Returns true if `child` is part of the field `name` of `parent`.
+pub(super) fn field_matches(parent: &SyntaxNode, child: &SyntaxElement, name: &str) -> bool {
+    FIELDS.iter().any(|&(kind, field, kinds)| {
+        kind == parent.kind() && field == name && kinds.contains(&child.kind())
+    })
+}
+
+fn lookup_kinds(name: &str) -> Option<Vec<SyntaxKind>> {
+    if let Some((_, kinds)) = KIND_ALIASES.iter().find(|(alias, _)| *alias == name) {
+        return Some(kinds.to_vec());
+    }
+    SyntaxKind::all()
+        .find(|kind| kind.info().name.eq_ignore_ascii_case(name))
+        .map(|kind| vec![kind])
+}
+
+pub(super) fn parse_query(source: &str) -> Result<Query, QueryError> {
+    let mut parser = Parser {
+        source,
+        pos: 0,
+        capture_names: Vec::new(),
+        predicates: Vec::new(),
+    };
+    let mut patterns = Vec::new();
+    loop {
+        parser.skip_trivia();
+        if parser.at_end() {
+            break;
+        }
+        let root = parser.pattern()?;
+        patterns.push(Pattern {
+            root,
+            predicates: std::mem::take(&mut parser.predicates),
+        });
+    }
+    Ok(Query {
+        patterns,
+        capture_names: parser.capture_names,
+    })
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+    capture_names: Vec<String>,
+    /// The predicates of the pattern that is currently being parsed
+    predicates: Vec<Predicate>,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, QueryError> {
+        Err(QueryError {
+            message: message.into(),
+            offset: self.pos,
+        })
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.source.len()
+    }
+
+    fn current(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+
+    fn nth(&self, n: usize) -> Option<char> {
+        self.source[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.current() {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.current() == Some(c) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), QueryError> {
+        self.skip_trivia();
+        if self.eat(c) {
+            Ok(())
+        } else {
+            self.error(format!("expected `{}`", c))
+        }
+    }
+
+    /// Skips whitespace and `;` comments.
+    fn skip_trivia(&mut self) {
+        while let Some(c) = self.current() {
+            if c.is_whitespace() {
+                self.bump();
+            } else if c == ';' {
+                while self.current().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn identifier(&mut self) -> Result<&'a str, QueryError> {
+        let start = self.pos;
+        while self
+            .current()
+            .is_some_and(|c| c.is_alphanumeric() || "_-.?!".contains(c))
+        {
+            self.bump();
+        }
+        if start == self.pos {
+            return self.error("expected an identifier");
+        }
+        Ok(&self.source[start..self.pos])
+    }
+
+    fn string(&mut self) -> Result<String, QueryError> {
+        self.expect('"')?;
+        let mut result = String::new();
+        loop {
+            match self.current() {
+                None => return self.error("unterminated string"),
+                Some('"') => {
+                    self.bump();
+                    return Ok(result);
+                }
+                Some('\\') => {
+                    self.bump();
+                    match self.current() {
+                        Some('n') => result.push('\n'),
+                        Some('t') => result.push('\t'),
+                        Some('r') => result.push('\r'),
+                        Some(c) => result.push(c),
+                        None => return self.error("unterminated string"),
+                    }
+                    self.bump();
+                }
+                Some(c) => {
+                    result.push(c);
+                    self.bump();
+                }
+            }
+        }
+    }
+
+    fn capture_index(&mut self, name: &str) -> usize {
+        match self.capture_names.iter().position(|it| it == name) {
+            Some(index) => index,
+            None => {
+                self.capture_names.push(name.to_string());
+                self.capture_names.len() - 1
+            }
+        }
+    }
+
+    /// Parses a pattern with an optional quantifier and captures.
+    fn pattern(&mut self) -> Result<PatternNode, QueryError> {
+        self.skip_trivia();
+        let mut node = match self.current() {
+            Some('(') => self.node()?,
+            Some('[') => {
+                self.bump();
+                let mut alternatives = Vec::new();
+                loop {
+                    self.skip_trivia();
+                    if self.eat(']') {
+                        break;
+                    }
+                    if self.at_end() {
+                        return self.error("expected `]`");
+                    }
+                    alternatives.push(self.pattern()?);
+                }
+                if alternatives.is_empty() {
+                    return self.error("empty alternation");
+                }
+                leaf(NodeMatcher::Alternation(alternatives))
+            }
+            Some('"') => leaf(NodeMatcher::Token(self.string()?)),
+            Some('_') => {
+                self.bump();
+                leaf(NodeMatcher::Any)
+            }
+            Some('.') => return self.error("anchors are not supported"),
+            Some('!') => return self.error("negated fields are not supported"),
+            _ => return self.error("expected a pattern"),
+        };
+
+        let quantifier = match self.current() {
+            Some('?') => Some(Quantifier::ZeroOrOne),
+            Some('*') => Some(Quantifier::ZeroOrMore),
+            Some('+') => Some(Quantifier::OneOrMore),
+            _ => None,
+        };
+        if let Some(quantifier) = quantifier {
+            self.bump();
+            node.quantifier = quantifier;
+        }
+
+        loop {
+            self.skip_trivia();
+            if !self.eat('@') {
+                break;
+            }
+            let name = self.identifier()?;
+            let index = self.capture_index(name);
+            node.captures.push(index);
+        }
+        Ok(node)
+    }
+
+    /// Parses a parenthesized pattern, i.e. a node like `(kind child*)` or a grouping like
+    /// `((kind) @capture (#predicate))`.
+    fn node(&mut self) -> Result<PatternNode, QueryError> {
+        self.expect('(')?;
+        self.skip_trivia();
+
+        let mut node = match self.current() {
+            Some('(') | Some('[') | Some('"') => {
+                let start = self.pos;
+                let children = self.children()?;
+                let mut children = children.into_iter();
+                match (children.next(), children.next()) {
+                    (Some(child), None) => child,
+                    (None, _) => return self.error("empty grouping"),
+                    (Some(_), Some(_)) => {
+                        self.pos = start;
+                        return self.error("sequences of sibling patterns are not supported");
+                    }
+                }
+            }
+            _ => {
+                let start = self.pos;
+                let name = self.identifier()?;
+                let matcher = if name == "_" {
+                    NodeMatcher::AnyNamed
+                } else {
+                    match lookup_kinds(name) {
+                        Some(kinds) => NodeMatcher::Kinds(kinds),
+                        None => {
+                            self.pos = start;
+                            return self.error(format!("unknown node kind `{}`", name));
+                        }
+                    }
+                };
+                PatternNode {
+                    children: self.children()?,
+                    ..leaf(matcher)
+                }
+            }
+        };
+        node.field = None;
+        self.expect(')')?;
+        Ok(node)
+    }
+
+    /// Parses child patterns and predicates up to the closing parenthesis of a node.
+    fn children(&mut self) -> Result<Vec<PatternNode>, QueryError> {
+        let mut children = Vec::new();
+        loop {
+            self.skip_trivia();
+            match self.current() {
+                Some(')') | None => return Ok(children),
+                Some('(') if self.nth(1) == Some('#') => self.predicate()?,
+                Some(c) if c.is_alphabetic() => {
+                    let start = self.pos;
+                    let name = self.identifier()?.to_string();
+                    self.skip_trivia();
+                    if !self.eat(':') {
+                        self.pos = start;
+                        return self.error("expected a pattern");
+                    }
+                    if !FIELDS.iter().any(|&(_, field, _)| field == name) {
+                        self.pos = start;
+                        return self.error(format!("unknown field `{}`", name));
+                    }
+                    let mut child = self.pattern()?;
+                    child.field = Some(name);
+                    children.push(child);
+                }
+                _ => children.push(self.pattern()?),
+            }
+        }
+    }
+
+    /// Parses a predicate like `(#eq? @capture "text")`. Directives like `(#set! key value)`
+    /// are accepted but ignored.
+    fn predicate(&mut self) -> Result<(), QueryError> {
+        self.expect('(')?;
+        self.expect('#')?;
+        let start = self.pos;
+        let name = self.identifier()?;
+
+        let mut args = Vec::new();
+        loop {
+            self.skip_trivia();
+            match self.current() {
+                Some(')') => {
+                    self.bump();
+                    break;
+                }
+                Some('@') => {
+                    self.bump();
+                    let capture = self.identifier()?;
+                    match self.capture_names.iter().position(|it| it == capture) {
+                        Some(index) => args.push(Arg::Capture(index)),
+                        None => return self.error(format!("unknown capture `@{}`", capture)),
+                    }
+                }
+                Some('"') => args.push(Arg::String(self.string()?)),
+                Some(_) => args.push(Arg::String(self.identifier()?.to_string())),
+                None => return self.error("expected `)`"),
+            }
+        }
+
+        let (negated, operator) = match name.strip_prefix("not-") {
+            Some(operator) => (true, operator),
+            None => (false, name),
+        };
+        let predicate = match (operator, &args[..]) {
+            ("eq?", [Arg::Capture(a), Arg::Capture(b)]) => Predicate::EqCapture(*a, *b, negated),
+            ("eq?", [Arg::Capture(a), Arg::String(text)]) => {
+                Predicate::EqText(*a, text.clone(), negated)
+            }
+            ("match?", [Arg::Capture(a), Arg::String(regex)]) => match Regex::new(regex) {
+                Ok(regex) => Predicate::Match(*a, regex, negated),
+                Err(err) => {
+                    self.pos = start;
+                    return self.error(format!("invalid regex: {}", err));
+                }
+            },
+            ("any-of?", [Arg::Capture(a), values @ ..]) if !values.is_empty() => {
+                let strings: Vec<_> = values
+                    .iter()
+                    .filter_map(|value| match value {
+                        Arg::String(value) => Some(value.clone()),
+                        Arg::Capture(_) => None,
+                    })
+                    .collect();
+                if strings.len() != values.len() {
+                    self.pos = start;
+                    return self.error("`#any-of?` expects a capture followed by strings");
+                }
+                Predicate::AnyOf(*a, strings, negated)
+            }
+            ("eq?", _) | ("match?", _) | ("any-of?", _) => {
+                self.pos = start;
+                return self.error(format!("invalid arguments for `#{}`", name));
+            }
+            _ if name.ends_with('!') => return Ok(()),
+            _ => {
+                self.pos = start;
+                return self.error(format!("unknown predicate `#{}`", name));
+            }
+        };
+        self.predicates.push(predicate);
+        Ok(())
+    }
+}
+
+enum Arg {
+    Capture(usize),
+    String(String),
+}
+
+fn leaf(matcher: NodeMatcher) -> PatternNode {
+    PatternNode {
+        matcher,
+        field: None,
+        children: Vec::new(),
+        quantifier: Quantifier::One,
+        captures: Vec::new(),
+    }
+}
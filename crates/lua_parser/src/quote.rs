@@ -0,0 +1,174 @@
+//! Builds Lua code from templates, for tools that generate code.
+//!
+//! A template is Lua code in which placeholders like `$name` are replaced by identifiers, text or
+//! existing syntax nodes before the code is parsed, e.g.
+//! `lua_quote!("local $name = $value", name = "x", value = expr)`.
+
+use crate::{precedence::expr_precedence, AstNode, Chunk, SmolStr, SyntaxKind::*, SyntaxNode};
+use std::{collections::HashMap, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteError(String);
+
+impl fmt::Display for QuoteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for QuoteError {}
+
+/// A value that can replace a placeholder in a template.
+pub trait Splice {
+    /// Returns the Lua code that replaces the placeholder.
+    fn to_lua(&self) -> String;
+}
+
+/// Strings are inserted as written, e.g. the name of a variable or a snippet of code. A string
+/// value is inserted as a literal with `escape_lua_string`.
+impl Splice for str {
+    fn to_lua(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Splice for String {
+    fn to_lua(&self) -> String {
+        self.clone()
+    }
+}
+
+impl Splice for SmolStr {
+    fn to_lua(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Binary and unary expressions are put in parentheses, so they keep their meaning as an operand
+/// of another operator.
+impl Splice for SyntaxNode {
+    fn to_lua(&self) -> String {
+        if expr_precedence(self).is_some() {
+            format!("({})", self.text())
+        } else {
+            self.text().to_string()
+        }
+    }
+}
+
+impl<N: AstNode> Splice for N {
+    fn to_lua(&self) -> String {
+        self.syntax().to_lua()
+    }
+}
+
+/// Replaces the placeholders in `template` by the code in `splices`, which is given by the name
+/// of the placeholder without the `$`, and parses the result. Fails if a placeholder has no code
+/// or if the result doesn't parse. See `lua_quote!` for a shorter way to call it.
+pub fn quote(template: &str, splices: &[(&str, String)]) -> Result<Chunk, QuoteError> {
+    let splices: HashMap<_, _> = splices.iter().map(|(name, code)| (*name, code)).collect();
+    let tokens = crate::validation::significant_tokens(template);
+    let mut text = String::with_capacity(template.len());
+    let mut copied = 0;
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let token = &tokens[idx];
+        if token.kind != DOLLAR {
+            idx += 1;
+            continue;
+        }
+        let name = tokens
+            .get(idx + 1)
+            .filter(|name| name.kind == IDENT && name.range.start() == token.range.end())
+            .ok_or_else(|| {
+                QuoteError(format!(
+                    "expected a placeholder name after `$` at offset {}",
+                    u32::from(token.range.start())
+                ))
+            })?;
+        let name_text = &template[name.range];
+        let splice = splices
+            .get(name_text)
+            .ok_or_else(|| QuoteError(format!("no value for the placeholder `${}`", name_text)))?;
+        text.push_str(&template[copied..usize::from(token.range.start())]);
+        text.push_str(splice);
+        copied = usize::from(name.range.end());
+        idx += 2;
+    }
+    text.push_str(&template[copied..]);
+
+    let parse = Chunk::parse(&text);
+    match parse.errors().first() {
+        Some(error) => Err(QuoteError(format!(
+            "the quoted code `{}` is invalid: {}",
+            text, error
+        ))),
+        None => Ok(parse.tree()),
+    }
+}
+
+/// Builds a `Chunk` from a template, see `quote`. The placeholders are given as `name = value`,
+/// where the value implements `Splice`, e.g. a `&str` or an AST node:
+/// `lua_quote!("local $name = $sum * 2", name = "total", sum = expr)`.
+#[macro_export]
+macro_rules! lua_quote {
+    ($template:expr $(, $name:ident = $value:expr)* $(,)?) => {{
+        #[allow(unused_imports)]
+        use $crate::Splice as _;
+        $crate::quote($template, &[$((stringify!($name), $value.to_lua())),*])
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Expr;
+
+    #[test]
+    fn test_quote() {
+        let chunk = Chunk::parse("return f(x), not y").tree();
+        let exprs: Vec<_> = chunk
+            .syntax()
+            .descendants()
+            .filter_map(Expr::cast)
+            .collect();
+        let call = &exprs[0];
+        let not = exprs
+            .iter()
+            .find(|expr| expr.syntax().text() == "not y")
+            .unwrap();
+
+        let quoted = lua_quote!(
+            "local $name <const> = $call .. $negation\nprint($name)",
+            name = "z",
+            call = call,
+            negation = not.syntax().clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            quoted.syntax().text(),
+            "local z <const> = f(x) .. (not y)\nprint(z)"
+        );
+        let names = String::from("a, b");
+        assert_eq!(
+            lua_quote!("local $names", names = names)
+                .unwrap()
+                .syntax()
+                .text(),
+            "local a, b"
+        );
+    }
+
+    #[test]
+    fn test_quote_errors() {
+        assert_eq!(
+            lua_quote!("x = $y").unwrap_err().to_string(),
+            "no value for the placeholder `$y`"
+        );
+        assert_eq!(
+            lua_quote!("x = $ y", y = "1").unwrap_err().to_string(),
+            "expected a placeholder name after `$` at offset 4"
+        );
+        assert!(lua_quote!("x = $y", y = "1 +").is_err());
+    }
+}
@@ -0,0 +1,97 @@
+//! Extracts the modules that a chunk loads through `require`.
+
+use crate::{
+    validation::significant_tokens,
+    SyntaxKind::*,
+    TextRange,
+};
+
+/// A call to `require` with a literal module name, e.g. `require "foo.bar"` or
+/// `require("foo.bar")`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Require {
+    /// The name of the required module
+    pub name: String,
+    /// The range of the string literal that contains the module name
+    pub range: TextRange,
+}
+
+/// Returns all calls to the global `require` function in `text` whose argument is a string
+/// literal. Calls with computed module names cannot be resolved statically and are skipped.
+pub fn find_requires(text: &str) -> Vec<Require> {
+    let tokens = significant_tokens(text);
+    let mut result = Vec::new();
+    for (idx, token) in tokens.iter().enumerate() {
+        if token.kind != IDENT || &text[token.range] != "require" {
+            continue;
+        }
+        let is_field = idx > 0 && (tokens[idx - 1].kind == DOT || tokens[idx - 1].kind == COLON);
+        if is_field {
+            continue;
+        }
+
+        let kinds: Vec<_> = tokens[idx + 1..].iter().take(3).map(|t| t.kind).collect();
+        let literal = if kinds.starts_with(&[STRING]) {
+            &tokens[idx + 1]
+        } else if kinds == [L_PAREN, STRING, R_PAREN] {
+            &tokens[idx + 2]
+        } else {
+            continue;
+        };
+
+        if let Some(name) = string_value(&text[literal.range]) {
+            result.push(Require {
+                name,
+                range: literal.range,
+            });
+        }
+    }
+    result
+}
+
+/// Returns the value of a string literal, or `None` if the literal contains escape sequences.
+pub(crate) fn string_value(literal: &str) -> Option<String> {
+    if literal.starts_with('"') || literal.starts_with('\'') {
+        let quote = &literal[..1];
+        if literal.len() < 2 || !literal.ends_with(quote) || literal.contains('\\') {
+            return None;
+        }
+        return Some(literal[1..literal.len() - 1].to_string());
+    }
+
+    // A long string, e.g. `[==[foo]==]`
+    let level = literal[1..].find('[')?;
+    let content = literal.get(level + 2..literal.len().checked_sub(level + 2)?)?;
+    let content = content
+        .strip_prefix("\r\n")
+        .or_else(|| content.strip_prefix('\n'))
+        .unwrap_or(content);
+    Some(content.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(text: &str) -> Vec<String> {
+        find_requires(text).into_iter().map(|r| r.name).collect()
+    }
+
+    #[test]
+    fn test_find_requires() {
+        assert_eq!(
+            names("local a = require 'a'\nlocal b = require(\"b.c\")\nrequire [[d]]"),
+            vec!["a", "b.c", "d"]
+        );
+        let requires = find_requires("x = require('a')");
+        assert_eq!(requires[0].range, TextRange::at(12.into(), 3.into()));
+    }
+
+    #[test]
+    fn test_skip_dynamic_requires() {
+        assert!(names("require(name)").is_empty());
+        assert!(names("require('a' .. b)").is_empty());
+        assert!(names("obj:require 'a'").is_empty());
+        assert!(names("require '\\97'").is_empty());
+    }
+}
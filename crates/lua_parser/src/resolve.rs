@@ -0,0 +1,330 @@
+//! Resolves the names in a chunk to the local variables they refer to.
+//!
+//! Every local variable is visible in a range of the text, its scope. A name refers to the local
+//! with that name whose scope contains it and that was declared last, or to a global variable if
+//! there is no such local.
+
+use crate::{
+    ast::{
+        Block, Chunk, GenericForStmt, LocalAssignStmt, LocalFunctionStmt, Name, NameRef,
+        NumericForStmt, ParamList,
+    },
+    AstNode,
+    SyntaxKind::*,
+    SyntaxNode, TextRange, TextSize,
+};
+use std::collections::HashMap;
+
+/// Identifies a local variable in a `NameResolution`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LocalId(usize);
+
+/// A local variable, declared by a `local` statement, as a parameter or by a `for` loop.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Local {
+    pub name: String,
+    /// The range of the name in the declaration, or of the method name for the implicit `self`
+    /// parameter of a method
+    pub range: TextRange,
+    /// The range of the text in which the local is visible
+    pub scope: TextRange,
+    /// The node that declares the local, e.g. a `LOCAL_ASSIGN_STMT`, `PARAM_LIST` or
+    /// `NUMERIC_FOR_STMT`
+    pub declaration: SyntaxNode,
+}
+
+/// A name that reads or assigns a variable.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Reference {
+    pub name_ref: NameRef,
+    /// The local that the name refers to, or `None` for a global variable
+    pub local: Option<LocalId>,
+    /// Whether the reference assigns the variable, e.g. `x` in `x = 1` or `function x() end`
+    pub is_write: bool,
+}
+
+/// The locals of a chunk and the variables that its names refer to.
+#[derive(Debug, Clone)]
+pub struct NameResolution {
+    locals: Vec<Local>,
+    references: Vec<Reference>,
+    reference_idx: HashMap<NameRef, usize>,
+}
+
+impl NameResolution {
+    /// Returns the locals in the order of their declaration.
+    pub fn locals(&self) -> &[Local] {
+        &self.locals
+    }
+
+    pub fn local(&self, id: LocalId) -> &Local {
+        &self.locals[id.0]
+    }
+
+    /// Returns all names in the chunk, in the order of the text.
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+
+    /// Returns the names that refer to the local `id`.
+    pub fn local_references(&self, id: LocalId) -> impl Iterator<Item = &Reference> + '_ {
+        self.references
+            .iter()
+            .filter(move |reference| reference.local == Some(id))
+    }
+
+    /// Returns the reference of `name_ref`, or `None` if it isn't part of the resolved chunk.
+    pub fn reference(&self, name_ref: &NameRef) -> Option<&Reference> {
+        self.reference_idx
+            .get(name_ref)
+            .map(|&idx| &self.references[idx])
+    }
+
+    /// Returns the local that `name_ref` refers to, or `None` for a global variable.
+    pub fn resolve(&self, name_ref: &NameRef) -> Option<LocalId> {
+        self.reference_idx
+            .get(name_ref)
+            .and_then(|&idx| self.references[idx].local)
+    }
+
+    /// Returns the local that `name` would refer to at `offset`, or `None` for a global variable.
+    pub fn lookup(&self, name: &str, offset: TextSize) -> Option<LocalId> {
+        self.locals
+            .iter()
+            .enumerate()
+            .filter(|(_, local)| local.name == name && local.scope.contains(offset))
+            .max_by_key(|(idx, local)| (local.scope.start(), *idx))
+            .map(|(idx, _)| LocalId(idx))
+    }
+
+    /// Returns the local whose declaration or reference is at `offset`.
+    pub fn local_at(&self, offset: TextSize) -> Option<LocalId> {
+        let declared = self
+            .locals
+            .iter()
+            .position(|local| local.range.contains_inclusive(offset))
+            .map(LocalId);
+        declared.or_else(|| {
+            self.references
+                .iter()
+                .find(|reference| {
+                    reference
+                        .name_ref
+                        .syntax()
+                        .text_range()
+                        .contains_inclusive(offset)
+                })
+                .and_then(|reference| reference.local)
+        })
+    }
+}
+
+/// Resolves all names in `chunk`.
+pub fn resolve_names(chunk: &Chunk) -> NameResolution {
+    let mut resolution = NameResolution {
+        locals: Vec::new(),
+        references: Vec::new(),
+        reference_idx: HashMap::new(),
+    };
+    for node in chunk.syntax().descendants() {
+        declare_locals(&node, &mut resolution.locals);
+    }
+    for name_ref in chunk.syntax().descendants().filter_map(NameRef::cast) {
+        let local = resolution.lookup(&name_ref.syntax().text().to_string(), start(&name_ref));
+        let is_write = is_write(&name_ref);
+        resolution
+            .reference_idx
+            .insert(name_ref.clone(), resolution.references.len());
+        resolution.references.push(Reference {
+            name_ref,
+            local,
+            is_write,
+        });
+    }
+    resolution
+}
+
+/// Adds the locals that `node` declares.
+fn declare_locals(node: &SyntaxNode, locals: &mut Vec<Local>) {
+    let mut declare = |name: Name, scope: TextRange| {
+        locals.push(Local {
+            name: name.syntax().text().to_string(),
+            range: name.syntax().text_range(),
+            scope,
+            declaration: node.clone(),
+        })
+    };
+    match node.kind() {
+        LOCAL_ASSIGN_STMT => {
+            let stmt = LocalAssignStmt::cast(node.clone()).unwrap();
+            let scope = TextRange::new(node.text_range().end(), scope_end(node));
+            for name in stmt.name_list().iter().flat_map(|names| names.names()) {
+                declare(name, scope);
+            }
+        }
+        LOCAL_FUNCTION_STMT => {
+            // The function can call itself
+            if let Some(name) = LocalFunctionStmt::cast(node.clone()).unwrap().name() {
+                let scope = TextRange::new(start(&name), scope_end(node));
+                declare(name, scope);
+            }
+        }
+        PARAM_LIST => {
+            let function = match node.parent() {
+                Some(function) => function,
+                None => return,
+            };
+            let scope = TextRange::new(node.text_range().start(), function.text_range().end());
+            for param in ParamList::cast(node.clone()).unwrap().params() {
+                declare(param, scope);
+            }
+            let method_name = function
+                .children()
+                .find(|child| child.kind() == FUNCTION_NAME)
+                .filter(|name| name.children_with_tokens().any(|t| t.kind() == COLON))
+                .and_then(|name| name.last_token());
+            if let Some(method_name) = method_name {
+                locals.push(Local {
+                    name: "self".to_string(),
+                    range: method_name.text_range(),
+                    scope,
+                    declaration: node.clone(),
+                });
+            }
+        }
+        NUMERIC_FOR_STMT => {
+            let stmt = NumericForStmt::cast(node.clone()).unwrap();
+            if let (Some(name), Some(scope)) = (stmt.name(), loop_scope(node, stmt.block())) {
+                declare(name, scope);
+            }
+        }
+        GENERIC_FOR_STMT => {
+            let stmt = GenericForStmt::cast(node.clone()).unwrap();
+            if let Some(scope) = loop_scope(node, stmt.block()) {
+                for name in stmt.name_list().iter().flat_map(|names| names.names()) {
+                    declare(name, scope);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the end of the scope of a local declared by the statement `stmt`, the end of the
+/// enclosing block. The condition of `repeat ... until` can use the locals of its block.
+fn scope_end(stmt: &SyntaxNode) -> TextSize {
+    let block = match stmt.parent() {
+        Some(block) => block,
+        None => return stmt.text_range().end(),
+    };
+    match block.parent() {
+        Some(repeat) if repeat.kind() == REPEAT_STMT => repeat.text_range().end(),
+        _ => block.text_range().end(),
+    }
+}
+
+/// The variables of a `for` loop are visible in its body, but not in the loop header.
+fn loop_scope(stmt: &SyntaxNode, block: Option<Block>) -> Option<TextRange> {
+    Some(TextRange::new(
+        block?.syntax().text_range().start(),
+        stmt.text_range().end(),
+    ))
+}
+
+fn is_write(name_ref: &NameRef) -> bool {
+    let parent = match name_ref.syntax().parent() {
+        Some(parent) => parent,
+        None => return false,
+    };
+    match parent.kind() {
+        // The targets are the first `EXPR_LIST` of an assignment
+        EXPR_LIST => parent.parent().is_some_and(|assign| {
+            assign.kind() == ASSIGN_STMT && assign.first_child().as_ref() == Some(&parent)
+        }),
+        // `function a.b() end` only reads `a`
+        FUNCTION_NAME => !parent
+            .children_with_tokens()
+            .any(|element| matches!(element.kind(), DOT | COLON)),
+        _ => false,
+    }
+}
+
+fn start<N: AstNode>(node: &N) -> TextSize {
+    node.syntax().text_range().start()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns each name in `text` with the offset of the declaration it refers to.
+    fn resolved(text: &str) -> Vec<(String, Option<u32>)> {
+        let chunk = Chunk::parse(text).tree();
+        let resolution = resolve_names(&chunk);
+        resolution
+            .references()
+            .iter()
+            .map(|reference| {
+                let local = reference
+                    .local
+                    .map(|id| u32::from(resolution.local(id).range.start()));
+                (reference.name_ref.syntax().text().to_string(), local)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_names() {
+        assert_eq!(
+            resolved("local x = x\nprint(x)"),
+            vec![
+                ("x".to_string(), None),
+                ("print".to_string(), None),
+                ("x".to_string(), Some(6)),
+            ]
+        );
+        // Shadowing, parameters and the implicit `self`
+        assert_eq!(
+            resolved("local a = 1 local a = a function t:m(a) return self, a end"),
+            vec![
+                ("a".to_string(), Some(6)),
+                ("t".to_string(), None),
+                ("self".to_string(), Some(35)),
+                ("a".to_string(), Some(37)),
+            ]
+        );
+        assert_eq!(
+            resolved("local function f() f() end for i = i, 2 do g(i) end"),
+            vec![
+                ("f".to_string(), Some(15)),
+                ("i".to_string(), None),
+                ("g".to_string(), None),
+                ("i".to_string(), Some(31)),
+            ]
+        );
+        assert_eq!(
+            resolved("repeat local done = f() until done"),
+            vec![("f".to_string(), None), ("done".to_string(), Some(13))]
+        );
+        assert_eq!(
+            resolved("do local x end x = 1"),
+            vec![("x".to_string(), None)]
+        );
+    }
+
+    #[test]
+    fn test_writes() {
+        let chunk =
+            Chunk::parse("local x, t\nx, t.y = 1, x\nfunction x() end\nfunction t.f() end").tree();
+        let resolution = resolve_names(&chunk);
+        let writes: Vec<_> = resolution
+            .references()
+            .iter()
+            .map(|reference| reference.is_write)
+            .collect();
+        assert_eq!(writes, vec![true, false, false, true, false]);
+        let x = resolution.local_at(6.into()).unwrap();
+        assert_eq!(resolution.local(x).name, "x");
+        assert_eq!(resolution.local_references(x).count(), 3);
+    }
+}
@@ -0,0 +1,306 @@
+//! Maps ranges in generated text back to the ranges in the original source they were produced
+//! from, e.g. after formatting a chunk or applying a `TextEdit`.
+
+use crate::{LineCol, LineIndex, TextRange, TextSize};
+use serde::Serialize;
+
+/// A range in the generated text together with the range in the original text it stems from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Mapping {
+    pub generated: TextRange,
+    pub original: TextRange,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    /// The mappings, ordered by their generated range
+    mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// Adds a mapping. Mappings must be added in the order of their generated ranges.
+    pub fn add(&mut self, generated: TextRange, original: TextRange) {
+        debug_assert!(self
+            .mappings
+            .last()
+            .is_none_or(|last| last.generated.end() <= generated.start()));
+        self.mappings.push(Mapping {
+            generated,
+            original,
+        });
+    }
+
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    /// Returns the mapping that contains the specified offset of the generated text.
+    pub fn mapping_at(&self, generated: TextSize) -> Option<&Mapping> {
+        let idx = match self
+            .mappings
+            .binary_search_by_key(&generated, |mapping| mapping.generated.start())
+        {
+            Ok(idx) => idx,
+            Err(0) => return None,
+            Err(idx) => idx - 1,
+        };
+        let mapping = &self.mappings[idx];
+        if generated < mapping.generated.end() {
+            Some(mapping)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the offset in the original text that corresponds to an offset in the generated
+    /// text. Offsets inside text that was copied verbatim map to the exact original offset,
+    /// offsets inside rewritten text map to the start of the original range.
+    pub fn original_offset(&self, generated: TextSize) -> Option<TextSize> {
+        let mapping = self.mapping_at(generated)?;
+        if mapping.generated.len() == mapping.original.len() {
+            Some(mapping.original.start() + (generated - mapping.generated.start()))
+        } else {
+            Some(mapping.original.start())
+        }
+    }
+
+    /// Exports the map in the source map revision 3 JSON format. `source` is the name of the
+    /// original file and `file` the optional name of the generated file. Columns are counted in
+    /// UTF-16 code units, as the format requires.
+    pub fn to_json(
+        &self,
+        generated_text: &str,
+        original_text: &str,
+        source: &str,
+        file: Option<&str>,
+    ) -> String {
+        let mut generated_columns = Utf16Columns::new(generated_text);
+        let mut original_columns = Utf16Columns::new(original_text);
+        let mut encoder = MappingsEncoder::new();
+
+        for mapping in self.mappings.iter() {
+            encoder.add(
+                generated_columns.line_col(mapping.generated.start()),
+                original_columns.line_col(mapping.original.start()),
+            );
+
+            // Text that was copied verbatim can span multiple lines, every line needs its own
+            // segment.
+            if mapping.generated.len() == mapping.original.len() {
+                let first = generated_columns
+                    .index
+                    .line_col(mapping.generated.start())
+                    .line;
+                let last = generated_columns
+                    .index
+                    .line_col(mapping.generated.end())
+                    .line;
+                for line in first + 1..=last {
+                    let line_start = generated_columns.index.line_range(line).start();
+                    if line_start >= mapping.generated.end() {
+                        break;
+                    }
+                    let original =
+                        mapping.original.start() + (line_start - mapping.generated.start());
+                    encoder.add(
+                        generated_columns.line_col(line_start),
+                        original_columns.line_col(original),
+                    );
+                }
+            }
+        }
+
+        let json = JsonSourceMap {
+            version: 3,
+            file,
+            sources: vec![source],
+            names: Vec::new(),
+            mappings: encoder.finish(),
+        };
+        serde_json::to_string(&json).expect("source maps are always serializable")
+    }
+}
+
+#[derive(Serialize)]
+struct JsonSourceMap<'a> {
+    version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    sources: Vec<&'a str>,
+    names: Vec<&'a str>,
+    mappings: String,
+}
+
+/// Converts offsets to lines and columns in UTF-16 code units. Offsets are mostly converted in
+/// increasing order, so the column of the previous offset is reused for an offset further on the
+/// same line, which keeps long lines, e.g. of minified code, from being scanned over and over.
+struct Utf16Columns<'a> {
+    text: &'a str,
+    index: LineIndex,
+    /// The last converted offset and its column
+    last: (TextSize, u32),
+}
+
+impl<'a> Utf16Columns<'a> {
+    fn new(text: &'a str) -> Utf16Columns<'a> {
+        Utf16Columns {
+            text,
+            index: LineIndex::new(text),
+            last: (0.into(), 0),
+        }
+    }
+
+    /// Returns the line and the UTF-16 column of `offset`.
+    fn line_col(&mut self, offset: TextSize) -> (u32, u32) {
+        let LineCol { line, col } = self.index.line_col(offset);
+        let line_start = offset - col;
+        let (start, column) = match self.last {
+            (last, column) if line_start <= last && last <= offset => (last, column),
+            _ => (line_start, 0),
+        };
+        let column = column
+            + self.text[TextRange::new(start, offset)]
+                .encode_utf16()
+                .count() as u32;
+        self.last = (offset, column);
+        (line, column)
+    }
+}
+
+/// Encodes segments into the `mappings` field of a source map. Every field of a segment is
+/// stored relative to the same field of the previous segment as a base64 VLQ.
+struct MappingsEncoder {
+    result: String,
+    line: u32,
+    column: i64,
+    original_line: i64,
+    original_column: i64,
+    first_in_line: bool,
+}
+
+impl MappingsEncoder {
+    fn new() -> MappingsEncoder {
+        MappingsEncoder {
+            result: String::new(),
+            line: 0,
+            column: 0,
+            original_line: 0,
+            original_column: 0,
+            first_in_line: true,
+        }
+    }
+
+    /// Adds a segment that maps the generated line and column to the original line and column.
+    fn add(&mut self, generated: (u32, u32), original: (u32, u32)) {
+        let (line, column) = generated;
+        while self.line < line {
+            self.result.push(';');
+            self.line += 1;
+            self.column = 0;
+            self.first_in_line = true;
+        }
+        if !self.first_in_line {
+            self.result.push(',');
+        }
+        self.first_in_line = false;
+
+        let column = i64::from(column);
+        let original_line = i64::from(original.0);
+        let original_column = i64::from(original.1);
+        encode_vlq(&mut self.result, column - self.column);
+        encode_vlq(&mut self.result, 0);
+        encode_vlq(&mut self.result, original_line - self.original_line);
+        encode_vlq(&mut self.result, original_column - self.original_column);
+        self.column = column;
+        self.original_line = original_line;
+        self.original_column = original_column;
+    }
+
+    fn finish(self) -> String {
+        self.result
+    }
+}
+
+fn encode_vlq(buf: &mut String, value: i64) {
+    const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut value = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = value & 0b1_1111;
+        value >>= 5;
+        if value > 0 {
+            digit |= 0b10_0000;
+        }
+        buf.push(BASE64[digit as usize] as char);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: u32, end: u32) -> TextRange {
+        TextRange::new(start.into(), end.into())
+    }
+
+    #[test]
+    fn test_original_offset() {
+        let mut map = SourceMap::new();
+        map.add(range(0, 5), range(2, 7));
+        map.add(range(6, 8), range(10, 13));
+        assert_eq!(map.original_offset(3.into()), Some(5.into()));
+        assert_eq!(map.original_offset(5.into()), None);
+        assert_eq!(map.original_offset(7.into()), Some(10.into()));
+    }
+
+    #[test]
+    fn test_encode_vlq() {
+        let mut buf = String::new();
+        for &value in [0, 1, -1, 15, 16, -16, 1000].iter() {
+            encode_vlq(&mut buf, value);
+            buf.push(' ');
+        }
+        assert_eq!(buf, "A C D e gB hB w+B ");
+    }
+
+    #[test]
+    fn test_to_json() {
+        let original = "local  a=1\nreturn a";
+        let generated = "local a = 1\nreturn a\n";
+        let mut map = SourceMap::new();
+        map.add(range(0, 5), range(0, 5));
+        map.add(range(6, 7), range(7, 8));
+        map.add(range(8, 9), range(8, 9));
+        map.add(range(10, 11), range(9, 10));
+        map.add(range(12, 18), range(11, 17));
+        map.add(range(19, 20), range(18, 19));
+        assert_eq!(
+            map.to_json(generated, original, "main.lua", None),
+            r#"{"version":3,"sources":["main.lua"],"names":[],"mappings":"AAAA,MAAO,EAAC,EAAC;AACT,OAAO"}"#
+        );
+    }
+
+    #[test]
+    fn test_to_json_counts_utf16_columns() {
+        // `é` is two bytes and one UTF-16 code unit, `😀` four bytes and two code units
+        let original = "a='é😀'  b";
+        let generated = "a='é😀' b";
+        let mut map = SourceMap::new();
+        map.add(range(0, 10), range(0, 10));
+        map.add(range(11, 12), range(12, 13));
+        assert_eq!(
+            map.to_json(generated, original, "main.lua", None),
+            r#"{"version":3,"sources":["main.lua"],"names":[],"mappings":"AAAA,QAAS"}"#
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! Decoding of Lua source that is not known to be valid UTF-8.
+//!
+//! Lua itself treats source as bytes, so legacy files often contain Latin-1 or other non UTF-8
+//! encoded text in strings and comments. The syntax tree is built from a `str`, so invalid
+//! sequences are either rejected with their position or replaced by `U+FFFD`. Replacing changes
+//! the length of the text, `SourceText` keeps track of the replacements to map offsets in the
+//! tree back to offsets in the original bytes.
+
+use crate::{Chunk, Diagnostic, DiagnosticCode, LineCol, Parse, TextRange, TextSize};
+use std::{fmt, ops::Range, str};
+
+pub const INVALID_UTF8: DiagnosticCode = DiagnosticCode("W0004");
+pub const CONTROL_CHARACTER: DiagnosticCode = DiagnosticCode("W0005");
+
+/// The error returned by `SourceText::from_utf8` for bytes that are not valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvalidUtf8Error {
+    /// The offset of the first invalid byte
+    pub offset: usize,
+    /// The number of bytes of the invalid sequence, or `None` if the bytes end in the middle of a
+    /// sequence
+    pub len: Option<usize>,
+    /// The line and column of the first invalid byte, the column is measured in bytes
+    pub line_col: LineCol,
+}
+
+impl fmt::Display for InvalidUtf8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid UTF-8 at line {}, column {} (byte offset {})",
+            self.line_col.line + 1,
+            u32::from(self.line_col.col) + 1,
+            self.offset
+        )
+    }
+}
+
+impl std::error::Error for InvalidUtf8Error {}
+
+/// An invalid UTF-8 sequence that was replaced by `U+FFFD`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Replacement {
+    /// The range of the replacement character in the decoded text
+    pub range: TextRange,
+    /// The range of the invalid sequence in the original bytes
+    pub original: Range<usize>,
+    /// The bytes of the invalid sequence
+    pub bytes: Vec<u8>,
+}
+
+/// Text decoded from bytes, together with the invalid UTF-8 sequences that were replaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceText {
+    text: String,
+    replacements: Vec<Replacement>,
+}
+
+impl SourceText {
+    /// Decodes `bytes`, returning the position of the first invalid sequence if `bytes` are not
+    /// valid UTF-8.
+    pub fn from_utf8(bytes: &[u8]) -> Result<SourceText, InvalidUtf8Error> {
+        match str::from_utf8(bytes) {
+            Ok(text) => Ok(SourceText {
+                text: text.to_string(),
+                replacements: Vec::new(),
+            }),
+            Err(error) => {
+                let offset = error.valid_up_to();
+                let line_start = bytes[..offset]
+                    .iter()
+                    .rposition(|&b| b == b'\n')
+                    .map_or(0, |idx| idx + 1);
+                let line = bytes[..offset].iter().filter(|&&b| b == b'\n').count();
+                Err(InvalidUtf8Error {
+                    offset,
+                    len: error.error_len(),
+                    line_col: LineCol {
+                        line: line as u32,
+                        col: TextSize::from((offset - line_start) as u32),
+                    },
+                })
+            }
+        }
+    }
+
+    /// Decodes `bytes`, replacing every invalid sequence by `U+FFFD` like
+    /// `String::from_utf8_lossy`.
+    pub fn from_utf8_lossy(bytes: &[u8]) -> SourceText {
+        let mut text = String::with_capacity(bytes.len());
+        let mut replacements = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            match str::from_utf8(&bytes[offset..]) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    break;
+                }
+                Err(error) => {
+                    let valid_end = offset + error.valid_up_to();
+                    // The prefix was just validated by `from_utf8`
+                    text.push_str(str::from_utf8(&bytes[offset..valid_end]).unwrap());
+                    let invalid_end = error.error_len().map_or(bytes.len(), |len| valid_end + len);
+                    let start = TextSize::of(text.as_str());
+                    text.push(char::REPLACEMENT_CHARACTER);
+                    replacements.push(Replacement {
+                        range: TextRange::at(start, TextSize::of(char::REPLACEMENT_CHARACTER)),
+                        original: valid_end..invalid_end,
+                        bytes: bytes[valid_end..invalid_end].to_vec(),
+                    });
+                    offset = invalid_end;
+                }
+            }
+        }
+        SourceText { text, replacements }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    /// Returns the replaced sequences, in the order of the text.
+    pub fn replacements(&self) -> &[Replacement] {
+        &self.replacements
+    }
+
+    /// Returns the offset in the original bytes of `offset` in the decoded text. An offset inside
+    /// a replacement character maps to the start of the sequence it replaces.
+    pub fn original_offset(&self, offset: TextSize) -> usize {
+        let idx = self
+            .replacements
+            .partition_point(|replacement| replacement.range.end() <= offset);
+        if let Some(replacement) = self.replacements.get(idx) {
+            if replacement.range.start() < offset {
+                return replacement.original.start;
+            }
+        }
+        let (text_end, original_end) = match idx.checked_sub(1) {
+            Some(prev) => {
+                let prev = &self.replacements[prev];
+                (prev.range.end(), prev.original.end)
+            }
+            None => (TextSize::from(0), 0),
+        };
+        original_end + usize::from(offset - text_end)
+    }
+
+    /// Returns the range in the original bytes of `range` in the decoded text.
+    pub fn original_range(&self, range: TextRange) -> Range<usize> {
+        let start = self.original_offset(range.start());
+        let end = match self
+            .replacements
+            .iter()
+            .find(|replacement| replacement.range.contains(range.end()))
+        {
+            // The end lies inside a replacement character, include the whole sequence
+            Some(replacement) if range.end() > replacement.range.start() => {
+                replacement.original.end
+            }
+            _ => self.original_offset(range.end()),
+        };
+        start..end
+    }
+
+    /// Returns the offset in the decoded text of `offset` in the original bytes. An offset inside
+    /// a replaced sequence maps to the start of its replacement character.
+    pub fn text_offset(&self, offset: usize) -> TextSize {
+        let idx = self
+            .replacements
+            .partition_point(|replacement| replacement.original.end <= offset);
+        if let Some(replacement) = self.replacements.get(idx) {
+            if replacement.original.start < offset {
+                return replacement.range.start();
+            }
+        }
+        let (text_end, original_end) = match idx.checked_sub(1) {
+            Some(prev) => {
+                let prev = &self.replacements[prev];
+                (prev.range.end(), prev.original.end)
+            }
+            None => (TextSize::from(0), 0),
+        };
+        text_end + TextSize::from((offset - original_end) as u32)
+    }
+
+    /// Returns a warning for every replaced sequence and for every control character that isn't
+    /// whitespace, e.g. a NUL byte. Lua accepts both in strings and comments, but they usually
+    /// mean that the file isn't UTF-8 encoded or isn't a text file at all.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut replacements = self.replacements.iter().peekable();
+        for (idx, c) in self.text.char_indices() {
+            let range = TextRange::at(TextSize::from(idx as u32), TextSize::of(c));
+            if let Some(replacement) = replacements.next_if(|r| r.range == range) {
+                let bytes: String = replacement
+                    .bytes
+                    .iter()
+                    .map(|b| format!("\\x{:02X}", b))
+                    .collect();
+                diagnostics.push(Diagnostic::warning(
+                    INVALID_UTF8,
+                    range,
+                    format!("invalid UTF-8 sequence `{}`", bytes),
+                ));
+            } else if c.is_control() && !c.is_whitespace() {
+                diagnostics.push(Diagnostic::warning(
+                    CONTROL_CHARACTER,
+                    range,
+                    format!("control character U+{:04X}", c as u32),
+                ));
+            }
+        }
+        diagnostics
+    }
+}
+
+impl Chunk {
+    /// Parses `bytes` that may not be valid UTF-8, replacing invalid sequences. The offsets in
+    /// the tree are offsets in the returned `SourceText`.
+    pub fn parse_bytes(bytes: &[u8]) -> (Parse<Chunk>, SourceText) {
+        let source = SourceText::from_utf8_lossy(bytes);
+        (Chunk::parse(source.as_str()), source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_utf8() {
+        let source = SourceText::from_utf8(b"print('ok')").unwrap();
+        assert_eq!(source.as_str(), "print('ok')");
+
+        let error = SourceText::from_utf8(b"x = 1\ny = 'caf\xE9'").unwrap_err();
+        assert_eq!(error.offset, 14);
+        assert_eq!(error.len, Some(1));
+        assert_eq!(
+            error.to_string(),
+            "invalid UTF-8 at line 2, column 9 (byte offset 14)"
+        );
+        assert_eq!(SourceText::from_utf8(b"'\xE2\x82").unwrap_err().len, None);
+    }
+
+    #[test]
+    fn test_from_utf8_lossy() {
+        // Latin-1 `é` (1 byte) becomes a 3 byte replacement character
+        let bytes = b"s = 'caf\xE9' -- \xE9t\xE9\n";
+        let (parse, source) = Chunk::parse_bytes(bytes);
+        assert!(parse.errors().is_empty());
+        assert_eq!(source.as_str(), "s = 'caf\u{FFFD}' -- \u{FFFD}t\u{FFFD}\n");
+        assert_eq!(source.replacements().len(), 3);
+
+        let comment = TextRange::new(13.into(), 24.into());
+        assert_eq!(&source.as_str()[comment], "-- \u{FFFD}t\u{FFFD}\n");
+        assert_eq!(source.original_range(comment), 11..18);
+        assert_eq!(source.original_offset(9.into()), 8);
+        assert_eq!(source.text_offset(8), 8.into());
+        assert_eq!(source.text_offset(10), 12.into());
+        for offset in 0..=bytes.len() {
+            let text_offset = source.text_offset(offset);
+            assert!(source.as_str().is_char_boundary(text_offset.into()));
+            assert_eq!(source.original_offset(text_offset), offset);
+        }
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let source = SourceText::from_utf8_lossy(b"s = '\xE9\x00'\t\x01");
+        let diagnostics: Vec<_> = source
+            .diagnostics()
+            .into_iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.message))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (INVALID_UTF8, "invalid UTF-8 sequence `\\xE9`".to_string()),
+                (CONTROL_CHARACTER, "control character U+0000".to_string()),
+                (CONTROL_CHARACTER, "control character U+0001".to_string()),
+            ]
+        );
+        // Unlike a panic, the parser reports the stray control character
+        let (parse, _) = Chunk::parse_bytes(b"x = 1\x00");
+        assert_eq!(parse.errors().len(), 1);
+    }
+}
@@ -0,0 +1,373 @@
+//! Structural search and replace.
+//!
+//! A pattern is Lua code in which placeholders like `$name` stand for arbitrary code, e.g.
+//! `$f($args)` or `if $cond then $body end`. Tokens of the pattern must match the searched code
+//! exactly, apart from trivia. A placeholder matches the shortest non-empty sequence of tokens
+//! with balanced brackets and blocks after which the rest of the pattern matches.
+//!
+//! A placeholder that directly follows `then`, `do`, `else` or `repeat`, or that is directly
+//! followed by `end`, `else`, `elseif` or `until`, stands for a block of statements. Other
+//! placeholders stand for expressions or lists of expressions and never span multiple statements.
+//! A placeholder at the end of a pattern extends until the code matched by it can no longer be
+//! continued as an expression, so `return $x` matches all of `return a + b`. When the same
+//! placeholder occurs multiple times, every occurrence must match the same code.
+
+use crate::{
+    AstNode, Chunk,
+    SyntaxKind::{self, *},
+    TextEdit, TextEditBuilder, TextRange,
+};
+use std::{collections::HashMap, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsrError(String);
+
+impl fmt::Display for SsrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SsrError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatternElement {
+    Token { kind: SyntaxKind, text: String },
+    Placeholder { name: String, is_block: bool },
+}
+
+/// An occurrence of a pattern in a chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsrMatch {
+    pub range: TextRange,
+    /// The code matched by every placeholder, by name without the `$`
+    pub placeholders: HashMap<String, TextRange>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SsrMatcher {
+    pattern: Vec<PatternElement>,
+}
+
+struct Token {
+    kind: SyntaxKind,
+    range: TextRange,
+}
+
+impl SsrMatcher {
+    pub fn new(pattern: &str) -> Result<SsrMatcher, SsrError> {
+        let tokens: Vec<_> = crate::validation::significant_tokens(pattern);
+        let mut elements = Vec::new();
+        let mut idx = 0;
+        while idx < tokens.len() {
+            let token = &tokens[idx];
+            let text = &pattern[token.range];
+            if token.kind == DOLLAR {
+                let name = tokens
+                    .get(idx + 1)
+                    .filter(|name| name.kind == IDENT && name.range.start() == token.range.end())
+                    .ok_or_else(|| {
+                        SsrError(format!(
+                            "expected a placeholder name after `$` at offset {}",
+                            u32::from(token.range.start())
+                        ))
+                    })?;
+                let follows_block_start = idx > 0
+                    && matches!(
+                        tokens[idx - 1].kind,
+                        THEN_KW | DO_KW | ELSE_KW | REPEAT_KW
+                    );
+                let precedes_block_end = tokens.get(idx + 2).is_some_and(|next| {
+                    matches!(next.kind, END_KW | ELSE_KW | ELSEIF_KW | UNTIL_KW)
+                });
+                elements.push(PatternElement::Placeholder {
+                    name: pattern[name.range].to_string(),
+                    is_block: follows_block_start || precedes_block_end,
+                });
+                idx += 2;
+            } else {
+                elements.push(PatternElement::Token {
+                    kind: token.kind,
+                    text: text.to_string(),
+                });
+                idx += 1;
+            }
+        }
+        if elements.is_empty() {
+            return Err(SsrError("the pattern is empty".to_string()));
+        }
+        Ok(SsrMatcher { pattern: elements })
+    }
+
+    /// Returns all non-overlapping occurrences of the pattern in `chunk`, from left to right.
+    pub fn find(&self, chunk: &Chunk) -> Vec<SsrMatch> {
+        let text = chunk.syntax().text().to_string();
+        let tokens: Vec<_> = chunk
+            .syntax()
+            .descendants_with_tokens()
+            .filter_map(|element| element.into_token())
+            .filter(|token| !token.kind().is_trivia())
+            .map(|token| Token {
+                kind: token.kind(),
+                range: token.text_range(),
+            })
+            .collect();
+
+        let mut result = Vec::new();
+        let mut start = 0;
+        while start < tokens.len() {
+            let mut matcher = Matcher {
+                text: &text,
+                tokens: &tokens,
+                bindings: HashMap::new(),
+            };
+            match matcher.match_elements(&self.pattern, start) {
+                Some(end) => {
+                    result.push(SsrMatch {
+                        range: TextRange::new(
+                            tokens[start].range.start(),
+                            tokens[end - 1].range.end(),
+                        ),
+                        placeholders: matcher.bindings,
+                    });
+                    start = end;
+                }
+                None => start += 1,
+            }
+        }
+        result
+    }
+
+    /// Returns an edit that replaces every occurrence of the pattern in `chunk` by `template`,
+    /// in which every `$name` is substituted by the code matched by the placeholder `name`.
+    pub fn replace(&self, chunk: &Chunk, template: &str) -> TextEdit {
+        let text = chunk.syntax().text().to_string();
+        let mut builder = TextEditBuilder::default();
+        for ssr_match in self.find(chunk) {
+            builder.replace(
+                ssr_match.range,
+                substitute(template, &text, &ssr_match.placeholders),
+            );
+        }
+        builder.finish()
+    }
+}
+
+fn substitute(template: &str, text: &str, placeholders: &HashMap<String, TextRange>) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(dollar) = rest.find('$') {
+        result.push_str(&rest[..dollar]);
+        let name_len = rest[dollar + 1..]
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len() - dollar - 1);
+        let name = &rest[dollar + 1..dollar + 1 + name_len];
+        match placeholders.get(name) {
+            Some(&range) => result.push_str(&text[range]),
+            None => result.push_str(&rest[dollar..=dollar + name_len]),
+        }
+        rest = &rest[dollar + 1 + name_len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+struct Matcher<'a> {
+    text: &'a str,
+    tokens: &'a [Token],
+    bindings: HashMap<String, TextRange>,
+}
+
+impl<'a> Matcher<'a> {
+    /// Matches `elements` against the tokens starting at `start` and returns the index of the
+    /// first token after the match.
+    fn match_elements(&mut self, elements: &[PatternElement], start: usize) -> Option<usize> {
+        let (element, rest) = match elements.split_first() {
+            Some(split) => split,
+            None => return Some(start),
+        };
+        match element {
+            PatternElement::Token { kind, text } => {
+                let token = self.tokens.get(start)?;
+                if token.kind == *kind && &self.text[token.range] == text {
+                    self.match_elements(rest, start + 1)
+                } else {
+                    None
+                }
+            }
+            PatternElement::Placeholder { name, is_block } => {
+                for end in self.balanced_ends(start, *is_block) {
+                    if rest.is_empty() && !self.ends_expression(end) {
+                        continue;
+                    }
+                    let range = TextRange::new(
+                        self.tokens[start].range.start(),
+                        self.tokens[end - 1].range.end(),
+                    );
+                    if let Some(&bound) = self.bindings.get(name) {
+                        if self.text[bound] != self.text[range] {
+                            continue;
+                        }
+                    }
+                    let previous = self.bindings.insert(name.clone(), range);
+                    if let Some(end) = self.match_elements(rest, end) {
+                        return Some(end);
+                    }
+                    match previous {
+                        Some(previous) => self.bindings.insert(name.clone(), previous),
+                        None => self.bindings.remove(name),
+                    };
+                }
+                None
+            }
+        }
+    }
+
+    /// Returns the ends of all non-empty token sequences starting at `start` in which brackets
+    /// and blocks are balanced, from short to long. Unless `is_block` is set, the sequences end
+    /// before the first token that starts a new statement.
+    fn balanced_ends(&self, start: usize, is_block: bool) -> Vec<usize> {
+        let mut result = Vec::new();
+        let mut depth = 0;
+        let mut awaiting_do = false;
+        for (idx, token) in self.tokens.iter().enumerate().skip(start) {
+            if !is_block && depth == 0 && self.starts_statement(start, idx) {
+                break;
+            }
+            match token.kind {
+                L_PAREN | L_CURLY | L_BRACKET | FUNCTION_KW | IF_KW | REPEAT_KW => depth += 1,
+                WHILE_KW | FOR_KW => {
+                    depth += 1;
+                    awaiting_do = true;
+                }
+                DO_KW if awaiting_do => awaiting_do = false,
+                DO_KW => depth += 1,
+                R_PAREN | R_CURLY | R_BRACKET | END_KW | UNTIL_KW => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                }
+                _ => (),
+            }
+            if depth == 0 && !awaiting_do {
+                result.push(idx + 1);
+            }
+        }
+        result
+    }
+
+    /// Returns true if the token at `idx` starts a new statement when it is not nested in brackets
+    /// or blocks, given that the code starts at `start`.
+    fn starts_statement(&self, start: usize, idx: usize) -> bool {
+        let prev = if idx > start {
+            Some(self.tokens[idx - 1].kind)
+        } else {
+            None
+        };
+        match self.tokens[idx].kind {
+            LOCAL_KW | RETURN_KW | BREAK_KW | GOTO_KW | THEN_KW | ELSE_KW | ELSEIF_KW | IN_KW
+            | SEMI | DO_KW | WHILE_KW | FOR_KW | REPEAT_KW | IF_KW => true,
+            // A single `=` is an assignment, unlike `==`, `<=` and `>=`
+            EQ => {
+                let next = self.tokens.get(idx + 1).map(|t| t.kind);
+                prev != Some(EQ) && prev != Some(LT) && prev != Some(GT) && next != Some(EQ)
+            }
+            // Two operands without an operator in between belong to different statements
+            IDENT | INT_NUMBER | FLOAT_NUMBER | NIL_KW | TRUE_KW | FALSE_KW | FUNCTION_KW
+            | NOT_KW => {
+                idx > start
+                    && (ends_operand(self.tokens[idx - 1].kind) || self.is_vararg_end(idx))
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns true if the token before `end` is the last dot of a `...`.
+    fn is_vararg_end(&self, end: usize) -> bool {
+        end >= 3 && self.tokens[end - 3..end].iter().all(|token| token.kind == DOT)
+    }
+
+    /// Returns true if the tokens before `end` form code that cannot be continued as an
+    /// expression by the token at `end`.
+    fn ends_expression(&self, end: usize) -> bool {
+        let last = self.tokens[end - 1].kind;
+        if !(ends_operand(last) || self.is_vararg_end(end)) {
+            return false;
+        }
+        let next = match self.tokens.get(end) {
+            Some(next) => next.kind,
+            None => return true,
+        };
+        match next {
+            DOT | COLON | L_PAREN | L_BRACKET | L_CURLY | STRING | PLUS | MINUS | STAR | SLASH
+            | PERCENT | CARET | LT | GT | EQ | NEQ | AMP | PIPE | TILDE | AND_KW | OR_KW => {
+                // `a = b` is an assignment, `a == b` a comparison
+                next == EQ && self.tokens.get(end + 1).is_none_or(|t| t.kind != EQ)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Returns true if a token of this kind can be the last token of an operand.
+fn ends_operand(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        IDENT
+            | INT_NUMBER
+            | FLOAT_NUMBER
+            | STRING
+            | R_PAREN
+            | R_BRACKET
+            | R_CURLY
+            | END_KW
+            | NIL_KW
+            | TRUE_KW
+            | FALSE_KW
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flat_chunk;
+
+    fn find(pattern: &str, text: &str) -> Vec<String> {
+        let chunk = flat_chunk(text);
+        SsrMatcher::new(pattern)
+            .unwrap()
+            .find(&chunk)
+            .into_iter()
+            .map(|ssr_match| text[ssr_match.range].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("$f($a)", "x = a.b(c) d(e, f)"), vec!["a.b(c)", "d(e, f)"]);
+        assert_eq!(
+            find("if $c then $b end", "if a then if b then c() end end"),
+            vec!["if a then if b then c() end end"]
+        );
+        assert_eq!(find("return $x", "return a + b.c end"), vec!["return a + b.c"]);
+        assert_eq!(find("$x == $x", "a == a b == c"), vec!["a == a"]);
+        assert_eq!(find("$x = $y", "a = b == c"), vec!["a = b == c"]);
+    }
+
+    #[test]
+    fn test_replace() {
+        let text = "local s = string.format('%d', n)\nprint(string.format(x))";
+        let chunk = flat_chunk(text);
+        let edit = SsrMatcher::new("string.format($args)")
+            .unwrap()
+            .replace(&chunk, "fmt($args)");
+        assert_eq!(edit.apply(text), "local s = fmt('%d', n)\nprint(fmt(x))");
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(SsrMatcher::new("").is_err());
+        assert!(SsrMatcher::new("f($ a)").is_err());
+    }
+}
@@ -0,0 +1,365 @@
+//! Indexes the globals and module fields that a chunk defines, the symbols that other files can
+//! refer to.
+//!
+//! A module field is a field of the module table: the table constructor or the local that the
+//! chunk returns, e.g. `foo` in `local M = {} function M.foo() end return M`. A global is a
+//! global variable or a field of one, e.g. `foo` and `string.trim` in `foo = 1` and
+//! `function string.trim(s) end`.
+
+use crate::{
+    ast::{
+        AssignStmt, Expr, FunctionName, FunctionStmt, LocalAssignStmt, NameRef, TableExpr,
+        TableField,
+    },
+    resolve_names, AstNode, Chunk, LocalId, NameResolution,
+    SyntaxKind::*,
+    SyntaxNode, TextRange,
+};
+
+/// Whether a symbol is reached through a global variable or through the module table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolScope {
+    Global,
+    Module,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    /// A function, e.g. `function M.foo() end` or `M.foo = function() end`
+    Function,
+    /// A function with an implicit `self` parameter, e.g. `function M:foo() end`
+    Method,
+    /// A table constructor, e.g. `M.config = {}`
+    Table,
+    /// Any other value
+    Variable,
+}
+
+/// A definition of a global or a module field.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Symbol {
+    /// The name as it is written in the definition, e.g. `M.foo` or `M:foo`
+    pub name: String,
+    pub scope: SymbolScope,
+    /// The names that lead to the symbol from its scope, e.g. `["foo"]` for `M.foo` and
+    /// `["string", "trim"]` for the global `string.trim`
+    pub path: Vec<String>,
+    pub kind: SymbolKind,
+    /// The range of the definition, e.g. of the assignment or the table field
+    pub range: TextRange,
+    /// The range of the name in the definition
+    pub name_range: TextRange,
+}
+
+/// The symbols that a chunk defines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolIndex {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolIndex {
+    /// Returns the symbols in the order of their definitions.
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Returns the definitions of the symbol at `path` in `scope`, e.g. of `foo` for `M.foo`.
+    pub fn lookup<'a, S: AsRef<str>>(
+        &'a self,
+        scope: SymbolScope,
+        path: &'a [S],
+    ) -> impl Iterator<Item = &'a Symbol> + 'a {
+        self.symbols.iter().filter(move |symbol| {
+            symbol.scope == scope
+                && symbol.path.len() == path.len()
+                && symbol.path.iter().zip(path).all(|(a, b)| a == b.as_ref())
+        })
+    }
+}
+
+/// Returns the globals and module fields that `chunk` defines, by assignments, function
+/// statements and the named fields of table constructors that are assigned to them. Writes to a
+/// global inside functions are definitions too, e.g. of `count` in `function f() count = 1 end`.
+pub fn symbol_index(chunk: &Chunk) -> SymbolIndex {
+    let resolution = resolve_names(chunk);
+    let mut indexer = Indexer {
+        module: module_local(chunk, &resolution),
+        resolution,
+        symbols: Vec::new(),
+    };
+    if let Some(Expr::TableExpr(table)) = single_return(chunk) {
+        indexer.define_fields(SymbolScope::Module, &[], &table);
+    }
+    if let Some(Expr::TableExpr(table)) = indexer
+        .module
+        .and_then(|id| initializer(&indexer.resolution, id))
+    {
+        indexer.define_fields(SymbolScope::Module, &[], &table);
+    }
+    for node in chunk.syntax().descendants() {
+        match node.kind() {
+            FUNCTION_STMT => {
+                let stmt = FunctionStmt::cast(node).unwrap();
+                if let Some(name) = stmt.function_name() {
+                    indexer.define_function(&stmt, &name);
+                }
+            }
+            ASSIGN_STMT => {
+                let stmt = AssignStmt::cast(node.clone()).unwrap();
+                let targets = stmt.targets().into_iter().flat_map(|list| list.exprs());
+                let mut values = stmt.values().into_iter().flat_map(|list| list.exprs());
+                for target in targets {
+                    let value = values.next();
+                    indexer.define_target(&target, value.as_ref(), node.text_range());
+                }
+            }
+            _ => {}
+        }
+    }
+    indexer
+        .symbols
+        .sort_by_key(|symbol| (symbol.range.start(), symbol.name_range.start()));
+    SymbolIndex {
+        symbols: indexer.symbols,
+    }
+}
+
+struct Indexer {
+    resolution: NameResolution,
+    /// The local that holds the module table
+    module: Option<LocalId>,
+    symbols: Vec<Symbol>,
+}
+
+impl Indexer {
+    fn define_function(&mut self, stmt: &FunctionStmt, name: &FunctionName) {
+        let root = match name.name_ref() {
+            Some(root) => root,
+            None => return,
+        };
+        let mut fields = Vec::new();
+        let mut is_method = false;
+        for token in name.syntax().children_with_tokens() {
+            match token.kind() {
+                IDENT => fields.push(token.to_string()),
+                COLON => is_method = true,
+                _ => {}
+            }
+        }
+        let (scope, path) = match self.scope(&root, fields) {
+            Some(scope) => scope,
+            None => return,
+        };
+        self.symbols.push(Symbol {
+            name: non_trivia_text(name.syntax()),
+            scope,
+            path,
+            kind: if is_method {
+                SymbolKind::Method
+            } else {
+                SymbolKind::Function
+            },
+            range: stmt.syntax().text_range(),
+            name_range: name.syntax().text_range(),
+        });
+    }
+
+    fn define_target(&mut self, target: &Expr, value: Option<&Expr>, range: TextRange) {
+        let mut path = match target.full_index_path() {
+            Some(path) => path,
+            None => return,
+        };
+        let root = match target.chain_root() {
+            Expr::NameRef(root) => root,
+            _ => return,
+        };
+        let (scope, path) = match self.scope(&root, path.split_off(1)) {
+            Some(scope) => scope,
+            None => return,
+        };
+        self.symbols.push(Symbol {
+            name: non_trivia_text(target.syntax()),
+            scope,
+            path: path.clone(),
+            kind: value_kind(value),
+            range,
+            name_range: target.syntax().text_range(),
+        });
+        if let Some(Expr::TableExpr(table)) = value {
+            self.define_fields(scope, &path, table);
+        }
+    }
+
+    /// Defines the named fields of `table`, the value of the symbol at `path`.
+    fn define_fields(&mut self, scope: SymbolScope, path: &[String], table: &TableExpr) {
+        for field in table.fields() {
+            let field = match field {
+                TableField::NamedField(field) => field,
+                _ => continue,
+            };
+            let name = match field.name() {
+                Some(name) => name,
+                None => continue,
+            };
+            let mut field_path = path.to_vec();
+            field_path.push(name.text().to_string());
+            let value = field.value();
+            self.symbols.push(Symbol {
+                name: name.text().to_string(),
+                scope,
+                path: field_path.clone(),
+                kind: value_kind(value.as_ref()),
+                range: field.syntax().text_range(),
+                name_range: name.text_range(),
+            });
+            if let Some(Expr::TableExpr(table)) = value {
+                self.define_fields(scope, &field_path, &table);
+            }
+        }
+    }
+
+    /// Returns the scope and path of the symbol at the `fields` of `root`, or `None` if `root` is
+    /// a local other than the module table.
+    fn scope(&self, root: &NameRef, fields: Vec<String>) -> Option<(SymbolScope, Vec<String>)> {
+        match self.resolution.resolve(root) {
+            None => {
+                let mut path = vec![root.syntax().text().to_string()];
+                path.extend(fields);
+                Some((SymbolScope::Global, path))
+            }
+            // Assigning the module local itself doesn't define a field
+            Some(local) if Some(local) == self.module && !fields.is_empty() => {
+                Some((SymbolScope::Module, fields))
+            }
+            Some(_) => None,
+        }
+    }
+}
+
+/// Returns the value that the declaration of the local `id` initializes it with, e.g. `{}` for
+/// `local M = {}`.
+pub(crate) fn initializer(resolution: &NameResolution, id: LocalId) -> Option<Expr> {
+    let local = resolution.local(id);
+    let stmt = LocalAssignStmt::cast(local.declaration.clone())?;
+    let names = stmt.name_list()?.names();
+    let mut values = stmt.expr_list().into_iter().flat_map(|list| list.exprs());
+    for name in names {
+        let value = values.next();
+        if name.syntax().text_range() == local.range {
+            return value;
+        }
+    }
+    None
+}
+
+/// Returns the expression if the chunk ends with a `return` of a single expression.
+fn single_return(chunk: &Chunk) -> Option<Expr> {
+    let mut exprs = chunk.module_returns()?.exprs();
+    match (exprs.next(), exprs.next()) {
+        (Some(expr), None) => Some(expr),
+        _ => None,
+    }
+}
+
+/// Returns the local that the chunk returns as its module table, e.g. `M` in `return M`.
+pub(crate) fn module_local(chunk: &Chunk, resolution: &NameResolution) -> Option<LocalId> {
+    match single_return(chunk)? {
+        Expr::NameRef(name_ref) => resolution.resolve(&name_ref),
+        _ => None,
+    }
+}
+
+/// Returns the text of `node` without whitespace and comments, e.g. `M.foo` for `M . foo`.
+fn non_trivia_text(node: &SyntaxNode) -> String {
+    node.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+        .map(|token| token.text().to_string())
+        .collect()
+}
+
+fn value_kind(value: Option<&Expr>) -> SymbolKind {
+    match value {
+        Some(Expr::FunctionExpr(_)) => SymbolKind::Function,
+        Some(Expr::TableExpr(_)) => SymbolKind::Table,
+        _ => SymbolKind::Variable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(text: &str) -> Vec<(SymbolScope, String, SymbolKind, &str)> {
+        symbol_index(&Chunk::parse(text).tree())
+            .symbols()
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.scope,
+                    symbol.path.join("."),
+                    symbol.kind,
+                    &text[symbol.name_range],
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_module_fields() {
+        use SymbolKind::*;
+        use SymbolScope::Module;
+        let text = "local M = { version = 1, util = { trim = function() end } }\n\
+                    local helper = {}\n\
+                    function M.foo() M.count = 0 end\n\
+                    function M:bar() end\n\
+                    M.config, helper.x = {}, 1\n\
+                    M['baz'] = f\n\
+                    function helper.y() end\n\
+                    return M";
+        assert_eq!(
+            symbols(text),
+            vec![
+                (Module, "version".to_string(), Variable, "version"),
+                (Module, "util".to_string(), Table, "util"),
+                (Module, "util.trim".to_string(), Function, "trim"),
+                (Module, "foo".to_string(), Function, "M.foo"),
+                (Module, "count".to_string(), Variable, "M.count"),
+                (Module, "bar".to_string(), Method, "M:bar"),
+                (Module, "config".to_string(), Table, "M.config"),
+                (Module, "baz".to_string(), Variable, "M['baz']"),
+            ]
+        );
+        assert_eq!(
+            symbols("return { f = function() end }"),
+            vec![(Module, "f".to_string(), Function, "f")]
+        );
+    }
+
+    #[test]
+    fn test_globals() {
+        use SymbolKind::*;
+        use SymbolScope::Global;
+        let text = "function string.trim(s) end\n\
+                    count = 0\n\
+                    local function inc() count = count + 1 end\n\
+                    config = { debug = true }\n\
+                    local x\n\
+                    x = 1";
+        let index = symbol_index(&Chunk::parse(text).tree());
+        assert_eq!(
+            symbols(text),
+            vec![
+                (Global, "string.trim".to_string(), Function, "string.trim"),
+                (Global, "count".to_string(), Variable, "count"),
+                (Global, "count".to_string(), Variable, "count"),
+                (Global, "config".to_string(), Table, "config"),
+                (Global, "config.debug".to_string(), Variable, "debug"),
+            ]
+        );
+        assert_eq!(index.lookup(Global, &["count"]).count(), 2);
+        assert_eq!(index.lookup(Global, &["string", "trim"]).count(), 1);
+        assert_eq!(index.lookup(SymbolScope::Module, &["count"]).count(), 0);
+        assert_eq!(index.symbols()[0].name, "string.trim");
+    }
+}
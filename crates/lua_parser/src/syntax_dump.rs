@@ -0,0 +1,278 @@
+//! Textual representations of syntax trees, for use outside of Rust, e.g. by command line tools,
+//! and for golden files.
+//!
+//! All representations are written while walking the tree, so deeply nested trees don't
+//! overflow the stack.
+
+use crate::{NodeOrToken, Parse, SyntaxElement, SyntaxError, SyntaxNode, WalkEvent};
+use std::{
+    fmt::{self, Write},
+    io::{self, IsTerminal},
+};
+
+const NODE_COLOR: &str = "\x1b[1;34m";
+const TOKEN_COLOR: &str = "\x1b[36m";
+const TRIVIA_COLOR: &str = "\x1b[90m";
+const ERROR_COLOR: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+/// Options for `Parse::debug_dump_with_config`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpConfig {
+    /// Whether to write the byte range of every node and token, e.g. `NAME_REF@4..5`. Without the
+    /// ranges, an edit only changes the lines of the dump that belong to the edited elements.
+    pub ranges: bool,
+}
+
+impl Default for DumpConfig {
+    fn default() -> DumpConfig {
+        DumpConfig { ranges: true }
+    }
+}
+
+impl<T> Parse<T> {
+    /// Returns a textual representation of the tree like `debug_dump`, but the text of every
+    /// token is written in full, with special characters escaped, e.g. `STRING "'a\nb'"`.
+    /// `debug_dump` shortens long tokens, which hides changes to them in golden files.
+    pub fn debug_dump_with_config(&self, config: &DumpConfig) -> String {
+        let write_element = |buf: &mut String, element: &SyntaxElement| {
+            write!(buf, "{:?}", element.kind())?;
+            if config.ranges {
+                write!(buf, "@{:?}", element.text_range())?;
+            }
+            match element {
+                NodeOrToken::Node(_) => Ok(()),
+                NodeOrToken::Token(token) => write!(buf, " {:?}", token.text()),
+            }
+        };
+        dump_tree(
+            &self.syntax_node(),
+            self.errors(),
+            write_element,
+            write_error,
+        )
+    }
+
+    /// Returns `debug_dump` with ANSI colors for the node kinds, the token kinds and the errors,
+    /// for debugging in a terminal. The colors are left out if stdout is not a terminal, e.g. if
+    /// the output is redirected to a file.
+    pub fn debug_dump_colored(&self) -> String {
+        if io::stdout().is_terminal() {
+            self.debug_dump_with_colors()
+        } else {
+            self.debug_dump()
+        }
+    }
+
+    fn debug_dump_with_colors(&self) -> String {
+        let write_element = |buf: &mut String, element: &SyntaxElement| {
+            let kind = format!("{:?}", element.kind());
+            let (color, debug) = match element {
+                NodeOrToken::Node(node) => (NODE_COLOR, format!("{:?}", node)),
+                NodeOrToken::Token(token) if token.kind().is_trivia() => {
+                    (TRIVIA_COLOR, format!("{:?}", token))
+                }
+                NodeOrToken::Token(token) => (TOKEN_COLOR, format!("{:?}", token)),
+            };
+            // The debug representation starts with the kind, followed by the range and the text
+            write!(buf, "{}{}{}{}", color, kind, RESET, &debug[kind.len()..])
+        };
+        let write_colored_error = |buf: &mut String, error: &SyntaxError| {
+            write!(buf, "{}", ERROR_COLOR)?;
+            write_error(buf, error)?;
+            write!(buf, "{}", RESET)
+        };
+        dump_tree(
+            &self.syntax_node(),
+            self.errors(),
+            write_element,
+            write_colored_error,
+        )
+    }
+}
+
+pub(crate) fn write_error(buf: &mut String, error: &SyntaxError) -> fmt::Result {
+    write!(buf, "err: `{}`", error)
+}
+
+/// Writes every element of the tree on its own line, indented by its depth, with the errors
+/// after the token they belong to. `write_element` and `write_error` write a single element or
+/// error without the line break.
+pub(crate) fn dump_tree(
+    root: &SyntaxNode,
+    errors: &[SyntaxError],
+    mut write_element: impl FnMut(&mut String, &SyntaxElement) -> fmt::Result,
+    mut write_error: impl FnMut(&mut String, &SyntaxError) -> fmt::Result,
+) -> String {
+    let mut errors = errors.to_vec();
+    errors.sort_by_key(|e| e.location().offset());
+    let mut err_pos = 0;
+    let mut level = 0;
+    let mut buf = String::new();
+    macro_rules! indent {
+        () => {
+            for _ in 0..level {
+                buf.push_str("  ");
+            }
+        };
+    }
+
+    for event in root.preorder_with_tokens() {
+        match event {
+            WalkEvent::Enter(element) => {
+                indent!();
+                write_element(&mut buf, &element).unwrap();
+                buf.push('\n');
+                if let NodeOrToken::Token(token) = element {
+                    let off = token.text_range().end();
+                    while err_pos < errors.len() && errors[err_pos].location().offset() <= off {
+                        indent!();
+                        write_error(&mut buf, &errors[err_pos]).unwrap();
+                        buf.push('\n');
+                        err_pos += 1;
+                    }
+                }
+                level += 1;
+            }
+            WalkEvent::Leave(_) => level -= 1,
+        }
+    }
+
+    assert_eq!(level, 0);
+    for err in errors[err_pos..].iter() {
+        write_error(&mut buf, err).unwrap();
+        buf.push('\n');
+    }
+
+    buf
+}
+
+/// Writes the tree as an S-expression, e.g. `(CHUNK (BLOCK (EXPR_STMT ...)))`. Tokens are written
+/// as their kind followed by their text; whitespace and comments are left out.
+pub fn syntax_tree_to_sexpr(node: &SyntaxNode) -> String {
+    let mut buf = String::new();
+    for event in node.preorder_with_tokens() {
+        match event {
+            WalkEvent::Enter(NodeOrToken::Node(node)) => {
+                if !buf.is_empty() {
+                    buf.push(' ');
+                }
+                write!(buf, "({:?}", node.kind()).unwrap();
+            }
+            WalkEvent::Enter(NodeOrToken::Token(token)) if !token.kind().is_trivia() => {
+                write!(buf, " ({:?} {:?})", token.kind(), token.text()).unwrap();
+            }
+            WalkEvent::Leave(NodeOrToken::Node(_)) => buf.push(')'),
+            _ => (),
+        }
+    }
+    buf
+}
+
+/// Serializes the tree to JSON. Every element is an object with a `kind` and the byte offsets of
+/// its `range`; nodes have `children` and tokens have their `text`. Whitespace and comments are
+/// included, so the text of the tokens adds up to the source text.
+pub fn syntax_tree_to_json(node: &SyntaxNode) -> String {
+    let mut buf = String::new();
+    // Whether the innermost open node already has a child, i.e. whether a comma is needed.
+    let mut has_children = vec![false];
+    for event in node.preorder_with_tokens() {
+        let element = match event {
+            WalkEvent::Enter(element) => element,
+            WalkEvent::Leave(NodeOrToken::Node(_)) => {
+                has_children.pop();
+                buf.push_str("]}");
+                continue;
+            }
+            WalkEvent::Leave(NodeOrToken::Token(_)) => continue,
+        };
+        if let Some(has_children) = has_children.last_mut() {
+            if *has_children {
+                buf.push(',');
+            }
+            *has_children = true;
+        }
+        let range = element.text_range();
+        write!(
+            buf,
+            "{{\"kind\":\"{:?}\",\"range\":[{},{}],",
+            element.kind(),
+            u32::from(range.start()),
+            u32::from(range.end())
+        )
+        .unwrap();
+        match element {
+            NodeOrToken::Node(_) => {
+                buf.push_str("\"children\":[");
+                has_children.push(false);
+            }
+            NodeOrToken::Token(token) => {
+                let text = serde_json::to_string(token.text()).expect("strings are serializable");
+                write!(buf, "\"text\":{}}}", text).unwrap();
+            }
+        }
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chunk;
+
+    #[test]
+    fn test_dump_with_config() {
+        let parse = Chunk::parse("s = [[a very long string that is \"cut\"\n]]");
+        let dump = parse.debug_dump_with_config(&DumpConfig { ranges: false });
+        let literal = r#"LITERAL
+          STRING "[[a very long string that is \"cut\"\n]]"
+"#;
+        assert!(dump.ends_with(literal), "{}", dump);
+        assert!(!parse.debug_dump().contains("cut"));
+
+        let dump = parse.debug_dump_with_config(&DumpConfig::default());
+        assert!(dump.starts_with("CHUNK@0..41\n  BLOCK@0..41\n"), "{}", dump);
+    }
+
+    #[test]
+    fn test_colored_dump() {
+        let parse = Chunk::parse("f(");
+        let dump = parse.debug_dump_with_colors();
+        assert!(
+            dump.starts_with("\x1b[1;34mCHUNK\x1b[0m@0..2\n"),
+            "{:?}",
+            dump
+        );
+        assert!(
+            dump.contains("\x1b[36mIDENT\x1b[0m@0..1 \"f\"\n"),
+            "{:?}",
+            dump
+        );
+        assert!(dump.contains("\x1b[31merr: `"), "{:?}", dump);
+    }
+
+    #[test]
+    fn test_sexpr() {
+        let parse = Chunk::parse("f(1) -- call\n");
+        assert_eq!(
+            syntax_tree_to_sexpr(&parse.syntax_node()),
+            r#"(CHUNK (BLOCK (EXPR_STMT (CALL_EXPR (NAME_REF (IDENT "f")) (ARG_LIST (L_PAREN "(") (LITERAL (INT_NUMBER "1")) (R_PAREN ")"))))))"#
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let parse = Chunk::parse("x = \"a\\\"\"");
+        let json = syntax_tree_to_json(&parse.syntax_node());
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["kind"], "CHUNK");
+        assert_eq!(value["range"], serde_json::json!([0, 9]));
+        let stmt = &value["children"][0]["children"][0];
+        assert_eq!(stmt["kind"], "ASSIGN_STMT");
+        assert_eq!(stmt["children"][1]["text"], " ");
+        assert_eq!(
+            stmt["children"][4]["children"][0]["children"][0]["text"],
+            "\"a\\\"\""
+        );
+    }
+}
@@ -1,34 +1,33 @@
-use crate::{ParseError, TextRange, TextUnit};
-use std::cmp::min;
+use crate::{SyntaxKind, TextRange, TextSize, TokenSet};
 use std::fmt;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Location {
-    Offset(TextUnit),
+    Offset(TextSize),
     Range(TextRange),
 }
 
-impl Into<Location> for TextUnit {
-    fn into(self) -> Location {
-        Location::Offset(self)
+impl From<TextSize> for Location {
+    fn from(val: TextSize) -> Self {
+        Location::Offset(val)
     }
 }
 
-impl Into<Location> for TextRange {
-    fn into(self) -> Location {
-        Location::Range(self)
+impl From<TextRange> for Location {
+    fn from(val: TextRange) -> Self {
+        Location::Range(val)
     }
 }
 
 impl Location {
-    pub fn offset(&self) -> TextUnit {
+    pub fn offset(&self) -> TextSize {
         match &self {
             Location::Offset(offset) => *offset,
             Location::Range(range) => range.start(),
         }
     }
 
-    pub fn add_offset(&self, plus_offset: TextUnit, minus_offset: TextUnit) -> Location {
+    pub fn add_offset(&self, plus_offset: TextSize, minus_offset: TextSize) -> Location {
         match &self {
             Location::Range(range) => Location::Range(range + plus_offset - minus_offset),
             Location::Offset(offset) => Location::Offset(offset + plus_offset - minus_offset),
@@ -65,6 +64,14 @@ impl fmt::Display for SyntaxError {
     }
 }
 
+impl std::error::Error for SyntaxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            SyntaxErrorKind::ParseError(error) => Some(error),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SyntaxErrorKind {
     ParseError(ParseError),
@@ -74,7 +81,102 @@ impl fmt::Display for SyntaxErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use self::SyntaxErrorKind::*;
         match self {
-            ParseError(msg) => write!(f, "{}", msg.0),
+            ParseError(err) => err.fmt(f),
+        }
+    }
+}
+
+/// An error encountered while parsing. Next to a message it holds the set of tokens that would
+/// have been valid at the point of failure so tools can act on it without parsing the message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParseError {
+    pub message: String,
+    pub expected: TokenSet,
+}
+
+impl ParseError {
+    pub fn new(message: impl Into<String>) -> ParseError {
+        ParseError {
+            message: message.into(),
+            expected: TokenSet::EMPTY,
+        }
+    }
+
+    /// Constructs an error which states which tokens were expected, e.g.
+    /// `expected 'then', '=' or '('`
+    pub fn expected(expected: TokenSet) -> ParseError {
+        ParseError {
+            message: String::new(),
+            expected,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if self.expected.is_empty() {
+            return Ok(());
+        }
+        if !self.message.is_empty() {
+            f.write_str(", ")?;
         }
+        f.write_str("expected ")?;
+        let expected: Vec<_> = self.expected.iter().map(describe_kind).collect();
+        for (idx, kind) in expected.iter().enumerate() {
+            if idx > 0 {
+                let separator = if idx + 1 == expected.len() { " or " } else { ", " };
+                f.write_str(separator)?;
+            }
+            f.write_str(kind)?;
+        }
+        Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl std::error::Error for ParseError {}
+
+/// Returns a user facing description of a kind of token.
+fn describe_kind(kind: SyntaxKind) -> String {
+    if let Some(text) = kind.token_text() {
+        return format!("'{}'", text);
+    }
+    let description = match kind {
+        SyntaxKind::IDENT => "identifier",
+        SyntaxKind::INT_NUMBER | SyntaxKind::FLOAT_NUMBER => "number",
+        SyntaxKind::STRING => "string",
+        SyntaxKind::EOF => "end of file",
+        _ => return format!("{:?}", kind),
+    };
+    description.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SyntaxKind::*;
+
+    #[test]
+    fn test_parse_error_expected() {
+        let error = ParseError::expected(token_set![THEN_KW]);
+        assert_eq!(error.to_string(), "expected 'then'");
+
+        let error = ParseError::expected(token_set![EQ, L_PAREN, THEN_KW]);
+        assert_eq!(error.to_string(), "expected '=', '(' or 'then'");
+
+        let mut error = ParseError::expected(token_set![IDENT, STRING]);
+        error.message = "unexpected token".to_string();
+        assert_eq!(error.to_string(), "unexpected token, expected string or identifier");
+    }
+
+    #[test]
+    fn test_syntax_error_source() {
+        use std::error::Error;
+        let error = SyntaxError::new(
+            SyntaxErrorKind::ParseError(ParseError::expected(token_set![END_KW])),
+            TextSize::from(3),
+        );
+        assert_eq!(error.to_string(), "expected 'end'");
+        assert_eq!(error.source().unwrap().to_string(), "expected 'end'");
+    }
+}
@@ -1,7 +1,7 @@
 mod generated;
 
 pub use self::generated::SyntaxKind;
-use std::fmt;
+use std::{convert::TryFrom, fmt, str::FromStr};
 
 impl fmt::Debug for SyntaxKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -16,9 +16,122 @@ pub(crate) struct SyntaxInfo {
 
 impl SyntaxKind {
     pub fn is_trivia(self) -> bool {
-        match self {
-            SyntaxKind::WHITESPACE | SyntaxKind::COMMENT => true,
-            _ => false,
+        matches!(self, SyntaxKind::WHITESPACE | SyntaxKind::COMMENT)
+    }
+
+    /// Returns the numeric representation of the kind, which can be converted back with
+    /// `SyntaxKind::try_from`. The numbers are pinned by `tests/data/syntax_kinds.txt`; changing
+    /// them is a breaking change.
+    pub fn into_raw(self) -> u16 {
+        self as u16
+    }
+
+    /// Returns the Lua operator that is meant by an operator of another language, e.g. `~=` for
+    /// `!=`. Returns `None` for `++`, which has no Lua equivalent.
+    pub fn intended_operator(self) -> Option<SyntaxKind> {
+        let kind = match self {
+            SyntaxKind::BANGEQ => SyntaxKind::NEQ,
+            SyntaxKind::AMPAMP => SyntaxKind::AND_KW,
+            SyntaxKind::PIPEPIPE => SyntaxKind::OR_KW,
+            SyntaxKind::EQEQEQ => SyntaxKind::EQEQ,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// Returns all kinds, ordered by their numeric value.
+    pub(crate) fn all() -> impl Iterator<Item = SyntaxKind> {
+        (0..SyntaxKind::__LAST as u16).map(|raw| SyntaxKind::try_from(raw).unwrap())
+    }
+
+    /// Returns all keywords, e.g. to offer them as completions.
+    pub fn keywords() -> impl Iterator<Item = SyntaxKind> {
+        SyntaxKind::all().filter(|kind| kind.is_keyword())
+    }
+}
+
+impl FromStr for SyntaxKind {
+    type Err = ParseSyntaxKindError;
+
+    /// Parses the text of a keyword or symbol, e.g. `local` or `==`, or the name of a kind as it
+    /// is printed by `Debug`, e.g. `IDENT`.
+    fn from_str(s: &str) -> Result<SyntaxKind, ParseSyntaxKindError> {
+        SyntaxKind::all()
+            .find(|kind| kind.token_text() == Some(s))
+            .or_else(|| SyntaxKind::all().find(|kind| kind.info().name == s))
+            .ok_or_else(|| ParseSyntaxKindError(s.to_string()))
+    }
+}
+
+/// The error returned when converting a number that doesn't represent a kind to a `SyntaxKind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyntaxKindFromRawError(pub u16);
+
+impl fmt::Display for SyntaxKindFromRawError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is not a valid syntax kind", self.0)
+    }
+}
+
+impl std::error::Error for SyntaxKindFromRawError {}
+
+/// The error returned when parsing a string that isn't a keyword, symbol or the name of a kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSyntaxKindError(pub String);
+
+impl fmt::Display for ParseSyntaxKindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}` is not a syntax kind", self.0)
+    }
+}
+
+impl std::error::Error for ParseSyntaxKindError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_groups() {
+        for kind in SyntaxKind::all() {
+            let name = format!("{:?}", kind);
+            assert_eq!(kind.is_statement_kind(), name.ends_with("_STMT"), "{}", name);
+            assert!(!(kind.is_statement_kind() && kind.is_expression_kind()));
+            if kind.is_operator() {
+                assert!(kind.is_symbol() || kind.is_keyword(), "{}", name);
+            }
         }
+        assert!(SyntaxKind::BIN_EXPR.is_expression_kind());
+        assert!(SyntaxKind::NOT_KW.is_operator());
+        assert!(!SyntaxKind::EQ.is_operator());
+    }
+
+    #[test]
+    fn test_raw_roundtrip() {
+        for kind in SyntaxKind::all() {
+            assert_eq!(SyntaxKind::try_from(kind.into_raw()), Ok(kind));
+        }
+        let last = SyntaxKind::__LAST.into_raw();
+        assert_eq!(SyntaxKind::try_from(last), Err(SyntaxKindFromRawError(last)));
+    }
+
+    #[test]
+    fn test_keywords() {
+        assert_eq!(SyntaxKind::keywords().count(), 22);
+        for kind in SyntaxKind::keywords() {
+            let text = kind.token_text().unwrap();
+            assert_eq!(SyntaxKind::from_keyword(text), Some(kind));
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("local".parse(), Ok(SyntaxKind::LOCAL_KW));
+        assert_eq!("==".parse(), Ok(SyntaxKind::EQEQ));
+        assert_eq!("FUNCTION_STMT".parse(), Ok(SyntaxKind::FUNCTION_STMT));
+        assert_eq!(
+            "lokal".parse::<SyntaxKind>(),
+            Err(ParseSyntaxKindError("lokal".to_string()))
+        );
     }
 }
@@ -3,7 +3,8 @@
 
 #![allow(bad_style, missing_docs, unreachable_pub)]
 #![cfg_attr(rustfmt, rustfmt_skip)]
-use super::SyntaxInfo;
+use super::{SyntaxInfo, SyntaxKindFromRawError};
+use std::convert::TryFrom;
 
 /// The kind of syntax node, e.g. `IDENT`, `USE_KW`, or `STRUCT_DEF`.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -35,6 +36,9 @@ pub enum SyntaxKind {
     SEMI,
     COLON,
     COMMA,
+    AMP,
+    PIPE,
+    TILDE,
     EQEQ,
     NEQ,
     LTEQ,
@@ -42,6 +46,9 @@ pub enum SyntaxKind {
     DOTDOT,
     DOTDOTDOT,
     COLONCOLON,
+    SLASHSLASH,
+    SHL,
+    SHR,
     AND_KW,
     BREAK_KW,
     DO_KW,
@@ -72,16 +79,66 @@ pub enum SyntaxKind {
     WHITESPACE,
     COMMENT,
     CHUNK,
+    BLOCK,
+    EXPR_STMT,
+    ASSIGN_STMT,
+    LOCAL_ASSIGN_STMT,
+    LOCAL_FUNCTION_STMT,
+    FUNCTION_STMT,
+    DO_STMT,
+    WHILE_STMT,
+    REPEAT_STMT,
+    IF_STMT,
+    ELSE_IF_CLAUSE,
+    ELSE_CLAUSE,
+    NUMERIC_FOR_STMT,
+    GENERIC_FOR_STMT,
+    RETURN_STMT,
+    BREAK_STMT,
+    GOTO_STMT,
+    LABEL_STMT,
+    LITERAL,
+    NAME_REF,
+    PAREN_EXPR,
+    BIN_EXPR,
+    UNARY_EXPR,
+    CALL_EXPR,
+    METHOD_CALL_EXPR,
+    INDEX_EXPR,
+    FIELD_EXPR,
+    FUNCTION_EXPR,
+    TABLE_EXPR,
+    NAMED_FIELD,
+    INDEXED_FIELD,
+    POSITIONAL_FIELD,
+    NAME,
+    NAME_LIST,
+    ATTRIBUTE,
+    EXPR_LIST,
+    PARAM_LIST,
+    ARG_LIST,
+    FUNCTION_NAME,
+    BANGEQ,
+    AMPAMP,
+    PIPEPIPE,
+    EQEQEQ,
+    PLUSPLUS,
+    DOLLAR,
+    PLACEHOLDER,
     // Technical kind so that we can cast from u16 safely
     #[doc(hidden)]
     __LAST,
 }
 use self::SyntaxKind::*;
 
-impl From<u16> for SyntaxKind {
-    fn from(d: u16) -> SyntaxKind {
-        assert!(d <= (__LAST as u16));
-        unsafe { std::mem::transmute::<u16, SyntaxKind>(d) }
+impl TryFrom<u16> for SyntaxKind {
+    type Error = SyntaxKindFromRawError;
+
+    fn try_from(d: u16) -> Result<SyntaxKind, SyntaxKindFromRawError> {
+        if d >= (__LAST as u16) {
+            return Err(SyntaxKindFromRawError(d));
+        }
+        Ok(unsafe { std::mem::transmute::<u16, SyntaxKind>(d) })
     }
 }
 
@@ -93,7 +150,7 @@ impl From<SyntaxKind> for u16 {
 
 impl SyntaxKind {
     pub fn is_keyword(self) -> bool {
-        match self {
+        matches!(self,
             | AND_KW
             | BREAK_KW
             | DO_KW
@@ -116,13 +173,11 @@ impl SyntaxKind {
             | TRUE_KW
             | UNTIL_KW
             | WHILE_KW
-                => true,
-            _ => false
-        }
+        )
     }
 
     pub fn is_symbol(self) -> bool {
-            match self {
+            matches!(self,
                 | PLUS
                 | MINUS
                 | STAR
@@ -143,6 +198,9 @@ impl SyntaxKind {
                 | SEMI
                 | COLON
                 | COMMA
+                | AMP
+                | PIPE
+                | TILDE
                 | EQEQ
                 | NEQ
                 | LTEQ
@@ -150,19 +208,96 @@ impl SyntaxKind {
                 | DOTDOT
                 | DOTDOTDOT
                 | COLONCOLON
-                    => true,
-                _ => false
-            }
+                | SLASHSLASH
+                | SHL
+                | SHR
+            )
+    }
+
+    /// Returns true for the operators of other languages that are recognized to report them,
+    /// e.g. `!=`
+    pub fn is_typo(self) -> bool {
+            matches!(self,
+                | BANGEQ
+                | AMPAMP
+                | PIPEPIPE
+                | EQEQEQ
+                | PLUSPLUS
+            )
     }
 
     pub fn is_literal(self) -> bool {
-            match self {
+            matches!(self,
                 | INT_NUMBER
                 | FLOAT_NUMBER
                 | STRING
-                    => true,
-                _ => false
-            }
+            )
+    }
+
+    /// Returns true for the tokens that are the operator of a binary or unary expression
+    pub fn is_operator(self) -> bool {
+            matches!(self,
+                | PLUS
+                | MINUS
+                | STAR
+                | SLASH
+                | SLASHSLASH
+                | PERCENT
+                | CARET
+                | HASH
+                | LT
+                | GT
+                | LTEQ
+                | GTEQ
+                | EQEQ
+                | NEQ
+                | DOTDOT
+                | AMP
+                | PIPE
+                | TILDE
+                | SHL
+                | SHR
+                | AND_KW
+                | OR_KW
+                | NOT_KW
+            )
+    }
+
+    pub fn is_statement_kind(self) -> bool {
+            matches!(self,
+                | EXPR_STMT
+                | ASSIGN_STMT
+                | LOCAL_ASSIGN_STMT
+                | LOCAL_FUNCTION_STMT
+                | FUNCTION_STMT
+                | DO_STMT
+                | WHILE_STMT
+                | REPEAT_STMT
+                | IF_STMT
+                | NUMERIC_FOR_STMT
+                | GENERIC_FOR_STMT
+                | RETURN_STMT
+                | BREAK_STMT
+                | GOTO_STMT
+                | LABEL_STMT
+            )
+    }
+
+    pub fn is_expression_kind(self) -> bool {
+            matches!(self,
+                | LITERAL
+                | NAME_REF
+                | PAREN_EXPR
+                | BIN_EXPR
+                | UNARY_EXPR
+                | CALL_EXPR
+                | METHOD_CALL_EXPR
+                | INDEX_EXPR
+                | FIELD_EXPR
+                | FUNCTION_EXPR
+                | TABLE_EXPR
+                | PLACEHOLDER
+            )
     }
 
     pub(crate) fn info(self) -> &'static SyntaxInfo {
@@ -187,6 +322,9 @@ impl SyntaxKind {
                 SEMI => &SyntaxInfo { name: "SEMI" },
                 COLON => &SyntaxInfo { name: "COLON" },
                 COMMA => &SyntaxInfo { name: "COMMA" },
+                AMP => &SyntaxInfo { name: "AMP" },
+                PIPE => &SyntaxInfo { name: "PIPE" },
+                TILDE => &SyntaxInfo { name: "TILDE" },
                 EQEQ => &SyntaxInfo { name: "EQEQ" },
                 NEQ => &SyntaxInfo { name: "NEQ" },
                 LTEQ => &SyntaxInfo { name: "LTEQ" },
@@ -194,6 +332,9 @@ impl SyntaxKind {
                 DOTDOT => &SyntaxInfo { name: "DOTDOT" },
                 DOTDOTDOT => &SyntaxInfo { name: "DOTDOTDOT" },
                 COLONCOLON => &SyntaxInfo { name: "COLONCOLON" },
+                SLASHSLASH => &SyntaxInfo { name: "SLASHSLASH" },
+                SHL => &SyntaxInfo { name: "SHL" },
+                SHR => &SyntaxInfo { name: "SHR" },
                 AND_KW => &SyntaxInfo { name: "AND_KW" },
                 BREAK_KW => &SyntaxInfo { name: "BREAK_KW" },
                 DO_KW => &SyntaxInfo { name: "DO_KW" },
@@ -224,6 +365,52 @@ impl SyntaxKind {
                 WHITESPACE => &SyntaxInfo { name: "WHITESPACE" },
                 COMMENT => &SyntaxInfo { name: "COMMENT" },
                 CHUNK => &SyntaxInfo { name: "CHUNK" },
+                BLOCK => &SyntaxInfo { name: "BLOCK" },
+                EXPR_STMT => &SyntaxInfo { name: "EXPR_STMT" },
+                ASSIGN_STMT => &SyntaxInfo { name: "ASSIGN_STMT" },
+                LOCAL_ASSIGN_STMT => &SyntaxInfo { name: "LOCAL_ASSIGN_STMT" },
+                LOCAL_FUNCTION_STMT => &SyntaxInfo { name: "LOCAL_FUNCTION_STMT" },
+                FUNCTION_STMT => &SyntaxInfo { name: "FUNCTION_STMT" },
+                DO_STMT => &SyntaxInfo { name: "DO_STMT" },
+                WHILE_STMT => &SyntaxInfo { name: "WHILE_STMT" },
+                REPEAT_STMT => &SyntaxInfo { name: "REPEAT_STMT" },
+                IF_STMT => &SyntaxInfo { name: "IF_STMT" },
+                ELSE_IF_CLAUSE => &SyntaxInfo { name: "ELSE_IF_CLAUSE" },
+                ELSE_CLAUSE => &SyntaxInfo { name: "ELSE_CLAUSE" },
+                NUMERIC_FOR_STMT => &SyntaxInfo { name: "NUMERIC_FOR_STMT" },
+                GENERIC_FOR_STMT => &SyntaxInfo { name: "GENERIC_FOR_STMT" },
+                RETURN_STMT => &SyntaxInfo { name: "RETURN_STMT" },
+                BREAK_STMT => &SyntaxInfo { name: "BREAK_STMT" },
+                GOTO_STMT => &SyntaxInfo { name: "GOTO_STMT" },
+                LABEL_STMT => &SyntaxInfo { name: "LABEL_STMT" },
+                LITERAL => &SyntaxInfo { name: "LITERAL" },
+                NAME_REF => &SyntaxInfo { name: "NAME_REF" },
+                PAREN_EXPR => &SyntaxInfo { name: "PAREN_EXPR" },
+                BIN_EXPR => &SyntaxInfo { name: "BIN_EXPR" },
+                UNARY_EXPR => &SyntaxInfo { name: "UNARY_EXPR" },
+                CALL_EXPR => &SyntaxInfo { name: "CALL_EXPR" },
+                METHOD_CALL_EXPR => &SyntaxInfo { name: "METHOD_CALL_EXPR" },
+                INDEX_EXPR => &SyntaxInfo { name: "INDEX_EXPR" },
+                FIELD_EXPR => &SyntaxInfo { name: "FIELD_EXPR" },
+                FUNCTION_EXPR => &SyntaxInfo { name: "FUNCTION_EXPR" },
+                TABLE_EXPR => &SyntaxInfo { name: "TABLE_EXPR" },
+                NAMED_FIELD => &SyntaxInfo { name: "NAMED_FIELD" },
+                INDEXED_FIELD => &SyntaxInfo { name: "INDEXED_FIELD" },
+                POSITIONAL_FIELD => &SyntaxInfo { name: "POSITIONAL_FIELD" },
+                NAME => &SyntaxInfo { name: "NAME" },
+                NAME_LIST => &SyntaxInfo { name: "NAME_LIST" },
+                ATTRIBUTE => &SyntaxInfo { name: "ATTRIBUTE" },
+                EXPR_LIST => &SyntaxInfo { name: "EXPR_LIST" },
+                PARAM_LIST => &SyntaxInfo { name: "PARAM_LIST" },
+                ARG_LIST => &SyntaxInfo { name: "ARG_LIST" },
+                FUNCTION_NAME => &SyntaxInfo { name: "FUNCTION_NAME" },
+                BANGEQ => &SyntaxInfo { name: "BANGEQ" },
+                AMPAMP => &SyntaxInfo { name: "AMPAMP" },
+                PIPEPIPE => &SyntaxInfo { name: "PIPEPIPE" },
+                EQEQEQ => &SyntaxInfo { name: "EQEQEQ" },
+                PLUSPLUS => &SyntaxInfo { name: "PLUSPLUS" },
+                DOLLAR => &SyntaxInfo { name: "DOLLAR" },
+                PLACEHOLDER => &SyntaxInfo { name: "PLACEHOLDER" },
                 TOMBSTONE => &SyntaxInfo { name: "TOMBSTONE" },
                 EOF => &SyntaxInfo { name: "EOF" },
                 __LAST => &SyntaxInfo { name: "__LAST" },
@@ -281,10 +468,83 @@ impl SyntaxKind {
                 ';' => SEMI,
                 ':' => COLON,
                 ',' => COMMA,
+                '&' => AMP,
+                '|' => PIPE,
+                '~' => TILDE,
+                '$' => DOLLAR,
                 _ => return None,
             };
             Some(tok)
         }
+
+    /// Returns the literal text of keywords and symbols
+    pub fn token_text(self) -> Option<&'static str> {
+            let text = match self {
+                PLUS => "+",
+                MINUS => "-",
+                STAR => "*",
+                SLASH => "/",
+                PERCENT => "%",
+                CARET => "^",
+                HASH => "#",
+                DOT => ".",
+                LT => "<",
+                GT => ">",
+                EQ => "=",
+                L_PAREN => "(",
+                R_PAREN => ")",
+                L_CURLY => "{",
+                R_CURLY => "}",
+                L_BRACKET => "[",
+                R_BRACKET => "]",
+                SEMI => ";",
+                COLON => ":",
+                COMMA => ",",
+                AMP => "&",
+                PIPE => "|",
+                TILDE => "~",
+                EQEQ => "==",
+                NEQ => "~=",
+                LTEQ => "<=",
+                GTEQ => ">=",
+                DOTDOT => "..",
+                DOTDOTDOT => "...",
+                COLONCOLON => "::",
+                SLASHSLASH => "//",
+                SHL => "<<",
+                SHR => ">>",
+                AND_KW => "and",
+                BREAK_KW => "break",
+                DO_KW => "do",
+                ELSE_KW => "else",
+                ELSEIF_KW => "elseif",
+                END_KW => "end",
+                FALSE_KW => "false",
+                FOR_KW => "for",
+                FUNCTION_KW => "function",
+                GOTO_KW => "goto",
+                IF_KW => "if",
+                IN_KW => "in",
+                LOCAL_KW => "local",
+                NIL_KW => "nil",
+                NOT_KW => "not",
+                OR_KW => "or",
+                REPEAT_KW => "repeat",
+                RETURN_KW => "return",
+                THEN_KW => "then",
+                TRUE_KW => "true",
+                UNTIL_KW => "until",
+                WHILE_KW => "while",
+                BANGEQ => "!=",
+                AMPAMP => "&&",
+                PIPEPIPE => "||",
+                EQEQEQ => "===",
+                PLUSPLUS => "++",
+                DOLLAR => "$",
+                _ => return None,
+            };
+            Some(text)
+    }
 }
 
 
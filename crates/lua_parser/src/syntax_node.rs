@@ -6,22 +6,17 @@
 //! The *real* implementation is in the (language-agnostic) `rowan` crate, this
 //! modules just wraps its API.
 
-use std::{
-    any::Any,
-    borrow::Borrow,
-    fmt::{self, Write},
-    iter::successors,
-};
+mod node_cache;
 
+use self::node_cache::{GreenElement, NodeCache};
 use crate::{
-    ParseError,
     syntax_error::{SyntaxError, SyntaxErrorKind},
-    AstNode, SmolStr, Chunk, SyntaxKind, SyntaxText, TextRange, TextUnit,
+    Parse, ParseError, SmolStr, SyntaxKind, TextSize,
 };
-use rowan::{GreenNodeBuilder, TransparentNewType};
+use rowan::Language;
+use std::convert::TryFrom;
 
-pub use rowan::WalkEvent;
-pub(crate) use rowan::{GreenNode, GreenToken};
+pub use rowan::{Direction, GreenNode, NodeOrToken, WalkEvent};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum InsertPosition<T> {
@@ -31,529 +26,313 @@ pub enum InsertPosition<T> {
     After(T),
 }
 
-/// Marker trait for CST and AST nodes
-pub trait SyntaxNodeWrapper: TransparentNewType<Repr = rowan::SyntaxNode> {}
-impl<T: TransparentNewType<Repr = rowan::SyntaxNode>> SyntaxNodeWrapper for T {}
-
-/// An owning smart pointer for CST or AST nodes.
-#[derive(PartialEq, Eq, Hash)]
-pub struct TreeArc<T: SyntaxNodeWrapper>(pub(crate) rowan::TreeArc<T>);
-
-impl<T: SyntaxNodeWrapper> Borrow<T> for TreeArc<T> {
-    fn borrow(&self) -> &T {
-        &*self
-    }
-}
-
-impl<T> TreeArc<T>
-    where
-        T: SyntaxNodeWrapper,
-{
-    pub(crate) fn cast<U>(this: TreeArc<T>) -> TreeArc<U>
-        where
-            U: SyntaxNodeWrapper,
-    {
-        TreeArc(rowan::TreeArc::cast(this.0))
-    }
-}
-
-impl<T> std::ops::Deref for TreeArc<T>
-    where
-        T: SyntaxNodeWrapper,
-{
-    type Target = T;
-    fn deref(&self) -> &T {
-        self.0.deref()
-    }
-}
-
-impl<T> PartialEq<T> for TreeArc<T>
-    where
-        T: SyntaxNodeWrapper,
-        T: PartialEq<T>,
-{
-    fn eq(&self, other: &T) -> bool {
-        let t: &T = self;
-        t == other
-    }
-}
-
-impl<T> Clone for TreeArc<T>
-    where
-        T: SyntaxNodeWrapper,
-{
-    fn clone(&self) -> TreeArc<T> {
-        TreeArc(self.0.clone())
-    }
-}
-
-impl<T> fmt::Debug for TreeArc<T>
-    where
-        T: SyntaxNodeWrapper,
-        T: fmt::Debug,
-{
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(&self.0, fmt)
-    }
-}
-
-#[derive(PartialEq, Eq, Hash)]
-#[repr(transparent)]
-pub struct SyntaxNode(pub(crate) rowan::SyntaxNode);
-unsafe impl TransparentNewType for SyntaxNode {
-    type Repr = rowan::SyntaxNode;
-}
+/// The Lua language for `rowan`, which maps its untyped kinds to `SyntaxKind`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LuaLanguage {}
 
-impl ToOwned for SyntaxNode {
-    type Owned = TreeArc<SyntaxNode>;
-    fn to_owned(&self) -> TreeArc<SyntaxNode> {
-        let ptr = TreeArc(self.0.to_owned());
-        TreeArc::cast(ptr)
-    }
-}
+impl Language for LuaLanguage {
+    type Kind = SyntaxKind;
 
-impl fmt::Debug for SyntaxNode {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "{:?}@{:?}", self.kind(), self.range())
+    fn kind_from_raw(raw: rowan::SyntaxKind) -> SyntaxKind {
+        SyntaxKind::try_from(raw.0).expect("rowan kinds are created from syntax kinds")
     }
-}
 
-impl fmt::Display for SyntaxNode {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.text(), fmt)
+    fn kind_to_raw(kind: SyntaxKind) -> rowan::SyntaxKind {
+        rowan::SyntaxKind(kind.into_raw())
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Direction {
-    Next,
-    Prev,
-}
-
-impl SyntaxNode {
-    pub(crate) fn new(green: GreenNode, errors: Vec<SyntaxError>) -> TreeArc<SyntaxNode> {
-        let errors: Option<Box<Any + Send + Sync>> = if errors.is_empty() {
-            None
-        } else {
-            Some(Box::new(errors))
-        };
-        let ptr = TreeArc(rowan::SyntaxNode::new(green, errors));
-        TreeArc::cast(ptr)
-    }
-
-    pub fn kind(&self) -> SyntaxKind {
-        self.0.kind().0.into()
-    }
-
-    pub fn range(&self) -> TextRange {
-        self.0.range()
-    }
-
-    pub fn text(&self) -> SyntaxText {
-        SyntaxText::new(self)
-    }
-
-    pub fn parent(&self) -> Option<&SyntaxNode> {
-        self.0.parent().map(SyntaxNode::from_repr)
-    }
-
-    pub fn first_child(&self) -> Option<&SyntaxNode> {
-        self.0.first_child().map(SyntaxNode::from_repr)
-    }
-
-    pub fn first_child_or_token(&self) -> Option<SyntaxElement> {
-        self.0.first_child_or_token().map(SyntaxElement::from)
-    }
-
-    pub fn last_child(&self) -> Option<&SyntaxNode> {
-        self.0.last_child().map(SyntaxNode::from_repr)
-    }
-
-    pub fn last_child_or_token(&self) -> Option<SyntaxElement> {
-        self.0.last_child_or_token().map(SyntaxElement::from)
-    }
-
-    pub fn next_sibling(&self) -> Option<&SyntaxNode> {
-        self.0.next_sibling().map(SyntaxNode::from_repr)
-    }
-
-    pub fn next_sibling_or_token(&self) -> Option<SyntaxElement> {
-        self.0.next_sibling_or_token().map(SyntaxElement::from)
-    }
-
-    pub fn prev_sibling(&self) -> Option<&SyntaxNode> {
-        self.0.prev_sibling().map(SyntaxNode::from_repr)
-    }
-
-    pub fn prev_sibling_or_token(&self) -> Option<SyntaxElement> {
-        self.0.prev_sibling_or_token().map(SyntaxElement::from)
-    }
-
-    pub fn children(&self) -> SyntaxNodeChildren {
-        SyntaxNodeChildren(self.0.children())
-    }
-
-    pub fn children_with_tokens(&self) -> SyntaxElementChildren {
-        SyntaxElementChildren(self.0.children_with_tokens())
-    }
-
-    pub fn first_token(&self) -> Option<SyntaxToken> {
-        self.0.first_token().map(SyntaxToken::from)
-    }
-
-    pub fn last_token(&self) -> Option<SyntaxToken> {
-        self.0.last_token().map(SyntaxToken::from)
-    }
-
-    pub fn ancestors(&self) -> impl Iterator<Item = &SyntaxNode> {
-        successors(Some(self), |&node| node.parent())
-    }
-
-    pub fn descendants_with_tokens(&self) -> impl Iterator<Item = SyntaxElement> {
-        self.preorder_with_tokens().filter_map(|event| match event {
-            WalkEvent::Enter(it) => Some(it),
-            WalkEvent::Leave(_) => None,
-        })
-    }
-
-    pub fn siblings(&self, direction: Direction) -> impl Iterator<Item = &SyntaxNode> {
-        successors(Some(self), move |&node| match direction {
-            Direction::Next => node.next_sibling(),
-            Direction::Prev => node.prev_sibling(),
-        })
-    }
-
-    pub fn siblings_with_tokens(
-        &self,
-        direction: Direction,
-    ) -> impl Iterator<Item = SyntaxElement> {
-        let me: SyntaxElement = self.into();
-        successors(Some(me), move |el| match direction {
-            Direction::Next => el.next_sibling_or_token(),
-            Direction::Prev => el.prev_sibling_or_token(),
-        })
-    }
-
-    pub fn preorder(&self) -> impl Iterator<Item = WalkEvent<&SyntaxNode>> {
-        self.0.preorder().map(|event| match event {
-            WalkEvent::Enter(n) => WalkEvent::Enter(SyntaxNode::from_repr(n)),
-            WalkEvent::Leave(n) => WalkEvent::Leave(SyntaxNode::from_repr(n)),
-        })
-    }
-
-    pub fn preorder_with_tokens(&self) -> impl Iterator<Item = WalkEvent<SyntaxElement>> {
-        self.0.preorder_with_tokens().map(|event| match event {
-            WalkEvent::Enter(n) => WalkEvent::Enter(n.into()),
-            WalkEvent::Leave(n) => WalkEvent::Leave(n.into()),
-        })
-    }
-
-    pub fn memory_size_of_subtree(&self) -> usize {
-        self.0.memory_size_of_subtree()
-    }
-
-    pub fn debug_dump(&self) -> String {
-        let mut errors: Vec<_> = match self.ancestors().find_map(Chunk::cast) {
-            Some(file) => file.errors(),
-            None => self.root_data().to_vec(),
-        };
-        errors.sort_by_key(|e| e.location().offset());
-        let mut err_pos = 0;
-        let mut level = 0;
-        let mut buf = String::new();
-        macro_rules! indent {
-            () => {
-                for _ in 0..level {
-                    buf.push_str("  ");
-                }
-            };
-        }
-
-        for event in self.preorder_with_tokens() {
-            match event {
-                WalkEvent::Enter(element) => {
-                    indent!();
-                    match element {
-                        SyntaxElement::Node(node) => writeln!(buf, "{:?}", node).unwrap(),
-                        SyntaxElement::Token(token) => {
-                            writeln!(buf, "{:?}", token).unwrap();
-                            let off = token.range().end();
-                            while err_pos < errors.len() && errors[err_pos].location().offset() <= off {
-                                indent!();
-                                writeln!(buf, "err: `{}`", errors[err_pos]).unwrap();
-                                err_pos += 1;
-                            }
-                        }
-                    }
-                    level += 1;
-                }
-                WalkEvent::Leave(_) => level -= 1,
-            }
-        }
-
-        assert_eq!(level, 0);
-        for err in errors[err_pos..].iter() {
-            writeln!(buf, "err: `{}`", err).unwrap();
-        }
-
-        buf
-    }
-
-    pub(crate) fn root_data(&self) -> &[SyntaxError] {
-        match self.0.root_data() {
-            None => &[],
-            Some(data) => {
-                let data: &Vec<SyntaxError> = std::any::Any::downcast_ref(data).unwrap();
-                data.as_slice()
-            }
-        }
-    }
-}
+pub type SyntaxNode = rowan::SyntaxNode<LuaLanguage>;
+pub type SyntaxToken = rowan::SyntaxToken<LuaLanguage>;
+pub type SyntaxElement = rowan::SyntaxElement<LuaLanguage>;
+pub type SyntaxNodeChildren = rowan::SyntaxNodeChildren<LuaLanguage>;
+pub type SyntaxElementChildren = rowan::SyntaxElementChildren<LuaLanguage>;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-pub struct SyntaxToken<'a>(pub(crate) rowan::SyntaxToken<'a>);
+/// Queries on the children of a `SyntaxNode` by kind, for simple lookups that don't need the typed
+/// AST, e.g. `node.first_child_of_kind(BLOCK)`.
+pub trait SyntaxNodeExt {
+    /// Returns the child nodes of `kind`.
+    fn children_of_kind(&self, kind: SyntaxKind) -> impl Iterator<Item = SyntaxNode>;
 
-//FIXME: always output text
-impl<'a> fmt::Debug for SyntaxToken<'a> {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "{:?}@{:?}", self.kind(), self.range())?;
-        if self.text().len() < 25 {
-            return write!(fmt, " {:?}", self.text());
-        }
-        let text = self.text().as_str();
-        for idx in 21..25 {
-            if text.is_char_boundary(idx) {
-                let text = format!("{} ...", &text[..idx]);
-                return write!(fmt, " {:?}", text);
-            }
-        }
-        unreachable!()
+    fn first_child_of_kind(&self, kind: SyntaxKind) -> Option<SyntaxNode> {
+        self.children_of_kind(kind).next()
     }
-}
 
-impl<'a> fmt::Display for SyntaxToken<'a> {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(self.text(), fmt)
+    /// Returns the `n`th child node of `kind`, counting from zero.
+    fn nth_child_of_kind(&self, kind: SyntaxKind, n: usize) -> Option<SyntaxNode> {
+        self.children_of_kind(kind).nth(n)
     }
-}
 
-impl<'a> From<rowan::SyntaxToken<'a>> for SyntaxToken<'a> {
-    fn from(t: rowan::SyntaxToken<'a>) -> Self {
-        SyntaxToken(t)
-    }
+    /// Returns the tokens of `kind` that are direct children of the node.
+    fn tokens_of_kind(&self, kind: SyntaxKind) -> impl Iterator<Item = SyntaxToken>;
 }
 
-impl<'a> SyntaxToken<'a> {
-    pub fn kind(&self) -> SyntaxKind {
-        self.0.kind().0.into()
-    }
-
-    pub fn text(&self) -> &'a SmolStr {
-        self.0.text()
-    }
-
-    pub fn range(&self) -> TextRange {
-        self.0.range()
-    }
-
-    pub fn parent(&self) -> &'a SyntaxNode {
-        SyntaxNode::from_repr(self.0.parent())
-    }
-
-    pub fn next_sibling_or_token(&self) -> Option<SyntaxElement<'a>> {
-        self.0.next_sibling_or_token().map(SyntaxElement::from)
-    }
-
-    pub fn prev_sibling_or_token(&self) -> Option<SyntaxElement<'a>> {
-        self.0.prev_sibling_or_token().map(SyntaxElement::from)
-    }
-
-    pub fn siblings_with_tokens(
-        &self,
-        direction: Direction,
-    ) -> impl Iterator<Item = SyntaxElement<'a>> {
-        let me: SyntaxElement = (*self).into();
-        successors(Some(me), move |el| match direction {
-            Direction::Next => el.next_sibling_or_token(),
-            Direction::Prev => el.prev_sibling_or_token(),
-        })
-    }
-
-    pub fn next_token(&self) -> Option<SyntaxToken<'a>> {
-        self.0.next_token().map(SyntaxToken::from)
-    }
-
-    pub fn prev_token(&self) -> Option<SyntaxToken<'a>> {
-        self.0.prev_token().map(SyntaxToken::from)
+impl SyntaxNodeExt for SyntaxNode {
+    fn children_of_kind(&self, kind: SyntaxKind) -> impl Iterator<Item = SyntaxNode> {
+        self.children().filter(move |child| child.kind() == kind)
     }
 
-    pub(crate) fn replace_with(&self, new_token: GreenToken) -> GreenNode {
-        self.0.replace_with(new_token)
+    fn tokens_of_kind(&self, kind: SyntaxKind) -> impl Iterator<Item = SyntaxToken> {
+        self.children_with_tokens()
+            .filter_map(NodeOrToken::into_token)
+            .filter(move |token| token.kind() == kind)
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-pub enum SyntaxElement<'a> {
-    Node(&'a SyntaxNode),
-    Token(SyntaxToken<'a>),
-}
+/// A position in a `SyntaxTreeBuilder` at which a node can be started after the fact, see
+/// `SyntaxTreeBuilder::checkpoint`.
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint(usize);
 
-impl<'a> fmt::Display for SyntaxElement<'a> {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            SyntaxElement::Node(it) => fmt::Display::fmt(it, fmt),
-            SyntaxElement::Token(it) => fmt::Display::fmt(it, fmt),
-        }
-    }
+/// Builds a syntax tree from tokens and nodes. Identical tokens and small nodes are shared, so
+/// machine generated Lua files, e.g. large data tables, don't store the same subtrees over and
+/// over again. Nodes with a lot of text are never shared, they rarely repeat and would make the
+/// cache grow with the depth of the tree. Building takes linear time, also for deeply nested trees.
+#[derive(Default)]
+pub struct SyntaxTreeBuilder {
+    errors: Vec<SyntaxError>,
+    cache: NodeCache,
+    /// The kinds of the nodes that have been started but not yet finished and the index of their
+    /// first child in `children`.
+    open_nodes: Vec<(SyntaxKind, usize)>,
+    /// The children of the open nodes, with their hash if they are interned.
+    children: Vec<(Option<u64>, GreenElement)>,
+    has_root: bool,
 }
 
-impl<'a> SyntaxElement<'a> {
-    pub fn kind(&self) -> SyntaxKind {
-        match self {
-            SyntaxElement::Node(it) => it.kind(),
-            SyntaxElement::Token(it) => it.kind(),
-        }
-    }
-
-    pub fn as_node(&self) -> Option<&'a SyntaxNode> {
-        match self {
-            SyntaxElement::Node(node) => Some(*node),
-            SyntaxElement::Token(_) => None,
+impl SyntaxTreeBuilder {
+    pub(crate) fn finish_raw(mut self) -> (GreenNode, Vec<SyntaxError>) {
+        let open_nodes: Vec<_> = self.open_nodes.iter().map(|(kind, _)| kind).collect();
+        assert!(
+            open_nodes.is_empty(),
+            "the syntax tree was finished while nodes were still open: {:?}; every `start_node` \
+             needs a matching `finish_node`",
+            open_nodes
+        );
+        assert!(
+            self.has_root,
+            "the syntax tree was finished without a root node"
+        );
+        match self.children.pop() {
+            Some((_, NodeOrToken::Node(green))) if self.children.is_empty() => (green, self.errors),
+            _ => unreachable!(),
         }
     }
 
-    pub fn as_token(&self) -> Option<SyntaxToken<'a>> {
-        match self {
-            SyntaxElement::Node(_) => None,
-            SyntaxElement::Token(token) => Some(*token),
+    pub fn finish(self) -> Parse<SyntaxNode> {
+        let (green, errors) = self.finish_raw();
+        if cfg!(debug_assertions) {
+            let node = SyntaxNode::new_root(green.clone());
+            crate::validation::validate_block_structure(&node);
         }
+        Parse::new(green, errors)
     }
 
-    pub fn next_sibling_or_token(&self) -> Option<SyntaxElement<'a>> {
-        match self {
-            SyntaxElement::Node(it) => it.next_sibling_or_token(),
-            SyntaxElement::Token(it) => it.next_sibling_or_token(),
-        }
+    pub fn token(&mut self, kind: SyntaxKind, text: SmolStr) {
+        assert!(
+            !self.open_nodes.is_empty(),
+            "token {:?} {:?} was added outside of a node; call `start_node` first",
+            kind,
+            text
+        );
+        let (hash, token) = self.cache.token(LuaLanguage::kind_to_raw(kind), text);
+        self.children.push((hash, NodeOrToken::Token(token)));
     }
 
-    pub fn prev_sibling_or_token(&self) -> Option<SyntaxElement<'a>> {
-        match self {
-            SyntaxElement::Node(it) => it.prev_sibling_or_token(),
-            SyntaxElement::Token(it) => it.prev_sibling_or_token(),
-        }
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.check_single_root(kind);
+        self.open_nodes.push((kind, self.children.len()));
     }
 
-    pub fn ancestors(&self) -> impl Iterator<Item = &'a SyntaxNode> {
-        match self {
-            SyntaxElement::Node(it) => it,
-            SyntaxElement::Token(it) => it.parent(),
+    pub fn finish_node(&mut self) {
+        let (kind, first_child) = self
+            .open_nodes
+            .pop()
+            .expect("`finish_node` was called without a matching `start_node`");
+        if self.open_nodes.is_empty() {
+            self.has_root = true;
         }
-            .ancestors()
+        let (hash, node) = self.cache.node(
+            LuaLanguage::kind_to_raw(kind),
+            &mut self.children,
+            first_child,
+        );
+        self.children.push((hash, NodeOrToken::Node(node)));
+    }
+
+    /// Returns a checkpoint for the current position. Passing it to `start_node_at` later starts
+    /// a node that wraps everything added after the checkpoint, e.g. to wrap the left-hand side of
+    /// `a + b` in a `BIN_EXPR` node once the operator is seen.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.children.len())
+    }
+
+    /// Starts a node of `kind` at a previously created `checkpoint`. All tokens and nodes that
+    /// were added after the checkpoint become children of the new node. The checkpoint must have
+    /// been created in the currently open node.
+    pub fn start_node_at(&mut self, checkpoint: Checkpoint, kind: SyntaxKind) {
+        self.check_single_root(kind);
+        let Checkpoint(first_child) = checkpoint;
+        let open_node_start = self.open_nodes.last().map_or(0, |&(_, start)| start);
+        assert!(
+            open_node_start <= first_child && first_child <= self.children.len(),
+            "the checkpoint was not created in the currently open node"
+        );
+        self.open_nodes.push((kind, first_child));
+    }
+
+    pub fn error(&mut self, error: ParseError, text_pos: TextSize) {
+        let error = SyntaxError::new(SyntaxErrorKind::ParseError(error), text_pos);
+        self.errors.push(error)
     }
 
-    fn text_len(&self) -> TextUnit {
-        match self {
-            SyntaxElement::Node(node) => node.0.green().text_len(),
-            SyntaxElement::Token(token) => TextUnit::of_str(token.0.text()),
-        }
+    fn check_single_root(&self, kind: SyntaxKind) {
+        assert!(
+            !self.has_root,
+            "node {:?} was started after the root node was finished; a tree has a single root",
+            kind
+        );
     }
 }
 
-impl<'a> From<rowan::SyntaxElement<'a>> for SyntaxElement<'a> {
-    fn from(el: rowan::SyntaxElement<'a>) -> Self {
-        match el {
-            rowan::SyntaxElement::Node(n) => SyntaxElement::Node(SyntaxNode::from_repr(n)),
-            rowan::SyntaxElement::Token(t) => SyntaxElement::Token(t.into()),
+#[cfg(test)]
+mod tests {
+    use super::{NodeOrToken, SyntaxNodeExt, SyntaxTreeBuilder};
+    use crate::{Chunk, SyntaxKind::*};
+
+    #[test]
+    fn start_node_at_wraps_previous_elements() {
+        // a + b + c
+        let mut builder = SyntaxTreeBuilder::default();
+        builder.start_node(CHUNK);
+        let checkpoint = builder.checkpoint();
+        builder.start_node(NAME_REF);
+        builder.token(IDENT, "a".into());
+        builder.finish_node();
+        for name in &["b", "c"] {
+            builder.start_node_at(checkpoint, BIN_EXPR);
+            builder.token(PLUS, "+".into());
+            builder.start_node(NAME_REF);
+            builder.token(IDENT, (*name).into());
+            builder.finish_node();
+            builder.finish_node();
         }
-    }
-}
-
-impl<'a> From<&'a SyntaxNode> for SyntaxElement<'a> {
-    fn from(node: &'a SyntaxNode) -> SyntaxElement<'a> {
-        SyntaxElement::Node(node)
-    }
-}
-
-impl<'a> From<SyntaxToken<'a>> for SyntaxElement<'a> {
-    fn from(token: SyntaxToken<'a>) -> SyntaxElement<'a> {
-        SyntaxElement::Token(token)
-    }
-}
-
-impl<'a> SyntaxElement<'a> {
-    pub fn range(&self) -> TextRange {
-        match self {
-            SyntaxElement::Node(it) => it.range(),
-            SyntaxElement::Token(it) => it.range(),
+        builder.finish_node();
+
+        assert_eq!(
+            builder.finish().debug_dump(),
+            r#"CHUNK@0..5
+  BIN_EXPR@0..5
+    BIN_EXPR@0..3
+      NAME_REF@0..1
+        IDENT@0..1 "a"
+      PLUS@1..2 "+"
+      NAME_REF@2..3
+        IDENT@2..3 "b"
+    PLUS@3..4 "+"
+    NAME_REF@4..5
+      IDENT@4..5 "c"
+"#
+        );
+    }
+
+    #[test]
+    fn identical_tokens_and_small_nodes_are_shared() {
+        // x = {1, 1}
+        let mut builder = SyntaxTreeBuilder::default();
+        builder.start_node(CHUNK);
+        builder.token(IDENT, "x".into());
+        builder.token(EQ, "=".into());
+        builder.start_node(TABLE_EXPR);
+        builder.token(L_CURLY, "{".into());
+        for idx in 0..2 {
+            if idx > 0 {
+                builder.token(COMMA, ",".into());
+            }
+            builder.start_node(POSITIONAL_FIELD);
+            builder.token(INT_NUMBER, "1".into());
+            builder.finish_node();
         }
-    }
-}
-
-#[derive(Debug)]
-pub struct SyntaxNodeChildren<'a>(rowan::SyntaxNodeChildren<'a>);
-
-impl<'a> Iterator for SyntaxNodeChildren<'a> {
-    type Item = &'a SyntaxNode;
-
-    fn next(&mut self) -> Option<&'a SyntaxNode> {
-        self.0.next().map(SyntaxNode::from_repr)
-    }
-}
-
-#[derive(Debug)]
-pub struct SyntaxElementChildren<'a>(rowan::SyntaxElementChildren<'a>);
-
-impl<'a> Iterator for SyntaxElementChildren<'a> {
-    type Item = SyntaxElement<'a>;
-
-    fn next(&mut self) -> Option<SyntaxElement<'a>> {
-        self.0.next().map(SyntaxElement::from)
-    }
-}
-
-pub struct SyntaxTreeBuilder {
-    errors: Vec<SyntaxError>,
-    inner: GreenNodeBuilder,
-}
-
-impl Default for SyntaxTreeBuilder {
-    fn default() -> SyntaxTreeBuilder {
-        SyntaxTreeBuilder {
-            errors: Vec::new(),
-            inner: GreenNodeBuilder::new(),
+        builder.token(R_CURLY, "}".into());
+        builder.finish_node();
+        builder.finish_node();
+
+        let node = builder.finish().syntax_node();
+        assert_eq!(node.text().to_string(), "x={1,1}");
+        let table = node.first_child().unwrap();
+        let green = table.green();
+        let fields: Vec<_> = green
+            .children()
+            .filter_map(NodeOrToken::into_node)
+            .collect();
+        assert_eq!(fields.len(), 2);
+        assert!(std::ptr::eq(fields[0], fields[1]));
+    }
+
+    #[test]
+    fn repetitive_tables_are_shared() {
+        let text = format!("x = {{ {} }}", "{ 1, 2 }, ".repeat(1000));
+        let stats = Chunk::parse(&text).memory_stats();
+        // The outer table and a single `{ 1, 2 }`
+        assert_eq!(stats.kinds[&TABLE_EXPR].count, 2);
+        assert!(stats.shared > 999);
+    }
+
+    #[test]
+    fn long_chains_are_not_interned() {
+        // a.b.b.b…
+        let mut builder = SyntaxTreeBuilder::default();
+        builder.start_node(CHUNK);
+        let checkpoint = builder.checkpoint();
+        builder.start_node(NAME_REF);
+        builder.token(IDENT, "a".into());
+        builder.finish_node();
+        for _ in 0..1_000 {
+            builder.start_node_at(checkpoint, FIELD_EXPR);
+            builder.token(DOT, ".".into());
+            builder.token(IDENT, "b".into());
+            builder.finish_node();
         }
+        builder.finish_node();
+
+        // Only the short chains at the bottom are interned, the cache doesn't grow with the
+        // depth of the tree.
+        assert!(builder.cache.len() <= 64);
+        assert_eq!(
+            builder.finish().syntax_node().text_range().len(),
+            2_001.into()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "nodes were still open: [CHUNK, BLOCK]")]
+    fn unbalanced_nodes_panic() {
+        let mut builder = SyntaxTreeBuilder::default();
+        builder.start_node(CHUNK);
+        builder.start_node(BLOCK);
+        builder.finish();
+    }
+
+    #[test]
+    #[should_panic(expected = "without a matching `start_node`")]
+    fn unmatched_finish_node_panics() {
+        let mut builder = SyntaxTreeBuilder::default();
+        builder.finish_node();
+    }
+
+    #[test]
+    fn children_and_tokens_of_kind() {
+        let node = Chunk::parse("f(a, (b), c)").syntax_node();
+        let args = node.descendants().find(|n| n.kind() == ARG_LIST).unwrap();
+        let names: Vec<_> = args
+            .children_of_kind(NAME_REF)
+            .map(|name| name.text().to_string())
+            .collect();
+        assert_eq!(names, vec!["a", "c"]);
+        assert_eq!(args.first_child_of_kind(PAREN_EXPR).unwrap().text(), "(b)");
+        assert_eq!(args.nth_child_of_kind(NAME_REF, 1).unwrap().text(), "c");
+        assert_eq!(args.nth_child_of_kind(NAME_REF, 2), None);
+        assert_eq!(args.tokens_of_kind(COMMA).count(), 2);
+        assert_eq!(node.tokens_of_kind(COMMA).count(), 0);
     }
 }
-
-impl SyntaxTreeBuilder {
-    pub(crate) fn finish_raw(self) -> (GreenNode, Vec<SyntaxError>) {
-        let green = self.inner.finish();
-        (green, self.errors)
-    }
-
-    pub fn finish(self) -> TreeArc<SyntaxNode> {
-        let (green, errors) = self.finish_raw();
-        let node = SyntaxNode::new(green, errors);
-        //        if cfg!(debug_assertions) {
-        //            crate::validation::validate_block_structure(&node);
-        //        }
-        node
-    }
-
-    pub fn token(&mut self, kind: SyntaxKind, text: SmolStr) {
-        self.inner.token(rowan::SyntaxKind(kind.into()), text)
-    }
-
-    pub fn start_node(&mut self, kind: SyntaxKind) {
-        self.inner.start_node(rowan::SyntaxKind(kind.into()))
-    }
-
-    pub fn finish_node(&mut self) {
-        self.inner.finish_node()
-    }
-
-    pub fn error(&mut self, error: ParseError, text_pos: TextUnit) {
-        let error = SyntaxError::new(SyntaxErrorKind::ParseError(error), text_pos);
-        self.errors.push(error)
-    }
-}
\ No newline at end of file
@@ -0,0 +1,127 @@
+//! Interning of green tokens and nodes, so identical subtrees are only stored once.
+//!
+//! `rowan` has an interner of its own, but it recomputes the hash of every interned node from all
+//! its descendants whenever its table grows. For deeply nested trees like the chain of `FIELD_EXPR`
+//! nodes of `a.b.b.b…` that makes building the tree quadratic in the depth. This cache stores the
+//! hash with every entry instead, and only interns small nodes: large nodes rarely repeat and
+//! comparing them is costly.
+
+use crate::{NodeOrToken, SmolStr};
+use rowan::{GreenNode, GreenToken, SyntaxKind, TextSize};
+use smallvec::SmallVec;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+pub(super) type GreenElement = NodeOrToken<GreenNode, GreenToken>;
+
+/// Nodes with more text than this are not interned. This also limits the depth of interned
+/// subtrees, because the text of a node grows with every level of nesting.
+const MAX_INTERNED_LEN: u32 = 128;
+
+/// The key of an interned node: its kind and the identities of its children, which are interned
+/// themselves. `hash` is computed from the hashes of the children when the node is created.
+#[derive(PartialEq, Eq)]
+struct NodeKey {
+    hash: u64,
+    kind: SyntaxKind,
+    children: SmallVec<[usize; 4]>,
+}
+
+impl Hash for NodeKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash)
+    }
+}
+
+#[derive(Default)]
+pub(super) struct NodeCache {
+    nodes: HashMap<NodeKey, GreenNode>,
+    tokens: HashMap<(SyntaxKind, SmolStr), (u64, GreenToken)>,
+}
+
+impl NodeCache {
+    /// Returns the token of `kind` with `text` and its hash.
+    pub(super) fn token(&mut self, kind: SyntaxKind, text: SmolStr) -> (Option<u64>, GreenToken) {
+        let (hash, token) = self
+            .tokens
+            .entry((kind, text))
+            .or_insert_with_key(|(kind, text)| {
+                let mut hasher = DefaultHasher::new();
+                (kind, text).hash(&mut hasher);
+                (hasher.finish(), GreenToken::new(*kind, text))
+            });
+        (Some(*hash), token.clone())
+    }
+
+    /// Returns the number of interned nodes.
+    #[cfg(test)]
+    pub(super) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Builds the node of `kind` from the elements of `children` after `first_child`, which are
+    /// removed. Returns the hash of the node if it is interned. A node is only interned if all its
+    /// children are.
+    pub(super) fn node(
+        &mut self,
+        kind: SyntaxKind,
+        children: &mut Vec<(Option<u64>, GreenElement)>,
+        first_child: usize,
+    ) -> (Option<u64>, GreenNode) {
+        let new_children = &children[first_child..];
+        let text_len: TextSize = new_children
+            .iter()
+            .map(|(_, element)| match element {
+                NodeOrToken::Node(node) => node.text_len(),
+                NodeOrToken::Token(token) => token.text_len(),
+            })
+            .sum();
+        let mut hasher = DefaultHasher::new();
+        kind.hash(&mut hasher);
+        let mut is_interned = text_len <= TextSize::from(MAX_INTERNED_LEN);
+        for (hash, _) in new_children {
+            match hash {
+                Some(hash) if is_interned => hasher.write_u64(*hash),
+                _ => is_interned = false,
+            }
+        }
+        if !is_interned {
+            let node = GreenNode::new(kind, children.drain(first_child..).map(|(_, it)| it));
+            return (None, node);
+        }
+
+        let key = NodeKey {
+            hash: hasher.finish(),
+            kind,
+            children: new_children
+                .iter()
+                .map(|(_, element)| element_id(element))
+                .collect(),
+        };
+        let hash = key.hash;
+        let node = self
+            .nodes
+            .entry(key)
+            .or_insert_with(|| {
+                GreenNode::new(
+                    kind,
+                    children[first_child..]
+                        .iter()
+                        .map(|(_, element)| element.clone()),
+                )
+            })
+            .clone();
+        children.truncate(first_child);
+        (Some(hash), node)
+    }
+}
+
+/// Returns the address of the data of `element`, which identifies interned elements.
+fn element_id(element: &GreenElement) -> usize {
+    match element {
+        NodeOrToken::Node(node) => &**node as *const _ as *const () as usize,
+        NodeOrToken::Token(token) => &**token as *const _ as *const () as usize,
+    }
+}
@@ -1,155 +1,175 @@
-use std::{fmt, ops};
+//! Searches in the text of a node without converting it to a `String`.
+//!
+//! The text of a node is stored in its tokens, so `SyntaxText` is a sequence of chunks, one per
+//! token. Comparing two texts with `==` and folding over the chunks with `try_fold_chunks` is
+//! provided by `rowan`; the queries here also match across chunk boundaries.
 
-use crate::{SyntaxElement, SyntaxNode, TextRange, TextUnit};
+use crate::{SyntaxText, TextSize};
+use memchr::memmem;
 
-#[derive(Clone)]
-pub struct SyntaxText<'a> {
-    node: &'a SyntaxNode,
-    range: TextRange,
-}
-
-impl<'a> SyntaxText<'a> {
-    pub(crate) fn new(node: &'a SyntaxNode) -> SyntaxText<'a> {
-        SyntaxText {
-            node,
-            range: node.range(),
-        }
-    }
-
-    pub fn chunks(&self) -> impl Iterator<Item = &'a str> {
-        let range = self.range;
-        self.node
-            .descendants_with_tokens()
-            .filter_map(move |el| match el {
-                SyntaxElement::Token(t) => {
-                    let text = t.text();
-                    let range = range.intersection(&t.range())?;
-                    let range = range - t.range().start();
-                    Some(&text[range])
-                }
-                SyntaxElement::Node(_) => None,
-            })
-    }
+/// Queries on the text of a node, e.g. `node.text().starts_with("local")`.
+pub trait SyntaxTextExt {
+    fn starts_with(&self, prefix: &str) -> bool;
 
-    pub fn push_to(&self, buf: &mut String) {
-        self.chunks().for_each(|it| buf.push_str(it));
-    }
+    /// Returns the offset of the first occurrence of `needle`, relative to the start of the text.
+    fn find_str(&self, needle: &str) -> Option<TextSize>;
 
-    pub fn to_string(&self) -> String {
-        self.chunks().collect()
-    }
+    /// Returns the characters of the text with their offsets, relative to the start of the text.
+    /// The text is copied once, as the chunks can't outlive a fold over them.
+    fn char_indices(&self) -> CharIndices;
 
-    pub fn contains(&self, c: char) -> bool {
-        self.chunks().any(|it| it.contains(c))
-    }
+    /// Returns the character that starts at `offset`, or `None` if `offset` is past the end of the
+    /// text or inside a character. Unlike `SyntaxText::char_at` it doesn't panic in the latter case.
+    fn try_char_at(&self, offset: TextSize) -> Option<char>;
+}
 
-    pub fn find(&self, c: char) -> Option<TextUnit> {
-        let mut acc: TextUnit = 0.into();
-        for chunk in self.chunks() {
-            if let Some(pos) = chunk.find(c) {
-                let pos: TextUnit = (pos as u32).into();
-                return Some(acc + pos);
-            }
-            acc += TextUnit::of_str(chunk);
+impl SyntaxTextExt for SyntaxText {
+    fn starts_with(&self, prefix: &str) -> bool {
+        if TextSize::of(prefix) > self.len() {
+            return false;
         }
-        None
-    }
-
-    pub fn len(&self) -> TextUnit {
-        self.range.len()
-    }
-
-    pub fn slice(&self, range: impl SyntaxTextSlice) -> SyntaxText<'a> {
-        let range = range.restrict(self.range).unwrap_or_else(|| {
-            panic!("invalid slice, range: {:?}, slice: {:?}", self.range, range)
+        // `Err` stops the fold once the result is known
+        let rest = self.try_fold_chunks(prefix.as_bytes(), |rest, chunk| {
+            if rest.is_empty() {
+                return Err(true);
+            }
+            let len = rest.len().min(chunk.len());
+            if rest[..len] == chunk.as_bytes()[..len] {
+                Ok(&rest[len..])
+            } else {
+                Err(false)
+            }
         });
-        SyntaxText {
-            node: self.node,
-            range,
+        match rest {
+            Ok(rest) => rest.is_empty(),
+            Err(result) => result,
         }
     }
 
-    pub fn char_at(&self, offset: impl Into<TextUnit>) -> Option<char> {
-        let mut start: TextUnit = 0.into();
-        let offset = offset.into();
-        for chunk in self.chunks() {
-            let end = start + TextUnit::of_str(chunk);
-            if start <= offset && offset < end {
-                let off: usize = u32::from(offset - start) as usize;
-                return Some(chunk[off..].chars().next().unwrap());
+    fn find_str(&self, needle: &str) -> Option<TextSize> {
+        let finder = memmem::Finder::new(needle);
+        // The end of the previous chunks, where a match can start that ends in the next chunk
+        let mut window = Vec::new();
+        let mut window_start = 0;
+        let found = self.try_for_each_chunk(|chunk| {
+            window.extend_from_slice(chunk.as_bytes());
+            if let Some(idx) = finder.find(&window) {
+                return Err(window_start + idx);
             }
-            start = end;
+            let keep = window.len().min(needle.len().saturating_sub(1));
+            let drop = window.len() - keep;
+            window.drain(..drop);
+            window_start += drop;
+            Ok(())
+        });
+        match found {
+            Err(idx) => Some(TextSize::from(idx as u32)),
+            // An empty needle is found in an empty text, which has no chunks
+            Ok(()) if needle.is_empty() => Some(TextSize::from(0)),
+            Ok(()) => None,
         }
-        None
     }
-}
 
-impl<'a> fmt::Debug for SyntaxText<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(&self.to_string(), f)
-    }
-}
-
-impl<'a> fmt::Display for SyntaxText<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.to_string(), f)
-    }
-}
-
-pub trait SyntaxTextSlice: fmt::Debug {
-    fn restrict(&self, range: TextRange) -> Option<TextRange>;
-}
-
-impl SyntaxTextSlice for TextRange {
-    fn restrict(&self, range: TextRange) -> Option<TextRange> {
-        self.intersection(&range)
-    }
-}
-
-impl SyntaxTextSlice for ops::RangeTo<TextUnit> {
-    fn restrict(&self, range: TextRange) -> Option<TextRange> {
-        if !range.contains_inclusive(self.end) {
-            return None;
+    fn char_indices(&self) -> CharIndices {
+        CharIndices {
+            text: self.to_string(),
+            idx: 0,
         }
-        Some(TextRange::from_to(range.start(), self.end))
     }
-}
 
-impl SyntaxTextSlice for ops::RangeFrom<TextUnit> {
-    fn restrict(&self, range: TextRange) -> Option<TextRange> {
-        if !range.contains_inclusive(self.start) {
-            return None;
-        }
-        Some(TextRange::from_to(self.start, range.end()))
+    fn try_char_at(&self, offset: TextSize) -> Option<char> {
+        let offset = usize::from(offset);
+        let mut chunk_start = 0;
+        let found = self.try_for_each_chunk(|chunk| {
+            if offset < chunk_start + chunk.len() {
+                let idx = offset - chunk_start;
+                return Err(chunk.get(idx..).and_then(|rest| rest.chars().next()));
+            }
+            chunk_start += chunk.len();
+            Ok(())
+        });
+        found.err().flatten()
     }
 }
 
-impl SyntaxTextSlice for ops::Range<TextUnit> {
-    fn restrict(&self, range: TextRange) -> Option<TextRange> {
-        TextRange::from_to(self.start, self.end).restrict(range)
-    }
+/// An iterator over the characters of a `SyntaxText` and their offsets, see
+/// `SyntaxTextExt::char_indices`.
+#[derive(Debug, Clone)]
+pub struct CharIndices {
+    text: String,
+    idx: usize,
 }
 
-impl From<SyntaxText<'_>> for String {
-    fn from(text: SyntaxText) -> String {
-        text.to_string()
-    }
-}
+impl Iterator for CharIndices {
+    type Item = (TextSize, char);
 
-impl PartialEq<str> for SyntaxText<'_> {
-    fn eq(&self, mut rhs: &str) -> bool {
-        for chunk in self.chunks() {
-            if !rhs.starts_with(chunk) {
-                return false;
-            }
-            rhs = &rhs[chunk.len()..];
-        }
-        rhs.is_empty()
+    fn next(&mut self) -> Option<(TextSize, char)> {
+        let c = self.text[self.idx..].chars().next()?;
+        let offset = TextSize::from(self.idx as u32);
+        self.idx += c.len_utf8();
+        Some((offset, c))
     }
 }
 
-impl PartialEq<&'_ str> for SyntaxText<'_> {
-    fn eq(&self, rhs: &&str) -> bool {
-        self == *rhs
+#[cfg(test)]
+mod tests {
+    use super::SyntaxTextExt;
+    use crate::{Chunk, SyntaxKind::*, TextSize};
+
+    #[test]
+    fn test_search_across_tokens() {
+        let node = Chunk::parse("local s = 'é' .. x").syntax_node();
+        let text = node.text();
+        assert!(text.starts_with("local s"));
+        assert!(text.starts_with(""));
+        assert!(!text.starts_with("locals"));
+        assert!(!text.starts_with("local s = 'é' .. x!"));
+
+        assert_eq!(text.find_str("s = 'é'"), Some(TextSize::from(6)));
+        assert_eq!(text.find_str(".. x"), Some(TextSize::from(15)));
+        assert_eq!(text.find_str("x"), Some(TextSize::from(18)));
+        assert_eq!(text.find_str(""), Some(TextSize::from(0)));
+        assert_eq!(text.find_str("..x"), None);
+
+        let chars: Vec<_> = text
+            .slice(TextSize::from(10)..)
+            .char_indices()
+            .take(3)
+            .collect();
+        assert_eq!(
+            chars,
+            vec![
+                (TextSize::from(0), '\''),
+                (TextSize::from(1), 'é'),
+                (TextSize::from(3), '\'')
+            ]
+        );
+    }
+
+    #[test]
+    fn test_try_char_at() {
+        let node = Chunk::parse("x = 'é😀'").syntax_node();
+        let text = node.text();
+        assert_eq!(text.try_char_at(TextSize::from(0)), Some('x'));
+        assert_eq!(text.try_char_at(TextSize::from(5)), Some('é'));
+        assert_eq!(text.try_char_at(TextSize::from(6)), None);
+        assert_eq!(text.try_char_at(TextSize::from(7)), Some('😀'));
+        assert_eq!(text.try_char_at(TextSize::from(9)), None);
+        assert_eq!(text.try_char_at(TextSize::from(11)), Some('\''));
+        assert_eq!(text.try_char_at(TextSize::from(12)), None);
+    }
+
+    #[test]
+    fn test_compare_texts() {
+        let node = Chunk::parse("x = a + a").syntax_node();
+        let names: Vec<_> = node
+            .descendants()
+            .filter(|node| node.kind() == NAME_REF)
+            .map(|node| node.text())
+            .collect();
+        // `x` and both `a`s
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[1], names[2]);
+        assert_ne!(names[0], names[1]);
     }
 }
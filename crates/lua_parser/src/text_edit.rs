@@ -0,0 +1,192 @@
+//! Describes changes to a text as a set of non-overlapping replacements.
+
+use crate::{SourceMap, TextRange, TextSize};
+
+/// Replaces the text in `delete` with `insert`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AtomTextEdit {
+    pub delete: TextRange,
+    pub insert: String,
+}
+
+impl AtomTextEdit {
+    pub fn replace(range: TextRange, replace_with: String) -> AtomTextEdit {
+        AtomTextEdit {
+            delete: range,
+            insert: replace_with,
+        }
+    }
+
+    pub fn delete(range: TextRange) -> AtomTextEdit {
+        AtomTextEdit::replace(range, String::new())
+    }
+
+    pub fn insert(offset: TextSize, text: String) -> AtomTextEdit {
+        AtomTextEdit::replace(TextRange::at(offset, 0.into()), text)
+    }
+}
+
+/// A set of `AtomTextEdit`s that are applied to a text at once. All ranges refer to the original
+/// text and do not overlap.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct TextEdit {
+    atoms: Vec<AtomTextEdit>,
+}
+
+impl TextEdit {
+    pub fn insert(offset: TextSize, text: String) -> TextEdit {
+        let mut builder = TextEditBuilder::default();
+        builder.insert(offset, text);
+        builder.finish()
+    }
+
+    pub fn delete(range: TextRange) -> TextEdit {
+        let mut builder = TextEditBuilder::default();
+        builder.delete(range);
+        builder.finish()
+    }
+
+    pub fn replace(range: TextRange, replace_with: String) -> TextEdit {
+        let mut builder = TextEditBuilder::default();
+        builder.replace(range, replace_with);
+        builder.finish()
+    }
+
+    /// Returns the edits ordered by their position in the original text.
+    pub fn as_atoms(&self) -> &[AtomTextEdit] {
+        &self.atoms
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.atoms.is_empty()
+    }
+
+    /// Returns the result of applying the edits to `text`.
+    pub fn apply(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut last = 0;
+        for atom in self.atoms.iter() {
+            let start = u32::from(atom.delete.start()) as usize;
+            let end = u32::from(atom.delete.end()) as usize;
+            result.push_str(&text[last..start]);
+            result.push_str(&atom.insert);
+            last = end;
+        }
+        result.push_str(&text[last..]);
+        result
+    }
+
+    /// Returns a `SourceMap` that maps the result of `apply(text)` back to `text`. Unchanged text
+    /// maps to itself and inserted text maps to the range it replaced.
+    pub fn source_map(&self, text: &str) -> SourceMap {
+        let mut source_map = SourceMap::new();
+        let mut original = TextSize::from(0);
+        let mut generated = TextSize::from(0);
+        let mut add = |original: TextRange, generated_len: TextSize, generated: &mut TextSize| {
+            if generated_len > 0.into() {
+                source_map.add(TextRange::at(*generated, generated_len), original);
+            }
+            *generated += generated_len;
+        };
+        for atom in self.atoms.iter() {
+            let unchanged = TextRange::new(original, atom.delete.start());
+            add(unchanged, unchanged.len(), &mut generated);
+            add(atom.delete, TextSize::of(&atom.insert), &mut generated);
+            original = atom.delete.end();
+        }
+        let rest = TextRange::new(original, TextSize::of(text));
+        add(rest, rest.len(), &mut generated);
+        source_map
+    }
+
+    /// Maps an offset in the original text to the corresponding offset in the edited text.
+    /// Returns `None` if the offset is inside a range that is replaced.
+    pub fn apply_to_offset(&self, offset: TextSize) -> Option<TextSize> {
+        let mut result = offset;
+        for atom in self.atoms.iter() {
+            if atom.delete.start() >= offset {
+                break;
+            }
+            if offset < atom.delete.end() {
+                return None;
+            }
+            result += TextSize::of(&atom.insert);
+            result -= atom.delete.len();
+        }
+        Some(result)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TextEditBuilder {
+    atoms: Vec<AtomTextEdit>,
+}
+
+impl TextEditBuilder {
+    pub fn replace(&mut self, range: TextRange, replace_with: String) {
+        self.atoms.push(AtomTextEdit::replace(range, replace_with))
+    }
+
+    pub fn delete(&mut self, range: TextRange) {
+        self.atoms.push(AtomTextEdit::delete(range))
+    }
+
+    pub fn insert(&mut self, offset: TextSize, text: String) {
+        self.atoms.push(AtomTextEdit::insert(offset, text))
+    }
+
+    /// Finishes the edit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the edited ranges overlap.
+    pub fn finish(self) -> TextEdit {
+        let mut atoms = self.atoms;
+        atoms.sort_by_key(|atom| (atom.delete.start(), atom.delete.end()));
+        for pair in atoms.windows(2) {
+            assert!(
+                pair[0].delete.end() <= pair[1].delete.start(),
+                "overlapping text edits: {:?} and {:?}",
+                pair[0].delete,
+                pair[1].delete
+            );
+        }
+        TextEdit { atoms }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: u32, end: u32) -> TextRange {
+        TextRange::new(start.into(), end.into())
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut builder = TextEditBuilder::default();
+        builder.replace(range(6, 7), "value".to_string());
+        builder.insert(0.into(), "local ".to_string());
+        builder.delete(range(9, 12));
+        let edit = builder.finish();
+        assert_eq!(edit.apply("print(a) --x"), "local print(value) ");
+    }
+
+    #[test]
+    fn test_source_map() {
+        let edit = TextEdit::replace(range(6, 7), "value".to_string());
+        let source_map = edit.source_map("print(a)");
+        assert_eq!(source_map.original_offset(3.into()), Some(3.into()));
+        assert_eq!(source_map.original_offset(8.into()), Some(6.into()));
+        assert_eq!(source_map.original_offset(11.into()), Some(7.into()));
+    }
+
+    #[test]
+    fn test_apply_to_offset() {
+        let edit = TextEdit::replace(range(2, 4), "abc".to_string());
+        assert_eq!(edit.apply_to_offset(1.into()), Some(1.into()));
+        assert_eq!(edit.apply_to_offset(3.into()), None);
+        assert_eq!(edit.apply_to_offset(5.into()), Some(6.into()));
+    }
+}
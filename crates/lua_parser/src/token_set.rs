@@ -0,0 +1,64 @@
+use crate::SyntaxKind;
+use std::{convert::TryFrom, fmt};
+
+/// A bit-set of `SyntaxKind`s, used to describe the set of tokens that are valid at a certain
+/// point while parsing.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TokenSet(u128);
+
+impl TokenSet {
+    pub const EMPTY: TokenSet = TokenSet(0);
+
+    pub const fn singleton(kind: SyntaxKind) -> TokenSet {
+        TokenSet(mask(kind))
+    }
+
+    pub const fn union(self, other: TokenSet) -> TokenSet {
+        TokenSet(self.0 | other.0)
+    }
+
+    pub fn contains(&self, kind: SyntaxKind) -> bool {
+        self.0 & mask(kind) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns an iterator over all the kinds in this set, ordered by their numeric value.
+    pub fn iter(&self) -> impl Iterator<Item = SyntaxKind> {
+        let bits = self.0;
+        (0..128u16)
+            .filter(move |idx| bits & (1u128 << idx) != 0)
+            .map(|idx| SyntaxKind::try_from(idx).expect("token sets only contain syntax kinds"))
+    }
+}
+
+impl fmt::Debug for TokenSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+// Every kind must fit in the bits of a `u128`, otherwise `mask` overflows
+const _: () = assert!((SyntaxKind::__LAST as usize) <= 128);
+
+const fn mask(kind: SyntaxKind) -> u128 {
+    1u128 << (kind as usize)
+}
+
+#[macro_export]
+macro_rules! token_set {
+    ($($t:ident),*) => { $crate::TokenSet::EMPTY$(.union($crate::TokenSet::singleton($t)))* };
+    ($($t:ident),* ,) => { $crate::token_set!($($t),*) };
+}
+
+#[test]
+fn token_set_works_for_tokens() {
+    use crate::SyntaxKind::*;
+    let ts = token_set![EOF, COMMENT];
+    assert!(ts.contains(EOF));
+    assert!(ts.contains(COMMENT));
+    assert!(!ts.contains(PLUS));
+    assert_eq!(ts.iter().collect::<Vec<_>>(), vec![EOF, COMMENT]);
+}
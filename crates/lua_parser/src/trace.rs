@@ -0,0 +1,99 @@
+//! Instrumentation with the `tracing` crate, so that integrators can find out where the time goes
+//! in production editors. Lexing, parsing and validation run in spans that record the size of
+//! their input, and events report the results, e.g. the number of tokens that relexing reused.
+//!
+//! The instrumentation is only compiled with the `tracing` feature, without it the macros expand
+//! to nothing.
+
+/// Enters a `DEBUG` span that lasts until the end of the enclosing block.
+macro_rules! trace_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($($arg)*).entered();
+    };
+}
+
+/// Emits a `DEBUG` event in the current span.
+macro_rules! trace_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use crate::{Chunk, Workspace};
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    /// Records the names of the spans that are created and the messages of the events.
+    #[derive(Default)]
+    struct Recorder {
+        next_id: AtomicU64,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct Message(String);
+
+    impl Visit for Message {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.0 = format!("{:?}", value);
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes) -> span::Id {
+            self.log
+                .lock()
+                .unwrap()
+                .push(span.metadata().name().to_string());
+            span::Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record) {}
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut message = Message(String::new());
+            event.record(&mut message);
+            self.log.lock().unwrap().push(message.0);
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[test]
+    fn test_spans_and_events() {
+        let recorder = Recorder::default();
+        let log = recorder.log.clone();
+        tracing::subscriber::with_default(recorder, || {
+            let mut workspace = Workspace::new();
+            let file_id = workspace.add_file("main.lua", "local x = 1");
+            workspace.parse(file_id);
+            workspace.parse(file_id);
+            Chunk::parse("print(x)");
+        });
+        let log = log.lock().unwrap();
+        let count = |entry: &str| log.iter().filter(|logged| *logged == entry).count();
+        assert_eq!(count("parse"), 2, "{:?}", log);
+        assert_eq!(count("tokenize"), 2, "{:?}", log);
+        assert_eq!(count("parsed"), 2, "{:?}", log);
+        assert_eq!(count("parsing file"), 1, "{:?}", log);
+    }
+}
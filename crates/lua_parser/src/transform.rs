@@ -0,0 +1,320 @@
+//! Rewrites syntax trees, the basis of migrations between Lua versions and macro expanders.
+//!
+//! A `Rewriter` is offered every node of a tree, children before their parents, and decides
+//! whether to replace it. A node is offered after everything inside it was rewritten, so the
+//! rewriter sees the results of the rewrites below, e.g. a call whose arguments were already
+//! replaced. `transform` returns the new tree together with the edit that turns the original text
+//! into the new text and a source map between both.
+
+use crate::{
+    ast::Expr,
+    tokenize, AstNode, Chunk, GreenNode, NodeOrToken, SourceMap, SyntaxElement,
+    SyntaxKind::{self, *},
+    SyntaxNode, TextEdit, TextEditBuilder, TextRange, TextSize,
+};
+use rowan::GreenToken;
+
+type GreenElement = NodeOrToken<GreenNode, GreenToken>;
+
+/// What a node is replaced by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Replacement {
+    /// Code that is parsed in the place of the node: as statements if the node is a statement, as
+    /// an expression if the node is an expression and as plain tokens otherwise, e.g. for a
+    /// `NAME`.
+    Text(String),
+    /// A node, e.g. one that was built with `lua_quote!` or taken from another tree. A `CHUNK`
+    /// that replaces a statement is replaced by its statements.
+    Node(SyntaxNode),
+    /// Removes the node, e.g. a statement.
+    Remove,
+}
+
+pub trait Rewriter {
+    /// Returns what `node` is replaced by, or `None` to keep it. `node` is part of the tree in
+    /// which everything before it and inside it is already rewritten, so its text range refers
+    /// to the partially rewritten text.
+    fn rewrite(&mut self, node: &SyntaxNode) -> Option<Replacement>;
+}
+
+impl<F> Rewriter for F
+where
+    F: FnMut(&SyntaxNode) -> Option<Replacement>,
+{
+    fn rewrite(&mut self, node: &SyntaxNode) -> Option<Replacement> {
+        self(node)
+    }
+}
+
+/// The result of `transform`.
+#[derive(Debug, Clone)]
+pub struct Transformed {
+    root: SyntaxNode,
+    edit: TextEdit,
+    source_map: SourceMap,
+}
+
+impl Transformed {
+    /// Returns the root of the rewritten tree.
+    pub fn syntax_node(&self) -> SyntaxNode {
+        self.root.clone()
+    }
+
+    pub fn text(&self) -> String {
+        self.root.text().to_string()
+    }
+
+    /// Returns the edit that turns the original text into the rewritten text. It replaces every
+    /// node that was rewritten and isn't inside another rewritten node.
+    pub fn edit(&self) -> &TextEdit {
+        &self.edit
+    }
+
+    /// Returns the map from the rewritten text to the original text.
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    pub fn is_changed(&self) -> bool {
+        !self.edit.is_empty()
+    }
+}
+
+/// Rewrites the tree of `root` with `rewriter`, see the module documentation. The original tree
+/// is not modified; the text ranges of the edit and the source map are relative to the start of
+/// `root`.
+pub fn transform(root: &SyntaxNode, rewriter: &mut dyn Rewriter) -> Transformed {
+    let original = root.clone_subtree();
+    let mut transformer = Transformer {
+        root: original.clone(),
+        rewriter,
+        edits: Vec::new(),
+    };
+    transformer.visit(&original, &mut Vec::new());
+
+    let mut builder = TextEditBuilder::default();
+    for (range, text) in transformer.edits {
+        builder.replace(range, text);
+    }
+    let edit = builder.finish();
+    let source_map = edit.source_map(&original.text().to_string());
+    Transformed {
+        root: transformer.root,
+        edit,
+        source_map,
+    }
+}
+
+struct Transformer<'a> {
+    /// The root of the tree that is being rewritten
+    root: SyntaxNode,
+    rewriter: &'a mut dyn Rewriter,
+    /// The replaced ranges of the original text and their new text, in the order of the text
+    edits: Vec<(TextRange, String)>,
+}
+
+impl Transformer<'_> {
+    /// Rewrites the node at `path` in the current tree, whose children are the children of
+    /// `original` that are not rewritten yet. Returns the number of elements that took its place.
+    fn visit(&mut self, original: &SyntaxNode, path: &mut Vec<usize>) -> usize {
+        let edits_before = self.edits.len();
+        let mut idx = 0;
+        for child in original.children_with_tokens() {
+            match child {
+                NodeOrToken::Node(child) => {
+                    path.push(idx);
+                    idx += self.visit(&child, path);
+                    path.pop();
+                }
+                NodeOrToken::Token(_) => idx += 1,
+            }
+        }
+
+        let node = self.node_at(path);
+        let elements = match self.rewriter.rewrite(&node) {
+            None => return 1,
+            Some(Replacement::Text(text)) => parse_in_place_of(&node, &text),
+            Some(Replacement::Node(new)) => node_in_place_of(&node, &new),
+            Some(Replacement::Remove) => Vec::new(),
+        };
+        let text: String = elements.iter().map(|element| element.to_string()).collect();
+        // The edits inside the node are part of the replacement
+        self.edits.truncate(edits_before);
+        self.edits.push((original.text_range(), text));
+
+        let n_elements = elements.len();
+        let root = match node.parent() {
+            Some(parent) => {
+                let idx = node.index();
+                parent.replace_with(parent.green().splice_children(idx..idx + 1, elements))
+            }
+            None => match single_node(&elements) {
+                Some(green) => green,
+                None => GreenNode::new(raw_kind(node.kind()), elements),
+            },
+        };
+        self.root = SyntaxNode::new_root(root);
+        n_elements
+    }
+
+    fn node_at(&self, path: &[usize]) -> SyntaxNode {
+        path.iter().fold(self.root.clone(), |node, &idx| {
+            node.children_with_tokens()
+                .nth(idx)
+                .and_then(|element| element.into_node())
+                .expect("rewritten nodes keep the position of their siblings")
+        })
+    }
+}
+
+/// Parses the code of a `Replacement::Text` that replaces `node`.
+fn parse_in_place_of(node: &SyntaxNode, text: &str) -> Vec<GreenElement> {
+    let is_statement = node.parent().is_some_and(|parent| parent.kind() == BLOCK);
+    if matches!(node.kind(), CHUNK | BLOCK) || is_statement {
+        let chunk = Chunk::parse(text).syntax_node();
+        if node.kind() == CHUNK {
+            return vec![NodeOrToken::Node(chunk.green().into_owned())];
+        }
+        let block = chunk.first_child().expect("a chunk always has a block");
+        if node.kind() == BLOCK {
+            return vec![NodeOrToken::Node(block.green().into_owned())];
+        }
+        return block.children_with_tokens().map(to_green).collect();
+    }
+    if Expr::can_cast(node.kind()) {
+        let chunk = Chunk::parse(&format!("return {}", text.trim())).syntax_node();
+        if let Some(exprs) = chunk.descendants().find(|node| node.kind() == EXPR_LIST) {
+            return exprs.children_with_tokens().map(to_green).collect();
+        }
+    }
+
+    let mut offset = TextSize::from(0);
+    let tokens = tokenize(text).into_iter().map(|token| {
+        let range = TextRange::at(offset, token.len);
+        offset += token.len;
+        NodeOrToken::Token(GreenToken::new(raw_kind(token.kind), &text[range]))
+    });
+    vec![NodeOrToken::Node(GreenNode::new(
+        raw_kind(node.kind()),
+        tokens.collect::<Vec<_>>(),
+    ))]
+}
+
+/// Returns the elements of a `Replacement::Node` that replaces `node`.
+fn node_in_place_of(node: &SyntaxNode, new: &SyntaxNode) -> Vec<GreenElement> {
+    let is_statement = node.parent().is_some_and(|parent| parent.kind() == BLOCK);
+    if new.kind() == CHUNK && is_statement {
+        if let Some(block) = new.first_child() {
+            return block.children_with_tokens().map(to_green).collect();
+        }
+    }
+    vec![NodeOrToken::Node(new.green().into_owned())]
+}
+
+fn single_node(elements: &[GreenElement]) -> Option<GreenNode> {
+    match elements {
+        [NodeOrToken::Node(node)] => Some(node.clone()),
+        _ => None,
+    }
+}
+
+fn to_green(element: SyntaxElement) -> GreenElement {
+    match element {
+        NodeOrToken::Node(node) => NodeOrToken::Node(node.green().into_owned()),
+        NodeOrToken::Token(token) => NodeOrToken::Token(token.green().to_owned()),
+    }
+}
+
+fn raw_kind(kind: SyntaxKind) -> rowan::SyntaxKind {
+    rowan::SyntaxKind(kind.into_raw())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::CallExpr;
+
+    fn transform_text(text: &str, rewriter: &mut dyn Rewriter) -> Transformed {
+        let transformed = transform(&Chunk::parse(text).syntax_node(), rewriter);
+        assert_eq!(transformed.edit().apply(text), transformed.text());
+        transformed
+    }
+
+    #[test]
+    fn test_rename_references() {
+        let text = "local a, b = unpack(t)\nprint(unpack(u))";
+        let transformed = transform_text(text, &mut |node: &SyntaxNode| {
+            if node.kind() == NAME_REF && node.text() == "unpack" {
+                Some(Replacement::Text("table.unpack".to_string()))
+            } else {
+                None
+            }
+        });
+        assert_eq!(
+            transformed.text(),
+            "local a, b = table.unpack(t)\nprint(table.unpack(u))"
+        );
+        assert_eq!(transformed.edit().as_atoms().len(), 2);
+        assert!(transformed
+            .syntax_node()
+            .descendants()
+            .any(|node| node.kind() == FIELD_EXPR));
+
+        let source_map = transformed.source_map();
+        // `(t)` after the first replacement
+        assert_eq!(
+            source_map.original_offset(TextSize::from(25)),
+            Some(TextSize::from(19))
+        );
+        assert_eq!(
+            source_map.original_offset(TextSize::from(16)),
+            Some(TextSize::from(13))
+        );
+    }
+
+    #[test]
+    fn test_parents_see_rewritten_children() {
+        let text = "x = double(double(y))";
+        let transformed = transform_text(text, &mut |node: &SyntaxNode| {
+            let call = CallExpr::cast(node.clone())?;
+            if call.callee()?.syntax().text() != "double" {
+                return None;
+            }
+            let arg = call.arg_list()?.syntax().first_child()?;
+            Some(Replacement::Text(format!("({}) * 2", arg.text())))
+        });
+        assert_eq!(transformed.text(), "x = ((y) * 2) * 2");
+        // The edit of the inner call is part of the edit of the outer call
+        assert_eq!(transformed.edit().as_atoms().len(), 1);
+        let kinds: Vec<_> = transformed
+            .syntax_node()
+            .descendants()
+            .map(|node| node.kind())
+            .filter(|kind| kind.is_expression_kind())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![NAME_REF, BIN_EXPR, PAREN_EXPR, BIN_EXPR, PAREN_EXPR, NAME_REF, LITERAL, LITERAL]
+        );
+    }
+
+    #[test]
+    fn test_replace_and_remove_statements() {
+        let text = "debug(1)\nlocal x = 1\ndebug(x) do debug(2) end\nreturn x";
+        let quoted = crate::lua_quote!("local y = x * 2\ny = y + 1").unwrap();
+        let transformed = transform_text(text, &mut |node: &SyntaxNode| {
+            if node.kind() == EXPR_STMT && node.text().to_string().starts_with("debug") {
+                Some(Replacement::Remove)
+            } else if node.kind() == RETURN_STMT {
+                Some(Replacement::Node(quoted.syntax().clone()))
+            } else {
+                None
+            }
+        });
+        assert_eq!(
+            transformed.text(),
+            "\nlocal x = 1\n do  end\nlocal y = x * 2\ny = y + 1"
+        );
+        assert!(Chunk::parse(&transformed.text()).errors().is_empty());
+    }
+}
@@ -0,0 +1,300 @@
+//! A binary format for parses, so that indexing tools can store the trees of a large workspace on
+//! disk and skip parsing the files that didn't change since the previous run.
+//!
+//! The format starts with a header of a magic number, the version of the format and the length
+//! and a hash of the source text. The tree follows in pre-order as a sequence of node starts,
+//! node ends and tokens, and then the errors. Tokens only store their kind and length, their text
+//! is taken from the source when the parse is loaded, which is why loading requires the text
+//! the parse was created from. All numbers are little endian.
+//!
+//! `SyntaxKind`s are stored by their numeric value. When the numbering of the kinds changes,
+//! `TREE_CACHE_VERSION` must be bumped so that old caches are rejected.
+
+use crate::{
+    syntax_error::Location, AstNode, Parse, ParseError, SyntaxError, SyntaxErrorKind, SyntaxKind,
+    SyntaxNode, SyntaxTreeBuilder, TextRange, TextSize, TokenSet,
+};
+use rowan::{NodeOrToken, WalkEvent};
+use std::{convert::TryFrom, fmt};
+
+/// The version of the format, stored in the header of every serialized parse.
+pub const TREE_CACHE_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 4] = b"LUAT";
+
+const START_NODE: u8 = 0;
+const FINISH_NODE: u8 = 1;
+const TOKEN: u8 = 2;
+
+const OFFSET: u8 = 0;
+const RANGE: u8 = 1;
+
+/// The reasons a serialized parse can't be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeCacheError {
+    /// The bytes are not a serialized parse, or they are truncated or corrupted.
+    InvalidFormat,
+    /// The parse was serialized with another version of the format.
+    VersionMismatch { found: u32, expected: u32 },
+    /// The text differs from the text the parse was created from, so the file has to be parsed
+    /// again.
+    SourceChanged,
+}
+
+impl fmt::Display for TreeCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TreeCacheError::InvalidFormat => f.write_str("invalid tree cache"),
+            TreeCacheError::VersionMismatch { found, expected } => write!(
+                f,
+                "tree cache has version {}, expected version {}",
+                found, expected
+            ),
+            TreeCacheError::SourceChanged => f.write_str("the source of the tree cache changed"),
+        }
+    }
+}
+
+impl std::error::Error for TreeCacheError {}
+
+/// Serializes the tree and errors of `parse`, which was created from `text`.
+pub fn serialize_parse<T>(parse: &Parse<T>, text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    bytes.extend_from_slice(MAGIC);
+    write_u32(&mut bytes, TREE_CACHE_VERSION);
+    write_u32(&mut bytes, text.len() as u32);
+    bytes.extend_from_slice(&source_hash(text).to_le_bytes());
+
+    for event in parse.syntax_node().preorder_with_tokens() {
+        match event {
+            WalkEvent::Enter(NodeOrToken::Node(node)) => {
+                bytes.push(START_NODE);
+                write_u16(&mut bytes, node.kind().into_raw());
+            }
+            WalkEvent::Leave(NodeOrToken::Node(_)) => bytes.push(FINISH_NODE),
+            WalkEvent::Enter(NodeOrToken::Token(token)) => {
+                bytes.push(TOKEN);
+                write_u16(&mut bytes, token.kind().into_raw());
+                write_u32(&mut bytes, u32::from(token.text_range().len()));
+            }
+            WalkEvent::Leave(NodeOrToken::Token(_)) => {}
+        }
+    }
+
+    write_u32(&mut bytes, parse.errors().len() as u32);
+    for error in parse.errors() {
+        match error.location() {
+            Location::Offset(offset) => {
+                bytes.push(OFFSET);
+                write_u32(&mut bytes, offset.into());
+            }
+            Location::Range(range) => {
+                bytes.push(RANGE);
+                write_u32(&mut bytes, range.start().into());
+                write_u32(&mut bytes, range.end().into());
+            }
+        }
+        let SyntaxErrorKind::ParseError(error) = error.kind();
+        write_u32(&mut bytes, error.message.len() as u32);
+        bytes.extend_from_slice(error.message.as_bytes());
+        write_u16(&mut bytes, error.expected.iter().count() as u16);
+        for kind in error.expected.iter() {
+            write_u16(&mut bytes, kind.into_raw());
+        }
+    }
+    bytes
+}
+
+/// Loads a parse that was serialized with `serialize_parse` from `text`. Fails with
+/// `TreeCacheError::SourceChanged` if `text` is not the text the parse was created from.
+pub fn deserialize_parse<T: AstNode>(bytes: &[u8], text: &str) -> Result<Parse<T>, TreeCacheError> {
+    let mut reader = Reader { bytes };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(TreeCacheError::InvalidFormat);
+    }
+    let version = reader.u32()?;
+    if version != TREE_CACHE_VERSION {
+        return Err(TreeCacheError::VersionMismatch {
+            found: version,
+            expected: TREE_CACHE_VERSION,
+        });
+    }
+    let len = reader.u32()?;
+    let hash = reader.u64()?;
+    if len as usize != text.len() || hash != source_hash(text) {
+        return Err(TreeCacheError::SourceChanged);
+    }
+
+    let mut builder = SyntaxTreeBuilder::default();
+    let mut depth = 0usize;
+    let mut offset = 0usize;
+    loop {
+        match reader.u8()? {
+            START_NODE => {
+                let kind = reader.kind()?;
+                builder.start_node(kind);
+                depth += 1;
+            }
+            FINISH_NODE => {
+                depth = depth.checked_sub(1).ok_or(TreeCacheError::InvalidFormat)?;
+                builder.finish_node();
+                if depth == 0 {
+                    break;
+                }
+            }
+            TOKEN if depth > 0 => {
+                let kind = reader.kind()?;
+                let end = offset + reader.u32()? as usize;
+                let token_text = text.get(offset..end).ok_or(TreeCacheError::InvalidFormat)?;
+                builder.token(kind, token_text.into());
+                offset = end;
+            }
+            _ => return Err(TreeCacheError::InvalidFormat),
+        }
+    }
+    if offset != text.len() {
+        return Err(TreeCacheError::InvalidFormat);
+    }
+
+    let error_count = reader.u32()?;
+    let mut errors = Vec::new();
+    for _ in 0..error_count {
+        let location: Location = match reader.u8()? {
+            OFFSET => TextSize::from(reader.u32()?).into(),
+            RANGE => {
+                let start = TextSize::from(reader.u32()?);
+                let end = TextSize::from(reader.u32()?);
+                if start > end {
+                    return Err(TreeCacheError::InvalidFormat);
+                }
+                TextRange::new(start, end).into()
+            }
+            _ => return Err(TreeCacheError::InvalidFormat),
+        };
+        let message_len = reader.u32()? as usize;
+        let message = String::from_utf8(reader.take(message_len)?.to_vec())
+            .map_err(|_| TreeCacheError::InvalidFormat)?;
+        let mut expected = TokenSet::EMPTY;
+        for _ in 0..reader.u16()? {
+            let kind = reader.kind()?;
+            // A `TokenSet` only holds the first 128 kinds
+            if kind.into_raw() >= 128 {
+                return Err(TreeCacheError::InvalidFormat);
+            }
+            expected = expected.union(TokenSet::singleton(kind));
+        }
+        let kind = SyntaxErrorKind::ParseError(ParseError { message, expected });
+        errors.push(SyntaxError::new(kind, location));
+    }
+    if !reader.bytes.is_empty() {
+        return Err(TreeCacheError::InvalidFormat);
+    }
+
+    let (green, _) = builder.finish_raw();
+    Parse::<SyntaxNode>::from_green(green, errors)
+        .cast()
+        .ok_or(TreeCacheError::InvalidFormat)
+}
+
+/// The 64-bit FNV-1a hash of `text`. Unlike the hashers of the standard library, its result is
+/// the same for every build, which is required for a hash that is stored on disk.
+fn source_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn write_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], TreeCacheError> {
+        if len > self.bytes.len() {
+            return Err(TreeCacheError::InvalidFormat);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, TreeCacheError> {
+        let mut buf = [0; 1];
+        buf.copy_from_slice(self.take(1)?);
+        Ok(buf[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, TreeCacheError> {
+        let mut buf = [0; 2];
+        buf.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    fn u32(&mut self) -> Result<u32, TreeCacheError> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn u64(&mut self) -> Result<u64, TreeCacheError> {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn kind(&mut self) -> Result<SyntaxKind, TreeCacheError> {
+        SyntaxKind::try_from(self.u16()?).map_err(|_| TreeCacheError::InvalidFormat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chunk;
+
+    #[test]
+    fn test_roundtrip() {
+        let text = "local x = 1\nif x then\n  print(\"é\")\nend\nlocal = 2\n";
+        let parse = Chunk::parse(text);
+        assert!(!parse.errors().is_empty());
+        let bytes = serialize_parse(&parse, text);
+        let loaded = deserialize_parse::<Chunk>(&bytes, text).unwrap();
+        assert_eq!(loaded, parse);
+        assert_eq!(loaded.debug_dump(), parse.debug_dump());
+    }
+
+    #[test]
+    fn test_rejected_caches() {
+        let text = "return 1 + 2";
+        let bytes = serialize_parse(&Chunk::parse(text), text);
+        assert_eq!(
+            deserialize_parse::<Chunk>(&bytes, "return 1 + 3"),
+            Err(TreeCacheError::SourceChanged)
+        );
+
+        let mut old_version = bytes.clone();
+        old_version[4..8].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(
+            deserialize_parse::<Chunk>(&old_version, text),
+            Err(TreeCacheError::VersionMismatch {
+                found: 0,
+                expected: TREE_CACHE_VERSION
+            })
+        );
+
+        for len in 0..bytes.len() {
+            assert_eq!(
+                deserialize_parse::<Chunk>(&bytes[..len], text),
+                Err(TreeCacheError::InvalidFormat)
+            );
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Attaches trivia to the tokens around it, so that refactorings and formatters can move code
+//! together with its comments.
+//!
+//! The trailing trivia of a token is the trivia that follows it up to and including the first
+//! line break. All other trivia is the leading trivia of the token that follows it, e.g. in
+//!
+//! ```lua
+//! x = 1 -- one
+//! -- two
+//! y = 2
+//! ```
+//!
+//! `-- one` and the line break after it are trailing trivia of `1`, `-- two` and its line break
+//! are leading trivia of `y`. The trivia after the last token is all trailing trivia of that
+//! token. Trivia is described by ranges rather than tokens, because a single whitespace token can
+//! contain both trailing and leading trivia.
+
+use crate::{SyntaxKind::*, SyntaxNode, SyntaxToken, TextRange, TextSize};
+
+/// A token together with the trivia attached to it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TokenTrivia {
+    pub token: SyntaxToken,
+    /// The trivia directly in front of the token, may be empty
+    pub leading: TextRange,
+    /// The trivia directly after the token, may be empty
+    pub trailing: TextRange,
+}
+
+impl TokenTrivia {
+    /// Returns the range of the token including its leading and trailing trivia.
+    pub fn full_range(&self) -> TextRange {
+        self.leading.cover(self.trailing)
+    }
+}
+
+/// The trivia of every non-trivia token in a tree, see the module documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriviaView {
+    tokens: Vec<TokenTrivia>,
+}
+
+impl TriviaView {
+    pub fn new(root: &SyntaxNode) -> TriviaView {
+        let tokens: Vec<_> = root
+            .descendants_with_tokens()
+            .filter_map(|element| element.into_token())
+            .collect();
+        let mut result: Vec<TokenTrivia> = Vec::new();
+        let mut leading_start = root.text_range().start();
+        for (idx, token) in tokens.iter().enumerate() {
+            if token.kind().is_trivia() {
+                continue;
+            }
+            let trailing_end = trailing_end(token, &tokens[idx + 1..]);
+            result.push(TokenTrivia {
+                token: token.clone(),
+                leading: TextRange::new(leading_start, token.text_range().start()),
+                trailing: TextRange::new(token.text_range().end(), trailing_end),
+            });
+            leading_start = trailing_end;
+        }
+        if let Some(last) = result.last_mut() {
+            last.trailing = TextRange::new(last.trailing.start(), root.text_range().end());
+        }
+        TriviaView { tokens: result }
+    }
+
+    /// Returns the non-trivia tokens with their trivia, in the order of the source.
+    pub fn tokens(&self) -> &[TokenTrivia] {
+        &self.tokens
+    }
+
+    /// Returns the trivia of `token`, or `None` if it is trivia itself or not part of the tree.
+    pub fn token(&self, token: &SyntaxToken) -> Option<&TokenTrivia> {
+        let idx = self
+            .tokens
+            .binary_search_by_key(&token.text_range().start(), |trivia| {
+                trivia.token.text_range().start()
+            })
+            .ok()?;
+        Some(&self.tokens[idx]).filter(|trivia| &trivia.token == token)
+    }
+
+    /// Returns the range of `node` extended by the leading trivia of its first token and the
+    /// trailing trivia of its last token, e.g. to move or delete a statement together with its
+    /// comments.
+    pub fn full_range(&self, node: &SyntaxNode) -> TextRange {
+        let range = node.text_range();
+        let first = self
+            .tokens
+            .iter()
+            .find(|t| range.contains_range(t.token.text_range()));
+        let last = self
+            .tokens
+            .iter()
+            .rev()
+            .find(|t| range.contains_range(t.token.text_range()));
+        match (first, last) {
+            (Some(first), Some(last)) => range.cover(first.leading).cover(last.trailing),
+            _ => range,
+        }
+    }
+}
+
+/// Returns where the trailing trivia of `token` ends, `following` are the tokens after it.
+fn trailing_end(token: &SyntaxToken, following: &[SyntaxToken]) -> TextSize {
+    let mut end = token.text_range().end();
+    for next in following {
+        match next.kind() {
+            WHITESPACE => {
+                if let Some(idx) = next.text().find('\n') {
+                    return next.text_range().start() + TextSize::from(idx as u32 + 1);
+                }
+            }
+            COMMENT => {}
+            _ => break,
+        }
+        end = next.text_range().end();
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chunk;
+
+    #[test]
+    fn test_trivia() {
+        let text = "x = 1 -- one\r\n-- two\n  y = 2\n\n";
+        let root = Chunk::parse(text).syntax_node();
+        let view = TriviaView::new(&root);
+        let trivia: Vec<_> = view
+            .tokens()
+            .iter()
+            .map(|t| (t.token.text(), &text[t.leading], &text[t.trailing]))
+            .collect();
+        assert_eq!(
+            trivia,
+            vec![
+                ("x", "", " "),
+                ("=", "", " "),
+                ("1", "", " -- one\r\n"),
+                ("y", "-- two\n  ", " "),
+                ("=", "", " "),
+                ("2", "", "\n\n"),
+            ]
+        );
+
+        let statements: Vec<_> = root
+            .descendants()
+            .filter(|n| n.kind() == ASSIGN_STMT)
+            .collect();
+        assert_eq!(&text[view.full_range(&statements[0])], "x = 1 -- one\r\n");
+        assert_eq!(
+            &text[view.full_range(&statements[1])],
+            "-- two\n  y = 2\n\n"
+        );
+
+        let y = &view.tokens()[3].token;
+        assert_eq!(view.token(y).unwrap().leading, view.tokens()[3].leading);
+    }
+}
@@ -0,0 +1,278 @@
+//! Edits that editors apply while the user is typing, e.g. to fix the indentation.
+
+use crate::{
+    blocks::{BlockChange, BlockTracker},
+    detect_line_endings,
+    validation::{significant_tokens, SignificantToken},
+    IndentStyle,
+    SyntaxKind::{self, *},
+    TextEdit, TextRange, TextSize,
+};
+
+/// Computes the indentation of the line that starts at `offset`, right after the user typed a
+/// line break. The new line is indented one level deeper than the previous line if that line
+/// opened a block or bracket, e.g. after `then`, `do` or `function f()`. If the new line starts
+/// with a keyword or bracket that closes a block, it is aligned with the line that opened it.
+pub fn on_enter(text: &str, offset: TextSize, indent_style: IndentStyle) -> Option<TextEdit> {
+    let offset_idx = u32::from(offset) as usize;
+    if !text[..offset_idx].ends_with('\n') {
+        return None;
+    }
+    let prev_line_start = line_start(text, offset_idx - 1);
+    let prev_line = text[prev_line_start..offset_idx].trim_end_matches(&['\r', '\n'][..]);
+    let mut indent = leading_whitespace(prev_line).to_string();
+
+    let tokens = significant_tokens(&text[..offset_idx]);
+    let line_tokens = tokens
+        .iter()
+        .position(|token| to_index(token.range.start()) >= prev_line_start)
+        .unwrap_or(tokens.len());
+    let opened = depth(&tokens[..]) - depth(&tokens[..line_tokens]);
+    let starts_with_else = tokens
+        .get(line_tokens)
+        .is_some_and(|token| token.kind == ELSE_KW || token.kind == ELSEIF_KW);
+    let opens_block = opened > 0 || (opened == 0 && starts_with_else);
+
+    let existing_indent = leading_whitespace(&text[offset_idx..]);
+    let next_kind = significant_tokens(&text[offset_idx + existing_indent.len()..])
+        .first()
+        .map(|token| token.kind);
+    let closes_block = next_kind.is_some_and(is_closing);
+
+    if opens_block && !closes_block {
+        indent.push_str(&indent_style.unit());
+    } else if !opens_block && closes_block {
+        let unit = indent_style.unit();
+        if indent.ends_with(&unit) {
+            indent.truncate(indent.len() - unit.len());
+        }
+    }
+
+    if indent == existing_indent {
+        return None;
+    }
+    Some(TextEdit::replace(
+        TextRange::at(offset, TextSize::of(existing_indent)),
+        indent,
+    ))
+}
+
+/// Re-indents the current line after the user finished typing a keyword that closes a block,
+/// i.e. `end`, `else`, `elseif` or `until`, with `offset` right after the keyword. The line is
+/// aligned with the line that opened the block if the keyword is the first token on its line.
+pub fn on_keyword_typed(text: &str, offset: TextSize) -> Option<TextEdit> {
+    let offset_idx = u32::from(offset) as usize;
+    let tokens = significant_tokens(&text[..offset_idx]);
+    let (keyword, preceding) = tokens.split_last()?;
+    if keyword.range.end() != offset || !is_closing(keyword.kind) || keyword.kind.is_symbol() {
+        return None;
+    }
+
+    let keyword_start = to_index(keyword.range.start());
+    let current_line_start = line_start(text, keyword_start);
+    if !text[current_line_start..keyword_start].trim().is_empty() {
+        return None;
+    }
+
+    let mut blocks = BlockTracker::default();
+    for (idx, token) in preceding.iter().enumerate() {
+        blocks.visit(idx, token.kind);
+    }
+    let opener = preceding[*blocks.open_blocks().last()?].range.start();
+    let indent = leading_whitespace(&text[line_start(text, to_index(opener))..]);
+
+    let current_indent = &text[current_line_start..keyword_start];
+    if indent == current_indent {
+        return None;
+    }
+    Some(TextEdit::replace(
+        TextRange::new(
+            TextSize::from(current_line_start as u32),
+            keyword.range.start(),
+        ),
+        indent.to_string(),
+    ))
+}
+
+/// Inserts the `end` of a block after the user typed `then`, `do` or the parameters of a
+/// `function`, with `offset` right after the typed token. The `end` goes on a new line, aligned
+/// with the line that opened the block.
+///
+/// Returns `None` if the block is already closed. Blocks are matched with their `end` by keyword,
+/// so a block typed in front of existing code takes the `end` of an enclosing block. If the file
+/// has unclosed blocks and the matched `end` is indented less than the new block, it is assumed to
+/// belong to an enclosing block.
+pub fn on_block_opened(text: &str, offset: TextSize) -> Option<TextEdit> {
+    let tokens = significant_tokens(text);
+    let typed = tokens
+        .iter()
+        .position(|token| token.range.end() == offset)?;
+    let mut blocks = BlockTracker::default();
+    for (idx, token) in tokens[..=typed].iter().enumerate() {
+        blocks.visit(idx, token.kind);
+    }
+    let opener = *blocks.open_blocks().last()?;
+    let opens_body = match tokens[typed].kind {
+        THEN_KW => tokens[opener].kind == IF_KW,
+        DO_KW => matches!(tokens[opener].kind, WHILE_KW | FOR_KW | DO_KW),
+        // The parameter list is the first pair of parentheses after `function`
+        R_PAREN => {
+            tokens[opener].kind == FUNCTION_KW
+                && tokens[opener..]
+                    .iter()
+                    .position(|token| token.kind == R_PAREN)
+                    .is_some_and(|idx| opener + idx == typed)
+        }
+        _ => false,
+    };
+    if !opens_body {
+        return None;
+    }
+
+    let opener_line_start = line_start(text, to_index(tokens[opener].range.start()));
+    let indent = leading_whitespace(&text[opener_line_start..]);
+    let mut closer = None;
+    for (idx, token) in tokens.iter().enumerate().skip(typed + 1) {
+        if blocks.visit(idx, token.kind) == BlockChange::Closed(opener) {
+            closer = Some(idx);
+        }
+    }
+    if let Some(closer) = closer {
+        let closer_start = to_index(tokens[closer].range.start());
+        let closer_indent = leading_whitespace(&text[line_start(text, closer_start)..]);
+        if blocks.depth() == 0 || closer_indent.len() >= indent.len() {
+            return None;
+        }
+    }
+
+    // Code after the cursor stays in the block, unless it closes a bracket the block is in, e.g.
+    // `f(function() end)`
+    let offset_idx = to_index(offset);
+    let line_end = text[offset_idx..]
+        .find(&['\r', '\n'][..])
+        .map_or(text.len(), |idx| offset_idx + idx);
+    let rest = text[offset_idx..line_end].trim_start();
+    let insert_at = if rest.starts_with(&[')', '}', ']', ','][..]) {
+        offset_idx
+    } else {
+        line_end
+    };
+    let line_ending = detect_line_endings(text).dominant();
+    Some(TextEdit::insert(
+        TextSize::from(insert_at as u32),
+        format!("{}{}end", line_ending.as_str(), indent),
+    ))
+}
+
+fn is_closing(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        END_KW | ELSE_KW | ELSEIF_KW | UNTIL_KW | R_CURLY | R_PAREN | R_BRACKET
+    )
+}
+
+/// Returns the number of blocks and brackets that are open after `tokens`.
+fn depth(tokens: &[SignificantToken]) -> isize {
+    let mut blocks = BlockTracker::default();
+    let mut brackets = 0;
+    for (idx, token) in tokens.iter().enumerate() {
+        match token.kind {
+            L_PAREN | L_CURLY | L_BRACKET => brackets += 1,
+            R_PAREN | R_CURLY | R_BRACKET if brackets > 0 => brackets -= 1,
+            kind => {
+                blocks.visit(idx, kind);
+            }
+        }
+    }
+    (blocks.depth() + brackets) as isize
+}
+
+fn line_start(text: &str, idx: usize) -> usize {
+    text[..idx].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    let len = line.len() - line.trim_start_matches(&[' ', '\t'][..]).len();
+    &line[..len]
+}
+
+fn to_index(offset: TextSize) -> usize {
+    u32::from(offset) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enter(text: &str, indent_style: IndentStyle) -> String {
+        let offset = TextSize::of(&text[..text.find('|').unwrap()]);
+        let text = text.replace('|', "");
+        match on_enter(&text, offset, indent_style) {
+            Some(edit) => edit.apply(&text),
+            None => text,
+        }
+    }
+
+    fn keyword(text: &str) -> String {
+        let offset = TextSize::of(&text[..text.find('|').unwrap()]);
+        let text = text.replace('|', "");
+        match on_keyword_typed(&text, offset) {
+            Some(edit) => edit.apply(&text),
+            None => text,
+        }
+    }
+
+    #[test]
+    fn test_on_enter() {
+        let spaces = IndentStyle::Spaces(2);
+        assert_eq!(enter("if a then\n|", spaces), "if a then\n  ");
+        assert_eq!(enter("  while a do\n|", IndentStyle::Tabs), "  while a do\n  \t");
+        assert_eq!(enter("  f(function()\n|", spaces), "  f(function()\n    ");
+        assert_eq!(enter("  x = 1\n|    y", spaces), "  x = 1\n  y");
+        assert_eq!(enter("  else\n|", spaces), "  else\n    ");
+        assert_eq!(enter("  if a then return end\n|", spaces), "  if a then return end\n  ");
+        assert_eq!(enter("local t = {\n|}", spaces), "local t = {\n}");
+        assert_eq!(enter("  return 1\n|end", spaces), "  return 1\nend");
+    }
+
+    fn block_opened(text: &str) -> String {
+        let offset = TextSize::of(&text[..text.find('|').unwrap()]);
+        let text = text.replace('|', "");
+        match on_block_opened(&text, offset) {
+            Some(edit) => edit.apply(&text),
+            None => text,
+        }
+    }
+
+    #[test]
+    fn test_on_block_opened() {
+        assert_eq!(block_opened("  if a then|"), "  if a then\n  end");
+        assert_eq!(
+            block_opened("for i = 1, 2 do|\r\n"),
+            "for i = 1, 2 do\r\nend\r\n"
+        );
+        assert_eq!(block_opened("f(function(a)|)"), "f(function(a)\nend)");
+        assert_eq!(
+            block_opened("local function f()| g()"),
+            "local function f() g()\nend"
+        );
+        assert_eq!(block_opened("if a then| b() end"), "if a then b() end");
+        assert_eq!(block_opened("while x do|\nend"), "while x do\nend");
+        assert_eq!(block_opened("f(a)|"), "f(a)");
+        assert_eq!(block_opened("elseif x then|"), "elseif x then");
+        // The `end` belongs to the function, which would otherwise be unclosed
+        assert_eq!(
+            block_opened("function f()\n  if a then|\n  b()\nend"),
+            "function f()\n  if a then\n  end\n  b()\nend"
+        );
+    }
+
+    #[test]
+    fn test_on_keyword_typed() {
+        assert_eq!(keyword("  if a then\n    b()\n    end|"), "  if a then\n    b()\n  end");
+        assert_eq!(keyword("while a do\n  b()\n  else|"), "while a do\n  b()\nelse");
+        assert_eq!(keyword("repeat\n\ta()\n\tuntil|"), "repeat\n\ta()\nuntil");
+        assert_eq!(keyword("if a then\n  b() end|"), "if a then\n  b() end");
+        assert_eq!(keyword("  b()\n  end|"), "  b()\n  end");
+    }
+}
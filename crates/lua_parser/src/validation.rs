@@ -0,0 +1,275 @@
+//! Checks on Lua source whose outcome depends on the targeted `Dialect`, and invariant checks of
+//! the syntax tree that catch bugs in the parser in debug builds.
+
+use crate::{
+    tokenize, Diagnostic, DiagnosticCode, Dialect,
+    SyntaxKind::{self, *},
+    SyntaxNode, TextRange, TextSize,
+};
+
+pub const AMBIGUOUS_SYNTAX: DiagnosticCode = DiagnosticCode("E0002");
+pub const AMBIGUOUS_SYNTAX_PORTABILITY: DiagnosticCode = DiagnosticCode("W0001");
+
+/// A non-trivia token together with its range in the source.
+pub(crate) struct SignificantToken {
+    pub kind: SyntaxKind,
+    pub range: TextRange,
+    /// True if the trivia in front of this token contains a line break.
+    pub newline_before: bool,
+}
+
+pub(crate) fn significant_tokens(text: &str) -> Vec<SignificantToken> {
+    let mut result = Vec::new();
+    let mut offset = TextSize::from(0);
+    let mut newline_before = false;
+    for token in tokenize(text) {
+        let range = TextRange::at(offset, token.len);
+        offset += token.len;
+        if token.kind.is_trivia() {
+            let token_text = &text[range];
+            newline_before |= token_text.contains('\n') || token_text.contains('\r');
+            continue;
+        }
+        result.push(SignificantToken {
+            kind: token.kind,
+            range,
+            newline_before,
+        });
+        newline_before = false;
+    }
+    result
+}
+
+/// Detects call arguments that start on a new line, e.g. `f\n(g)`. Lua 5.1 rejects this as
+/// "ambiguous syntax", later versions parse it as a call. For `Dialect::Lua51` an error is
+/// reported for every occurrence, for later dialects a portability warning is reported if
+/// `portability_warnings` is set.
+pub fn check_ambiguous_calls(
+    text: &str,
+    dialect: Dialect,
+    portability_warnings: bool,
+) -> Vec<Diagnostic> {
+    if dialect != Dialect::Lua51 && !portability_warnings {
+        return Vec::new();
+    }
+
+    let tokens = significant_tokens(text);
+    let mut diagnostics = Vec::new();
+    for (idx, token) in tokens.iter().enumerate() {
+        if token.kind != L_PAREN || !token.newline_before || idx == 0 {
+            continue;
+        }
+        if !ends_prefix_expr(&tokens, idx - 1) {
+            continue;
+        }
+        let diagnostic = if dialect == Dialect::Lua51 {
+            Diagnostic::error(
+                AMBIGUOUS_SYNTAX,
+                token.range,
+                "ambiguous syntax (function call x new statement)",
+            )
+        } else {
+            Diagnostic::warning(
+                AMBIGUOUS_SYNTAX_PORTABILITY,
+                token.range,
+                "call arguments on a new line are ambiguous syntax in Lua 5.1",
+            )
+        };
+        diagnostics.push(diagnostic.with_label(
+            tokens[idx - 1].range,
+            "parsed as a call of the expression ending here",
+        ));
+    }
+    diagnostics
+}
+
+/// Returns true if the token at `idx` can be the last token of a prefix expression, i.e. an
+/// expression which is turned into a call when followed by arguments.
+fn ends_prefix_expr(tokens: &[SignificantToken], idx: usize) -> bool {
+    match tokens[idx].kind {
+        IDENT => !is_declared_name(tokens, idx),
+        R_BRACKET => true,
+        R_PAREN => match matching_open(tokens, idx, L_PAREN, R_PAREN) {
+            Some(open) => !is_function_header(tokens, open),
+            None => false,
+        },
+        // A table or string literal only ends a prefix expression if it is a call argument.
+        R_CURLY => match matching_open(tokens, idx, L_CURLY, R_CURLY) {
+            Some(open) => open > 0 && ends_prefix_expr(tokens, open - 1),
+            None => false,
+        },
+        STRING => idx > 0 && ends_prefix_expr(tokens, idx - 1),
+        _ => false,
+    }
+}
+
+/// Returns true if the identifier at `idx` declares a name instead of referring to one, e.g. a
+/// local, a function name or a label.
+fn is_declared_name(tokens: &[SignificantToken], idx: usize) -> bool {
+    let mut idx = idx;
+    while idx > 0 {
+        match tokens[idx - 1].kind {
+            LOCAL_KW | FUNCTION_KW | GOTO_KW => return true,
+            COLON if idx >= 2 && tokens[idx - 2].kind == COLON => return true,
+            COMMA | DOT | COLON if idx >= 2 && tokens[idx - 2].kind == IDENT => idx -= 2,
+            _ => return false,
+        }
+    }
+    false
+}
+
+/// Returns true if the parenthesis at `open` starts the parameter list of a function.
+fn is_function_header(tokens: &[SignificantToken], open: usize) -> bool {
+    let mut idx = open;
+    while idx > 0 {
+        idx -= 1;
+        match tokens[idx].kind {
+            FUNCTION_KW => return true,
+            IDENT if idx > 0 && (tokens[idx - 1].kind == DOT || tokens[idx - 1].kind == COLON) => {
+                idx -= 1
+            }
+            IDENT => return idx > 0 && tokens[idx - 1].kind == FUNCTION_KW,
+            _ => return false,
+        }
+    }
+    false
+}
+
+fn matching_open(
+    tokens: &[SignificantToken],
+    close: usize,
+    open_kind: SyntaxKind,
+    close_kind: SyntaxKind,
+) -> Option<usize> {
+    let mut depth = 0;
+    for idx in (0..=close).rev() {
+        let kind = tokens[idx].kind;
+        if kind == close_kind {
+            depth += 1;
+        } else if kind == open_kind {
+            depth -= 1;
+            if depth == 0 {
+                return Some(idx);
+            }
+        }
+    }
+    None
+}
+
+/// Checks that the children of every node are contiguous and exactly cover the range of their
+/// parent. Panics with a description of the first violation.
+pub(crate) fn validate_block_structure(root: &SyntaxNode) {
+    for node in root.descendants() {
+        let mut offset = node.text_range().start();
+        for child in node.children_with_tokens() {
+            assert!(
+                child.text_range().start() == offset,
+                "invalid tree structure: {:?}@{:?} in {:?}@{:?} should start at {:?}",
+                child.kind(),
+                child.text_range(),
+                node.kind(),
+                node.text_range(),
+                offset
+            );
+            offset = child.text_range().end();
+        }
+        assert!(
+            offset == node.text_range().end(),
+            "invalid tree structure: the children of {:?}@{:?} end at {:?}",
+            node.kind(),
+            node.text_range(),
+            offset
+        );
+    }
+}
+
+/// Checks that the text of the tokens of the tree built from `text` is exactly `text`. Panics
+/// with the first token that does not match the input, which usually means that a `TreeSink` lost
+/// or duplicated trivia.
+pub(crate) fn validate_text(root: &SyntaxNode, text: &str) {
+    let mut offset = 0;
+    for element in root.descendants_with_tokens() {
+        let token = match element.into_token() {
+            Some(token) => token,
+            None => continue,
+        };
+        let token_text = token.text();
+        let end = offset + token_text.len();
+        assert!(
+            text.get(offset..end) == Some(token_text),
+            "the text of {:?}@{:?} {:?} does not match the input {:?}",
+            token.kind(),
+            token.text_range(),
+            token_text,
+            text.get(offset..end.min(text.len())).unwrap_or_default()
+        );
+        offset = end;
+    }
+    assert!(
+        offset == text.len(),
+        "the tree only covers the first {} of the {} bytes of the input",
+        offset,
+        text.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chunk, Severity};
+
+    fn check(text: &str, dialect: Dialect) -> Vec<(Severity, TextRange)> {
+        check_ambiguous_calls(text, dialect, true)
+            .into_iter()
+            .map(|d| (d.severity, d.range))
+            .collect()
+    }
+
+    #[test]
+    fn test_ambiguous_call() {
+        let range = TextRange::at(2.into(), 1.into());
+        assert_eq!(check("f\n(g)", Dialect::Lua51), vec![(Severity::Error, range)]);
+        assert_eq!(check("f\n(g)", Dialect::Lua53), vec![(Severity::Warning, range)]);
+        assert!(check_ambiguous_calls("f\n(g)", Dialect::Lua53, false).is_empty());
+    }
+
+    #[test]
+    fn test_ambiguous_call_chains() {
+        assert_eq!(check("a.b:c 'x'\n(g)", Dialect::Lua51).len(), 1);
+        assert_eq!(check("f{}\n(g)()", Dialect::Lua51).len(), 1);
+        assert_eq!(check("f(a, b\n(c))", Dialect::Lua51).len(), 1);
+    }
+
+    #[test]
+    fn test_not_ambiguous() {
+        assert!(check("f(g)\n", Dialect::Lua51).is_empty());
+        assert!(check("f();\n(g)()", Dialect::Lua51).is_empty());
+        assert!(check("local a, b\n(f)()", Dialect::Lua51).is_empty());
+        assert!(check("x = 'a'\n(f)()", Dialect::Lua51).is_empty());
+        assert!(check("function a.b\n(c) end", Dialect::Lua51).is_empty());
+        assert!(check("function f()\n(g)() end", Dialect::Lua51).is_empty());
+        assert!(check("goto l\n(f)()", Dialect::Lua51).is_empty());
+    }
+
+    #[test]
+    fn test_validate_parsed_tree() {
+        let text = "local t = {1, f(2)} -- comment\nreturn t.x";
+        let root = Chunk::parse(text).syntax_node();
+        validate_block_structure(&root);
+        validate_text(&root, text);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not match the input")]
+    fn test_validate_text_mismatch() {
+        let root = Chunk::parse("local a = 1").syntax_node();
+        validate_text(&root, "local b = 1");
+    }
+
+    #[test]
+    #[should_panic(expected = "only covers the first 5 of the 11 bytes")]
+    fn test_validate_text_missing_input() {
+        let root = Chunk::parse("local").syntax_node();
+        validate_text(&root, "local a = 1");
+    }
+}
@@ -0,0 +1,301 @@
+//! A collection of Lua source files that can reference each other through `require`.
+//!
+//! The `Workspace` owns the text of every file and lazily computes derived data, like the tokens,
+//! the parse and the required modules of a file. Derived data is cached until the text of the file
+//! changes. The caches are `OnceLock`s, so a `Workspace` can be queried from several threads at
+//! once; a value that is requested by two threads at the same time is computed once.
+
+use crate::{
+    find_requires, symbol_index, tokenize, Chunk, LineIndex, Parse, Require, SymbolIndex, Token,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+/// Identifies a file in a `Workspace`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(pub u32);
+
+#[derive(Debug, Default)]
+struct FileCache {
+    tokens: OnceLock<Arc<Vec<Token>>>,
+    line_index: OnceLock<Arc<LineIndex>>,
+    requires: OnceLock<Arc<Vec<Require>>>,
+    parse: OnceLock<Parse<Chunk>>,
+    symbol_index: OnceLock<Arc<SymbolIndex>>,
+}
+
+#[derive(Debug)]
+struct FileData {
+    path: PathBuf,
+    text: Arc<String>,
+    cache: FileCache,
+}
+
+#[derive(Debug)]
+pub struct Workspace {
+    files: Vec<Option<FileData>>,
+    paths: HashMap<PathBuf, FileId>,
+    search_paths: Vec<String>,
+    module_graph: OnceLock<Arc<ModuleGraph>>,
+}
+
+impl Default for Workspace {
+    fn default() -> Workspace {
+        Workspace {
+            files: Vec::new(),
+            paths: HashMap::new(),
+            search_paths: vec!["?.lua".to_string(), "?/init.lua".to_string()],
+            module_graph: OnceLock::new(),
+        }
+    }
+}
+
+impl Workspace {
+    pub fn new() -> Workspace {
+        Workspace::default()
+    }
+
+    /// Adds a file to the workspace. If a file with the same path already exists its text is
+    /// replaced and the existing id is returned.
+    pub fn add_file(&mut self, path: impl Into<PathBuf>, text: impl Into<String>) -> FileId {
+        let path = path.into();
+        if let Some(&file_id) = self.paths.get(&path) {
+            self.set_file_text(file_id, text);
+            return file_id;
+        }
+
+        let file_id = FileId(self.files.len() as u32);
+        self.files.push(Some(FileData {
+            path: path.clone(),
+            text: Arc::new(text.into()),
+            cache: FileCache::default(),
+        }));
+        self.paths.insert(path, file_id);
+        self.invalidate_module_graph();
+        file_id
+    }
+
+    /// Replaces the text of a file and invalidates everything derived from it.
+    pub fn set_file_text(&mut self, file_id: FileId, text: impl Into<String>) {
+        let file = self.file_mut(file_id);
+        file.text = Arc::new(text.into());
+        file.cache = FileCache::default();
+        self.invalidate_module_graph();
+    }
+
+    /// Removes a file from the workspace. The id of a removed file is never reused.
+    pub fn remove_file(&mut self, file_id: FileId) {
+        if let Some(file) = self.files[file_id.0 as usize].take() {
+            self.paths.remove(&file.path);
+            self.invalidate_module_graph();
+        }
+    }
+
+    /// Returns the ids of all files in the workspace.
+    pub fn files(&self) -> impl Iterator<Item = FileId> + '_ {
+        self.files
+            .iter()
+            .enumerate()
+            .filter(|(_, file)| file.is_some())
+            .map(|(idx, _)| FileId(idx as u32))
+    }
+
+    pub fn file_id(&self, path: impl AsRef<Path>) -> Option<FileId> {
+        self.paths.get(path.as_ref()).cloned()
+    }
+
+    pub fn file_path(&self, file_id: FileId) -> &Path {
+        &self.file(file_id).path
+    }
+
+    pub fn file_text(&self, file_id: FileId) -> Arc<String> {
+        self.file(file_id).text.clone()
+    }
+
+    pub fn tokens(&self, file_id: FileId) -> Arc<Vec<Token>> {
+        let file = self.file(file_id);
+        file.cache
+            .tokens
+            .get_or_init(|| Arc::new(tokenize(&file.text)))
+            .clone()
+    }
+
+    pub fn line_index(&self, file_id: FileId) -> Arc<LineIndex> {
+        let file = self.file(file_id);
+        file.cache
+            .line_index
+            .get_or_init(|| Arc::new(LineIndex::new(&file.text)))
+            .clone()
+    }
+
+    /// Returns the modules that are statically required by a file.
+    pub fn requires(&self, file_id: FileId) -> Arc<Vec<Require>> {
+        let file = self.file(file_id);
+        file.cache
+            .requires
+            .get_or_init(|| Arc::new(find_requires(&file.text)))
+            .clone()
+    }
+
+    /// Returns the parse of a file, which is parsed at most once per version of its text.
+    pub fn parse(&self, file_id: FileId) -> Parse<Chunk> {
+        let file = self.file(file_id);
+        file.cache
+            .parse
+            .get_or_init(|| {
+                trace_event!(path = %file.path.display(), "parsing file");
+                Chunk::parse(&file.text)
+            })
+            .clone()
+    }
+
+    /// Returns the globals and module fields that a file defines.
+    pub fn symbol_index(&self, file_id: FileId) -> Arc<SymbolIndex> {
+        self.file(file_id)
+            .cache
+            .symbol_index
+            .get_or_init(|| Arc::new(symbol_index(&self.parse(file_id).tree())))
+            .clone()
+    }
+
+    /// Sets the templates used to find the file of a module, in the format of Lua's
+    /// `package.path` without the separating semicolons. Every `?` is replaced by the module name
+    /// with dots replaced by slashes. Defaults to `?.lua` and `?/init.lua`.
+    pub fn set_search_paths(&mut self, search_paths: Vec<String>) {
+        self.search_paths = search_paths;
+        self.invalidate_module_graph();
+    }
+
+    /// Returns the file that is loaded by `require(name)`, if it is part of the workspace.
+    pub fn resolve_module(&self, name: &str) -> Option<FileId> {
+        let module_path = name.replace('.', "/");
+        self.search_paths
+            .iter()
+            .find_map(|template| self.file_id(template.replace('?', &module_path)))
+    }
+
+    /// Returns the dependencies between the files of the workspace.
+    pub fn module_graph(&self) -> Arc<ModuleGraph> {
+        self.module_graph
+            .get_or_init(|| Arc::new(ModuleGraph::build(self)))
+            .clone()
+    }
+
+    fn file(&self, file_id: FileId) -> &FileData {
+        self.files[file_id.0 as usize]
+            .as_ref()
+            .expect("file has been removed from the workspace")
+    }
+
+    fn file_mut(&mut self, file_id: FileId) -> &mut FileData {
+        self.files[file_id.0 as usize]
+            .as_mut()
+            .expect("file has been removed from the workspace")
+    }
+
+    fn invalidate_module_graph(&mut self) {
+        self.module_graph.take();
+    }
+}
+
+/// The `require` dependencies between the files of a `Workspace`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ModuleGraph {
+    dependencies: HashMap<FileId, Vec<FileId>>,
+    dependents: HashMap<FileId, Vec<FileId>>,
+    unresolved: Vec<(FileId, Require)>,
+}
+
+impl ModuleGraph {
+    fn build(workspace: &Workspace) -> ModuleGraph {
+        let mut graph = ModuleGraph::default();
+        for file_id in workspace.files() {
+            for require in workspace.requires(file_id).iter() {
+                match workspace.resolve_module(&require.name) {
+                    Some(dependency) => {
+                        let dependencies = graph.dependencies.entry(file_id).or_default();
+                        if !dependencies.contains(&dependency) {
+                            dependencies.push(dependency);
+                            graph.dependents.entry(dependency).or_default().push(file_id);
+                        }
+                    }
+                    None => graph.unresolved.push((file_id, require.clone())),
+                }
+            }
+        }
+        graph
+    }
+
+    /// Returns the files required by `file_id`, in the order they are first required.
+    pub fn dependencies(&self, file_id: FileId) -> &[FileId] {
+        self.dependencies
+            .get(&file_id)
+            .map_or(&[], |files| files.as_slice())
+    }
+
+    /// Returns the files that require `file_id`.
+    pub fn dependents(&self, file_id: FileId) -> &[FileId] {
+        self.dependents
+            .get(&file_id)
+            .map_or(&[], |files| files.as_slice())
+    }
+
+    /// Returns the requires that do not refer to a file in the workspace, e.g. external libraries.
+    pub fn unresolved(&self) -> &[(FileId, Require)] {
+        &self.unresolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AstNode;
+
+    #[test]
+    fn test_workspace_is_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Workspace>();
+    }
+
+    #[test]
+    fn test_module_graph() {
+        let mut workspace = Workspace::new();
+        let main = workspace.add_file("main.lua", "local util = require 'util'\nrequire 'json'");
+        let util = workspace.add_file("util/init.lua", "return require('util.strings')");
+        let strings = workspace.add_file("util/strings.lua", "return {}");
+
+        let graph = workspace.module_graph();
+        assert_eq!(graph.dependencies(main), &[util]);
+        assert_eq!(graph.dependencies(util), &[strings]);
+        assert_eq!(graph.dependents(strings), &[util]);
+        assert_eq!(graph.unresolved().len(), 1);
+        assert_eq!(graph.unresolved()[0].1.name, "json");
+
+        workspace.add_file("json.lua", "return {}");
+        assert!(workspace.module_graph().unresolved().is_empty());
+    }
+
+    #[test]
+    fn test_invalidate_on_edit() {
+        let mut workspace = Workspace::new();
+        let file = workspace.add_file("a.lua", "require 'b'");
+        assert_eq!(workspace.requires(file).len(), 1);
+        assert_eq!(workspace.tokens(file).len(), 3);
+
+        workspace.set_file_text(file, "return 1");
+        assert!(workspace.requires(file).is_empty());
+        assert_eq!(workspace.tokens(file).len(), 3);
+        assert!(workspace.module_graph().unresolved().is_empty());
+
+        let parse = workspace.parse(file);
+        assert_eq!(parse.tree().syntax().to_string(), "return 1");
+        assert_eq!(workspace.parse(file), parse);
+
+        workspace.remove_file(file);
+        assert_eq!(workspace.files().count(), 0);
+        assert_eq!(workspace.file_id("a.lua"), None);
+    }
+}
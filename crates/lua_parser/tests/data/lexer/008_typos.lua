@@ -0,0 +1,2 @@
+a != b && c || d === e
+i++ + +j [===[x]===]
\ No newline at end of file
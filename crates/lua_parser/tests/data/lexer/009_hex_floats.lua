@@ -0,0 +1 @@
+0XA 0x1p4 0x1.8p+1 0xA.8 0x.1P-4 0x1.999999999999ap-4 0xA.e 1.5e3 0x1.x
\ No newline at end of file
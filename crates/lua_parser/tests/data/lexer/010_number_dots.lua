@@ -0,0 +1 @@
+1..2 3. 3.e2 .5 a..5
@@ -0,0 +1,4 @@
+goto = 1
+local t = { goto = goto }
+t.goto(goto)
+function t.goto() end
@@ -0,0 +1,2 @@
+do goto done end
+::done::
@@ -0,0 +1 @@
+local a <foo> = 1
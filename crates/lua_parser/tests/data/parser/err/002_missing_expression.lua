@@ -0,0 +1,3 @@
+local a =
+b = 1 +
+if then end
@@ -0,0 +1,3 @@
+x
+a + b = 1
+f() = 2
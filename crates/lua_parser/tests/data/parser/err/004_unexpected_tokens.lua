@@ -0,0 +1,4 @@
+end
+local t = {1 2}
+return 1
+print(x)
@@ -0,0 +1,2 @@
+local a <foo> = 1
+local b <const> = 2
@@ -0,0 +1 @@
+i++
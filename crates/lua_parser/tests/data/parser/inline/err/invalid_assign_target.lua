@@ -0,0 +1,2 @@
+f() = 1
+a, (b) = 1, 2
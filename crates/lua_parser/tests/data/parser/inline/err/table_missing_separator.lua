@@ -0,0 +1 @@
+t = { 1 2, x = 3 }
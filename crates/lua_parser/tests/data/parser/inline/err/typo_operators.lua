@@ -0,0 +1 @@
+if a != b && c || d === e then end
@@ -0,0 +1,2 @@
+for k, v in pairs(t) do end
+for line in io.lines(path), 1 do end
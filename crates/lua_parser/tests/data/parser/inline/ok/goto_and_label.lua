@@ -0,0 +1,2 @@
+goto done
+::done::
@@ -0,0 +1,3 @@
+goto = 1
+local t = { goto = goto }
+t.goto(goto)
@@ -0,0 +1 @@
+local x <const>, y <close> = 1, f()
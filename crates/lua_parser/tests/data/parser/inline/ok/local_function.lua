@@ -0,0 +1 @@
+local function f(a, ...) return a end
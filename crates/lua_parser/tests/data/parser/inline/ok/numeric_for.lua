@@ -0,0 +1,2 @@
+for i = 1, 10, 2 do end
+for i = n, 1 do f(i) end
@@ -0,0 +1,2 @@
+x = -a ^ 2 .. b .. c + d * e or not f and g
+y = a < b == c ~ d | e & f << g
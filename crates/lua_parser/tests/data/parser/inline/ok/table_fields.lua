@@ -0,0 +1 @@
+t = { 1, x = 2; [y] = 3, }
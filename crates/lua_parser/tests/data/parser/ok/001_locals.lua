@@ -0,0 +1,3 @@
+local a
+local b, c = 1, 2
+local d <const>, e <close> = 3, f()
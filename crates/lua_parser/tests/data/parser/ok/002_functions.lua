@@ -0,0 +1,11 @@
+function f(a, b)
+    return a + b
+end
+
+function t.a.b:m(...)
+    return self, ...
+end
+
+local function g() end
+
+local h = function(x) return x end
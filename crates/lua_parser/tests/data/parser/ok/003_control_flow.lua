@@ -0,0 +1,23 @@
+if a then
+    b()
+elseif c then
+    d()
+else
+    e()
+end
+
+while true do
+    break
+end
+
+repeat
+    local x = x - 1
+until x == 0
+
+for i = 1, 10, 2 do end
+for k, v in pairs(t) do end
+
+do
+    goto continue
+    ::continue::
+end
@@ -0,0 +1,7 @@
+x = 1 + 2 * 3 - 4 / 5
+x = -x ^ 2
+x = a .. b .. c
+x = not a == b and c or d
+x = a < b, a <= b, a > b, a >= b, a ~= b
+x = a & b | c ~ d << 1 >> 2, ~a, a // b % c, #t
+x = nil, true, false, 1.5, "s", [[long]], ...
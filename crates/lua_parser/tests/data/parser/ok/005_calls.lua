@@ -0,0 +1,6 @@
+print("hello")
+obj:method(1, 2)
+f"string" {1} (a)
+a.b[c].d:e()
+require "module"
+(f or g)()
@@ -0,0 +1,3 @@
+local t = {}
+local u = {1, 2, 3,}
+local v = {x = 1; y = 2, [3] = "three", {nested = true}}
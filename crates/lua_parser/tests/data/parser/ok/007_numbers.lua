@@ -0,0 +1,2 @@
+x = .5 + 3.e2 + 3. + .5e-3
+y = a..5
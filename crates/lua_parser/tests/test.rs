@@ -1,8 +1,18 @@
 extern crate lua_parser;
 
-use lua_parser::{AstNode, Chunk};
-use std::{fmt::Write, path::PathBuf};
-use test_utils::{dir_tests, project_dir};
+use lua_parser::{
+    check_ambiguous_calls, check_compatibility, Chunk, Diagnostic, Dialect, Severity, SyntaxKind,
+};
+use proptest::{collection::vec, prelude::*};
+use std::{
+    convert::TryFrom,
+    env, fs,
+    fmt::Write,
+    io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+use test_utils::{assert_equal_text, collect_tests, dir_tests, project_dir};
 
 #[test]
 fn lexer_tests() {
@@ -13,20 +23,187 @@ fn lexer_tests() {
     });
 }
 
-//#[test]
-//fn parser_tests() {
-//    dir_tests(&test_data_dir(), &["parser/ok"], |text, path| {
-//        let file = Chunk::parse(text);
-//        let errors = file.errors();
-//        assert_eq!(
-//            &*errors,
-//            &[] as &[mun_syntax::SyntaxError],
-//            "There should be no errors in the file {:?}",
-//            path.display()
-//        );
-//        file.syntax().debug_dump()
-//    })
-//}
+/// The numeric representation of the kinds is part of the public API, it must only change on
+/// purpose.
+#[test]
+fn syntax_kind_numbering_is_stable() {
+    let mut actual = String::new();
+    for raw in 0.. {
+        match SyntaxKind::try_from(raw) {
+            Ok(kind) => writeln!(actual, "{} {:?}", raw, kind).unwrap(),
+            Err(_) => break,
+        }
+    }
+    let path = test_data_dir().join("syntax_kinds.txt");
+    let expected = fs::read_to_string(&path).unwrap_or_default();
+    assert_equal_text(&expected, &actual, &path);
+}
+
+#[test]
+fn parser_tests() {
+    dir_tests(
+        &test_data_dir(),
+        &["parser/ok", "parser/inline/ok"],
+        |text, path| {
+            let file = Chunk::parse(text);
+            let errors = file.errors();
+            assert_eq!(
+                errors,
+                &[] as &[lua_parser::SyntaxError],
+                "There should be no errors in the file {:?}",
+                path.display()
+            );
+            file.debug_dump()
+        },
+    )
+}
+
+#[test]
+fn parser_err_tests() {
+    dir_tests(
+        &test_data_dir(),
+        &["parser/err", "parser/inline/err"],
+        |text, path| {
+            let file = Chunk::parse(text);
+            let errors = file.errors();
+            assert!(
+                !errors.is_empty(),
+                "There should be errors in the file {:?}",
+                path.display()
+            );
+            file.debug_dump()
+        },
+    )
+}
+
+/// Compares the parser with the reference implementation: every file of the parser corpus must be
+/// accepted by `luac -p` if and only if it parses without errors for the version of `luac`. Only
+/// runs if the `LUAC` environment variable names a `luac` binary, e.g. `LUAC=luac5.4 cargo test`.
+#[test]
+fn luac_differential_tests() {
+    let luac = match env::var_os("LUAC") {
+        Some(luac) => PathBuf::from(luac),
+        None => return,
+    };
+    let dialect = luac_dialect(&luac);
+    let mut divergences = String::new();
+    for (path, text) in collect_tests(
+        &test_data_dir(),
+        &[
+            "parser/ok",
+            "parser/err",
+            "parser/inline/ok",
+            "parser/inline/err",
+            "luac",
+        ],
+    ) {
+        let accepted_by_luac = luac_accepts(&luac, &text);
+        if parser_accepts(&text, dialect) == accepted_by_luac {
+            continue;
+        }
+        // Keep the direction of the divergence while minimizing
+        let minimized = minimize(&text, |text| {
+            parser_accepts(text, dialect) != accepted_by_luac
+                && luac_accepts(&luac, text) == accepted_by_luac
+        });
+        writeln!(
+            divergences,
+            "{}: {} luac {} the file, minimized input:\n{}\n",
+            path.display(),
+            dialect,
+            if accepted_by_luac {
+                "accepts"
+            } else {
+                "rejects"
+            },
+            minimized
+        )
+        .unwrap();
+    }
+    assert!(
+        divergences.is_empty(),
+        "the parser and luac disagree:\n\n{}",
+        divergences
+    );
+}
+
+/// Returns true if `text` parses without errors and only uses syntax that is available in
+/// `dialect`.
+fn parser_accepts(text: &str, dialect: Dialect) -> bool {
+    let is_error = |diagnostic: &Diagnostic| diagnostic.severity == Severity::Error;
+    Chunk::parse(text).errors().is_empty()
+        && !check_compatibility(text, dialect).iter().any(is_error)
+        && !check_ambiguous_calls(text, dialect, false)
+            .iter()
+            .any(is_error)
+}
+
+/// Returns the version of Lua that `luac` implements, from its version banner, e.g.
+/// `Lua 5.4.6  Copyright (C) 1994-2023 Lua.org, PUC-Rio`.
+fn luac_dialect(luac: &Path) -> Dialect {
+    let output = Command::new(luac)
+        .arg("-v")
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run {}: {}", luac.display(), err));
+    let banner = String::from_utf8_lossy(&output.stdout);
+    let dialects = [
+        Dialect::Lua51,
+        Dialect::Lua52,
+        Dialect::Lua53,
+        Dialect::Lua54,
+    ];
+    dialects
+        .iter()
+        .copied()
+        .find(|dialect| banner.starts_with(&format!("{}.", dialect)))
+        .unwrap_or_else(|| panic!("unknown version of {}: {}", luac.display(), banner))
+}
+
+fn luac_accepts(luac: &Path, text: &str) -> bool {
+    let mut child = Command::new(luac)
+        .args(["-p", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap_or_else(|err| panic!("failed to run {}: {}", luac.display(), err));
+    let mut stdin = child.stdin.take().unwrap();
+    io::Write::write_all(&mut stdin, text.as_bytes()).unwrap();
+    drop(stdin);
+    child.wait().unwrap().success()
+}
+
+/// Removes tokens from `text` for as long as `diverges` holds for the remaining text, so a
+/// divergence is reported with a minimal input.
+fn minimize(text: &str, diverges: impl Fn(&str) -> bool) -> String {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    for token in lua_parser::tokenize(text) {
+        let len = usize::from(token.len);
+        tokens.push(&text[offset..offset + len]);
+        offset += len;
+    }
+
+    let mut chunk_len = tokens.len() / 2;
+    while chunk_len > 0 {
+        let mut removed = false;
+        let mut start = 0;
+        while start < tokens.len() {
+            let end = (start + chunk_len).min(tokens.len());
+            let candidate = [&tokens[..start], &tokens[end..]].concat();
+            if diverges(&candidate.concat()) {
+                tokens = candidate;
+                removed = true;
+            } else {
+                start = end;
+            }
+        }
+        if !removed {
+            chunk_len /= 2;
+        }
+    }
+    tokens.concat()
+}
 
 fn test_data_dir() -> PathBuf {
     project_dir().join("crates/lua_parser/tests/data")
@@ -40,7 +217,185 @@ fn dump_tokens(tokens: &[lua_parser::Token], text: &str) -> String {
         let len = len as usize;
         let token_text = &text[offset..offset + len];
         offset += len;
-        write!(acc, "{:?} {} {:?}\n", token.kind, token.len, token_text).unwrap()
+        writeln!(acc, "{:?} {} {:?}", token.kind, len, token_text).unwrap()
     }
     acc
 }
+
+/// Generates identifiers that are not keywords.
+fn name() -> impl Strategy<Value = String> {
+    "[a-z_][a-z0-9_]{0,4}".prop_filter("keywords are not names", |name| {
+        SyntaxKind::from_keyword(name).is_none()
+    })
+}
+
+/// Generates syntactically valid expressions.
+fn expr() -> impl Strategy<Value = String> {
+    let leaf = prop_oneof![
+        name(),
+        "[0-9]{1,4}(\\.[0-9]{1,2})?",
+        "\"[a-z ]{0,5}\"",
+        "'[a-z ]{0,5}'",
+        "\\[=?\\[[a-z\n ]{0,5}\\]=?\\]".prop_filter("unbalanced long bracket", |s| {
+            s.starts_with("[[") == s.ends_with("]]")
+        }),
+        Just("nil".to_string()),
+        Just("true".to_string()),
+        Just("...".to_string()),
+    ];
+    let binary_op = prop_oneof![
+        Just("+"),
+        Just("-"),
+        Just("*"),
+        Just("/"),
+        Just("//"),
+        Just("%"),
+        Just("^"),
+        Just(".."),
+        Just("=="),
+        Just("~="),
+        Just("<="),
+        Just(">="),
+        Just("<"),
+        Just(">"),
+        Just("and"),
+        Just("or"),
+        Just("&"),
+        Just("|"),
+        Just("~"),
+        Just("<<"),
+        Just(">>"),
+    ];
+    let unary_op = prop_oneof![Just("-"), Just("not"), Just("#"), Just("~")];
+    leaf.prop_recursive(4, 32, 3, move |inner| {
+        prop_oneof![
+            (inner.clone(), binary_op.clone(), inner.clone())
+                .prop_map(|(lhs, op, rhs)| format!("{} {} {}", lhs, op, rhs)),
+            (unary_op.clone(), inner.clone()).prop_map(|(op, e)| format!("{} {}", op, e)),
+            inner.clone().prop_map(|e| format!("({})", e)),
+            // Spaces keep the index from being lexed as a long bracket, e.g. `t[[[s]]]`
+            (name(), inner.clone()).prop_map(|(name, e)| format!("{}[ {} ]", name, e)),
+            (name(), vec(inner.clone(), 0..3)).prop_map(|(name, args)| format!(
+                "{}({})",
+                name,
+                args.join(", ")
+            )),
+            (name(), name(), inner.clone())
+                .prop_map(|(obj, method, e)| format!("{}:{}({})", obj, method, e)),
+            vec(inner.clone(), 0..3).prop_map(|fields| format!("{{{}}}", fields.join(", "))),
+            (name(), inner.clone()).prop_map(|(name, e)| format!("{{ {} = {}; }}", name, e)),
+            inner.prop_map(|e| format!("function(a, ...) return {} end", e)),
+        ]
+    })
+}
+
+/// Generates syntactically valid blocks.
+fn block() -> impl Strategy<Value = String> {
+    let stmt = prop_oneof![
+        (name(), expr()).prop_map(|(name, e)| format!("local {} = {}", name, e)),
+        (name(), expr()).prop_map(|(name, e)| format!("{}.x, {} = {}, 1", name, name, e)),
+        (name(), expr()).prop_map(|(name, e)| format!("{}({})", name, e)),
+        name().prop_map(|name| format!("goto {} ::{}::", name, name)),
+    ];
+    let stmt = stmt.prop_recursive(3, 16, 3, |inner| {
+        let block = vec(inner, 0..3).prop_map(|stmts| stmts.join("\n"));
+        prop_oneof![
+            (expr(), block.clone(), block.clone())
+                .prop_map(|(e, a, b)| format!("if {} then {} elseif x then {} else end", e, a, b)),
+            (expr(), block.clone()).prop_map(|(e, b)| format!("while {} do {} end", e, b)),
+            block.clone().prop_map(|b| format!("do {} end", b)),
+            (name(), expr(), block.clone())
+                .prop_map(|(i, e, b)| format!("for {} = 1, {} do {} end", i, e, b)),
+            (expr(), block.clone())
+                .prop_map(|(e, b)| format!("for k, v in pairs({}) do {} end", e, b)),
+            (block.clone(), expr()).prop_map(|(b, e)| format!("repeat {} until {}", b, e)),
+            (name(), block.clone())
+                .prop_map(|(name, b)| format!("function {}.m:f(a, b) {} end", name, b)),
+            (name(), block).prop_map(|(name, b)| format!("local function {}() {} end", name, b)),
+        ]
+    });
+    let separator = prop_oneof![Just("\n"), Just(" "), Just("; "), Just(" -- c\n")];
+    (vec((stmt, separator), 0..5), prop::option::of(expr())).prop_map(|(stmts, ret)| {
+        let mut text: String = stmts
+            .into_iter()
+            .map(|(stmt, separator)| stmt + separator)
+            .collect();
+        if let Some(e) = ret {
+            write!(text, "return {}", e).unwrap();
+        }
+        text
+    })
+}
+
+/// Generates arbitrary sequences of Lua tokens and stray characters, most of which are invalid.
+fn token_soup() -> impl Strategy<Value = String> {
+    let token = prop_oneof![
+        name(),
+        "[0-9]{1,3}",
+        "[-+*/%^#&~|<>=(){}\\[\\];:,.]{1,3}",
+        prop_oneof![
+            Just("local"),
+            Just("function"),
+            Just("end"),
+            Just("if"),
+            Just("then"),
+            Just("else"),
+            Just("for"),
+            Just("in"),
+            Just("do"),
+            Just("repeat"),
+            Just("until"),
+            Just("return"),
+            Just("\"unterminated"),
+            Just("--[[ comment"),
+            Just("[==["),
+            Just("@"),
+            Just("$"),
+        ]
+        .prop_map(String::from),
+    ];
+    let separator = prop_oneof![Just(""), Just(" "), Just("\n"), Just("-- c\n")];
+    vec((token, separator), 0..30).prop_map(|tokens| {
+        tokens
+            .into_iter()
+            .map(|(token, separator)| token + separator)
+            .collect()
+    })
+}
+
+/// Asserts that the tree covers `text` exactly and that parsing the text of the tree again
+/// results in the same tree.
+fn assert_lossless(text: &str) -> Result<(), TestCaseError> {
+    let parse = Chunk::parse(text);
+    let tree_text = parse.syntax_node().text().to_string();
+    prop_assert_eq!(&tree_text, text);
+    prop_assert_eq!(Chunk::parse(&tree_text).debug_dump(), parse.debug_dump());
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn valid_code_roundtrips(text in block()) {
+        let parse = Chunk::parse(&text);
+        prop_assert_eq!(parse.errors(), &[], "{}", parse.debug_dump());
+        assert_lossless(&text)?;
+    }
+
+    #[test]
+    fn invalid_code_roundtrips(text in token_soup()) {
+        assert_lossless(&text)?;
+    }
+
+    /// Any text can be parsed, including multi-byte characters right after the brackets, quotes
+    /// and escapes that the lexer scans bytewise.
+    #[test]
+    fn arbitrary_text_roundtrips(text in "[-\\[\\]=\"'\\\\\r\na é😀\u{10FFFF}]{0,40}|\\PC*") {
+        assert_lossless(&text)?;
+    }
+
+    #[test]
+    fn arbitrary_bytes_parse(bytes in vec(any::<u8>(), 0..64)) {
+        let (parse, source) = Chunk::parse_bytes(&bytes);
+        prop_assert_eq!(parse.syntax_node().text().to_string(), source.as_str());
+    }
+}
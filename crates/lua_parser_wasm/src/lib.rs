@@ -0,0 +1,136 @@
+//! JavaScript bindings for `lua_parser`, for use in web playgrounds and editor extensions that run
+//! in a browser.
+//!
+//! All functions take the source text and return JSON, which is cheaper to pass across the wasm
+//! boundary than a tree of JavaScript objects. Ranges are byte offsets into the UTF-8 encoded
+//! text.
+
+use lua_parser::{
+    diagnostics_to_json, syntax_tree_to_json, Chunk, HighlightTag, TextRange, TextSize,
+};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+#[derive(Serialize)]
+struct JsonToken<'a> {
+    kind: String,
+    range: [u32; 2],
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonHighlight {
+    tag: &'static str,
+    range: [u32; 2],
+}
+
+fn json_range(range: TextRange) -> [u32; 2] {
+    [range.start().into(), range.end().into()]
+}
+
+/// Parses `text` and returns an object with the syntax `tree` in the format of
+/// `syntax_tree_to_json` and the `diagnostics` in the format of `diagnostics_to_json`.
+#[wasm_bindgen]
+pub fn parse(text: &str) -> String {
+    let parse = Chunk::parse(text);
+    format!(
+        "{{\"tree\":{},\"diagnostics\":{}}}",
+        syntax_tree_to_json(&parse.syntax_node()),
+        diagnostics_to_json(&parse.diagnostics(), text)
+    )
+}
+
+/// Returns the syntax errors in `text` in the format of `diagnostics_to_json`.
+#[wasm_bindgen]
+pub fn diagnostics(text: &str) -> String {
+    diagnostics_to_json(&Chunk::parse(text).diagnostics(), text)
+}
+
+/// Returns the tokens of `text` as an array of objects with a `kind`, `range` and `text`.
+#[wasm_bindgen]
+pub fn tokenize(text: &str) -> String {
+    let mut offset = TextSize::from(0);
+    let tokens: Vec<_> = lua_parser::tokenize(text)
+        .into_iter()
+        .map(|token| {
+            let range = TextRange::at(offset, token.len);
+            offset += token.len;
+            JsonToken {
+                kind: format!("{:?}", token.kind),
+                range: json_range(range),
+                text: &text[range],
+            }
+        })
+        .collect();
+    serde_json::to_string(&tokens).expect("tokens are always serializable")
+}
+
+/// Returns the highlighted ranges of `text` as an array of objects with a `tag` and `range`.
+#[wasm_bindgen]
+pub fn highlight(text: &str) -> String {
+    let highlights: Vec<_> = lua_parser::highlight(&Chunk::parse(text).syntax_node())
+        .into_iter()
+        .map(|highlighted| JsonHighlight {
+            tag: tag_name(highlighted.tag),
+            range: json_range(highlighted.range),
+        })
+        .collect();
+    serde_json::to_string(&highlights).expect("highlights are always serializable")
+}
+
+fn tag_name(tag: HighlightTag) -> &'static str {
+    match tag {
+        HighlightTag::Keyword => "keyword",
+        HighlightTag::Comment => "comment",
+        HighlightTag::String => "string",
+        HighlightTag::Number => "number",
+        HighlightTag::Operator => "operator",
+        HighlightTag::Punctuation => "punctuation",
+        HighlightTag::Function => "function",
+        HighlightTag::Parameter => "parameter",
+        HighlightTag::Local => "local",
+        HighlightTag::Variable => "variable",
+        HighlightTag::Field => "field",
+        HighlightTag::Label => "label",
+        HighlightTag::Attribute => "attribute",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn to_value(json: String) -> Value {
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let value = to_value(parse("x = "));
+        assert_eq!(value["tree"]["kind"], "CHUNK");
+        assert_eq!(value["diagnostics"][0]["severity"], "error");
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let value = to_value(tokenize("x = 1"));
+        assert_eq!(
+            value[0],
+            json!({ "kind": "IDENT", "range": [0, 1], "text": "x" })
+        );
+        assert_eq!(value.as_array().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_highlight() {
+        let value = to_value(highlight("local x"));
+        assert_eq!(
+            value,
+            json!([
+                { "tag": "keyword", "range": [0, 5] },
+                { "tag": "local", "range": [6, 7] },
+            ])
+        );
+    }
+}
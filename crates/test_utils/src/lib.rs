@@ -1,5 +1,5 @@
 use std::{
-    fs,
+    env, fs,
     path::{Path, PathBuf},
 };
 
@@ -24,6 +24,9 @@ macro_rules! assert_eq_text {
     }};
 }
 
+/// Runs `f` on every `.lua` file in the `paths` of `test_data_dir` and compares the result with
+/// the `.txt` file next to it. Missing `.txt` files are created. If `UPDATE_EXPECT` is set,
+/// differing files are rewritten instead of failing the test.
 pub fn dir_tests<F>(test_data_dir: &Path, paths: &[&str], f: F)
 where
     F: Fn(&str, &Path) -> String,
@@ -36,7 +39,10 @@ where
             println!("No .txt file with expected result, creating...\n");
             println!("{}\n{}", input_code, actual);
             fs::write(&path, &actual).unwrap();
-            panic!("No expected result");
+            if !update_expect() {
+                panic!("No expected result");
+            }
+            continue;
         }
         let expected = read_text(&path);
         let expected = expected.as_str();
@@ -61,13 +67,13 @@ pub fn collect_tests(test_data_dir: &Path, paths: &[&str]) -> Vec<(PathBuf, Stri
 
 fn read_text(path: &Path) -> String {
     fs::read_to_string(path)
-        .expect(&format!("File at {:?} should be valid", path))
+        .unwrap_or_else(|_| panic!("File at {:?} should be valid", path))
         .replace("\r\n", "\n")
 }
 
 fn test_from_dir(dir: &Path) -> Vec<PathBuf> {
     let mut acc = Vec::new();
-    for file in fs::read_dir(&dir).unwrap() {
+    for file in fs::read_dir(dir).unwrap() {
         let file = file.unwrap();
         let path = file.path();
         if path.extension().unwrap_or_default() == "lua" {
@@ -78,6 +84,12 @@ fn test_from_dir(dir: &Path) -> Vec<PathBuf> {
     acc
 }
 
+/// Returns true if expected results should be updated instead of compared, which is enabled by
+/// setting the `UPDATE_EXPECT` environment variable, e.g. `UPDATE_EXPECT=1 cargo test`.
+pub fn update_expect() -> bool {
+    env::var_os("UPDATE_EXPECT").is_some()
+}
+
 pub fn project_dir() -> PathBuf {
     let dir = env!("CARGO_MANIFEST_DIR");
     PathBuf::from(dir)
@@ -88,21 +100,25 @@ pub fn project_dir() -> PathBuf {
         .to_owned()
 }
 
-fn assert_equal_text(expected: &str, actual: &str, path: &Path) {
+/// Compares `actual` with the `expected` contents of the file at `path`. If `UPDATE_EXPECT` is set
+/// or only whitespace differs, the file is rewritten instead of failing.
+pub fn assert_equal_text(expected: &str, actual: &str, path: &Path) {
     if expected == actual {
         return;
     }
     let dir = project_dir();
-    let pretty_path = path.strip_prefix(&dir).unwrap_or_else(|_| path);
+    let pretty_path = path.strip_prefix(&dir).unwrap_or(path);
     if expected.trim() == actual.trim() {
         println!("whitespace difference, rewriting");
         println!("file: {}\n", pretty_path.display());
         fs::write(path, actual).unwrap();
         return;
     }
-    //        println!("rewriting {}", pretty_path.display());
-    //        fs::write(path, actual).unwrap();
-    //        return;
+    if update_expect() {
+        println!("rewriting {}", pretty_path.display());
+        fs::write(path, actual).unwrap();
+        return;
+    }
 
     assert_eq_text!(expected, actual, "file: {}", pretty_path.display());
 }
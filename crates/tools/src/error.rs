@@ -0,0 +1,84 @@
+use std::{error, fmt, io, path::PathBuf};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// An error that makes a subcommand fail, as opposed to errors in the Lua code it processes.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// A file or stdin couldn't be read.
+    Read {
+        path: PathBuf,
+        error: io::Error,
+    },
+    /// A `.luacheckrc` or `selene.toml` is invalid.
+    Config {
+        path: PathBuf,
+        error: lua_parser::ConfigError,
+    },
+    WalkDir(walkdir::Error),
+    Watch(notify::Error),
+    /// Stdin was passed to a subcommand that runs in watch mode.
+    WatchStdin,
+    /// Rendering the generated code failed, holds the message of the code generator.
+    Codegen(String),
+    DuplicateInlineTest {
+        name: String,
+        path: PathBuf,
+    },
+    /// A generated file differs from what would be generated in `Verify` mode.
+    OutOfDate {
+        path: PathBuf,
+        command: &'static str,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(error) => error.fmt(f),
+            Error::Read { path, error } => write!(f, "can't read {}: {}", path.display(), error),
+            Error::Config { path, error } => write!(f, "{}: {}", path.display(), error),
+            Error::WalkDir(error) => error.fmt(f),
+            Error::Watch(error) => error.fmt(f),
+            Error::WatchStdin => f.write_str("stdin can't be watched"),
+            Error::Codegen(message) => f.write_str(message),
+            Error::DuplicateInlineTest { name, path } => {
+                write!(f, "duplicate inline test `{}` in {}", name, path.display())
+            }
+            Error::OutOfDate { path, command } => {
+                write!(f, "{} is not up to date, run `{}`", path.display(), command)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(error) | Error::Read { error, .. } => Some(error),
+            Error::Config { error, .. } => Some(error),
+            Error::WalkDir(error) => Some(error),
+            Error::Watch(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl From<walkdir::Error> for Error {
+    fn from(error: walkdir::Error) -> Error {
+        Error::WalkDir(error)
+    }
+}
+
+impl From<notify::Error> for Error {
+    fn from(error: notify::Error) -> Error {
+        Error::Watch(error)
+    }
+}
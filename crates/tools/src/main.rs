@@ -1,22 +1,683 @@
-pub type Result<T> = std::result::Result<T, failure::Error>;
+mod error;
 
-use std::path::{Path, PathBuf};
+use crate::error::{Error, Result};
+use lua_parser::{
+    check_ambiguous_calls, check_compatibility, check_constants, comment_directives,
+    detect_line_endings, format, highlight, render_diagnostic, syntax_tree_to_json,
+    syntax_tree_to_sexpr, tokenize, Chunk, Dialect, FmtConfig, HighlightTag, LintConfig, Severity,
+    SourceText, SyntaxKind, TextRange, TextSize, WalkEvent, LUACHECKRC, SELENE_TOML,
+};
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    env, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
 pub use teraron::{Mode, Overwrite, Verify};
-use clap::{App, SubCommand};
+use clap::{App, Arg, SubCommand};
 
 pub const GRAMMAR: &str = "crates/lua_parser/src/grammar.ron";
 pub const SYNTAX_KINDS: &str = "crates/lua_parser/src/syntax_kind/generated.rs.tera";
 pub const AST: &str = "crates/lua_parser/src/ast/generated.rs.tera";
+pub const PRECEDENCE: &str = "crates/lua_parser/src/precedence/generated.rs.tera";
+pub const DOCS: &str = "docs/grammar.md.tera";
+pub const GRAMMAR_DIR: &str = "crates/lua_parser/src/parsing/grammar";
+pub const INLINE_TESTS_DIR: &str = "crates/lua_parser/tests/data/parser/inline";
 
+/// Generates the syntax kinds, AST and precedence table from the grammar. In `Verify` mode the
+/// differences are printed and an error is returned if the generated files are not up to date.
 pub fn generate(mode: Mode) -> Result<()> {
-    let grammar = project_root().join(GRAMMAR);
-    let syntax_kinds = project_root().join(SYNTAX_KINDS);
-    let ast = project_root().join(AST);
-    teraron::generate(&syntax_kinds, &grammar, mode)?;
-    teraron::generate(&ast, &grammar, mode)?;
+    let mut result = Ok(());
+    for template in &[SYNTAX_KINDS, AST, PRECEDENCE] {
+        let template = project_root().join(template);
+        match generate_template(&template, mode, "cargo gen-syntax") {
+            Err(error @ Error::OutOfDate { .. }) if result.is_ok() => result = Err(error),
+            Err(Error::OutOfDate { .. }) => {}
+            other => other?,
+        }
+    }
+    result
+}
+
+/// Renders the grammar reference in `DOCS` from the grammar.
+pub fn generate_docs(mode: Mode) -> Result<()> {
+    generate_template(&project_root().join(DOCS), mode, "cargo gen-docs")
+}
+
+/// Renders `template` into the file next to it without the `.tera` extension. In `Verify` mode the
+/// rendered template is only compared to that file.
+fn generate_template(template: &Path, mode: Mode, command: &'static str) -> Result<()> {
+    let grammar = ron::de::from_str(&fs::read_to_string(project_root().join(GRAMMAR))?)
+        .map_err(codegen_error)?;
+    let generated =
+        teraron::render(&fs::read_to_string(template)?, grammar).map_err(codegen_error)?;
+    let path = template.with_extension("");
+    let generated = if path.extension().is_some_and(|extension| extension == "rs") {
+        reformat(&generated)?
+    } else {
+        generated
+    };
+
+    let current = fs::read_to_string(&path).unwrap_or_default();
+    if current == generated {
+        return Ok(());
+    }
+    if let Verify = mode {
+        print_diff(&path.display().to_string(), &current, &generated);
+        return Err(Error::OutOfDate { path, command });
+    }
+    eprintln!("updating {}", path.display());
+    fs::write(&path, generated)?;
+    Ok(())
+}
+
+/// Runs generated Rust code through rustfmt. Files that should keep the layout of their template
+/// opt out with `#![cfg_attr(rustfmt, rustfmt_skip)]`.
+fn reformat(text: &str) -> Result<String> {
+    let mut rustfmt = process::Command::new("rustfmt")
+        .args(["--edition", "2018"])
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .spawn()?;
+    rustfmt.stdin.take().unwrap().write_all(text.as_bytes())?;
+    let output = rustfmt.wait_with_output()?;
+    if !output.status.success() {
+        return Err(Error::Codegen("rustfmt failed on the generated code".to_string()));
+    }
+    String::from_utf8(output.stdout).map_err(codegen_error)
+}
+
+fn codegen_error(error: impl ToString) -> Error {
+    Error::Codegen(error.to_string())
+}
+
+/// A parser test that is written as a comment in the grammar, e.g.
+///
+/// ```text
+/// // test numeric_for
+/// // for i = 1, 10 do end
+/// ```
+///
+/// Tests that start with `test_err` instead of `test` are expected to produce errors.
+#[derive(Debug)]
+struct InlineTest {
+    name: String,
+    text: String,
+    ok: bool,
+}
+
+/// Extracts the inline tests from the grammar into `INLINE_TESTS_DIR`. In `Verify` mode an error
+/// is returned if the extracted tests are not up to date instead.
+pub fn generate_tests(mode: Mode) -> Result<()> {
+    let mut tests: HashMap<String, InlineTest> = HashMap::new();
+    for path in grammar_files()? {
+        let text = fs::read_to_string(&path)?;
+        for test in collect_inline_tests(&text) {
+            if tests.contains_key(&test.name) {
+                return Err(Error::DuplicateInlineTest {
+                    name: test.name,
+                    path,
+                });
+            }
+            tests.insert(test.name.clone(), test);
+        }
+    }
+
+    let tests_dir = project_root().join(INLINE_TESTS_DIR);
+    for test in tests.values() {
+        let dir = tests_dir.join(if test.ok { "ok" } else { "err" });
+        let path = dir.join(format!("{}.lua", test.name));
+        if fs::read_to_string(&path).ok().as_ref() == Some(&test.text) {
+            continue;
+        }
+        match mode {
+            Overwrite => {
+                fs::create_dir_all(&dir)?;
+                fs::write(&path, &test.text)?;
+                println!("updated {}", path.display());
+            }
+            Verify => {
+                return Err(Error::OutOfDate {
+                    path,
+                    command: "cargo gen-tests",
+                })
+            }
+        }
+    }
     Ok(())
 }
 
+/// Returns the source files of the grammar.
+fn grammar_files() -> Result<Vec<PathBuf>> {
+    let grammar_dir = project_root().join(GRAMMAR_DIR);
+    let mut files = vec![grammar_dir.with_extension("rs")];
+    for entry in fs::read_dir(&grammar_dir)? {
+        let path = entry?.path();
+        if path.extension().unwrap_or_default() == "rs" {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn collect_inline_tests(text: &str) -> Vec<InlineTest> {
+    let mut tests = Vec::new();
+    let mut lines = text.lines().map(str::trim_start).peekable();
+    while let Some(line) = lines.next() {
+        let (name, ok) = if let Some(name) = line.strip_prefix("// test ") {
+            (name, true)
+        } else if let Some(name) = line.strip_prefix("// test_err ") {
+            (name, false)
+        } else {
+            continue;
+        };
+
+        let mut text = String::new();
+        while let Some(line) = lines.peek().and_then(|line| line.strip_prefix("//")) {
+            text.push_str(line.strip_prefix(' ').unwrap_or(line));
+            text.push('\n');
+            lines.next();
+        }
+        tests.push(InlineTest {
+            name: name.trim().to_string(),
+            text,
+            ok,
+        });
+    }
+    tests
+}
+
+/// The path that stands for stdin in the arguments of the subcommands.
+const STDIN: &str = "-";
+
+/// Reads the file at `path`, or stdin if `path` is `-`. Input that isn't valid UTF-8 is rejected
+/// with the position of the first invalid byte.
+fn read_input(path: &Path) -> Result<String> {
+    let read_error = |error| Error::Read {
+        path: path.to_path_buf(),
+        error,
+    };
+    let bytes = if path == Path::new(STDIN) {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        bytes
+    } else {
+        fs::read(path).map_err(read_error)?
+    };
+    SourceText::from_utf8(&bytes)
+        .map(SourceText::into_string)
+        .map_err(|error| read_error(io::Error::new(io::ErrorKind::InvalidData, error)))
+}
+
+/// Returns the name of `path` as it is shown in the output.
+fn input_name(path: &Path) -> String {
+    if path == Path::new(STDIN) {
+        "<stdin>".to_string()
+    } else {
+        path.display().to_string()
+    }
+}
+
+/// The formats in which the `parse` subcommand prints the syntax tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TreeFormat {
+    DebugDump,
+    SExpr,
+    Json,
+}
+
+/// Parses `path` and prints its syntax tree. The errors are printed to stderr; returns false if
+/// there were any.
+fn parse_file(path: &Path, format: TreeFormat) -> Result<bool> {
+    let text = read_input(path)?;
+    let parse = Chunk::parse(&text);
+    let tree = match format {
+        TreeFormat::DebugDump => parse.debug_dump_colored(),
+        TreeFormat::SExpr => syntax_tree_to_sexpr(&parse.syntax_node()),
+        TreeFormat::Json => syntax_tree_to_json(&parse.syntax_node()),
+    };
+    println!("{}", tree.trim_end());
+
+    let file_name = input_name(path);
+    for diagnostic in parse.diagnostics() {
+        eprintln!("{}", render_diagnostic(&diagnostic, Some(&file_name), &text));
+    }
+    Ok(parse.errors().is_empty())
+}
+
+/// Prints the tokens of `path` in the format of the lexer tests, with the range of every token
+/// instead of its length. If `errors_only` is set, only invalid tokens are printed. Returns false
+/// if there were invalid tokens.
+fn lex_file(path: &Path, errors_only: bool) -> Result<bool> {
+    let text = read_input(path)?;
+    let mut offset = TextSize::from(0);
+    let mut ok = true;
+    for token in tokenize(&text) {
+        let range = TextRange::at(offset, token.len);
+        offset += token.len;
+        let token_text = &text[range];
+        let error = token_error(token.kind, token_text);
+        ok &= error.is_none();
+        match error {
+            Some(error) => println!(
+                "{:?} {:?} {:?} error: {}",
+                token.kind, range, token_text, error
+            ),
+            None if !errors_only => println!("{:?} {:?} {:?}", token.kind, range, token_text),
+            None => (),
+        }
+    }
+    Ok(ok)
+}
+
+/// Returns why a token is invalid, if it is.
+fn token_error(kind: SyntaxKind, text: &str) -> Option<&'static str> {
+    match kind {
+        SyntaxKind::ERROR => Some("unexpected character"),
+        SyntaxKind::STRING if !is_terminated_string(text) => Some("unterminated string"),
+        _ => None,
+    }
+}
+
+fn is_terminated_string(text: &str) -> bool {
+    if let Some(rest) = text.strip_prefix('[') {
+        let level = rest.chars().take_while(|&c| c == '=').count();
+        let close = format!("]{}]", "=".repeat(level));
+        return text.len() >= 2 * close.len() && text.ends_with(&close);
+    }
+    let quote = match text.chars().next() {
+        Some(quote) => quote,
+        None => return false,
+    };
+    let body = &text[1..];
+    match body.strip_suffix(quote) {
+        // The closing quote must not be escaped
+        Some(body) => body.chars().rev().take_while(|&c| c == '\\').count() % 2 == 0,
+        None => false,
+    }
+}
+
+/// The number of files and diagnostics seen by the `check` subcommand.
+#[derive(Debug, Default)]
+struct CheckSummary {
+    files: usize,
+    errors: usize,
+    warnings: usize,
+}
+
+/// Returns all Lua files in `path`, recursively, or `path` itself if it is a file or stdin.
+fn lua_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if path == Path::new(STDIN) {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    for entry in walkdir::WalkDir::new(path).sort_by_file_name() {
+        let entry = entry?;
+        let is_lua = entry.path().extension().unwrap_or_default() == "lua";
+        if entry.file_type().is_file() && (is_lua || entry.depth() == 0) {
+            files.push(entry.into_path());
+        }
+    }
+    Ok(files)
+}
+
+/// Parses the text of a lint configuration file.
+type ConfigParser = fn(&str) -> std::result::Result<LintConfig, lua_parser::ConfigError>;
+
+/// Returns the `.luacheckrc` or `selene.toml` in the directory of `path` or the closest of its
+/// ancestors. If a directory contains both, the `.luacheckrc` is used.
+fn find_lint_config(path: &Path) -> Result<Option<LintConfig>> {
+    let start = if path == Path::new(STDIN) {
+        env::current_dir()?
+    } else if path.is_dir() {
+        path.to_path_buf()
+    } else {
+        path.parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf()
+    };
+    let parsers: [(&str, ConfigParser); 2] = [
+        (LUACHECKRC, LintConfig::from_luacheckrc),
+        (SELENE_TOML, LintConfig::from_selene_toml),
+    ];
+    for dir in start.ancestors() {
+        for (file_name, parse) in parsers.iter() {
+            let config_path = dir.join(file_name);
+            if !config_path.is_file() {
+                continue;
+            }
+            let text = fs::read_to_string(&config_path).map_err(|error| Error::Read {
+                path: config_path.clone(),
+                error,
+            })?;
+            return parse(&text).map(Some).map_err(|error| Error::Config {
+                path: config_path,
+                error,
+            });
+        }
+    }
+    Ok(None)
+}
+
+/// Parses `path`, runs the checks for `dialect` and prints the diagnostics to stderr, except the
+/// ones that are suppressed by `config` or by directives in comments.
+fn check_file(
+    path: &Path,
+    dialect: Dialect,
+    config: Option<&LintConfig>,
+    summary: &mut CheckSummary,
+) -> Result<()> {
+    let text = read_input(path)?;
+    let parse = Chunk::parse(&text);
+    let mut diagnostics = parse.diagnostics();
+    diagnostics.extend(check_ambiguous_calls(&text, dialect, false));
+    diagnostics.extend(check_compatibility(&text, dialect));
+    diagnostics.extend(check_constants(&parse.tree(), dialect));
+    let mut directives = comment_directives(&parse.tree());
+    if let Some(config) = config {
+        directives = directives.with_config(config);
+    }
+    let mut diagnostics = directives.apply(diagnostics);
+    diagnostics.sort_by_key(|diagnostic| diagnostic.range.start());
+
+    let file_name = input_name(path);
+    for diagnostic in diagnostics {
+        match diagnostic.severity {
+            Severity::Error => summary.errors += 1,
+            Severity::Warning => summary.warnings += 1,
+            Severity::Hint => (),
+        }
+        eprintln!(
+            "{}",
+            render_diagnostic(&diagnostic, Some(&file_name), &text)
+        );
+    }
+    summary.files += 1;
+    Ok(())
+}
+
+/// Checks all Lua files in `path` and prints a summary. Returns false if there were errors. The
+/// `.luacheckrc` or `selene.toml` of `path` is used for all files.
+fn check_path(path: &Path, dialect: Dialect) -> Result<bool> {
+    let config = find_lint_config(path)?;
+    let mut summary = CheckSummary::default();
+    for path in lua_files(path)? {
+        check_file(&path, dialect, config.as_ref(), &mut summary)?;
+    }
+    eprintln!(
+        "checked {} files: {} errors, {} warnings",
+        summary.files, summary.errors, summary.warnings
+    );
+    Ok(summary.errors == 0)
+}
+
+fn parse_dialect(name: &str) -> Dialect {
+    match name {
+        "5.1" => Dialect::Lua51,
+        "5.2" => Dialect::Lua52,
+        "5.3" => Dialect::Lua53,
+        _ => Dialect::Lua54,
+    }
+}
+
+/// Formats `text`, keeping the line ending that it uses most. Returns `None` after printing the
+/// diagnostics if `text` contains syntax errors, since formatting it could change its meaning.
+fn format_text(text: &str, file_name: &str) -> Option<String> {
+    let parse = Chunk::parse(text);
+    if !parse.errors().is_empty() {
+        for diagnostic in parse.diagnostics() {
+            eprintln!("{}", render_diagnostic(&diagnostic, Some(file_name), text));
+        }
+        return None;
+    }
+    let config = FmtConfig {
+        line_ending: detect_line_endings(text).dominant(),
+        ..FmtConfig::default()
+    };
+    Some(format(&parse.tree(), &config))
+}
+
+/// Prints the lines that differ between `before` and `after`, grouped by the line in `before`
+/// where they start.
+fn print_diff(file_name: &str, before: &str, after: &str) {
+    let mut line = 1;
+    let mut in_change = false;
+    for change in diff::lines(before, after) {
+        let (prefix, text) = match change {
+            diff::Result::Both(..) => {
+                line += 1;
+                in_change = false;
+                continue;
+            }
+            diff::Result::Left(text) => ('-', text),
+            diff::Result::Right(text) => ('+', text),
+        };
+        if !in_change {
+            println!("Diff in {} at line {}:", file_name, line);
+            in_change = true;
+        }
+        if prefix == '-' {
+            line += 1;
+        }
+        println!("{}{}", prefix, text);
+    }
+}
+
+/// Formats `path` in place, or if `check` is set, prints how the formatting would change it.
+/// Returns false if the file contains errors or, in check mode, isn't formatted.
+fn format_file(path: &Path, check: bool) -> Result<bool> {
+    if path == Path::new(STDIN) {
+        return format_stdin(check);
+    }
+    let text = read_input(path)?;
+    let file_name = path.display().to_string();
+    let formatted = match format_text(&text, &file_name) {
+        Some(formatted) => formatted,
+        None => return Ok(false),
+    };
+    if formatted == text {
+        return Ok(true);
+    }
+    if check {
+        print_diff(&file_name, &text, &formatted);
+        return Ok(false);
+    }
+    fs::write(path, formatted)?;
+    Ok(true)
+}
+
+/// Formats stdin to stdout, or if `check` is set, prints how the formatting would change it.
+fn format_stdin(check: bool) -> Result<bool> {
+    let text = read_input(Path::new(STDIN))?;
+    let formatted = match format_text(&text, "<stdin>") {
+        Some(formatted) => formatted,
+        None => return Ok(false),
+    };
+    if !check {
+        print!("{}", formatted);
+        return Ok(true);
+    }
+    print_diff("<stdin>", &text, &formatted);
+    Ok(formatted == text)
+}
+
+/// Returns the ANSI escape code that selects the color of `tag`.
+fn ansi_color(tag: HighlightTag) -> &'static str {
+    match tag {
+        HighlightTag::Keyword => "\x1b[35m",
+        HighlightTag::Comment => "\x1b[90m",
+        HighlightTag::String => "\x1b[32m",
+        HighlightTag::Number => "\x1b[33m",
+        HighlightTag::Operator => "\x1b[36m",
+        HighlightTag::Punctuation => "\x1b[37m",
+        HighlightTag::Function => "\x1b[34m",
+        HighlightTag::Parameter => "\x1b[3;31m",
+        HighlightTag::Local => "\x1b[31m",
+        HighlightTag::Variable => "\x1b[39m",
+        HighlightTag::Field => "\x1b[96m",
+        HighlightTag::Label => "\x1b[93m",
+        HighlightTag::Attribute => "\x1b[95m",
+    }
+}
+
+/// Prints `path` with its tokens colored by their highlighting classification.
+fn highlight_file(path: &Path) -> Result<()> {
+    let text = read_input(path)?;
+    let parse = Chunk::parse(&text);
+    let mut buf = String::new();
+    let mut offset = TextSize::from(0);
+    for highlighted in highlight(&parse.syntax_node()) {
+        assert!(
+            highlighted.range.start() >= offset,
+            "highlighted ranges overlap at {:?}",
+            highlighted.range
+        );
+        buf.push_str(&text[TextRange::new(offset, highlighted.range.start())]);
+        buf.push_str(ansi_color(highlighted.tag));
+        buf.push_str(&text[highlighted.range]);
+        buf.push_str("\x1b[0m");
+        offset = highlighted.range.end();
+    }
+    buf.push_str(&text[usize::from(offset)..]);
+    print!("{}", buf);
+    Ok(())
+}
+
+/// Statistics of a single file, see the `stats` subcommand.
+#[derive(Debug)]
+struct FileStats {
+    path: PathBuf,
+    bytes: usize,
+    parse_time: Duration,
+    errors: usize,
+    /// The depth of the most deeply nested node, the root has depth 1.
+    max_depth: usize,
+    nodes: BTreeMap<SyntaxKind, usize>,
+}
+
+fn file_stats(path: &Path) -> Result<FileStats> {
+    let text = read_input(path)?;
+    let start = Instant::now();
+    let parse = Chunk::parse(&text);
+    let parse_time = start.elapsed();
+
+    let mut nodes = BTreeMap::new();
+    let mut depth = 0;
+    let mut max_depth = 0;
+    for event in parse.syntax_node().preorder() {
+        match event {
+            WalkEvent::Enter(node) => {
+                *nodes.entry(node.kind()).or_insert(0) += 1;
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            WalkEvent::Leave(_) => depth -= 1,
+        }
+    }
+    Ok(FileStats {
+        path: path.to_path_buf(),
+        bytes: text.len(),
+        parse_time,
+        errors: parse.errors().len(),
+        max_depth,
+        nodes,
+    })
+}
+
+/// Prints the statistics as CSV with a row per file and a column per kind of node.
+fn print_stats_csv(stats: &[FileStats]) {
+    let kinds: BTreeSet<SyntaxKind> = stats.iter().flat_map(|s| s.nodes.keys().copied()).collect();
+    let mut header = "file,bytes,parse_time_us,errors,max_depth".to_string();
+    for kind in &kinds {
+        header.push_str(&format!(",{:?}", kind));
+    }
+    println!("{}", header);
+    for file in stats {
+        let mut row = format!(
+            "{},{},{},{},{}",
+            csv_field(&input_name(&file.path)),
+            file.bytes,
+            file.parse_time.as_micros(),
+            file.errors,
+            file.max_depth
+        );
+        for kind in &kinds {
+            row.push_str(&format!(",{}", file.nodes.get(kind).unwrap_or(&0)));
+        }
+        println!("{}", row);
+    }
+}
+
+/// Quotes `field` if it contains characters that have a special meaning in CSV.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Prints the statistics as a JSON array with an object per file.
+fn print_stats_json(stats: &[FileStats]) {
+    let files: Vec<_> = stats
+        .iter()
+        .map(|file| {
+            let nodes: serde_json::Map<_, _> = file
+                .nodes
+                .iter()
+                .map(|(kind, count)| (format!("{:?}", kind), (*count).into()))
+                .collect();
+            serde_json::json!({
+                "file": input_name(&file.path),
+                "bytes": file.bytes,
+                "parse_time_us": file.parse_time.as_micros() as u64,
+                "errors": file.errors,
+                "max_depth": file.max_depth,
+                "nodes": nodes,
+            })
+        })
+        .collect();
+    println!("{}", serde_json::Value::Array(files));
+}
+
+/// Calls `run` and calls it again every time something in `path` changes, printing how long every
+/// run took. Only returns if watching fails or the watcher stops.
+fn watch(path: &Path, mut run: impl FnMut() -> Result<bool>) -> Result<()> {
+    if path == Path::new(STDIN) {
+        return Err(Error::WatchStdin);
+    }
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::watcher(sender, Duration::from_millis(100))?;
+    watcher.watch(path, RecursiveMode::Recursive)?;
+    loop {
+        let start = Instant::now();
+        // A file may be removed while it is read, that shouldn't stop the watcher.
+        if let Err(error) = run() {
+            eprintln!("error: {}", error);
+        }
+        eprintln!("finished in {:.2?}, watching for changes", start.elapsed());
+        loop {
+            let event = match receiver.recv() {
+                Ok(event) => event,
+                Err(_) => return Ok(()),
+            };
+            match event {
+                DebouncedEvent::Create(_)
+                | DebouncedEvent::Write(_)
+                | DebouncedEvent::Remove(_)
+                | DebouncedEvent::Rename(..) => break,
+                DebouncedEvent::Error(error, _) => return Err(error.into()),
+                _ => (),
+            }
+        }
+    }
+}
+
 pub fn project_root() -> PathBuf {
     Path::new(&env!("CARGO_MANIFEST_DIR"))
         .ancestors()
@@ -25,17 +686,245 @@ pub fn project_root() -> PathBuf {
         .to_path_buf()
 }
 
-fn main() -> Result<()> {
+/// Returns the mode of a `gen-*` subcommand with a `--verify` flag.
+fn verify_mode(matches: &clap::ArgMatches) -> Mode {
+    if matches.is_present("verify") {
+        Verify
+    } else {
+        Overwrite
+    }
+}
+
+fn watch_arg() -> Arg<'static, 'static> {
+    Arg::with_name("watch")
+        .long("watch")
+        .help("Runs again every time the input changes")
+}
+
+/// Exit code if there were no errors.
+const EXIT_SUCCESS: i32 = 0;
+/// Exit code if the input contains errors or, for `fmt --check`, isn't formatted.
+const EXIT_ERRORS: i32 = 1;
+/// Exit code if the tool itself failed, e.g. because a file couldn't be read.
+const EXIT_FAILURE: i32 = 2;
+
+const EXIT_STATUS_HELP: &str = "EXIT STATUS:
+    0  no errors were found
+    1  the input contains errors, or `fmt --check` found files that aren't formatted
+    2  the tool failed, e.g. because the arguments are invalid or a file can't be read
+
+Every file or path argument can be `-` to read from stdin. Trees, tokens, diffs and statistics
+are written to stdout, diagnostics to stderr.";
+
+fn main() {
+    let code = match run() {
+        Ok(true) => EXIT_SUCCESS,
+        Ok(false) => EXIT_ERRORS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            EXIT_FAILURE
+        }
+    };
+    process::exit(code);
+}
+
+/// Runs the subcommand, returns false if the input contains errors.
+fn run() -> Result<bool> {
     let matches = App::new("tasks")
         .setting(clap::AppSettings::SubcommandRequiredElseHelp)
-        .subcommand(SubCommand::with_name("gen-syntax"))
-        .get_matches();
-    match matches
-        .subcommand_name()
-        .expect("Subcommand must be specified")
-        {
-            "gen-syntax" => generate(Overwrite)?,
-            _ => unreachable!(),
+        .after_help(EXIT_STATUS_HELP)
+        .subcommand(
+            SubCommand::with_name("gen-syntax").arg(
+                Arg::with_name("verify")
+                    .long("verify")
+                    .help("Checks that the generated code is up to date"),
+            ),
+        )
+        .subcommand(
+            SubCommand::with_name("gen-docs").arg(
+                Arg::with_name("verify")
+                    .long("verify")
+                    .help("Checks that the grammar reference is up to date"),
+            ),
+        )
+        .subcommand(
+            SubCommand::with_name("gen-tests").arg(
+                Arg::with_name("verify")
+                    .long("verify")
+                    .help("Checks that the inline tests are up to date"),
+            ),
+        )
+        .subcommand(
+            SubCommand::with_name("parse")
+                .about("Parses a file and prints its syntax tree, fails if there are errors")
+                .arg(Arg::with_name("file").required(true))
+                .arg(
+                    Arg::with_name("sexpr")
+                        .long("sexpr")
+                        .help("Prints the tree as an S-expression"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .conflicts_with("sexpr")
+                        .help("Prints the tree as JSON"),
+                )
+                .arg(watch_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("lex")
+                .about("Prints the tokens of a file, fails if there are invalid tokens")
+                .arg(Arg::with_name("file").required(true))
+                .arg(
+                    Arg::with_name("errors-only")
+                        .long("errors-only")
+                        .help("Only prints invalid tokens"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Checks all Lua files in a directory, fails if there are errors")
+                .arg(Arg::with_name("path").required(true))
+                .arg(
+                    Arg::with_name("dialect")
+                        .long("dialect")
+                        .takes_value(true)
+                        .possible_values(&["5.1", "5.2", "5.3", "5.4"])
+                        .default_value("5.4")
+                        .help("The Lua version the files are checked against"),
+                )
+                .arg(watch_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Formats Lua files in place")
+                .arg(
+                    Arg::with_name("paths")
+                        .multiple(true)
+                        .required_unless("stdin")
+                        .conflicts_with("stdin"),
+                )
+                .arg(
+                    Arg::with_name("check")
+                        .long("check")
+                        .help("Only prints the changes, fails if there are any"),
+                )
+                .arg(
+                    Arg::with_name("stdin")
+                        .long("stdin")
+                        .help("Formats stdin and prints the result to stdout"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("highlight")
+                .about("Prints a file with its tokens colored with ANSI escape codes")
+                .arg(Arg::with_name("file").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Prints parse statistics of all Lua files in a directory as CSV")
+                .arg(Arg::with_name("path").required(true))
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Prints the statistics as JSON"),
+                ),
+        )
+        .get_matches_safe();
+    let matches = match matches {
+        Ok(matches) => matches,
+        Err(error) if error.use_stderr() => {
+            eprintln!("{}", error.message);
+            process::exit(EXIT_FAILURE);
         }
-    Ok(())
+        // Help and version information
+        Err(error) => error.exit(),
+    };
+    let ok = match matches.subcommand() {
+        ("gen-syntax", Some(matches)) => {
+            generate(verify_mode(matches))?;
+            true
+        }
+        ("gen-docs", Some(matches)) => {
+            generate_docs(verify_mode(matches))?;
+            true
+        }
+        ("gen-tests", Some(matches)) => {
+            generate_tests(verify_mode(matches))?;
+            true
+        }
+        ("parse", Some(matches)) => {
+            let format = if matches.is_present("sexpr") {
+                TreeFormat::SExpr
+            } else if matches.is_present("json") {
+                TreeFormat::Json
+            } else {
+                TreeFormat::DebugDump
+            };
+            let path = Path::new(matches.value_of("file").unwrap());
+            if matches.is_present("watch") {
+                watch(path, || parse_file(path, format))?;
+                true
+            } else {
+                parse_file(path, format)?
+            }
+        }
+        ("lex", Some(matches)) => {
+            let path = Path::new(matches.value_of("file").unwrap());
+            lex_file(path, matches.is_present("errors-only"))?
+        }
+        ("check", Some(matches)) => {
+            let dialect = parse_dialect(matches.value_of("dialect").unwrap());
+            let path = Path::new(matches.value_of("path").unwrap());
+            if matches.is_present("watch") {
+                watch(path, || check_path(path, dialect))?;
+                true
+            } else {
+                check_path(path, dialect)?
+            }
+        }
+        ("fmt", Some(matches)) => {
+            let check = matches.is_present("check");
+            let mut ok = true;
+            if matches.is_present("stdin") {
+                ok = format_stdin(check)?;
+            } else {
+                for path in matches.values_of("paths").unwrap() {
+                    for path in lua_files(Path::new(path))? {
+                        ok &= format_file(&path, check)?;
+                    }
+                }
+            }
+            ok
+        }
+        ("highlight", Some(matches)) => {
+            highlight_file(Path::new(matches.value_of("file").unwrap()))?;
+            true
+        }
+        ("stats", Some(matches)) => {
+            let stats = lua_files(Path::new(matches.value_of("path").unwrap()))?
+                .iter()
+                .map(|path| file_stats(path))
+                .collect::<Result<Vec<_>>>()?;
+            if matches.is_present("json") {
+                print_stats_json(&stats);
+            } else {
+                print_stats_csv(&stats);
+            }
+            true
+        }
+        _ => unreachable!(),
+    };
+    Ok(ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_files_are_up_to_date() {
+        generate(Verify).unwrap();
+        generate_docs(Verify).unwrap();
+    }
 }